@@ -0,0 +1,365 @@
+//! Rule-based grouping of photos into stacks. Each rule is a heuristic
+//! the user can switch off or tune: files that share a name are one shot
+//! saved twice, frames at stepped exposure biases moments apart are a
+//! bracket, and frames moments apart at the same settings are a burst.
+//! [`propose`] only suggests stacks; nothing is written until the caller
+//! stores the ones the user keeps.
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::models::{Photo, PhotoId, StackKind};
+
+/// Exposure biases closer than this count as the same step.
+const BIAS_EPSILON: f64 = 0.01;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AutoStackRules {
+    /// Stack files with the same name in the same folder, like a RAW and
+    /// the JPEG the camera wrote beside it.
+    pub same_stem: bool,
+    pub brackets: bool,
+    /// Longest pause between frames of one bracket, in seconds.
+    pub bracket_gap_secs: u32,
+    /// Fewest frames, each at a different exposure bias, that make a bracket.
+    pub bracket_min_frames: usize,
+    pub bursts: bool,
+    /// Longest pause between frames of one burst, in seconds.
+    pub burst_gap_secs: u32,
+    pub burst_min_frames: usize,
+}
+
+impl Default for AutoStackRules {
+    fn default() -> Self {
+        Self {
+            same_stem: true,
+            brackets: true,
+            bracket_gap_secs: 2,
+            bracket_min_frames: 3,
+            bursts: true,
+            burst_gap_secs: 1,
+            burst_min_frames: 3,
+        }
+    }
+}
+
+/// A stack the rules suggest, cover first.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProposedStack {
+    pub kind: StackKind,
+    pub members: Vec<PhotoId>,
+}
+
+/// One shot: a photo, or every file sharing its name when `same_stem`
+/// is on. Time rules group units so a RAW+JPEG bracket stays one stack.
+struct Unit<'a> {
+    photos: Vec<&'a Photo>,
+    camera: Option<&'a str>,
+    time: Option<i64>,
+    bias: Option<f64>,
+}
+
+impl Unit<'_> {
+    fn ids(&self) -> impl Iterator<Item = PhotoId> + '_ {
+        self.photos.iter().map(|p| p.id)
+    }
+}
+
+/// Group `photos` by the enabled rules. `biases` holds each photo's
+/// exposure compensation in EV where its EXIF records one; brackets are
+/// only found among photos that have it. Photos no rule matches are left
+/// out.
+pub fn propose(
+    photos: &[Photo],
+    biases: &HashMap<PhotoId, f64>,
+    rules: &AutoStackRules,
+) -> Vec<ProposedStack> {
+    let mut units = units(photos, biases, rules.same_stem);
+    // Time rules look at each camera's shots in order; undated ones only
+    // ever stack by name.
+    units.sort_by(|a, b| {
+        (a.camera, a.time)
+            .cmp(&(b.camera, b.time))
+            .then_with(|| a.photos[0].file_path.cmp(&b.photos[0].file_path))
+    });
+
+    let mut used = vec![false; units.len()];
+    let mut stacks = Vec::new();
+    if rules.brackets {
+        for run in runs(&units, &used, rules.bracket_gap_secs) {
+            for bracket in split_brackets(&units, &run) {
+                if bracket.len() >= rules.bracket_min_frames.max(2) {
+                    stacks.push(stack_of(StackKind::Bracket, &units, &bracket));
+                    bracket.iter().for_each(|&i| used[i] = true);
+                }
+            }
+        }
+    }
+    if rules.bursts {
+        for run in runs(&units, &used, rules.burst_gap_secs) {
+            if run.len() >= rules.burst_min_frames.max(2) {
+                stacks.push(stack_of(StackKind::Burst, &units, &run));
+                run.iter().for_each(|&i| used[i] = true);
+            }
+        }
+    }
+    for (i, unit) in units.iter().enumerate() {
+        if !used[i] && unit.photos.len() > 1 {
+            stacks.push(ProposedStack {
+                kind: StackKind::RawJpeg,
+                members: unit.ids().collect(),
+            });
+        }
+    }
+    stacks
+}
+
+fn units<'a>(
+    photos: &'a [Photo],
+    biases: &HashMap<PhotoId, f64>,
+    same_stem: bool,
+) -> Vec<Unit<'a>> {
+    let mut groups: BTreeMap<(String, String), Vec<&Photo>> = BTreeMap::new();
+    for (index, photo) in photos.iter().enumerate() {
+        let path = Path::new(&photo.file_path);
+        let key = if same_stem {
+            let folder = path.parent().unwrap_or(Path::new("")).to_string_lossy();
+            let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+            (folder.to_string(), stem.to_lowercase())
+        } else {
+            (String::new(), index.to_string())
+        };
+        groups.entry(key).or_default().push(photo);
+    }
+    groups
+        .into_values()
+        .map(|mut photos| {
+            // RAW first, since that's the file worth developing.
+            photos.sort_by_key(|p| (!is_raw(p), p.file_path.clone()));
+            let time = photos
+                .iter()
+                .filter_map(|p| p.date_taken.as_deref().and_then(seconds))
+                .min();
+            let bias = photos.iter().find_map(|p| biases.get(&p.id).copied());
+            Unit {
+                camera: photos[0].camera_model.as_deref(),
+                time,
+                bias,
+                photos,
+            }
+        })
+        .collect()
+}
+
+fn is_raw(photo: &Photo) -> bool {
+    Path::new(&photo.file_path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| crema_core::raw::is_raw_extension(&e.to_lowercase()))
+}
+
+/// Indices of unused, dated units from one camera where each is at most
+/// `gap` seconds after the one before. `units` must be sorted by camera
+/// and time.
+fn runs(units: &[Unit], used: &[bool], gap: u32) -> Vec<Vec<usize>> {
+    let mut runs: Vec<Vec<usize>> = Vec::new();
+    let mut previous: Option<usize> = None;
+    for (i, unit) in units.iter().enumerate() {
+        if used[i] || unit.time.is_none() {
+            continue;
+        }
+        let continues = previous.is_some_and(|p| {
+            let prev = &units[p];
+            prev.camera == unit.camera
+                && unit
+                    .time
+                    .zip(prev.time)
+                    .is_some_and(|(t, pt)| t - pt <= i64::from(gap))
+        });
+        if continues {
+            runs.last_mut().unwrap().push(i);
+        } else {
+            runs.push(vec![i]);
+        }
+        previous = Some(i);
+    }
+    runs
+}
+
+/// Cut a run into brackets: a bracket ends when a frame repeats an
+/// exposure bias already in it, or has none.
+fn split_brackets(units: &[Unit], run: &[usize]) -> Vec<Vec<usize>> {
+    let mut brackets = Vec::new();
+    let mut current: Vec<usize> = Vec::new();
+    for &i in run {
+        let Some(bias) = units[i].bias else {
+            brackets.push(std::mem::take(&mut current));
+            continue;
+        };
+        let repeats = current.iter().any(|&j| {
+            units[j]
+                .bias
+                .is_some_and(|b| (b - bias).abs() < BIAS_EPSILON)
+        });
+        if repeats {
+            brackets.push(std::mem::take(&mut current));
+        }
+        current.push(i);
+    }
+    brackets.push(current);
+    brackets
+}
+
+/// A stack of the given units. A bracket's cover is its frame nearest
+/// 0 EV, a burst's its first frame.
+fn stack_of(kind: StackKind, units: &[Unit], indices: &[usize]) -> ProposedStack {
+    let cover = match kind {
+        StackKind::Bracket => indices
+            .iter()
+            .copied()
+            .min_by(|&a, &b| {
+                let bias = |i: usize| units[i].bias.unwrap_or(0.0).abs();
+                bias(a).total_cmp(&bias(b))
+            })
+            .unwrap_or(indices[0]),
+        _ => indices[0],
+    };
+    let mut members: Vec<PhotoId> = units[cover].ids().collect();
+    for &i in indices.iter().filter(|&&i| i != cover) {
+        members.extend(units[i].ids());
+    }
+    ProposedStack { kind, members }
+}
+
+/// Seconds since 1970 for an EXIF-style `YYYY-MM-DD HH:MM:SS` date, with
+/// any separators. Time zones are ignored; only differences matter here.
+fn seconds(date: &str) -> Option<i64> {
+    let fields: Vec<i64> = date
+        .split(|c: char| !c.is_ascii_digit())
+        .filter(|f| !f.is_empty())
+        .take(6)
+        .map(|f| f.parse().ok())
+        .collect::<Option<_>>()?;
+    let [year, month, day, hour, minute, second] = fields[..] else {
+        return None;
+    };
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+    // Days from civil, after Howard Hinnant.
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let mp = (month + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146_097 + doe - 719_468;
+    Some(days * 86_400 + hour * 3_600 + minute * 60 + second)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn photo(id: PhotoId, path: &str, date: Option<&str>) -> Photo {
+        Photo {
+            id,
+            file_path: path.into(),
+            camera_model: Some("Z 7".into()),
+            date_taken: date.map(String::from),
+            ..Photo::default()
+        }
+    }
+
+    #[test]
+    fn seconds_parses_exif_dates() {
+        assert_eq!(seconds("1970-01-01 00:00:00"), Some(0));
+        assert_eq!(seconds("2026:03:01 00:00:01"), Some(1_772_323_201));
+        assert_eq!(
+            seconds("2026-02-28 23:59:59").map(|s| s + 2),
+            seconds("2026-03-01 00:00:01")
+        );
+        assert_eq!(seconds("2026-13-01 00:00:00"), None);
+        assert_eq!(seconds("yesterday"), None);
+    }
+
+    #[test]
+    fn raw_and_jpeg_pairs_stack_raw_first() {
+        let photos = [
+            photo(1, "/a/DSC_0001.JPG", Some("2026-05-02 09:00:00")),
+            photo(2, "/a/DSC_0001.NEF", Some("2026-05-02 09:00:00")),
+            photo(3, "/b/DSC_0001.JPG", None),
+            photo(4, "/a/DSC_0002.jpg", Some("2026-05-02 10:00:00")),
+        ];
+        let rules = AutoStackRules::default();
+        let stacks = propose(&photos, &HashMap::new(), &rules);
+        assert_eq!(
+            stacks,
+            [ProposedStack {
+                kind: StackKind::RawJpeg,
+                members: vec![2, 1],
+            }]
+        );
+
+        let off = AutoStackRules {
+            same_stem: false,
+            ..rules
+        };
+        assert!(propose(&photos, &HashMap::new(), &off).is_empty());
+    }
+
+    #[test]
+    fn brackets_split_on_repeated_bias_and_cover_the_middle_frame() {
+        let photos: Vec<Photo> = (1..=6)
+            .map(|id| photo(id, &format!("/a/{id}.nef"), Some("2026-05-02 09:00:00")))
+            .collect();
+        let biases = HashMap::from([(1, -2.0), (2, 0.0), (3, 2.0), (4, -2.0), (5, 0.0), (6, 2.0)]);
+        let rules = AutoStackRules {
+            bursts: false,
+            ..AutoStackRules::default()
+        };
+        let stacks = propose(&photos, &biases, &rules);
+        assert_eq!(stacks.len(), 2);
+        assert!(stacks.iter().all(|s| s.kind == StackKind::Bracket));
+        assert_eq!(stacks[0].members, vec![2, 1, 3]);
+        assert_eq!(stacks[1].members, vec![5, 4, 6]);
+
+        let strict = AutoStackRules {
+            bracket_min_frames: 5,
+            ..rules
+        };
+        assert!(propose(&photos, &biases, &strict).is_empty());
+    }
+
+    #[test]
+    fn bursts_follow_the_time_gap_and_keep_pairs_together() {
+        let photos = [
+            photo(1, "/a/1.nef", Some("2026-05-02 09:00:00")),
+            photo(2, "/a/1.jpg", Some("2026-05-02 09:00:00")),
+            photo(3, "/a/2.nef", Some("2026-05-02 09:00:01")),
+            photo(4, "/a/3.nef", Some("2026-05-02 09:00:01")),
+            photo(5, "/a/4.nef", Some("2026-05-02 09:00:04")),
+        ];
+        let rules = AutoStackRules::default();
+        let stacks = propose(&photos, &HashMap::new(), &rules);
+        assert_eq!(
+            stacks,
+            [ProposedStack {
+                kind: StackKind::Burst,
+                members: vec![1, 2, 3, 4],
+            }]
+        );
+
+        let patient = AutoStackRules {
+            burst_gap_secs: 3,
+            ..rules
+        };
+        assert_eq!(
+            propose(&photos, &HashMap::new(), &patient)[0].members,
+            vec![1, 2, 3, 4, 5]
+        );
+    }
+}
@@ -1,8 +1,24 @@
+use std::collections::HashMap;
+
 use anyhow::{Context, Result};
-use rusqlite::{Connection, params};
-use tracing::info;
+use rusqlite::{Connection, OptionalExtension, params};
+use tracing::{info, warn};
+
+use crema_core::annotation::Annotation;
+use crema_core::dark_frame::MasterDark;
+use crema_core::defects::DefectMap;
+use crema_core::flat_field::FlatField;
 
-use crate::models::{EditRecord, Photo, PhotoId};
+use crate::models::{
+    Animation, CameraDefectMap, DarkFrameSettings, DerivationKind, EditRecord, LensFlatField,
+    MasterDarkId, MasterDarkInfo, MetadataOverride, PastState, PathRemap, Photo, PhotoId,
+    PhotoLink, PhotoStack, QuarantineId, QuarantinedFile, SmartPreview, Snapshot, SnapshotId,
+    StackId, StackKind, ThumbnailFidelity,
+};
+
+/// How long after a photo's history entry was first recorded further
+/// edits are folded into it, as an SQLite date modifier.
+const HISTORY_MERGE_WINDOW: &str = "-5 minutes";
 
 pub struct Catalog {
     conn: Connection,
@@ -16,6 +32,16 @@ impl Catalog {
         Ok(catalog)
     }
 
+    /// Open a catalog that [`Catalog::open`] has already brought up to
+    /// date, skipping the migrations. For the app's background work, which
+    /// opens its own connection each time.
+    pub fn open_existing(path: &str) -> Result<Self> {
+        let conn = Connection::open(path).context("failed to open catalog database")?;
+        // Foreign keys are enforced per connection.
+        conn.execute_batch("PRAGMA foreign_keys = ON;")?;
+        Ok(Self { conn })
+    }
+
     pub fn open_in_memory() -> Result<Self> {
         let conn = Connection::open_in_memory()?;
         let catalog = Self { conn };
@@ -61,7 +87,125 @@ impl Catalog {
                 updated_at TEXT NOT NULL DEFAULT (datetime('now'))
             );
 
+            CREATE TABLE IF NOT EXISTS snapshots (
+                id         INTEGER PRIMARY KEY,
+                photo_id   INTEGER NOT NULL REFERENCES photos(id),
+                name       TEXT NOT NULL,
+                params     TEXT NOT NULL,
+                created_at TEXT NOT NULL DEFAULT (datetime('now'))
+            );
+
+            CREATE TABLE IF NOT EXISTS defect_maps (
+                camera_serial TEXT PRIMARY KEY,
+                camera_model  TEXT,
+                map           TEXT NOT NULL,
+                created_at    TEXT NOT NULL DEFAULT (datetime('now'))
+            );
+
+            CREATE TABLE IF NOT EXISTS flat_fields (
+                camera_model TEXT NOT NULL,
+                lens         TEXT NOT NULL,
+                field        TEXT NOT NULL,
+                created_at   TEXT NOT NULL DEFAULT (datetime('now')),
+                PRIMARY KEY (camera_model, lens)
+            );
+
+            CREATE TABLE IF NOT EXISTS master_darks (
+                id            INTEGER PRIMARY KEY,
+                camera_model  TEXT NOT NULL,
+                iso           INTEGER NOT NULL,
+                exposure_secs REAL NOT NULL,
+                temperature   REAL,
+                frame_count   INTEGER NOT NULL,
+                width         INTEGER NOT NULL,
+                height        INTEGER NOT NULL,
+                data          BLOB NOT NULL,
+                created_at    TEXT NOT NULL DEFAULT (datetime('now'))
+            );
+
+            CREATE TABLE IF NOT EXISTS smart_previews (
+                photo_id   INTEGER PRIMARY KEY REFERENCES photos(id),
+                path       TEXT NOT NULL,
+                width      INTEGER NOT NULL,
+                height     INTEGER NOT NULL,
+                created_at TEXT NOT NULL DEFAULT (datetime('now'))
+            );
+
+            CREATE TABLE IF NOT EXISTS derived_from (
+                derived_id INTEGER NOT NULL REFERENCES photos(id),
+                source_id  INTEGER NOT NULL REFERENCES photos(id),
+                kind       TEXT NOT NULL,
+                created_at TEXT NOT NULL DEFAULT (datetime('now')),
+                PRIMARY KEY (derived_id, source_id)
+            );
+
+            CREATE TABLE IF NOT EXISTS animations (
+                photo_id    INTEGER PRIMARY KEY REFERENCES photos(id),
+                frame_count INTEGER NOT NULL,
+                key_frame   INTEGER NOT NULL DEFAULT 0
+            );
+
+            CREATE TABLE IF NOT EXISTS quarantine (
+                id         INTEGER PRIMARY KEY,
+                file_path  TEXT NOT NULL UNIQUE,
+                reason     TEXT NOT NULL,
+                failed_at  TEXT NOT NULL DEFAULT (datetime('now')),
+                dismissed  INTEGER NOT NULL DEFAULT 0
+            );
+
+            CREATE TABLE IF NOT EXISTS edit_history (
+                id          INTEGER PRIMARY KEY,
+                photo_id    INTEGER NOT NULL REFERENCES photos(id),
+                params      TEXT NOT NULL,
+                recorded_at TEXT NOT NULL DEFAULT (datetime('now'))
+            );
+
+            CREATE TABLE IF NOT EXISTS rating_history (
+                id          INTEGER PRIMARY KEY,
+                photo_id    INTEGER NOT NULL REFERENCES photos(id),
+                rating      INTEGER NOT NULL,
+                recorded_at TEXT NOT NULL DEFAULT (datetime('now'))
+            );
+
+            CREATE TABLE IF NOT EXISTS metadata_overrides (
+                photo_id     INTEGER PRIMARY KEY REFERENCES photos(id),
+                camera_make  TEXT,
+                camera_model TEXT,
+                lens         TEXT,
+                focal_length REAL
+            );
+
+            CREATE TABLE IF NOT EXISTS annotations (
+                photo_id INTEGER PRIMARY KEY REFERENCES photos(id),
+                data     TEXT NOT NULL
+            );
+
+            CREATE TABLE IF NOT EXISTS photo_stacks (
+                id         INTEGER PRIMARY KEY,
+                kind       TEXT NOT NULL,
+                created_at TEXT NOT NULL DEFAULT (datetime('now'))
+            );
+
+            CREATE TABLE IF NOT EXISTS stack_members (
+                photo_id INTEGER PRIMARY KEY REFERENCES photos(id),
+                stack_id INTEGER NOT NULL REFERENCES photo_stacks(id),
+                position INTEGER NOT NULL
+            );
+
+            CREATE TABLE IF NOT EXISTS folder_settings (
+                folder             TEXT PRIMARY KEY,
+                thumbnail_fidelity TEXT NOT NULL
+            );
+
             CREATE INDEX IF NOT EXISTS idx_photos_hash ON photos(file_hash);
+            CREATE INDEX IF NOT EXISTS idx_photos_date ON photos(date_taken, id);
+            CREATE INDEX IF NOT EXISTS idx_snapshots_photo ON snapshots(photo_id);
+            CREATE INDEX IF NOT EXISTS idx_edit_history_photo
+                ON edit_history(photo_id, recorded_at);
+            CREATE INDEX IF NOT EXISTS idx_rating_history_photo
+                ON rating_history(photo_id, recorded_at);
+            CREATE INDEX IF NOT EXISTS idx_stack_members_stack
+                ON stack_members(stack_id, position);
             ",
         )?;
 
@@ -88,6 +232,10 @@ impl Catalog {
             "ALTER TABLE edits ADD COLUMN nr_color REAL NOT NULL DEFAULT 0.0",
             "ALTER TABLE edits ADD COLUMN vignette_amount REAL NOT NULL DEFAULT 0.0",
             "ALTER TABLE edits ADD COLUMN distortion REAL NOT NULL DEFAULT 0.0",
+            "ALTER TABLE photos ADD COLUMN title TEXT",
+            "ALTER TABLE photos ADD COLUMN caption TEXT",
+            "ALTER TABLE photos ADD COLUMN copyright TEXT",
+            "ALTER TABLE photos ADD COLUMN keywords TEXT",
         ];
         for stmt in alter_stmts {
             match self.conn.execute(stmt, []) {
@@ -97,6 +245,33 @@ impl Catalog {
             }
         }
 
+        self.backfill_history()
+    }
+
+    /// Give photos edited before history was kept a starting entry as of
+    /// their last save. Ratings get none: when a rating was set isn't
+    /// recorded anywhere, so any date would be made up.
+    fn backfill_history(&self) -> Result<()> {
+        let mut stmt = self.conn.prepare(
+            "SELECT photo_id, updated_at FROM edits
+             WHERE photo_id NOT IN (SELECT photo_id FROM edit_history)",
+        )?;
+        let missing = stmt
+            .query_map([], |row| {
+                Ok((row.get::<_, PhotoId>(0)?, row.get::<_, String>(1)?))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        for (id, updated_at) in missing {
+            let Some(edits) = self.get_edits(id)? else {
+                continue;
+            };
+            let json = serde_json::to_string(&edits.to_edit_params())
+                .context("failed to serialize edit history")?;
+            self.conn.execute(
+                "INSERT INTO edit_history (photo_id, params, recorded_at) VALUES (?1, ?2, ?3)",
+                params![id, json, updated_at],
+            )?;
+        }
         Ok(())
     }
 
@@ -136,7 +311,8 @@ impl Catalog {
         let mut stmt = self.conn.prepare(
             "SELECT id, file_path, file_hash, file_size, width, height,
                     camera_make, camera_model, lens, focal_length, aperture,
-                    shutter_speed, iso, date_taken, imported_at, thumbnail_path, rating
+                    shutter_speed, iso, date_taken, imported_at, thumbnail_path, rating,
+                    title, caption, copyright, keywords
              FROM photos WHERE id = ?1",
         )?;
         let mut rows = stmt.query_map(params![id], row_to_photo)?;
@@ -147,7 +323,8 @@ impl Catalog {
         let mut stmt = self.conn.prepare(
             "SELECT id, file_path, file_hash, file_size, width, height,
                     camera_make, camera_model, lens, focal_length, aperture,
-                    shutter_speed, iso, date_taken, imported_at, thumbnail_path, rating
+                    shutter_speed, iso, date_taken, imported_at, thumbnail_path, rating,
+                    title, caption, copyright, keywords
              FROM photos ORDER BY date_taken DESC, id DESC",
         )?;
         let photos = stmt
@@ -156,6 +333,38 @@ impl Catalog {
         Ok(photos)
     }
 
+    /// Walk the same rows as [`Catalog::list_photos`] with a single query,
+    /// handing them to `on_page` in chunks of `page_size` as they are read.
+    /// The date index lets SQLite return the first page without sorting the
+    /// whole table. Stops early if `on_page` returns `false`.
+    pub fn list_photos_paged(
+        &self,
+        page_size: usize,
+        mut on_page: impl FnMut(Vec<Photo>) -> bool,
+    ) -> Result<()> {
+        let page_size = page_size.max(1);
+        let mut stmt = self.conn.prepare(
+            "SELECT id, file_path, file_hash, file_size, width, height,
+                    camera_make, camera_model, lens, focal_length, aperture,
+                    shutter_speed, iso, date_taken, imported_at, thumbnail_path, rating,
+                    title, caption, copyright, keywords
+             FROM photos ORDER BY date_taken DESC, id DESC",
+        )?;
+        let mut page = Vec::with_capacity(page_size);
+        for photo in stmt.query_map([], row_to_photo)? {
+            page.push(photo?);
+            if page.len() == page_size
+                && !on_page(std::mem::replace(&mut page, Vec::with_capacity(page_size)))
+            {
+                return Ok(());
+            }
+        }
+        if !page.is_empty() {
+            on_page(page);
+        }
+        Ok(())
+    }
+
     pub fn update_thumbnail(&self, id: PhotoId, thumbnail_path: &str) -> Result<()> {
         self.conn.execute(
             "UPDATE photos SET thumbnail_path = ?1 WHERE id = ?2",
@@ -220,6 +429,15 @@ impl Catalog {
         &self,
         photo_id: PhotoId,
         params: &crema_core::image_buf::EditParams,
+    ) -> Result<()> {
+        self.write_edits(photo_id, params)?;
+        self.record_edit_history(photo_id, params, true)
+    }
+
+    fn write_edits(
+        &self,
+        photo_id: PhotoId,
+        params: &crema_core::image_buf::EditParams,
     ) -> Result<()> {
         self.conn.execute(
             "INSERT INTO edits (photo_id, exposure, wb_temp, wb_tint,
@@ -298,475 +516,2582 @@ impl Catalog {
         Ok(())
     }
 
-    pub fn set_rating(&self, id: PhotoId, rating: i32) -> Result<()> {
+    /// Append `params` to the photo's edit history unless they're what was
+    /// last recorded, so saving unchanged edits adds nothing. Edits are
+    /// saved on every preview render, so with `fold` set, saves within
+    /// [`HISTORY_MERGE_WINDOW`] of when the last entry was first recorded,
+    /// on the same day, replace its edits instead: one editing session
+    /// leaves one entry, dated when it began and holding its last change.
+    fn record_edit_history(
+        &self,
+        photo_id: PhotoId,
+        params: &crema_core::image_buf::EditParams,
+        fold: bool,
+    ) -> Result<()> {
+        let json = serde_json::to_string(params).context("failed to serialize edit history")?;
+        let last: Option<(i64, String, bool)> = self
+            .conn
+            .query_row(
+                "SELECT id, params,
+                        recorded_at >= datetime('now', ?2)
+                        AND date(recorded_at, 'localtime') = date('now', 'localtime')
+                 FROM edit_history WHERE photo_id = ?1 ORDER BY id DESC LIMIT 1",
+                params![photo_id, HISTORY_MERGE_WINDOW],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .optional()?;
+        match last {
+            Some((_, last, _)) if last == json => {}
+            Some((id, _, true)) if fold => {
+                self.conn.execute(
+                    "UPDATE edit_history SET params = ?2 WHERE id = ?1",
+                    params![id, json],
+                )?;
+            }
+            _ => {
+                self.conn.execute(
+                    "INSERT INTO edit_history (photo_id, params) VALUES (?1, ?2)",
+                    params![photo_id, json],
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Apply `adjust` to the stored edits of every photo in `ids` inside a
+    /// single transaction. Photos without edits start from the defaults.
+    /// Each change gets its own history entry, so the edits from before
+    /// the batch can still be recovered. Returns each photo's new
+    /// parameters.
+    pub fn adjust_edits(
+        &self,
+        ids: &[PhotoId],
+        adjust: impl Fn(&mut crema_core::image_buf::EditParams),
+    ) -> Result<Vec<(PhotoId, crema_core::image_buf::EditParams)>> {
+        let tx = self
+            .conn
+            .unchecked_transaction()
+            .context("failed to start edits transaction")?;
+        let mut updated = Vec::with_capacity(ids.len());
+        for &id in ids {
+            let mut params = self
+                .get_edits(id)?
+                .map(|e| e.to_edit_params())
+                .unwrap_or_default();
+            adjust(&mut params);
+            self.write_edits(id, &params)?;
+            self.record_edit_history(id, &params, false)?;
+            updated.push((id, params));
+        }
+        tx.commit().context("failed to commit edits")?;
+        Ok(updated)
+    }
+
+    /// Store a named copy of `params` for later comparison or restore.
+    pub fn save_snapshot(
+        &self,
+        photo_id: PhotoId,
+        name: &str,
+        params: &crema_core::image_buf::EditParams,
+    ) -> Result<SnapshotId> {
+        let json = serde_json::to_string(params).context("failed to serialize snapshot")?;
+        self.conn.execute(
+            "INSERT INTO snapshots (photo_id, name, params) VALUES (?1, ?2, ?3)",
+            params![photo_id, name, json],
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    pub fn list_snapshots(&self, photo_id: PhotoId) -> Result<Vec<Snapshot>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, photo_id, name, params, created_at
+             FROM snapshots WHERE photo_id = ?1 ORDER BY id",
+        )?;
+        let rows = stmt
+            .query_map(params![photo_id], |row| {
+                Ok((
+                    row.get::<_, SnapshotId>(0)?,
+                    row.get::<_, PhotoId>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, String>(3)?,
+                    row.get::<_, String>(4)?,
+                ))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        rows.into_iter()
+            .map(|(id, photo_id, name, json, created_at)| {
+                let params = serde_json::from_str(&json)
+                    .with_context(|| format!("snapshot {id} has malformed params"))?;
+                Ok(Snapshot {
+                    id,
+                    photo_id,
+                    name,
+                    params,
+                    created_at,
+                })
+            })
+            .collect()
+    }
+
+    pub fn update_snapshot(
+        &self,
+        id: SnapshotId,
+        params: &crema_core::image_buf::EditParams,
+    ) -> Result<()> {
+        let json = serde_json::to_string(params).context("failed to serialize snapshot")?;
         self.conn.execute(
-            "UPDATE photos SET rating = ?1 WHERE id = ?2",
-            params![rating.clamp(-1, 5), id],
+            "UPDATE snapshots SET params = ?1 WHERE id = ?2",
+            params![json, id],
         )?;
         Ok(())
     }
 
-    pub fn delete_photo(&self, id: PhotoId) -> Result<()> {
-        self.conn
-            .execute("DELETE FROM edits WHERE photo_id = ?1", params![id])?;
+    pub fn delete_snapshot(&self, id: SnapshotId) -> Result<()> {
         self.conn
-            .execute("DELETE FROM photos WHERE id = ?1", params![id])?;
+            .execute("DELETE FROM snapshots WHERE id = ?1", params![id])?;
         Ok(())
     }
 
-    pub fn photo_count(&self) -> Result<i64> {
-        Ok(self
-            .conn
-            .query_row("SELECT COUNT(*) FROM photos", [], |row| row.get(0))?)
+    /// Record that `file_path` could not be imported or decoded. A file
+    /// that fails again keeps its dismissed state, so a dismissed file
+    /// stays out of the review list.
+    pub fn quarantine_file(&self, file_path: &str, reason: &str) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO quarantine (file_path, reason) VALUES (?1, ?2)
+             ON CONFLICT(file_path) DO UPDATE SET
+                reason = excluded.reason,
+                failed_at = datetime('now')",
+            params![file_path, reason],
+        )?;
+        Ok(())
     }
-}
 
-fn row_to_photo(row: &rusqlite::Row<'_>) -> rusqlite::Result<Photo> {
-    Ok(Photo {
-        id: row.get(0)?,
-        file_path: row.get(1)?,
-        file_hash: row.get(2)?,
-        file_size: row.get(3)?,
-        width: row.get(4)?,
-        height: row.get(5)?,
-        camera_make: row.get(6)?,
-        camera_model: row.get(7)?,
-        lens: row.get(8)?,
-        focal_length: row.get(9)?,
-        aperture: row.get(10)?,
-        shutter_speed: row.get(11)?,
-        iso: row.get(12)?,
-        date_taken: row.get(13)?,
-        imported_at: row.get(14)?,
-        thumbnail_path: row.get(15)?,
-        rating: row.get(16)?,
-    })
-}
+    /// Quarantined files awaiting review, most recent failure first.
+    pub fn list_quarantine(&self) -> Result<Vec<QuarantinedFile>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, file_path, reason, failed_at FROM quarantine
+             WHERE dismissed = 0 ORDER BY failed_at DESC, id DESC",
+        )?;
+        let files = stmt
+            .query_map([], |row| {
+                Ok(QuarantinedFile {
+                    id: row.get(0)?,
+                    file_path: row.get(1)?,
+                    reason: row.get(2)?,
+                    failed_at: row.get(3)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(files)
+    }
 
-pub struct InsertPhoto {
-    pub file_path: String,
-    pub file_hash: String,
-    pub file_size: i64,
-    pub width: Option<u32>,
-    pub height: Option<u32>,
-    pub camera_make: Option<String>,
-    pub camera_model: Option<String>,
-    pub lens: Option<String>,
-    pub focal_length: Option<f64>,
-    pub aperture: Option<f64>,
-    pub shutter_speed: Option<String>,
-    pub iso: Option<u32>,
-    pub date_taken: Option<String>,
-    pub thumbnail_path: Option<String>,
-}
+    /// Forget a quarantined file, typically because it now decodes.
+    pub fn release_quarantine(&self, file_path: &str) -> Result<()> {
+        self.conn.execute(
+            "DELETE FROM quarantine WHERE file_path = ?1",
+            params![file_path],
+        )?;
+        Ok(())
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    /// Hide a quarantined file from review for good.
+    pub fn dismiss_quarantine(&self, id: QuarantineId) -> Result<()> {
+        self.conn.execute(
+            "UPDATE quarantine SET dismissed = 1 WHERE id = ?1",
+            params![id],
+        )?;
+        Ok(())
+    }
 
-    #[test]
-    fn create_and_list_photos() {
-        let catalog = Catalog::open_in_memory().unwrap();
+    /// Store the dead pixel map for a camera body, replacing any earlier one.
+    pub fn save_defect_map(
+        &self,
+        camera_serial: &str,
+        camera_model: Option<&str>,
+        map: &DefectMap,
+    ) -> Result<()> {
+        let json = serde_json::to_string(map).context("failed to serialize defect map")?;
+        self.conn.execute(
+            "INSERT OR REPLACE INTO defect_maps (camera_serial, camera_model, map)
+             VALUES (?1, ?2, ?3)",
+            params![camera_serial, camera_model, json],
+        )?;
+        Ok(())
+    }
 
-        let photo = InsertPhoto {
-            file_path: "/test/photo.jpg".to_string(),
-            file_hash: "abc123".to_string(),
-            file_size: 1024,
-            width: Some(4000),
-            height: Some(3000),
-            camera_make: Some("Canon".to_string()),
-            camera_model: Some("EOS R5".to_string()),
-            lens: None,
-            focal_length: Some(50.0),
-            aperture: Some(2.8),
-            shutter_speed: Some("1/200".to_string()),
-            iso: Some(400),
-            date_taken: Some("2024-01-15T10:30:00".to_string()),
-            thumbnail_path: None,
-        };
+    /// Every stored dead pixel map, keyed by camera serial.
+    pub fn list_defect_maps(&self) -> Result<Vec<CameraDefectMap>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT camera_serial, camera_model, map, created_at FROM defect_maps
+             ORDER BY camera_model, camera_serial",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, Option<String>>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, String>(3)?,
+            ))
+        })?;
+        let mut maps = Vec::new();
+        for row in rows {
+            let (camera_serial, camera_model, json, created_at) = row?;
+            let map = serde_json::from_str(&json)
+                .with_context(|| format!("corrupt defect map for camera {camera_serial}"))?;
+            maps.push(CameraDefectMap {
+                camera_serial,
+                camera_model,
+                map,
+                created_at,
+            });
+        }
+        Ok(maps)
+    }
 
-        let id = catalog
-            .insert_photo(&photo)
-            .unwrap()
-            .expect("should insert");
-        assert!(id > 0);
+    pub fn delete_defect_map(&self, camera_serial: &str) -> Result<()> {
+        self.conn.execute(
+            "DELETE FROM defect_maps WHERE camera_serial = ?1",
+            params![camera_serial],
+        )?;
+        Ok(())
+    }
 
-        let photos = catalog.list_photos().unwrap();
-        assert_eq!(photos.len(), 1);
-        assert_eq!(photos[0].file_path, "/test/photo.jpg");
-        assert_eq!(photos[0].camera_make.as_deref(), Some("Canon"));
+    /// Store the flat field for a camera and lens, replacing any earlier one.
+    pub fn save_flat_field(&self, camera_model: &str, lens: &str, field: &FlatField) -> Result<()> {
+        let json = serde_json::to_string(field).context("failed to serialize flat field")?;
+        self.conn.execute(
+            "INSERT OR REPLACE INTO flat_fields (camera_model, lens, field)
+             VALUES (?1, ?2, ?3)",
+            params![camera_model, lens, json],
+        )?;
+        Ok(())
     }
 
-    #[test]
-    fn duplicate_path_ignored() {
-        let catalog = Catalog::open_in_memory().unwrap();
+    pub fn list_flat_fields(&self) -> Result<Vec<LensFlatField>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT camera_model, lens, field, created_at FROM flat_fields
+             ORDER BY camera_model, lens",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, String>(3)?,
+            ))
+        })?;
+        let mut fields = Vec::new();
+        for row in rows {
+            let (camera_model, lens, json, created_at) = row?;
+            let field = serde_json::from_str(&json)
+                .with_context(|| format!("corrupt flat field for {camera_model} with {lens}"))?;
+            fields.push(LensFlatField {
+                camera_model,
+                lens,
+                field,
+                created_at,
+            });
+        }
+        Ok(fields)
+    }
 
-        let photo = InsertPhoto {
-            file_path: "/test/dup.jpg".to_string(),
-            file_hash: "hash1".to_string(),
-            file_size: 100,
-            width: None,
-            height: None,
-            camera_make: None,
-            camera_model: None,
-            lens: None,
-            focal_length: None,
-            aperture: None,
-            shutter_speed: None,
-            iso: None,
-            date_taken: None,
-            thumbnail_path: None,
-        };
+    pub fn delete_flat_field(&self, camera_model: &str, lens: &str) -> Result<()> {
+        self.conn.execute(
+            "DELETE FROM flat_fields WHERE camera_model = ?1 AND lens = ?2",
+            params![camera_model, lens],
+        )?;
+        Ok(())
+    }
 
-        let first = catalog.insert_photo(&photo).unwrap();
-        assert!(first.is_some());
-        let second = catalog.insert_photo(&photo).unwrap();
-        assert!(second.is_none());
+    pub fn save_master_dark(
+        &self,
+        settings: &DarkFrameSettings,
+        frame_count: u32,
+        dark: &MasterDark,
+    ) -> Result<MasterDarkId> {
+        self.conn.execute(
+            "INSERT INTO master_darks
+                (camera_model, iso, exposure_secs, temperature, frame_count, width, height, data)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                settings.camera_model,
+                settings.iso,
+                settings.exposure_secs,
+                settings.temperature,
+                frame_count,
+                dark.width,
+                dark.height,
+                dark.to_bytes(),
+            ],
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
 
-        assert_eq!(catalog.photo_count().unwrap(), 1);
+    /// Every master dark's settings. The pixels stay on disk until a
+    /// matching photo needs them.
+    pub fn list_master_darks(&self) -> Result<Vec<MasterDarkInfo>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, camera_model, iso, exposure_secs, temperature, frame_count,
+                    width, height, created_at
+             FROM master_darks ORDER BY camera_model, iso, exposure_secs",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok(MasterDarkInfo {
+                id: row.get(0)?,
+                settings: DarkFrameSettings {
+                    camera_model: row.get(1)?,
+                    iso: row.get(2)?,
+                    exposure_secs: row.get(3)?,
+                    temperature: row.get(4)?,
+                },
+                frame_count: row.get(5)?,
+                width: row.get(6)?,
+                height: row.get(7)?,
+                created_at: row.get(8)?,
+            })
+        })?;
+        rows.collect::<Result<Vec<_>, _>>()
+            .context("failed to list master darks")
     }
 
-    #[test]
-    fn save_and_load_edits() {
+    pub fn load_master_dark(&self, id: MasterDarkId) -> Result<MasterDark> {
+        let (width, height, data): (u32, u32, Vec<u8>) = self
+            .conn
+            .query_row(
+                "SELECT width, height, data FROM master_darks WHERE id = ?1",
+                params![id],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .context("master dark not found")?;
+        MasterDark::from_bytes(width, height, &data)
+    }
+
+    pub fn delete_master_dark(&self, id: MasterDarkId) -> Result<()> {
+        self.conn
+            .execute("DELETE FROM master_darks WHERE id = ?1", params![id])?;
+        Ok(())
+    }
+
+    pub fn set_rating(&self, id: PhotoId, rating: i32) -> Result<()> {
+        let rating = rating.clamp(-1, 5);
+        let changed = self.conn.execute(
+            "UPDATE photos SET rating = ?1 WHERE id = ?2 AND rating != ?1",
+            params![rating, id],
+        )?;
+        if changed > 0 {
+            self.conn.execute(
+                "INSERT INTO rating_history (photo_id, rating) VALUES (?1, ?2)",
+                params![id, rating],
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Every photo imported by `at`, a `YYYY-MM-DD HH:MM:SS` UTC time, with
+    /// the rating and edits it had then. Photos untouched by that time have
+    /// no rating and default edits, except that a rating never changed
+    /// while history was kept is taken as the one it has now.
+    pub fn library_as_of(&self, at: &str) -> Result<Vec<PastState>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT p.id,
+                    COALESCE(
+                        (SELECT rating FROM rating_history r
+                         WHERE r.photo_id = p.id AND r.recorded_at <= ?1
+                         ORDER BY r.recorded_at DESC, r.id DESC LIMIT 1),
+                        -- Never changed while history was kept.
+                        (SELECT p.rating WHERE NOT EXISTS
+                            (SELECT 1 FROM rating_history r WHERE r.photo_id = p.id))
+                    ),
+                    (SELECT params FROM edit_history e
+                     WHERE e.photo_id = p.id AND e.recorded_at <= ?1
+                     ORDER BY e.recorded_at DESC, e.id DESC LIMIT 1)
+             FROM photos p WHERE p.imported_at <= ?1 ORDER BY p.id",
+        )?;
+        let rows = stmt
+            .query_map(params![at], |row| {
+                Ok((
+                    row.get::<_, PhotoId>(0)?,
+                    row.get::<_, Option<i32>>(1)?,
+                    row.get::<_, Option<String>>(2)?,
+                ))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        rows.into_iter()
+            .map(|(photo_id, rating, params)| {
+                let params = match params {
+                    Some(json) => {
+                        serde_json::from_str(&json).context("failed to parse edit history")?
+                    }
+                    None => crema_core::image_buf::EditParams::default(),
+                };
+                Ok(PastState {
+                    photo_id,
+                    rating: rating.unwrap_or(0),
+                    params,
+                })
+            })
+            .collect()
+    }
+
+    /// Local days, newest first, on which any rating or edit changed: the
+    /// dates worth browsing the library as of.
+    pub fn history_dates(&self) -> Result<Vec<String>> {
+        self.history_dates_in("localtime")
+    }
+
+    /// [`Self::history_dates`] with history stamps taken to local time by
+    /// the SQLite date modifier `to_local`.
+    fn history_dates_in(&self, to_local: &str) -> Result<Vec<String>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT DISTINCT date(recorded_at, ?1) AS day FROM (
+                 SELECT recorded_at FROM edit_history
+                 UNION ALL SELECT recorded_at FROM rating_history
+             ) ORDER BY day DESC",
+        )?;
+        let dates = stmt
+            .query_map(params![to_local], |row| row.get(0))?
+            .collect::<rusqlite::Result<Vec<String>>>()?;
+        Ok(dates)
+    }
+
+    /// The UTC time, as history is stamped, of the local time `at`, both
+    /// `YYYY-MM-DD HH:MM:SS`.
+    pub fn local_to_utc(&self, at: &str) -> Result<String> {
+        self.shift_time(at, "utc")
+    }
+
+    /// `at` moved by the SQLite date modifier `modifier`.
+    fn shift_time(&self, at: &str, modifier: &str) -> Result<String> {
+        let shifted: Option<String> =
+            self.conn
+                .query_row("SELECT datetime(?1, ?2)", params![at, modifier], |row| {
+                    row.get(0)
+                })?;
+        shifted.with_context(|| format!("invalid time: {at}"))
+    }
+
+    /// Apply the same metadata change to every photo in `ids` inside a single
+    /// transaction. Fields left as `None` in `update` are not written.
+    /// Returns the number of photos updated.
+    pub fn update_metadata(&self, ids: &[PhotoId], update: &MetadataUpdate) -> Result<usize> {
+        if ids.is_empty() || update.is_empty() {
+            return Ok(0);
+        }
+
+        let keywords = update.keywords.as_deref().map(normalize_keywords);
+        let mut assignments = Vec::new();
+        let mut values: Vec<Option<String>> = Vec::new();
+        for (column, value) in [
+            ("title", update.title.as_deref()),
+            ("caption", update.caption.as_deref()),
+            ("copyright", update.copyright.as_deref()),
+            ("keywords", keywords.as_deref()),
+        ] {
+            if let Some(value) = value {
+                values.push(non_empty(value));
+                assignments.push(format!("{column} = ?{}", values.len()));
+            }
+        }
+        if let Some(seconds) = update.date_shift_seconds.filter(|s| *s != 0) {
+            values.push(Some(format!("{seconds:+} seconds")));
+            assignments.push(format!(
+                "date_taken = COALESCE(datetime({ISO_DATE_TAKEN}, ?{}), date_taken)",
+                values.len()
+            ));
+        }
+        if assignments.is_empty() {
+            return Ok(0);
+        }
+
+        let sql = format!(
+            "UPDATE photos SET {} WHERE id = ?{}",
+            assignments.join(", "),
+            values.len() + 1
+        );
+        let tx = self
+            .conn
+            .unchecked_transaction()
+            .context("failed to start metadata transaction")?;
+        let mut updated = 0;
+        {
+            let mut stmt = tx.prepare(&sql)?;
+            for &id in ids {
+                let mut bound: Vec<&dyn rusqlite::ToSql> =
+                    values.iter().map(|v| v as &dyn rusqlite::ToSql).collect();
+                bound.push(&id);
+                updated += stmt.execute(bound.as_slice())?;
+            }
+        }
+        tx.commit().context("failed to commit metadata update")?;
+        Ok(updated)
+    }
+
+    /// Store `fixup` as the camera and lens override of every photo in
+    /// `ids`, replacing earlier ones. An empty override clears them.
+    pub fn set_metadata_override(&self, ids: &[PhotoId], fixup: &MetadataOverride) -> Result<()> {
+        let tx = self
+            .conn
+            .unchecked_transaction()
+            .context("failed to start override transaction")?;
+        for &id in ids {
+            if fixup.is_empty() {
+                tx.execute(
+                    "DELETE FROM metadata_overrides WHERE photo_id = ?1",
+                    params![id],
+                )?;
+            } else {
+                tx.execute(
+                    "INSERT OR REPLACE INTO metadata_overrides
+                         (photo_id, camera_make, camera_model, lens, focal_length)
+                     VALUES (?1, ?2, ?3, ?4, ?5)",
+                    params![
+                        id,
+                        fixup.camera_make,
+                        fixup.camera_model,
+                        fixup.lens,
+                        fixup.focal_length,
+                    ],
+                )?;
+            }
+        }
+        tx.commit().context("failed to commit metadata override")
+    }
+
+    pub fn metadata_override(&self, id: PhotoId) -> Result<Option<MetadataOverride>> {
+        self.conn
+            .query_row(
+                "SELECT camera_make, camera_model, lens, focal_length
+                 FROM metadata_overrides WHERE photo_id = ?1",
+                params![id],
+                |row| row_to_metadata_override(row, 0),
+            )
+            .optional()
+            .context("failed to load metadata override")
+    }
+
+    pub fn list_metadata_overrides(&self) -> Result<HashMap<PhotoId, MetadataOverride>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT photo_id, camera_make, camera_model, lens, focal_length
+             FROM metadata_overrides",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok((row.get(0)?, row_to_metadata_override(row, 1)?))
+        })?;
+        rows.collect::<Result<_, _>>()
+            .context("failed to list metadata overrides")
+    }
+
+    /// Overrides keyed by file path, for corrections that only see the file.
+    pub fn metadata_overrides_by_path(&self) -> Result<HashMap<String, MetadataOverride>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT p.file_path, o.camera_make, o.camera_model, o.lens, o.focal_length
+             FROM metadata_overrides o JOIN photos p ON p.id = o.photo_id",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok((row.get(0)?, row_to_metadata_override(row, 1)?))
+        })?;
+        rows.collect::<Result<_, _>>()
+            .context("failed to list metadata overrides")
+    }
+
+    pub fn save_smart_preview(&self, preview: &SmartPreview) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO smart_previews (photo_id, path, width, height)
+             VALUES (?1, ?2, ?3, ?4)",
+            params![
+                preview.photo_id,
+                preview.path,
+                preview.width,
+                preview.height
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn smart_preview(&self, id: PhotoId) -> Result<Option<SmartPreview>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT photo_id, path, width, height FROM smart_previews WHERE photo_id = ?1",
+        )?;
+        let mut rows = stmt.query_map(params![id], row_to_smart_preview)?;
+        Ok(rows.next().transpose()?)
+    }
+
+    pub fn list_smart_previews(&self) -> Result<Vec<SmartPreview>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT photo_id, path, width, height FROM smart_previews")?;
+        let rows = stmt.query_map([], row_to_smart_preview)?;
+        rows.collect::<Result<Vec<_>, _>>()
+            .context("failed to list smart previews")
+    }
+
+    /// Mark a photo as animated. A key frame chosen earlier is kept if the
+    /// file still has it.
+    pub fn set_animation(&self, id: PhotoId, frame_count: u32) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO animations (photo_id, frame_count) VALUES (?1, ?2)
+             ON CONFLICT(photo_id) DO UPDATE SET
+                frame_count = excluded.frame_count,
+                key_frame = MIN(key_frame, excluded.frame_count - 1)",
+            params![id, frame_count],
+        )?;
+        Ok(())
+    }
+
+    pub fn animation(&self, id: PhotoId) -> Result<Option<Animation>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT photo_id, frame_count, key_frame FROM animations WHERE photo_id = ?1",
+        )?;
+        let mut rows = stmt.query_map(params![id], row_to_animation)?;
+        Ok(rows.next().transpose()?)
+    }
+
+    pub fn list_animations(&self) -> Result<Vec<Animation>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT photo_id, frame_count, key_frame FROM animations")?;
+        let rows = stmt.query_map([], row_to_animation)?;
+        rows.collect::<Result<Vec<_>, _>>()
+            .context("failed to list animations")
+    }
+
+    /// Choose the frame that stands for an animated photo.
+    pub fn set_key_frame(&self, id: PhotoId, key_frame: u32) -> Result<()> {
+        let animation = self
+            .animation(id)?
+            .with_context(|| format!("photo {id} is not animated"))?;
+        if key_frame >= animation.frame_count {
+            anyhow::bail!(
+                "frame {key_frame} is out of range; photo {id} has {} frames",
+                animation.frame_count
+            );
+        }
+        self.conn.execute(
+            "UPDATE animations SET key_frame = ?1 WHERE photo_id = ?2",
+            params![key_frame, id],
+        )?;
+        Ok(())
+    }
+
+    /// The photos whose paths lie under `from`, and where they'd move to
+    /// under `to`.
+    pub fn plan_path_remap(&self, from: &str, to: &str) -> Result<Vec<PathRemap>> {
+        let folder = from.trim_end_matches('/');
+        if folder.is_empty() {
+            return Ok(Vec::new());
+        }
+        // LIKE narrows the scan; `remap_prefix` then matches exactly, since
+        // LIKE ignores ASCII case.
+        let escaped = folder
+            .replace('\\', "\\\\")
+            .replace('%', "\\%")
+            .replace('_', "\\_");
+        let mut stmt = self.conn.prepare(
+            "SELECT id, file_path FROM photos
+             WHERE file_path = ?1 OR file_path LIKE ?2 ESCAPE '\\'
+             ORDER BY file_path",
+        )?;
+        let rows = stmt.query_map(params![folder, format!("{escaped}/%")], |row| {
+            Ok((row.get::<_, PhotoId>(0)?, row.get::<_, String>(1)?))
+        })?;
+        let mut remaps = Vec::new();
+        for row in rows {
+            let (photo_id, old_path) = row?;
+            if let Some(new_path) = remap_prefix(&old_path, from, to) {
+                remaps.push(PathRemap {
+                    photo_id,
+                    old_path,
+                    new_path,
+                });
+            }
+        }
+        Ok(remaps)
+    }
+
+    /// Rewrite every photo path under `from` to lie under `to`, along with
+    /// quarantined files there, in one transaction. Fails without changing
+    /// anything if a new path is already in the catalog.
+    pub fn remap_path_prefix(&self, from: &str, to: &str) -> Result<Vec<PathRemap>> {
+        let tx = self
+            .conn
+            .unchecked_transaction()
+            .context("failed to start remap transaction")?;
+        let remaps = self.plan_path_remap(from, to)?;
+        for remap in &remaps {
+            self.conn
+                .execute(
+                    "UPDATE photos SET file_path = ?1 WHERE id = ?2",
+                    params![remap.new_path, remap.photo_id],
+                )
+                .map_err(|err| match err.sqlite_error_code() {
+                    Some(rusqlite::ErrorCode::ConstraintViolation) => {
+                        anyhow::anyhow!("{} is already in the catalog", remap.new_path)
+                    }
+                    _ => anyhow::Error::new(err)
+                        .context(format!("failed to move {}", remap.old_path)),
+                })?;
+        }
+        // Dismissed files move too, so they stay dismissed at the new path.
+        let quarantined: Vec<String> = self
+            .conn
+            .prepare("SELECT file_path FROM quarantine")?
+            .query_map([], |row| row.get(0))?
+            .collect::<rusqlite::Result<_>>()?;
+        for old in quarantined {
+            if let Some(new) = remap_prefix(&old, from, to) {
+                self.conn.execute(
+                    "UPDATE OR REPLACE quarantine SET file_path = ?1 WHERE file_path = ?2",
+                    params![new, old],
+                )?;
+            }
+        }
+        for old in self.folder_thumbnail_fidelity()?.into_keys() {
+            if let Some(new) = remap_prefix(&old, from, to) {
+                self.conn.execute(
+                    "UPDATE OR REPLACE folder_settings SET folder = ?1 WHERE folder = ?2",
+                    params![new, old],
+                )?;
+            }
+        }
+        tx.commit().context("failed to commit remap")?;
+        info!(count = remaps.len(), from, to, "remapped photo paths");
+        Ok(remaps)
+    }
+
+    /// Record that `derived` was made from `sources`.
+    pub fn link_derived(
+        &self,
+        derived: PhotoId,
+        sources: &[PhotoId],
+        kind: DerivationKind,
+    ) -> Result<()> {
+        let tx = self
+            .conn
+            .unchecked_transaction()
+            .context("failed to start provenance transaction")?;
+        for &source in sources.iter().filter(|&&s| s != derived) {
+            self.conn.execute(
+                "INSERT OR REPLACE INTO derived_from (derived_id, source_id, kind)
+                 VALUES (?1, ?2, ?3)",
+                params![derived, source, kind.as_str()],
+            )?;
+        }
+        tx.commit().context("failed to commit provenance")
+    }
+
+    /// The photos `id` was made from.
+    pub fn sources_of(&self, id: PhotoId) -> Result<Vec<PhotoLink>> {
+        self.photo_links(
+            "SELECT source_id, kind FROM derived_from WHERE derived_id = ?1 ORDER BY source_id",
+            id,
+        )
+    }
+
+    /// The photos made from `id`.
+    pub fn derivatives_of(&self, id: PhotoId) -> Result<Vec<PhotoLink>> {
+        self.photo_links(
+            "SELECT derived_id, kind FROM derived_from WHERE source_id = ?1 ORDER BY derived_id",
+            id,
+        )
+    }
+
+    fn photo_links(&self, sql: &str, id: PhotoId) -> Result<Vec<PhotoLink>> {
+        let mut stmt = self.conn.prepare(sql)?;
+        let rows = stmt.query_map(params![id], |row| {
+            let kind: String = row.get(1)?;
+            Ok((row.get(0)?, kind))
+        })?;
+        let mut links = Vec::new();
+        for row in rows {
+            let (photo_id, kind) = row?;
+            match DerivationKind::parse(&kind) {
+                Some(kind) => links.push(PhotoLink { photo_id, kind }),
+                None => warn!(kind, "unknown derivation kind"),
+            }
+        }
+        Ok(links)
+    }
+
+    /// Replace the photo's annotations; an empty list removes them.
+    pub fn save_annotations(&self, id: PhotoId, annotations: &[Annotation]) -> Result<()> {
+        if annotations.is_empty() {
+            self.conn
+                .execute("DELETE FROM annotations WHERE photo_id = ?1", params![id])?;
+            return Ok(());
+        }
+        let data = serde_json::to_string(annotations).context("failed to encode annotations")?;
+        self.conn.execute(
+            "INSERT OR REPLACE INTO annotations (photo_id, data) VALUES (?1, ?2)",
+            params![id, data],
+        )?;
+        Ok(())
+    }
+
+    pub fn annotations(&self, id: PhotoId) -> Result<Vec<Annotation>> {
+        let data: Option<String> = self
+            .conn
+            .query_row(
+                "SELECT data FROM annotations WHERE photo_id = ?1",
+                params![id],
+                |row| row.get(0),
+            )
+            .optional()?;
+        match data {
+            Some(data) => serde_json::from_str(&data).context("failed to decode annotations"),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Thumbnail fidelity set for folders, overriding the app's own, by
+    /// folder path.
+    pub fn folder_thumbnail_fidelity(&self) -> Result<HashMap<String, ThumbnailFidelity>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT folder, thumbnail_fidelity FROM folder_settings")?;
+        let rows = stmt.query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })?;
+        let mut overrides = HashMap::new();
+        for row in rows {
+            let (folder, fidelity) = row?;
+            match ThumbnailFidelity::parse(&fidelity) {
+                Some(fidelity) => {
+                    overrides.insert(folder, fidelity);
+                }
+                None => warn!(%folder, %fidelity, "ignoring unknown thumbnail fidelity"),
+            }
+        }
+        Ok(overrides)
+    }
+
+    /// Override thumbnail fidelity for photos in `folder` and below it, or
+    /// with `None`, go back to the app's setting.
+    pub fn set_folder_thumbnail_fidelity(
+        &self,
+        folder: &str,
+        fidelity: Option<ThumbnailFidelity>,
+    ) -> Result<()> {
+        match fidelity {
+            Some(fidelity) => self.conn.execute(
+                "INSERT OR REPLACE INTO folder_settings (folder, thumbnail_fidelity)
+                 VALUES (?1, ?2)",
+                params![folder, fidelity.as_str()],
+            )?,
+            None => self.conn.execute(
+                "DELETE FROM folder_settings WHERE folder = ?1",
+                params![folder],
+            )?,
+        };
+        Ok(())
+    }
+
+    /// Stack `members`, cover first. Photos already in another stack move
+    /// to this one, and stacks left with a single photo are dissolved.
+    pub fn create_stack(&self, kind: StackKind, members: &[PhotoId]) -> Result<StackId> {
+        if members.len() < 2 {
+            anyhow::bail!("a stack needs at least two photos");
+        }
+        let tx = self
+            .conn
+            .unchecked_transaction()
+            .context("failed to start stack transaction")?;
+        tx.execute(
+            "INSERT INTO photo_stacks (kind) VALUES (?1)",
+            params![kind.as_str()],
+        )?;
+        let id = tx.last_insert_rowid();
+        for (position, &photo_id) in members.iter().enumerate() {
+            tx.execute(
+                "INSERT OR REPLACE INTO stack_members (photo_id, stack_id, position)
+                 VALUES (?1, ?2, ?3)",
+                params![photo_id, id, position as i64],
+            )?;
+        }
+        prune_stacks(&tx)?;
+        tx.commit().context("failed to commit stack")?;
+        Ok(id)
+    }
+
+    pub fn list_stacks(&self) -> Result<Vec<PhotoStack>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT s.id, s.kind, m.photo_id
+             FROM photo_stacks s JOIN stack_members m ON m.stack_id = s.id
+             ORDER BY s.id, m.position",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            let kind: String = row.get(1)?;
+            Ok((row.get::<_, StackId>(0)?, kind, row.get::<_, PhotoId>(2)?))
+        })?;
+        let mut stacks: Vec<PhotoStack> = Vec::new();
+        for row in rows {
+            let (id, kind, photo_id) = row?;
+            match stacks.last_mut() {
+                Some(stack) if stack.id == id => stack.members.push(photo_id),
+                _ => match StackKind::parse(&kind) {
+                    Some(kind) => stacks.push(PhotoStack {
+                        id,
+                        kind,
+                        members: vec![photo_id],
+                    }),
+                    None => warn!(kind, "unknown stack kind"),
+                },
+            }
+        }
+        Ok(stacks)
+    }
+
+    /// Dissolve a stack, leaving its photos in the library.
+    pub fn unstack(&self, id: StackId) -> Result<()> {
+        self.conn
+            .execute("DELETE FROM stack_members WHERE stack_id = ?1", params![id])?;
+        self.conn
+            .execute("DELETE FROM photo_stacks WHERE id = ?1", params![id])?;
+        Ok(())
+    }
+
+    /// Remove the photo and everything recorded about it, including its
+    /// smart preview record and provenance links in either direction. The
+    /// preview file is left to the caller.
+    pub fn delete_photo(&self, id: PhotoId) -> Result<()> {
+        let tx = self
+            .conn
+            .unchecked_transaction()
+            .context("failed to start delete transaction")?;
+        self.conn.execute(
+            "DELETE FROM derived_from WHERE derived_id = ?1 OR source_id = ?1",
+            params![id],
+        )?;
+        self.conn.execute(
+            "DELETE FROM smart_previews WHERE photo_id = ?1",
+            params![id],
+        )?;
+        self.conn
+            .execute("DELETE FROM animations WHERE photo_id = ?1", params![id])?;
+        self.conn
+            .execute("DELETE FROM edits WHERE photo_id = ?1", params![id])?;
+        self.conn
+            .execute("DELETE FROM edit_history WHERE photo_id = ?1", params![id])?;
+        self.conn.execute(
+            "DELETE FROM rating_history WHERE photo_id = ?1",
+            params![id],
+        )?;
+        self.conn
+            .execute("DELETE FROM snapshots WHERE photo_id = ?1", params![id])?;
+        self.conn.execute(
+            "DELETE FROM metadata_overrides WHERE photo_id = ?1",
+            params![id],
+        )?;
+        self.conn
+            .execute("DELETE FROM annotations WHERE photo_id = ?1", params![id])?;
+        self.conn
+            .execute("DELETE FROM stack_members WHERE photo_id = ?1", params![id])?;
+        prune_stacks(&self.conn)?;
+        self.conn.execute(
+            "DELETE FROM quarantine
+             WHERE file_path = (SELECT file_path FROM photos WHERE id = ?1)",
+            params![id],
+        )?;
+        self.conn
+            .execute("DELETE FROM photos WHERE id = ?1", params![id])?;
+        tx.commit().context("failed to commit photo deletion")?;
+        Ok(())
+    }
+
+    pub fn photo_count(&self) -> Result<i64> {
+        Ok(self
+            .conn
+            .query_row("SELECT COUNT(*) FROM photos", [], |row| row.get(0))?)
+    }
+
+    /// Photos per capture year, newest first. Photos without a usable
+    /// `YYYY-MM-DD` capture date aren't counted.
+    pub fn year_counts(&self) -> Result<Vec<(u16, usize)>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT CAST(substr(date_taken, 1, 4) AS INTEGER) AS year, COUNT(*)
+             FROM photos WHERE {DATED}
+             GROUP BY year ORDER BY year DESC"
+        ))?;
+        let counts = stmt
+            .query_map([], |row| {
+                Ok((row.get::<_, u16>(0)?, row.get::<_, i64>(1)? as usize))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(counts)
+    }
+
+    /// Photos per capture day within `year`, as `(month, day, count)` in
+    /// calendar order.
+    pub fn day_counts(&self, year: u16) -> Result<Vec<(u8, u8, usize)>> {
+        // A range on the raw column, so the date index narrows it down.
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT CAST(substr(date_taken, 6, 2) AS INTEGER) AS month,
+                    CAST(substr(date_taken, 9, 2) AS INTEGER) AS day, COUNT(*)
+             FROM photos
+             WHERE date_taken >= ?1 AND date_taken < ?2 AND {DATED}
+             GROUP BY month, day ORDER BY month, day"
+        ))?;
+        let counts = stmt
+            .query_map(
+                params![format!("{year:04}-"), format!("{:04}-", year + 1)],
+                |row| {
+                    Ok((
+                        row.get::<_, u8>(0)?,
+                        row.get::<_, u8>(1)?,
+                        row.get::<_, i64>(2)? as usize,
+                    ))
+                },
+            )?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(counts)
+    }
+}
+
+/// `date_taken` with EXIF's `YYYY:MM:DD` date written as `YYYY-MM-DD`, the
+/// form SQLite's date functions read. Other values pass through unchanged.
+const ISO_DATE_TAKEN: &str = "CASE
+    WHEN date_taken GLOB '[0-9][0-9][0-9][0-9]:[0-9][0-9]:[0-9][0-9]*'
+    THEN replace(substr(date_taken, 1, 10), ':', '-') || substr(date_taken, 11)
+    ELSE date_taken END";
+
+/// Matches rows whose `date_taken` starts with a valid `YYYY-MM-DD`, the
+/// dates the date sidebar groups by.
+const DATED: &str = "date_taken GLOB '[0-9][0-9][0-9][0-9]-[0-9][0-9]-[0-9][0-9]*'
+    AND CAST(substr(date_taken, 6, 2) AS INTEGER) BETWEEN 1 AND 12
+    AND CAST(substr(date_taken, 9, 2) AS INTEGER) BETWEEN 1 AND 31";
+
+/// `path` moved from under `from` to under `to`, or `None` if it isn't
+/// under `from`. Prefixes match whole path components, so `/Volumes/Old`
+/// doesn't take in `/Volumes/Older`.
+pub fn remap_prefix(path: &str, from: &str, to: &str) -> Option<String> {
+    if from.is_empty() {
+        return None;
+    }
+    let rest = std::path::Path::new(path)
+        .strip_prefix(from.trim_end_matches('/'))
+        .ok()?;
+    let moved = if rest.as_os_str().is_empty() {
+        std::path::PathBuf::from(to)
+    } else {
+        std::path::Path::new(to).join(rest)
+    };
+    Some(moved.to_string_lossy().to_string())
+}
+
+fn row_to_smart_preview(row: &rusqlite::Row<'_>) -> rusqlite::Result<SmartPreview> {
+    Ok(SmartPreview {
+        photo_id: row.get(0)?,
+        path: row.get(1)?,
+        width: row.get(2)?,
+        height: row.get(3)?,
+    })
+}
+
+/// Read an override from the four columns starting at `first`.
+fn row_to_metadata_override(
+    row: &rusqlite::Row<'_>,
+    first: usize,
+) -> rusqlite::Result<MetadataOverride> {
+    Ok(MetadataOverride {
+        camera_make: row.get(first)?,
+        camera_model: row.get(first + 1)?,
+        lens: row.get(first + 2)?,
+        focal_length: row.get(first + 3)?,
+    })
+}
+
+/// Drop stacks left with fewer than two photos.
+fn prune_stacks(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "DELETE FROM stack_members WHERE stack_id IN
+             (SELECT stack_id FROM stack_members GROUP BY stack_id HAVING COUNT(*) < 2);
+         DELETE FROM photo_stacks WHERE id NOT IN (SELECT stack_id FROM stack_members);",
+    )
+    .context("failed to prune stacks")
+}
+
+fn row_to_animation(row: &rusqlite::Row<'_>) -> rusqlite::Result<Animation> {
+    Ok(Animation {
+        photo_id: row.get(0)?,
+        frame_count: row.get(1)?,
+        key_frame: row.get(2)?,
+    })
+}
+
+fn row_to_photo(row: &rusqlite::Row<'_>) -> rusqlite::Result<Photo> {
+    Ok(Photo {
+        id: row.get(0)?,
+        file_path: row.get(1)?,
+        file_hash: row.get(2)?,
+        file_size: row.get(3)?,
+        width: row.get(4)?,
+        height: row.get(5)?,
+        camera_make: row.get(6)?,
+        camera_model: row.get(7)?,
+        lens: row.get(8)?,
+        focal_length: row.get(9)?,
+        aperture: row.get(10)?,
+        shutter_speed: row.get(11)?,
+        iso: row.get(12)?,
+        date_taken: row.get(13)?,
+        imported_at: row.get(14)?,
+        thumbnail_path: row.get(15)?,
+        rating: row.get(16)?,
+        title: row.get(17)?,
+        caption: row.get(18)?,
+        copyright: row.get(19)?,
+        keywords: row.get(20)?,
+    })
+}
+
+fn non_empty(value: &str) -> Option<String> {
+    let trimmed = value.trim();
+    (!trimmed.is_empty()).then(|| trimmed.to_string())
+}
+
+/// A batch metadata change. `Some("")` clears a field; `None` leaves it as is.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MetadataUpdate {
+    pub title: Option<String>,
+    pub caption: Option<String>,
+    pub copyright: Option<String>,
+    /// Comma-separated keyword list; normalized before it is stored.
+    pub keywords: Option<String>,
+    pub date_shift_seconds: Option<i64>,
+}
+
+impl MetadataUpdate {
+    pub fn is_empty(&self) -> bool {
+        self.title.is_none()
+            && self.caption.is_none()
+            && self.copyright.is_none()
+            && self.keywords.is_none()
+            && self.date_shift_seconds.unwrap_or(0) == 0
+    }
+}
+
+/// Trim, drop empties and duplicates, and join keywords with ", ".
+pub fn normalize_keywords(raw: &str) -> String {
+    let mut keywords: Vec<&str> = Vec::new();
+    for keyword in raw.split(',').map(str::trim) {
+        if !keyword.is_empty() && !keywords.iter().any(|k| k.eq_ignore_ascii_case(keyword)) {
+            keywords.push(keyword);
+        }
+    }
+    keywords.join(", ")
+}
+
+pub struct InsertPhoto {
+    pub file_path: String,
+    pub file_hash: String,
+    pub file_size: i64,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub camera_make: Option<String>,
+    pub camera_model: Option<String>,
+    pub lens: Option<String>,
+    pub focal_length: Option<f64>,
+    pub aperture: Option<f64>,
+    pub shutter_speed: Option<String>,
+    pub iso: Option<u32>,
+    pub date_taken: Option<String>,
+    pub thumbnail_path: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn create_and_list_photos() {
+        let catalog = Catalog::open_in_memory().unwrap();
+
+        let photo = InsertPhoto {
+            file_path: "/test/photo.jpg".to_string(),
+            file_hash: "abc123".to_string(),
+            file_size: 1024,
+            width: Some(4000),
+            height: Some(3000),
+            camera_make: Some("Canon".to_string()),
+            camera_model: Some("EOS R5".to_string()),
+            lens: None,
+            focal_length: Some(50.0),
+            aperture: Some(2.8),
+            shutter_speed: Some("1/200".to_string()),
+            iso: Some(400),
+            date_taken: Some("2024-01-15T10:30:00".to_string()),
+            thumbnail_path: None,
+        };
+
+        let id = catalog
+            .insert_photo(&photo)
+            .unwrap()
+            .expect("should insert");
+        assert!(id > 0);
+
+        let photos = catalog.list_photos().unwrap();
+        assert_eq!(photos.len(), 1);
+        assert_eq!(photos[0].file_path, "/test/photo.jpg");
+        assert_eq!(photos[0].camera_make.as_deref(), Some("Canon"));
+    }
+
+    #[test]
+    fn duplicate_path_ignored() {
+        let catalog = Catalog::open_in_memory().unwrap();
+
+        let photo = InsertPhoto {
+            file_path: "/test/dup.jpg".to_string(),
+            file_hash: "hash1".to_string(),
+            file_size: 100,
+            width: None,
+            height: None,
+            camera_make: None,
+            camera_model: None,
+            lens: None,
+            focal_length: None,
+            aperture: None,
+            shutter_speed: None,
+            iso: None,
+            date_taken: None,
+            thumbnail_path: None,
+        };
+
+        let first = catalog.insert_photo(&photo).unwrap();
+        assert!(first.is_some());
+        let second = catalog.insert_photo(&photo).unwrap();
+        assert!(second.is_none());
+
+        assert_eq!(catalog.photo_count().unwrap(), 1);
+    }
+
+    #[test]
+    fn save_and_load_edits() {
+        let catalog = Catalog::open_in_memory().unwrap();
+
+        let photo = InsertPhoto {
+            file_path: "/test/edit.jpg".to_string(),
+            file_hash: "hash2".to_string(),
+            file_size: 200,
+            width: None,
+            height: None,
+            camera_make: None,
+            camera_model: None,
+            lens: None,
+            focal_length: None,
+            aperture: None,
+            shutter_speed: None,
+            iso: None,
+            date_taken: None,
+            thumbnail_path: None,
+        };
+
+        let id = catalog.insert_photo(&photo).unwrap().unwrap();
+
+        let params = crema_core::image_buf::EditParams {
+            exposure: 1.5,
+            wb_temp: 6500.0,
+            wb_tint: -5.0,
+            ..Default::default()
+        };
+        catalog.save_edits(id, &params).unwrap();
+
+        let edit = catalog.get_edits(id).unwrap().unwrap();
+        assert!((edit.exposure - 1.5).abs() < 1e-6);
+        assert!((edit.wb_temp - 6500.0).abs() < 1e-6);
+    }
+
+    fn minimal_photo(path: &str) -> InsertPhoto {
+        InsertPhoto {
+            file_path: path.to_string(),
+            file_hash: format!("hash_{path}"),
+            file_size: 100,
+            width: None,
+            height: None,
+            camera_make: None,
+            camera_model: None,
+            lens: None,
+            focal_length: None,
+            aperture: None,
+            shutter_speed: None,
+            iso: None,
+            date_taken: None,
+            thumbnail_path: None,
+        }
+    }
+
+    #[test]
+    fn get_photo_by_id() {
+        let catalog = Catalog::open_in_memory().unwrap();
+        let id = catalog
+            .insert_photo(&minimal_photo("/a.jpg"))
+            .unwrap()
+            .unwrap();
+        let photo = catalog.get_photo(id).unwrap().unwrap();
+        assert_eq!(photo.file_path, "/a.jpg");
+    }
+
+    #[test]
+    fn get_nonexistent_photo() {
+        let catalog = Catalog::open_in_memory().unwrap();
+        assert!(catalog.get_photo(999).unwrap().is_none());
+    }
+
+    #[test]
+    fn get_edits_nonexistent() {
+        let catalog = Catalog::open_in_memory().unwrap();
+        let id = catalog
+            .insert_photo(&minimal_photo("/no_edits.jpg"))
+            .unwrap()
+            .unwrap();
+        assert!(catalog.get_edits(id).unwrap().is_none());
+    }
+
+    #[test]
+    fn update_thumbnail_path() {
+        let catalog = Catalog::open_in_memory().unwrap();
+        let id = catalog
+            .insert_photo(&minimal_photo("/thumb.jpg"))
+            .unwrap()
+            .unwrap();
+        catalog.update_thumbnail(id, "/cache/abc.jpg").unwrap();
+        let photo = catalog.get_photo(id).unwrap().unwrap();
+        assert_eq!(photo.thumbnail_path.as_deref(), Some("/cache/abc.jpg"));
+    }
+
+    #[test]
+    fn save_edits_overwrites_previous() {
+        let catalog = Catalog::open_in_memory().unwrap();
+        let id = catalog
+            .insert_photo(&minimal_photo("/overwrite.jpg"))
+            .unwrap()
+            .unwrap();
+
+        let params1 = crema_core::image_buf::EditParams {
+            exposure: 1.0,
+            ..Default::default()
+        };
+        catalog.save_edits(id, &params1).unwrap();
+
+        let params2 = crema_core::image_buf::EditParams {
+            exposure: 2.0,
+            ..Default::default()
+        };
+        catalog.save_edits(id, &params2).unwrap();
+
+        let edit = catalog.get_edits(id).unwrap().unwrap();
+        assert!((edit.exposure - 2.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn multiple_photos_ordering() {
+        let catalog = Catalog::open_in_memory().unwrap();
+
+        let mut p1 = minimal_photo("/first.jpg");
+        p1.date_taken = Some("2024-01-01T00:00:00".to_string());
+
+        let mut p2 = minimal_photo("/second.jpg");
+        p2.date_taken = Some("2024-06-01T00:00:00".to_string());
+
+        catalog.insert_photo(&p1).unwrap().unwrap();
+        catalog.insert_photo(&p2).unwrap().unwrap();
+
+        let photos = catalog.list_photos().unwrap();
+        assert_eq!(photos.len(), 2);
+        // Ordered by date_taken DESC
+        assert_eq!(photos[0].file_path, "/second.jpg");
+        assert_eq!(photos[1].file_path, "/first.jpg");
+    }
+
+    #[test]
+    fn photo_count_empty() {
         let catalog = Catalog::open_in_memory().unwrap();
+        assert_eq!(catalog.photo_count().unwrap(), 0);
+    }
 
-        let photo = InsertPhoto {
-            file_path: "/test/edit.jpg".to_string(),
-            file_hash: "hash2".to_string(),
-            file_size: 200,
-            width: None,
-            height: None,
-            camera_make: None,
-            camera_model: None,
-            lens: None,
-            focal_length: None,
-            aperture: None,
-            shutter_speed: None,
-            iso: None,
-            date_taken: None,
-            thumbnail_path: None,
+    #[test]
+    fn date_counts_group_by_year_then_day() {
+        let catalog = Catalog::open_in_memory().unwrap();
+        let dates = [
+            Some("2026-02-05 10:00:00"),
+            Some("2026-02-05 11:00:00"),
+            Some("2026-01-15 08:00:00"),
+            Some("2025-06-01 12:00:00"),
+            Some("2025-13-01 12:00:00"),
+            None,
+        ];
+        for (i, date) in dates.into_iter().enumerate() {
+            let photo = InsertPhoto {
+                date_taken: date.map(String::from),
+                ..minimal_photo(&format!("/{i}.jpg"))
+            };
+            catalog.insert_photo(&photo).unwrap();
+        }
+
+        assert_eq!(catalog.year_counts().unwrap(), [(2026, 3), (2025, 1)]);
+        assert_eq!(catalog.day_counts(2026).unwrap(), [(1, 15, 1), (2, 5, 2)]);
+        assert_eq!(catalog.day_counts(2025).unwrap(), [(6, 1, 1)]);
+        assert!(catalog.day_counts(2024).unwrap().is_empty());
+    }
+
+    #[test]
+    fn edit_record_to_edit_params() {
+        let catalog = Catalog::open_in_memory().unwrap();
+        let id = catalog
+            .insert_photo(&minimal_photo("/convert.jpg"))
+            .unwrap()
+            .unwrap();
+
+        let params = crema_core::image_buf::EditParams {
+            exposure: -2.0,
+            wb_temp: 8000.0,
+            wb_tint: 15.0,
+            contrast: 30.0,
+            highlights: -50.0,
+            shadows: 25.0,
+            blacks: -15.0,
+            vibrance: 20.0,
+            saturation: -10.0,
+            hsl_hue: 30.0,
+            hsl_saturation: -20.0,
+            hsl_lightness: 15.0,
+            split_shadow_hue: 220.0,
+            split_shadow_sat: 40.0,
+            split_highlight_hue: 45.0,
+            split_highlight_sat: 25.0,
+            split_balance: -10.0,
+            nr_luminance: 35.0,
+            nr_color: 20.0,
+            sharpen_amount: 50.0,
+            sharpen_radius: 1.5,
+            vignette_amount: -25.0,
+            distortion: 10.0,
+            rotation: 12.5,
+            crop_x: 0.1,
+            crop_y: 0.2,
+            crop_w: 0.5,
+            crop_h: 0.6,
+        };
+        catalog.save_edits(id, &params).unwrap();
+
+        let edit = catalog.get_edits(id).unwrap().unwrap();
+        let converted = edit.to_edit_params();
+        assert!((converted.exposure - (-2.0)).abs() < 1e-6);
+        assert!((converted.wb_temp - 8000.0).abs() < 1e-6);
+        assert!((converted.contrast - 30.0).abs() < 1e-6);
+        assert!((converted.highlights - (-50.0)).abs() < 1e-6);
+        assert!((converted.shadows - 25.0).abs() < 1e-6);
+        assert!((converted.blacks - (-15.0)).abs() < 1e-6);
+        assert!((converted.vibrance - 20.0).abs() < 1e-6);
+        assert!((converted.saturation - (-10.0)).abs() < 1e-6);
+        assert!((converted.vignette_amount - (-25.0)).abs() < 1e-6);
+        assert!((converted.distortion - 10.0).abs() < 1e-6);
+        assert!((converted.crop_x - 0.1).abs() < 1e-6);
+        assert!((converted.crop_h - 0.6).abs() < 1e-6);
+    }
+
+    #[test]
+    fn roundtrip_new_fields() {
+        let catalog = Catalog::open_in_memory().unwrap();
+        let id = catalog
+            .insert_photo(&minimal_photo("/new_fields.jpg"))
+            .unwrap()
+            .unwrap();
+
+        let params = crema_core::image_buf::EditParams {
+            contrast: 25.0,
+            highlights: -30.0,
+            shadows: 40.0,
+            blacks: -10.0,
+            vibrance: 15.0,
+            saturation: -20.0,
+            ..Default::default()
+        };
+        catalog.save_edits(id, &params).unwrap();
+
+        let edit = catalog.get_edits(id).unwrap().unwrap();
+        assert!((edit.contrast - 25.0).abs() < 1e-6);
+        assert!((edit.highlights - (-30.0)).abs() < 1e-6);
+        assert!((edit.shadows - 40.0).abs() < 1e-6);
+        assert!((edit.blacks - (-10.0)).abs() < 1e-6);
+        assert!((edit.vibrance - 15.0).abs() < 1e-6);
+        assert!((edit.saturation - (-20.0)).abs() < 1e-6);
+    }
+
+    #[test]
+    fn set_and_get_rating() {
+        let catalog = Catalog::open_in_memory().unwrap();
+        let id = catalog
+            .insert_photo(&minimal_photo("/rated.jpg"))
+            .unwrap()
+            .unwrap();
+
+        let photo = catalog.get_photo(id).unwrap().unwrap();
+        assert_eq!(photo.rating, 0);
+
+        catalog.set_rating(id, 3).unwrap();
+        let photo = catalog.get_photo(id).unwrap().unwrap();
+        assert_eq!(photo.rating, 3);
+
+        catalog.set_rating(id, 5).unwrap();
+        let photo = catalog.get_photo(id).unwrap().unwrap();
+        assert_eq!(photo.rating, 5);
+    }
+
+    #[test]
+    fn metadata_override_lays_over_exif_until_cleared() {
+        let catalog = Catalog::open_in_memory().unwrap();
+        let a = catalog
+            .insert_photo(&minimal_photo("/shoot/a.jpg"))
+            .unwrap()
+            .unwrap();
+        let b = catalog
+            .insert_photo(&minimal_photo("/shoot/b.jpg"))
+            .unwrap()
+            .unwrap();
+        let fixup = MetadataOverride {
+            lens: Some("Helios 44-2 58mm f/2".into()),
+            focal_length: Some(58.0),
+            ..Default::default()
+        };
+        catalog.set_metadata_override(&[a, b], &fixup).unwrap();
+
+        // The EXIF columns are untouched; the override sits beside them.
+        let mut photo = catalog.get_photo(a).unwrap().unwrap();
+        assert_eq!(photo.lens, None);
+        assert_eq!(catalog.metadata_override(a).unwrap(), Some(fixup.clone()));
+        fixup.apply(&mut photo);
+        assert_eq!(photo.lens.as_deref(), Some("Helios 44-2 58mm f/2"));
+        assert_eq!(photo.focal_length, Some(58.0));
+        assert_eq!(
+            catalog.metadata_overrides_by_path().unwrap()["/shoot/b.jpg"],
+            fixup
+        );
+
+        catalog
+            .set_metadata_override(&[a], &MetadataOverride::default())
+            .unwrap();
+        let remaining = catalog.list_metadata_overrides().unwrap();
+        assert_eq!(remaining.keys().collect::<Vec<_>>(), vec![&b]);
+
+        catalog.delete_photo(b).unwrap();
+        assert!(catalog.list_metadata_overrides().unwrap().is_empty());
+    }
+
+    #[test]
+    fn annotations_round_trip_and_clear() {
+        let catalog = Catalog::open_in_memory().unwrap();
+        let id = catalog
+            .insert_photo(&minimal_photo("/proof.jpg"))
+            .unwrap()
+            .unwrap();
+        assert!(catalog.annotations(id).unwrap().is_empty());
+
+        let notes = vec![
+            Annotation::Arrow {
+                from: [0.1, 0.2],
+                to: [0.3, 0.4],
+            },
+            Annotation::Note {
+                at: [0.5, 0.5],
+                text: "Clone out the sign".into(),
+            },
+        ];
+        catalog.save_annotations(id, &notes).unwrap();
+        assert_eq!(catalog.annotations(id).unwrap(), notes);
+
+        catalog.save_annotations(id, &[]).unwrap();
+        assert!(catalog.annotations(id).unwrap().is_empty());
+        catalog.save_annotations(id, &notes).unwrap();
+        catalog.delete_photo(id).unwrap();
+        assert!(catalog.annotations(id).unwrap().is_empty());
+    }
+
+    #[test]
+    fn stacks_move_members_and_dissolve_when_one_is_left() {
+        let catalog = Catalog::open_in_memory().unwrap();
+        let ids: Vec<PhotoId> = ["/s/1.nef", "/s/1.jpg", "/s/2.nef", "/s/3.nef"]
+            .iter()
+            .map(|path| catalog.insert_photo(&minimal_photo(path)).unwrap().unwrap())
+            .collect();
+        let pair = catalog
+            .create_stack(StackKind::RawJpeg, &[ids[0], ids[1]])
+            .unwrap();
+        assert!(catalog.create_stack(StackKind::Burst, &[ids[2]]).is_err());
+
+        // Taking the JPEG into a burst leaves the pair with one photo.
+        let burst = catalog
+            .create_stack(StackKind::Burst, &[ids[2], ids[1], ids[3]])
+            .unwrap();
+        let stacks = catalog.list_stacks().unwrap();
+        assert_eq!(
+            stacks,
+            vec![PhotoStack {
+                id: burst,
+                kind: StackKind::Burst,
+                members: vec![ids[2], ids[1], ids[3]],
+            }]
+        );
+        assert_ne!(pair, burst);
+        assert_eq!(stacks[0].cover(), ids[2]);
+
+        catalog.delete_photo(ids[3]).unwrap();
+        assert_eq!(catalog.list_stacks().unwrap()[0].members.len(), 2);
+        catalog.unstack(burst).unwrap();
+        assert!(catalog.list_stacks().unwrap().is_empty());
+    }
+
+    #[test]
+    fn set_rating_clamps() {
+        let catalog = Catalog::open_in_memory().unwrap();
+        let id = catalog
+            .insert_photo(&minimal_photo("/clamp.jpg"))
+            .unwrap()
+            .unwrap();
+
+        catalog.set_rating(id, 10).unwrap();
+        let photo = catalog.get_photo(id).unwrap().unwrap();
+        assert_eq!(photo.rating, 5);
+
+        catalog.set_rating(id, -3).unwrap();
+        let photo = catalog.get_photo(id).unwrap().unwrap();
+        assert_eq!(photo.rating, -1);
+    }
+
+    #[test]
+    fn delete_photo_removes_photo_and_edits() {
+        let catalog = Catalog::open_in_memory().unwrap();
+        let id = catalog
+            .insert_photo(&minimal_photo("/delete_me.jpg"))
+            .unwrap()
+            .unwrap();
+
+        let params = crema_core::image_buf::EditParams {
+            exposure: 1.0,
+            ..Default::default()
         };
+        catalog.save_edits(id, &params).unwrap();
+
+        catalog.delete_photo(id).unwrap();
+        assert!(catalog.get_photo(id).unwrap().is_none());
+        assert!(catalog.get_edits(id).unwrap().is_none());
+        assert_eq!(catalog.photo_count().unwrap(), 0);
+    }
+
+    #[test]
+    fn rating_default_zero_in_list() {
+        let catalog = Catalog::open_in_memory().unwrap();
+        catalog
+            .insert_photo(&minimal_photo("/default_rating.jpg"))
+            .unwrap()
+            .unwrap();
+
+        let photos = catalog.list_photos().unwrap();
+        assert_eq!(photos[0].rating, 0);
+    }
+
+    #[test]
+    fn idempotent_migration() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("test_catalog.db");
+        let path_str = db_path.to_str().unwrap();
+
+        let _catalog1 = Catalog::open(path_str).unwrap();
+        drop(_catalog1);
+        let _catalog2 = Catalog::open(path_str).unwrap();
+    }
+
+    #[test]
+    fn open_existing_reads_a_migrated_catalog() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("test_catalog.db");
+        let path_str = db_path.to_str().unwrap();
+        let id = Catalog::open(path_str)
+            .unwrap()
+            .insert_photo(&minimal_photo("/a.jpg"))
+            .unwrap()
+            .unwrap();
+
+        let catalog = Catalog::open_existing(path_str).unwrap();
+        assert_eq!(catalog.photo_count().unwrap(), 1);
+        let enforced: bool = catalog
+            .conn
+            .query_row("PRAGMA foreign_keys", [], |row| row.get(0))
+            .unwrap();
+        assert!(enforced);
+        catalog.set_rating(id, 5).unwrap();
+    }
+
+    #[test]
+    fn foreign_key_rejects_orphan_edit() {
+        let catalog = Catalog::open_in_memory().unwrap();
+        let params = crema_core::image_buf::EditParams::default();
+        let result = catalog.save_edits(999, &params);
+        assert!(
+            result.is_err(),
+            "FK should reject edit for nonexistent photo"
+        );
+    }
 
-        let id = catalog.insert_photo(&photo).unwrap().unwrap();
+    #[test]
+    fn old_edits_get_defaults() {
+        let catalog = Catalog::open_in_memory().unwrap();
+        let id = catalog
+            .insert_photo(&minimal_photo("/old_style.jpg"))
+            .unwrap()
+            .unwrap();
 
         let params = crema_core::image_buf::EditParams {
-            exposure: 1.5,
-            wb_temp: 6500.0,
-            wb_tint: -5.0,
+            exposure: 1.0,
+            wb_temp: 5500.0,
+            wb_tint: 0.0,
             ..Default::default()
         };
         catalog.save_edits(id, &params).unwrap();
 
         let edit = catalog.get_edits(id).unwrap().unwrap();
-        assert!((edit.exposure - 1.5).abs() < 1e-6);
-        assert!((edit.wb_temp - 6500.0).abs() < 1e-6);
+        assert!((edit.contrast - 0.0).abs() < 1e-6);
+        assert!((edit.highlights - 0.0).abs() < 1e-6);
+        assert!((edit.shadows - 0.0).abs() < 1e-6);
+        assert!((edit.blacks - 0.0).abs() < 1e-6);
+        assert!((edit.vibrance - 0.0).abs() < 1e-6);
+        assert!((edit.saturation - 0.0).abs() < 1e-6);
     }
 
-    fn minimal_photo(path: &str) -> InsertPhoto {
-        InsertPhoto {
-            file_path: path.to_string(),
-            file_hash: format!("hash_{path}"),
-            file_size: 100,
-            width: None,
-            height: None,
-            camera_make: None,
-            camera_model: None,
-            lens: None,
-            focal_length: None,
-            aperture: None,
-            shutter_speed: None,
-            iso: None,
-            date_taken: None,
-            thumbnail_path: None,
+    #[test]
+    fn update_metadata_writes_only_given_fields() {
+        let catalog = Catalog::open_in_memory().unwrap();
+        let a = catalog
+            .insert_photo(&minimal_photo("/meta_a.jpg"))
+            .unwrap()
+            .unwrap();
+        let b = catalog
+            .insert_photo(&minimal_photo("/meta_b.jpg"))
+            .unwrap()
+            .unwrap();
+
+        let first = MetadataUpdate {
+            title: Some("Original".into()),
+            caption: Some("Keep me".into()),
+            ..Default::default()
+        };
+        assert_eq!(catalog.update_metadata(&[a, b], &first).unwrap(), 2);
+
+        let second = MetadataUpdate {
+            title: Some("Renamed".into()),
+            ..Default::default()
+        };
+        catalog.update_metadata(&[a], &second).unwrap();
+
+        let pa = catalog.get_photo(a).unwrap().unwrap();
+        let pb = catalog.get_photo(b).unwrap().unwrap();
+        assert_eq!(pa.title.as_deref(), Some("Renamed"));
+        assert_eq!(pa.caption.as_deref(), Some("Keep me"));
+        assert_eq!(pb.title.as_deref(), Some("Original"));
+    }
+
+    #[test]
+    fn update_metadata_empty_string_clears() {
+        let catalog = Catalog::open_in_memory().unwrap();
+        let id = catalog
+            .insert_photo(&minimal_photo("/clear.jpg"))
+            .unwrap()
+            .unwrap();
+        let set = MetadataUpdate {
+            copyright: Some("(c) Someone".into()),
+            ..Default::default()
+        };
+        catalog.update_metadata(&[id], &set).unwrap();
+        let clear = MetadataUpdate {
+            copyright: Some("  ".into()),
+            ..Default::default()
+        };
+        catalog.update_metadata(&[id], &clear).unwrap();
+        assert!(catalog.get_photo(id).unwrap().unwrap().copyright.is_none());
+    }
+
+    #[test]
+    fn update_metadata_normalizes_keywords() {
+        let catalog = Catalog::open_in_memory().unwrap();
+        let id = catalog
+            .insert_photo(&minimal_photo("/kw.jpg"))
+            .unwrap()
+            .unwrap();
+        let update = MetadataUpdate {
+            keywords: Some(" beach, , Sunset,beach ,sunset ".into()),
+            ..Default::default()
+        };
+        catalog.update_metadata(&[id], &update).unwrap();
+        let photo = catalog.get_photo(id).unwrap().unwrap();
+        assert_eq!(photo.keywords.as_deref(), Some("beach, Sunset"));
+    }
+
+    #[test]
+    fn update_metadata_shifts_dates() {
+        let catalog = Catalog::open_in_memory().unwrap();
+        let mut dated = minimal_photo("/dated.jpg");
+        dated.date_taken = Some("2024-12-31 23:30:00".into());
+        let a = catalog.insert_photo(&dated).unwrap().unwrap();
+        let b = catalog
+            .insert_photo(&minimal_photo("/undated.jpg"))
+            .unwrap()
+            .unwrap();
+
+        let update = MetadataUpdate {
+            date_shift_seconds: Some(90 * 60),
+            ..Default::default()
+        };
+        catalog.update_metadata(&[a, b], &update).unwrap();
+
+        let pa = catalog.get_photo(a).unwrap().unwrap();
+        let pb = catalog.get_photo(b).unwrap().unwrap();
+        assert_eq!(pa.date_taken.as_deref(), Some("2025-01-01 01:00:00"));
+        assert!(pb.date_taken.is_none());
+    }
+
+    #[test]
+    fn update_metadata_shifts_exif_dates() {
+        let catalog = Catalog::open_in_memory().unwrap();
+        let mut exif = minimal_photo("/exif.jpg");
+        exif.date_taken = Some("2024:12:31 23:30:00".into());
+        let a = catalog.insert_photo(&exif).unwrap().unwrap();
+        let mut garbled = minimal_photo("/garbled.jpg");
+        garbled.date_taken = Some("0000:00:00 00:00:00".into());
+        let b = catalog.insert_photo(&garbled).unwrap().unwrap();
+
+        let update = MetadataUpdate {
+            date_shift_seconds: Some(-30 * 60),
+            ..Default::default()
+        };
+        catalog.update_metadata(&[a, b], &update).unwrap();
+
+        let pa = catalog.get_photo(a).unwrap().unwrap();
+        let pb = catalog.get_photo(b).unwrap().unwrap();
+        assert_eq!(pa.date_taken.as_deref(), Some("2024-12-31 23:00:00"));
+        // Dates SQLite can't read are kept rather than wiped.
+        assert_eq!(pb.date_taken.as_deref(), Some("0000:00:00 00:00:00"));
+    }
+
+    #[test]
+    fn update_metadata_noop() {
+        let catalog = Catalog::open_in_memory().unwrap();
+        let id = catalog
+            .insert_photo(&minimal_photo("/noop.jpg"))
+            .unwrap()
+            .unwrap();
+        assert!(MetadataUpdate::default().is_empty());
+        let update = MetadataUpdate {
+            date_shift_seconds: Some(0),
+            ..Default::default()
+        };
+        assert_eq!(catalog.update_metadata(&[id], &update).unwrap(), 0);
+        assert_eq!(
+            catalog
+                .update_metadata(&[], &MetadataUpdate::default())
+                .unwrap(),
+            0
+        );
+    }
+
+    #[test]
+    fn snapshot_round_trip() {
+        let catalog = Catalog::open_in_memory().unwrap();
+        let id = catalog
+            .insert_photo(&minimal_photo("/snap.jpg"))
+            .unwrap()
+            .unwrap();
+        let params = crema_core::image_buf::EditParams {
+            exposure: 0.7,
+            crop_w: 0.5,
+            ..crema_core::image_buf::EditParams::default()
+        };
+        let snap = catalog.save_snapshot(id, "Warm", &params).unwrap();
+
+        let snapshots = catalog.list_snapshots(id).unwrap();
+        assert_eq!(snapshots.len(), 1);
+        assert_eq!(snapshots[0].id, snap);
+        assert_eq!(snapshots[0].name, "Warm");
+        assert_eq!(snapshots[0].params, params);
+
+        let updated = crema_core::image_buf::EditParams {
+            exposure: -1.0,
+            ..params
+        };
+        catalog.update_snapshot(snap, &updated).unwrap();
+        assert_eq!(catalog.list_snapshots(id).unwrap()[0].params, updated);
+
+        catalog.delete_snapshot(snap).unwrap();
+        assert!(catalog.list_snapshots(id).unwrap().is_empty());
+    }
+
+    #[test]
+    fn failed_delete_leaves_photo_intact() {
+        let catalog = Catalog::open_in_memory().unwrap();
+        let id = catalog
+            .insert_photo(&minimal_photo("/a.jpg"))
+            .unwrap()
+            .unwrap();
+        catalog
+            .save_edits(id, &crema_core::image_buf::EditParams::default())
+            .unwrap();
+        // Fail on the last statement, after the related rows are gone.
+        catalog
+            .conn
+            .execute_batch(
+                "CREATE TRIGGER keep_photos BEFORE DELETE ON photos
+                 BEGIN SELECT RAISE(ABORT, 'photo is locked'); END;",
+            )
+            .unwrap();
+
+        assert!(catalog.delete_photo(id).is_err());
+        assert!(catalog.get_photo(id).unwrap().is_some());
+        assert!(catalog.get_edits(id).unwrap().is_some());
+    }
+
+    #[test]
+    fn delete_photo_removes_snapshots() {
+        let catalog = Catalog::open_in_memory().unwrap();
+        let id = catalog
+            .insert_photo(&minimal_photo("/gone.jpg"))
+            .unwrap()
+            .unwrap();
+        catalog
+            .save_snapshot(id, "A", &crema_core::image_buf::EditParams::default())
+            .unwrap();
+        catalog.delete_photo(id).unwrap();
+        assert!(catalog.list_snapshots(id).unwrap().is_empty());
+    }
+
+    #[test]
+    fn animation_key_frame_round_trip() {
+        let catalog = Catalog::open_in_memory().unwrap();
+        let id = catalog
+            .insert_photo(&minimal_photo("/loop.gif"))
+            .unwrap()
+            .unwrap();
+        assert!(catalog.animation(id).unwrap().is_none());
+        assert!(catalog.set_key_frame(id, 0).is_err());
+
+        catalog.set_animation(id, 12).unwrap();
+        catalog.set_key_frame(id, 7).unwrap();
+        assert!(catalog.set_key_frame(id, 12).is_err());
+        assert_eq!(catalog.animation(id).unwrap().unwrap().key_frame, 7);
+
+        // Re-marking keeps the choice while the frame still exists.
+        catalog.set_animation(id, 10).unwrap();
+        assert_eq!(catalog.animation(id).unwrap().unwrap().key_frame, 7);
+        catalog.set_animation(id, 4).unwrap();
+        let animation = catalog.animation(id).unwrap().unwrap();
+        assert_eq!((animation.frame_count, animation.key_frame), (4, 3));
+        assert_eq!(catalog.list_animations().unwrap(), vec![animation]);
+
+        catalog.delete_photo(id).unwrap();
+        assert!(catalog.list_animations().unwrap().is_empty());
+    }
+
+    #[test]
+    fn library_as_of_reconstructs_past_ratings_and_edits() {
+        let catalog = Catalog::open_in_memory().unwrap();
+        let a = catalog
+            .insert_photo(&minimal_photo("/a.jpg"))
+            .unwrap()
+            .unwrap();
+        let b = catalog
+            .insert_photo(&minimal_photo("/b.jpg"))
+            .unwrap()
+            .unwrap();
+        let backdate = |table: &str, when: &str| {
+            catalog
+                .conn
+                .execute(
+                    &format!("UPDATE {table} SET recorded_at = ?1 WHERE recorded_at > ?1"),
+                    params![when],
+                )
+                .unwrap();
+        };
+        catalog
+            .conn
+            .execute("UPDATE photos SET imported_at = '2024-01-01 09:00:00'", [])
+            .unwrap();
+
+        catalog.set_rating(a, 4).unwrap();
+        let mut edits = crema_core::image_buf::EditParams {
+            exposure: 0.5,
+            ..crema_core::image_buf::EditParams::default()
+        };
+        catalog.save_edits(a, &edits).unwrap();
+        backdate("rating_history", "2024-02-01 12:00:00");
+        backdate("edit_history", "2024-02-01 12:00:00");
+
+        // An overzealous batch change later on.
+        catalog.set_rating(a, 1).unwrap();
+        catalog.set_rating(b, 1).unwrap();
+        edits.exposure = 3.0;
+        catalog.save_edits(a, &edits).unwrap();
+        // Saving the same edits again records nothing new.
+        catalog.save_edits(a, &edits).unwrap();
+
+        let past = catalog.library_as_of("2024-02-01 23:59:59").unwrap();
+        assert_eq!(past.len(), 2);
+        assert_eq!((past[0].photo_id, past[0].rating), (a, 4));
+        assert_eq!(past[0].params.exposure, 0.5);
+        assert_eq!((past[1].photo_id, past[1].rating), (b, 0));
+        assert_eq!(past[1].params, Default::default());
+
+        assert!(
+            catalog
+                .library_as_of("2023-12-31 23:59:59")
+                .unwrap()
+                .is_empty()
+        );
+        let dates = catalog.history_dates().unwrap();
+        assert_eq!(dates.last().map(String::as_str), Some("2024-02-01"));
+        assert_eq!(dates.len(), 2);
+
+        catalog.delete_photo(a).unwrap();
+        let count: i64 = catalog
+            .conn
+            .query_row("SELECT COUNT(*) FROM edit_history", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(count, 0);
+    }
+
+    #[test]
+    fn history_days_are_local() {
+        let catalog = Catalog::open_in_memory().unwrap();
+        let id = catalog
+            .insert_photo(&minimal_photo("/a.jpg"))
+            .unwrap()
+            .unwrap();
+        catalog
+            .conn
+            .execute("UPDATE photos SET imported_at = '2024-01-01 09:00:00'", [])
+            .unwrap();
+        catalog.set_rating(id, 4).unwrap();
+        // An evening rating five hours behind UTC is stamped the next day.
+        catalog
+            .conn
+            .execute(
+                "UPDATE rating_history SET recorded_at = '2024-02-02 01:30:00'",
+                [],
+            )
+            .unwrap();
+
+        assert_eq!(
+            catalog.history_dates_in("-5 hours").unwrap(),
+            ["2024-02-01"]
+        );
+        let end = catalog
+            .shift_time("2024-02-01 23:59:59", "+5 hours")
+            .unwrap();
+        assert_eq!(end, "2024-02-02 04:59:59");
+        assert_eq!(catalog.library_as_of(&end).unwrap()[0].rating, 4);
+        let before = catalog
+            .shift_time("2024-01-31 23:59:59", "+5 hours")
+            .unwrap();
+        assert_eq!(catalog.library_as_of(&before).unwrap()[0].rating, 0);
+        assert!(catalog.local_to_utc("not a time").is_err());
+    }
+
+    #[test]
+    fn one_editing_session_records_one_history_entry() {
+        let catalog = Catalog::open_in_memory().unwrap();
+        let id = catalog
+            .insert_photo(&minimal_photo("/a.jpg"))
+            .unwrap()
+            .unwrap();
+        // A slider drag saves on every render.
+        for step in 1..=20 {
+            let edits = crema_core::image_buf::EditParams {
+                exposure: step as f32 * 0.1,
+                ..crema_core::image_buf::EditParams::default()
+            };
+            catalog.save_edits(id, &edits).unwrap();
+        }
+
+        let history: Vec<String> = catalog
+            .conn
+            .prepare("SELECT params FROM edit_history WHERE photo_id = ?1")
+            .unwrap()
+            .query_map(params![id], |row| row.get(0))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap();
+        assert_eq!(history.len(), 1);
+        let last: crema_core::image_buf::EditParams = serde_json::from_str(&history[0]).unwrap();
+        assert_eq!(last.exposure, 2.0);
+    }
+
+    #[test]
+    fn long_sessions_and_batch_changes_keep_earlier_history() {
+        let catalog = Catalog::open_in_memory().unwrap();
+        let id = catalog
+            .insert_photo(&minimal_photo("/a.jpg"))
+            .unwrap()
+            .unwrap();
+        let started = |minutes_ago: i32| {
+            catalog
+                .conn
+                .execute(
+                    "UPDATE edit_history SET recorded_at = datetime('now', ?1)",
+                    params![format!("-{minutes_ago} minutes")],
+                )
+                .unwrap();
+        };
+        let with_exposure = |exposure| crema_core::image_buf::EditParams {
+            exposure,
+            ..crema_core::image_buf::EditParams::default()
+        };
+        let history = || -> Vec<f32> {
+            catalog
+                .conn
+                .prepare("SELECT params FROM edit_history WHERE photo_id = ?1 ORDER BY id")
+                .unwrap()
+                .query_map(params![id], |row| row.get::<_, String>(0))
+                .unwrap()
+                .map(|json| {
+                    serde_json::from_str::<crema_core::image_buf::EditParams>(&json.unwrap())
+                        .unwrap()
+                        .exposure
+                })
+                .collect()
+        };
+
+        // Saves a few minutes apart fold in only while the entry is young.
+        catalog.save_edits(id, &with_exposure(0.5)).unwrap();
+        started(4);
+        catalog.save_edits(id, &with_exposure(1.0)).unwrap();
+        started(6);
+        catalog.save_edits(id, &with_exposure(1.5)).unwrap();
+        assert_eq!(history(), vec![1.0, 1.5]);
+
+        // A batch change right after an edit keeps the edit.
+        catalog.adjust_edits(&[id], |p| p.exposure += 1.0).unwrap();
+        assert_eq!(history(), vec![1.0, 1.5, 2.5]);
+    }
+
+    #[test]
+    fn ratings_from_before_history_are_not_backdated() {
+        let catalog = Catalog::open_in_memory().unwrap();
+        let a = catalog
+            .insert_photo(&minimal_photo("/a.jpg"))
+            .unwrap()
+            .unwrap();
+        // Rated before ratings were recorded.
+        catalog
+            .conn
+            .execute("UPDATE photos SET rating = 3 WHERE id = ?1", params![a])
+            .unwrap();
+        catalog.backfill_history().unwrap();
+
+        let rows: i64 = catalog
+            .conn
+            .query_row("SELECT COUNT(*) FROM rating_history", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(rows, 0);
+        assert!(catalog.history_dates().unwrap().is_empty());
+        let past = catalog.library_as_of("9999-12-31 23:59:59").unwrap();
+        assert_eq!(past[0].rating, 3);
+    }
+
+    #[test]
+    fn adjust_edits_is_relative() {
+        let catalog = Catalog::open_in_memory().unwrap();
+        let a = catalog
+            .insert_photo(&minimal_photo("/a.jpg"))
+            .unwrap()
+            .unwrap();
+        let b = catalog
+            .insert_photo(&minimal_photo("/b.jpg"))
+            .unwrap()
+            .unwrap();
+        let edited = crema_core::image_buf::EditParams {
+            exposure: 1.0,
+            ..crema_core::image_buf::EditParams::default()
+        };
+        catalog.save_edits(a, &edited).unwrap();
+
+        let updated = catalog
+            .adjust_edits(&[a, b], |p| p.exposure += 0.5)
+            .unwrap();
+        assert_eq!(updated.len(), 2);
+        assert_eq!(updated[0].0, a);
+
+        let ea = catalog.get_edits(a).unwrap().unwrap();
+        let eb = catalog.get_edits(b).unwrap().unwrap();
+        assert!((ea.exposure - 1.5).abs() < 1e-6);
+        assert!((eb.exposure - 0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn list_photos_paged_matches_list_photos() {
+        let catalog = Catalog::open_in_memory().unwrap();
+        for i in 0..7 {
+            let mut photo = minimal_photo(&format!("/p{i}.jpg"));
+            photo.date_taken = Some(format!("2024-01-0{} 12:00:00", i % 3 + 1));
+            catalog.insert_photo(&photo).unwrap();
         }
+
+        let mut pages = Vec::new();
+        catalog
+            .list_photos_paged(3, |page| {
+                pages.push(page.len());
+                true
+            })
+            .unwrap();
+        assert_eq!(pages, [3, 3, 1]);
+
+        let mut paged = Vec::new();
+        catalog
+            .list_photos_paged(3, |page| {
+                paged.extend(page.into_iter().map(|p| p.id));
+                true
+            })
+            .unwrap();
+        let all: Vec<_> = catalog
+            .list_photos()
+            .unwrap()
+            .into_iter()
+            .map(|p| p.id)
+            .collect();
+        assert_eq!(paged, all);
     }
 
     #[test]
-    fn get_photo_by_id() {
+    fn list_photos_paged_stops_early() {
         let catalog = Catalog::open_in_memory().unwrap();
-        let id = catalog
-            .insert_photo(&minimal_photo("/a.jpg"))
-            .unwrap()
+        for i in 0..5 {
+            catalog
+                .insert_photo(&minimal_photo(&format!("/s{i}.jpg")))
+                .unwrap();
+        }
+        let mut calls = 0;
+        catalog
+            .list_photos_paged(2, |_| {
+                calls += 1;
+                false
+            })
             .unwrap();
-        let photo = catalog.get_photo(id).unwrap().unwrap();
-        assert_eq!(photo.file_path, "/a.jpg");
+        assert_eq!(calls, 1);
     }
 
     #[test]
-    fn get_nonexistent_photo() {
+    fn quarantine_lists_and_releases() {
         let catalog = Catalog::open_in_memory().unwrap();
-        assert!(catalog.get_photo(999).unwrap().is_none());
+        catalog
+            .quarantine_file("/a.nef", "unsupported camera")
+            .unwrap();
+        catalog.quarantine_file("/b.jpg", "truncated").unwrap();
+        catalog
+            .quarantine_file("/a.nef", "still unsupported")
+            .unwrap();
+
+        let files = catalog.list_quarantine().unwrap();
+        assert_eq!(files.len(), 2);
+        let a = files.iter().find(|f| f.file_path == "/a.nef").unwrap();
+        assert_eq!(a.reason, "still unsupported");
+
+        catalog.release_quarantine("/a.nef").unwrap();
+        let files = catalog.list_quarantine().unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].file_path, "/b.jpg");
     }
 
     #[test]
-    fn get_edits_nonexistent() {
+    fn dismissed_quarantine_stays_hidden() {
         let catalog = Catalog::open_in_memory().unwrap();
-        let id = catalog
-            .insert_photo(&minimal_photo("/no_edits.jpg"))
-            .unwrap()
+        catalog.quarantine_file("/bad.cr3", "corrupt").unwrap();
+        let id = catalog.list_quarantine().unwrap()[0].id;
+        catalog.dismiss_quarantine(id).unwrap();
+        assert!(catalog.list_quarantine().unwrap().is_empty());
+
+        catalog
+            .quarantine_file("/bad.cr3", "corrupt again")
             .unwrap();
-        assert!(catalog.get_edits(id).unwrap().is_none());
+        assert!(catalog.list_quarantine().unwrap().is_empty());
     }
 
     #[test]
-    fn update_thumbnail_path() {
+    fn dismissed_quarantine_stays_hidden_after_a_remap() {
         let catalog = Catalog::open_in_memory().unwrap();
-        let id = catalog
-            .insert_photo(&minimal_photo("/thumb.jpg"))
-            .unwrap()
+        catalog
+            .quarantine_file("/Volumes/Old/bad.cr3", "corrupt")
             .unwrap();
-        catalog.update_thumbnail(id, "/cache/abc.jpg").unwrap();
-        let photo = catalog.get_photo(id).unwrap().unwrap();
-        assert_eq!(photo.thumbnail_path.as_deref(), Some("/cache/abc.jpg"));
+        let id = catalog.list_quarantine().unwrap()[0].id;
+        catalog.dismiss_quarantine(id).unwrap();
+
+        catalog
+            .remap_path_prefix("/Volumes/Old", "/Volumes/New")
+            .unwrap();
+        catalog
+            .quarantine_file("/Volumes/New/bad.cr3", "corrupt again")
+            .unwrap();
+        assert!(catalog.list_quarantine().unwrap().is_empty());
     }
 
     #[test]
-    fn save_edits_overwrites_previous() {
+    fn defect_maps_are_keyed_by_serial() {
         let catalog = Catalog::open_in_memory().unwrap();
-        let id = catalog
-            .insert_photo(&minimal_photo("/overwrite.jpg"))
-            .unwrap()
+        let map = DefectMap {
+            width: 100,
+            height: 80,
+            pixels: vec![(3, 4), (90, 70)],
+        };
+        catalog.save_defect_map("123", Some("X-T5"), &map).unwrap();
+        catalog
+            .save_defect_map(
+                "456",
+                None,
+                &DefectMap {
+                    pixels: vec![],
+                    ..map.clone()
+                },
+            )
             .unwrap();
 
-        let params1 = crema_core::image_buf::EditParams {
-            exposure: 1.0,
-            ..Default::default()
+        let replacement = DefectMap {
+            pixels: vec![(1, 1)],
+            ..map.clone()
         };
-        catalog.save_edits(id, &params1).unwrap();
+        catalog
+            .save_defect_map("123", Some("X-T5"), &replacement)
+            .unwrap();
 
-        let params2 = crema_core::image_buf::EditParams {
-            exposure: 2.0,
-            ..Default::default()
-        };
-        catalog.save_edits(id, &params2).unwrap();
+        let maps = catalog.list_defect_maps().unwrap();
+        assert_eq!(maps.len(), 2);
+        let x_t5 = maps.iter().find(|m| m.camera_serial == "123").unwrap();
+        assert_eq!(x_t5.camera_model.as_deref(), Some("X-T5"));
+        assert_eq!(x_t5.map, replacement);
 
-        let edit = catalog.get_edits(id).unwrap().unwrap();
-        assert!((edit.exposure - 2.0).abs() < 1e-6);
+        catalog.delete_defect_map("123").unwrap();
+        assert_eq!(catalog.list_defect_maps().unwrap().len(), 1);
     }
 
     #[test]
-    fn multiple_photos_ordering() {
+    fn flat_fields_are_keyed_by_camera_and_lens() {
         let catalog = Catalog::open_in_memory().unwrap();
+        let field = FlatField {
+            width: 2,
+            height: 1,
+            gains: vec![1.0, 1.0, 1.0, 1.5, 1.4, 1.6],
+        };
+        catalog
+            .save_flat_field("Z 7", "50mm f/1.8", &field)
+            .unwrap();
+        catalog.save_flat_field("Z 7", "", &field).unwrap();
+        catalog
+            .save_flat_field("Z 7", "50mm f/1.8", &field)
+            .unwrap();
 
-        let mut p1 = minimal_photo("/first.jpg");
-        p1.date_taken = Some("2024-01-01T00:00:00".to_string());
-
-        let mut p2 = minimal_photo("/second.jpg");
-        p2.date_taken = Some("2024-06-01T00:00:00".to_string());
-
-        catalog.insert_photo(&p1).unwrap().unwrap();
-        catalog.insert_photo(&p2).unwrap().unwrap();
+        let fields = catalog.list_flat_fields().unwrap();
+        assert_eq!(fields.len(), 2);
+        assert_eq!(fields[1].lens, "50mm f/1.8");
+        assert_eq!(fields[1].field, field);
 
-        let photos = catalog.list_photos().unwrap();
-        assert_eq!(photos.len(), 2);
-        // Ordered by date_taken DESC
-        assert_eq!(photos[0].file_path, "/second.jpg");
-        assert_eq!(photos[1].file_path, "/first.jpg");
+        catalog.delete_flat_field("Z 7", "").unwrap();
+        assert_eq!(catalog.list_flat_fields().unwrap().len(), 1);
     }
 
     #[test]
-    fn photo_count_empty() {
+    fn master_darks_store_settings_and_pixels() {
         let catalog = Catalog::open_in_memory().unwrap();
-        assert_eq!(catalog.photo_count().unwrap(), 0);
+        let settings = DarkFrameSettings {
+            camera_model: "EOS Ra".into(),
+            iso: 1600,
+            exposure_secs: 120.0,
+            temperature: Some(12.5),
+        };
+        let dark = MasterDark {
+            width: 2,
+            height: 1,
+            data: vec![0.01, 0.02],
+        };
+        let id = catalog.save_master_dark(&settings, 20, &dark).unwrap();
+
+        let darks = catalog.list_master_darks().unwrap();
+        assert_eq!(darks.len(), 1);
+        assert_eq!(darks[0].id, id);
+        assert_eq!(darks[0].settings, settings);
+        assert_eq!(darks[0].frame_count, 20);
+        assert_eq!(catalog.load_master_dark(id).unwrap(), dark);
+
+        catalog.delete_master_dark(id).unwrap();
+        assert!(catalog.list_master_darks().unwrap().is_empty());
+        assert!(catalog.load_master_dark(id).is_err());
     }
 
     #[test]
-    fn edit_record_to_edit_params() {
+    fn smart_previews_are_recorded_per_photo() {
+        let dir = tempfile::tempdir().unwrap();
+        let proxy = dir.path().join("proxy.jpg");
+        std::fs::write(&proxy, b"proxy").unwrap();
+
         let catalog = Catalog::open_in_memory().unwrap();
         let id = catalog
-            .insert_photo(&minimal_photo("/convert.jpg"))
+            .insert_photo(&minimal_photo("/photos/photo.jpg"))
             .unwrap()
             .unwrap();
+        assert!(catalog.smart_preview(id).unwrap().is_none());
 
-        let params = crema_core::image_buf::EditParams {
-            exposure: -2.0,
-            wb_temp: 8000.0,
-            wb_tint: 15.0,
-            contrast: 30.0,
-            highlights: -50.0,
-            shadows: 25.0,
-            blacks: -15.0,
-            vibrance: 20.0,
-            saturation: -10.0,
-            hsl_hue: 30.0,
-            hsl_saturation: -20.0,
-            hsl_lightness: 15.0,
-            split_shadow_hue: 220.0,
-            split_shadow_sat: 40.0,
-            split_highlight_hue: 45.0,
-            split_highlight_sat: 25.0,
-            split_balance: -10.0,
-            nr_luminance: 35.0,
-            nr_color: 20.0,
-            sharpen_amount: 50.0,
-            sharpen_radius: 1.5,
-            vignette_amount: -25.0,
-            distortion: 10.0,
-            rotation: 12.5,
-            crop_x: 0.1,
-            crop_y: 0.2,
-            crop_w: 0.5,
-            crop_h: 0.6,
+        let preview = SmartPreview {
+            photo_id: id,
+            path: proxy.to_string_lossy().to_string(),
+            width: 2560,
+            height: 1707,
         };
-        catalog.save_edits(id, &params).unwrap();
+        catalog.save_smart_preview(&preview).unwrap();
+        assert_eq!(catalog.smart_preview(id).unwrap(), Some(preview.clone()));
+        assert_eq!(catalog.list_smart_previews().unwrap(), vec![preview]);
 
-        let edit = catalog.get_edits(id).unwrap().unwrap();
-        let converted = edit.to_edit_params();
-        assert!((converted.exposure - (-2.0)).abs() < 1e-6);
-        assert!((converted.wb_temp - 8000.0).abs() < 1e-6);
-        assert!((converted.contrast - 30.0).abs() < 1e-6);
-        assert!((converted.highlights - (-50.0)).abs() < 1e-6);
-        assert!((converted.shadows - 25.0).abs() < 1e-6);
-        assert!((converted.blacks - (-15.0)).abs() < 1e-6);
-        assert!((converted.vibrance - 20.0).abs() < 1e-6);
-        assert!((converted.saturation - (-10.0)).abs() < 1e-6);
-        assert!((converted.vignette_amount - (-25.0)).abs() < 1e-6);
-        assert!((converted.distortion - 10.0).abs() < 1e-6);
-        assert!((converted.crop_x - 0.1).abs() < 1e-6);
-        assert!((converted.crop_h - 0.6).abs() < 1e-6);
+        catalog.delete_photo(id).unwrap();
+        assert!(proxy.exists());
+        assert!(catalog.list_smart_previews().unwrap().is_empty());
     }
 
     #[test]
-    fn roundtrip_new_fields() {
-        let catalog = Catalog::open_in_memory().unwrap();
-        let id = catalog
-            .insert_photo(&minimal_photo("/new_fields.jpg"))
-            .unwrap()
-            .unwrap();
-
-        let params = crema_core::image_buf::EditParams {
-            contrast: 25.0,
-            highlights: -30.0,
-            shadows: 40.0,
-            blacks: -10.0,
-            vibrance: 15.0,
-            saturation: -20.0,
-            ..Default::default()
-        };
-        catalog.save_edits(id, &params).unwrap();
-
-        let edit = catalog.get_edits(id).unwrap().unwrap();
-        assert!((edit.contrast - 25.0).abs() < 1e-6);
-        assert!((edit.highlights - (-30.0)).abs() < 1e-6);
-        assert!((edit.shadows - 40.0).abs() < 1e-6);
-        assert!((edit.blacks - (-10.0)).abs() < 1e-6);
-        assert!((edit.vibrance - 15.0).abs() < 1e-6);
-        assert!((edit.saturation - (-20.0)).abs() < 1e-6);
+    fn remap_prefix_matches_whole_components() {
+        let remap = |path| remap_prefix(path, "/Volumes/Old", "/Volumes/New");
+        assert_eq!(
+            remap("/Volumes/Old/2024/a.nef").as_deref(),
+            Some("/Volumes/New/2024/a.nef")
+        );
+        assert_eq!(remap("/Volumes/Older/a.nef"), None);
+        assert_eq!(remap("/Users/me/a.nef"), None);
+        assert_eq!(
+            remap_prefix("/Volumes/Old/a.nef", "/Volumes/Old/", "/Volumes/New/").as_deref(),
+            Some("/Volumes/New/a.nef")
+        );
+        assert_eq!(remap_prefix("/a.nef", "", "/b"), None);
     }
 
     #[test]
-    fn set_and_get_rating() {
+    fn remap_rewrites_photos_and_quarantine() {
         let catalog = Catalog::open_in_memory().unwrap();
-        let id = catalog
-            .insert_photo(&minimal_photo("/rated.jpg"))
-            .unwrap()
+        let insert = |path: &str| catalog.insert_photo(&minimal_photo(path)).unwrap().unwrap();
+        let moved = insert("/Volumes/Old/a.nef");
+        let kept = insert("/Users/me/b.nef");
+        catalog
+            .quarantine_file("/Volumes/Old/broken.nef", "decode failed")
             .unwrap();
 
-        let photo = catalog.get_photo(id).unwrap().unwrap();
-        assert_eq!(photo.rating, 0);
-
-        catalog.set_rating(id, 3).unwrap();
-        let photo = catalog.get_photo(id).unwrap().unwrap();
-        assert_eq!(photo.rating, 3);
-
-        catalog.set_rating(id, 5).unwrap();
-        let photo = catalog.get_photo(id).unwrap().unwrap();
-        assert_eq!(photo.rating, 5);
+        let plan = catalog
+            .plan_path_remap("/Volumes/Old", "/Volumes/New")
+            .unwrap();
+        assert_eq!(
+            plan,
+            vec![PathRemap {
+                photo_id: moved,
+                old_path: "/Volumes/Old/a.nef".into(),
+                new_path: "/Volumes/New/a.nef".into(),
+            }]
+        );
+        assert_eq!(
+            catalog
+                .remap_path_prefix("/Volumes/Old", "/Volumes/New")
+                .unwrap(),
+            plan
+        );
+        let path = |id| catalog.get_photo(id).unwrap().unwrap().file_path;
+        assert_eq!(path(moved), "/Volumes/New/a.nef");
+        assert_eq!(path(kept), "/Users/me/b.nef");
+        assert_eq!(
+            catalog.list_quarantine().unwrap()[0].file_path,
+            "/Volumes/New/broken.nef"
+        );
     }
 
     #[test]
-    fn set_rating_clamps() {
+    fn remap_plans_take_folder_names_literally() {
         let catalog = Catalog::open_in_memory().unwrap();
-        let id = catalog
-            .insert_photo(&minimal_photo("/clamp.jpg"))
-            .unwrap()
-            .unwrap();
-
-        catalog.set_rating(id, 10).unwrap();
-        let photo = catalog.get_photo(id).unwrap().unwrap();
-        assert_eq!(photo.rating, 5);
+        let insert = |path: &str| catalog.insert_photo(&minimal_photo(path)).unwrap().unwrap();
+        let literal = insert("/shoots/100%_done/a.nef");
+        insert("/shoots/100x_done/b.nef");
+        insert("/SHOOTS/100%_done/c.nef");
 
-        catalog.set_rating(id, -3).unwrap();
-        let photo = catalog.get_photo(id).unwrap().unwrap();
-        assert_eq!(photo.rating, -1);
+        let plan = catalog
+            .plan_path_remap("/shoots/100%_done", "/archive")
+            .unwrap();
+        let ids: Vec<_> = plan.iter().map(|r| r.photo_id).collect();
+        assert_eq!(ids, [literal]);
     }
 
     #[test]
-    fn delete_photo_removes_photo_and_edits() {
+    fn remap_onto_a_cataloged_path_names_it() {
         let catalog = Catalog::open_in_memory().unwrap();
-        let id = catalog
-            .insert_photo(&minimal_photo("/delete_me.jpg"))
-            .unwrap()
+        catalog
+            .insert_photo(&minimal_photo("/Volumes/Old/a.nef"))
             .unwrap();
-
-        let params = crema_core::image_buf::EditParams {
-            exposure: 1.0,
-            ..Default::default()
-        };
-        catalog.save_edits(id, &params).unwrap();
-
-        catalog.delete_photo(id).unwrap();
-        assert!(catalog.get_photo(id).unwrap().is_none());
-        assert!(catalog.get_edits(id).unwrap().is_none());
-        assert_eq!(catalog.photo_count().unwrap(), 0);
+        catalog
+            .insert_photo(&minimal_photo("/Volumes/New/a.nef"))
+            .unwrap();
+        let err = catalog
+            .remap_path_prefix("/Volumes/Old", "/Volumes/New")
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "/Volumes/New/a.nef is already in the catalog"
+        );
     }
 
     #[test]
-    fn rating_default_zero_in_list() {
+    fn folder_thumbnail_fidelity_overrides_follow_remaps() {
         let catalog = Catalog::open_in_memory().unwrap();
         catalog
-            .insert_photo(&minimal_photo("/default_rating.jpg"))
-            .unwrap()
+            .set_folder_thumbnail_fidelity("/Volumes/Old/Client", Some(ThumbnailFidelity::Accurate))
             .unwrap();
+        catalog
+            .set_folder_thumbnail_fidelity("/Users/me", Some(ThumbnailFidelity::Fast))
+            .unwrap();
+        catalog
+            .remap_path_prefix("/Volumes/Old", "/Volumes/New")
+            .unwrap();
+        let overrides = catalog.folder_thumbnail_fidelity().unwrap();
+        assert_eq!(
+            overrides.get("/Volumes/New/Client"),
+            Some(&ThumbnailFidelity::Accurate)
+        );
+        assert_eq!(overrides.len(), 2);
 
-        let photos = catalog.list_photos().unwrap();
-        assert_eq!(photos[0].rating, 0);
-    }
-
-    #[test]
-    fn idempotent_migration() {
-        let dir = tempfile::tempdir().unwrap();
-        let db_path = dir.path().join("test_catalog.db");
-        let path_str = db_path.to_str().unwrap();
-
-        let _catalog1 = Catalog::open(path_str).unwrap();
-        drop(_catalog1);
-        let _catalog2 = Catalog::open(path_str).unwrap();
+        catalog
+            .set_folder_thumbnail_fidelity("/Users/me", None)
+            .unwrap();
+        assert_eq!(catalog.folder_thumbnail_fidelity().unwrap().len(), 1);
     }
 
     #[test]
-    fn foreign_key_rejects_orphan_edit() {
+    fn remap_onto_an_existing_photo_changes_nothing() {
         let catalog = Catalog::open_in_memory().unwrap();
-        let params = crema_core::image_buf::EditParams::default();
-        let result = catalog.save_edits(999, &params);
-        assert!(
-            result.is_err(),
-            "FK should reject edit for nonexistent photo"
+        let insert = |path: &str| catalog.insert_photo(&minimal_photo(path)).unwrap().unwrap();
+        let a = insert("/old/a.nef");
+        insert("/old/b.nef");
+        insert("/new/b.nef");
+
+        assert!(catalog.remap_path_prefix("/old", "/new").is_err());
+        assert_eq!(
+            catalog.get_photo(a).unwrap().unwrap().file_path,
+            "/old/a.nef"
         );
     }
 
     #[test]
-    fn old_edits_get_defaults() {
+    fn provenance_links_both_ways() {
         let catalog = Catalog::open_in_memory().unwrap();
-        let id = catalog
-            .insert_photo(&minimal_photo("/old_style.jpg"))
-            .unwrap()
-            .unwrap();
+        let insert = |path: &str| catalog.insert_photo(&minimal_photo(path)).unwrap().unwrap();
+        let (a, b, stack) = (insert("/a.nef"), insert("/b.nef"), insert("/stack.tif"));
 
-        let params = crema_core::image_buf::EditParams {
-            exposure: 1.0,
-            wb_temp: 5500.0,
-            wb_tint: 0.0,
-            ..Default::default()
+        catalog
+            .link_derived(stack, &[a, b, stack], DerivationKind::Stack)
+            .unwrap();
+        let link = |photo_id| PhotoLink {
+            photo_id,
+            kind: DerivationKind::Stack,
         };
-        catalog.save_edits(id, &params).unwrap();
-
-        let edit = catalog.get_edits(id).unwrap().unwrap();
-        assert!((edit.contrast - 0.0).abs() < 1e-6);
-        assert!((edit.highlights - 0.0).abs() < 1e-6);
-        assert!((edit.shadows - 0.0).abs() < 1e-6);
-        assert!((edit.blacks - 0.0).abs() < 1e-6);
-        assert!((edit.vibrance - 0.0).abs() < 1e-6);
-        assert!((edit.saturation - 0.0).abs() < 1e-6);
+        assert_eq!(catalog.sources_of(stack).unwrap(), vec![link(a), link(b)]);
+        assert_eq!(catalog.derivatives_of(a).unwrap(), vec![link(stack)]);
+        assert!(catalog.sources_of(a).unwrap().is_empty());
+
+        catalog.delete_photo(a).unwrap();
+        assert_eq!(catalog.sources_of(stack).unwrap(), vec![link(b)]);
+        catalog.delete_photo(stack).unwrap();
+        assert!(catalog.derivatives_of(b).unwrap().is_empty());
     }
 }
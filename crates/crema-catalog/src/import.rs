@@ -49,14 +49,7 @@ pub fn import_folder(catalog: &Catalog, folder: &Path) -> Result<ImportResult> {
             continue;
         }
 
-        match import_file(catalog, &path) {
-            Ok(Some(id)) => result.imported.push(id),
-            Ok(None) => result.skipped += 1,
-            Err(err) => {
-                warn!(?path, %err, "failed to import");
-                result.errors.push(format!("{}: {err}", path.display()));
-            }
-        }
+        import_one(catalog, &path, &mut result);
     }
 
     info!(
@@ -99,14 +92,7 @@ pub fn import_paths(catalog: &Catalog, paths: &[PathBuf]) -> Result<ImportResult
             if !crema_core::raw::is_supported_extension(ext) {
                 continue;
             }
-            match import_file(catalog, path) {
-                Ok(Some(id)) => result.imported.push(id),
-                Ok(None) => result.skipped += 1,
-                Err(err) => {
-                    warn!(?path, %err, "failed to import");
-                    result.errors.push(format!("{}: {err}", path.display()));
-                }
-            }
+            import_one(catalog, path, &mut result);
         }
     }
 
@@ -120,6 +106,41 @@ pub fn import_paths(catalog: &Catalog, paths: &[PathBuf]) -> Result<ImportResult
     Ok(result)
 }
 
+/// Import one file into `result`, quarantining it on failure so it can be
+/// reviewed and retried instead of only being counted. A file that has
+/// gone missing is only counted: quarantine is for files that are there
+/// but can't be imported.
+fn import_one(catalog: &Catalog, path: &Path, result: &mut ImportResult) {
+    let key = quarantine_key(path);
+    match import_file(catalog, path) {
+        Ok(Some(id)) => {
+            result.imported.push(id);
+            if let Err(err) = catalog.release_quarantine(&key) {
+                warn!(?path, %err, "failed to release quarantined file");
+            }
+        }
+        Ok(None) => result.skipped += 1,
+        Err(err) => {
+            warn!(?path, %err, "failed to import");
+            result.errors.push(format!("{}: {err}", path.display()));
+            if !path.is_file() {
+                return;
+            }
+            if let Err(err) = catalog.quarantine_file(&key, &format!("{err:#}")) {
+                warn!(?path, %err, "failed to quarantine file");
+            }
+        }
+    }
+}
+
+/// The path a file is quarantined under: canonical like `photos.file_path`.
+fn quarantine_key(path: &Path) -> String {
+    path.canonicalize()
+        .unwrap_or_else(|_| path.to_path_buf())
+        .to_string_lossy()
+        .to_string()
+}
+
 fn sidecar_path(photo_path: &Path) -> PathBuf {
     photo_path.with_extension("crema.json")
 }
@@ -173,6 +194,16 @@ pub fn import_file(catalog: &Catalog, path: &Path) -> Result<Option<PhotoId>> {
     let photo_id = catalog.insert_photo(&insert)?;
 
     if let Some(id) = photo_id {
+        match crema_core::raw::frame_count(&canonical) {
+            Ok(frames) if frames > 1 => {
+                if let Err(err) = catalog.set_animation(id, frames) {
+                    warn!(?canonical, %err, "failed to mark photo as animated");
+                }
+            }
+            Ok(_) => {}
+            Err(err) => warn!(?canonical, %err, "failed to count frames"),
+        }
+
         let sidecar = sidecar_path(&canonical);
         if sidecar.is_file() {
             match fs::read_to_string(&sidecar) {
@@ -464,10 +495,12 @@ mod tests {
         let dir = tempfile::tempdir().unwrap();
         let jpeg_path = create_minimal_jpeg(dir.path(), "edited.jpg", b"has edits");
 
-        let mut params = crema_core::image_buf::EditParams::default();
-        params.exposure = 1.5;
-        params.wb_temp = 5500.0;
-        params.contrast = 0.3;
+        let params = crema_core::image_buf::EditParams {
+            exposure: 1.5,
+            wb_temp: 5500.0,
+            contrast: 0.3,
+            ..Default::default()
+        };
         let sidecar = dir.path().join("edited.crema.json");
         fs::write(&sidecar, serde_json::to_string_pretty(&params).unwrap()).unwrap();
 
@@ -533,4 +566,41 @@ mod tests {
         assert_eq!(result.imported.len(), 4);
         assert_eq!(catalog.photo_count().unwrap(), 4);
     }
+
+    #[test]
+    #[cfg(unix)]
+    fn failed_import_is_quarantined_and_released_on_success() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let catalog = Catalog::open_in_memory().unwrap();
+        let mut result = ImportResult {
+            imported: Vec::new(),
+            skipped: 0,
+            errors: Vec::new(),
+        };
+
+        // A missing file is an error, but not one to quarantine.
+        import_one(&catalog, &dir.path().join("gone.jpg"), &mut result);
+        assert_eq!(result.errors.len(), 1);
+        assert!(catalog.list_quarantine().unwrap().is_empty());
+
+        let locked = create_minimal_jpeg(dir.path(), "locked.jpg", b"no access");
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o000)).unwrap();
+        if fs::File::open(&locked).is_ok() {
+            // Running as root, which reads anything.
+            return;
+        }
+        import_one(&catalog, &locked, &mut result);
+        assert_eq!(result.errors.len(), 2);
+        let quarantined = catalog.list_quarantine().unwrap();
+        assert_eq!(quarantined.len(), 1);
+        assert!(quarantined[0].file_path.ends_with("locked.jpg"));
+        assert!(quarantined[0].reason.contains("failed to open"));
+
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o644)).unwrap();
+        import_one(&catalog, &locked, &mut result);
+        assert_eq!(result.imported.len(), 1);
+        assert!(catalog.list_quarantine().unwrap().is_empty());
+    }
 }
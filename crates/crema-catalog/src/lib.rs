@@ -1,3 +1,4 @@
+pub mod auto_stack;
 pub mod db;
 pub mod import;
 pub mod models;
@@ -1,8 +1,12 @@
 use serde::{Deserialize, Serialize};
 
 pub type PhotoId = i64;
+pub type SnapshotId = i64;
+pub type QuarantineId = i64;
+pub type MasterDarkId = i64;
+pub type StackId = i64;
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct Photo {
     pub id: PhotoId,
     pub file_path: String,
@@ -21,6 +25,291 @@ pub struct Photo {
     pub imported_at: String,
     pub thumbnail_path: Option<String>,
     pub rating: i32,
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub caption: Option<String>,
+    #[serde(default)]
+    pub copyright: Option<String>,
+    #[serde(default)]
+    pub keywords: Option<String>,
+}
+
+/// A named copy of a photo's edit parameters.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub id: SnapshotId,
+    pub photo_id: PhotoId,
+    pub name: String,
+    pub params: crema_core::image_buf::EditParams,
+    pub created_at: String,
+}
+
+/// The dead pixel map detected for one camera body.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CameraDefectMap {
+    pub camera_serial: String,
+    pub camera_model: Option<String>,
+    pub map: crema_core::defects::DefectMap,
+    pub created_at: String,
+}
+
+/// The flat-field calibration for one camera and lens combination.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LensFlatField {
+    pub camera_model: String,
+    /// Empty when the lens isn't reported in EXIF, e.g. manual lenses.
+    pub lens: String,
+    pub field: crema_core::flat_field::FlatField,
+    pub created_at: String,
+}
+
+/// The capture settings a master dark was shot at. Only lights shot the
+/// same way share its thermal signal.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DarkFrameSettings {
+    pub camera_model: String,
+    pub iso: u32,
+    pub exposure_secs: f64,
+    /// Degrees Celsius, when the camera records it.
+    pub temperature: Option<f64>,
+}
+
+/// A stored master dark, without its pixels; see `Catalog::load_master_dark`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MasterDarkInfo {
+    pub id: MasterDarkId,
+    pub settings: DarkFrameSettings,
+    pub frame_count: u32,
+    pub width: u32,
+    pub height: u32,
+    pub created_at: String,
+}
+
+/// A reduced, compressed copy of a photo's calibrated decode, so it can
+/// still be edited while the drive holding the original is disconnected.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SmartPreview {
+    pub photo_id: PhotoId,
+    pub path: String,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// An animated GIF or PNG in the catalog. The file is left as it is; the
+/// key frame is the one shown, edited and exported as the photo.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Animation {
+    pub photo_id: PhotoId,
+    pub frame_count: u32,
+    pub key_frame: u32,
+}
+
+/// A photo's rating and edits as they stood at some past moment,
+/// reconstructed from the edit and rating history.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PastState {
+    pub photo_id: PhotoId,
+    pub rating: i32,
+    pub params: crema_core::image_buf::EditParams,
+}
+
+/// Camera and lens details entered by hand where EXIF is missing or
+/// wrong, such as an adapted manual lens that reports nothing. Kept apart
+/// from the EXIF columns so clearing it restores what the file said.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct MetadataOverride {
+    pub camera_make: Option<String>,
+    pub camera_model: Option<String>,
+    pub lens: Option<String>,
+    pub focal_length: Option<f64>,
+}
+
+impl MetadataOverride {
+    pub fn is_empty(&self) -> bool {
+        self.camera_make.is_none()
+            && self.camera_model.is_none()
+            && self.lens.is_none()
+            && self.focal_length.is_none()
+    }
+
+    /// Lay the overridden fields over a photo's EXIF values.
+    pub fn apply(&self, photo: &mut Photo) {
+        override_field(&mut photo.camera_make, &self.camera_make);
+        override_field(&mut photo.camera_model, &self.camera_model);
+        override_field(&mut photo.lens, &self.lens);
+        override_field(&mut photo.focal_length, &self.focal_length);
+    }
+
+    /// Lay the overridden fields over EXIF read from the file, so lens
+    /// corrections match what the photo was really shot with.
+    pub fn apply_to_exif(&self, exif: &mut crema_metadata::exif::ExifData) {
+        override_field(&mut exif.camera_make, &self.camera_make);
+        override_field(&mut exif.camera_model, &self.camera_model);
+        override_field(&mut exif.lens, &self.lens);
+        override_field(&mut exif.focal_length, &self.focal_length);
+    }
+}
+
+fn override_field<T: Clone>(field: &mut Option<T>, value: &Option<T>) {
+    if value.is_some() {
+        field.clone_from(value);
+    }
+}
+
+/// How a derived photo was made from its sources.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DerivationKind {
+    Stack,
+    Hdr,
+    Panorama,
+    /// Edited in another application and brought back.
+    ExternalEdit,
+}
+
+impl DerivationKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            DerivationKind::Stack => "stack",
+            DerivationKind::Hdr => "hdr",
+            DerivationKind::Panorama => "panorama",
+            DerivationKind::ExternalEdit => "external_edit",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "stack" => Some(DerivationKind::Stack),
+            "hdr" => Some(DerivationKind::Hdr),
+            "panorama" => Some(DerivationKind::Panorama),
+            "external_edit" => Some(DerivationKind::ExternalEdit),
+            _ => None,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            DerivationKind::Stack => "Stack",
+            DerivationKind::Hdr => "HDR",
+            DerivationKind::Panorama => "Panorama",
+            DerivationKind::ExternalEdit => "External Edit",
+        }
+    }
+}
+
+/// Why photos were stacked together.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum StackKind {
+    /// Files sharing a name, such as a RAW and the JPEG written with it.
+    RawJpeg,
+    /// Frames of one exposure bracket.
+    Bracket,
+    /// Frames shot in quick succession.
+    Burst,
+}
+
+impl StackKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            StackKind::RawJpeg => "raw_jpeg",
+            StackKind::Bracket => "bracket",
+            StackKind::Burst => "burst",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "raw_jpeg" => Some(StackKind::RawJpeg),
+            "bracket" => Some(StackKind::Bracket),
+            "burst" => Some(StackKind::Burst),
+            _ => None,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            StackKind::RawJpeg => "RAW + JPEG",
+            StackKind::Bracket => "Bracket",
+            StackKind::Burst => "Burst",
+        }
+    }
+}
+
+/// What library thumbnails show.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ThumbnailFidelity {
+    /// The original file, as imported. Quick to make.
+    #[default]
+    Fast,
+    /// The photo with its edits applied, rendered again after each edit.
+    Accurate,
+}
+
+impl ThumbnailFidelity {
+    pub const ALL: [Self; 2] = [Self::Fast, Self::Accurate];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ThumbnailFidelity::Fast => "fast",
+            ThumbnailFidelity::Accurate => "accurate",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "fast" => Some(ThumbnailFidelity::Fast),
+            "accurate" => Some(ThumbnailFidelity::Accurate),
+            _ => None,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ThumbnailFidelity::Fast => "Original (fast)",
+            ThumbnailFidelity::Accurate => "With edits",
+        }
+    }
+}
+
+/// Photos shown as one grid cell until expanded. The first member is the
+/// cover that stands for the rest.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PhotoStack {
+    pub id: StackId,
+    pub kind: StackKind,
+    pub members: Vec<PhotoId>,
+}
+
+impl PhotoStack {
+    pub fn cover(&self) -> PhotoId {
+        self.members[0]
+    }
+}
+
+/// One end of a provenance link: the photo on the other side and how the
+/// derivative was made.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PhotoLink {
+    pub photo_id: PhotoId,
+    pub kind: DerivationKind,
+}
+
+/// A photo whose path a prefix remap rewrites.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PathRemap {
+    pub photo_id: PhotoId,
+    pub old_path: String,
+    pub new_path: String,
+}
+
+/// A file that failed to import or decode, kept for review and retry.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct QuarantinedFile {
+    pub id: QuarantineId,
+    pub file_path: String,
+    pub reason: String,
+    pub failed_at: String,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
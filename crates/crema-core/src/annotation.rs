@@ -0,0 +1,284 @@
+//! Review annotations: arrows, boxes and text notes drawn over a photo for
+//! retouching handoff. They're vector data kept beside the edit, never part
+//! of the image, and only burned in when a proof export asks for them.
+//!
+//! Points are fractions of the uncropped, unrotated source frame, so a note
+//! stays on the same spot of the scene when the crop or straighten angle
+//! changes afterwards. [`FrameMap`] carries them into the developed frame.
+
+use serde::{Deserialize, Serialize};
+
+use crate::bitmap_font;
+use crate::image_buf::{EditParams, ImageBuf};
+
+/// Linear RGB of every mark: a saturated red that reads on most photos.
+pub const COLOR: [f32; 3] = [1.0, 0.08, 0.05];
+/// Marks are drawn this many pixels thick per 1000 pixels of the shorter
+/// output edge, and never thinner than two.
+const STROKE_PER_1000: f32 = 3.0;
+/// Arrowhead sides, as a fraction of the shorter output edge.
+const HEAD_FRACTION: f32 = 0.03;
+const HEAD_ANGLE_DEG: f32 = 28.0;
+/// Note text is one glyph pixel per this many pixels of the shorter edge.
+const NOTE_SCALE_EDGE: u32 = 400;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Annotation {
+    Arrow {
+        from: [f32; 2],
+        to: [f32; 2],
+    },
+    Rect {
+        from: [f32; 2],
+        to: [f32; 2],
+    },
+    /// A marker with text beside it.
+    Note {
+        at: [f32; 2],
+        text: String,
+    },
+}
+
+/// Maps between the source frame annotations are stored in and the
+/// developed frame, which is cropped and straightened the way the crop
+/// module does it. Developed points are fractions of the output size.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FrameMap {
+    source_w: f32,
+    source_h: f32,
+    crop: [f32; 4],
+    cos: f32,
+    sin: f32,
+}
+
+impl FrameMap {
+    /// `source_w` and `source_h` are the size of the image the pipeline
+    /// was given; only their ratio matters.
+    pub fn new(params: &EditParams, source_w: u32, source_h: u32) -> Self {
+        let angle = params.rotation.to_radians();
+        Self {
+            source_w: source_w.max(1) as f32,
+            source_h: source_h.max(1) as f32,
+            crop: [
+                params.crop_x,
+                params.crop_y,
+                params.crop_w.max(f32::EPSILON),
+                params.crop_h.max(f32::EPSILON),
+            ],
+            cos: angle.cos(),
+            sin: angle.sin(),
+        }
+    }
+
+    pub fn to_output(&self, point: [f32; 2]) -> [f32; 2] {
+        let (cx, cy) = (self.source_w * 0.5, self.source_h * 0.5);
+        let (x, y) = (point[0] * self.source_w - cx, point[1] * self.source_h - cy);
+        let rx = cx + self.cos * x - self.sin * y;
+        let ry = cy + self.sin * x + self.cos * y;
+        let [crop_x, crop_y, crop_w, crop_h] = self.crop;
+        [
+            (rx / self.source_w - crop_x) / crop_w,
+            (ry / self.source_h - crop_y) / crop_h,
+        ]
+    }
+
+    pub fn to_source(&self, point: [f32; 2]) -> [f32; 2] {
+        let [crop_x, crop_y, crop_w, crop_h] = self.crop;
+        let (cx, cy) = (self.source_w * 0.5, self.source_h * 0.5);
+        let x = (crop_x + point[0] * crop_w) * self.source_w - cx;
+        let y = (crop_y + point[1] * crop_h) * self.source_h - cy;
+        let sx = cx + self.cos * x + self.sin * y;
+        let sy = cy - self.sin * x + self.cos * y;
+        [sx / self.source_w, sy / self.source_h]
+    }
+}
+
+/// The line segments that draw an arrow from `from` to `to`, in any
+/// coordinate space: the shaft and both sides of the head, `head` long.
+pub fn arrow_segments(from: [f32; 2], to: [f32; 2], head: f32) -> [[[f32; 2]; 2]; 3] {
+    let (dx, dy) = (from[0] - to[0], from[1] - to[1]);
+    let length = (dx * dx + dy * dy).sqrt().max(f32::EPSILON);
+    let (ux, uy) = (dx / length, dy / length);
+    let side = |angle: f32| {
+        let (sin, cos) = angle.to_radians().sin_cos();
+        [
+            to[0] + head * (cos * ux - sin * uy),
+            to[1] + head * (sin * ux + cos * uy),
+        ]
+    };
+    [
+        [from, to],
+        [to, side(HEAD_ANGLE_DEG)],
+        [to, side(-HEAD_ANGLE_DEG)],
+    ]
+}
+
+/// Burn `annotations` into a developed image. `map` places them, built
+/// from the parameters the image was rendered with.
+pub fn render(buf: &mut ImageBuf, annotations: &[Annotation], map: &FrameMap) {
+    let (w, h) = (buf.width as f32, buf.height as f32);
+    let short = w.min(h);
+    let stroke = (short * STROKE_PER_1000 / 1000.0).max(2.0);
+    let to_px = |point: [f32; 2]| {
+        let [x, y] = map.to_output(point);
+        [x * w, y * h]
+    };
+    for annotation in annotations {
+        match annotation {
+            Annotation::Arrow { from, to } => {
+                for [a, b] in arrow_segments(to_px(*from), to_px(*to), short * HEAD_FRACTION) {
+                    draw_line(buf, a, b, stroke);
+                }
+            }
+            Annotation::Rect { from, to } => {
+                // Corners go through the map separately, so a box on a
+                // straightened photo stays on the same part of the scene.
+                let corners = [
+                    to_px(*from),
+                    to_px([to[0], from[1]]),
+                    to_px(*to),
+                    to_px([from[0], to[1]]),
+                ];
+                for i in 0..4 {
+                    draw_line(buf, corners[i], corners[(i + 1) % 4], stroke);
+                }
+            }
+            Annotation::Note { at, text } => {
+                let [x, y] = to_px(*at);
+                draw_dot(buf, [x, y], stroke * 2.0);
+                let scale = (short as u32 / NOTE_SCALE_EDGE).max(1);
+                let gap = stroke * 3.0;
+                if x + gap >= 0.0 && y >= 0.0 {
+                    bitmap_font::draw_label(buf, (x + gap) as u32, y as u32, scale, text.trim());
+                }
+            }
+        }
+    }
+}
+
+fn draw_line(buf: &mut ImageBuf, a: [f32; 2], b: [f32; 2], width: f32) {
+    let radius = width * 0.5;
+    let (dx, dy) = (b[0] - a[0], b[1] - a[1]);
+    let length_sq = (dx * dx + dy * dy).max(f32::EPSILON);
+    fill_where(buf, a, b, radius, |px, py| {
+        let t = (((px - a[0]) * dx + (py - a[1]) * dy) / length_sq).clamp(0.0, 1.0);
+        let (nx, ny) = (a[0] + t * dx - px, a[1] + t * dy - py);
+        nx * nx + ny * ny <= radius * radius
+    });
+}
+
+fn draw_dot(buf: &mut ImageBuf, center: [f32; 2], radius: f32) {
+    fill_where(buf, center, center, radius, |px, py| {
+        let (dx, dy) = (px - center[0], py - center[1]);
+        dx * dx + dy * dy <= radius * radius
+    });
+}
+
+/// Paint pixels in the box around `a` and `b`, grown by `margin`, whose
+/// centers pass `inside`.
+fn fill_where(
+    buf: &mut ImageBuf,
+    a: [f32; 2],
+    b: [f32; 2],
+    margin: f32,
+    inside: impl Fn(f32, f32) -> bool,
+) {
+    let clamp_x = |v: f32| v.clamp(0.0, buf.width as f32) as u32;
+    let clamp_y = |v: f32| v.clamp(0.0, buf.height as f32) as u32;
+    let (x0, x1) = (
+        clamp_x(a[0].min(b[0]) - margin),
+        clamp_x(a[0].max(b[0]) + margin + 1.0),
+    );
+    let (y0, y1) = (
+        clamp_y(a[1].min(b[1]) - margin),
+        clamp_y(a[1].max(b[1]) + margin + 1.0),
+    );
+    for y in y0..y1 {
+        for x in x0..x1 {
+            if inside(x as f32 + 0.5, y as f32 + 0.5) {
+                let i = ((y * buf.width + x) * 3) as usize;
+                buf.data[i..i + 3].copy_from_slice(&COLOR);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: [f32; 2], b: [f32; 2]) -> bool {
+        (a[0] - b[0]).abs() < 1e-4 && (a[1] - b[1]).abs() < 1e-4
+    }
+
+    #[test]
+    fn frame_map_follows_crop_and_straighten() {
+        let cropped = EditParams {
+            crop_x: 0.25,
+            crop_y: 0.5,
+            crop_w: 0.5,
+            crop_h: 0.5,
+            ..EditParams::default()
+        };
+        let map = FrameMap::new(&cropped, 400, 200);
+        assert!(close(map.to_output([0.5, 0.75]), [0.5, 0.5]));
+        assert!(close(map.to_output([0.25, 0.5]), [0.0, 0.0]));
+
+        let straightened = EditParams {
+            rotation: 90.0,
+            ..EditParams::default()
+        };
+        let map = FrameMap::new(&straightened, 200, 200);
+        // A quarter turn about the center carries the top middle to the
+        // right middle.
+        assert!(close(map.to_output([0.5, 0.0]), [1.0, 0.5]));
+        for point in [[0.1, 0.2], [0.7, 0.9]] {
+            assert!(close(map.to_source(map.to_output(point)), point));
+        }
+    }
+
+    #[test]
+    fn render_burns_marks_in_place() {
+        let mut buf = ImageBuf::from_data(100, 100, vec![0.5; 100 * 100 * 3]).unwrap();
+        let annotations = [
+            Annotation::Rect {
+                from: [0.1, 0.1],
+                to: [0.4, 0.4],
+            },
+            Annotation::Arrow {
+                from: [0.6, 0.9],
+                to: [0.9, 0.9],
+            },
+            Annotation::Note {
+                at: [0.5, 0.5],
+                text: "Fix".into(),
+            },
+        ];
+        render(
+            &mut buf,
+            &annotations,
+            &FrameMap::new(&EditParams::default(), 100, 100),
+        );
+        let pixel = |x: u32, y: u32| {
+            let i = ((y * 100 + x) * 3) as usize;
+            [buf.data[i], buf.data[i + 1], buf.data[i + 2]]
+        };
+        assert_eq!(pixel(10, 25), COLOR);
+        assert_eq!(pixel(25, 25), [0.5; 3]);
+        assert_eq!(pixel(75, 90), COLOR);
+        assert_eq!(pixel(75, 80), [0.5; 3]);
+        assert_eq!(pixel(50, 50), COLOR);
+    }
+
+    #[test]
+    fn annotations_serialize_tagged() {
+        let note = Annotation::Note {
+            at: [0.5, 0.25],
+            text: "Remove sign".into(),
+        };
+        let json = serde_json::to_string(&note).unwrap();
+        assert!(json.contains("\"kind\":\"note\""));
+        assert_eq!(serde_json::from_str::<Annotation>(&json).unwrap(), note);
+    }
+}
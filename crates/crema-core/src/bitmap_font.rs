@@ -0,0 +1,143 @@
+//! A 5x7 bitmap font for text burned into exported images, such as
+//! comparison labels and annotation notes. Letters are drawn in capitals;
+//! characters without a glyph leave a gap.
+
+use crate::image_buf::ImageBuf;
+
+pub const GLYPH_W: u32 = 5;
+pub const GLYPH_H: u32 = 7;
+/// Labels sit on a plate that darkens what's behind them by this much.
+const PLATE_DARKEN: f32 = 0.45;
+
+/// Width and height of the plate `draw_label` draws for `text`.
+pub fn label_size(text: &str, scale: u32) -> (u32, u32) {
+    let pad = 3 * scale;
+    let count = text.chars().count().max(1) as u32;
+    let text_w = (count * (GLYPH_W + 1) - 1) * scale;
+    (text_w + 2 * pad, GLYPH_H * scale + 2 * pad)
+}
+
+/// Draw `text` in white on a darkened plate whose top-left corner is at
+/// (`x`, `y`), clipped to the canvas.
+pub fn draw_label(canvas: &mut ImageBuf, x: u32, y: u32, scale: u32, text: &str) {
+    let pad = 3 * scale;
+    let (plate_w, plate_h) = label_size(text, scale);
+
+    let mut shade = |px: u32, py: u32, value: Option<f32>| {
+        if px >= canvas.width || py >= canvas.height {
+            return;
+        }
+        let i = ((py * canvas.width + px) * 3) as usize;
+        for c in &mut canvas.data[i..i + 3] {
+            *c = value.unwrap_or(*c * (1.0 - PLATE_DARKEN));
+        }
+    };
+
+    for py in y..y.saturating_add(plate_h) {
+        for px in x..x.saturating_add(plate_w) {
+            shade(px, py, None);
+        }
+    }
+    for (n, ch) in text.chars().enumerate() {
+        let Some(rows) = glyph(ch) else {
+            continue;
+        };
+        let gx = x + pad + n as u32 * (GLYPH_W + 1) * scale;
+        for (row, bits) in rows.iter().enumerate() {
+            for col in 0..GLYPH_W {
+                if bits & (1 << (GLYPH_W - 1 - col)) == 0 {
+                    continue;
+                }
+                for sy in 0..scale {
+                    for sx in 0..scale {
+                        shade(
+                            gx + col * scale + sx,
+                            y + pad + row as u32 * scale + sy,
+                            Some(1.0),
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// 5x7 bitmaps for letters, digits and common punctuation, one row per
+/// byte with the leftmost pixel in the high bit.
+pub fn glyph(ch: char) -> Option<[u8; GLYPH_H as usize]> {
+    Some(match ch.to_ascii_uppercase() {
+        ' ' => [0x00; 7],
+        'A' => [0x0E, 0x11, 0x11, 0x1F, 0x11, 0x11, 0x11],
+        'B' => [0x1E, 0x11, 0x11, 0x1E, 0x11, 0x11, 0x1E],
+        'C' => [0x0E, 0x11, 0x10, 0x10, 0x10, 0x11, 0x0E],
+        'D' => [0x1C, 0x12, 0x11, 0x11, 0x11, 0x12, 0x1C],
+        'E' => [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x1F],
+        'F' => [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x10],
+        'G' => [0x0E, 0x11, 0x10, 0x17, 0x11, 0x11, 0x0F],
+        'H' => [0x11, 0x11, 0x11, 0x1F, 0x11, 0x11, 0x11],
+        'I' => [0x0E, 0x04, 0x04, 0x04, 0x04, 0x04, 0x0E],
+        'J' => [0x07, 0x02, 0x02, 0x02, 0x02, 0x12, 0x0C],
+        'K' => [0x11, 0x12, 0x14, 0x18, 0x14, 0x12, 0x11],
+        'L' => [0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x1F],
+        'M' => [0x11, 0x1B, 0x15, 0x15, 0x11, 0x11, 0x11],
+        'N' => [0x11, 0x11, 0x19, 0x15, 0x13, 0x11, 0x11],
+        'O' => [0x0E, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E],
+        'P' => [0x1E, 0x11, 0x11, 0x1E, 0x10, 0x10, 0x10],
+        'Q' => [0x0E, 0x11, 0x11, 0x11, 0x15, 0x12, 0x0D],
+        'R' => [0x1E, 0x11, 0x11, 0x1E, 0x14, 0x12, 0x11],
+        'S' => [0x0F, 0x10, 0x10, 0x0E, 0x01, 0x01, 0x1E],
+        'T' => [0x1F, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04],
+        'U' => [0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E],
+        'V' => [0x11, 0x11, 0x11, 0x11, 0x11, 0x0A, 0x04],
+        'W' => [0x11, 0x11, 0x11, 0x15, 0x15, 0x15, 0x0A],
+        'X' => [0x11, 0x11, 0x0A, 0x04, 0x0A, 0x11, 0x11],
+        'Y' => [0x11, 0x11, 0x11, 0x0A, 0x04, 0x04, 0x04],
+        'Z' => [0x1F, 0x01, 0x02, 0x04, 0x08, 0x10, 0x1F],
+        '0' => [0x0E, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0E],
+        '1' => [0x04, 0x0C, 0x04, 0x04, 0x04, 0x04, 0x0E],
+        '2' => [0x0E, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1F],
+        '3' => [0x1F, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0E],
+        '4' => [0x02, 0x06, 0x0A, 0x12, 0x1F, 0x02, 0x02],
+        '5' => [0x1F, 0x10, 0x1E, 0x01, 0x01, 0x11, 0x0E],
+        '6' => [0x06, 0x08, 0x10, 0x1E, 0x11, 0x11, 0x0E],
+        '7' => [0x1F, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08],
+        '8' => [0x0E, 0x11, 0x11, 0x0E, 0x11, 0x11, 0x0E],
+        '9' => [0x0E, 0x11, 0x11, 0x0F, 0x01, 0x02, 0x0C],
+        '.' => [0x00, 0x00, 0x00, 0x00, 0x00, 0x0C, 0x0C],
+        ',' => [0x00, 0x00, 0x00, 0x00, 0x0C, 0x04, 0x08],
+        '!' => [0x04, 0x04, 0x04, 0x04, 0x04, 0x00, 0x04],
+        '?' => [0x0E, 0x11, 0x01, 0x02, 0x04, 0x00, 0x04],
+        ':' => [0x00, 0x0C, 0x0C, 0x00, 0x0C, 0x0C, 0x00],
+        ';' => [0x00, 0x0C, 0x0C, 0x00, 0x0C, 0x04, 0x08],
+        '\'' => [0x0C, 0x04, 0x08, 0x00, 0x00, 0x00, 0x00],
+        '"' => [0x0A, 0x0A, 0x0A, 0x00, 0x00, 0x00, 0x00],
+        '-' => [0x00, 0x00, 0x00, 0x1F, 0x00, 0x00, 0x00],
+        '+' => [0x00, 0x04, 0x04, 0x1F, 0x04, 0x04, 0x00],
+        '=' => [0x00, 0x00, 0x1F, 0x00, 0x1F, 0x00, 0x00],
+        '(' => [0x02, 0x04, 0x08, 0x08, 0x08, 0x04, 0x02],
+        ')' => [0x08, 0x04, 0x02, 0x02, 0x02, 0x04, 0x08],
+        '/' => [0x00, 0x01, 0x02, 0x04, 0x08, 0x10, 0x00],
+        '%' => [0x18, 0x19, 0x02, 0x04, 0x08, 0x13, 0x03],
+        '#' => [0x0A, 0x0A, 0x1F, 0x0A, 0x1F, 0x0A, 0x0A],
+        '&' => [0x0C, 0x12, 0x14, 0x08, 0x15, 0x12, 0x0D],
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn label_is_white_text_on_a_darkened_plate_clipped_to_the_canvas() {
+        let mut canvas = ImageBuf::from_data(40, 12, vec![0.5; 40 * 12 * 3]).unwrap();
+        draw_label(&mut canvas, 0, 0, 1, "hi!");
+        let pixel = |x: u32, y: u32| canvas.data[((y * 40 + x) * 3) as usize];
+        // 'H' has its left column set; the padding around it is only shaded.
+        assert_eq!(pixel(3, 3), 1.0);
+        assert!((pixel(1, 1) - 0.5 * (1.0 - PLATE_DARKEN)).abs() < 1e-6);
+        assert_eq!(label_size("hi!", 1), (23, 13));
+        assert_eq!(pixel(30, 5), 0.5);
+        assert!(glyph('a').is_some() && glyph('~').is_none());
+    }
+}
@@ -1,18 +1,20 @@
+use crate::float::Float;
+
 /// Inverse sRGB EOTF (IEC 61966-2-1): linear light [0,1] -> perceptual sRGB [0,1].
-pub fn linear_to_srgb(x: f32) -> f32 {
-    if x <= 0.0031308 {
-        12.92 * x
+pub fn linear_to_srgb<T: Float>(x: T) -> T {
+    if x <= T::of(0.0031308) {
+        T::of(12.92) * x
     } else {
-        1.055 * x.powf(1.0 / 2.4) - 0.055
+        T::of(1.055) * x.powf(T::of(1.0) / T::of(2.4)) - T::of(0.055)
     }
 }
 
 /// sRGB EOTF (IEC 61966-2-1): perceptual sRGB [0,1] -> linear light [0,1].
-pub fn srgb_to_linear(x: f32) -> f32 {
-    if x <= 0.04045 {
-        x / 12.92
+pub fn srgb_to_linear<T: Float>(x: T) -> T {
+    if x <= T::of(0.04045) {
+        x / T::of(12.92)
     } else {
-        ((x + 0.055) / 1.055).powf(2.4)
+        ((x + T::of(0.055)) / T::of(1.055)).powf(T::of(2.4))
     }
 }
 
@@ -20,22 +22,39 @@ pub fn srgb_to_linear(x: f32) -> f32 {
 ///
 /// Returns (L, a, b) where L is in [0,1] for in-gamut colors,
 /// a and b are roughly +/-0.3. Chroma = sqrt(a^2 + b^2).
-pub fn linear_srgb_to_oklab(r: f32, g: f32, b: f32) -> (f32, f32, f32) {
-    let l = 0.412_221_46 * r + 0.536_332_55 * g + 0.051_445_995 * b;
-    let m = 0.211_903_5 * r + 0.680_699_5 * g + 0.107_396_96 * b;
-    let s = 0.088_302_46 * r + 0.281_718_85 * g + 0.629_978_7 * b;
+pub fn linear_srgb_to_oklab<T: Float>(r: T, g: T, b: T) -> (T, T, T) {
+    let l = T::of(0.412_221_46) * r + T::of(0.536_332_55) * g + T::of(0.051_445_995) * b;
+    let m = T::of(0.211_903_5) * r + T::of(0.680_699_5) * g + T::of(0.107_396_96) * b;
+    let s = T::of(0.088_302_46) * r + T::of(0.281_718_85) * g + T::of(0.629_978_7) * b;
 
-    let l_ = l.max(0.0).cbrt();
-    let m_ = m.max(0.0).cbrt();
-    let s_ = s.max(0.0).cbrt();
+    let zero = T::of(0.0);
+    let l_ = l.max(zero).cbrt();
+    let m_ = m.max(zero).cbrt();
+    let s_ = s.max(zero).cbrt();
 
-    let big_l = 0.210_454_26 * l_ + 0.793_617_8 * m_ - 0.004_072_047 * s_;
-    let ok_a = 1.977_998_5 * l_ - 2.428_592_2 * m_ + 0.450_593_7 * s_;
-    let ok_b = 0.025_904_037 * l_ + 0.782_771_77 * m_ - 0.808_675_77 * s_;
+    let big_l = T::of(0.210_454_26) * l_ + T::of(0.793_617_8) * m_ - T::of(0.004_072_047) * s_;
+    let ok_a = T::of(1.977_998_5) * l_ - T::of(2.428_592_2) * m_ + T::of(0.450_593_7) * s_;
+    let ok_b = T::of(0.025_904_037) * l_ + T::of(0.782_771_77) * m_ - T::of(0.808_675_77) * s_;
 
     (big_l, ok_a, ok_b)
 }
 
+/// Re-express linear sRGB in Display P3 primaries. Both share the D65
+/// white point and the sRGB transfer curve, so the result encodes with
+/// [`linear_to_srgb`] like any other export.
+pub fn linear_srgb_to_display_p3(r: f32, g: f32, b: f32) -> (f32, f32, f32) {
+    (
+        0.822_462_1 * r + 0.177_538 * g,
+        0.033_194_1 * r + 0.966_805_8 * g,
+        0.017_082_7 * r + 0.072_397_4 * g + 0.910_519_9 * b,
+    )
+}
+
+/// An ICC profile describing Display P3, for embedding in exports.
+pub fn display_p3_icc() -> Option<Vec<u8>> {
+    moxcms::ColorProfile::new_display_p3().encode().ok()
+}
+
 /// Approximate maximum OKLab chroma for in-gamut sRGB colors.
 /// Actual max is ~0.323 (pure magenta). Rounded up for a clean margin.
 pub const OKLAB_MAX_CHROMA: f32 = 0.33;
@@ -55,10 +74,10 @@ mod tests {
 
     #[test]
     fn srgb_endpoints() {
-        assert!((linear_to_srgb(0.0)).abs() < 1e-7);
-        assert!((linear_to_srgb(1.0) - 1.0).abs() < 1e-7);
-        assert!((srgb_to_linear(0.0)).abs() < 1e-7);
-        assert!((srgb_to_linear(1.0) - 1.0).abs() < 1e-7);
+        assert!((linear_to_srgb(0.0_f32)).abs() < 1e-7);
+        assert!((linear_to_srgb(1.0_f32) - 1.0).abs() < 1e-7);
+        assert!((srgb_to_linear(0.0_f32)).abs() < 1e-7);
+        assert!((srgb_to_linear(1.0_f32) - 1.0).abs() < 1e-7);
     }
 
     #[test]
@@ -84,6 +103,16 @@ mod tests {
         }
     }
 
+    #[test]
+    fn display_p3_keeps_white_and_narrows_srgb_primaries() {
+        let (r, g, b) = linear_srgb_to_display_p3(1.0, 1.0, 1.0);
+        assert!((r - 1.0).abs() < 1e-5 && (g - 1.0).abs() < 1e-5 && (b - 1.0).abs() < 1e-5);
+        // sRGB red sits inside the P3 gamut, so it needs some green.
+        let (r, g, b) = linear_srgb_to_display_p3(1.0, 0.0, 0.0);
+        assert!(r < 0.83 && g > 0.03 && b > 0.01);
+        assert!(display_p3_icc().is_some_and(|icc| icc.len() > 128));
+    }
+
     #[test]
     fn oklab_black() {
         let (l, a, b) = linear_srgb_to_oklab(0.0, 0.0, 0.0);
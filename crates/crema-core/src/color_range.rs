@@ -0,0 +1,185 @@
+//! Color range measurement: how much of a rendered frame falls within a
+//! hue, saturation and lightness range, for checking that a product color
+//! holds across a set and, later, for building range masks.
+//!
+//! Ranges are judged in HSL on display-encoded sRGB, the space color
+//! pickers use, so a range reads the way it looks on screen.
+
+use crate::color::linear_to_srgb;
+use crate::image_buf::ImageBuf;
+
+/// Pixels less saturated than this have no meaningful hue; they pass the
+/// hue test and are left to the saturation bounds.
+const ACHROMATIC: f32 = 0.01;
+/// How bright out-of-range pixels are drawn in a mask preview, relative to
+/// their own lightness.
+const MASK_DIM: f32 = 0.3;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ColorRange {
+    /// Center hue in degrees.
+    pub hue: f32,
+    /// Degrees either side of `hue` that still match. 180 matches every hue.
+    pub hue_width: f32,
+    /// Saturation bounds, 0..=1.
+    pub sat_min: f32,
+    pub sat_max: f32,
+    /// Lightness bounds, 0..=1.
+    pub lum_min: f32,
+    pub lum_max: f32,
+}
+
+impl Default for ColorRange {
+    /// Saturated reds of any everyday brightness.
+    fn default() -> Self {
+        Self {
+            hue: 0.0,
+            hue_width: 20.0,
+            sat_min: 0.25,
+            sat_max: 1.0,
+            lum_min: 0.1,
+            lum_max: 0.9,
+        }
+    }
+}
+
+impl ColorRange {
+    /// Whether a display-encoded sRGB color, 0..=1 per channel, is in range.
+    pub fn contains(&self, r: f32, g: f32, b: f32) -> bool {
+        let (h, s, l) = hsl(r, g, b);
+        if s < self.sat_min || s > self.sat_max || l < self.lum_min || l > self.lum_max {
+            return false;
+        }
+        if s < ACHROMATIC || self.hue_width >= 180.0 {
+            return true;
+        }
+        let distance = (h - self.hue).rem_euclid(360.0);
+        distance.min(360.0 - distance) <= self.hue_width
+    }
+
+    /// The fraction of `buf`, a linear render, that is in range.
+    pub fn coverage(&self, buf: &ImageBuf) -> f32 {
+        let pixels = buf.data.len() / 3;
+        if pixels == 0 {
+            return 0.0;
+        }
+        let matched = buf
+            .data
+            .chunks_exact(3)
+            .filter(|p| {
+                self.contains(
+                    linear_to_srgb(p[0].clamp(0.0, 1.0)),
+                    linear_to_srgb(p[1].clamp(0.0, 1.0)),
+                    linear_to_srgb(p[2].clamp(0.0, 1.0)),
+                )
+            })
+            .count();
+        matched as f32 / pixels as f32
+    }
+
+    /// [`ColorRange::coverage`] of 8-bit RGBA display pixels, such as the
+    /// develop preview.
+    pub fn coverage_rgba8(&self, rgba: &[u8]) -> f32 {
+        let pixels = rgba.len() / 4;
+        if pixels == 0 {
+            return 0.0;
+        }
+        let matched = rgba.chunks_exact(4).filter(|p| self.contains_u8(p)).count();
+        matched as f32 / pixels as f32
+    }
+
+    /// A preview of 8-bit RGBA display pixels with everything out of range
+    /// dimmed to gray, so what's in range stands out in its own colors.
+    pub fn mask_rgba8(&self, rgba: &[u8]) -> Vec<u8> {
+        let mut out = rgba.to_vec();
+        for p in out.chunks_exact_mut(4) {
+            if self.contains_u8(p) {
+                continue;
+            }
+            let (_, _, l) = hsl(
+                p[0] as f32 / 255.0,
+                p[1] as f32 / 255.0,
+                p[2] as f32 / 255.0,
+            );
+            let gray = (l * MASK_DIM * 255.0).round() as u8;
+            p[..3].fill(gray);
+        }
+        out
+    }
+
+    fn contains_u8(&self, p: &[u8]) -> bool {
+        self.contains(
+            p[0] as f32 / 255.0,
+            p[1] as f32 / 255.0,
+            p[2] as f32 / 255.0,
+        )
+    }
+}
+
+/// Hue in degrees, saturation and lightness of an sRGB color.
+fn hsl(r: f32, g: f32, b: f32) -> (f32, f32, f32) {
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let l = (max + min) / 2.0;
+    let d = max - min;
+    if d <= f32::EPSILON {
+        return (0.0, 0.0, l);
+    }
+    let s = d / (1.0 - (2.0 * l - 1.0).abs());
+    let h = if max == r {
+        ((g - b) / d).rem_euclid(6.0)
+    } else if max == g {
+        (b - r) / d + 2.0
+    } else {
+        (r - g) / d + 4.0
+    };
+    (h * 60.0, s.min(1.0), l)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hue_wraps_around_red() {
+        let range = ColorRange::default();
+        assert!(range.contains(0.8, 0.1, 0.1));
+        // A red leaning toward magenta sits just below 360 degrees.
+        assert!(range.contains(0.8, 0.1, 0.2));
+        assert!(!range.contains(0.1, 0.8, 0.1));
+        // Saturated enough in hue but too dark.
+        assert!(!range.contains(0.08, 0.0, 0.0));
+    }
+
+    #[test]
+    fn grays_are_judged_only_by_saturation_and_lightness() {
+        let range = ColorRange {
+            sat_min: 0.0,
+            ..ColorRange::default()
+        };
+        assert!(range.contains(0.5, 0.5, 0.5));
+        assert!(!ColorRange::default().contains(0.5, 0.5, 0.5));
+    }
+
+    #[test]
+    fn coverage_counts_the_matching_share() {
+        // One red pixel and three blue ones.
+        let red = crate::color::srgb_to_linear(0.8);
+        let low = crate::color::srgb_to_linear(0.1);
+        let mut data = vec![red, low, low];
+        for _ in 0..3 {
+            data.extend_from_slice(&[low, low, red]);
+        }
+        let buf = ImageBuf::from_data(2, 2, data).unwrap();
+        let range = ColorRange::default();
+        assert_eq!(range.coverage(&buf), 0.25);
+
+        let rgba = buf.to_rgba_u8_srgb();
+        assert_eq!(range.coverage_rgba8(&rgba), 0.25);
+        let mask = range.mask_rgba8(&rgba);
+        assert_eq!(mask[..4], rgba[..4]);
+        assert_eq!(mask[4], mask[5]);
+        assert_eq!(mask[5], mask[6]);
+        assert!(mask[4] < rgba[6]);
+    }
+}
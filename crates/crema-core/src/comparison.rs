@@ -0,0 +1,170 @@
+//! Before/after comparison images: the unedited and edited renders of a
+//! photo composed into one canvas, side by side or stacked, for sharing
+//! with clients.
+
+use crate::bitmap_font::draw_label;
+use crate::image_buf::{EditParams, ImageBuf};
+
+/// Space between the two halves, as a fraction of the longer cell edge.
+const GUTTER_FRACTION: u32 = 100;
+const MIN_GUTTER: u32 = 4;
+/// Label text is drawn at one glyph pixel per this many image pixels of
+/// the shorter cell edge, so it reads the same at any export size.
+const LABEL_SCALE_EDGE: u32 = 180;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ComparisonLayout {
+    SideBySide,
+    TopBottom,
+}
+
+impl ComparisonLayout {
+    pub const ALL: [ComparisonLayout; 2] =
+        [ComparisonLayout::SideBySide, ComparisonLayout::TopBottom];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ComparisonLayout::SideBySide => "Side by Side",
+            ComparisonLayout::TopBottom => "Top / Bottom",
+        }
+    }
+}
+
+/// The "before" of an edit: default adjustments with the edit's geometry,
+/// so both renders frame the same scene at the same size.
+pub fn before_params(params: &EditParams) -> EditParams {
+    EditParams {
+        crop_x: params.crop_x,
+        crop_y: params.crop_y,
+        crop_w: params.crop_w,
+        crop_h: params.crop_h,
+        rotation: params.rotation,
+        ..EditParams::default()
+    }
+}
+
+/// Compose `before` and `after` into one image on a black canvas, before
+/// on the left or top. Renders of different sizes are centered in equal
+/// cells. With `labels`, each half is marked BEFORE or AFTER in its corner.
+pub fn compose(
+    before: &ImageBuf,
+    after: &ImageBuf,
+    layout: ComparisonLayout,
+    labels: bool,
+) -> ImageBuf {
+    let cell_w = before.width.max(after.width);
+    let cell_h = before.height.max(after.height);
+    let gutter = (cell_w.max(cell_h) / GUTTER_FRACTION).max(MIN_GUTTER);
+    let (width, height) = match layout {
+        ComparisonLayout::SideBySide => (cell_w * 2 + gutter, cell_h),
+        ComparisonLayout::TopBottom => (cell_w, cell_h * 2 + gutter),
+    };
+    let mut canvas = ImageBuf::new(width, height);
+
+    for (index, (image, label)) in [(before, "BEFORE"), (after, "AFTER")]
+        .into_iter()
+        .enumerate()
+    {
+        let index = index as u32;
+        let (cell_x, cell_y) = match layout {
+            ComparisonLayout::SideBySide => (index * (cell_w + gutter), 0),
+            ComparisonLayout::TopBottom => (0, index * (cell_h + gutter)),
+        };
+        let x = cell_x + (cell_w - image.width) / 2;
+        let y = cell_y + (cell_h - image.height) / 2;
+        blit(&mut canvas, image, x, y);
+        if labels {
+            let scale = (image.width.min(image.height) / LABEL_SCALE_EDGE).max(1);
+            draw_label(&mut canvas, x + 4 * scale, y + 4 * scale, scale, label);
+        }
+    }
+    canvas
+}
+
+fn blit(canvas: &mut ImageBuf, image: &ImageBuf, x: u32, y: u32) {
+    let row_len = (image.width * 3) as usize;
+    for row in 0..image.height {
+        let src = (row * image.width * 3) as usize;
+        let dst = (((y + row) * canvas.width + x) * 3) as usize;
+        canvas.data[dst..dst + row_len].copy_from_slice(&image.data[src..src + row_len]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn flat(width: u32, height: u32, v: f32) -> ImageBuf {
+        ImageBuf::from_data(width, height, vec![v; (width * height * 3) as usize]).unwrap()
+    }
+
+    fn pixel(buf: &ImageBuf, x: u32, y: u32) -> f32 {
+        buf.data[((y * buf.width + x) * 3) as usize]
+    }
+
+    #[test]
+    fn places_before_then_after_with_a_gutter() {
+        let before = flat(40, 30, 0.2);
+        let after = flat(40, 30, 0.6);
+
+        let side = compose(&before, &after, ComparisonLayout::SideBySide, false);
+        assert_eq!((side.width, side.height), (84, 30));
+        assert_eq!(pixel(&side, 10, 10), 0.2);
+        assert_eq!(pixel(&side, 41, 10), 0.0);
+        assert_eq!(pixel(&side, 60, 10), 0.6);
+
+        let stacked = compose(&before, &after, ComparisonLayout::TopBottom, false);
+        assert_eq!((stacked.width, stacked.height), (40, 64));
+        assert_eq!(pixel(&stacked, 10, 10), 0.2);
+        assert_eq!(pixel(&stacked, 10, 50), 0.6);
+    }
+
+    #[test]
+    fn centers_renders_of_different_sizes() {
+        let before = flat(40, 30, 0.2);
+        let after = flat(20, 10, 0.6);
+        let side = compose(&before, &after, ComparisonLayout::SideBySide, false);
+        assert_eq!((side.width, side.height), (84, 30));
+        // The smaller render sits in the middle of its cell.
+        assert_eq!(pixel(&side, 44 + 5, 15), 0.0);
+        assert_eq!(pixel(&side, 44 + 10, 15), 0.6);
+        assert_eq!(pixel(&side, 44 + 10, 5), 0.0);
+    }
+
+    #[test]
+    fn labels_mark_each_half() {
+        let before = flat(200, 200, 0.5);
+        let after = flat(200, 200, 0.5);
+        let plain = compose(&before, &after, ComparisonLayout::SideBySide, false);
+        let labeled = compose(&before, &after, ComparisonLayout::SideBySide, true);
+        assert_eq!((plain.width, plain.height), (labeled.width, labeled.height));
+
+        let changed = |x0: u32, x1: u32| {
+            (0..200)
+                .flat_map(|y| (x0..x1).map(move |x| (x, y)))
+                .filter(|&(x, y)| pixel(&plain, x, y) != pixel(&labeled, x, y))
+                .count()
+        };
+        assert!(changed(0, 200) > 0);
+        assert!(changed(204, 404) > 0);
+        // Text is white, the plate darkened.
+        assert_eq!(pixel(&labeled, 4 + 3 + 1, 4 + 3), 1.0);
+        assert!(pixel(&labeled, 5, 5) < 0.5);
+        // Nothing strays into the bottom of the frame.
+        assert_eq!(pixel(&labeled, 100, 150), 0.5);
+    }
+
+    #[test]
+    fn before_keeps_only_the_geometry() {
+        let params = EditParams {
+            exposure: 1.0,
+            crop_w: 0.5,
+            rotation: 3.0,
+            ..EditParams::default()
+        };
+        let before = before_params(&params);
+        assert_eq!(before.exposure, 0.0);
+        assert_eq!(before.crop_w, 0.5);
+        assert_eq!(before.rotation, 3.0);
+    }
+}
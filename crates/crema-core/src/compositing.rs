@@ -0,0 +1,497 @@
+//! Multi-frame compositing. Stacking a burst of the same scene averages
+//! away sensor noise, which matters most for night and astro shots where
+//! a single exposure can't gather enough light.
+
+use std::fs::File;
+use std::io::{BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use anyhow::{Context, Result, bail};
+
+use crate::color::linear_to_srgb;
+use crate::image_buf::ImageBuf;
+
+/// Longest edge of the coarsest alignment level.
+const COARSE_EDGE: u32 = 128;
+/// Largest drift searched for, as a fraction of the coarse level's edge.
+const MAX_DRIFT: f32 = 0.1;
+/// Samples compared per candidate shift; larger levels are strided down to
+/// roughly this many so alignment cost stays flat with resolution.
+const ALIGN_SAMPLES: u32 = 200_000;
+/// Samples further than this many standard deviations from the median are
+/// rejected by sigma clipping.
+const CLIP_KAPPA: f32 = 2.5;
+const CLIP_ITERATIONS: usize = 3;
+/// Memory the clipped mean and median may spend on one band of rows from
+/// every frame. Those need all of a pixel's samples at once, so aligned
+/// frames are spilled to disk and read back a band at a time.
+const BAND_BYTES: usize = 256 << 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StackMethod {
+    /// Plain average: the most noise reduction, but passing satellites and
+    /// planes leave faint trails.
+    Mean,
+    /// Average after rejecting outliers per pixel, which removes trails
+    /// while keeping most of the mean's noise reduction. Needs three or
+    /// more frames to have anything to reject.
+    SigmaClippedMean,
+    /// Per-pixel median: robust to outliers but noisier than a clipped mean.
+    Median,
+}
+
+impl StackMethod {
+    pub const ALL: [StackMethod; 3] = [
+        StackMethod::SigmaClippedMean,
+        StackMethod::Mean,
+        StackMethod::Median,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            StackMethod::Mean => "Mean",
+            StackMethod::SigmaClippedMean => "Sigma-Clipped Mean",
+            StackMethod::Median => "Median",
+        }
+    }
+}
+
+/// Translate each frame onto the first and combine them per pixel. The
+/// result has the first frame's size; edges not covered by every frame are
+/// combined from the frames that do cover them.
+///
+/// Frames are taken one at a time, so only the first and the one being
+/// aligned are ever decoded at once, however long the sequence.
+///
+/// Alignment is translation only, which covers handheld bursts and short
+/// tracked sequences. Long untracked star sequences also rotate about the
+/// pole and will soften towards the frame edges.
+pub fn stack(
+    frames: impl IntoIterator<Item = Result<ImageBuf>>,
+    method: StackMethod,
+) -> Result<ImageBuf> {
+    stack_in_bands(frames, method, BAND_BYTES)
+}
+
+fn stack_in_bands(
+    frames: impl IntoIterator<Item = Result<ImageBuf>>,
+    method: StackMethod,
+    band_bytes: usize,
+) -> Result<ImageBuf> {
+    let mut frames = frames.into_iter();
+    let Some(reference) = frames.next().transpose()? else {
+        bail!("no frames to stack");
+    };
+    let mut combined = match method {
+        StackMethod::Mean => Combined::Mean(RunningMean::new(&reference)),
+        StackMethod::SigmaClippedMean | StackMethod::Median => {
+            Combined::Spilled(Spill::new(&reference)?)
+        }
+    };
+    combined.add(&reference, (0, 0))?;
+    for frame in frames {
+        let frame = frame?;
+        if (frame.width, frame.height) != (reference.width, reference.height) {
+            bail!(
+                "frames differ in size: {}x{} and {}x{}",
+                reference.width,
+                reference.height,
+                frame.width,
+                frame.height
+            );
+        }
+        combined.add(&frame, estimate_shift(&reference, &frame))?;
+    }
+    match combined {
+        Combined::Mean(mean) => Ok(mean.finish()),
+        Combined::Spilled(spill) => spill.finish(method, band_bytes),
+    }
+}
+
+enum Combined {
+    Mean(RunningMean),
+    Spilled(Spill),
+}
+
+impl Combined {
+    fn add(&mut self, frame: &ImageBuf, shift: (i32, i32)) -> Result<()> {
+        match self {
+            Combined::Mean(mean) => {
+                mean.add(frame, shift);
+                Ok(())
+            }
+            Combined::Spilled(spill) => spill.add(frame, shift),
+        }
+    }
+}
+
+/// The samples of `frame` shifted by `shift`, in the reference's pixel
+/// order, or `None` where it doesn't cover the reference.
+fn aligned(frame: &ImageBuf, (dx, dy): (i32, i32)) -> impl Iterator<Item = Option<&[f32]>> {
+    let (w, h) = (frame.width as i32, frame.height as i32);
+    (0..h).flat_map(move |y| {
+        (0..w).map(move |x| {
+            let (sx, sy) = (x + dx, y + dy);
+            (sx >= 0 && sy >= 0 && sx < w && sy < h).then(|| {
+                let i = ((sy * w + sx) * 3) as usize;
+                &frame.data[i..i + 3]
+            })
+        })
+    })
+}
+
+/// Per-pixel sums and coverage, for the plain mean.
+struct RunningMean {
+    width: u32,
+    height: u32,
+    sum: Vec<f32>,
+    count: Vec<u32>,
+}
+
+impl RunningMean {
+    fn new(reference: &ImageBuf) -> Self {
+        Self {
+            width: reference.width,
+            height: reference.height,
+            sum: vec![0.0; reference.data.len()],
+            count: vec![0; (reference.width * reference.height) as usize],
+        }
+    }
+
+    fn add(&mut self, frame: &ImageBuf, shift: (i32, i32)) {
+        let pixels = self.sum.chunks_exact_mut(3).zip(&mut self.count);
+        for ((sum, count), sample) in pixels.zip(aligned(frame, shift)) {
+            if let Some(sample) = sample {
+                for (s, v) in sum.iter_mut().zip(sample) {
+                    *s += v;
+                }
+                *count += 1;
+            }
+        }
+    }
+
+    fn finish(self) -> ImageBuf {
+        let mut out = ImageBuf::new(self.width, self.height);
+        let pixels = out.data.chunks_exact_mut(3).zip(self.sum.chunks_exact(3));
+        for ((px, sum), &count) in pixels.zip(&self.count) {
+            for (p, s) in px.iter_mut().zip(sum) {
+                *p = if count == 0 { 0.0 } else { s / count as f32 };
+            }
+        }
+        out
+    }
+}
+
+/// Aligned frames written to an unnamed temporary file as little-endian
+/// `f32`s in the reference's layout, NaN where a frame doesn't cover it.
+struct Spill {
+    file: File,
+    width: u32,
+    height: u32,
+    frames: usize,
+}
+
+impl Spill {
+    fn new(reference: &ImageBuf) -> Result<Self> {
+        Ok(Self {
+            file: tempfile::tempfile().context("failed to create a file to stack in")?,
+            width: reference.width,
+            height: reference.height,
+            frames: 0,
+        })
+    }
+
+    fn add(&mut self, frame: &ImageBuf, shift: (i32, i32)) -> Result<()> {
+        let mut out = BufWriter::new(&mut self.file);
+        for sample in aligned(frame, shift) {
+            let sample = sample.unwrap_or(&[f32::NAN; 3]);
+            for v in sample {
+                out.write_all(&v.to_le_bytes())?;
+            }
+        }
+        out.flush().context("failed to spill a frame to disk")?;
+        self.frames += 1;
+        Ok(())
+    }
+
+    /// Combine the frames a band of rows at a time, each band read from
+    /// every frame so it fits in `band_bytes`.
+    fn finish(mut self, method: StackMethod, band_bytes: usize) -> Result<ImageBuf> {
+        let row_bytes = self.width as usize * 3 * 4;
+        let frame_bytes = row_bytes * self.height as usize;
+        let rows = (band_bytes / (row_bytes * self.frames)).max(1) as u32;
+        let mut out = ImageBuf::new(self.width, self.height);
+        let mut bytes = Vec::new();
+        let mut bands = vec![Vec::new(); self.frames];
+        let mut samples = Vec::with_capacity(self.frames);
+        for y0 in (0..self.height).step_by(rows as usize) {
+            let band_rows = rows.min(self.height - y0) as usize;
+            bytes.resize(band_rows * row_bytes, 0);
+            for (i, band) in bands.iter_mut().enumerate() {
+                let offset = i * frame_bytes + y0 as usize * row_bytes;
+                self.file.seek(SeekFrom::Start(offset as u64))?;
+                self.file
+                    .read_exact(&mut bytes)
+                    .context("failed to read a spilled frame")?;
+                band.clear();
+                band.extend(
+                    bytes
+                        .chunks_exact(4)
+                        .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]])),
+                );
+            }
+            let start = y0 as usize * self.width as usize * 3;
+            for (i, v) in out.data[start..start + bands[0].len()]
+                .iter_mut()
+                .enumerate()
+            {
+                samples.clear();
+                samples.extend(bands.iter().map(|band| band[i]).filter(|s| !s.is_nan()));
+                *v = combine(&mut samples, method);
+            }
+        }
+        Ok(out)
+    }
+}
+
+fn combine(samples: &mut Vec<f32>, method: StackMethod) -> f32 {
+    match method {
+        StackMethod::Mean => mean(samples),
+        StackMethod::Median => median(samples),
+        StackMethod::SigmaClippedMean => {
+            for _ in 0..CLIP_ITERATIONS {
+                if samples.len() < 3 {
+                    break;
+                }
+                let center = median(samples);
+                let m = mean(samples);
+                let std = (samples.iter().map(|v| (v - m).powi(2)).sum::<f32>()
+                    / samples.len() as f32)
+                    .sqrt();
+                if std == 0.0 {
+                    break;
+                }
+                let before = samples.len();
+                samples.retain(|v| (v - center).abs() <= CLIP_KAPPA * std);
+                if samples.len() == before {
+                    break;
+                }
+            }
+            mean(samples)
+        }
+    }
+}
+
+fn mean(samples: &[f32]) -> f32 {
+    if samples.is_empty() {
+        return 0.0;
+    }
+    samples.iter().sum::<f32>() / samples.len() as f32
+}
+
+fn median(samples: &mut [f32]) -> f32 {
+    if samples.is_empty() {
+        return 0.0;
+    }
+    samples.sort_by(f32::total_cmp);
+    let mid = samples.len() / 2;
+    if samples.len().is_multiple_of(2) {
+        (samples[mid - 1] + samples[mid]) / 2.0
+    } else {
+        samples[mid]
+    }
+}
+
+/// The offset `(dx, dy)` at which `frame` lines up with `reference`:
+/// `frame(x + dx, y + dy)` shows what `reference(x, y)` does. Searched
+/// coarse to fine, widening only at the coarsest level.
+pub fn estimate_shift(reference: &ImageBuf, frame: &ImageBuf) -> (i32, i32) {
+    let longest = reference.width.max(reference.height);
+    let mut edge = COARSE_EDGE.min(longest);
+    let mut radius = ((edge as f32 * MAX_DRIFT).ceil() as i32).max(1);
+    let mut shift = (0i32, 0i32);
+    let mut prev_width = 0u32;
+    loop {
+        let a = reference.downsample(edge);
+        let b = frame.downsample(edge);
+        if prev_width > 0 {
+            let scale = a.width as f32 / prev_width as f32;
+            shift = (
+                (shift.0 as f32 * scale).round() as i32,
+                (shift.1 as f32 * scale).round() as i32,
+            );
+        }
+        let (la, lb) = (luma(&a), luma(&b));
+        let step = ((a.width * a.height) as f32 / ALIGN_SAMPLES as f32)
+            .sqrt()
+            .max(1.0) as usize;
+
+        let mut best = (f32::INFINITY, shift);
+        for dy in shift.1 - radius..=shift.1 + radius {
+            for dx in shift.0 - radius..=shift.0 + radius {
+                let cost = difference(&la, &lb, a.width, a.height, dx, dy, step);
+                if cost < best.0 {
+                    best = (cost, (dx, dy));
+                }
+            }
+        }
+        shift = best.1;
+
+        if edge >= longest {
+            return shift;
+        }
+        prev_width = a.width;
+        edge = (edge * 2).min(longest);
+        radius = 2;
+    }
+}
+
+fn luma(buf: &ImageBuf) -> Vec<f32> {
+    buf.data
+        .chunks_exact(3)
+        .map(|p| 0.2126 * p[0] + 0.7152 * p[1] + 0.0722 * p[2])
+        .collect()
+}
+
+/// Mean absolute difference over the overlap of `a` and `b` shifted by
+/// `(dx, dy)`, sampling every `step`th row and column.
+fn difference(a: &[f32], b: &[f32], w: u32, h: u32, dx: i32, dy: i32, step: usize) -> f32 {
+    let (w, h) = (w as i32, h as i32);
+    let (x0, x1) = ((-dx).max(0), (w - dx).min(w));
+    let (y0, y1) = ((-dy).max(0), (h - dy).min(h));
+    // Require a meaningful overlap so large shifts can't win on a sliver.
+    if (x1 - x0) < w / 2 || (y1 - y0) < h / 2 {
+        return f32::INFINITY;
+    }
+    let mut sum = 0.0;
+    let mut count = 0u32;
+    for y in (y0..y1).step_by(step) {
+        for x in (x0..x1).step_by(step) {
+            sum += (a[(y * w + x) as usize] - b[((y + dy) * w + x + dx) as usize]).abs();
+            count += 1;
+        }
+    }
+    sum / count.max(1) as f32
+}
+
+/// Write `buf` as a 16-bit sRGB TIFF, keeping the precision a stack gains
+/// over its individual frames.
+pub fn save_tiff16(buf: &ImageBuf, path: &Path) -> Result<()> {
+    let data: Vec<u16> = buf
+        .data
+        .iter()
+        .map(|&v| (linear_to_srgb(v.clamp(0.0, 1.0)) * 65535.0).round() as u16)
+        .collect();
+    let img = image::ImageBuffer::<image::Rgb<u16>, _>::from_raw(buf.width, buf.height, data)
+        .context("could not construct TIFF buffer")?;
+    img.save_with_format(path, image::ImageFormat::Tiff)
+        .with_context(|| format!("failed to write {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A dark field with a scattering of soft stars, offset by `(ox, oy)`.
+    fn star_field(w: u32, h: u32, ox: i32, oy: i32, noise_seed: u32) -> ImageBuf {
+        let stars = [
+            (30, 20),
+            (90, 45),
+            (150, 100),
+            (200, 30),
+            (60, 140),
+            (180, 150),
+        ];
+        let mut buf = ImageBuf::new(w, h);
+        let mut seed = noise_seed.wrapping_mul(2_654_435_761).wrapping_add(1);
+        for y in 0..h as i32 {
+            for x in 0..w as i32 {
+                let mut v = 0.02;
+                for &(sx, sy) in &stars {
+                    let d2 = ((x - sx - ox).pow(2) + (y - sy - oy).pow(2)) as f32;
+                    v += 0.8 * (-d2 / 8.0).exp();
+                }
+                seed ^= seed << 13;
+                seed ^= seed >> 17;
+                seed ^= seed << 5;
+                v += (seed % 1000) as f32 / 1000.0 * 0.02 - 0.01;
+                let i = ((y as u32 * w + x as u32) * 3) as usize;
+                buf.data[i..i + 3].copy_from_slice(&[v, v, v]);
+            }
+        }
+        buf
+    }
+
+    #[test]
+    fn finds_translation() {
+        let reference = star_field(240, 180, 0, 0, 1);
+        let moved = star_field(240, 180, 7, -4, 2);
+        assert_eq!(estimate_shift(&reference, &moved), (7, -4));
+    }
+
+    #[test]
+    fn aligned_stack_keeps_stars_sharp() {
+        let frames: Vec<_> = (0..4)
+            .map(|i| star_field(240, 180, i * 3, i * 2, i as u32 + 1))
+            .collect();
+        let out = stack(frames.into_iter().map(Ok), StackMethod::Mean).unwrap();
+        let peak = out.data[((20 * 240 + 30) * 3) as usize];
+        assert!(peak > 0.7, "star smeared to {peak}");
+    }
+
+    #[test]
+    fn sigma_clipping_rejects_a_trail() {
+        let mut frames: Vec<_> = (0..6)
+            .map(|i| ImageBuf::from_data(8, 8, vec![0.1 + i as f32 * 0.002; 8 * 8 * 3]).unwrap())
+            .collect();
+        // A satellite crossing row 3 of one frame.
+        for x in 0..8 {
+            let i = ((3 * 8 + x) * 3) as usize;
+            frames[2].data[i..i + 3].copy_from_slice(&[0.9, 0.9, 0.9]);
+        }
+        let at = |buf: &ImageBuf| buf.data[((3 * 8 + 4) * 3) as usize];
+
+        let all = || frames.iter().cloned().map(Ok);
+        let mean = stack(all(), StackMethod::Mean).unwrap();
+        let clipped = stack(all(), StackMethod::SigmaClippedMean).unwrap();
+        let median = stack(all(), StackMethod::Median).unwrap();
+        assert!(at(&mean) > 0.2);
+        assert!((at(&clipped) - 0.1052).abs() < 1e-4, "{}", at(&clipped));
+        assert!((at(&median) - 0.107).abs() < 1e-4);
+    }
+
+    #[test]
+    fn bands_match_a_single_pass() {
+        let frames: Vec<_> = (0..3)
+            .map(|i| star_field(240, 180, i * 2, -i, i as u32 + 1))
+            .collect();
+        let all = || frames.iter().cloned().map(Ok);
+        let whole = stack_in_bands(all(), StackMethod::Median, usize::MAX).unwrap();
+        // One row of every frame per band.
+        let banded = stack_in_bands(all(), StackMethod::Median, 1).unwrap();
+        assert_eq!(whole.data, banded.data);
+        // The shifted frames leave the bottom right to the reference alone.
+        let corner = ((179 * 240 + 239) * 3) as usize;
+        assert_eq!(whole.data[corner], frames[0].data[corner]);
+    }
+
+    #[test]
+    fn rejects_mismatched_frames() {
+        let a = ImageBuf::new(4, 4);
+        let b = ImageBuf::new(4, 5);
+        assert!(stack([Ok(a), Ok(b)], StackMethod::Mean).is_err());
+        assert!(stack([], StackMethod::Mean).is_err());
+    }
+
+    #[test]
+    fn tiff_round_trips_at_16_bits() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("stack.tif");
+        let buf = ImageBuf::from_data(2, 1, vec![0.0, 0.001, 0.18, 0.5, 0.75, 1.0]).unwrap();
+        save_tiff16(&buf, &path).unwrap();
+        let loaded = crate::raw::load_any(&path).unwrap();
+        for (a, b) in loaded.data.iter().zip(&buf.data) {
+            assert!((a - b).abs() < 1e-4, "{a} vs {b}");
+        }
+    }
+}
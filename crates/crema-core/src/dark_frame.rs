@@ -0,0 +1,165 @@
+//! Dark frame subtraction for long exposures. Several frames shot with the
+//! lens capped, at the same ISO, shutter speed and sensor temperature as
+//! the lights, are averaged into a master dark that records the sensor's
+//! thermal signal and amp glow; subtracting it leaves only the scene.
+//! Both are photosite data, before demosaic and white balance, where the
+//! thermal signal is still the same additive offset it was on the sensor.
+
+use anyhow::{Result, bail};
+
+use crate::raw::SensorData;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MasterDark {
+    pub width: u32,
+    pub height: u32,
+    /// One value per photosite, the same layout as `SensorData::data`.
+    pub data: Vec<f32>,
+}
+
+impl MasterDark {
+    /// Subtract the dark from `sensor`, clamping at black. Returns false and
+    /// leaves `sensor` alone when the sizes differ, since a dark only lines
+    /// up with the sensor mode it was shot in.
+    pub fn subtract(&self, sensor: &mut SensorData) -> bool {
+        if sensor.width != self.width || sensor.height != self.height {
+            return false;
+        }
+        for (v, d) in sensor.data.iter_mut().zip(&self.data) {
+            *v = (*v - d).max(0.0);
+        }
+        true
+    }
+
+    /// Little-endian `f32` samples, for storage.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.data.iter().flat_map(|v| v.to_le_bytes()).collect()
+    }
+
+    pub fn from_bytes(width: u32, height: u32, bytes: &[u8]) -> Result<Self> {
+        let expected = width as usize * height as usize * 4;
+        if bytes.len() != expected {
+            bail!(
+                "master dark is {} bytes, expected {expected} for {width}x{height}",
+                bytes.len()
+            );
+        }
+        let data = bytes
+            .chunks_exact(4)
+            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect();
+        Ok(Self {
+            width,
+            height,
+            data,
+        })
+    }
+}
+
+/// Running mean of dark frames, so a long series never has to be held in
+/// memory at once.
+#[derive(Debug, Default)]
+pub struct DarkStack {
+    width: u32,
+    height: u32,
+    sum: Vec<f32>,
+    count: u32,
+}
+
+impl DarkStack {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, frame: &SensorData) -> Result<()> {
+        if self.count == 0 {
+            self.width = frame.width;
+            self.height = frame.height;
+            self.sum = vec![0.0; frame.data.len()];
+        } else if (frame.width, frame.height) != (self.width, self.height) {
+            bail!(
+                "dark frame is {}x{}, the others are {}x{}",
+                frame.width,
+                frame.height,
+                self.width,
+                self.height
+            );
+        }
+        for (s, v) in self.sum.iter_mut().zip(&frame.data) {
+            *s += v;
+        }
+        self.count += 1;
+        Ok(())
+    }
+
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+
+    pub fn finish(self) -> Result<MasterDark> {
+        if self.count == 0 {
+            bail!("no dark frames to average");
+        }
+        let inv = 1.0 / self.count as f32;
+        Ok(MasterDark {
+            width: self.width,
+            height: self.height,
+            data: self.sum.into_iter().map(|s| s * inv).collect(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::raw::sensor::tests::bayer;
+
+    #[test]
+    fn averages_frames() {
+        let mut stack = DarkStack::new();
+        stack.add(&bayer(4, 2, 0.01)).unwrap();
+        stack.add(&bayer(4, 2, 0.03)).unwrap();
+        let dark = stack.finish().unwrap();
+        assert_eq!(dark.data.len(), 8);
+        assert!(dark.data.iter().all(|&v| (v - 0.02).abs() < 1e-6));
+    }
+
+    #[test]
+    fn rejects_mismatched_sizes() {
+        let mut stack = DarkStack::new();
+        stack.add(&bayer(4, 2, 0.01)).unwrap();
+        assert!(stack.add(&bayer(2, 2, 0.0)).is_err());
+        assert!(DarkStack::new().finish().is_err());
+    }
+
+    #[test]
+    fn subtracts_and_clamps() {
+        let mut data = vec![0.05; 8];
+        data[0] = 0.5;
+        let dark = MasterDark {
+            width: 4,
+            height: 2,
+            data,
+        };
+        let mut light = bayer(4, 2, 0.25);
+        assert!(dark.subtract(&mut light));
+        assert_eq!(light.data[0], 0.0);
+        assert!((light.data[1] - 0.2).abs() < 1e-6);
+
+        let mut small = bayer(2, 2, 0.3);
+        assert!(!dark.subtract(&mut small));
+        assert_eq!(small.data[0], 0.3);
+    }
+
+    #[test]
+    fn round_trips_through_bytes() {
+        let dark = MasterDark {
+            width: 4,
+            height: 2,
+            data: (0..8).map(|i| i as f32 * 0.001).collect(),
+        };
+        let restored = MasterDark::from_bytes(4, 2, &dark.to_bytes()).unwrap();
+        assert_eq!(restored, dark);
+        assert!(MasterDark::from_bytes(4, 3, &dark.to_bytes()).is_err());
+    }
+}
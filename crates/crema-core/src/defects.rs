@@ -0,0 +1,188 @@
+//! Dead and stuck pixel maps. A map is detected once from a RAW dark
+//! frame (lens cap on, shortest shutter) and then used to patch the same
+//! photosites in every RAW from that camera body, before demosaic.
+
+use anyhow::{Result, bail};
+use serde::{Deserialize, Serialize};
+
+use crate::raw::SensorData;
+
+/// A photosite must stand this far above its same-color neighbors' median,
+/// as a fraction of the sensor's range, to be flagged.
+const MIN_EXCESS: f32 = 0.02;
+/// ...and this many standard deviations above the frame's mean.
+const SIGMA: f32 = 8.0;
+/// More defects than this fraction of the frame means the capture isn't
+/// a dark frame.
+const MAX_DEFECT_FRACTION: f32 = 0.001;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DefectMap {
+    /// Dimensions of the sensor the map was detected on.
+    pub width: u32,
+    pub height: u32,
+    /// Defective photosite positions, row-major.
+    pub pixels: Vec<(u32, u32)>,
+}
+
+impl DefectMap {
+    /// Find stuck (hot) photosites in a dark frame: ones far brighter than
+    /// both their same-color neighbors and the frame's noise floor.
+    pub fn detect(dark: &SensorData) -> Result<Self> {
+        let (w, h) = (dark.width, dark.height);
+        if w < 5 || h < 5 {
+            bail!("dark frame is too small");
+        }
+
+        let value = |x: u32, y: u32| dark.data[(y * w + x) as usize];
+        let n = dark.data.len() as f64;
+        let (sum, sum_sq) = dark.data.iter().fold((0.0f64, 0.0f64), |(s, sq), &v| {
+            let v = v as f64;
+            (s + v, sq + v * v)
+        });
+        let mean = (sum / n) as f32;
+        let std = ((sum_sq / n) as f32 - mean * mean).max(0.0).sqrt();
+        if mean > 0.1 {
+            bail!("not a dark frame: mean level is {:.0}%", mean * 100.0);
+        }
+        let floor = mean + SIGMA * std;
+
+        let mut pixels = Vec::new();
+        for y in 0..h {
+            for x in 0..w {
+                let v = value(x, y);
+                if v < floor {
+                    continue;
+                }
+                let mut around: Vec<f32> = dark
+                    .same_color_neighbors(x, y)
+                    .map(|(nx, ny)| value(nx, ny))
+                    .collect();
+                if v - median(&mut around) >= MIN_EXCESS {
+                    pixels.push((x, y));
+                }
+            }
+        }
+
+        if pixels.len() as f32 > n as f32 * MAX_DEFECT_FRACTION {
+            bail!(
+                "not a dark frame: {} pixels stand out, expected a handful",
+                pixels.len()
+            );
+        }
+        Ok(Self {
+            width: w,
+            height: h,
+            pixels,
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pixels.is_empty()
+    }
+
+    /// Replace each defective photosite with the median of its healthy
+    /// same-color neighbors. Returns false and leaves `sensor` alone when
+    /// it isn't the size the map was detected on.
+    pub fn apply(&self, sensor: &mut SensorData) -> bool {
+        if (sensor.width, sensor.height) != (self.width, self.height) {
+            return false;
+        }
+        let defects: std::collections::HashSet<(u32, u32)> = self.pixels.iter().copied().collect();
+        let w = sensor.width;
+        for &(x, y) in &self.pixels {
+            let mut healthy: Vec<f32> = sensor
+                .same_color_neighbors(x, y)
+                .filter(|p| !defects.contains(p))
+                .map(|(nx, ny)| sensor.data[(ny * w + nx) as usize])
+                .collect();
+            if !healthy.is_empty() {
+                sensor.data[(y * w + x) as usize] = median(&mut healthy);
+            }
+        }
+        true
+    }
+}
+
+fn median(values: &mut [f32]) -> f32 {
+    values.sort_by(f32::total_cmp);
+    let mid = values.len() / 2;
+    if values.len().is_multiple_of(2) {
+        (values[mid - 1] + values[mid]) / 2.0
+    } else {
+        values[mid]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::raw::sensor::tests::bayer;
+
+    fn dark_frame(w: u32, h: u32, hot: &[(u32, u32)]) -> SensorData {
+        let mut dark = bayer(w, h, 0.0);
+        for (i, v) in dark.data.iter_mut().enumerate() {
+            *v = 0.002 + (i % 7) as f32 * 0.0005;
+        }
+        for &(x, y) in hot {
+            dark.data[(y * w + x) as usize] = 0.9;
+        }
+        dark
+    }
+
+    #[test]
+    fn finds_hot_pixels() {
+        let hot = [(5, 7), (40, 3), (63, 63)];
+        let map = DefectMap::detect(&dark_frame(64, 64, &hot)).unwrap();
+        assert_eq!(map.pixels, [(40, 3), (5, 7), (63, 63)]);
+        assert_eq!((map.width, map.height), (64, 64));
+    }
+
+    #[test]
+    fn clean_frame_has_no_defects() {
+        assert!(
+            DefectMap::detect(&dark_frame(32, 32, &[]))
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn rejects_a_normal_exposure() {
+        assert!(DefectMap::detect(&bayer(16, 16, 0.4)).is_err());
+    }
+
+    #[test]
+    fn apply_patches_from_same_color_neighbors() {
+        let map = DefectMap {
+            width: 8,
+            height: 8,
+            pixels: vec![(4, 4)],
+        };
+        let mut sensor = bayer(8, 8, 0.25);
+        // Greens are brighter; a red photosite must not borrow from them.
+        for y in 0..8 {
+            for x in 0..8 {
+                if sensor.color_at(x, y) == 1 {
+                    sensor.data[(y * 8 + x) as usize] = 0.6;
+                }
+            }
+        }
+        sensor.data[4 * 8 + 4] = 1.0;
+        assert!(map.apply(&mut sensor));
+        assert_eq!(sensor.data[4 * 8 + 4], 0.25);
+    }
+
+    #[test]
+    fn apply_skips_other_sensor_sizes() {
+        let map = DefectMap {
+            width: 100,
+            height: 100,
+            pixels: vec![(50, 20)],
+        };
+        let mut sensor = bayer(10, 10, 0.1);
+        sensor.data[25] = 0.8;
+        assert!(!map.apply(&mut sensor));
+        assert_eq!(sensor.data[25], 0.8);
+    }
+}
@@ -0,0 +1,352 @@
+//! Dithering for 8-bit export. Rounding a smooth gradient such as a clear
+//! sky to 256 levels leaves visible steps; adding about one level of noise
+//! before rounding trades the steps for fine grain that the eye averages
+//! back into a smooth ramp.
+
+use std::sync::LazyLock;
+
+use serde::{Deserialize, Serialize};
+
+use crate::color::linear_to_srgb;
+use crate::image_buf::ImageBuf;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Dither {
+    /// Plain rounding, identical to the preview path.
+    #[default]
+    Off,
+    /// White noise with a triangular distribution spanning two levels, the
+    /// textbook choice: it fully decorrelates the error from the signal.
+    Triangular,
+    /// A tiled blue-noise mask. The noise sits at high frequencies, so the
+    /// grain is finer and less visible than triangular at the same depth.
+    BlueNoise,
+}
+
+impl Dither {
+    pub const ALL: [Dither; 3] = [Dither::Off, Dither::Triangular, Dither::BlueNoise];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Dither::Off => "Off",
+            Dither::Triangular => "Triangular",
+            Dither::BlueNoise => "Blue Noise",
+        }
+    }
+}
+
+/// Edge of the square, tileable blue-noise mask.
+const TILE: usize = 64;
+/// Gaussian radius used to measure clustering while building the mask.
+const TILE_SIGMA: f32 = 1.5;
+
+/// Convert to RGBA u8 with sRGB gamma, dithering the rounding step.
+pub fn to_rgba_u8_srgb(buf: &ImageBuf, dither: Dither) -> Vec<u8> {
+    if dither == Dither::Off {
+        return buf.to_rgba_u8_srgb();
+    }
+    let width = buf.width.max(1) as usize;
+    let mut out = Vec::with_capacity(buf.pixel_count() * 4);
+    for (i, pixel) in buf.data.chunks_exact(3).enumerate() {
+        let (x, y) = (i % width, i / width);
+        for (c, &v) in pixel.iter().enumerate() {
+            out.push(quantize(v, x, y, c, dither));
+        }
+        out.push(255);
+    }
+    out
+}
+
+/// Convert the rows of `buf` from `first_row` on to RGB u8, three bytes a
+/// pixel, exactly as [`to_rgba_u8_srgb`] would without the alpha. Filling
+/// `out` a band at a time lets a full resolution export convert on several
+/// threads and never hold a second full-size copy.
+pub fn rows_to_rgb_u8_srgb(buf: &ImageBuf, dither: Dither, first_row: u32, out: &mut [u8]) {
+    let width = buf.width.max(1) as usize;
+    let start = first_row as usize * width * 3;
+    let samples = &buf.data[start..start + out.len()];
+    for (i, (&v, level)) in samples.iter().zip(out.iter_mut()).enumerate() {
+        let pixel = i / 3;
+        let (x, y) = (pixel % width, first_row as usize + pixel / width);
+        *level = match dither {
+            Dither::Off => crate::image_buf::linear_to_srgb_u8(v),
+            _ => quantize(v, x, y, i % 3, dither),
+        };
+    }
+}
+
+/// One dithered 8-bit level of sample `v`, channel `c` of pixel (x, y).
+fn quantize(v: f32, x: usize, y: usize, c: usize, dither: Dither) -> u8 {
+    let level = linear_to_srgb(v.clamp(0.0, 1.0)) * 255.0;
+    let noise = match dither {
+        Dither::Off => 0.0,
+        Dither::Triangular => triangular(x as u32, y as u32, c as u32),
+        Dither::BlueNoise => blue_noise(x, y, c),
+    };
+    (level + noise).round().clamp(0.0, 255.0) as u8
+}
+
+/// Triangular noise in (-1, 1), hashed from the position so exports are
+/// reproducible and every channel gets independent noise.
+fn triangular(x: u32, y: u32, c: u32) -> f32 {
+    let h = hash(x ^ hash(y ^ hash(c)));
+    let a = (h & 0xffff) as f32 / 65536.0;
+    let b = (h >> 16) as f32 / 65536.0;
+    a - b
+}
+
+fn hash(mut v: u32) -> u32 {
+    v ^= v >> 16;
+    v = v.wrapping_mul(0x7feb_352d);
+    v ^= v >> 15;
+    v = v.wrapping_mul(0x846c_a68b);
+    v ^ (v >> 16)
+}
+
+/// Blue noise in (-1, 1), reshaped from the uniform mask to a triangular
+/// distribution; uniform noise a level wide can't move values sitting
+/// right on a level, leaving those bands in place. Each channel reads the
+/// mask at a different offset so the grain doesn't line up into colored
+/// specks.
+fn blue_noise(x: usize, y: usize, c: usize) -> f32 {
+    let (ox, oy) = [(0, 0), (TILE / 2, TILE / 3), (TILE / 3, TILE / 2)][c];
+    let v = BLUE_NOISE[((y + oy) % TILE) * TILE + (x + ox) % TILE] * 2.0 - 1.0;
+    v.signum() * (1.0 - (1.0 - v.abs()).sqrt())
+}
+
+static BLUE_NOISE: LazyLock<Vec<f32>> = LazyLock::new(|| {
+    let n = TILE * TILE;
+    void_and_cluster()
+        .into_iter()
+        .map(|rank| (rank as f32 + 0.5) / n as f32)
+        .collect()
+});
+
+/// Rank every cell of a `TILE` x `TILE` torus by Ulichney's
+/// void-and-cluster method: cells are switched on one at a time, always in
+/// the emptiest remaining region, so any threshold of the ranks gives an
+/// evenly spread pattern.
+fn void_and_cluster() -> Vec<usize> {
+    let n = TILE * TILE;
+    let kernel: Vec<f32> = (0..n)
+        .map(|i| {
+            let wrap = |d: usize| d.min(TILE - d) as f32;
+            let (dx, dy) = (wrap(i % TILE), wrap(i / TILE));
+            (-(dx * dx + dy * dy) / (2.0 * TILE_SIGMA * TILE_SIGMA)).exp()
+        })
+        .collect();
+    let spread = |energy: &mut [f32], cell: usize, sign: f32| {
+        let (cx, cy) = (cell % TILE, cell / TILE);
+        for (i, e) in energy.iter_mut().enumerate() {
+            let dx = (i % TILE + TILE - cx) % TILE;
+            let dy = (i / TILE + TILE - cy) % TILE;
+            *e += sign * kernel[dy * TILE + dx];
+        }
+    };
+    let extreme = |energy: &[f32], on: &[bool], want_on: bool, pick_max: bool| {
+        let candidates = (0..n).filter(|&i| on[i] == want_on);
+        if pick_max {
+            candidates.max_by(|&a, &b| energy[a].total_cmp(&energy[b]))
+        } else {
+            candidates.min_by(|&a, &b| energy[a].total_cmp(&energy[b]))
+        }
+        .unwrap()
+    };
+
+    // A sparse random start, relaxed until the tightest cluster and the
+    // largest void coincide.
+    let mut on = vec![false; n];
+    let mut energy = vec![0.0; n];
+    let mut seed = 0x2545_f491u32;
+    let initial = n / 10;
+    let mut placed = 0;
+    while placed < initial {
+        seed = hash(seed);
+        let cell = seed as usize % n;
+        if !on[cell] {
+            on[cell] = true;
+            spread(&mut energy, cell, 1.0);
+            placed += 1;
+        }
+    }
+    loop {
+        let cluster = extreme(&energy, &on, true, true);
+        on[cluster] = false;
+        spread(&mut energy, cluster, -1.0);
+        let void = extreme(&energy, &on, false, false);
+        on[void] = true;
+        spread(&mut energy, void, 1.0);
+        if void == cluster {
+            break;
+        }
+    }
+
+    let mut rank = vec![0; n];
+    // Ranks below the start: peel off the tightest clusters.
+    let (mut pattern, mut pattern_energy) = (on.clone(), energy.clone());
+    for r in (0..initial).rev() {
+        let cluster = extreme(&pattern_energy, &pattern, true, true);
+        pattern[cluster] = false;
+        spread(&mut pattern_energy, cluster, -1.0);
+        rank[cluster] = r;
+    }
+    // Ranks above: fill the largest voids. Past half full this is the same
+    // as removing clusters from the inverted pattern, since each cell's
+    // energy from on and off cells sums to a constant.
+    for r in initial..n {
+        let void = extreme(&energy, &on, false, false);
+        on[void] = true;
+        spread(&mut energy, void, 1.0);
+        rank[void] = r;
+    }
+    rank
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A sky-like ramp spanning a few output levels across the width.
+    fn sky(w: u32, h: u32) -> ImageBuf {
+        let mut buf = ImageBuf::new(w, h);
+        for (i, px) in buf.data.chunks_exact_mut(3).enumerate() {
+            let t = (i as u32 % w) as f32 / (w - 1) as f32;
+            let v = crate::color::srgb_to_linear((150.0 + 4.0 * t) / 255.0);
+            px.copy_from_slice(&[v * 0.6, v * 0.8, v]);
+        }
+        buf
+    }
+
+    /// The ideal, unquantized 8-bit level of every red sample.
+    fn ideal(buf: &ImageBuf) -> Vec<f32> {
+        buf.data
+            .chunks_exact(3)
+            .map(|p| linear_to_srgb(p[0]) * 255.0)
+            .collect()
+    }
+
+    fn red(rgba: &[u8]) -> Vec<f32> {
+        rgba.chunks_exact(4).map(|p| p[0] as f32).collect()
+    }
+
+    /// Longest run of identical values along any row: the width of the
+    /// widest band.
+    fn widest_band(levels: &[f32], w: usize) -> usize {
+        levels
+            .chunks_exact(w)
+            .map(|row| {
+                let (mut longest, mut run) = (1, 1);
+                for pair in row.windows(2) {
+                    run = if pair[0] == pair[1] { run + 1 } else { 1 };
+                    longest = longest.max(run);
+                }
+                longest
+            })
+            .max()
+            .unwrap()
+    }
+
+    /// Mean error after averaging `block` x `block` squares, roughly what
+    /// the eye sees from a normal viewing distance.
+    fn blurred_error(levels: &[f32], ideal: &[f32], w: usize, block: usize) -> f32 {
+        let h = levels.len() / w;
+        let mut total = 0.0;
+        let mut count = 0;
+        for by in (0..h - block + 1).step_by(block) {
+            for bx in (0..w - block + 1).step_by(block) {
+                let mut diff = 0.0;
+                for y in by..by + block {
+                    for x in bx..bx + block {
+                        diff += levels[y * w + x] - ideal[y * w + x];
+                    }
+                }
+                total += (diff / (block * block) as f32).abs();
+                count += 1;
+            }
+        }
+        total / count as f32
+    }
+
+    #[test]
+    fn off_matches_plain_rounding() {
+        let buf = sky(64, 8);
+        assert_eq!(to_rgba_u8_srgb(&buf, Dither::Off), buf.to_rgba_u8_srgb());
+    }
+
+    #[test]
+    fn dithering_breaks_up_bands() {
+        let (w, h) = (512, 64);
+        let buf = sky(w, h);
+        let plain = widest_band(&red(&to_rgba_u8_srgb(&buf, Dither::Off)), w as usize);
+        assert!(
+            plain > 50,
+            "expected bands in the plain export, widest {plain}"
+        );
+        for dither in [Dither::Triangular, Dither::BlueNoise] {
+            let levels = red(&to_rgba_u8_srgb(&buf, dither));
+            let widest = widest_band(&levels, w as usize);
+            assert!(widest * 5 < plain, "{dither:?}: widest band {widest}");
+        }
+    }
+
+    #[test]
+    fn dithering_tracks_the_gradient_on_average() {
+        let (w, h) = (512, 64);
+        let buf = sky(w, h);
+        let ideal = ideal(&buf);
+        let error =
+            |dither| blurred_error(&red(&to_rgba_u8_srgb(&buf, dither)), &ideal, w as usize, 8);
+        let plain = error(Dither::Off);
+        let triangular = error(Dither::Triangular);
+        let blue = error(Dither::BlueNoise);
+        assert!(triangular < plain * 0.6, "{triangular} vs {plain}");
+        // Blue noise cancels out over small areas; white noise doesn't.
+        assert!(blue < triangular, "{blue} vs {triangular}");
+    }
+
+    #[test]
+    fn noise_stays_within_a_level() {
+        let buf = sky(128, 16);
+        let ideal = ideal(&buf);
+        for dither in [Dither::Triangular, Dither::BlueNoise] {
+            for (out, want) in red(&to_rgba_u8_srgb(&buf, dither)).iter().zip(&ideal) {
+                assert!((out - want).abs() <= 1.5, "{dither:?}: {out} for {want}");
+            }
+        }
+    }
+
+    #[test]
+    fn blue_noise_mask_is_a_permutation() {
+        let mut ranks = void_and_cluster();
+        ranks.sort_unstable();
+        assert!(ranks.iter().enumerate().all(|(i, &r)| i == r));
+    }
+
+    #[test]
+    fn blacks_and_whites_stay_clean() {
+        let buf = ImageBuf::from_data(2, 1, vec![0.0, 0.0, 0.0, 1.0, 1.0, 1.0]).unwrap();
+        for dither in Dither::ALL {
+            let out = to_rgba_u8_srgb(&buf, dither);
+            assert!(out[..3].iter().all(|&v| v <= 1), "{dither:?}: {out:?}");
+            assert!(out[4..7].iter().all(|&v| v >= 254), "{dither:?}: {out:?}");
+        }
+    }
+
+    #[test]
+    fn row_bands_match_the_whole_frame() {
+        let buf = sky(70, 9);
+        for dither in Dither::ALL {
+            let whole = to_rgba_u8_srgb(&buf, dither);
+            let mut banded = vec![0; 70 * 9 * 3];
+            for (band, rows) in banded.chunks_mut(70 * 4 * 3).enumerate() {
+                rows_to_rgb_u8_srgb(&buf, dither, band as u32 * 4, rows);
+            }
+            let rgb: Vec<u8> = whole
+                .chunks_exact(4)
+                .flat_map(|p| p[..3].to_vec())
+                .collect();
+            assert_eq!(banded, rgb, "{dither:?}");
+        }
+    }
+}
@@ -0,0 +1,187 @@
+//! Parameter-by-parameter comparison of two edit states.
+
+use crate::image_buf::EditParams;
+
+/// Values closer than this are treated as equal, so slider round-off and
+/// the REAL round trip through the catalog don't show up as differences.
+const EPSILON: f32 = 1e-4;
+
+/// One adjustable parameter of [`EditParams`].
+#[derive(Clone, Copy)]
+pub struct EditParam {
+    /// Field name, stable across releases and used as the identifier.
+    pub key: &'static str,
+    pub label: &'static str,
+    get: fn(&EditParams) -> f32,
+    get_mut: fn(&mut EditParams) -> &mut f32,
+}
+
+impl EditParam {
+    pub fn value(&self, params: &EditParams) -> f32 {
+        (self.get)(params)
+    }
+
+    pub fn set(&self, params: &mut EditParams, value: f32) {
+        *(self.get_mut)(params) = value;
+    }
+}
+
+impl std::fmt::Debug for EditParam {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("EditParam").field(&self.key).finish()
+    }
+}
+
+impl PartialEq for EditParam {
+    fn eq(&self, other: &Self) -> bool {
+        self.key == other.key
+    }
+}
+
+macro_rules! edit_params {
+    ($($field:ident => $label:literal),* $(,)?) => {
+        /// Every parameter, in the order the develop panel shows them.
+        pub const PARAMS: &[EditParam] = &[$(
+            EditParam {
+                key: stringify!($field),
+                label: $label,
+                get: |p| p.$field,
+                get_mut: |p| &mut p.$field,
+            },
+        )*];
+    };
+}
+
+edit_params! {
+    exposure => "Exposure",
+    contrast => "Contrast",
+    highlights => "Highlights",
+    shadows => "Shadows",
+    blacks => "Blacks",
+    wb_temp => "Temperature",
+    wb_tint => "Tint",
+    vibrance => "Vibrance",
+    saturation => "Saturation",
+    hsl_hue => "HSL Hue",
+    hsl_saturation => "HSL Saturation",
+    hsl_lightness => "HSL Lightness",
+    split_shadow_hue => "Shadow Hue",
+    split_shadow_sat => "Shadow Saturation",
+    split_highlight_hue => "Highlight Hue",
+    split_highlight_sat => "Highlight Saturation",
+    split_balance => "Split Balance",
+    nr_luminance => "Luminance NR",
+    nr_color => "Color NR",
+    sharpen_amount => "Sharpen Amount",
+    sharpen_radius => "Sharpen Radius",
+    vignette_amount => "Vignette",
+    distortion => "Distortion",
+    rotation => "Straighten",
+    crop_x => "Crop Left",
+    crop_y => "Crop Top",
+    crop_w => "Crop Width",
+    crop_h => "Crop Height",
+}
+
+pub fn find(key: &str) -> Option<&'static EditParam> {
+    PARAMS.iter().find(|p| p.key == key)
+}
+
+/// A parameter whose value differs between the two sides.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ParamDiff {
+    pub param: &'static EditParam,
+    pub left: f32,
+    pub right: f32,
+}
+
+/// List the parameters that differ between `left` and `right`.
+pub fn diff(left: &EditParams, right: &EditParams) -> Vec<ParamDiff> {
+    PARAMS
+        .iter()
+        .filter_map(|param| {
+            let (l, r) = (param.value(left), param.value(right));
+            ((l - r).abs() > EPSILON).then_some(ParamDiff {
+                param,
+                left: l,
+                right: r,
+            })
+        })
+        .collect()
+}
+
+/// Copy one parameter from `source` into `target`. Returns `false` if `key`
+/// doesn't name a parameter.
+pub fn take(target: &mut EditParams, source: &EditParams, key: &str) -> bool {
+    let Some(param) = find(key) else {
+        return false;
+    };
+    param.set(target, param.value(source));
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn identical_params_have_no_diff() {
+        let p = EditParams::default();
+        assert!(diff(&p, &p).is_empty());
+    }
+
+    #[test]
+    fn diff_lists_changed_params_in_panel_order() {
+        let left = EditParams {
+            exposure: 1.0,
+            crop_w: 0.5,
+            ..EditParams::default()
+        };
+        let right = EditParams {
+            saturation: 20.0,
+            ..EditParams::default()
+        };
+        let keys: Vec<_> = diff(&left, &right).iter().map(|d| d.param.key).collect();
+        assert_eq!(keys, ["exposure", "saturation", "crop_w"]);
+
+        let exposure = diff(&left, &right)[0];
+        assert_eq!(exposure.left, 1.0);
+        assert_eq!(exposure.right, 0.0);
+    }
+
+    #[test]
+    fn tiny_differences_are_ignored() {
+        let left = EditParams::default();
+        let right = EditParams {
+            exposure: 0.00001,
+            ..EditParams::default()
+        };
+        assert!(diff(&left, &right).is_empty());
+    }
+
+    #[test]
+    fn take_copies_a_single_param() {
+        let mut target = EditParams::default();
+        let source = EditParams {
+            contrast: 30.0,
+            shadows: 15.0,
+            ..EditParams::default()
+        };
+        assert!(take(&mut target, &source, "contrast"));
+        assert_eq!(target.contrast, 30.0);
+        assert_eq!(target.shadows, 0.0);
+        assert!(!take(&mut target, &source, "nope"));
+    }
+
+    #[test]
+    fn every_param_round_trips_through_accessors() {
+        let mut params = EditParams::default();
+        for (i, param) in PARAMS.iter().enumerate() {
+            param.set(&mut params, i as f32 + 100.0);
+        }
+        for (i, param) in PARAMS.iter().enumerate() {
+            assert_eq!(param.value(&params), i as f32 + 100.0, "{}", param.key);
+        }
+        assert_eq!(diff(&params, &EditParams::default()).len(), PARAMS.len());
+    }
+}
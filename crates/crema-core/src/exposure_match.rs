@@ -0,0 +1,73 @@
+//! Exposure matching across a series. Exposure is a plain linear gain, so
+//! two photos match in brightness when their decoded median luminance,
+//! scaled by their exposure settings, is the same.
+
+use crate::image_buf::ImageBuf;
+
+/// Exposure slider range; matched values are clamped to it.
+const EXPOSURE_RANGE: (f32, f32) = (-5.0, 5.0);
+/// Medians below this are too dark to measure, such as a lens-cap frame,
+/// and are left alone rather than boosted by the full range.
+const MIN_MEDIAN: f32 = 1e-4;
+
+/// Median linear luminance of `buf`, before any edit.
+pub fn median_luminance(buf: &ImageBuf) -> f32 {
+    let mut luma: Vec<f32> = buf
+        .data
+        .chunks_exact(3)
+        .map(|p| 0.2126 * p[0] + 0.7152 * p[1] + 0.0722 * p[2])
+        .collect();
+    if luma.is_empty() {
+        return 0.0;
+    }
+    let mid = luma.len() / 2;
+    *luma.select_nth_unstable_by(mid, f32::total_cmp).1
+}
+
+/// The exposure that brings a photo with `median` luminance to the
+/// brightness of a reference with `reference_median` at
+/// `reference_exposure`. `None` when either photo is too dark to measure.
+pub fn matched_exposure(
+    reference_median: f32,
+    reference_exposure: f32,
+    median: f32,
+) -> Option<f32> {
+    if reference_median < MIN_MEDIAN || median < MIN_MEDIAN {
+        return None;
+    }
+    let exposure = reference_exposure + (reference_median / median).log2();
+    Some(exposure.clamp(EXPOSURE_RANGE.0, EXPOSURE_RANGE.1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn flat(v: f32) -> ImageBuf {
+        ImageBuf::from_data(4, 4, vec![v; 48]).unwrap()
+    }
+
+    #[test]
+    fn median_ignores_a_few_bright_pixels() {
+        let mut buf = flat(0.1);
+        buf.data[..6].copy_from_slice(&[1.0; 6]);
+        assert!((median_luminance(&buf) - 0.1).abs() < 1e-6);
+    }
+
+    #[test]
+    fn matches_a_darker_frame_to_the_reference() {
+        let reference = median_luminance(&flat(0.2));
+        let darker = median_luminance(&flat(0.05));
+        let ev = matched_exposure(reference, 0.5, darker).unwrap();
+        assert!((ev - 2.5).abs() < 1e-5, "{ev}");
+        // The reference itself is unchanged.
+        assert_eq!(matched_exposure(reference, 0.5, reference), Some(0.5));
+    }
+
+    #[test]
+    fn clamps_and_skips_unmeasurable_frames() {
+        assert_eq!(matched_exposure(0.5, 0.0, 0.001), Some(5.0));
+        assert_eq!(matched_exposure(0.5, 0.0, 0.0), None);
+        assert_eq!(matched_exposure(0.0, 0.0, 0.5), None);
+    }
+}
@@ -0,0 +1,162 @@
+//! Flat-field correction. A calibration frame of an evenly lit surface,
+//! shot with a given camera and lens, records the lens's falloff and
+//! color cast; dividing it out of later photos leaves an even field.
+
+use anyhow::{Result, bail};
+use serde::{Deserialize, Serialize};
+
+use crate::image_buf::ImageBuf;
+
+/// Longest edge of the stored gain grid. Falloff and cast are smooth, so a
+/// coarse grid interpolated at apply time loses nothing and keeps the
+/// catalog entry small.
+const GRID_EDGE: u32 = 64;
+/// Gains are capped so a dark corner in a bad calibration frame can't blow
+/// out the photo.
+const MAX_GAIN: f32 = 8.0;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FlatField {
+    pub width: u32,
+    pub height: u32,
+    /// Per-cell RGB multipliers, row-major, 1.0 at the frame center.
+    pub gains: Vec<f32>,
+}
+
+impl FlatField {
+    /// Build the gain grid from a flat frame. Each channel is normalized to
+    /// its own center, so the frame's overall color doesn't shift the
+    /// white balance of corrected photos; only variation across the frame
+    /// is removed.
+    pub fn from_frame(flat: &ImageBuf) -> Result<Self> {
+        if flat.width < 8 || flat.height < 8 {
+            bail!("flat frame is too small");
+        }
+        let grid = flat.downsample(GRID_EDGE);
+        let (w, h) = (grid.width, grid.height);
+
+        let mut center = [0.0f32; 3];
+        let (cx, cy) = (w / 2, h / 2);
+        let mut count = 0.0;
+        for y in cy.saturating_sub(1)..(cy + 2).min(h) {
+            for x in cx.saturating_sub(1)..(cx + 2).min(w) {
+                let i = ((y * w + x) * 3) as usize;
+                for (sum, v) in center.iter_mut().zip(&grid.data[i..i + 3]) {
+                    *sum += v;
+                }
+                count += 1.0;
+            }
+        }
+        for c in &mut center {
+            *c /= count;
+        }
+        if center.iter().any(|&c| c < 0.02) {
+            bail!("flat frame is too dark; expose the even surface to mid-gray");
+        }
+        if center.iter().any(|&c| c > 0.95) {
+            bail!("flat frame is clipped; expose the even surface to mid-gray");
+        }
+
+        let gains = grid
+            .data
+            .chunks_exact(3)
+            .flat_map(|px| {
+                (0..3).map(move |c| (center[c] / px[c].max(1e-6)).clamp(1.0 / MAX_GAIN, MAX_GAIN))
+            })
+            .collect();
+        Ok(Self {
+            width: w,
+            height: h,
+            gains,
+        })
+    }
+
+    /// Multiply every pixel by the gain interpolated at its position.
+    pub fn apply(&self, buf: &mut ImageBuf) {
+        if self.width == 0 || self.height == 0 || buf.width == 0 || buf.height == 0 {
+            return;
+        }
+        let (w, h) = (buf.width, buf.height);
+        let sx = self.width as f32 / w as f32;
+        let sy = self.height as f32 / h as f32;
+        for y in 0..h {
+            let gy = ((y as f32 + 0.5) * sy - 0.5).clamp(0.0, (self.height - 1) as f32);
+            for x in 0..w {
+                let gx = ((x as f32 + 0.5) * sx - 0.5).clamp(0.0, (self.width - 1) as f32);
+                let gain = self.sample(gx, gy);
+                let i = ((y * w + x) * 3) as usize;
+                for (v, g) in buf.data[i..i + 3].iter_mut().zip(gain) {
+                    *v *= g;
+                }
+            }
+        }
+    }
+
+    fn sample(&self, x: f32, y: f32) -> [f32; 3] {
+        let (x0, y0) = (x.floor() as u32, y.floor() as u32);
+        let (x1, y1) = ((x0 + 1).min(self.width - 1), (y0 + 1).min(self.height - 1));
+        let (fx, fy) = (x - x0 as f32, y - y0 as f32);
+        let at = |x: u32, y: u32, c: usize| self.gains[((y * self.width + x) * 3) as usize + c];
+        std::array::from_fn(|c| {
+            let top = at(x0, y0, c) + (at(x1, y0, c) - at(x0, y0, c)) * fx;
+            let bottom = at(x0, y1, c) + (at(x1, y1, c) - at(x0, y1, c)) * fx;
+            top + (bottom - top) * fy
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An even gray surface with radial falloff and a green cast towards
+    /// the edges.
+    fn vignetted(w: u32, h: u32, level: f32) -> ImageBuf {
+        let mut data = Vec::with_capacity((w * h * 3) as usize);
+        for y in 0..h {
+            for x in 0..w {
+                let dx = x as f32 / w as f32 - 0.5;
+                let dy = y as f32 / h as f32 - 0.5;
+                let falloff = 1.0 - (dx * dx + dy * dy);
+                data.extend([
+                    level * falloff,
+                    level * falloff * (1.0 + dx.abs()),
+                    level * falloff,
+                ]);
+            }
+        }
+        ImageBuf::from_data(w, h, data).unwrap()
+    }
+
+    #[test]
+    fn corrected_flat_is_even() {
+        let field = FlatField::from_frame(&vignetted(64, 48, 0.5)).unwrap();
+        let mut photo = vignetted(64, 48, 0.3);
+        field.apply(&mut photo);
+        let center = photo.data[((24 * 64 + 32) * 3) as usize];
+        for px in photo.data.chunks_exact(3) {
+            for &v in px {
+                assert!((v - center).abs() < 0.01, "{v} vs {center}");
+            }
+        }
+    }
+
+    #[test]
+    fn applies_to_any_resolution() {
+        let field = FlatField::from_frame(&vignetted(256, 128, 0.5)).unwrap();
+        assert!(field.width <= GRID_EDGE && field.height <= GRID_EDGE);
+
+        let mut photo = vignetted(40, 20, 0.4);
+        let corner_before = photo.data[0];
+        field.apply(&mut photo);
+        assert!(photo.data[0] > corner_before);
+    }
+
+    #[test]
+    fn rejects_dark_and_clipped_frames() {
+        let dark = ImageBuf::from_data(16, 16, vec![0.001; 16 * 16 * 3]).unwrap();
+        assert!(FlatField::from_frame(&dark).is_err());
+        let clipped = ImageBuf::from_data(16, 16, vec![1.0; 16 * 16 * 3]).unwrap();
+        assert!(FlatField::from_frame(&clipped).is_err());
+    }
+}
@@ -0,0 +1,111 @@
+//! The arithmetic the pipeline's color math is written against, so one
+//! implementation serves both the `f32` path that ships and the `f64`
+//! reference the precision audit compares it with.
+
+use std::ops::{Add, Div, Mul, MulAssign, Neg, Rem, Sub};
+
+pub trait Float:
+    Copy
+    + PartialOrd
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + Div<Output = Self>
+    + Neg<Output = Self>
+    + Rem<Output = Self>
+    + MulAssign
+{
+    /// The constant `v`. Constants are written as `f32` so the `f32` path
+    /// computes exactly what it did before it was generic; the `f64`
+    /// reference then differs only in the precision of its arithmetic,
+    /// which is what the audit measures.
+    fn of(v: f32) -> Self;
+    fn to_f64(self) -> f64;
+    fn powf(self, n: Self) -> Self;
+    fn powi(self, n: i32) -> Self;
+    fn sqrt(self) -> Self;
+    fn cbrt(self) -> Self;
+    fn abs(self) -> Self;
+    fn signum(self) -> Self;
+    fn sin(self) -> Self;
+    fn cos(self) -> Self;
+    fn to_radians(self) -> Self;
+    fn rem_euclid(self, rhs: Self) -> Self;
+    fn max(self, other: Self) -> Self;
+    fn min(self, other: Self) -> Self;
+    fn clamp(self, min: Self, max: Self) -> Self;
+}
+
+macro_rules! impl_float {
+    ($t:ty) => {
+        impl Float for $t {
+            fn of(v: f32) -> Self {
+                v as $t
+            }
+
+            fn to_f64(self) -> f64 {
+                self as f64
+            }
+
+            fn powf(self, n: Self) -> Self {
+                <$t>::powf(self, n)
+            }
+
+            fn powi(self, n: i32) -> Self {
+                <$t>::powi(self, n)
+            }
+
+            fn sqrt(self) -> Self {
+                <$t>::sqrt(self)
+            }
+
+            fn cbrt(self) -> Self {
+                <$t>::cbrt(self)
+            }
+
+            fn abs(self) -> Self {
+                <$t>::abs(self)
+            }
+
+            fn signum(self) -> Self {
+                <$t>::signum(self)
+            }
+
+            fn sin(self) -> Self {
+                <$t>::sin(self)
+            }
+
+            fn cos(self) -> Self {
+                <$t>::cos(self)
+            }
+
+            fn to_radians(self) -> Self {
+                <$t>::to_radians(self)
+            }
+
+            fn rem_euclid(self, rhs: Self) -> Self {
+                <$t>::rem_euclid(self, rhs)
+            }
+
+            fn max(self, other: Self) -> Self {
+                <$t>::max(self, other)
+            }
+
+            fn min(self, other: Self) -> Self {
+                <$t>::min(self, other)
+            }
+
+            fn clamp(self, min: Self, max: Self) -> Self {
+                <$t>::clamp(self, min, max)
+            }
+        }
+    };
+}
+
+impl_float!(f32);
+impl_float!(f64);
+
+/// Rec. 709 luminance of linear RGB.
+pub fn luma<T: Float>(r: T, g: T, b: T) -> T {
+    T::of(0.2126) * r + T::of(0.7152) * g + T::of(0.0722) * b
+}
@@ -0,0 +1,104 @@
+//! Per-channel histograms of displayed 8-bit pixels, as drawn in the
+//! develop panel and checked by the display chain tests.
+
+pub const NUM_BINS: usize = 256;
+
+#[derive(Clone, Debug)]
+pub struct HistogramData {
+    pub r: [u32; NUM_BINS],
+    pub g: [u32; NUM_BINS],
+    pub b: [u32; NUM_BINS],
+    pub max_count: u32,
+}
+
+impl HistogramData {
+    pub fn from_rgba_u8(pixels: &[u8]) -> Self {
+        let mut r = [0u32; NUM_BINS];
+        let mut g = [0u32; NUM_BINS];
+        let mut b = [0u32; NUM_BINS];
+
+        for pixel in pixels.chunks_exact(4) {
+            r[pixel[0] as usize] += 1;
+            g[pixel[1] as usize] += 1;
+            b[pixel[2] as usize] += 1;
+        }
+
+        let max_count = r
+            .iter()
+            .chain(g.iter())
+            .chain(b.iter())
+            .copied()
+            .max()
+            .unwrap_or(0)
+            .max(1);
+
+        Self { r, g, b, max_count }
+    }
+
+    /// True when any channel has pixels crushed to pure black.
+    pub fn clips_shadows(&self) -> bool {
+        self.r[0] > 0 || self.g[0] > 0 || self.b[0] > 0
+    }
+
+    /// True when any channel has pixels blown to pure white.
+    pub fn clips_highlights(&self) -> bool {
+        let last = NUM_BINS - 1;
+        self.r[last] > 0 || self.g[last] > 0 || self.b[last] > 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn histogram_from_solid_color() {
+        // 2x2 image, all red (255,0,0,255)
+        let pixels = vec![
+            255, 0, 0, 255, 255, 0, 0, 255, 255, 0, 0, 255, 255, 0, 0, 255,
+        ];
+        let hist = HistogramData::from_rgba_u8(&pixels);
+        assert_eq!(hist.r[255], 4);
+        assert_eq!(hist.r[0], 0);
+        assert_eq!(hist.g[0], 4);
+        assert_eq!(hist.b[0], 4);
+        assert_eq!(hist.max_count, 4);
+    }
+
+    #[test]
+    fn histogram_empty_image() {
+        let pixels: Vec<u8> = Vec::new();
+        let hist = HistogramData::from_rgba_u8(&pixels);
+        assert_eq!(hist.max_count, 1); // clamped to 1 to avoid div-by-zero
+    }
+
+    #[test]
+    fn histogram_gradient() {
+        // 256 pixels, each with incrementing R value
+        let mut pixels = Vec::with_capacity(256 * 4);
+        for i in 0..=255u8 {
+            pixels.extend_from_slice(&[i, 128, 0, 255]);
+        }
+        let hist = HistogramData::from_rgba_u8(&pixels);
+        for i in 0..256 {
+            assert_eq!(hist.r[i], 1);
+        }
+        assert_eq!(hist.g[128], 256);
+        assert_eq!(hist.b[0], 256);
+    }
+
+    #[test]
+    fn clipping_flags() {
+        let mid = HistogramData::from_rgba_u8(&[128, 128, 128, 255]);
+        assert!(!mid.clips_shadows());
+        assert!(!mid.clips_highlights());
+
+        let black = HistogramData::from_rgba_u8(&[0, 40, 40, 255]);
+        assert!(black.clips_shadows());
+        assert!(!black.clips_highlights());
+
+        let white = HistogramData::from_rgba_u8(&[40, 40, 255, 255]);
+        assert!(!white.clips_shadows());
+        assert!(white.clips_highlights());
+    }
+}
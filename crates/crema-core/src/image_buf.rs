@@ -142,7 +142,7 @@ static SRGB_LUT: LazyLock<[u8; SRGB_LUT_SIZE]> = LazyLock::new(|| {
     lut
 });
 
-fn linear_to_srgb_u8(v: f32) -> u8 {
+pub(crate) fn linear_to_srgb_u8(v: f32) -> u8 {
     let v = v.clamp(0.0, 1.0);
     let idx_f = v * (SRGB_LUT_SIZE - 1) as f32;
     let i0 = (idx_f as usize).min(SRGB_LUT_SIZE - 2);
@@ -1,4 +1,21 @@
+pub mod annotation;
+pub mod bitmap_font;
 pub mod color;
+pub mod color_range;
+pub mod comparison;
+pub mod compositing;
+pub mod dark_frame;
+pub mod defects;
+pub mod dither;
+pub mod edit_diff;
+pub mod exposure_match;
+pub mod flat_field;
+pub mod float;
+pub mod histogram;
 pub mod image_buf;
 pub mod pipeline;
+pub mod preset;
+pub mod preview;
 pub mod raw;
+pub mod render_diff;
+pub mod scan_border;
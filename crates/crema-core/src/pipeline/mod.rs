@@ -1,9 +1,13 @@
 pub mod auto_enhance;
 pub mod module;
 pub mod modules;
+pub mod precision;
 
 pub use modules::tone_curve::build_lut as tone_curve_lut;
 
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
 use anyhow::Result;
 use tracing::debug;
 
@@ -50,6 +54,113 @@ impl Pipeline {
         }
         Ok(current)
     }
+
+    /// Run the full CPU pipeline on up to `threads` threads, for full
+    /// resolution renders. Runs of pointwise modules are applied band by
+    /// band in parallel; the rest run as they do in [`Pipeline::process_cpu`],
+    /// so the output is identical. `progress` receives the fraction done,
+    /// and once `cancel` is set the render stops early and returns `None`.
+    pub fn process_cpu_parallel(
+        &self,
+        input: ImageBuf,
+        params: &EditParams,
+        threads: usize,
+        cancel: &AtomicBool,
+        progress: &(dyn Fn(f32) + Sync),
+    ) -> Result<Option<ImageBuf>> {
+        let total = self.modules.len() as f32;
+        let mut current = input;
+        let mut done = 0;
+        while done < self.modules.len() {
+            if cancel.load(Ordering::Relaxed) {
+                return Ok(None);
+            }
+            let pointwise = self.modules[done..]
+                .iter()
+                .take_while(|m| m.is_pointwise())
+                .count();
+            if pointwise == 0 {
+                let module = &self.modules[done];
+                debug!(module = module.name(), "processing");
+                current = module.process_cpu(current, params)?;
+                done += 1;
+            } else {
+                let run = &self.modules[done..done + pointwise];
+                let base = done as f32;
+                let report = |share: f32| progress((base + share * run.len() as f32) / total);
+                if !process_bands(&mut current, run, params, threads, cancel, &report)? {
+                    return Ok(None);
+                }
+                done += pointwise;
+            }
+            progress(done as f32 / total);
+        }
+        Ok(Some(current))
+    }
+}
+
+/// Rows per band when splitting pointwise work across threads: small enough
+/// to balance the load and report progress often, large enough that each
+/// band amortizes its setup.
+const BAND_ROWS: usize = 64;
+
+/// Apply pointwise `modules` to `buf` in place, one band of rows at a time
+/// across `threads` workers. Returns false if cancelled part way.
+fn process_bands(
+    buf: &mut ImageBuf,
+    modules: &[Box<dyn ProcessingModule>],
+    params: &EditParams,
+    threads: usize,
+    cancel: &AtomicBool,
+    progress: &(dyn Fn(f32) + Sync),
+) -> Result<bool> {
+    let width = buf.width;
+    let band_len = BAND_ROWS * width as usize * 3;
+    if band_len == 0 || buf.data.is_empty() {
+        return Ok(true);
+    }
+    let band_count = buf.data.len().div_ceil(band_len);
+    let bands = Mutex::new(buf.data.chunks_mut(band_len));
+    let finished = AtomicUsize::new(0);
+    let failure = Mutex::new(None);
+    let failed = AtomicBool::new(false);
+
+    std::thread::scope(|scope| {
+        for _ in 0..threads.clamp(1, band_count) {
+            scope.spawn(|| {
+                loop {
+                    if cancel.load(Ordering::Relaxed) || failed.load(Ordering::Relaxed) {
+                        return;
+                    }
+                    let Some(band) = bands.lock().unwrap().next() else {
+                        return;
+                    };
+                    let rows = (band.len() / (width as usize * 3)) as u32;
+                    let tile = ImageBuf::from_data(width, rows, band.to_vec()).and_then(|tile| {
+                        modules
+                            .iter()
+                            .try_fold(tile, |tile, module| module.process_cpu(tile, params))
+                    });
+                    let tile = match tile {
+                        Ok(tile) => tile,
+                        Err(e) => {
+                            *failure.lock().unwrap() = Some(e);
+                            failed.store(true, Ordering::Relaxed);
+                            return;
+                        }
+                    };
+                    band.copy_from_slice(&tile.data);
+                    let finished = finished.fetch_add(1, Ordering::Relaxed) + 1;
+                    progress(finished as f32 / band_count as f32);
+                }
+            });
+        }
+    });
+
+    if let Some(e) = failure.into_inner().unwrap() {
+        return Err(e);
+    }
+    Ok(finished.into_inner() == band_count)
 }
 
 impl Default for Pipeline {
@@ -145,4 +256,51 @@ mod tests {
             ]
         );
     }
+
+    #[test]
+    fn parallel_render_matches_sequential() {
+        let pipeline = Pipeline::new();
+        let (w, h) = (37, 150);
+        let data = (0..w * h * 3)
+            .map(|i| (i % 97) as f32 / 96.0)
+            .collect::<Vec<_>>();
+        let input = ImageBuf::from_data(w, h, data).unwrap();
+        let params = EditParams {
+            exposure: 0.7,
+            wb_temp: 4500.0,
+            contrast: 20.0,
+            vibrance: 30.0,
+            sharpen_amount: 40.0,
+            crop_w: 0.8,
+            ..Default::default()
+        };
+        let expected = pipeline.process_cpu(input.clone(), &params).unwrap();
+
+        let reported = Mutex::new(Vec::new());
+        let output = pipeline
+            .process_cpu_parallel(input, &params, 4, &AtomicBool::new(false), &|f| {
+                reported.lock().unwrap().push(f)
+            })
+            .unwrap()
+            .unwrap();
+        assert_eq!(output.width, expected.width);
+        assert_eq!(output.data, expected.data);
+        let reported = reported.into_inner().unwrap();
+        assert_eq!(reported.last(), Some(&1.0));
+        assert!(reported.iter().all(|f| (0.0..=1.0).contains(f)));
+    }
+
+    #[test]
+    fn cancelled_render_returns_none() {
+        let pipeline = Pipeline::new();
+        let params = EditParams {
+            exposure: 1.0,
+            ..Default::default()
+        };
+        let cancel = AtomicBool::new(true);
+        let output = pipeline
+            .process_cpu_parallel(test_image(), &params, 2, &cancel, &|_| {})
+            .unwrap();
+        assert!(output.is_none());
+    }
 }
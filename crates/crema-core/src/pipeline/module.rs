@@ -6,4 +6,17 @@ use crate::image_buf::{EditParams, ImageBuf};
 pub trait ProcessingModule: Send + Sync {
     fn name(&self) -> &str;
     fn process_cpu(&self, input: ImageBuf, params: &EditParams) -> Result<ImageBuf>;
+
+    /// Whether each output pixel depends only on the same input pixel, so
+    /// the image can be split into bands and processed on several threads.
+    fn is_pointwise(&self) -> bool {
+        false
+    }
+
+    /// Double-precision reference for the precision audit, applied in place
+    /// to interleaved RGB. Returns false when the module has no reference,
+    /// in which case the audit runs its `f32` path instead.
+    fn process_f64(&self, _data: &mut [f64], _params: &EditParams) -> bool {
+        false
+    }
 }
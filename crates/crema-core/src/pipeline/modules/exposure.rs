@@ -1,5 +1,6 @@
 use anyhow::Result;
 
+use crate::float::Float;
 use crate::image_buf::{EditParams, ImageBuf};
 use crate::pipeline::module::ProcessingModule;
 
@@ -10,17 +11,30 @@ impl ProcessingModule for Exposure {
         "exposure"
     }
 
+    fn is_pointwise(&self) -> bool {
+        true
+    }
+
     fn process_cpu(&self, mut input: ImageBuf, params: &EditParams) -> Result<ImageBuf> {
         if params.exposure == 0.0 {
             return Ok(input);
         }
 
-        let multiplier = 2.0_f32.powf(params.exposure);
-        for v in &mut input.data {
-            *v *= multiplier;
-        }
+        expose(&mut input.data, params);
         Ok(input)
     }
+
+    fn process_f64(&self, data: &mut [f64], params: &EditParams) -> bool {
+        expose(data, params);
+        true
+    }
+}
+
+fn expose<T: Float>(data: &mut [T], params: &EditParams) {
+    let multiplier = T::of(2.0).powf(T::of(params.exposure));
+    for v in data {
+        *v *= multiplier;
+    }
 }
 
 #[cfg(test)]
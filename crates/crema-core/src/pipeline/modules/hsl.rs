@@ -1,5 +1,6 @@
 use anyhow::Result;
 
+use crate::float::{Float, luma};
 use crate::image_buf::{EditParams, ImageBuf};
 use crate::pipeline::module::ProcessingModule;
 
@@ -10,64 +11,78 @@ impl ProcessingModule for Hsl {
         "hsl"
     }
 
+    fn is_pointwise(&self) -> bool {
+        true
+    }
+
     fn process_cpu(&self, mut input: ImageBuf, params: &EditParams) -> Result<ImageBuf> {
         if params.hsl_hue == 0.0 && params.hsl_saturation == 0.0 && params.hsl_lightness == 0.0 {
             return Ok(input);
         }
 
-        let do_hue = params.hsl_hue != 0.0;
-        let do_sat = params.hsl_saturation != 0.0;
-        let do_light = params.hsl_lightness != 0.0;
-
-        // Precompute hue rotation matrix (Rodrigues' rotation around luminance axis)
-        let hue_matrix = if do_hue {
-            Some(hue_rotation_matrix(params.hsl_hue))
-        } else {
-            None
-        };
-
-        let sat_blend = 1.0 + params.hsl_saturation / 100.0;
-        let light_scale = 1.0 + params.hsl_lightness / 100.0;
+        adjust(&mut input.data, params);
+        Ok(input)
+    }
 
-        for pixel in input.data.chunks_exact_mut(3) {
-            let (mut r, mut g, mut b) = (pixel[0], pixel[1], pixel[2]);
+    fn process_f64(&self, data: &mut [f64], params: &EditParams) -> bool {
+        adjust(data, params);
+        true
+    }
+}
 
-            // Hue rotation
-            if let Some(m) = &hue_matrix {
-                let nr = m[0] * r + m[1] * g + m[2] * b;
-                let ng = m[3] * r + m[4] * g + m[5] * b;
-                let nb = m[6] * r + m[7] * g + m[8] * b;
-                r = nr;
-                g = ng;
-                b = nb;
-            }
+fn adjust<T: Float>(data: &mut [T], params: &EditParams) {
+    let do_hue = params.hsl_hue != 0.0;
+    let do_sat = params.hsl_saturation != 0.0;
+    let do_light = params.hsl_lightness != 0.0;
+
+    // Precompute hue rotation matrix (Rodrigues' rotation around luminance axis)
+    let hue_matrix = if do_hue {
+        Some(hue_rotation_matrix(T::of(params.hsl_hue)))
+    } else {
+        None
+    };
+
+    let hundred = T::of(100.0);
+    let sat_blend = T::of(1.0) + T::of(params.hsl_saturation) / hundred;
+    let light_scale = T::of(1.0) + T::of(params.hsl_lightness) / hundred;
+    let zero = T::of(0.0);
+
+    for pixel in data.chunks_exact_mut(3) {
+        let (mut r, mut g, mut b) = (pixel[0], pixel[1], pixel[2]);
+
+        // Hue rotation
+        if let Some(m) = &hue_matrix {
+            let nr = m[0] * r + m[1] * g + m[2] * b;
+            let ng = m[3] * r + m[4] * g + m[5] * b;
+            let nb = m[6] * r + m[7] * g + m[8] * b;
+            r = nr;
+            g = ng;
+            b = nb;
+        }
 
-            // Saturation (blend toward luminance)
-            if do_sat {
-                let y = 0.2126 * r + 0.7152 * g + 0.0722 * b;
-                r = y + sat_blend * (r - y);
-                g = y + sat_blend * (g - y);
-                b = y + sat_blend * (b - y);
-            }
+        // Saturation (blend toward luminance)
+        if do_sat {
+            let y = luma(r, g, b);
+            r = y + sat_blend * (r - y);
+            g = y + sat_blend * (g - y);
+            b = y + sat_blend * (b - y);
+        }
 
-            // Lightness (scale luminance, redistribute preserving ratios)
-            if do_light {
-                let y = 0.2126 * r + 0.7152 * g + 0.0722 * b;
-                if y > 0.0 {
-                    let target_y = y * light_scale;
-                    let scale = target_y / y;
-                    r *= scale;
-                    g *= scale;
-                    b *= scale;
-                }
+        // Lightness (scale luminance, redistribute preserving ratios)
+        if do_light {
+            let y = luma(r, g, b);
+            if y > zero {
+                let target_y = y * light_scale;
+                let scale = target_y / y;
+                r *= scale;
+                g *= scale;
+                b *= scale;
             }
-
-            pixel[0] = r.max(0.0);
-            pixel[1] = g.max(0.0);
-            pixel[2] = b.max(0.0);
         }
 
-        Ok(input)
+        pixel[0] = r.max(zero);
+        pixel[1] = g.max(zero);
+        pixel[2] = b.max(zero);
     }
 }
 
@@ -75,17 +90,18 @@ impl ProcessingModule for Hsl {
 ///
 /// Uses Rodrigues' rotation formula with the luminance vector (0.2126, 0.7152, 0.0722)
 /// normalized as the rotation axis.
-fn hue_rotation_matrix(degrees: f32) -> [f32; 9] {
+fn hue_rotation_matrix<T: Float>(degrees: T) -> [T; 9] {
     let angle = degrees.to_radians();
     let cos_a = angle.cos();
     let sin_a = angle.sin();
 
     // Luminance axis (normalized)
-    let len = (0.2126_f32.powi(2) + 0.7152_f32.powi(2) + 0.0722_f32.powi(2)).sqrt();
-    let (kx, ky, kz) = (0.2126 / len, 0.7152 / len, 0.0722 / len);
+    let (wr, wg, wb) = (T::of(0.2126), T::of(0.7152), T::of(0.0722));
+    let len = (wr.powi(2) + wg.powi(2) + wb.powi(2)).sqrt();
+    let (kx, ky, kz) = (wr / len, wg / len, wb / len);
 
     // Rodrigues: R = I*cos(a) + (1-cos(a))*K*Kt + sin(a)*K_cross
-    let one_minus_cos = 1.0 - cos_a;
+    let one_minus_cos = T::of(1.0) - cos_a;
     [
         cos_a + one_minus_cos * kx * kx,
         one_minus_cos * kx * ky - sin_a * kz,
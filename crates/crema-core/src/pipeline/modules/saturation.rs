@@ -1,5 +1,6 @@
 use anyhow::Result;
 
+use crate::float::{Float, luma};
 use crate::image_buf::{EditParams, ImageBuf};
 use crate::pipeline::module::ProcessingModule;
 
@@ -10,21 +11,34 @@ impl ProcessingModule for Saturation {
         "saturation"
     }
 
+    fn is_pointwise(&self) -> bool {
+        true
+    }
+
     fn process_cpu(&self, mut input: ImageBuf, params: &EditParams) -> Result<ImageBuf> {
         if params.saturation == 0.0 {
             return Ok(input);
         }
 
-        let strength = params.saturation / 100.0;
-        let blend = 1.0 + strength;
-        for pixel in input.data.chunks_exact_mut(3) {
-            let y = 0.2126 * pixel[0] + 0.7152 * pixel[1] + 0.0722 * pixel[2];
-            pixel[0] = (y + blend * (pixel[0] - y)).max(0.0);
-            pixel[1] = (y + blend * (pixel[1] - y)).max(0.0);
-            pixel[2] = (y + blend * (pixel[2] - y)).max(0.0);
-        }
+        saturate(&mut input.data, params);
         Ok(input)
     }
+
+    fn process_f64(&self, data: &mut [f64], params: &EditParams) -> bool {
+        saturate(data, params);
+        true
+    }
+}
+
+fn saturate<T: Float>(data: &mut [T], params: &EditParams) {
+    let strength = T::of(params.saturation) / T::of(100.0);
+    let blend = T::of(1.0) + strength;
+    for pixel in data.chunks_exact_mut(3) {
+        let y = luma(pixel[0], pixel[1], pixel[2]);
+        for v in pixel {
+            *v = (y + blend * (*v - y)).max(T::of(0.0));
+        }
+    }
 }
 
 #[cfg(test)]
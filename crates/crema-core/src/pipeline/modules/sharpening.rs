@@ -133,7 +133,7 @@ mod tests {
         let result = Sharpening.process_cpu(buf, &params).unwrap();
 
         // The pixel just right of the edge should be brighter than original (overshoot)
-        let edge_right = (1 * w + w / 2) * 3;
+        let edge_right = (w + w / 2) * 3;
         assert!(
             result.data[edge_right] > 0.8,
             "sharpening should overshoot at edge: got {}",
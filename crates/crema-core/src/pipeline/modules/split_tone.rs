@@ -1,36 +1,38 @@
 use anyhow::Result;
 
+use crate::float::{Float, luma};
 use crate::image_buf::{EditParams, ImageBuf};
 use crate::pipeline::module::ProcessingModule;
 
 pub struct SplitTone;
 
 /// Convert HSL (h in degrees, s in 0..1, l=0.5) to linear RGB tint offsets.
-fn hsl_to_rgb(hue: f32, sat: f32) -> [f32; 3] {
-    if sat <= 0.0 {
-        return [0.0, 0.0, 0.0];
+fn hsl_to_rgb<T: Float>(hue: T, sat: T) -> [T; 3] {
+    let zero = T::of(0.0);
+    if sat <= zero {
+        return [zero, zero, zero];
     }
-    let h = hue % 360.0;
+    let h = hue % T::of(360.0);
     let c = sat; // chroma = sat * (1 - |2*0.5 - 1|) = sat
-    let h_prime = h / 60.0;
-    let x = c * (1.0 - (h_prime % 2.0 - 1.0).abs());
-
-    let (r1, g1, b1) = match h_prime as u32 {
-        0 => (c, x, 0.0),
-        1 => (x, c, 0.0),
-        2 => (0.0, c, x),
-        3 => (0.0, x, c),
-        4 => (x, 0.0, c),
-        _ => (c, 0.0, x),
+    let h_prime = h / T::of(60.0);
+    let x = c * (T::of(1.0) - (h_prime % T::of(2.0) - T::of(1.0)).abs());
+
+    let (r1, g1, b1) = match h_prime.to_f64() as u32 {
+        0 => (c, x, zero),
+        1 => (x, c, zero),
+        2 => (zero, c, x),
+        3 => (zero, x, c),
+        4 => (x, zero, c),
+        _ => (c, zero, x),
     };
 
-    let m = 0.5 - c / 2.0;
+    let m = T::of(0.5) - c / T::of(2.0);
     [r1 + m, g1 + m, b1 + m]
 }
 
-fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
-    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
-    t * t * (3.0 - 2.0 * t)
+fn smoothstep<T: Float>(edge0: T, edge1: T, x: T) -> T {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(T::of(0.0), T::of(1.0));
+    t * t * (T::of(3.0) - T::of(2.0) * t)
 }
 
 impl ProcessingModule for SplitTone {
@@ -38,50 +40,58 @@ impl ProcessingModule for SplitTone {
         "split_tone"
     }
 
+    fn is_pointwise(&self) -> bool {
+        true
+    }
+
     fn process_cpu(&self, mut input: ImageBuf, params: &EditParams) -> Result<ImageBuf> {
         if params.split_shadow_sat == 0.0 && params.split_highlight_sat == 0.0 {
             return Ok(input);
         }
 
-        let shadow_rgb = hsl_to_rgb(params.split_shadow_hue, params.split_shadow_sat / 100.0);
-        let highlight_rgb = hsl_to_rgb(
-            params.split_highlight_hue,
-            params.split_highlight_sat / 100.0,
-        );
+        tone(&mut input.data, params);
+        Ok(input)
+    }
 
-        // Balance shifts the crossover point. At 0, crossover is 0.5.
-        // Positive balance = more highlight area (crossover moves down).
-        // Negative balance = more shadow area (crossover moves up).
-        let crossover = 0.5 - params.split_balance / 200.0;
-
-        let shadow_strength = params.split_shadow_sat / 100.0;
-        let highlight_strength = params.split_highlight_sat / 100.0;
-
-        for pixel in input.data.chunks_exact_mut(3) {
-            let y = 0.2126 * pixel[0] + 0.7152 * pixel[1] + 0.0722 * pixel[2];
-            let y_clamped = y.clamp(0.0, 1.0);
-
-            // Shadow weight: 1.0 for dark pixels, 0.0 for bright pixels
-            let shadow_w = smoothstep(crossover, 0.0, y_clamped) * shadow_strength;
-            // Highlight weight: 1.0 for bright pixels, 0.0 for dark pixels
-            let highlight_w = smoothstep(crossover, 1.0, y_clamped) * highlight_strength;
-
-            // Tint offset: difference between tint color and neutral gray (0.5)
-            pixel[0] = (pixel[0]
-                + shadow_w * (shadow_rgb[0] - 0.5)
-                + highlight_w * (highlight_rgb[0] - 0.5))
-                .max(0.0);
-            pixel[1] = (pixel[1]
-                + shadow_w * (shadow_rgb[1] - 0.5)
-                + highlight_w * (highlight_rgb[1] - 0.5))
-                .max(0.0);
-            pixel[2] = (pixel[2]
-                + shadow_w * (shadow_rgb[2] - 0.5)
-                + highlight_w * (highlight_rgb[2] - 0.5))
-                .max(0.0);
-        }
+    fn process_f64(&self, data: &mut [f64], params: &EditParams) -> bool {
+        tone(data, params);
+        true
+    }
+}
 
-        Ok(input)
+fn tone<T: Float>(data: &mut [T], params: &EditParams) {
+    let (zero, one, half, hundred) = (T::of(0.0), T::of(1.0), T::of(0.5), T::of(100.0));
+    let shadow_rgb = hsl_to_rgb(
+        T::of(params.split_shadow_hue),
+        T::of(params.split_shadow_sat) / hundred,
+    );
+    let highlight_rgb = hsl_to_rgb(
+        T::of(params.split_highlight_hue),
+        T::of(params.split_highlight_sat) / hundred,
+    );
+
+    // Balance shifts the crossover point. At 0, crossover is 0.5.
+    // Positive balance = more highlight area (crossover moves down).
+    // Negative balance = more shadow area (crossover moves up).
+    let crossover = half - T::of(params.split_balance) / T::of(200.0);
+
+    let shadow_strength = T::of(params.split_shadow_sat) / hundred;
+    let highlight_strength = T::of(params.split_highlight_sat) / hundred;
+
+    for pixel in data.chunks_exact_mut(3) {
+        let y = luma(pixel[0], pixel[1], pixel[2]);
+        let y_clamped = y.clamp(zero, one);
+
+        // Shadow weight: 1.0 for dark pixels, 0.0 for bright pixels
+        let shadow_w = smoothstep(crossover, zero, y_clamped) * shadow_strength;
+        // Highlight weight: 1.0 for bright pixels, 0.0 for dark pixels
+        let highlight_w = smoothstep(crossover, one, y_clamped) * highlight_strength;
+
+        // Tint offset: difference between tint color and neutral gray (0.5)
+        for (c, v) in pixel.iter_mut().enumerate() {
+            *v = (*v + shadow_w * (shadow_rgb[c] - half) + highlight_w * (highlight_rgb[c] - half))
+                .max(zero);
+        }
     }
 }
 
@@ -1,6 +1,7 @@
 use anyhow::Result;
 
 use crate::color::{linear_to_srgb, srgb_to_linear};
+use crate::float::{Float, luma};
 use crate::image_buf::{EditParams, ImageBuf};
 use crate::pipeline::module::ProcessingModule;
 
@@ -17,6 +18,10 @@ impl ProcessingModule for ToneCurve {
         "tone_curve"
     }
 
+    fn is_pointwise(&self) -> bool {
+        true
+    }
+
     fn process_cpu(&self, mut input: ImageBuf, params: &EditParams) -> Result<ImageBuf> {
         if params.contrast == 0.0
             && params.highlights == 0.0
@@ -56,6 +61,30 @@ impl ProcessingModule for ToneCurve {
 
         Ok(input)
     }
+
+    /// Evaluates the curve directly rather than through the LUT, so the
+    /// audit also sees the LUT's interpolation error.
+    fn process_f64(&self, data: &mut [f64], params: &EditParams) -> bool {
+        let shape = CurveShape::<f64>::new(params);
+        let step = 1.0 / (LUT_SIZE - 1) as f64;
+        let top = shape.eval(1.0);
+        let slope = (top - shape.eval(1.0 - step)) / step;
+        for pixel in data.chunks_exact_mut(3) {
+            let y = luma(pixel[0], pixel[1], pixel[2]);
+            if y < 1e-6 {
+                continue;
+            }
+            let scale = if y <= 1.0 {
+                shape.eval(y) / y
+            } else {
+                ((top + slope * (y - 1.0)) / y).max(0.0)
+            };
+            for v in pixel {
+                *v = (*v * scale).max(0.0);
+            }
+        }
+        true
+    }
 }
 
 // ── Zone layout ──────────────────────────────────────────────────────────
@@ -82,57 +111,76 @@ const HIGHLIGHT_HI: f32 = 0.90;
 const BLACKS_HI: f32 = 0.15;
 const FEATHER: f32 = 0.05;
 
-fn build_tone_lut(params: &EditParams) -> [f32; LUT_SIZE] {
-    let contrast = params.contrast / 100.0;
-    let highlights = params.highlights / 100.0;
-    let shadows = params.shadows / 100.0;
-    let blacks = params.blacks / 100.0;
-
-    let shadow_width = SHADOW_HI - SHADOW_LO;
-    let highlight_width = HIGHLIGHT_HI - HIGHLIGHT_LO;
+/// Slider-derived shape of the curve, computed once per frame. Built in
+/// `f32` for the LUT and in `f64` for the precision audit's reference.
+struct CurveShape<T> {
+    contrast: T,
+    highlights: T,
+    shadows: T,
+    blacks: T,
+    shadow_gamma: T,
+    highlight_gamma: T,
+}
 
-    // gamma = 3^(-slider): positive slider -> gamma < 1 -> lift/boost
-    //                       negative slider -> gamma > 1 -> crush/recover
-    let shadow_gamma = 3.0_f32.powf(-shadows);
-    let highlight_gamma = 3.0_f32.powf(-highlights);
+impl<T: Float> CurveShape<T> {
+    fn new(params: &EditParams) -> Self {
+        let hundred = T::of(100.0);
+        let highlights = T::of(params.highlights) / hundred;
+        let shadows = T::of(params.shadows) / hundred;
+        // gamma = 3^(-slider): positive slider -> gamma < 1 -> lift/boost
+        //                       negative slider -> gamma > 1 -> crush/recover
+        Self {
+            contrast: T::of(params.contrast) / hundred,
+            highlights,
+            shadows,
+            blacks: T::of(params.blacks) / hundred,
+            shadow_gamma: T::of(3.0).powf(-shadows),
+            highlight_gamma: T::of(3.0).powf(-highlights),
+        }
+    }
 
-    let mut lut = [0.0_f32; LUT_SIZE];
+    /// Map linear luminance in [0, 1] through the curve, without the
+    /// monotonicity fix-up the LUT applies.
+    fn eval(&self, linear_in: T) -> T {
+        let (zero, one) = (T::of(0.0), T::of(1.0));
+        let (shadow_lo, shadow_hi) = (T::of(SHADOW_LO), T::of(SHADOW_HI));
+        let (highlight_lo, highlight_hi) = (T::of(HIGHLIGHT_LO), T::of(HIGHLIGHT_HI));
+        let (blacks_hi, feather) = (T::of(BLACKS_HI), T::of(FEATHER));
+        let shadow_width = shadow_hi - shadow_lo;
+        let highlight_width = highlight_hi - highlight_lo;
 
-    for (i, entry) in lut.iter_mut().enumerate() {
-        let linear_in = i as f32 / (LUT_SIZE - 1) as f32;
         let t = linear_to_srgb(linear_in);
-
         let mut out = t;
 
         // Shadow zone [SHADOW_LO, SHADOW_HI] with feathered boundaries
-        if t > SHADOW_LO - FEATHER && t < SHADOW_HI + FEATHER && shadows != 0.0 {
-            let n = ((t - SHADOW_LO) / shadow_width).clamp(0.0, 1.0);
-            let shadow_val = SHADOW_LO + n.powf(shadow_gamma) * shadow_width;
+        if t > shadow_lo - feather && t < shadow_hi + feather && self.shadows != zero {
+            let n = ((t - shadow_lo) / shadow_width).clamp(zero, one);
+            let shadow_val = shadow_lo + n.powf(self.shadow_gamma) * shadow_width;
 
-            if t <= SHADOW_LO {
+            if t <= shadow_lo {
                 // Below zone: feather in from identity
-                let blend = smoothstep((t - (SHADOW_LO - FEATHER)) / FEATHER);
-                out = t * (1.0 - blend) + shadow_val * blend;
-            } else if t >= SHADOW_HI {
+                let blend = smoothstep((t - (shadow_lo - feather)) / feather);
+                out = t * (one - blend) + shadow_val * blend;
+            } else if t >= shadow_hi {
                 // Above zone: feather out to identity
-                let blend = smoothstep((t - SHADOW_HI) / FEATHER);
-                out = shadow_val * (1.0 - blend) + t * blend;
+                let blend = smoothstep((t - shadow_hi) / feather);
+                out = shadow_val * (one - blend) + t * blend;
             } else {
                 out = shadow_val;
             }
         }
 
         // Highlight zone [HIGHLIGHT_LO, HIGHLIGHT_HI] with feathered boundaries
-        if t > HIGHLIGHT_LO - FEATHER && t < HIGHLIGHT_HI + FEATHER && highlights != 0.0 {
-            let n = ((t - HIGHLIGHT_LO) / highlight_width).clamp(0.0, 1.0);
-            let highlight_val = HIGHLIGHT_LO + n.powf(highlight_gamma) * highlight_width;
-
-            if t <= HIGHLIGHT_LO {
-                let blend = smoothstep((t - (HIGHLIGHT_LO - FEATHER)) / FEATHER);
-                out = out * (1.0 - blend) + highlight_val * blend;
-            } else if t >= HIGHLIGHT_HI {
-                let blend = smoothstep((t - HIGHLIGHT_HI) / FEATHER);
-                out = highlight_val * (1.0 - blend) + t * blend;
+        if t > highlight_lo - feather && t < highlight_hi + feather && self.highlights != zero {
+            let n = ((t - highlight_lo) / highlight_width).clamp(zero, one);
+            let highlight_val = highlight_lo + n.powf(self.highlight_gamma) * highlight_width;
+
+            if t <= highlight_lo {
+                let blend = smoothstep((t - (highlight_lo - feather)) / feather);
+                out = out * (one - blend) + highlight_val * blend;
+            } else if t >= highlight_hi {
+                let blend = smoothstep((t - highlight_hi) / feather);
+                out = highlight_val * (one - blend) + t * blend;
             } else {
                 out = highlight_val;
             }
@@ -140,8 +188,8 @@ fn build_tone_lut(params: &EditParams) -> [f32; LUT_SIZE] {
 
         // Contrast: slope-based S-curve using x^a / (x^a + (1-x)^a)
         // a=1 is identity, a>1 increases contrast, a<1 decreases contrast
-        if contrast != 0.0 {
-            let a = 3.0_f32.powf(contrast);
+        if self.contrast != zero {
+            let a = T::of(3.0).powf(self.contrast);
             out = s_curve(out, a);
         }
 
@@ -149,22 +197,30 @@ fn build_tone_lut(params: &EditParams) -> [f32; LUT_SIZE] {
         // Positive: lift = proportional offset raising the black point + gamma < 1.
         // Negative: gamma > 1 crushes dark tones toward zero.
         // Inherently monotonic (no post-hoc fix needed for this stage).
-        if blacks != 0.0 && out < BLACKS_HI + FEATHER {
-            let gamma = 3.0_f32.powf(-blacks);
-            let lift = blacks.max(0.0) * 0.10;
-            let range = BLACKS_HI - lift;
-            let n = (out / BLACKS_HI).clamp(0.0, 1.0);
+        if self.blacks != zero && out < blacks_hi + feather {
+            let gamma = T::of(3.0).powf(-self.blacks);
+            let lift = self.blacks.max(zero) * T::of(0.10);
+            let range = blacks_hi - lift;
+            let n = (out / blacks_hi).clamp(zero, one);
             let blacks_val = lift + n.powf(gamma) * range;
 
-            if out >= BLACKS_HI {
-                let blend = smoothstep((out - BLACKS_HI) / FEATHER);
-                out = blacks_val * (1.0 - blend) + out * blend;
+            if out >= blacks_hi {
+                let blend = smoothstep((out - blacks_hi) / feather);
+                out = blacks_val * (one - blend) + out * blend;
             } else {
                 out = blacks_val;
             }
         }
 
-        *entry = srgb_to_linear(out.clamp(0.0, 1.0));
+        srgb_to_linear(out.clamp(zero, one))
+    }
+}
+
+fn build_tone_lut(params: &EditParams) -> [f32; LUT_SIZE] {
+    let shape = CurveShape::<f32>::new(params);
+    let mut lut = [0.0_f32; LUT_SIZE];
+    for (i, entry) in lut.iter_mut().enumerate() {
+        *entry = shape.eval(i as f32 / (LUT_SIZE - 1) as f32);
     }
 
     // Enforce monotonicity (safety net for extreme combined settings)
@@ -182,22 +238,23 @@ fn build_tone_lut(params: &EditParams) -> [f32; LUT_SIZE] {
 /// Properties: f(0)=0, f(1)=1, f(0.5)=0.5, monotonic for a>0.
 /// a=1 is identity; a>1 increases slope at midpoint (contrast boost);
 /// a<1 decreases slope (contrast reduction).
-fn s_curve(x: f32, a: f32) -> f32 {
-    if x <= 0.0 {
-        return 0.0;
+fn s_curve<T: Float>(x: T, a: T) -> T {
+    let (zero, one) = (T::of(0.0), T::of(1.0));
+    if x <= zero {
+        return zero;
     }
-    if x >= 1.0 {
-        return 1.0;
+    if x >= one {
+        return one;
     }
     let xa = x.powf(a);
-    let one_minus_xa = (1.0 - x).powf(a);
+    let one_minus_xa = (one - x).powf(a);
     xa / (xa + one_minus_xa)
 }
 
 /// Hermite smoothstep: 0 at t<=0, 1 at t>=1, smooth in between.
-fn smoothstep(t: f32) -> f32 {
-    let t = t.clamp(0.0, 1.0);
-    t * t * (3.0 - 2.0 * t)
+fn smoothstep<T: Float>(t: T) -> T {
+    let t = t.clamp(T::of(0.0), T::of(1.0));
+    t * t * (T::of(3.0) - T::of(2.0) * t)
 }
 
 fn lut_lerp(lut: &[f32; LUT_SIZE], y: f32) -> f32 {
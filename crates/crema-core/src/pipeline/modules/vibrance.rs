@@ -1,6 +1,7 @@
 use anyhow::Result;
 
 use crate::color::{OKLAB_MAX_CHROMA, linear_srgb_to_oklab};
+use crate::float::{Float, luma};
 use crate::image_buf::{EditParams, ImageBuf};
 use crate::pipeline::module::ProcessingModule;
 
@@ -11,45 +12,59 @@ impl ProcessingModule for Vibrance {
         "vibrance"
     }
 
+    fn is_pointwise(&self) -> bool {
+        true
+    }
+
     fn process_cpu(&self, mut input: ImageBuf, params: &EditParams) -> Result<ImageBuf> {
         if params.vibrance == 0.0 {
             return Ok(input);
         }
 
-        let strength = params.vibrance / 100.0;
-        let sign = strength.signum();
-        for pixel in input.data.chunks_exact_mut(3) {
-            let y = 0.2126 * pixel[0] + 0.7152 * pixel[1] + 0.0722 * pixel[2];
-
-            // OKLab chroma: perceptually uniform saturation metric.
-            let (_, ok_a, ok_b) = linear_srgb_to_oklab(pixel[0], pixel[1], pixel[2]);
-            let chroma = (ok_a * ok_a + ok_b * ok_b).sqrt();
-            let sat = (chroma / OKLAB_MAX_CHROMA).clamp(0.0, 1.0);
-
-            // Selective saturation (SweetFX/ReShade convention):
-            //   positive -> targets low-sat pixels (1 - sat)
-            //   negative -> targets high-sat pixels (1 + sat)
-            let mut effect = (strength * (1.0 - sign * sat)).max(-1.0);
-
-            // Skin tone protection: reduce effect for warm hues to prevent
-            // portraits from looking sunburned (boost) or sickly (cut).
-            let max_ch = pixel[0].max(pixel[1]).max(pixel[2]);
-            if max_ch > 1e-6 {
-                let skin_factor = skin_tone_weight(pixel[0], pixel[1], pixel[2]);
-                effect *= 1.0 - skin_factor * 0.7;
-            }
-
-            pixel[0] = (y + (1.0 + effect) * (pixel[0] - y)).max(0.0);
-            pixel[1] = (y + (1.0 + effect) * (pixel[1] - y)).max(0.0);
-            pixel[2] = (y + (1.0 + effect) * (pixel[2] - y)).max(0.0);
-        }
+        vibrance(&mut input.data, params);
         Ok(input)
     }
+
+    fn process_f64(&self, data: &mut [f64], params: &EditParams) -> bool {
+        vibrance(data, params);
+        true
+    }
 }
 
-fn smoothstep(t: f32) -> f32 {
-    let t = t.clamp(0.0, 1.0);
-    t * t * (3.0 - 2.0 * t)
+fn vibrance<T: Float>(data: &mut [T], params: &EditParams) {
+    let (zero, one) = (T::of(0.0), T::of(1.0));
+    let strength = T::of(params.vibrance) / T::of(100.0);
+    let sign = strength.signum();
+    for pixel in data.chunks_exact_mut(3) {
+        let y = luma(pixel[0], pixel[1], pixel[2]);
+
+        // OKLab chroma: perceptually uniform saturation metric.
+        let (_, ok_a, ok_b) = linear_srgb_to_oklab(pixel[0], pixel[1], pixel[2]);
+        let chroma = (ok_a * ok_a + ok_b * ok_b).sqrt();
+        let sat = (chroma / T::of(OKLAB_MAX_CHROMA)).clamp(zero, one);
+
+        // Selective saturation (SweetFX/ReShade convention):
+        //   positive -> targets low-sat pixels (1 - sat)
+        //   negative -> targets high-sat pixels (1 + sat)
+        let mut effect = (strength * (one - sign * sat)).max(-one);
+
+        // Skin tone protection: reduce effect for warm hues to prevent
+        // portraits from looking sunburned (boost) or sickly (cut).
+        let max_ch = pixel[0].max(pixel[1]).max(pixel[2]);
+        if max_ch > T::of(1e-6) {
+            let skin_factor = skin_tone_weight(pixel[0], pixel[1], pixel[2]);
+            effect *= one - skin_factor * T::of(0.7);
+        }
+
+        for v in pixel {
+            *v = (y + (one + effect) * (*v - y)).max(zero);
+        }
+    }
+}
+
+fn smoothstep<T: Float>(t: T) -> T {
+    let t = t.clamp(T::of(0.0), T::of(1.0));
+    t * t * (T::of(3.0) - T::of(2.0) * t)
 }
 
 /// Returns 0.0-1.0 indicating how much this pixel looks like a skin tone.
@@ -57,41 +72,49 @@ fn smoothstep(t: f32) -> f32 {
 /// Computes HSV hue directly from linear RGB. Hue angles shift slightly
 /// compared to gamma-encoded space, but the smoothstep ramps (15-30 degree
 /// feather) absorb the difference. Avoids 3x powf(1/2.4) per pixel.
-fn skin_tone_weight(r: f32, g: f32, b: f32) -> f32 {
-    let rg = r.max(0.0);
-    let gg = g.max(0.0);
-    let bg = b.max(0.0);
+fn skin_tone_weight<T: Float>(r: T, g: T, b: T) -> T {
+    let zero = T::of(0.0);
+    let rg = r.max(zero);
+    let gg = g.max(zero);
+    let bg = b.max(zero);
 
     let max_ch = rg.max(gg).max(bg);
     let min_ch = rg.min(gg).min(bg);
     let chroma = max_ch - min_ch;
-    if chroma < 1e-6 {
-        return 0.0;
+    let epsilon = T::of(1e-6);
+    if chroma < epsilon {
+        return zero;
     }
 
-    let hue = if (max_ch - rg).abs() < 1e-6 {
-        60.0 * ((gg - bg) / chroma).rem_euclid(6.0)
-    } else if (max_ch - gg).abs() < 1e-6 {
-        60.0 * ((bg - rg) / chroma + 2.0)
+    let sixty = T::of(60.0);
+    let hue = if (max_ch - rg).abs() < epsilon {
+        sixty * ((gg - bg) / chroma).rem_euclid(T::of(6.0))
+    } else if (max_ch - gg).abs() < epsilon {
+        sixty * ((bg - rg) / chroma + T::of(2.0))
     } else {
-        60.0 * ((rg - gg) / chroma + 4.0)
+        sixty * ((rg - gg) / chroma + T::of(4.0))
     };
-    let hue = if hue < 0.0 { hue + 360.0 } else { hue };
+    let full_turn = T::of(360.0);
+    let hue = if hue < zero { hue + full_turn } else { hue };
 
     // Skin tone range: 350-85 degrees (wraps around 0/360).
     // Ramp in: 350-5, plateau: 5-55, ramp out: 55-85.
     // Extended to 85 to cover olive/warm-yellow skin tones.
-    if hue >= 350.0 || hue <= 85.0 {
-        let h = if hue >= 350.0 { hue - 360.0 } else { hue };
-        if h < 5.0 {
-            smoothstep((h + 10.0) / 15.0)
-        } else if h > 55.0 {
-            smoothstep((85.0 - h) / 30.0)
+    if hue >= T::of(350.0) || hue <= T::of(85.0) {
+        let h = if hue >= T::of(350.0) {
+            hue - full_turn
+        } else {
+            hue
+        };
+        if h < T::of(5.0) {
+            smoothstep((h + T::of(10.0)) / T::of(15.0))
+        } else if h > T::of(55.0) {
+            smoothstep((T::of(85.0) - h) / T::of(30.0))
         } else {
-            1.0
+            T::of(1.0)
         }
     } else {
-        0.0
+        zero
     }
 }
 
@@ -396,7 +419,7 @@ mod tests {
     #[test]
     fn skin_tone_weight_dark_skin() {
         // Very dark skin tone: low absolute values, warm hue.
-        let w = skin_tone_weight(0.03, 0.015, 0.008);
+        let w: f32 = skin_tone_weight(0.03, 0.015, 0.008);
         assert!(w.is_finite(), "dark skin tone weight should be finite: {w}");
         // Hue should still be in skin range despite low values
         assert!(
@@ -408,7 +431,7 @@ mod tests {
     #[test]
     fn skin_tone_weight_hdr() {
         // HDR skin-tone pixel (values above 1.0)
-        let w = skin_tone_weight(2.0, 1.0, 0.5);
+        let w: f32 = skin_tone_weight(2.0, 1.0, 0.5);
         assert!(w.is_finite(), "HDR skin tone weight should be finite: {w}");
         assert!(
             w > 0.0,
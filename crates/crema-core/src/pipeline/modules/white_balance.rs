@@ -1,5 +1,6 @@
 use anyhow::Result;
 
+use crate::float::{Float, luma};
 use crate::image_buf::{EditParams, ImageBuf};
 use crate::pipeline::module::ProcessingModule;
 
@@ -10,6 +11,10 @@ impl ProcessingModule for WhiteBalance {
         "white_balance"
     }
 
+    fn is_pointwise(&self) -> bool {
+        true
+    }
+
     fn process_cpu(&self, mut input: ImageBuf, params: &EditParams) -> Result<ImageBuf> {
         let matrix = wb_matrix(params.wb_temp, params.wb_tint);
 
@@ -17,43 +22,52 @@ impl ProcessingModule for WhiteBalance {
             return Ok(input);
         }
 
-        for pixel in input.data.chunks_exact_mut(3) {
-            let r = pixel[0];
-            let g = pixel[1];
-            let b = pixel[2];
-            let out_r = matrix[0] * r + matrix[1] * g + matrix[2] * b;
-            let out_g = matrix[3] * r + matrix[4] * g + matrix[5] * b;
-            let out_b = matrix[6] * r + matrix[7] * g + matrix[8] * b;
-
-            let min_ch = out_r.min(out_g).min(out_b);
-            if min_ch >= 0.0 {
-                pixel[0] = out_r;
-                pixel[1] = out_g;
-                pixel[2] = out_b;
+        balance(&mut input.data, &matrix);
+        Ok(input)
+    }
+
+    fn process_f64(&self, data: &mut [f64], params: &EditParams) -> bool {
+        balance(data, &wb_matrix_f64(params.wb_temp, params.wb_tint));
+        true
+    }
+}
+
+fn balance<T: Float>(data: &mut [T], matrix: &[T; 9]) {
+    let zero = T::of(0.0);
+    for pixel in data.chunks_exact_mut(3) {
+        let r = pixel[0];
+        let g = pixel[1];
+        let b = pixel[2];
+        let out_r = matrix[0] * r + matrix[1] * g + matrix[2] * b;
+        let out_g = matrix[3] * r + matrix[4] * g + matrix[5] * b;
+        let out_b = matrix[6] * r + matrix[7] * g + matrix[8] * b;
+
+        let min_ch = out_r.min(out_g).min(out_b);
+        if min_ch >= zero {
+            pixel[0] = out_r;
+            pixel[1] = out_g;
+            pixel[2] = out_b;
+        } else {
+            // Desaturate toward luminance axis to bring negatives to zero.
+            // This preserves hue (direction from neutral) unlike hard clipping.
+            let y = luma(out_r, out_g, out_b);
+            if y <= zero {
+                pixel[0] = zero;
+                pixel[1] = zero;
+                pixel[2] = zero;
             } else {
-                // Desaturate toward luminance axis to bring negatives to zero.
-                // This preserves hue (direction from neutral) unlike hard clipping.
-                let y = 0.2126 * out_r + 0.7152 * out_g + 0.0722 * out_b;
-                if y <= 0.0 {
-                    pixel[0] = 0.0;
-                    pixel[1] = 0.0;
-                    pixel[2] = 0.0;
-                } else {
-                    // Find t in [0,1] where y + t*(ch - y) = 0 for the most-negative channel.
-                    let mut t = 1.0_f32;
-                    for &ch in &[out_r, out_g, out_b] {
-                        if ch < 0.0 {
-                            t = t.min(y / (y - ch));
-                        }
+                // Find t in [0,1] where y + t*(ch - y) = 0 for the most-negative channel.
+                let mut t = T::of(1.0);
+                for &ch in &[out_r, out_g, out_b] {
+                    if ch < zero {
+                        t = t.min(y / (y - ch));
                     }
-                    pixel[0] = (y + t * (out_r - y)).max(0.0);
-                    pixel[1] = (y + t * (out_g - y)).max(0.0);
-                    pixel[2] = (y + t * (out_b - y)).max(0.0);
                 }
+                pixel[0] = (y + t * (out_r - y)).max(zero);
+                pixel[1] = (y + t * (out_g - y)).max(zero);
+                pixel[2] = (y + t * (out_b - y)).max(zero);
             }
         }
-
-        Ok(input)
     }
 }
 
@@ -125,6 +139,11 @@ const REF_TEMP: f64 = 5500.0;
 /// Source white = Planckian chromaticity at `temp` + tint offset.
 /// Destination white = Planckian chromaticity at 5500 K (our neutral).
 pub fn wb_matrix(temp: f32, tint: f32) -> [f32; 9] {
+    wb_matrix_f64(temp, tint).map(|v| v as f32)
+}
+
+/// [`wb_matrix`] before rounding to `f32`.
+fn wb_matrix_f64(temp: f32, tint: f32) -> [f64; 9] {
     let temp = (temp as f64).clamp(1667.0, 25000.0);
     let tint = tint as f64;
 
@@ -138,19 +157,7 @@ pub fn wb_matrix(temp: f32, tint: f32) -> [f32; 9] {
 
     // Combined: XYZ_to_sRGB * adapt * sRGB_to_XYZ
     let tmp = mat3_mul(&adapt, &SRGB_TO_XYZ);
-    let combined = mat3_mul(&XYZ_TO_SRGB, &tmp);
-
-    [
-        combined[0] as f32,
-        combined[1] as f32,
-        combined[2] as f32,
-        combined[3] as f32,
-        combined[4] as f32,
-        combined[5] as f32,
-        combined[6] as f32,
-        combined[7] as f32,
-        combined[8] as f32,
-    ]
+    mat3_mul(&XYZ_TO_SRGB, &tmp)
 }
 
 // ── Planckian locus (Kang et al. 2002) ──────────────────────────────────
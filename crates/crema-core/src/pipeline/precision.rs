@@ -0,0 +1,257 @@
+//! Precision audit: runs the pipeline beside a double-precision reference
+//! and measures how far the `f32` result strays from it. Used to chase
+//! banding reports and to check that faster arithmetic (SIMD, half floats)
+//! stays within tolerance.
+
+use anyhow::Result;
+
+use super::Pipeline;
+use crate::color::linear_to_srgb;
+use crate::float::Float;
+use crate::image_buf::{EditParams, ImageBuf};
+
+/// Deviations are measured in 8-bit sRGB code values, so anything below
+/// 0.5 rounds to the same output level.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PrecisionReport {
+    pub max_levels: f64,
+    pub mean_levels: f64,
+    /// Pixel of the largest deviation, in output coordinates.
+    pub worst_pixel: (u32, u32),
+    pub modules: Vec<ModuleDeviation>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModuleDeviation {
+    pub name: String,
+    /// Error the module's `f32` path adds on its own, given the reference
+    /// input. `None` for modules without a double-precision reference,
+    /// whose `f32` path stands in for it.
+    pub max_levels: Option<f64>,
+}
+
+impl PrecisionReport {
+    /// The audited module contributing the largest error. A module whose
+    /// error came out NaN is taken as the worst, since its output can't be
+    /// trusted at all.
+    pub fn worst_module(&self) -> Option<&ModuleDeviation> {
+        self.modules
+            .iter()
+            .filter_map(|m| Some((m, m.max_levels?.abs())))
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(m, _)| m)
+    }
+}
+
+impl Pipeline {
+    /// Process `input` in `f32` as usual and again in `f64`, comparing
+    /// the two.
+    pub fn audit_precision(
+        &self,
+        input: &ImageBuf,
+        params: &EditParams,
+    ) -> Result<PrecisionReport> {
+        let mut actual = input.clone();
+        let (mut width, mut height) = (input.width, input.height);
+        let mut reference: Vec<f64> = input.data.iter().map(|&v| v as f64).collect();
+        let mut modules = Vec::with_capacity(self.modules.len());
+
+        for module in &self.modules {
+            actual = module.process_cpu(actual, params)?;
+            let rounded =
+                ImageBuf::from_data(width, height, reference.iter().map(|&v| v as f32).collect())?;
+            let mut next = reference.clone();
+            let max_levels = if module.process_f64(&mut next, params) {
+                let isolated = module.process_cpu(rounded, params)?;
+                reference = next;
+                Some(compare(&isolated.data, &reference, |_| {}).max_levels)
+            } else {
+                let out = module.process_cpu(rounded, params)?;
+                (width, height) = (out.width, out.height);
+                reference = out.data.iter().map(|&v| v as f64).collect();
+                None
+            };
+            modules.push(ModuleDeviation {
+                name: module.name().to_string(),
+                max_levels,
+            });
+        }
+
+        let deviation = compare(&actual.data, &reference, |_| {});
+        let worst = deviation.worst;
+        Ok(PrecisionReport {
+            max_levels: deviation.max_levels,
+            mean_levels: deviation.mean_levels,
+            worst_pixel: (worst as u32 % width.max(1), worst as u32 / width.max(1)),
+            modules,
+        })
+    }
+}
+
+/// How far one image strays from another, measured like
+/// [`PrecisionReport`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Deviation {
+    pub max_levels: f64,
+    pub mean_levels: f64,
+    /// Index of the pixel with the max.
+    pub worst: usize,
+    /// Pixels with a channel that rounds to a different 8-bit level.
+    pub differing: usize,
+}
+
+/// Deviation of `actual` from `reference`, encoded to sRGB in the
+/// reference's precision. `each` is called with every pixel's deviation,
+/// its largest over the three channels.
+pub(crate) fn compare<T: Float>(
+    actual: &[f32],
+    reference: &[T],
+    mut each: impl FnMut(f64),
+) -> Deviation {
+    let encode = |v: T| (linear_to_srgb(v.clamp(T::of(0.0), T::of(1.0))) * T::of(255.0)).to_f64();
+    let mut max = 0.0;
+    let mut sum = 0.0;
+    let mut worst = 0;
+    let mut differing = 0;
+    for (i, (a, r)) in actual
+        .chunks_exact(3)
+        .zip(reference.chunks_exact(3))
+        .enumerate()
+    {
+        let mut deviation = 0.0f64;
+        let mut rounds_apart = false;
+        for (&a, &r) in a.iter().zip(r) {
+            let (la, lr) = (encode(T::of(a)), encode(r));
+            deviation = deviation.max((la - lr).abs());
+            rounds_apart |= la.round() != lr.round();
+        }
+        each(deviation);
+        sum += deviation;
+        differing += usize::from(rounds_apart);
+        if deviation > max {
+            max = deviation;
+            worst = i;
+        }
+    }
+    let pixels = (actual.len() / 3).max(1);
+    Deviation {
+        max_levels: max,
+        mean_levels: sum / pixels as f64,
+        worst,
+        differing,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline::module::ProcessingModule;
+
+    /// Horizontal ramp with a different slope per channel.
+    fn gradient(w: u32, h: u32) -> ImageBuf {
+        let mut buf = ImageBuf::new(w, h);
+        for (i, px) in buf.data.chunks_exact_mut(3).enumerate() {
+            let t = (i as u32 % w) as f32 / (w - 1) as f32;
+            px.copy_from_slice(&[t, t * t, 0.2 + 0.6 * t]);
+        }
+        buf
+    }
+
+    #[test]
+    fn default_params_match_the_reference() {
+        let report = Pipeline::new()
+            .audit_precision(&gradient(64, 4), &EditParams::default())
+            .unwrap();
+        assert!(report.max_levels < 0.01, "{report:?}");
+    }
+
+    #[test]
+    fn heavy_edits_stay_within_half_a_level() {
+        let params = EditParams {
+            exposure: 1.3,
+            contrast: 60.0,
+            highlights: -80.0,
+            shadows: 70.0,
+            blacks: -30.0,
+            wb_temp: 3800.0,
+            wb_tint: 12.0,
+            vibrance: 40.0,
+            saturation: -20.0,
+            split_shadow_hue: 220.0,
+            split_shadow_sat: 30.0,
+            hsl_hue: 15.0,
+            ..Default::default()
+        };
+        let report = Pipeline::new()
+            .audit_precision(&gradient(256, 2), &params)
+            .unwrap();
+        assert!(report.max_levels < 0.5, "{report:?}");
+        assert!(report.max_levels > 0.0);
+        let tone = report.modules.iter().find(|m| m.name == "tone_curve");
+        assert!(tone.unwrap().max_levels.unwrap() > 0.0);
+    }
+
+    #[test]
+    fn spatial_modules_are_not_audited() {
+        let report = Pipeline::new()
+            .audit_precision(&gradient(8, 8), &EditParams::default())
+            .unwrap();
+        let crop = report.modules.iter().find(|m| m.name == "crop").unwrap();
+        assert_eq!(crop.max_levels, None);
+        assert!(report.worst_module().is_some());
+    }
+
+    #[test]
+    fn nan_error_is_the_worst() {
+        let module = |name: &str, max_levels| ModuleDeviation {
+            name: name.to_string(),
+            max_levels,
+        };
+        let report = PrecisionReport {
+            max_levels: f64::NAN,
+            mean_levels: f64::NAN,
+            worst_pixel: (0, 0),
+            modules: vec![
+                module("exposure", Some(0.2)),
+                module("degenerate", Some(f64::NAN)),
+                module("crop", None),
+                module("tone_curve", Some(0.4)),
+            ],
+        };
+        assert_eq!(report.worst_module().unwrap().name, "degenerate");
+    }
+
+    /// Quantizes to 8 bits in linear light, which bands shadows badly.
+    struct Quantize;
+
+    impl ProcessingModule for Quantize {
+        fn name(&self) -> &str {
+            "quantize"
+        }
+
+        fn process_cpu(&self, mut input: ImageBuf, _params: &EditParams) -> Result<ImageBuf> {
+            for v in &mut input.data {
+                *v = (*v * 255.0).round() / 255.0;
+            }
+            Ok(input)
+        }
+
+        fn process_f64(&self, _data: &mut [f64], _params: &EditParams) -> bool {
+            true
+        }
+    }
+
+    #[test]
+    fn finds_banding_and_where_it_is() {
+        let pipeline = Pipeline {
+            modules: vec![Box::new(Quantize)],
+        };
+        let report = pipeline
+            .audit_precision(&gradient(256, 2), &EditParams::default())
+            .unwrap();
+        assert!(report.max_levels > 2.0, "{report:?}");
+        // Linear quantization is coarsest in sRGB terms near black.
+        assert!(report.worst_pixel.0 < 32, "{report:?}");
+        assert_eq!(report.worst_module().unwrap().name, "quantize");
+    }
+}
@@ -0,0 +1,189 @@
+//! Partial processing presets. A preset stores only the parameters it
+//! means to change, by their [`edit_diff`](crate::edit_diff) keys, so
+//! applying several in turn composes: a split-tone preset leaves exposure
+//! alone instead of resetting it.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::edit_diff::{self, EditParam};
+use crate::image_buf::EditParams;
+
+/// A develop panel section and the parameters under it, so a preset can
+/// include a whole section or pick single parameters from it.
+#[derive(Debug, Clone, Copy)]
+pub struct ParamGroup {
+    pub label: &'static str,
+    pub keys: &'static [&'static str],
+}
+
+impl ParamGroup {
+    pub fn params(&self) -> impl Iterator<Item = &'static EditParam> + '_ {
+        self.keys.iter().filter_map(|key| edit_diff::find(key))
+    }
+}
+
+/// Every parameter under its section, in panel order.
+pub const GROUPS: &[ParamGroup] = &[
+    ParamGroup {
+        label: "Light",
+        keys: &["exposure", "contrast", "highlights", "shadows", "blacks"],
+    },
+    ParamGroup {
+        label: "Color",
+        keys: &["wb_temp", "wb_tint", "vibrance", "saturation"],
+    },
+    ParamGroup {
+        label: "HSL",
+        keys: &["hsl_hue", "hsl_saturation", "hsl_lightness"],
+    },
+    ParamGroup {
+        label: "Split Tone",
+        keys: &[
+            "split_shadow_hue",
+            "split_shadow_sat",
+            "split_highlight_hue",
+            "split_highlight_sat",
+            "split_balance",
+        ],
+    },
+    ParamGroup {
+        label: "Denoise",
+        keys: &["nr_luminance", "nr_color"],
+    },
+    ParamGroup {
+        label: "Detail",
+        keys: &["sharpen_amount", "sharpen_radius"],
+    },
+    ParamGroup {
+        label: "Lens",
+        keys: &["vignette_amount", "distortion"],
+    },
+    ParamGroup {
+        label: "Crop",
+        keys: &["rotation", "crop_x", "crop_y", "crop_w", "crop_h"],
+    },
+];
+
+/// Values closer to the default than this don't count as an adjustment.
+const EPSILON: f32 = 1e-4;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Preset {
+    pub name: String,
+    /// Included parameters by key. Anything absent is left as it was.
+    pub values: BTreeMap<String, f32>,
+}
+
+impl Preset {
+    /// A preset holding `params`' values for the parameters in `keys`.
+    /// Keys that don't name a parameter are dropped.
+    pub fn capture<'a>(
+        name: impl Into<String>,
+        params: &EditParams,
+        keys: impl IntoIterator<Item = &'a str>,
+    ) -> Self {
+        let values = keys
+            .into_iter()
+            .filter_map(edit_diff::find)
+            .map(|param| (param.key.to_string(), param.value(params)))
+            .collect();
+        Self {
+            name: name.into(),
+            values,
+        }
+    }
+
+    pub fn includes(&self, key: &str) -> bool {
+        self.values.contains_key(key)
+    }
+
+    /// Set the included parameters on `params` and leave the rest. Keys
+    /// this build doesn't know, say from a newer release, are skipped.
+    pub fn apply(&self, params: &mut EditParams) {
+        for (key, &value) in &self.values {
+            if let Some(param) = edit_diff::find(key) {
+                param.set(params, value);
+            }
+        }
+    }
+}
+
+/// The parameters `params` moves off their defaults, leaving out crop and
+/// straighten, which rarely carry from one photo to the next. A starting
+/// selection for a new preset.
+pub fn adjusted_keys(params: &EditParams) -> Vec<&'static str> {
+    let defaults = EditParams::default();
+    let crop = GROUPS.iter().find(|g| g.label == "Crop").map(|g| g.keys);
+    edit_diff::PARAMS
+        .iter()
+        .filter(|param| !crop.is_some_and(|keys| keys.contains(&param.key)))
+        .filter(|param| (param.value(params) - param.value(&defaults)).abs() > EPSILON)
+        .map(|param| param.key)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn groups_cover_every_param_once() {
+        let mut keys: Vec<&str> = GROUPS.iter().flat_map(|g| g.keys.iter().copied()).collect();
+        assert!(GROUPS.iter().all(|g| g.params().count() == g.keys.len()));
+        keys.sort_unstable();
+        let mut expected: Vec<&str> = edit_diff::PARAMS.iter().map(|p| p.key).collect();
+        expected.sort_unstable();
+        assert_eq!(keys, expected);
+    }
+
+    #[test]
+    fn presets_compose_instead_of_overwriting() {
+        let source = EditParams {
+            exposure: 1.5,
+            split_shadow_hue: 210.0,
+            split_shadow_sat: 30.0,
+            ..EditParams::default()
+        };
+        let cool_shadows = Preset::capture(
+            "Cool shadows",
+            &source,
+            ["split_shadow_hue", "split_shadow_sat"],
+        );
+        let brighten = Preset::capture("Brighten", &source, ["exposure"]);
+        assert!(!cool_shadows.includes("exposure"));
+
+        let mut params = EditParams {
+            contrast: 25.0,
+            ..EditParams::default()
+        };
+        brighten.apply(&mut params);
+        cool_shadows.apply(&mut params);
+        assert_eq!(params.exposure, 1.5);
+        assert_eq!(params.split_shadow_hue, 210.0);
+        assert_eq!(params.split_shadow_sat, 30.0);
+        assert_eq!(params.contrast, 25.0);
+    }
+
+    #[test]
+    fn unknown_keys_are_skipped() {
+        let mut preset = Preset::capture("Old", &EditParams::default(), ["exposure", "bogus"]);
+        assert_eq!(preset.values.len(), 1);
+        preset.values.insert("from_the_future".into(), 3.0);
+        let mut params = EditParams::default();
+        preset.apply(&mut params);
+        assert_eq!(params, EditParams::default());
+    }
+
+    #[test]
+    fn adjusted_keys_skip_crop() {
+        let params = EditParams {
+            vibrance: 20.0,
+            crop_w: 0.5,
+            rotation: 2.0,
+            ..EditParams::default()
+        };
+        assert_eq!(adjusted_keys(&params), ["vibrance"]);
+    }
+}
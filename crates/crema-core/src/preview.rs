@@ -0,0 +1,62 @@
+//! What the develop view shows: a decoded photo downsampled to the
+//! preview size, developed, converted to 8-bit sRGB and measured into the
+//! histogram. The app and the display chain tests both go through here.
+
+use crate::histogram::HistogramData;
+use crate::image_buf::{EditParams, ImageBuf};
+use crate::pipeline::Pipeline;
+
+/// Longest edge the develop view renders at.
+pub const PREVIEW_EDGE: u32 = 2048;
+
+/// A developed preview frame, ready to draw.
+pub struct Displayed {
+    pub width: u32,
+    pub height: u32,
+    pub rgba: Vec<u8>,
+    pub histogram: HistogramData,
+}
+
+/// `decoded` at the size the develop view renders at.
+pub fn downsample(decoded: &ImageBuf) -> ImageBuf {
+    decoded.downsample(PREVIEW_EDGE)
+}
+
+/// Display `preview` under `params`. `developed` is the GPU's render when
+/// it had one; otherwise the CPU pipeline develops `preview`, and should
+/// that fail the unedited preview is shown.
+pub fn display(preview: &ImageBuf, params: &EditParams, developed: Option<ImageBuf>) -> Displayed {
+    let developed = developed.or_else(|| Pipeline::new().process_cpu(preview.clone(), params).ok());
+    let shown = developed.as_ref().unwrap_or(preview);
+    let rgba = shown.to_rgba_u8_srgb();
+    let histogram = HistogramData::from_rgba_u8(&rgba);
+    Displayed {
+        width: shown.width,
+        height: shown.height,
+        rgba,
+        histogram,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display_measures_the_frame_it_shows() {
+        let preview = ImageBuf::from_data(4, 2, vec![0.18; 4 * 2 * 3]).unwrap();
+        let brighter = EditParams {
+            exposure: 1.0,
+            ..EditParams::default()
+        };
+
+        let shown = display(&preview, &brighter, None);
+        assert_eq!((shown.width, shown.height), (4, 2));
+        let level = shown.rgba[0] as usize;
+        assert_eq!(shown.histogram.r[level], 8);
+        assert!(level > display(&preview, &EditParams::default(), None).rgba[0] as usize);
+
+        let from_gpu = ImageBuf::from_data(4, 2, vec![1.0; 4 * 2 * 3]).unwrap();
+        assert_eq!(display(&preview, &brighter, Some(from_gpu)).rgba[0], 255);
+    }
+}
@@ -0,0 +1,223 @@
+//! Animated GIF and APNG stills. Plain decoding shows the first frame;
+//! these read the whole sequence so a photo can be marked as animated and
+//! another frame chosen to stand for it. Each frame is composited onto the
+//! ones before it, as a viewer would show it. HEIC sequences and Live
+//! Photos aren't supported yet: HEIC doesn't decode at all, and a Live
+//! Photo's video is a separate file that isn't paired with its still.
+
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+
+use anyhow::{Context, Result, bail};
+use image::codecs::gif::GifDecoder;
+use image::codecs::png::PngDecoder;
+use image::{AnimationDecoder, Frames};
+
+/// The frames of `path`, or `None` if it isn't an animated format.
+fn frames(path: &Path) -> Result<Option<Frames<'static>>> {
+    let open = || -> Result<BufReader<File>> {
+        let file =
+            File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+        Ok(BufReader::new(file))
+    };
+    let mut magic = [0u8; 8];
+    if open()?.read_exact(&mut magic).is_err() {
+        return Ok(None);
+    }
+    if magic.starts_with(b"GIF8") {
+        let decoder = GifDecoder::new(open()?)
+            .with_context(|| format!("failed to read GIF: {}", path.display()))?;
+        return Ok(Some(decoder.into_frames()));
+    }
+    if magic == *b"\x89PNG\r\n\x1a\n" {
+        let decoder = PngDecoder::new(open()?)
+            .with_context(|| format!("failed to read PNG: {}", path.display()))?;
+        if !decoder.is_apng()? {
+            return Ok(None);
+        }
+        return Ok(Some(decoder.apng()?.into_frames()));
+    }
+    Ok(None)
+}
+
+/// How many frames `path` holds: 1 for stills and formats that can't
+/// animate. Read from the container's structure without decoding any
+/// frame, since import asks this of every file.
+pub fn frame_count(path: &Path) -> Result<u32> {
+    let file = File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+    let mut reader = BufReader::new(file);
+    let mut magic = [0u8; 8];
+    if reader.read_exact(&mut magic).is_err() {
+        return Ok(1);
+    }
+    let count = if magic.starts_with(b"GIF8") {
+        gif_frame_count(&mut reader)
+            .with_context(|| format!("failed to read GIF: {}", path.display()))?
+    } else if magic == *b"\x89PNG\r\n\x1a\n" {
+        apng_frame_count(&mut reader)
+            .with_context(|| format!("failed to read PNG: {}", path.display()))?
+    } else {
+        1
+    };
+    Ok(count.max(1))
+}
+
+/// Image descriptors in a GIF, read past its first eight bytes. Each
+/// frame is one descriptor; its pixel data is skipped block by block.
+fn gif_frame_count(reader: &mut impl Read) -> Result<u32> {
+    // The rest of the logical screen descriptor: height, flags,
+    // background color and aspect ratio.
+    let mut screen = [0u8; 5];
+    reader.read_exact(&mut screen)?;
+    let packed = screen[2];
+    if packed & 0x80 != 0 {
+        skip(reader, 3 << ((packed & 0x07) + 1))?;
+    }
+    let mut count = 0;
+    loop {
+        match read_u8(reader)? {
+            // Extension: a label, then data sub-blocks.
+            0x21 => {
+                read_u8(reader)?;
+                skip_sub_blocks(reader)?;
+            }
+            // Image descriptor: position, size and flags, an optional
+            // local color table, the LZW code size, then data sub-blocks.
+            0x2C => {
+                let mut descriptor = [0u8; 9];
+                reader.read_exact(&mut descriptor)?;
+                let packed = descriptor[8];
+                if packed & 0x80 != 0 {
+                    skip(reader, 3 << ((packed & 0x07) + 1))?;
+                }
+                read_u8(reader)?;
+                skip_sub_blocks(reader)?;
+                count += 1;
+            }
+            0x3B => return Ok(count),
+            other => bail!("unexpected block 0x{other:02x}"),
+        }
+    }
+}
+
+/// Frames an APNG's animation control chunk declares, or 1 for a PNG
+/// without one before its image data.
+fn apng_frame_count(reader: &mut impl Read) -> Result<u32> {
+    loop {
+        let mut header = [0u8; 8];
+        reader.read_exact(&mut header)?;
+        let length = u32::from_be_bytes([header[0], header[1], header[2], header[3]]);
+        match &header[4..] {
+            b"acTL" => {
+                let mut frames = [0u8; 4];
+                reader.read_exact(&mut frames)?;
+                return Ok(u32::from_be_bytes(frames));
+            }
+            b"IDAT" | b"IEND" => return Ok(1),
+            // The chunk's data and its CRC.
+            _ => skip(reader, u64::from(length) + 4)?,
+        }
+    }
+}
+
+fn read_u8(reader: &mut impl Read) -> Result<u8> {
+    let mut byte = [0u8];
+    reader.read_exact(&mut byte)?;
+    Ok(byte[0])
+}
+
+fn skip(reader: &mut impl Read, len: u64) -> Result<()> {
+    let skipped = std::io::copy(&mut reader.take(len), &mut std::io::sink())?;
+    if skipped < len {
+        bail!("file ends early");
+    }
+    Ok(())
+}
+
+/// Skip GIF data sub-blocks up to and including the empty terminator.
+fn skip_sub_blocks(reader: &mut impl Read) -> Result<()> {
+    loop {
+        match read_u8(reader)? {
+            0 => return Ok(()),
+            len => skip(reader, u64::from(len))?,
+        }
+    }
+}
+
+/// Frame `index` of an animated file, as it appears on screen.
+pub fn load_frame(path: &Path, index: u32) -> Result<image::DynamicImage> {
+    let Some(mut frames) = frames(path)? else {
+        bail!("{} is not animated", path.display());
+    };
+    let frame = frames
+        .nth(index as usize)
+        .with_context(|| format!("{} has no frame {index}", path.display()))?
+        .with_context(|| format!("failed to decode frame {index} of {}", path.display()))?;
+    Ok(image::DynamicImage::ImageRgba8(frame.into_buffer()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::codecs::gif::GifEncoder;
+    use image::{Delay, Frame, Rgba, RgbaImage};
+
+    fn write_gif(path: &Path, shades: &[u8]) {
+        let file = File::create(path).unwrap();
+        let mut encoder = GifEncoder::new(file);
+        for &shade in shades {
+            let buffer = RgbaImage::from_pixel(4, 4, Rgba([shade, shade, shade, 255]));
+            let delay = Delay::from_numer_denom_ms(100, 1);
+            encoder
+                .encode_frame(Frame::from_parts(buffer, 0, 0, delay))
+                .unwrap();
+        }
+    }
+
+    #[test]
+    fn counts_and_reads_gif_frames() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("loop.gif");
+        write_gif(&path, &[0, 128, 255]);
+        assert_eq!(frame_count(&path).unwrap(), 3);
+
+        let middle = load_frame(&path, 1).unwrap().into_rgb8();
+        assert!(middle.pixels().all(|p| p[0].abs_diff(128) <= 2));
+        assert!(load_frame(&path, 3).is_err());
+    }
+
+    #[test]
+    fn stills_have_one_frame() {
+        let dir = tempfile::tempdir().unwrap();
+        let gif = dir.path().join("still.gif");
+        write_gif(&gif, &[64]);
+        assert_eq!(frame_count(&gif).unwrap(), 1);
+
+        let png = dir.path().join("still.png");
+        image::RgbImage::new(2, 2).save(&png).unwrap();
+        assert_eq!(frame_count(&png).unwrap(), 1);
+        assert!(load_frame(&png, 0).is_err());
+    }
+
+    #[test]
+    fn apng_frames_are_counted_from_the_control_chunk() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("loop.png");
+        image::RgbImage::new(2, 2).save(&path).unwrap();
+        let png = std::fs::read(&path).unwrap();
+
+        // acTL after the signature and IHDR: 12 frames, looping forever.
+        // The count is read without checking the chunk's CRC.
+        let ihdr_end = 8 + 8 + 13 + 4;
+        let mut actl = 8u32.to_be_bytes().to_vec();
+        actl.extend_from_slice(b"acTL");
+        actl.extend_from_slice(&12u32.to_be_bytes());
+        actl.extend_from_slice(&0u32.to_be_bytes());
+        actl.extend_from_slice(&0u32.to_be_bytes());
+        let apng = [&png[..ihdr_end], &actl, &png[ihdr_end..]].concat();
+        std::fs::write(&path, apng).unwrap();
+
+        assert_eq!(frame_count(&path).unwrap(), 12);
+    }
+}
@@ -0,0 +1,398 @@
+//! CMYK JPEG and TIFF sources, usually files prepared for print. The
+//! `image` crate flattens CMYK to RGB itself, ignoring the embedded
+//! profile and rounding to 8 bits; these are decoded here instead so the
+//! profile is honored and 16-bit separations keep their precision.
+//!
+//! YCCK JPEGs are left to `image`, whose conversion is the same naive one
+//! used here for files without a profile.
+
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use moxcms::{ColorProfile, DataColorSpace, Layout, TransformOptions};
+use tracing::{debug, warn};
+use zune_jpeg::JpegDecoder;
+use zune_jpeg::zune_core::colorspace::ColorSpace;
+use zune_jpeg::zune_core::options::DecoderOptions;
+
+use crate::image_buf::ImageBuf;
+
+/// Ink coverage per pixel, 0..=1, in C, M, Y, K order.
+struct Separation {
+    width: u32,
+    height: u32,
+    inks: Vec<f32>,
+    icc: Option<Vec<u8>>,
+}
+
+/// Decode `path` if it's a CMYK JPEG or TIFF, converting to linear sRGB.
+/// `Ok(None)` means it isn't CMYK and should be loaded as usual.
+pub fn load(path: &Path, max_edge: Option<u32>) -> Result<Option<ImageBuf>> {
+    let open = || -> Result<BufReader<File>> {
+        let file =
+            File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+        Ok(BufReader::new(file))
+    };
+    let mut magic = [0u8; 4];
+    if open()?.read_exact(&mut magic).is_err() {
+        return Ok(None);
+    }
+    let separation = if magic.starts_with(&[0xFF, 0xD8]) {
+        read_jpeg(open)?
+    } else if &magic == b"II*\0" || &magic == b"MM\0*" {
+        read_tiff(open()?)?
+    } else {
+        None
+    };
+    let Some(separation) = separation else {
+        return Ok(None);
+    };
+    debug!(
+        ?path,
+        width = separation.width,
+        height = separation.height,
+        profile = separation.icc.is_some(),
+        "decoding CMYK"
+    );
+    let buf = to_linear_rgb(separation)?;
+    Ok(Some(match max_edge {
+        Some(max) => buf.downsample(max),
+        None => buf,
+    }))
+}
+
+/// Only the headers are read unless the JPEG turns out to be CMYK.
+fn read_jpeg(open: impl Fn() -> Result<BufReader<File>>) -> Result<Option<Separation>> {
+    let mut decoder = JpegDecoder::new(open()?);
+    if decoder.decode_headers().is_err() || decoder.input_colorspace() != Some(ColorSpace::CMYK) {
+        return Ok(None);
+    }
+    let options = DecoderOptions::default().jpeg_set_out_colorspace(ColorSpace::CMYK);
+    let mut decoder = JpegDecoder::new_with_options(open()?, options);
+    let samples = decoder
+        .decode()
+        .map_err(|err| anyhow::anyhow!("failed to decode CMYK JPEG: {err:?}"))?;
+    let info = decoder.info().context("CMYK JPEG has no dimensions")?;
+    // Adobe applications store every channel inverted, and mark their
+    // files with an APP14 segment; other encoders write plain ink.
+    let inks = if has_adobe_marker(open()?)? {
+        samples.iter().map(|&v| 1.0 - v as f32 / 255.0).collect()
+    } else {
+        samples.iter().map(|&v| v as f32 / 255.0).collect()
+    };
+    Ok(Some(Separation {
+        width: info.width as u32,
+        height: info.height as u32,
+        inks,
+        icc: decoder.icc_profile(),
+    }))
+}
+
+/// Whether the JPEG in `reader` has Adobe's APP14 segment, looking only
+/// at the segments before the image data.
+fn has_adobe_marker(mut reader: impl Read) -> Result<bool> {
+    const APP14: u8 = 0xEE;
+    const SOS: u8 = 0xDA;
+    const EOI: u8 = 0xD9;
+
+    let mut soi = [0u8; 2];
+    reader.read_exact(&mut soi)?;
+    loop {
+        let mut marker = [0u8; 2];
+        reader.read_exact(&mut marker)?;
+        if marker[0] != 0xFF || marker[1] == SOS || marker[1] == EOI {
+            return Ok(false);
+        }
+        // Markers without a length.
+        if matches!(marker[1], 0x01 | 0xD0..=0xD7 | 0xFF) {
+            continue;
+        }
+        let mut len = [0u8; 2];
+        reader.read_exact(&mut len)?;
+        let len = usize::from(u16::from_be_bytes(len)).saturating_sub(2);
+        let mut payload = vec![0u8; len];
+        reader
+            .read_exact(&mut payload)
+            .context("truncated JPEG segment")?;
+        if marker[1] == APP14 && payload.starts_with(b"Adobe") {
+            return Ok(true);
+        }
+    }
+}
+
+fn read_tiff(reader: BufReader<File>) -> Result<Option<Separation>> {
+    use tiff::decoder::{Decoder, DecodingResult};
+
+    let Ok(mut decoder) = Decoder::new(reader) else {
+        return Ok(None);
+    };
+    let Ok(tiff::ColorType::CMYK(depth)) = decoder.colortype() else {
+        return Ok(None);
+    };
+    let (width, height) = decoder.dimensions()?;
+    let icc = decoder.get_tag_u8_vec(tiff::tags::Tag::IccProfile).ok();
+    let inks = match decoder.read_image().context("failed to decode CMYK TIFF")? {
+        DecodingResult::U8(v) => v.iter().map(|&s| s as f32 / 255.0).collect(),
+        DecodingResult::U16(v) => v.iter().map(|&s| s as f32 / 65535.0).collect(),
+        _ => anyhow::bail!("unsupported CMYK TIFF bit depth {depth}"),
+    };
+    Ok(Some(Separation {
+        width,
+        height,
+        inks,
+        icc,
+    }))
+}
+
+fn to_linear_rgb(separation: Separation) -> Result<ImageBuf> {
+    let Separation {
+        width,
+        height,
+        inks,
+        icc,
+    } = separation;
+    let srgb = match icc.as_deref().and_then(|icc| profile_transform(icc, &inks)) {
+        Some(srgb) => srgb,
+        None => naive_srgb(&inks),
+    };
+    let data = srgb
+        .into_iter()
+        .map(|v| crate::color::srgb_to_linear(v.clamp(0.0, 1.0)))
+        .collect();
+    ImageBuf::from_data(width, height, data)
+}
+
+/// Convert through the embedded profile to sRGB, or `None` if it can't be
+/// used.
+fn profile_transform(icc: &[u8], inks: &[f32]) -> Option<Vec<f32>> {
+    let profile = match ColorProfile::new_from_slice(icc) {
+        Ok(profile) if profile.color_space == DataColorSpace::Cmyk => profile,
+        Ok(_) => {
+            warn!("ignoring non-CMYK profile embedded in a CMYK image");
+            return None;
+        }
+        Err(err) => {
+            warn!(?err, "ignoring unreadable CMYK profile");
+            return None;
+        }
+    };
+    let transform = profile
+        .create_transform_f32(
+            Layout::Rgba,
+            &ColorProfile::new_srgb(),
+            Layout::Rgb,
+            TransformOptions::default(),
+        )
+        .inspect_err(|err| warn!(?err, "cannot convert from the embedded CMYK profile"))
+        .ok()?;
+    let mut srgb = vec![0.0; inks.len() / 4 * 3];
+    transform
+        .transform(inks, &mut srgb)
+        .inspect_err(|err| warn!(?err, "CMYK profile conversion failed"))
+        .ok()?;
+    Some(srgb)
+}
+
+/// Without a profile, treat the inks as ideal: each removes its
+/// complement, and black darkens all three.
+fn naive_srgb(inks: &[f32]) -> Vec<f32> {
+    inks.chunks_exact(4)
+        .flat_map(|p| {
+            let k = 1.0 - p[3];
+            [(1.0 - p[0]) * k, (1.0 - p[1]) * k, (1.0 - p[2]) * k]
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A minimal uncompressed single-strip CMYK TIFF.
+    fn cmyk_tiff(width: u32, height: u32, samples: &[u16], depth: u16) -> Vec<u8> {
+        let mut pixels = Vec::new();
+        for &s in samples {
+            if depth == 8 {
+                pixels.push(s as u8);
+            } else {
+                pixels.extend_from_slice(&s.to_le_bytes());
+            }
+        }
+        let entries: [(u16, u16, u32, u32); 9] = [
+            (256, 4, 1, width),               // ImageWidth
+            (257, 4, 1, height),              // ImageLength
+            (258, 3, 4, 0),                   // BitsPerSample, patched below
+            (259, 3, 1, 1),                   // Compression: none
+            (262, 3, 1, 5),                   // PhotometricInterpretation: separated
+            (273, 4, 1, 0),                   // StripOffsets, patched below
+            (277, 3, 1, 4),                   // SamplesPerPixel
+            (278, 4, 1, height),              // RowsPerStrip
+            (279, 4, 1, pixels.len() as u32), // StripByteCounts
+        ];
+        let ifd_len = 2 + entries.len() * 12 + 4;
+        let bits_offset = 8 + ifd_len as u32;
+        let strip_offset = bits_offset + 8;
+
+        let mut out = b"II*\0".to_vec();
+        out.extend_from_slice(&8u32.to_le_bytes());
+        out.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+        for (tag, kind, count, value) in entries {
+            let value = match tag {
+                258 => bits_offset,
+                273 => strip_offset,
+                _ => value,
+            };
+            out.extend_from_slice(&tag.to_le_bytes());
+            out.extend_from_slice(&kind.to_le_bytes());
+            out.extend_from_slice(&count.to_le_bytes());
+            if kind == 3 && count == 1 {
+                out.extend_from_slice(&(value as u16).to_le_bytes());
+                out.extend_from_slice(&[0, 0]);
+            } else {
+                out.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+        out.extend_from_slice(&0u32.to_le_bytes());
+        for _ in 0..4 {
+            out.extend_from_slice(&depth.to_le_bytes());
+        }
+        out.extend_from_slice(&pixels);
+        out
+    }
+
+    fn write(dir: &Path, bytes: &[u8]) -> std::path::PathBuf {
+        let path = dir.join("scan.tif");
+        std::fs::write(&path, bytes).unwrap();
+        path
+    }
+
+    #[test]
+    fn converts_cmyk_tiff_inks_to_rgb() {
+        let dir = tempfile::tempdir().unwrap();
+        // White paper, full cyan, and full black.
+        let samples = [0, 0, 0, 0, 255, 0, 0, 0, 0, 0, 0, 255];
+        let path = write(dir.path(), &cmyk_tiff(3, 1, &samples, 8));
+        let buf = load(&path, None).unwrap().unwrap();
+        assert_eq!((buf.width, buf.height), (3, 1));
+        let px = |i: usize| &buf.data[i * 3..i * 3 + 3];
+        assert!(px(0).iter().all(|&v| (v - 1.0).abs() < 1e-4), "{:?}", px(0));
+        assert!(px(1)[0] < 1e-4 && px(1)[1] > 0.99 && px(1)[2] > 0.99);
+        assert!(px(2).iter().all(|&v| v < 1e-4));
+    }
+
+    #[test]
+    fn keeps_16_bit_separations_precise() {
+        let dir = tempfile::tempdir().unwrap();
+        // Two tints of black one 16-bit step apart, far finer than 8 bits.
+        let samples = [0, 0, 0, 30000, 0, 0, 0, 30001];
+        let path = write(dir.path(), &cmyk_tiff(2, 1, &samples, 16));
+        let buf = load(&path, None).unwrap().unwrap();
+        assert!(buf.data[0] > buf.data[3]);
+    }
+
+    #[test]
+    fn leaves_rgb_files_alone() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("rgb.tif");
+        image::RgbImage::from_pixel(2, 2, image::Rgb([10, 20, 30]))
+            .save(&path)
+            .unwrap();
+        assert!(load(&path, None).unwrap().is_none());
+        let path = dir.path().join("rgb.jpg");
+        image::RgbImage::from_pixel(8, 8, image::Rgb([10, 20, 30]))
+            .save(&path)
+            .unwrap();
+        assert!(load(&path, None).unwrap().is_none());
+    }
+
+    /// A baseline 8x8 CMYK JPEG filled with one sample value per channel,
+    /// with Adobe's APP14 segment if `adobe` is set.
+    fn cmyk_jpeg(samples: [u8; 4], adobe: bool) -> Vec<u8> {
+        fn segment(out: &mut Vec<u8>, marker: u8, payload: &[u8]) {
+            out.extend_from_slice(&[0xFF, marker]);
+            out.extend_from_slice(&(payload.len() as u16 + 2).to_be_bytes());
+            out.extend_from_slice(payload);
+        }
+
+        let mut out = vec![0xFF, 0xD8];
+        if adobe {
+            // Version 100, no flags, transform 0: CMYK stored as is.
+            segment(&mut out, 0xEE, b"Adobe\0\x64\0\0\0\0\0");
+        }
+        // Every coefficient quantized by 1.
+        let mut dqt = vec![0u8];
+        dqt.extend_from_slice(&[1; 64]);
+        segment(&mut out, 0xDB, &dqt);
+        let mut sof = vec![8, 0, 8, 0, 8, 4];
+        for id in 1..=4 {
+            sof.extend_from_slice(&[id, 0x11, 0]);
+        }
+        segment(&mut out, 0xC0, &sof);
+        // DC categories 0-11 as 4-bit codes equal to the category, and an
+        // AC table holding only end-of-block, coded as a single 0 bit.
+        let mut dc = vec![0x00, 0, 0, 0, 12];
+        dc.extend_from_slice(&[0; 12]);
+        dc.extend(0..12u8);
+        segment(&mut out, 0xC4, &dc);
+        let mut ac = vec![0x10, 1];
+        ac.extend_from_slice(&[0; 15]);
+        ac.push(0x00);
+        segment(&mut out, 0xC4, &ac);
+        let mut sos = vec![4];
+        for id in 1..=4 {
+            sos.extend_from_slice(&[id, 0x00]);
+        }
+        sos.extend_from_slice(&[0, 63, 0]);
+        segment(&mut out, 0xDA, &sos);
+
+        // One block per channel, with only a DC coefficient.
+        let mut bits: Vec<bool> = Vec::new();
+        let mut push = |value: u32, len: u32| {
+            bits.extend((0..len).rev().map(|i| value >> i & 1 == 1));
+        };
+        for sample in samples {
+            let dc = 8 * (i32::from(sample) - 128);
+            let category = 32 - dc.unsigned_abs().leading_zeros();
+            push(category, 4);
+            let magnitude = if dc < 0 { dc + (1 << category) - 1 } else { dc };
+            push(magnitude as u32, category);
+            push(0, 1);
+        }
+        while !bits.len().is_multiple_of(8) {
+            bits.push(true);
+        }
+        for byte in bits.chunks(8) {
+            let byte = byte.iter().fold(0u8, |acc, &b| acc << 1 | u8::from(b));
+            out.push(byte);
+            if byte == 0xFF {
+                out.push(0);
+            }
+        }
+        out.extend_from_slice(&[0xFF, 0xD9]);
+        out
+    }
+
+    #[test]
+    fn only_adobe_cmyk_jpegs_are_inverted() {
+        let dir = tempfile::tempdir().unwrap();
+        let cyan = |buf: &ImageBuf| buf.data[0] < 1e-3 && buf.data[1] > 0.99 && buf.data[2] > 0.99;
+        let path = dir.path().join("plain.jpg");
+        std::fs::write(&path, cmyk_jpeg([255, 0, 0, 0], false)).unwrap();
+        let plain = load(&path, None).unwrap().unwrap();
+        assert!(cyan(&plain), "{:?}", &plain.data[..3]);
+
+        let path = dir.path().join("adobe.jpg");
+        std::fs::write(&path, cmyk_jpeg([0, 255, 255, 255], true)).unwrap();
+        let adobe = load(&path, None).unwrap().unwrap();
+        assert!(cyan(&adobe), "{:?}", &adobe.data[..3]);
+    }
+
+    #[test]
+    fn unusable_profiles_fall_back_to_the_naive_conversion() {
+        let inks = [0.0, 0.0, 0.0, 0.5];
+        assert!(profile_transform(b"not a profile", &inks).is_none());
+        assert_eq!(naive_srgb(&inks), vec![0.5, 0.5, 0.5]);
+    }
+}
@@ -1,3 +1,10 @@
+mod animated;
+mod cmyk;
+pub mod registry;
+pub(crate) mod sensor;
+
+pub use sensor::{SensorCorrections, SensorData, load_sensor_data};
+
 use std::path::Path;
 use std::sync::LazyLock;
 
@@ -12,15 +19,16 @@ pub const RAW_EXTENSIONS: &[&str] = &[
     "raw", "rwl", "srw", "x3f",
 ];
 
-pub const IMAGE_EXTENSIONS: &[&str] = &["jpg", "jpeg", "png", "tiff", "tif"];
+pub const IMAGE_EXTENSIONS: &[&str] = &["jpg", "jpeg", "png", "tiff", "tif", "gif"];
 
 pub fn is_supported_extension(ext: &str) -> bool {
-    let lower = ext.to_ascii_lowercase();
-    RAW_EXTENSIONS.contains(&lower.as_str()) || IMAGE_EXTENSIONS.contains(&lower.as_str())
+    registry::registry().supports_extension(ext)
 }
 
 pub fn is_raw_extension(ext: &str) -> bool {
-    RAW_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str())
+    registry::registry()
+        .for_extension(ext)
+        .is_some_and(|d| d.capabilities().raw)
 }
 
 /// Decode a RAW file to a linear f32 RGB ImageBuf.
@@ -29,12 +37,18 @@ pub fn is_raw_extension(ext: &str) -> bool {
 /// color calibration, and sRGB gamma. We undo the sRGB gamma to get
 /// linear light for our own pipeline.
 pub fn decode_raw(path: &Path) -> Result<ImageBuf> {
+    decode_raw_corrected(path, &SensorCorrections::default())
+}
+
+/// [`decode_raw`], making `corrections` to the photosites before demosaic.
+pub fn decode_raw_corrected(path: &Path, corrections: &SensorCorrections) -> Result<ImageBuf> {
     info!(?path, "decoding RAW file");
     let t0 = std::time::Instant::now();
 
-    let raw_image = rawler::decode_file(path)
+    let mut raw_image = rawler::decode_file(path)
         .with_context(|| format!("failed to decode RAW: {}", path.display()))?;
     debug!(elapsed_ms = t0.elapsed().as_millis(), "rawler decode_file");
+    corrections.apply_to_raw(&mut raw_image)?;
 
     let t1 = std::time::Instant::now();
     let develop = rawler::imgop::develop::RawDevelop::default();
@@ -74,13 +88,19 @@ pub fn load_image(path: &Path) -> Result<ImageBuf> {
 }
 
 /// Load a standard image, optionally resizing so the longest edge
-/// fits within `max_edge` pixels. Resizing happens in u8/sRGB space
-/// (before the linear conversion) so we avoid converting millions of
-/// pixels we'd immediately throw away.
+/// fits within `max_edge` pixels. Resizing happens in sRGB space at the
+/// file's own bit depth (before the linear conversion) so we avoid
+/// converting millions of pixels we'd immediately throw away. CMYK files
+/// are converted through their embedded profile.
 pub fn load_image_scaled(path: &Path, max_edge: Option<u32>) -> Result<ImageBuf> {
     info!(?path, "loading image file");
     let t0 = std::time::Instant::now();
 
+    if let Some(buf) = cmyk::load(path, max_edge)? {
+        debug!(elapsed_ms = t0.elapsed().as_millis(), "total load_image");
+        return Ok(buf);
+    }
+
     let img =
         image::open(path).with_context(|| format!("failed to open image: {}", path.display()))?;
     debug!(
@@ -90,6 +110,16 @@ pub fn load_image_scaled(path: &Path, max_edge: Option<u32>) -> Result<ImageBuf>
         "image decode"
     );
 
+    let buf = dynamic_to_linear(img, max_edge);
+    debug!(elapsed_ms = t0.elapsed().as_millis(), "total load_image");
+    buf
+}
+
+/// Convert a decoded sRGB image to a linear ImageBuf, resizing first if
+/// it exceeds `max_edge`. Images with more than 8 bits per channel, such
+/// as 16-bit TIFF scans, are resized and converted at their own depth so
+/// they keep their precision.
+fn dynamic_to_linear(img: image::DynamicImage, max_edge: Option<u32>) -> Result<ImageBuf> {
     let img = match max_edge {
         Some(max) if img.width().max(img.height()) > max => {
             let t1 = std::time::Instant::now();
@@ -98,13 +128,26 @@ pub fn load_image_scaled(path: &Path, max_edge: Option<u32>) -> Result<ImageBuf>
                 elapsed_ms = t1.elapsed().as_millis(),
                 width = resized.width(),
                 height = resized.height(),
-                "u8 resize"
+                "resize"
             );
-            resized.into_rgb8()
+            resized
         }
-        _ => img.into_rgb8(),
+        _ => img,
     };
 
+    if img.color().bytes_per_pixel() > img.color().channel_count() {
+        let img = img.into_rgb32f();
+        let (width, height) = img.dimensions();
+        let data = img
+            .into_raw()
+            .into_iter()
+            .map(crate::color::srgb_to_linear)
+            .collect();
+        return ImageBuf::from_data(width, height, data);
+    }
+
+    let img = img.into_rgb8();
+
     let width = img.width();
     let height = img.height();
     let pixel_count = (width * height) as usize;
@@ -116,11 +159,50 @@ pub fn load_image_scaled(path: &Path, max_edge: Option<u32>) -> Result<ImageBuf>
         data.push(lut[pixel.0[1] as usize]);
         data.push(lut[pixel.0[2] as usize]);
     }
-    debug!(elapsed_ms = t0.elapsed().as_millis(), "total load_image");
 
     ImageBuf::from_data(width, height, data)
 }
 
+/// Extract the camera-rendered JPEG preview embedded in a RAW file,
+/// limited to `max_edge`. Returns `None` when the file has no preview
+/// large enough to edit against.
+pub fn decode_embedded_preview(path: &Path, max_edge: Option<u32>) -> Result<Option<ImageBuf>> {
+    /// Smaller previews (typically the 160px EXIF thumbnail) look worse
+    /// than waiting for the full decode.
+    const MIN_EDGE: u32 = 1024;
+
+    let t0 = std::time::Instant::now();
+    let source = rawler::rawsource::RawSource::new(path)
+        .with_context(|| format!("failed to open RAW: {}", path.display()))?;
+    let decoder = rawler::get_decoder(&source)
+        .with_context(|| format!("unsupported RAW: {}", path.display()))?;
+    let params = rawler::decoders::RawDecodeParams::default();
+
+    let mut best: Option<image::DynamicImage> = None;
+    for candidate in [
+        decoder.preview_image(&source, &params),
+        decoder.full_image(&source, &params),
+    ] {
+        if let Ok(Some(img)) = candidate
+            && best
+                .as_ref()
+                .is_none_or(|b| img.width().max(img.height()) > b.width().max(b.height()))
+        {
+            best = Some(img);
+        }
+    }
+    let Some(img) = best.filter(|img| img.width().max(img.height()) >= MIN_EDGE) else {
+        return Ok(None);
+    };
+    debug!(
+        elapsed_ms = t0.elapsed().as_millis(),
+        width = img.width(),
+        height = img.height(),
+        "embedded preview"
+    );
+    dynamic_to_linear(img, max_edge).map(Some)
+}
+
 /// Load any supported image file (RAW or standard).
 pub fn load_any(path: &Path) -> Result<ImageBuf> {
     load_any_scaled(path, None)
@@ -131,18 +213,50 @@ pub fn load_any(path: &Path) -> Result<ImageBuf> {
 /// before converting to linear, avoiding work on pixels we'd discard.
 /// For RAW files, we must decode at full resolution (rawler doesn't
 /// support partial decode), then downsample in linear space.
+///
+/// The decoder is chosen by the [`registry`].
 pub fn load_any_scaled(path: &Path, max_edge: Option<u32>) -> Result<ImageBuf> {
-    let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+    registry::registry().decode(path, max_edge)
+}
 
-    if is_raw_extension(ext) {
-        let buf = decode_raw(path)?;
-        match max_edge {
-            Some(max) if buf.width.max(buf.height) > max => Ok(buf.downsample(max)),
-            _ => Ok(buf),
-        }
-    } else {
-        load_image_scaled(path, max_edge)
+/// Load frame `frame` of an animated GIF or PNG, optionally limiting the
+/// longest edge to `max_edge` pixels. Frame 0 is what [`load_any_scaled`]
+/// shows, so it goes through the registry like any other file.
+pub fn load_frame_scaled(path: &Path, frame: u32, max_edge: Option<u32>) -> Result<ImageBuf> {
+    if frame == 0 {
+        return load_any_scaled(path, max_edge);
+    }
+    dynamic_to_linear(animated::load_frame(path, frame)?, max_edge)
+}
+
+/// [`load_frame_scaled`] at full size, making `corrections` to a RAW
+/// file's photosites before demosaic. Other files have no photosites to
+/// correct and load as they are.
+pub fn load_frame_corrected(
+    path: &Path,
+    frame: u32,
+    corrections: &SensorCorrections,
+) -> Result<ImageBuf> {
+    let raw = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(is_raw_extension);
+    if corrections.is_empty() || frame != 0 || !raw {
+        return load_frame_scaled(path, frame, None);
     }
+    decode_raw_corrected(path, corrections)
+}
+
+/// How many frames `path` holds. Anything that isn't an animated GIF or
+/// PNG has one.
+pub fn frame_count(path: &Path) -> Result<u32> {
+    animated::frame_count(path)
+}
+
+/// A quick stand-in for `path` to show and edit while the full decode
+/// runs, if its format carries one. See [`registry::Decoder::embedded_preview`].
+pub fn load_embedded_preview(path: &Path, max_edge: Option<u32>) -> Option<ImageBuf> {
+    registry::registry().embedded_preview(path, max_edge)
 }
 
 /// Perfect 256-entry LUT for u8 sRGB -> linear f32 (used by load_image).
@@ -190,6 +304,7 @@ mod tests {
         assert!(is_supported_extension("CR2"));
         assert!(is_supported_extension("jpg"));
         assert!(is_supported_extension("PNG"));
+        assert!(is_supported_extension("gif"));
         assert!(!is_supported_extension("mp4"));
         assert!(is_raw_extension("nef"));
         assert!(!is_raw_extension("jpeg"));
@@ -207,6 +322,25 @@ mod tests {
         assert!((white - 1.0).abs() < 0.001);
     }
 
+    #[test]
+    fn scaled_16_bit_images_keep_their_precision() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("scan.tif");
+        // Alternating columns one 16-bit step apart survive a 2x downscale
+        // as a level between two 8-bit codes.
+        let img =
+            image::ImageBuffer::from_fn(64, 8, |x, _| image::Rgb([30000u16 + (x % 2) as u16; 3]));
+        img.save(&path).unwrap();
+
+        let full = load_image_scaled(&path, None).unwrap();
+        let scaled = load_image_scaled(&path, Some(32)).unwrap();
+        assert_eq!((scaled.width, scaled.height), (32, 4));
+        let as_8_bit = |v: f32| (v * 255.0).round() / 255.0;
+        let srgb = crate::color::linear_to_srgb(scaled.data[0]);
+        assert!((srgb - as_8_bit(srgb)).abs() > 1e-4, "{srgb}");
+        assert!(full.data[0] < full.data[3]);
+    }
+
     #[test]
     fn u8_lut_matches_exact() {
         let lut = &*SRGB_U8_TO_LINEAR;
@@ -0,0 +1,416 @@
+//! Decoders register here by extension and magic bytes, so a new format is
+//! one `Decoder` impl plus a line in [`DecoderRegistry::with_builtin`].
+
+use std::io::Read;
+use std::path::Path;
+use std::sync::LazyLock;
+
+use anyhow::{Context, Result, anyhow};
+use tracing::debug;
+
+use crate::image_buf::ImageBuf;
+
+use super::{IMAGE_EXTENSIONS, RAW_EXTENSIONS};
+
+/// Bytes read from the start of a file for magic-number sniffing.
+const HEADER_LEN: usize = 32;
+
+/// What a decoder's format offers beyond a plain full-resolution decode.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DecoderCapabilities {
+    /// Sensor data that goes through demosaicing and our RAW defaults.
+    pub raw: bool,
+    /// Files usually carry an embedded JPEG preview that can be shown
+    /// before the full decode finishes.
+    pub embedded_preview: bool,
+    /// Can produce a reduced-size image without decoding every pixel.
+    pub partial_decode: bool,
+}
+
+/// A source of linear f32 RGB images for one family of file formats.
+pub trait Decoder: Send + Sync {
+    fn name(&self) -> &str;
+
+    /// Lowercase extensions this decoder claims.
+    fn extensions(&self) -> &[&str];
+
+    /// Whether the leading bytes of a file identify this decoder's format.
+    /// Used when the extension is missing or unknown.
+    fn matches_magic(&self, _header: &[u8]) -> bool {
+        false
+    }
+
+    /// Higher priorities are tried first when several decoders claim a file.
+    fn priority(&self) -> i32 {
+        0
+    }
+
+    fn capabilities(&self) -> DecoderCapabilities;
+
+    /// Decode `path`, optionally limiting the longest edge to `max_edge`.
+    fn decode(&self, path: &Path, max_edge: Option<u32>) -> Result<ImageBuf>;
+
+    /// A reduced-fidelity image that is much faster to get than
+    /// [`Decoder::decode`], such as a RAW file's embedded JPEG. Only called
+    /// when [`DecoderCapabilities::embedded_preview`] is set.
+    fn embedded_preview(&self, _path: &Path, _max_edge: Option<u32>) -> Result<Option<ImageBuf>> {
+        Ok(None)
+    }
+}
+
+#[derive(Default)]
+pub struct DecoderRegistry {
+    decoders: Vec<Box<dyn Decoder>>,
+}
+
+impl DecoderRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The decoders crema ships with.
+    pub fn with_builtin() -> Self {
+        let mut registry = Self::new();
+        registry.register(RawlerDecoder);
+        registry.register(StandardImageDecoder);
+        registry
+    }
+
+    pub fn register(&mut self, decoder: impl Decoder + 'static) {
+        self.decoders.push(Box::new(decoder));
+        // Stable, so equal priorities keep registration order.
+        self.decoders
+            .sort_by_key(|decoder| std::cmp::Reverse(decoder.priority()));
+    }
+
+    pub fn decoders(&self) -> impl Iterator<Item = &dyn Decoder> {
+        self.decoders.iter().map(|d| d.as_ref())
+    }
+
+    /// The highest-priority decoder claiming `ext`.
+    pub fn for_extension(&self, ext: &str) -> Option<&dyn Decoder> {
+        let ext = ext.to_ascii_lowercase();
+        self.decoders()
+            .find(|d| d.extensions().contains(&ext.as_str()))
+    }
+
+    pub fn supports_extension(&self, ext: &str) -> bool {
+        self.for_extension(ext).is_some()
+    }
+
+    /// Decoders to try for a file, best first: those claiming its extension,
+    /// then those recognizing its header. A RAW extension never falls back to
+    /// a non-RAW decoder, since most RAWs are TIFFs whose first image is a
+    /// small preview that would otherwise be decoded as the photo.
+    pub fn candidates(&self, path: &Path, header: &[u8]) -> Vec<&dyn Decoder> {
+        let ext = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or("")
+            .to_ascii_lowercase();
+        let by_ext: Vec<_> = self
+            .decoders()
+            .filter(|d| d.extensions().contains(&ext.as_str()))
+            .collect();
+        let raw_only = by_ext.iter().any(|d| d.capabilities().raw);
+        let by_magic = self.decoders().filter(|d| {
+            !d.extensions().contains(&ext.as_str())
+                && (!raw_only || d.capabilities().raw)
+                && d.matches_magic(header)
+        });
+        by_ext.into_iter().chain(by_magic).collect()
+    }
+
+    /// The first embedded preview any candidate can provide. Failures are
+    /// logged and skipped; the full decode reports real errors.
+    pub fn embedded_preview(&self, path: &Path, max_edge: Option<u32>) -> Option<ImageBuf> {
+        let header = read_header(path).ok()?;
+        self.candidates(path, &header)
+            .into_iter()
+            .filter(|d| d.capabilities().embedded_preview)
+            .find_map(|decoder| match decoder.embedded_preview(path, max_edge) {
+                Ok(preview) => preview,
+                Err(err) => {
+                    debug!(decoder = decoder.name(), %err, "no embedded preview");
+                    None
+                }
+            })
+    }
+
+    /// Decode with the first candidate that succeeds. If none do, the
+    /// error from the best candidate is returned.
+    pub fn decode(&self, path: &Path, max_edge: Option<u32>) -> Result<ImageBuf> {
+        let header = read_header(path)?;
+        let mut first_err = None;
+        for decoder in self.candidates(path, &header) {
+            match decoder.decode(path, max_edge) {
+                Ok(buf) => return Ok(buf),
+                Err(err) => {
+                    debug!(decoder = decoder.name(), %err, "decoder failed, trying next");
+                    first_err.get_or_insert(err);
+                }
+            }
+        }
+        Err(first_err
+            .unwrap_or_else(|| anyhow!("no decoder for {}", path.display()))
+            .context(format!("failed to decode {}", path.display())))
+    }
+}
+
+/// The process-wide registry of built-in decoders.
+pub fn registry() -> &'static DecoderRegistry {
+    static REGISTRY: LazyLock<DecoderRegistry> = LazyLock::new(DecoderRegistry::with_builtin);
+    &REGISTRY
+}
+
+fn read_header(path: &Path) -> Result<Vec<u8>> {
+    let file =
+        std::fs::File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+    let mut header = Vec::with_capacity(HEADER_LEN);
+    file.take(HEADER_LEN as u64)
+        .read_to_end(&mut header)
+        .with_context(|| format!("failed to read {}", path.display()))?;
+    Ok(header)
+}
+
+/// Camera RAW formats via rawler.
+struct RawlerDecoder;
+
+impl Decoder for RawlerDecoder {
+    fn name(&self) -> &str {
+        "rawler"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        RAW_EXTENSIONS
+    }
+
+    /// Only signatures that can't be mistaken for an ordinary TIFF; plain
+    /// TIFF-based RAWs (NEF, ARW, DNG) need their extension.
+    fn matches_magic(&self, header: &[u8]) -> bool {
+        header.starts_with(b"FUJIFILMCCD-RAW")
+            || header.starts_with(b"IIRO")
+            || header.starts_with(b"IIU\0")
+            || header.get(4..12) == Some(b"ftypcrx ")
+    }
+
+    fn priority(&self) -> i32 {
+        10
+    }
+
+    fn capabilities(&self) -> DecoderCapabilities {
+        DecoderCapabilities {
+            raw: true,
+            embedded_preview: true,
+            partial_decode: false,
+        }
+    }
+
+    fn decode(&self, path: &Path, max_edge: Option<u32>) -> Result<ImageBuf> {
+        let buf = super::decode_raw(path)?;
+        match max_edge {
+            Some(max) if buf.width.max(buf.height) > max => Ok(buf.downsample(max)),
+            _ => Ok(buf),
+        }
+    }
+
+    fn embedded_preview(&self, path: &Path, max_edge: Option<u32>) -> Result<Option<ImageBuf>> {
+        super::decode_embedded_preview(path, max_edge)
+    }
+}
+
+/// JPEG, PNG and TIFF via the `image` crate.
+struct StandardImageDecoder;
+
+impl Decoder for StandardImageDecoder {
+    fn name(&self) -> &str {
+        "image"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        IMAGE_EXTENSIONS
+    }
+
+    fn matches_magic(&self, header: &[u8]) -> bool {
+        header.starts_with(&[0xFF, 0xD8, 0xFF])
+            || header.starts_with(b"\x89PNG\r\n\x1a\n")
+            || header.starts_with(b"II*\0")
+            || header.starts_with(b"MM\0*")
+            || header.starts_with(b"GIF8")
+    }
+
+    fn capabilities(&self) -> DecoderCapabilities {
+        DecoderCapabilities::default()
+    }
+
+    fn decode(&self, path: &Path, max_edge: Option<u32>) -> Result<ImageBuf> {
+        super::load_image_scaled(path, max_edge)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct FakeDecoder {
+        name: &'static str,
+        priority: i32,
+        magic: &'static [u8],
+        fails: bool,
+        preview: bool,
+    }
+
+    impl Decoder for FakeDecoder {
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        fn extensions(&self) -> &[&str] {
+            &["fake"]
+        }
+
+        fn matches_magic(&self, header: &[u8]) -> bool {
+            !self.magic.is_empty() && header.starts_with(self.magic)
+        }
+
+        fn priority(&self) -> i32 {
+            self.priority
+        }
+
+        fn capabilities(&self) -> DecoderCapabilities {
+            DecoderCapabilities {
+                embedded_preview: self.preview,
+                ..DecoderCapabilities::default()
+            }
+        }
+
+        fn decode(&self, _path: &Path, _max_edge: Option<u32>) -> Result<ImageBuf> {
+            if self.fails {
+                anyhow::bail!("{} can't decode this", self.name);
+            }
+            ImageBuf::from_data(1, 1, vec![self.priority as f32; 3])
+        }
+
+        fn embedded_preview(
+            &self,
+            _path: &Path,
+            _max_edge: Option<u32>,
+        ) -> Result<Option<ImageBuf>> {
+            if self.fails {
+                anyhow::bail!("{} has no readable preview", self.name);
+            }
+            ImageBuf::from_data(1, 1, vec![-self.priority as f32; 3]).map(Some)
+        }
+    }
+
+    fn fake(name: &'static str, priority: i32, fails: bool) -> FakeDecoder {
+        FakeDecoder {
+            name,
+            priority,
+            magic: b"",
+            fails,
+            preview: true,
+        }
+    }
+
+    #[test]
+    fn builtin_claims_known_extensions() {
+        let registry = DecoderRegistry::with_builtin();
+        assert_eq!(registry.for_extension("NEF").unwrap().name(), "rawler");
+        assert_eq!(registry.for_extension("jpg").unwrap().name(), "image");
+        assert!(registry.for_extension("mp4").is_none());
+        assert!(registry.for_extension("cr3").unwrap().capabilities().raw);
+    }
+
+    #[test]
+    fn higher_priority_wins() {
+        let mut registry = DecoderRegistry::new();
+        registry.register(fake("low", 1, false));
+        registry.register(fake("high", 5, false));
+        assert_eq!(registry.for_extension("fake").unwrap().name(), "high");
+    }
+
+    #[test]
+    fn falls_back_when_best_decoder_fails() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("x.fake");
+        std::fs::write(&path, b"data").unwrap();
+
+        let mut registry = DecoderRegistry::new();
+        registry.register(fake("broken", 9, true));
+        registry.register(fake("working", 2, false));
+        let buf = registry.decode(&path, None).unwrap();
+        assert_eq!(buf.data[0], 2.0);
+
+        let mut all_broken = DecoderRegistry::new();
+        all_broken.register(fake("broken", 9, true));
+        let err = all_broken.decode(&path, None).unwrap_err();
+        assert!(format!("{err:#}").contains("broken can't decode"));
+    }
+
+    #[test]
+    fn embedded_preview_skips_failing_and_incapable_decoders() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("x.fake");
+        std::fs::write(&path, b"data").unwrap();
+
+        let mut registry = DecoderRegistry::new();
+        registry.register(fake("broken", 9, true));
+        registry.register(FakeDecoder {
+            preview: false,
+            ..fake("no-preview", 5, false)
+        });
+        registry.register(fake("preview", 2, false));
+        let preview = registry.embedded_preview(&path, None).unwrap();
+        assert_eq!(preview.data[0], -2.0);
+
+        let jpeg = dir.path().join("plain.jpg");
+        std::fs::write(&jpeg, b"\xFF\xD8\xFF").unwrap();
+        assert!(
+            DecoderRegistry::with_builtin()
+                .embedded_preview(&jpeg, None)
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn sniffs_magic_when_extension_is_unknown() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("no_extension");
+        std::fs::write(&path, b"\x89PNG\r\n\x1a\nrest").unwrap();
+
+        let registry = DecoderRegistry::with_builtin();
+        let header = read_header(&path).unwrap();
+        let names: Vec<_> = registry
+            .candidates(&path, &header)
+            .iter()
+            .map(|d| d.name().to_string())
+            .collect();
+        assert_eq!(names, ["image"]);
+    }
+
+    #[test]
+    fn rawler_magic_skips_plain_tiff() {
+        assert!(!RawlerDecoder.matches_magic(b"II*\0\x08\0\0\0"));
+        assert!(RawlerDecoder.matches_magic(b"FUJIFILMCCD-RAW 0201"));
+        assert!(RawlerDecoder.matches_magic(b"\0\0\0\x18ftypcrx \0\0\0\x01"));
+        assert!(StandardImageDecoder.matches_magic(b"II*\0\x08\0\0\0"));
+    }
+
+    #[test]
+    fn raw_extensions_never_fall_back_to_tiff() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("broken.nef");
+        std::fs::write(&path, b"II*\0\x08\0\0\0not really a nef").unwrap();
+
+        let registry = DecoderRegistry::with_builtin();
+        let header = read_header(&path).unwrap();
+        let names: Vec<_> = registry
+            .candidates(&path, &header)
+            .iter()
+            .map(|d| d.name().to_string())
+            .collect();
+        assert_eq!(names, ["rawler"]);
+        assert!(registry.decode(&path, None).is_err());
+    }
+}
@@ -0,0 +1,178 @@
+//! A RAW file's sensor data before demosaic, while each photosite still
+//! holds the one color its filter passed. Defects are corrected here:
+//! after demosaic a stuck photosite has already bled into the colors of
+//! every pixel around it.
+
+use std::path::Path;
+
+use anyhow::{Context, Result, bail};
+use rawler::rawimage::{RawImage, RawImageData, RawPhotometricInterpretation};
+use tracing::{debug, warn};
+
+use crate::dark_frame::MasterDark;
+use crate::defects::DefectMap;
+
+/// Photosites of a sensor behind a color filter array, scaled so black is
+/// 0 and the white level is 1.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SensorData {
+    pub width: u32,
+    pub height: u32,
+    pub data: Vec<f32>,
+    /// Filter color at each position of the repeating pattern, row-major.
+    pub pattern: Vec<u8>,
+    pub pattern_width: u32,
+    pub pattern_height: u32,
+}
+
+impl SensorData {
+    pub fn color_at(&self, x: u32, y: u32) -> u8 {
+        let row = y % self.pattern_height;
+        let col = x % self.pattern_width;
+        self.pattern[(row * self.pattern_width + col) as usize]
+    }
+
+    /// Photosites within two positions of `(x, y)` under the same filter
+    /// color, the nearest that recorded the same light. That covers Bayer
+    /// and X-Trans layouts alike.
+    pub fn same_color_neighbors(&self, x: u32, y: u32) -> impl Iterator<Item = (u32, u32)> + '_ {
+        let color = self.color_at(x, y);
+        let (w, h) = (self.width as i64, self.height as i64);
+        (-2i64..=2)
+            .flat_map(|dy| (-2i64..=2).map(move |dx| (dx, dy)))
+            .filter(|&(dx, dy)| dx != 0 || dy != 0)
+            .map(move |(dx, dy)| (x as i64 + dx, y as i64 + dy))
+            .filter(move |&(nx, ny)| nx >= 0 && ny >= 0 && nx < w && ny < h)
+            .map(|(nx, ny)| (nx as u32, ny as u32))
+            .filter(move |&(nx, ny)| self.color_at(nx, ny) == color)
+    }
+
+    /// Take the photosites out of `raw`, scaled to its black and white
+    /// levels, or `None` for a sensor without a color filter array such as
+    /// a linear DNG. [`SensorData::restore`] puts them back.
+    fn take(raw: &mut RawImage) -> Result<Option<Self>> {
+        let RawPhotometricInterpretation::Cfa(config) = &raw.photometric else {
+            return Ok(None);
+        };
+        if raw.cpp != 1 {
+            return Ok(None);
+        }
+        let (pattern_width, pattern_height) = (config.cfa.width as u32, config.cfa.height as u32);
+        let pattern = config.cfa.flat_pattern();
+        raw.apply_scaling().context("failed to scale sensor data")?;
+        let data = match std::mem::replace(&mut raw.data, RawImageData::Float(Vec::new())) {
+            RawImageData::Float(data) => data,
+            RawImageData::Integer(data) => data.into_iter().map(f32::from).collect(),
+        };
+        Ok(Some(Self {
+            width: raw.width as u32,
+            height: raw.height as u32,
+            data,
+            pattern,
+            pattern_width,
+            pattern_height,
+        }))
+    }
+
+    /// Put corrected photosites back into the `raw` they were taken from,
+    /// which is now scaled to a black of 0 and white of 1.
+    fn restore(self, raw: &mut RawImage) {
+        raw.data = RawImageData::Float(self.data);
+    }
+}
+
+/// Corrections made to a RAW file's photosites before demosaic.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SensorCorrections<'a> {
+    pub dark: Option<&'a MasterDark>,
+    pub defects: Option<&'a DefectMap>,
+}
+
+impl SensorCorrections<'_> {
+    pub fn is_empty(&self) -> bool {
+        self.dark.is_none() && self.defects.is_none()
+    }
+
+    /// Subtract the dark first: it was recorded before any correction, and
+    /// a hot photosite's thermal signal shouldn't make it look stuck.
+    pub fn apply(&self, sensor: &mut SensorData) {
+        if let Some(dark) = self.dark
+            && !dark.subtract(sensor)
+        {
+            warn!(
+                dark = ?(dark.width, dark.height),
+                sensor = ?(sensor.width, sensor.height),
+                "master dark is for a different sensor size; skipped"
+            );
+        }
+        if let Some(map) = self.defects
+            && !map.apply(sensor)
+        {
+            warn!(
+                map = ?(map.width, map.height),
+                sensor = ?(sensor.width, sensor.height),
+                "dead pixel map is for a different sensor size; skipped"
+            );
+        }
+    }
+
+    /// Correct `raw` in place. Sensors without a color filter array are
+    /// left alone.
+    pub(super) fn apply_to_raw(&self, raw: &mut RawImage) -> Result<()> {
+        if self.is_empty() {
+            return Ok(());
+        }
+        match SensorData::take(raw)? {
+            Some(mut sensor) => {
+                self.apply(&mut sensor);
+                sensor.restore(raw);
+            }
+            None => debug!("no color filter array; sensor corrections skipped"),
+        }
+        Ok(())
+    }
+}
+
+/// The photosites of the RAW file at `path`, for measuring calibration
+/// frames.
+pub fn load_sensor_data(path: &Path) -> Result<SensorData> {
+    let mut raw = rawler::decode_file(path)
+        .with_context(|| format!("failed to decode RAW: {}", path.display()))?;
+    match SensorData::take(&mut raw)? {
+        Some(sensor) => Ok(sensor),
+        None => bail!("{} has no color filter array", path.display()),
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// An RGGB Bayer sensor of `width` x `height` photosites, all at
+    /// `level`.
+    pub(crate) fn bayer(width: u32, height: u32, level: f32) -> SensorData {
+        SensorData {
+            width,
+            height,
+            data: vec![level; (width * height) as usize],
+            pattern: vec![0, 1, 1, 2],
+            pattern_width: 2,
+            pattern_height: 2,
+        }
+    }
+
+    #[test]
+    fn neighbors_share_the_filter_color() {
+        let sensor = bayer(8, 8, 0.0);
+        let red: Vec<_> = sensor.same_color_neighbors(4, 4).collect();
+        assert_eq!(red.len(), 8);
+        assert!(red.iter().all(|&(x, y)| sensor.color_at(x, y) == 0));
+
+        // Green has its diagonal neighbors one position away, too.
+        let green: Vec<_> = sensor.same_color_neighbors(5, 4).collect();
+        assert_eq!(green.len(), 12);
+        assert!(green.contains(&(4, 5)));
+
+        assert_eq!(sensor.same_color_neighbors(0, 0).count(), 3);
+    }
+}
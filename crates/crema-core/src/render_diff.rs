@@ -0,0 +1,118 @@
+//! Comparison of two renders of the same edit, such as the CPU and GPU
+//! pipelines. The backends use different arithmetic, so small differences
+//! are expected; the heatmap shows whether larger ones are scattered
+//! rounding or concentrated in a region one backend gets wrong.
+
+use anyhow::{Result, bail};
+
+use crate::color::srgb_to_linear;
+use crate::image_buf::ImageBuf;
+use crate::pipeline::precision;
+
+/// Difference, in 8-bit levels, at which the heatmap saturates. Anything
+/// visible on screen is at least this far off.
+const HEATMAP_FULL_SCALE: f32 = 4.0;
+
+/// Differences are measured like
+/// [`PrecisionReport`](crate::pipeline::precision::PrecisionReport).
+#[derive(Debug, Clone)]
+pub struct RenderDiff {
+    pub max_levels: f32,
+    pub mean_levels: f32,
+    /// Pixel of the largest difference.
+    pub worst_pixel: (u32, u32),
+    /// Share of pixels that would round to a different 8-bit output.
+    pub differing_fraction: f32,
+    /// The per-pixel difference: black where the renders agree, through
+    /// blue and yellow to red at `HEATMAP_FULL_SCALE` levels and beyond.
+    pub heatmap: ImageBuf,
+}
+
+/// Compare two renders of the same size.
+pub fn compare(a: &ImageBuf, b: &ImageBuf) -> Result<RenderDiff> {
+    if (a.width, a.height) != (b.width, b.height) {
+        bail!(
+            "renders differ in size: {}x{} and {}x{}",
+            a.width,
+            a.height,
+            b.width,
+            b.height
+        );
+    }
+    let mut heatmap = ImageBuf::new(a.width, a.height);
+    let mut heat = heatmap.data.chunks_exact_mut(3);
+    let deviation = precision::compare(&a.data, &b.data, |levels| {
+        if let Some(out) = heat.next() {
+            out.copy_from_slice(&heat_color(levels as f32 / HEATMAP_FULL_SCALE));
+        }
+    });
+    let pixels = a.pixel_count().max(1);
+    let width = a.width.max(1);
+    Ok(RenderDiff {
+        max_levels: deviation.max_levels as f32,
+        mean_levels: deviation.mean_levels as f32,
+        worst_pixel: (
+            deviation.worst as u32 % width,
+            deviation.worst as u32 / width,
+        ),
+        differing_fraction: deviation.differing as f32 / pixels as f32,
+        heatmap,
+    })
+}
+
+/// Linear RGB for `t` in [0, 1] along black, blue, yellow, red. Stops are
+/// spaced evenly in display space so the ramp reads evenly on screen.
+fn heat_color(t: f32) -> [f32; 3] {
+    const STOPS: [[f32; 3]; 4] = [
+        [0.0, 0.0, 0.0],
+        [0.1, 0.3, 1.0],
+        [1.0, 0.9, 0.1],
+        [1.0, 0.1, 0.05],
+    ];
+    let t = t.clamp(0.0, 1.0) * (STOPS.len() - 1) as f32;
+    let i = (t as usize).min(STOPS.len() - 2);
+    let f = t - i as f32;
+    let (lo, hi) = (STOPS[i], STOPS[i + 1]);
+    [0, 1, 2].map(|c| srgb_to_linear(lo[c] + (hi[c] - lo[c]) * f))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::linear_to_srgb;
+
+    fn flat(w: u32, h: u32, v: f32) -> ImageBuf {
+        ImageBuf::from_data(w, h, vec![v; (w * h * 3) as usize]).unwrap()
+    }
+
+    #[test]
+    fn identical_renders_agree() {
+        let a = flat(8, 4, 0.3);
+        let diff = compare(&a, &a).unwrap();
+        assert_eq!(diff.max_levels, 0.0);
+        assert_eq!(diff.differing_fraction, 0.0);
+        assert!(diff.heatmap.data.iter().all(|&v| v == 0.0));
+    }
+
+    #[test]
+    fn locates_and_scales_a_discrepancy() {
+        let a = flat(8, 4, 0.2);
+        let mut b = a.clone();
+        let i = ((2 * 8 + 5) * 3) as usize;
+        b.data[i + 1] = srgb_to_linear(linear_to_srgb(0.2) + 10.0 / 255.0);
+
+        let diff = compare(&a, &b).unwrap();
+        assert!((diff.max_levels - 10.0).abs() < 0.01, "{}", diff.max_levels);
+        assert_eq!(diff.worst_pixel, (5, 2));
+        assert!((diff.differing_fraction - 1.0 / 32.0).abs() < 1e-6);
+        // Saturated red at the discrepancy, black elsewhere.
+        let hot = &diff.heatmap.data[i..i + 3];
+        assert!(hot[0] > 0.9 && hot[2] < 0.01, "{hot:?}");
+        assert!(diff.heatmap.data[..3].iter().all(|&v| v == 0.0));
+    }
+
+    #[test]
+    fn rejects_mismatched_sizes() {
+        assert!(compare(&flat(4, 4, 0.0), &flat(4, 5, 0.0)).is_err());
+    }
+}
@@ -0,0 +1,340 @@
+//! Scanner border detection. A print on a flatbed sits somewhere on a
+//! plain white lid or black bed, usually a degree or two off square; this
+//! finds the print, its skew and its aspect ratio, and turns them into the
+//! straighten and crop parameters that trim the bed away.
+
+use crate::color::linear_to_srgb;
+use crate::image_buf::{EditParams, ImageBuf};
+
+/// Longest edge analyzed; borders are found to within a pixel or two of
+/// this, well inside the inset.
+const ANALYSIS_EDGE: u32 = 512;
+/// Fraction of each edge sampled to estimate the bed's color.
+const BED_MARGIN: f32 = 0.02;
+/// Smallest display-space difference from the bed treated as print.
+const MIN_THRESHOLD: f32 = 0.08;
+/// Skew searched either side of square, in degrees.
+const MAX_SKEW: f32 = 5.0;
+/// A row or column belongs to the print when at least this share of the
+/// fullest one is print; dust and scratches on the bed fall far short.
+const EDGE_DENSITY: f32 = 0.4;
+/// Trimmed from each side of the found print, as a fraction of its size,
+/// so no sliver of bed or the print's shadow survives.
+const INSET: f32 = 0.006;
+/// Aspect ratios snapped to when the print is within `ASPECT_TOLERANCE`
+/// of one, covering common print and instant film sizes.
+const PRINT_ASPECTS: [(u32, u32); 6] = [(1, 1), (5, 4), (4, 3), (7, 5), (3, 2), (16, 9)];
+const ASPECT_TOLERANCE: f32 = 0.02;
+
+/// Where the print sits in the scan, in `EditParams` terms.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScanCrop {
+    /// Straighten angle in degrees.
+    pub rotation: f32,
+    pub crop_x: f32,
+    pub crop_y: f32,
+    pub crop_w: f32,
+    pub crop_h: f32,
+    /// The print size the crop was snapped to, as width:height.
+    pub aspect: Option<(u32, u32)>,
+}
+
+impl ScanCrop {
+    pub fn apply(&self, params: &mut EditParams) {
+        params.rotation = self.rotation;
+        params.crop_x = self.crop_x;
+        params.crop_y = self.crop_y;
+        params.crop_w = self.crop_w;
+        params.crop_h = self.crop_h;
+    }
+}
+
+/// Find the print inside a flatbed scan. `None` when the scan has no
+/// uniform border to remove: the print fills the frame, or nothing stands
+/// out from the bed.
+pub fn detect(buf: &ImageBuf) -> Option<ScanCrop> {
+    let small = buf.downsample(ANALYSIS_EDGE);
+    let (w, h) = (small.width as usize, small.height as usize);
+    if w < 16 || h < 16 {
+        return None;
+    }
+    let display: Vec<[f32; 3]> = small
+        .data
+        .chunks_exact(3)
+        .map(|p| [0, 1, 2].map(|c| linear_to_srgb(p[c].clamp(0.0, 1.0))))
+        .collect();
+
+    let (bed, spread) = bed_color(&display, w, h);
+    let threshold = (spread * 6.0).max(MIN_THRESHOLD);
+    let print: Vec<(f32, f32)> = display
+        .iter()
+        .enumerate()
+        .filter(|(_, p)| (0..3).any(|c| (p[c] - bed[c]).abs() > threshold))
+        .map(|(i, _)| ((i % w) as f32 + 0.5, (i / w) as f32 + 0.5))
+        .collect();
+    if print.len() < w * h / 50 {
+        return None;
+    }
+
+    let rotation = estimate_skew(&print, w, h);
+    let (x0, x1, y0, y1) = print_bounds(&print, w, h, rotation)?;
+    let (inset_x, inset_y) = ((x1 - x0) * INSET, (y1 - y0) * INSET);
+    let (mut x0, mut x1) = (x0 + inset_x, x1 - inset_x);
+    let (mut y0, mut y1) = (y0 + inset_y, y1 - inset_y);
+    let (wf, hf) = (w as f32, h as f32);
+    if x0 <= wf * BED_MARGIN && x1 >= wf * (1.0 - BED_MARGIN)
+        || y0 <= hf * BED_MARGIN && y1 >= hf * (1.0 - BED_MARGIN)
+    {
+        // No border along at least one axis: not a print on a bed.
+        return None;
+    }
+
+    let aspect = snap_aspect(x1 - x0, y1 - y0);
+    if let Some((aw, ah)) = aspect {
+        let ratio = aw as f32 / ah as f32;
+        let ratio = if x1 - x0 >= y1 - y0 {
+            ratio
+        } else {
+            1.0 / ratio
+        };
+        let (cx, cy) = ((x0 + x1) / 2.0, (y0 + y1) / 2.0);
+        if (x1 - x0) / (y1 - y0) > ratio {
+            let half = (y1 - y0) * ratio / 2.0;
+            (x0, x1) = (cx - half, cx + half);
+        } else {
+            let half = (x1 - x0) / ratio / 2.0;
+            (y0, y1) = (cy - half, cy + half);
+        }
+    }
+
+    let x0 = (x0 / wf).clamp(0.0, 1.0);
+    let y0 = (y0 / hf).clamp(0.0, 1.0);
+    Some(ScanCrop {
+        rotation,
+        crop_x: x0,
+        crop_y: y0,
+        crop_w: (x1 / wf).clamp(0.0, 1.0) - x0,
+        crop_h: (y1 / hf).clamp(0.0, 1.0) - y0,
+        aspect,
+    })
+}
+
+/// Median color of the outer margin, and the median distance from it as a
+/// measure of the bed's own noise and texture.
+fn bed_color(display: &[[f32; 3]], w: usize, h: usize) -> ([f32; 3], f32) {
+    let mx = ((w as f32 * BED_MARGIN).ceil() as usize).max(1);
+    let my = ((h as f32 * BED_MARGIN).ceil() as usize).max(1);
+    let margin: Vec<[f32; 3]> = display
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| {
+            let (x, y) = (i % w, i / w);
+            x < mx || x >= w - mx || y < my || y >= h - my
+        })
+        .map(|(_, &p)| p)
+        .collect();
+    let bed = [0, 1, 2].map(|c| median(margin.iter().map(|p| p[c]).collect()));
+    let spread = median(
+        margin
+            .iter()
+            .map(|p| (0..3).map(|c| (p[c] - bed[c]).abs()).fold(0.0, f32::max))
+            .collect(),
+    );
+    (bed, spread)
+}
+
+fn median(mut values: Vec<f32>) -> f32 {
+    if values.is_empty() {
+        return 0.0;
+    }
+    let mid = values.len() / 2;
+    *values.select_nth_unstable_by(mid, f32::total_cmp).1
+}
+
+/// The straighten angle that squares the print up: where its row and
+/// column profiles are sharpest. Searched coarse, then fine.
+fn estimate_skew(print: &[(f32, f32)], w: usize, h: usize) -> f32 {
+    let best_in = |from: f32, to: f32, step: f32| {
+        let steps = ((to - from) / step).round() as i32;
+        (0..=steps)
+            .map(|i| from + i as f32 * step)
+            .map(|angle| (angle, profile_sharpness(print, w, h, angle)))
+            // Ties go to the smaller correction.
+            .max_by(|a, b| a.1.total_cmp(&b.1).then(b.0.abs().total_cmp(&a.0.abs())))
+            .map_or(0.0, |(angle, _)| angle)
+    };
+    let coarse = best_in(-MAX_SKEW, MAX_SKEW, 0.25);
+    let fine = best_in(coarse - 0.25, coarse + 0.25, 0.05);
+    // Snap away float noise so a square scan reads exactly zero.
+    (fine * 100.0).round() / 100.0
+}
+
+/// Print coordinates as they land after straightening by `rotation`
+/// degrees, matching the crop module's rotation about the center.
+fn straightened(
+    print: &[(f32, f32)],
+    w: usize,
+    h: usize,
+    rotation: f32,
+) -> impl Iterator<Item = (f32, f32)> + '_ {
+    let (sin, cos) = rotation.to_radians().sin_cos();
+    let (cx, cy) = (w as f32 / 2.0, h as f32 / 2.0);
+    print.iter().map(move |&(x, y)| {
+        let (dx, dy) = (x - cx, y - cy);
+        (cx + cos * dx - sin * dy, cy + sin * dx + cos * dy)
+    })
+}
+
+/// Row and column histograms of the straightened print, one bin per
+/// pixel. Each point is split between the two nearest bins so a fraction
+/// of a degree still changes the profile.
+fn profiles(print: &[(f32, f32)], w: usize, h: usize, rotation: f32) -> (Vec<f32>, Vec<f32>) {
+    let mut cols = vec![0.0; w];
+    let mut rows = vec![0.0; h];
+    let splat = |bins: &mut [f32], at: f32| {
+        let at = at - 0.5;
+        let i = at.floor();
+        let f = at - i;
+        for (bin, weight) in [(i, 1.0 - f), (i + 1.0, f)] {
+            if bin >= 0.0 && (bin as usize) < bins.len() {
+                bins[bin as usize] += weight;
+            }
+        }
+    };
+    for (x, y) in straightened(print, w, h, rotation) {
+        splat(&mut cols, x);
+        splat(&mut rows, y);
+    }
+    (cols, rows)
+}
+
+/// Sum of squared profile counts: highest when the print's edges line up
+/// with the axes, so each edge lands in a single bin.
+fn profile_sharpness(print: &[(f32, f32)], w: usize, h: usize, rotation: f32) -> f64 {
+    let (cols, rows) = profiles(print, w, h, rotation);
+    cols.iter().chain(&rows).map(|&n| (n as f64).powi(2)).sum()
+}
+
+/// The straightened print's extent as `(x0, x1, y0, y1)` in pixels.
+fn print_bounds(
+    print: &[(f32, f32)],
+    w: usize,
+    h: usize,
+    rotation: f32,
+) -> Option<(f32, f32, f32, f32)> {
+    let (cols, rows) = profiles(print, w, h, rotation);
+    let span = |profile: &[f32]| {
+        let max = profile.iter().copied().fold(0.0, f32::max);
+        if max == 0.0 {
+            return None;
+        }
+        let dense = |&n: &f32| n >= max * EDGE_DENSITY;
+        let first = profile.iter().position(dense)?;
+        let last = profile.iter().rposition(dense)?;
+        Some((first as f32, last as f32 + 1.0))
+    };
+    let (x0, x1) = span(&cols)?;
+    let (y0, y1) = span(&rows)?;
+    Some((x0, x1, y0, y1))
+}
+
+/// The print size `width` x `height` is closest to, if it's close enough
+/// to be that size rather than a hand-trimmed print.
+fn snap_aspect(width: f32, height: f32) -> Option<(u32, u32)> {
+    let ratio = width.max(height) / width.min(height).max(1.0);
+    PRINT_ASPECTS
+        .iter()
+        .map(|&(a, b)| (a, b, (ratio / (a as f32 / b as f32) - 1.0).abs()))
+        .filter(|&(_, _, error)| error <= ASPECT_TOLERANCE)
+        .min_by(|a, b| a.2.total_cmp(&b.2))
+        .map(|(a, b, _)| if width >= height { (a, b) } else { (b, a) })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline::module::ProcessingModule;
+    use crate::pipeline::modules::Crop;
+
+    /// A `w` x `h` scan of a textured print `pw` x `ph` centered at
+    /// `(cx, cy)`, turned `skew` degrees on a bed of `bed` gray.
+    fn scan(w: u32, h: u32, print: (f32, f32, f32, f32), skew: f32, bed: f32) -> ImageBuf {
+        let (cx, cy, pw, ph) = print;
+        let (sin, cos) = skew.to_radians().sin_cos();
+        let mut buf = ImageBuf::new(w, h);
+        for (i, px) in buf.data.chunks_exact_mut(3).enumerate() {
+            let (x, y) = ((i as u32 % w) as f32 + 0.5, (i as u32 / w) as f32 + 0.5);
+            let (dx, dy) = (x - cx, y - cy);
+            let (u, v) = (cos * dx + sin * dy, -sin * dx + cos * dy);
+            let value = if u.abs() < pw / 2.0 && v.abs() < ph / 2.0 {
+                // Busy enough to look like a photo, never near the bed.
+                0.1 + 0.3 * ((u * 0.07).sin() * (v * 0.05).cos()).abs()
+            } else {
+                bed
+            };
+            px.copy_from_slice(&[value, value * 0.95, value * 0.9]);
+        }
+        buf
+    }
+
+    #[test]
+    fn finds_a_square_print_on_a_white_bed() {
+        let buf = scan(400, 300, (180.0, 140.0, 240.0, 160.0), 0.0, 0.9);
+        let crop = detect(&buf).unwrap();
+        assert_eq!(crop.rotation, 0.0);
+        assert_eq!(crop.aspect, Some((3, 2)));
+        let (x0, y0) = (crop.crop_x * 400.0, crop.crop_y * 300.0);
+        let (cw, ch) = (crop.crop_w * 400.0, crop.crop_h * 300.0);
+        assert!(
+            (x0 - 60.0).abs() < 4.0 && (y0 - 60.0).abs() < 4.0,
+            "{crop:?}"
+        );
+        assert!(
+            (cw - 240.0).abs() < 6.0 && (ch - 160.0).abs() < 6.0,
+            "{crop:?}"
+        );
+        assert!((cw / ch - 1.5).abs() < 0.01, "{crop:?}");
+    }
+
+    #[test]
+    fn straightens_a_skewed_print_and_crops_only_print() {
+        let (w, h) = (500, 400);
+        let buf = scan(w, h, (250.0, 200.0, 280.0, 200.0), 2.0, 0.95);
+        let crop = detect(&buf).unwrap();
+        // Straightening turns the print back the other way.
+        assert!((crop.rotation + 2.0).abs() < 0.15, "{crop:?}");
+
+        let mut params = EditParams::default();
+        crop.apply(&mut params);
+        let out = Crop.process_cpu(buf, &params).unwrap();
+        // Nothing of the bed is left anywhere in the result.
+        assert!(
+            out.data.iter().all(|&v| v < 0.5),
+            "bed left in a {}x{} crop",
+            out.width,
+            out.height
+        );
+        assert!(out.width > 260 && out.height > 185, "{crop:?}");
+    }
+
+    #[test]
+    fn works_on_a_black_bed_and_ignores_dust() {
+        let mut buf = scan(400, 300, (200.0, 150.0, 200.0, 200.0), 0.0, 0.0);
+        for &(x, y) in &[(10u32, 10u32), (380, 40), (30, 280)] {
+            let i = ((y * 400 + x) * 3) as usize;
+            buf.data[i..i + 3].copy_from_slice(&[0.8, 0.8, 0.8]);
+        }
+        let crop = detect(&buf).unwrap();
+        assert_eq!(crop.aspect, Some((1, 1)));
+        assert!((crop.crop_x * 400.0 - 100.0).abs() < 4.0, "{crop:?}");
+        assert!((crop.crop_w * 400.0 - 200.0).abs() < 6.0, "{crop:?}");
+    }
+
+    #[test]
+    fn full_frame_and_blank_scans_are_left_alone() {
+        let full = scan(300, 200, (150.0, 100.0, 300.0, 200.0), 0.0, 0.9);
+        assert_eq!(detect(&full), None);
+        let blank = ImageBuf::from_data(64, 64, vec![0.9; 64 * 64 * 3]).unwrap();
+        assert_eq!(detect(&blank), None);
+    }
+}
@@ -0,0 +1,392 @@
+//! Fixtures and helpers for end-to-end tests. The scenarios themselves
+//! live in `tests/`; they drive the catalog, pipeline, thumbnails and
+//! export the way the app does, against files generated here in a
+//! temporary directory, so breakage in the glue between crates shows up
+//! without a window.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, bail};
+
+use crema_catalog::db::Catalog;
+use crema_catalog::models::PhotoId;
+use crema_core::dither::Dither;
+use crema_core::image_buf::EditParams;
+use crema_core::preview::{self, Displayed};
+use crema_export::format::ExportEncoding;
+use crema_export::render::render_export;
+
+/// A scene with enough structure to notice when an edit or a crop goes
+/// astray: a diagonal gradient with a bright square in the top-left
+/// quarter.
+pub fn scene(width: u32, height: u32, tint: [u8; 3]) -> image::RgbImage {
+    image::RgbImage::from_fn(width, height, |x, y| {
+        if x < width / 4 && y < height / 4 {
+            return image::Rgb([240, 240, 240]);
+        }
+        let t = (x + y) as f32 / (width + height) as f32;
+        image::Rgb(tint.map(|c| (c as f32 * (0.2 + 0.6 * t)) as u8))
+    })
+}
+
+/// A 16-bit scene reaching full scale: a horizontal ramp from black to
+/// white over a vertical ramp of hue, with a row of saturated patches
+/// along the bottom. Highlights right at the top of the range are where
+/// tone mapping and HDR handling go wrong first.
+pub fn wide_scene(width: u32, height: u32) -> image::ImageBuffer<image::Rgb<u16>, Vec<u16>> {
+    const PATCHES: [[u16; 3]; 6] = [
+        [65535, 0, 0],
+        [0, 65535, 0],
+        [0, 0, 65535],
+        [65535, 65535, 0],
+        [0, 65535, 65535],
+        [65535, 0, 65535],
+    ];
+    image::ImageBuffer::from_fn(width, height, |x, y| {
+        if y >= height * 3 / 4 {
+            let patch = (x * PATCHES.len() as u32 / width) as usize;
+            return image::Rgb(PATCHES[patch]);
+        }
+        let level = x as f32 / (width - 1).max(1) as f32;
+        let hue = y as f32 / (height * 3 / 4) as f32;
+        let tint = [1.0, 1.0 - 0.5 * hue, 0.5 + 0.5 * hue];
+        image::Rgb(tint.map(|t| (level * t * 65535.0).round() as u16))
+    })
+}
+
+/// Write a fixture photo; the format follows the extension.
+pub fn write_photo(dir: &Path, name: &str, width: u32, height: u32, tint: [u8; 3]) -> PathBuf {
+    let path = dir.join(name);
+    scene(width, height, tint)
+        .save(&path)
+        .unwrap_or_else(|err| panic!("failed to write fixture {}: {err}", path.display()));
+    path
+}
+
+/// Write `wide_scene` as the sensor of a camera would have recorded it:
+/// a DNG of 16-bit photosites behind an RGGB Bayer filter, whose color
+/// matrix makes the camera's primaries those of sRGB. The name should end
+/// in `.dng`.
+pub fn write_raw(dir: &Path, name: &str, width: u32, height: u32) -> PathBuf {
+    use tiff::encoder::{SRational, TiffEncoder, colortype};
+    use tiff::tags::Tag;
+
+    // XYZ (D65) to linear sRGB, in ten-thousandths.
+    const XYZ_TO_CAMERA: [i32; 9] = [32406, -15372, -4986, -9689, 18758, 415, 557, -2040, 10570];
+    let scene = wide_scene(width, height);
+    let photosites: Vec<u16> = (0..height)
+        .flat_map(|y| (0..width).map(move |x| (x, y)))
+        .map(|(x, y)| {
+            let channel = match (y % 2, x % 2) {
+                (0, 0) => 0,
+                (1, 1) => 2,
+                _ => 1,
+            };
+            scene.get_pixel(x, y)[channel]
+        })
+        .collect();
+
+    let path = dir.join(name);
+    let write = || -> Result<()> {
+        let file = std::io::BufWriter::new(std::fs::File::create(&path)?);
+        let mut tiff = TiffEncoder::new(file)?;
+        let mut image = tiff.new_image::<colortype::Gray16>(width, height)?;
+        let ifd = image.encoder();
+        ifd.write_tag(Tag::PhotometricInterpretation, 32803u16)?;
+        ifd.write_tag(Tag::Unknown(33421), &[2u16, 2][..])?; // CFARepeatPatternDim
+        ifd.write_tag(Tag::Unknown(33422), &[0u8, 1, 1, 2][..])?; // CFAPattern
+        ifd.write_tag(Tag::Make, "Crema")?;
+        ifd.write_tag(Tag::Model, "Fixture")?;
+        ifd.write_tag(Tag::Unknown(50706), &[1u8, 4, 0, 0][..])?; // DNGVersion
+        ifd.write_tag(Tag::Unknown(50708), "Crema Fixture")?; // UniqueCameraModel
+        ifd.write_tag(Tag::Unknown(50714), 0u16)?; // BlackLevel
+        ifd.write_tag(Tag::Unknown(50717), u16::MAX)?; // WhiteLevel
+        let matrix = XYZ_TO_CAMERA.map(|n| SRational { n, d: 10000 });
+        ifd.write_tag(Tag::Unknown(50721), &matrix[..])?; // ColorMatrix1
+        ifd.write_tag(Tag::Unknown(50778), 21u16)?; // CalibrationIlluminant1: D65
+        image.write_data(&photosites)?;
+        Ok(())
+    };
+    write().unwrap_or_else(|err| panic!("failed to write fixture {}: {err:#}", path.display()));
+    path
+}
+
+/// A library on disk: a catalog file that can be closed and reopened like
+/// the app quitting and relaunching.
+pub struct Library {
+    pub catalog_path: PathBuf,
+    pub catalog: Catalog,
+}
+
+impl Library {
+    pub fn open(dir: &Path) -> Result<Self> {
+        let catalog_path = dir.join("catalog.db");
+        let catalog = Catalog::open(&catalog_path.to_string_lossy())?;
+        Ok(Self {
+            catalog_path,
+            catalog,
+        })
+    }
+
+    /// Close the catalog and open it again from disk.
+    pub fn relaunch(self) -> Result<Self> {
+        let Library {
+            catalog_path,
+            catalog,
+        } = self;
+        drop(catalog);
+        let catalog = Catalog::open(&catalog_path.to_string_lossy())?;
+        Ok(Self {
+            catalog_path,
+            catalog,
+        })
+    }
+
+    /// The stored edits for `path`, or the defaults if it has none.
+    pub fn edits_for(&self, path: &Path) -> Result<EditParams> {
+        let id = self.photo_id(path)?;
+        Ok(self
+            .catalog
+            .get_edits(id)?
+            .map(|e| e.to_edit_params())
+            .unwrap_or_default())
+    }
+
+    pub fn photo_id(&self, path: &Path) -> Result<PhotoId> {
+        let canonical = path.canonicalize()?.to_string_lossy().to_string();
+        self.catalog
+            .list_photos()?
+            .into_iter()
+            .find(|p| p.file_path == canonical)
+            .map(|p| p.id)
+            .with_context(|| format!("{} is not in the catalog", path.display()))
+    }
+}
+
+/// Render `source` with `params` and write it to `path` through the
+/// app's own export, in whatever format the extension names.
+pub fn export(source: &Path, params: &EditParams, dither: Dither, path: &Path) -> Result<()> {
+    let buf = crema_core::raw::load_any(source)?;
+    let encoding = ExportEncoding {
+        dither,
+        ..ExportEncoding::default()
+    };
+    render_export(buf, params, &[], &encoding, path).map_err(anyhow::Error::msg)?;
+    Ok(())
+}
+
+/// Mean 8-bit value of each channel in the image at `path`.
+pub fn mean_rgb(path: &Path) -> Result<[f32; 3]> {
+    let img = image::open(path)
+        .with_context(|| format!("failed to open {}", path.display()))?
+        .to_rgb8();
+    let n = (img.width() * img.height()).max(1) as f32;
+    let mut sum = [0.0f32; 3];
+    for p in img.pixels() {
+        for c in 0..3 {
+            sum[c] += p[c] as f32;
+        }
+    }
+    Ok(sum.map(|s| s / n))
+}
+
+/// What the develop view shows for `source` under `params`, decoded and
+/// then developed through the app's own preview chain.
+pub fn display(source: &Path, params: &EditParams) -> Result<Displayed> {
+    let decoded = crema_core::raw::load_frame_scaled(source, 0, None)?;
+    Ok(preview::display(
+        &preview::downsample(&decoded),
+        params,
+        None,
+    ))
+}
+
+/// Cells per side of the signature's thumbnail grid.
+const GRID: u32 = 8;
+/// Histogram bins per channel in a signature, coarse enough that a
+/// value rounding into the next 8-bit level doesn't register.
+const COARSE_BINS: usize = 16;
+/// Largest change in a grid cell's mean, in 8-bit levels, still treated
+/// as equal; absorbs float differences between platforms.
+const GRID_TOLERANCE: i32 = 2;
+/// Largest change in a coarse histogram bin, as a share of all pixels.
+const HISTOGRAM_TOLERANCE: f32 = 0.01;
+
+/// A compact, tolerant summary of a displayed frame to compare against a
+/// stored golden: its size, an 8x8 grid of mean colors and a coarse
+/// histogram. Small enough to review in a diff when it changes on
+/// purpose.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Signature {
+    pub width: u32,
+    pub height: u32,
+    pub grid: Vec<[u8; 3]>,
+    pub histogram: [[u32; COARSE_BINS]; 3],
+}
+
+impl Signature {
+    pub fn of(displayed: &Displayed) -> Self {
+        let (width, height) = (displayed.width, displayed.height);
+        let mut sums = vec![[0u64; 4]; (GRID * GRID) as usize];
+        for (i, pixel) in displayed.rgba.chunks_exact(4).enumerate() {
+            let (x, y) = (i as u32 % width, i as u32 / width);
+            let cell = &mut sums[((y * GRID / height) * GRID + x * GRID / width) as usize];
+            for c in 0..3 {
+                cell[c] += pixel[c] as u64;
+            }
+            cell[3] += 1;
+        }
+        let grid = sums
+            .iter()
+            .map(|cell| {
+                let n = cell[3].max(1);
+                [0, 1, 2].map(|c| ((cell[c] + n / 2) / n) as u8)
+            })
+            .collect();
+
+        let h = &displayed.histogram;
+        let histogram = [&h.r, &h.g, &h.b].map(|channel| {
+            let mut coarse = [0u32; COARSE_BINS];
+            for (level, count) in channel.iter().enumerate() {
+                coarse[level * COARSE_BINS / channel.len()] += count;
+            }
+            coarse
+        });
+        Self {
+            width,
+            height,
+            grid,
+            histogram,
+        }
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut out = format!("size {} {}\n", self.width, self.height);
+        for row in self.grid.chunks(GRID as usize) {
+            let cells: Vec<String> = row
+                .iter()
+                .map(|[r, g, b]| format!("{r:02x}{g:02x}{b:02x}"))
+                .collect();
+            out += &format!("grid {}\n", cells.join(" "));
+        }
+        for (name, bins) in ["r", "g", "b"].iter().zip(&self.histogram) {
+            let bins: Vec<String> = bins.iter().map(u32::to_string).collect();
+            out += &format!("hist {name} {}\n", bins.join(" "));
+        }
+        out
+    }
+
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut size = None;
+        let mut grid = Vec::new();
+        let mut histogram = Vec::new();
+        for line in text.lines().filter(|l| !l.trim().is_empty()) {
+            let mut words = line.split_whitespace();
+            match words.next() {
+                Some("size") => {
+                    let mut next = || -> Result<u32> {
+                        Ok(words.next().context("size needs two numbers")?.parse()?)
+                    };
+                    size = Some((next()?, next()?));
+                }
+                Some("grid") => {
+                    for cell in words {
+                        let value = u32::from_str_radix(cell, 16)
+                            .with_context(|| format!("bad grid cell {cell}"))?;
+                        grid.push([(value >> 16) as u8, (value >> 8) as u8, value as u8]);
+                    }
+                }
+                Some("hist") => {
+                    let bins = words
+                        .skip(1)
+                        .map(str::parse)
+                        .collect::<Result<Vec<u32>, _>>()?;
+                    let Ok(bins) = <[u32; COARSE_BINS]>::try_from(bins) else {
+                        bail!("histogram needs {COARSE_BINS} bins");
+                    };
+                    histogram.push(bins);
+                }
+                _ => bail!("unexpected line: {line}"),
+            }
+        }
+        let (width, height) = size.context("missing size")?;
+        if grid.len() != (GRID * GRID) as usize {
+            bail!("grid needs {} cells, found {}", GRID * GRID, grid.len());
+        }
+        let Ok(histogram) = <[[u32; COARSE_BINS]; 3]>::try_from(histogram) else {
+            bail!("expected three histogram lines");
+        };
+        Ok(Self {
+            width,
+            height,
+            grid,
+            histogram,
+        })
+    }
+
+    /// Where `self` strays from `golden` beyond the tolerances, one line
+    /// per difference. Empty when they match.
+    pub fn differences(&self, golden: &Signature) -> Vec<String> {
+        let mut found = Vec::new();
+        if (self.width, self.height) != (golden.width, golden.height) {
+            found.push(format!(
+                "size {}x{}, expected {}x{}",
+                self.width, self.height, golden.width, golden.height
+            ));
+            return found;
+        }
+        for (i, (got, want)) in self.grid.iter().zip(&golden.grid).enumerate() {
+            let off = (0..3).any(|c| (got[c] as i32 - want[c] as i32).abs() > GRID_TOLERANCE);
+            if off {
+                found.push(format!(
+                    "grid cell ({}, {}) is {got:?}, expected {want:?}",
+                    i as u32 % GRID,
+                    i as u32 / GRID
+                ));
+            }
+        }
+        let pixels = (self.width * self.height) as f32;
+        let limit = (pixels * HISTOGRAM_TOLERANCE).ceil() as i64;
+        for (c, (got, want)) in self.histogram.iter().zip(&golden.histogram).enumerate() {
+            for (bin, (g, w)) in got.iter().zip(want).enumerate() {
+                if (*g as i64 - *w as i64).abs() > limit {
+                    found.push(format!(
+                        "{} histogram bin {bin} holds {g} pixels, expected {w}",
+                        ["red", "green", "blue"][c]
+                    ));
+                }
+            }
+        }
+        found
+    }
+}
+
+/// Compare `signature` with the golden named `name` under
+/// `tests/golden/`. With `CREMA_UPDATE_GOLDEN` set the golden is written
+/// instead, for changes to the display chain that are meant to show.
+pub fn check_golden(name: &str, signature: &Signature) -> Result<()> {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("golden")
+        .join(format!("{name}.txt"));
+    if std::env::var_os("CREMA_UPDATE_GOLDEN").is_some() {
+        std::fs::create_dir_all(path.parent().expect("golden path has a parent"))?;
+        std::fs::write(&path, signature.to_text())?;
+        return Ok(());
+    }
+    let text = std::fs::read_to_string(&path).with_context(|| {
+        format!(
+            "missing golden {}; run with CREMA_UPDATE_GOLDEN=1 to record it",
+            path.display()
+        )
+    })?;
+    let golden = Signature::parse(&text)?;
+    let differences = signature.differences(&golden);
+    if !differences.is_empty() {
+        bail!(
+            "{name} no longer displays as recorded:\n  {}\nIf the change is intended, rerun with CREMA_UPDATE_GOLDEN=1 and review the diff.",
+            differences.join("\n  ")
+        );
+    }
+    Ok(())
+}
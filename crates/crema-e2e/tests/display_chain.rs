@@ -0,0 +1,120 @@
+//! Golden tests for the whole display chain: decode, pipeline, 8-bit sRGB
+//! conversion and histogram, compared with signatures stored under
+//! `tests/golden/`. A regression in any link shows up as a difference in
+//! what the user would see rather than only in that module's own tests.
+//! Rerun with `CREMA_UPDATE_GOLDEN=1` to record intended changes.
+
+use std::path::{Path, PathBuf};
+
+use crema_core::image_buf::EditParams;
+use crema_e2e::{Signature, check_golden, display, wide_scene, write_photo, write_raw};
+
+/// A 16-bit PNG reaching full scale and an 8-bit JPEG, covering both
+/// decode paths the develop view loads standard files through, and the
+/// same 16-bit scene as a Bayer DNG for the RAW path through rawler and
+/// demosaic.
+struct Fixtures {
+    wide: PathBuf,
+    jpeg: PathBuf,
+    raw: PathBuf,
+}
+
+fn fixtures(dir: &Path) -> Fixtures {
+    let wide = dir.join("wide.png");
+    wide_scene(96, 64).save(&wide).unwrap();
+    Fixtures {
+        wide,
+        jpeg: write_photo(dir, "scene.jpg", 96, 64, [200, 170, 120]),
+        raw: write_raw(dir, "scene.dng", 96, 64),
+    }
+}
+
+fn assert_golden(name: &str, source: &Path, params: &EditParams) {
+    let displayed = display(source, params).unwrap();
+    check_golden(name, &Signature::of(&displayed)).unwrap_or_else(|err| panic!("{err:#}"));
+}
+
+#[test]
+fn unedited_frames_display_as_recorded() {
+    let tmp = tempfile::tempdir().unwrap();
+    let fixtures = fixtures(tmp.path());
+    assert_golden("wide_unedited", &fixtures.wide, &EditParams::default());
+    assert_golden("jpeg_unedited", &fixtures.jpeg, &EditParams::default());
+    assert_golden("raw_unedited", &fixtures.raw, &EditParams::default());
+}
+
+#[test]
+fn tone_edits_display_as_recorded() {
+    let tmp = tempfile::tempdir().unwrap();
+    let fixtures = fixtures(tmp.path());
+    let params = EditParams {
+        exposure: 1.5,
+        contrast: 30.0,
+        highlights: -60.0,
+        shadows: 40.0,
+        blacks: -10.0,
+        ..EditParams::default()
+    };
+    assert_golden("wide_tone", &fixtures.wide, &params);
+    assert_golden("raw_tone", &fixtures.raw, &params);
+}
+
+#[test]
+fn color_edits_display_as_recorded() {
+    let tmp = tempfile::tempdir().unwrap();
+    let fixtures = fixtures(tmp.path());
+    let params = EditParams {
+        wb_temp: 4200.0,
+        wb_tint: 10.0,
+        vibrance: 30.0,
+        saturation: -20.0,
+        hsl_hue: 15.0,
+        hsl_saturation: 10.0,
+        hsl_lightness: -5.0,
+        split_shadow_hue: 210.0,
+        split_shadow_sat: 30.0,
+        split_highlight_hue: 40.0,
+        split_highlight_sat: 20.0,
+        ..EditParams::default()
+    };
+    assert_golden("wide_color", &fixtures.wide, &params);
+    assert_golden("jpeg_color", &fixtures.jpeg, &params);
+    assert_golden("raw_color", &fixtures.raw, &params);
+}
+
+#[test]
+fn detail_and_geometry_display_as_recorded() {
+    let tmp = tempfile::tempdir().unwrap();
+    let jpeg = fixtures(tmp.path()).jpeg;
+    let params = EditParams {
+        nr_luminance: 30.0,
+        nr_color: 20.0,
+        sharpen_amount: 60.0,
+        vignette_amount: -40.0,
+        distortion: 15.0,
+        rotation: 3.0,
+        crop_x: 0.1,
+        crop_y: 0.05,
+        crop_w: 0.8,
+        crop_h: 0.9,
+        ..EditParams::default()
+    };
+    assert_golden("jpeg_detail", &jpeg, &params);
+}
+
+#[test]
+fn signature_notices_a_small_exposure_change() {
+    let tmp = tempfile::tempdir().unwrap();
+    let wide = fixtures(tmp.path()).wide;
+    let before = Signature::of(&display(&wide, &EditParams::default()).unwrap());
+    let brighter = EditParams {
+        exposure: 0.25,
+        ..EditParams::default()
+    };
+    let after = Signature::of(&display(&wide, &brighter).unwrap());
+    assert!(!after.differences(&before).is_empty());
+    assert!(before.differences(&before).is_empty());
+
+    let parsed = Signature::parse(&before.to_text()).unwrap();
+    assert_eq!(parsed, before);
+}
@@ -0,0 +1,231 @@
+//! Whole workflows, from importing a folder to relaunching the app, run
+//! against a temporary library.
+
+use std::fs;
+use std::path::Path;
+
+use crema_catalog::import;
+use crema_catalog::models::SmartPreview;
+use crema_core::dither::Dither;
+use crema_core::image_buf::EditParams;
+use crema_e2e::{Library, export, mean_rgb, write_photo};
+
+#[test]
+fn import_edit_export_and_relaunch() {
+    let tmp = tempfile::tempdir().unwrap();
+    let photos = tmp.path().join("photos");
+    fs::create_dir_all(&photos).unwrap();
+    let beach = write_photo(&photos, "beach.jpg", 96, 64, [200, 170, 120]);
+    let forest = write_photo(&photos, "forest.png", 64, 96, [60, 140, 70]);
+
+    let library = Library::open(tmp.path()).unwrap();
+    let result = import::import_folder(&library.catalog, &photos).unwrap();
+    assert_eq!(result.imported.len(), 2, "{:?}", result.errors);
+    assert!(result.errors.is_empty());
+
+    let beach_id = library.photo_id(&beach).unwrap();
+    let params = EditParams {
+        exposure: 1.0,
+        crop_x: 0.25,
+        crop_y: 0.0,
+        crop_w: 0.5,
+        crop_h: 1.0,
+        ..Default::default()
+    };
+    library.catalog.save_edits(beach_id, &params).unwrap();
+    library.catalog.set_rating(beach_id, 4).unwrap();
+
+    let out = tmp.path().join("exports");
+    fs::create_dir_all(&out).unwrap();
+    let edited = out.join("beach.jpg");
+    let plain = out.join("forest.png");
+    export(&beach, &params, Dither::Off, &edited).unwrap();
+    export(&forest, &EditParams::default(), Dither::Off, &plain).unwrap();
+
+    // The crop halves the width and the exposure brightens what is left.
+    let (w, h) = image::image_dimensions(&edited).unwrap();
+    assert_eq!((w, h), (48, 64));
+    assert_eq!(image::image_dimensions(&plain).unwrap(), (64, 96));
+    let before = mean_rgb(&beach).unwrap();
+    let after = mean_rgb(&edited).unwrap();
+    assert!(after[1] > before[1] + 10.0, "{before:?} -> {after:?}");
+    // An unedited PNG export comes back within rounding of the original.
+    let original = mean_rgb(&forest).unwrap();
+    let exported = mean_rgb(&plain).unwrap();
+    for c in 0..3 {
+        assert!(
+            (original[c] - exported[c]).abs() < 1.0,
+            "{original:?} vs {exported:?}"
+        );
+    }
+
+    let library = library.relaunch().unwrap();
+    assert_eq!(library.catalog.list_photos().unwrap().len(), 2);
+    let photo = library.catalog.get_photo(beach_id).unwrap().unwrap();
+    assert_eq!(photo.rating, 4);
+    let restored = library.edits_for(&beach).unwrap();
+    assert_eq!(restored.exposure, 1.0);
+    assert_eq!(restored.crop_w, 0.5);
+    assert_eq!(library.edits_for(&forest).unwrap().exposure, 0.0);
+}
+
+#[test]
+fn reimporting_a_folder_skips_what_is_already_there() {
+    let tmp = tempfile::tempdir().unwrap();
+    let photos = tmp.path().join("photos");
+    fs::create_dir_all(&photos).unwrap();
+    write_photo(&photos, "a.jpg", 32, 32, [180, 90, 40]);
+
+    let library = Library::open(tmp.path()).unwrap();
+    import::import_folder(&library.catalog, &photos).unwrap();
+    write_photo(&photos, "b.jpg", 32, 32, [40, 90, 180]);
+
+    let library = library.relaunch().unwrap();
+    let result = import::import_folder(&library.catalog, &photos).unwrap();
+    assert_eq!(result.imported.len(), 1);
+    assert_eq!(result.skipped, 1);
+    assert_eq!(library.catalog.list_photos().unwrap().len(), 2);
+}
+
+#[test]
+fn moved_folder_is_found_again_after_remap() {
+    let tmp = tempfile::tempdir().unwrap();
+    let old = tmp.path().join("old");
+    fs::create_dir_all(&old).unwrap();
+    let photo = write_photo(&old, "a.jpg", 32, 32, [120, 120, 120]);
+
+    let library = Library::open(tmp.path()).unwrap();
+    import::import_folder(&library.catalog, &old).unwrap();
+    let id = library.photo_id(&photo).unwrap();
+    library
+        .catalog
+        .save_edits(
+            id,
+            &EditParams {
+                exposure: -0.5,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    let old_prefix = old.canonicalize().unwrap();
+
+    let new = tmp.path().join("new");
+    fs::rename(&old, &new).unwrap();
+    let library = library.relaunch().unwrap();
+    let original = |library: &Library| {
+        let photo = library.catalog.get_photo(id).unwrap().unwrap();
+        Path::new(&photo.file_path).exists()
+    };
+    assert!(!original(&library));
+
+    let new_prefix = new.canonicalize().unwrap();
+    let remapped = library
+        .catalog
+        .remap_path_prefix(&old_prefix.to_string_lossy(), &new_prefix.to_string_lossy())
+        .unwrap();
+    assert_eq!(remapped.len(), 1);
+
+    let library = library.relaunch().unwrap();
+    assert!(original(&library));
+    assert_eq!(
+        library.edits_for(&new.join("a.jpg")).unwrap().exposure,
+        -0.5
+    );
+}
+
+#[test]
+fn edited_thumbnail_reflects_the_edit() {
+    let tmp = tempfile::tempdir().unwrap();
+    let photo = write_photo(tmp.path(), "a.png", 64, 48, [100, 100, 100]);
+
+    let plain =
+        crema_thumbnails::generator::edited_thumbnail(&photo, 0, &EditParams::default()).unwrap();
+    let brighter = crema_thumbnails::generator::edited_thumbnail(
+        &photo,
+        0,
+        &EditParams {
+            exposure: 1.5,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    let mean = |bytes: &[u8]| {
+        let img = image::load_from_memory(bytes).unwrap().to_luma8();
+        img.pixels().map(|p| p[0] as f32).sum::<f32>() / img.pixels().len() as f32
+    };
+    assert!(mean(&brighter) > mean(&plain) + 10.0);
+}
+
+#[test]
+fn smart_preview_keeps_an_offline_photo_editable() {
+    let tmp = tempfile::tempdir().unwrap();
+    let drive = tmp.path().join("drive");
+    fs::create_dir_all(&drive).unwrap();
+    let photo = write_photo(&drive, "a.jpg", 80, 60, [150, 110, 90]);
+
+    let library = Library::open(tmp.path()).unwrap();
+    import::import_folder(&library.catalog, &drive).unwrap();
+    let id = library.photo_id(&photo).unwrap();
+    let buf = crema_core::raw::load_any(&photo).unwrap();
+    let proxy = tmp.path().join("previews").join(format!("{id}.jpg"));
+    let (width, height) =
+        crema_thumbnails::smart_preview::write_smart_preview(&buf, &proxy).unwrap();
+    library
+        .catalog
+        .save_smart_preview(&SmartPreview {
+            photo_id: id,
+            path: proxy.to_string_lossy().to_string(),
+            width,
+            height,
+        })
+        .unwrap();
+
+    // The drive goes away between sessions.
+    fs::remove_dir_all(&drive).unwrap();
+    let library = library.relaunch().unwrap();
+    assert!(!photo.exists());
+    let preview = library.catalog.smart_preview(id).unwrap().unwrap();
+    assert!(Path::new(&preview.path).exists());
+
+    let out = tmp.path().join("a-export.jpg");
+    export(&proxy, &EditParams::default(), Dither::Off, &out).unwrap();
+    assert_eq!(image::image_dimensions(&out).unwrap(), (80, 60));
+}
+
+#[test]
+fn animated_gif_imports_as_its_key_frame() {
+    use image::codecs::gif::GifEncoder;
+    use image::{Delay, Frame, Rgba, RgbaImage};
+
+    let tmp = tempfile::tempdir().unwrap();
+    let photos = tmp.path().join("photos");
+    fs::create_dir_all(&photos).unwrap();
+    let gif = photos.join("wave.gif");
+    let mut encoder = GifEncoder::new(fs::File::create(&gif).unwrap());
+    for shade in [40u8, 120, 220] {
+        let buffer = RgbaImage::from_pixel(8, 8, Rgba([shade, shade, shade, 255]));
+        let delay = Delay::from_numer_denom_ms(80, 1);
+        encoder
+            .encode_frame(Frame::from_parts(buffer, 0, 0, delay))
+            .unwrap();
+    }
+    drop(encoder);
+    let original = fs::read(&gif).unwrap();
+
+    let library = Library::open(tmp.path()).unwrap();
+    import::import_folder(&library.catalog, &photos).unwrap();
+    let id = library.photo_id(&gif).unwrap();
+    let animation = library.catalog.animation(id).unwrap().unwrap();
+    assert_eq!((animation.frame_count, animation.key_frame), (3, 0));
+
+    library.catalog.set_key_frame(id, 2).unwrap();
+    let library = library.relaunch().unwrap();
+    let key_frame = library.catalog.animation(id).unwrap().unwrap().key_frame;
+    assert_eq!(key_frame, 2);
+
+    let first = crema_core::raw::load_any(&gif).unwrap();
+    let chosen = crema_core::raw::load_frame_scaled(&gif, key_frame, None).unwrap();
+    assert!(chosen.data[0] > first.data[0] * 4.0);
+    // The file itself is never rewritten.
+    assert_eq!(fs::read(&gif).unwrap(), original);
+}
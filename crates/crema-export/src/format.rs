@@ -0,0 +1,509 @@
+//! How a rendered export is written: the color space its pixels are in,
+//! how many bits each channel gets and how rounding to 8 bits is dithered.
+
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use image::ImageEncoder;
+use serde::{Deserialize, Serialize};
+
+use crema_core::color::{display_p3_icc, linear_srgb_to_display_p3, linear_to_srgb};
+use crema_core::dither::Dither;
+use crema_core::image_buf::ImageBuf;
+
+/// Quality JPEG exports are written at unless fitting a target size.
+pub const JPEG_QUALITY: u8 = 92;
+/// Target sizes offered for JPEG exports, in bytes. Decimal megabytes, so
+/// a file fits limits stated either way.
+pub const TARGET_SIZES: [u64; 5] = [500_000, 1_000_000, 2_000_000, 5_000_000, 10_000_000];
+
+/// "2 MB" for a target size in bytes.
+pub fn size_label(bytes: u64) -> String {
+    if bytes >= 1_000_000 {
+        format!("{} MB", bytes as f64 / 1_000_000.0)
+    } else {
+        format!("{} KB", bytes / 1000)
+    }
+}
+
+/// How an export ended, carrying the line shown for it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExportOutcome {
+    Exported(String),
+    /// Written, but not as asked: a JPEG still over its size limit at the
+    /// lowest quality.
+    OverTarget(String),
+    Failed(String),
+    Cancelled,
+}
+
+impl ExportOutcome {
+    pub fn message(&self) -> &str {
+        match self {
+            ExportOutcome::Exported(msg)
+            | ExportOutcome::OverTarget(msg)
+            | ExportOutcome::Failed(msg) => msg,
+            ExportOutcome::Cancelled => "Export cancelled",
+        }
+    }
+
+    /// A file was written, even if it missed its size limit.
+    pub fn is_exported(&self) -> bool {
+        matches!(
+            self,
+            ExportOutcome::Exported(_) | ExportOutcome::OverTarget(_)
+        )
+    }
+
+    /// The outcome of writing `path`, with the reason it failed.
+    pub fn of_write(path: &Path, result: Result<Option<FittedQuality>>) -> Self {
+        match result {
+            Ok(
+                fitted @ Some(FittedQuality {
+                    over_target: true, ..
+                }),
+            ) => ExportOutcome::OverTarget(exported_message(path, fitted)),
+            Ok(fitted) => ExportOutcome::Exported(exported_message(path, fitted)),
+            Err(e) => ExportOutcome::Failed(format!("Export failed: {e:#}")),
+        }
+    }
+}
+
+/// The quality a JPEG was written at to fit its target size.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FittedQuality {
+    pub quality: u8,
+    /// Even the lowest quality came out larger than the target.
+    pub over_target: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportColorSpace {
+    #[default]
+    Srgb,
+    /// The wider gamut of recent Apple displays and most phones. Files
+    /// only look right where a profile tells the viewer what they hold.
+    DisplayP3,
+}
+
+impl ExportColorSpace {
+    pub const ALL: [Self; 2] = [Self::Srgb, Self::DisplayP3];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Srgb => "sRGB",
+            Self::DisplayP3 => "Display P3",
+        }
+    }
+
+    /// The profile embedded in files that can carry one. sRGB goes
+    /// without: untagged files are read as sRGB anyway.
+    pub fn icc_profile(self) -> Option<Vec<u8>> {
+        match self {
+            Self::Srgb => None,
+            Self::DisplayP3 => display_p3_icc(),
+        }
+    }
+
+    /// Re-express the linear sRGB `buf` the pipeline renders in this
+    /// color space's primaries.
+    pub fn convert(self, buf: &mut ImageBuf) {
+        if self == Self::Srgb {
+            return;
+        }
+        for pixel in buf.data.chunks_exact_mut(3) {
+            let (r, g, b) = linear_srgb_to_display_p3(pixel[0], pixel[1], pixel[2]);
+            pixel.copy_from_slice(&[r, g, b]);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ExportEncoding {
+    pub dither: Dither,
+    pub color_space: ExportColorSpace,
+    /// Write TIFFs at 16 bits per channel. Other formats stay at 8.
+    pub tiff_16bit: bool,
+    /// Largest size for JPEGs, in bytes. Quality is searched for the best
+    /// that fits; other formats ignore it.
+    pub target_size: Option<u64>,
+}
+
+impl ExportEncoding {
+    /// Bits per channel of a file written to `path`.
+    pub fn bit_depth(&self, path: &Path) -> u8 {
+        if self.tiff_16bit && is_tiff(path) {
+            16
+        } else {
+            8
+        }
+    }
+
+    /// Whether a file written to `path` tells viewers its color space,
+    /// either with an embedded profile or by being sRGB. Only JPEG, PNG and
+    /// TIFF carry a profile.
+    pub fn is_tagged(&self, path: &Path) -> bool {
+        self.color_space == ExportColorSpace::Srgb
+            || is_jpeg(path)
+            || is_tiff(path)
+            || extension(path) == "png"
+    }
+}
+
+fn extension(path: &Path) -> String {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("")
+        .to_ascii_lowercase()
+}
+
+pub fn is_jpeg(path: &Path) -> bool {
+    matches!(extension(path).as_str(), "jpg" | "jpeg")
+}
+
+pub fn is_tiff(path: &Path) -> bool {
+    matches!(extension(path).as_str(), "tif" | "tiff")
+}
+
+/// Write an already rendered image as an export, JPEG at quality 92 or
+/// whatever format the extension names.
+pub fn write(processed: ImageBuf, encoding: &ExportEncoding, path: &Path) -> ExportOutcome {
+    ExportOutcome::of_write(path, write_fitted(processed, encoding, path))
+}
+
+/// [`write`], returning the quality chosen when the JPEG was fit to the
+/// encoding's target size.
+pub fn write_fitted(
+    mut processed: ImageBuf,
+    encoding: &ExportEncoding,
+    path: &Path,
+) -> Result<Option<FittedQuality>> {
+    encoding.color_space.convert(&mut processed);
+    write_converted(&processed, encoding, path)
+}
+
+/// The status line for an export written to `path`.
+pub fn exported_message(path: &Path, fitted: Option<FittedQuality>) -> String {
+    match fitted {
+        None => format!("Exported to {}", path.display()),
+        Some(FittedQuality {
+            quality,
+            over_target: false,
+        }) => format!("Exported to {} at quality {quality}", path.display()),
+        Some(FittedQuality { quality, .. }) => format!(
+            "Exported to {} at quality {quality}, still over the target size",
+            path.display()
+        ),
+    }
+}
+
+/// Write `processed`, already in the encoding's color space.
+fn write_converted(
+    processed: &ImageBuf,
+    encoding: &ExportEncoding,
+    path: &Path,
+) -> Result<Option<FittedQuality>> {
+    let (w, h) = (processed.width, processed.height);
+    let icc = encoding.color_space.icc_profile();
+    if encoding.bit_depth(path) == 16 {
+        let data: Vec<u16> = processed
+            .data
+            .iter()
+            .map(|&v| (linear_to_srgb(v.clamp(0.0, 1.0)) * 65535.0).round() as u16)
+            .collect();
+        write_tiff::<tiff::encoder::colortype::RGB16>(&data, w, h, icc.as_deref(), path)?;
+        return Ok(None);
+    }
+    let rgba = crema_core::dither::to_rgba_u8_srgb(processed, encoding.dither);
+    let img = image::RgbaImage::from_raw(w, h, rgba).context("could not construct image buffer")?;
+
+    if let (Some(target), true) = (encoding.target_size, is_jpeg(path)) {
+        let rgb = image::DynamicImage::ImageRgba8(img).to_rgb8();
+        return write_jpeg_to_size(&rgb, w, h, icc.as_deref(), target, path, &mut |_| Ok(()))
+            .map(Some);
+    }
+    if is_jpeg(path) {
+        let file = std::fs::File::create(path)?;
+        let mut encoder = image::codecs::jpeg::JpegEncoder::new_with_quality(
+            std::io::BufWriter::new(file),
+            JPEG_QUALITY,
+        );
+        if let Some(icc) = icc {
+            encoder.set_icc_profile(icc)?;
+        }
+        let rgb = image::DynamicImage::ImageRgba8(img).to_rgb8();
+        encoder.write_image(&rgb, w, h, image::ExtendedColorType::Rgb8)?;
+    } else if is_tiff(path) {
+        let rgb = image::DynamicImage::ImageRgba8(img).to_rgb8();
+        write_tiff::<tiff::encoder::colortype::RGB8>(&rgb, w, h, icc.as_deref(), path)?;
+    } else if let (Some(icc), "png") = (icc, extension(path).as_str()) {
+        let file = std::fs::File::create(path)?;
+        let mut encoder = image::codecs::png::PngEncoder::new(std::io::BufWriter::new(file));
+        encoder.set_icc_profile(icc)?;
+        encoder.write_image(&img, w, h, image::ExtendedColorType::Rgba8)?;
+    } else {
+        img.save(path)?;
+    }
+    Ok(None)
+}
+
+/// Write RGB samples to `path` as a TIFF, with `icc` embedded when given.
+fn write_tiff<C: tiff::encoder::colortype::ColorType>(
+    data: &[C::Inner],
+    w: u32,
+    h: u32,
+    icc: Option<&[u8]>,
+    path: &Path,
+) -> Result<()>
+where
+    [C::Inner]: tiff::encoder::TiffValue,
+{
+    let file = std::fs::File::create(path)
+        .with_context(|| format!("failed to create {}", path.display()))?;
+    let mut encoder = tiff::encoder::TiffEncoder::new(std::io::BufWriter::new(file))?;
+    let mut image = encoder.new_image::<C>(w, h)?;
+    if let Some(icc) = icc {
+        image
+            .encoder()
+            .write_tag(tiff::tags::Tag::IccProfile, IccProfile(icc))?;
+    }
+    image.write_data(data)?;
+    Ok(())
+}
+
+/// An ICC profile as a TIFF tag value, which the spec types as UNDEFINED
+/// bytes rather than BYTE.
+struct IccProfile<'a>(&'a [u8]);
+
+impl tiff::encoder::TiffValue for IccProfile<'_> {
+    const BYTE_LEN: u8 = 1;
+    const FIELD_TYPE: tiff::tags::Type = tiff::tags::Type::UNDEFINED;
+
+    fn count(&self) -> usize {
+        self.0.len()
+    }
+
+    fn data(&self) -> std::borrow::Cow<'_, [u8]> {
+        std::borrow::Cow::Borrowed(self.0)
+    }
+}
+
+/// Encode 8-bit RGB as a JPEG in memory.
+fn encode_jpeg(rgb: &[u8], w: u32, h: u32, quality: u8, icc: Option<&[u8]>) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    let mut encoder = image::codecs::jpeg::JpegEncoder::new_with_quality(&mut bytes, quality);
+    if let Some(icc) = icc {
+        encoder.set_icc_profile(icc.to_vec())?;
+    }
+    encoder.write_image(rgb, w, h, image::ExtendedColorType::Rgb8)?;
+    Ok(bytes)
+}
+
+/// Write 8-bit RGB to `path` as the best quality JPEG no larger than
+/// `target` bytes, or at the lowest quality if none fits. `before_encode`
+/// is given the number of encodes done so far, at most [`FIT_ENCODES`],
+/// ahead of each one; an error from it stops the search and nothing is
+/// written.
+pub fn write_jpeg_to_size(
+    rgb: &[u8],
+    w: u32,
+    h: u32,
+    icc: Option<&[u8]>,
+    target: u64,
+    path: &Path,
+    before_encode: &mut dyn FnMut(usize) -> Result<()>,
+) -> Result<FittedQuality> {
+    let mut done = 0;
+    let (bytes, fitted) = fit_quality(target, |quality| {
+        before_encode(done)?;
+        done += 1;
+        encode_jpeg(rgb, w, h, quality, icc)
+    })?;
+    std::fs::write(path, bytes).with_context(|| format!("failed to write {}", path.display()))?;
+    Ok(fitted)
+}
+
+/// Most encodes [`fit_quality`] makes: a binary search over 100 qualities.
+pub const FIT_ENCODES: usize = 7;
+
+/// Binary search for the highest quality whose encoding is at most
+/// `target` bytes. File size grows with quality, so [`FIT_ENCODES`]
+/// encodes settle it.
+fn fit_quality(
+    target: u64,
+    mut encode: impl FnMut(u8) -> Result<Vec<u8>>,
+) -> Result<(Vec<u8>, FittedQuality)> {
+    let (mut low, mut high) = (1u8, 100u8);
+    let mut best = None;
+    let mut smallest = None;
+    while low <= high {
+        let quality = low + (high - low) / 2;
+        let bytes = encode(quality)?;
+        if bytes.len() as u64 <= target {
+            best = Some((bytes, quality));
+            low = quality + 1;
+        } else if quality == 1 {
+            smallest = Some(bytes);
+            break;
+        } else {
+            high = quality - 1;
+        }
+    }
+    match (best, smallest) {
+        (Some((bytes, quality)), _) => Ok((
+            bytes,
+            FittedQuality {
+                quality,
+                over_target: false,
+            },
+        )),
+        (None, smallest) => Ok((
+            match smallest {
+                Some(bytes) => bytes,
+                None => encode(1)?,
+            },
+            FittedQuality {
+                quality: 1,
+                over_target: true,
+            },
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::ImageDecoder;
+
+    use super::*;
+
+    fn gradient() -> ImageBuf {
+        let data = (0..64 * 8)
+            .flat_map(|i| {
+                let v = (i % 64) as f32 / 63.0;
+                [v, v * 0.5, 0.2]
+            })
+            .collect();
+        ImageBuf::from_data(64, 8, data).unwrap()
+    }
+
+    #[test]
+    fn display_p3_exports_embed_their_profile() {
+        let dir = tempfile::tempdir().unwrap();
+        let p3 = ExportEncoding {
+            color_space: ExportColorSpace::DisplayP3,
+            ..ExportEncoding::default()
+        };
+        let deep = ExportEncoding {
+            tiff_16bit: true,
+            ..p3
+        };
+        for (name, encoding) in [
+            ("a.jpg", &p3),
+            ("a.png", &p3),
+            ("a.tif", &p3),
+            ("deep.tif", &deep),
+        ] {
+            let path = dir.path().join(name);
+            assert!(write(gradient(), encoding, &path).is_exported());
+            let icc = if is_tiff(&path) {
+                let file = std::fs::File::open(&path).unwrap();
+                tiff::decoder::Decoder::new(file)
+                    .unwrap()
+                    .get_tag_u8_vec(tiff::tags::Tag::IccProfile)
+                    .ok()
+            } else {
+                image::ImageReader::open(&path)
+                    .unwrap()
+                    .with_guessed_format()
+                    .unwrap()
+                    .into_decoder()
+                    .unwrap()
+                    .icc_profile()
+                    .unwrap()
+            };
+            assert_eq!(icc, display_p3_icc(), "{name}");
+        }
+        assert!(p3.is_tagged(Path::new("a.tif")));
+        assert!(!p3.is_tagged(Path::new("a.gif")));
+    }
+
+    #[test]
+    fn sixteen_bit_applies_to_tiffs_only() {
+        let dir = tempfile::tempdir().unwrap();
+        let deep = ExportEncoding {
+            tiff_16bit: true,
+            ..ExportEncoding::default()
+        };
+        assert_eq!(deep.bit_depth(Path::new("a.jpg")), 8);
+        let path = dir.path().join("a.TIF");
+        assert_eq!(deep.bit_depth(&path), 16);
+        assert!(write(gradient(), &deep, &path).is_exported());
+        assert_eq!(image::open(&path).unwrap().color(), image::ColorType::Rgb16);
+    }
+
+    #[test]
+    fn quality_search_finds_the_best_that_fits() {
+        // A stand-in encoder whose files grow 1 KB per quality step.
+        let size = |quality: u8| Ok(vec![0; quality as usize * 1000]);
+        let (bytes, fitted) = fit_quality(72_500, size).unwrap();
+        assert_eq!(fitted.quality, 72);
+        assert!(!fitted.over_target);
+        assert_eq!(bytes.len(), 72_000);
+
+        let (_, fitted) = fit_quality(500, size).unwrap();
+        assert_eq!(fitted.quality, 1);
+        assert!(fitted.over_target);
+        assert_eq!(fit_quality(1_000_000, size).unwrap().1.quality, 100);
+
+        // The quality 1 encode already made is kept rather than redone.
+        let mut encodes = 0;
+        fit_quality(500, |quality| {
+            encodes += 1;
+            size(quality)
+        })
+        .unwrap();
+        assert!(encodes <= FIT_ENCODES);
+    }
+
+    #[test]
+    fn over_target_jpegs_warn_and_cancelled_fits_write_nothing() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("tiny.jpg");
+        let rgb = vec![128u8; 64 * 64 * 3];
+        let fitted = write_jpeg_to_size(&rgb, 64, 64, None, 10, &path, &mut |_| Ok(())).unwrap();
+        let outcome = ExportOutcome::of_write(&path, Ok(Some(fitted)));
+        assert!(matches!(outcome, ExportOutcome::OverTarget(_)));
+        assert!(outcome.is_exported());
+
+        let cancelled = dir.path().join("cancelled.jpg");
+        let result = write_jpeg_to_size(&rgb, 64, 64, None, 10_000, &cancelled, &mut |done| {
+            anyhow::ensure!(done < 2, "cancelled");
+            Ok(())
+        });
+        assert!(result.is_err());
+        assert!(!cancelled.exists());
+    }
+
+    #[test]
+    fn target_sized_jpegs_fit() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("small.jpg");
+        let data = (0..256 * 256)
+            .flat_map(|i| {
+                let v = ((i * 7919) % 256) as f32 / 255.0;
+                [v, 1.0 - v, (i % 256) as f32 / 255.0]
+            })
+            .collect();
+        let noisy = ImageBuf::from_data(256, 256, data).unwrap();
+        let encoding = ExportEncoding {
+            target_size: Some(20_000),
+            ..ExportEncoding::default()
+        };
+        let fitted = write_fitted(noisy, &encoding, &path).unwrap().unwrap();
+        assert!(std::fs::metadata(&path).unwrap().len() <= 20_000);
+        assert!(fitted.quality < JPEG_QUALITY);
+        assert_eq!(size_label(2_000_000), "2 MB");
+        assert_eq!(size_label(500_000), "500 KB");
+    }
+}
@@ -0,0 +1,481 @@
+//! Developing a photo into an export file: the edits and annotations are
+//! rendered, then written in the chosen format.
+
+use std::io::Write;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crema_core::annotation::{Annotation, FrameMap};
+use crema_core::image_buf::{EditParams, ImageBuf};
+
+use crate::format::{self, ExportEncoding, ExportOutcome, FittedQuality};
+
+/// Render and write `buf`, burning `annotations` into the developed frame.
+/// Returns the quality chosen when the JPEG was fit to a target size, or
+/// the failed status line.
+pub fn render_export(
+    buf: ImageBuf,
+    params: &EditParams,
+    annotations: &[Annotation],
+    encoding: &ExportEncoding,
+    path: &Path,
+) -> Result<Option<FittedQuality>, String> {
+    let map = FrameMap::new(params, buf.width, buf.height);
+    let pipeline = crema_core::pipeline::Pipeline::new();
+    let mut processed = pipeline
+        .process_cpu(buf, params)
+        .map_err(|e| format!("Export failed: {e}"))?;
+    crema_core::annotation::render(&mut processed, annotations, &map);
+    format::write_fitted(processed, encoding, path).map_err(|e| format!("Export failed: {e:#}"))
+}
+
+/// Share of a full resolution export's progress bar given to the render;
+/// the rest covers converting to 8 bits and encoding.
+const RENDER_SHARE: f32 = 0.7;
+const CONVERT_SHARE: f32 = 0.05;
+/// Roughly how many bytes a quality 92 JPEG spends per pixel on a typical
+/// photo. It only paces the progress bar through the encode.
+const JPEG_BYTES_PER_PIXEL: f32 = 0.4;
+
+/// Render and write `buf` at full resolution using every core: the
+/// pipeline runs in parallel bands, the 8-bit conversion is split across
+/// threads, and JPEGs stream to disk as they encode. A cancelled or failed
+/// export leaves no file behind.
+pub fn export_full_res(
+    buf: ImageBuf,
+    params: &EditParams,
+    annotations: &[Annotation],
+    encoding: &ExportEncoding,
+    path: &Path,
+    cancel: &AtomicBool,
+    progress: &(dyn Fn(f32) + Sync),
+) -> ExportOutcome {
+    let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let pipeline = crema_core::pipeline::Pipeline::new();
+    let render_progress = |fraction: f32| progress(fraction * RENDER_SHARE);
+    let map = FrameMap::new(params, buf.width, buf.height);
+    let mut processed =
+        match pipeline.process_cpu_parallel(buf, params, threads, cancel, &render_progress) {
+            Ok(Some(processed)) => processed,
+            Ok(None) => return ExportOutcome::Cancelled,
+            Err(e) => return ExportOutcome::Failed(format!("Export failed: {e}")),
+        };
+    crema_core::annotation::render(&mut processed, annotations, &map);
+    if !format::is_jpeg(path) {
+        return format::write(processed, encoding, path);
+    }
+    encoding.color_space.convert(&mut processed);
+
+    let (w, h) = (processed.width, processed.height);
+    let mut rgb = vec![0u8; processed.pixel_count() * 3];
+    let rows_per_thread = (h as usize).div_ceil(threads).max(1);
+    std::thread::scope(|scope| {
+        for (i, band) in rgb.chunks_mut(rows_per_thread * w as usize * 3).enumerate() {
+            let processed = &processed;
+            scope.spawn(move || {
+                let first_row = (i * rows_per_thread) as u32;
+                crema_core::dither::rows_to_rgb_u8_srgb(
+                    processed,
+                    encoding.dither,
+                    first_row,
+                    band,
+                );
+            });
+        }
+    });
+    drop(processed);
+    if cancel.load(Ordering::Relaxed) {
+        return ExportOutcome::Cancelled;
+    }
+    progress(RENDER_SHARE + CONVERT_SHARE);
+
+    if let Some(target) = encoding.target_size {
+        // Each attempt is a whole encode, so cancel and progress are
+        // checked between them rather than while one streams.
+        let icc = encoding.color_space.icc_profile();
+        let encode_share = 1.0 - RENDER_SHARE - CONVERT_SHARE;
+        let result =
+            format::write_jpeg_to_size(&rgb, w, h, icc.as_deref(), target, path, &mut |done| {
+                if cancel.load(Ordering::Relaxed) {
+                    anyhow::bail!("cancelled");
+                }
+                let fraction = done as f32 / format::FIT_ENCODES as f32;
+                progress(RENDER_SHARE + CONVERT_SHARE + fraction.min(1.0) * encode_share);
+                Ok(())
+            });
+        if cancel.load(Ordering::Relaxed) {
+            return ExportOutcome::Cancelled;
+        }
+        progress(1.0);
+        return ExportOutcome::of_write(path, result.map(Some));
+    }
+
+    let result = write_or_remove(path, |file| {
+        let mut out = std::io::BufWriter::new(EncodeWriter {
+            inner: file,
+            written: 0,
+            expected: (w as f32 * h as f32 * JPEG_BYTES_PER_PIXEL).max(1.0),
+            reported: 0.0,
+            cancel,
+            progress,
+        });
+        let mut encoder =
+            image::codecs::jpeg::JpegEncoder::new_with_quality(&mut out, format::JPEG_QUALITY);
+        if let Some(icc) = encoding.color_space.icc_profile() {
+            use image::ImageEncoder;
+            encoder.set_icc_profile(icc)?;
+        }
+        encoder.encode(&rgb, w, h, image::ExtendedColorType::Rgb8)?;
+        drop(encoder);
+        // Flushed here rather than on drop, which would swallow a failure.
+        out.flush()?;
+        Ok(())
+    });
+    if cancel.load(Ordering::Relaxed) {
+        std::fs::remove_file(path).ok();
+        return ExportOutcome::Cancelled;
+    }
+    progress(1.0);
+    match result {
+        Ok(()) => ExportOutcome::Exported(format!("Exported to {}", path.display())),
+        Err(e) => ExportOutcome::Failed(format!("Export failed: {e:#}")),
+    }
+}
+
+/// Create `path` and hand it to `write`, removing the file again unless
+/// `write` succeeds so a failed export never leaves a truncated image that
+/// looks valid.
+fn write_or_remove(
+    path: &Path,
+    write: impl FnOnce(std::fs::File) -> anyhow::Result<()>,
+) -> anyhow::Result<()> {
+    let file = std::fs::File::create(path)?;
+    let result = write(file);
+    if result.is_err() {
+        std::fs::remove_file(path).ok();
+    }
+    result
+}
+
+/// File writer for a streaming encode: it reports how far the encode has
+/// got and fails the next write once the export is cancelled, which stops
+/// the encoder.
+struct EncodeWriter<'a> {
+    inner: std::fs::File,
+    written: usize,
+    expected: f32,
+    reported: f32,
+    cancel: &'a AtomicBool,
+    progress: &'a (dyn Fn(f32) + Sync),
+}
+
+impl Write for EncodeWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if self.cancel.load(Ordering::Relaxed) {
+            return Err(std::io::Error::other("export cancelled"));
+        }
+        let n = self.inner.write(buf)?;
+        self.written += n;
+        let share = (self.written as f32 / self.expected).min(0.99);
+        // Report in whole percents so a large encode doesn't flood the UI.
+        if share - self.reported >= 0.01 {
+            self.reported = share;
+            let base = RENDER_SHARE + CONVERT_SHARE;
+            (self.progress)(base + share * (1.0 - base));
+        }
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crema_core::dither::Dither;
+
+    fn test_image() -> ImageBuf {
+        let mut data = vec![0.0f32; 4 * 2 * 3];
+        data[0] = 0.8;
+        data[1] = 0.1;
+        data[2] = 0.1;
+        let last = data.len() - 3;
+        data[last] = 0.1;
+        data[last + 1] = 0.1;
+        data[last + 2] = 0.8;
+        ImageBuf::from_data(4, 2, data).unwrap()
+    }
+
+    fn test_params() -> EditParams {
+        EditParams {
+            exposure: 0.5,
+            wb_temp: 6000.0,
+            wb_tint: 5.0,
+            ..EditParams::default()
+        }
+    }
+
+    /// [`render_export`] with `dither`, returning the status line.
+    fn export_image(
+        buf: ImageBuf,
+        params: &EditParams,
+        annotations: &[Annotation],
+        dither: Dither,
+        path: &Path,
+    ) -> String {
+        let encoding = ExportEncoding {
+            dither,
+            ..ExportEncoding::default()
+        };
+        match render_export(buf, params, annotations, &encoding, path) {
+            Ok(fitted) => format::exported_message(path, fitted),
+            Err(msg) => msg,
+        }
+    }
+
+    #[test]
+    fn export_jpeg_writes_valid_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("out.jpg");
+
+        let msg = export_image(test_image(), &test_params(), &[], Dither::Off, &path);
+
+        assert!(msg.starts_with("Exported to"), "unexpected: {msg}");
+        assert!(path.exists());
+
+        let img = image::open(&path).unwrap();
+        assert_eq!(img.width(), 4);
+        assert_eq!(img.height(), 2);
+    }
+
+    #[test]
+    fn proof_export_burns_in_annotations() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("proof.png");
+        let buf = ImageBuf::from_data(40, 40, vec![0.0; 40 * 40 * 3]).unwrap();
+        let box_mark = Annotation::Rect {
+            from: [0.25, 0.25],
+            to: [0.75, 0.75],
+        };
+        let msg = export_image(buf, &EditParams::default(), &[box_mark], Dither::Off, &path);
+        assert!(msg.starts_with("Exported to"), "unexpected: {msg}");
+
+        let img = image::open(&path).unwrap().into_rgb8();
+        assert!(img.get_pixel(10, 20).0[0] > 200);
+        assert_eq!(img.get_pixel(20, 20).0, [0, 0, 0]);
+    }
+
+    #[test]
+    fn full_res_export_matches_the_inline_export() {
+        let dir = tempfile::tempdir().unwrap();
+        let inline = dir.path().join("inline.jpg");
+        let threaded = dir.path().join("threaded.jpg");
+        export_image(
+            test_image(),
+            &test_params(),
+            &[],
+            Dither::BlueNoise,
+            &inline,
+        );
+
+        let reported = std::sync::Mutex::new(Vec::new());
+        let msg = export_full_res(
+            test_image(),
+            &test_params(),
+            &[],
+            &ExportEncoding {
+                dither: Dither::BlueNoise,
+                ..ExportEncoding::default()
+            },
+            &threaded,
+            &AtomicBool::new(false),
+            &|f| reported.lock().unwrap().push(f),
+        );
+        assert!(msg.message().starts_with("Exported to"), "{msg:?}");
+        assert_eq!(
+            std::fs::read(&threaded).unwrap(),
+            std::fs::read(&inline).unwrap()
+        );
+        assert_eq!(reported.into_inner().unwrap().last(), Some(&1.0));
+    }
+
+    #[test]
+    fn cancelled_full_res_export_leaves_no_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("out.jpg");
+        let msg = export_full_res(
+            test_image(),
+            &test_params(),
+            &[],
+            &ExportEncoding {
+                dither: Dither::Off,
+                ..ExportEncoding::default()
+            },
+            &path,
+            &AtomicBool::new(true),
+            &|_| {},
+        );
+        assert_eq!(msg, ExportOutcome::Cancelled);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn failed_write_leaves_no_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("out.jpg");
+        let result = write_or_remove(&path, |mut file| {
+            file.write_all(&[0xFF, 0xD8])?;
+            anyhow::bail!("disk full")
+        });
+        assert_eq!(result.unwrap_err().to_string(), "disk full");
+        assert!(!path.exists());
+
+        write_or_remove(&path, |mut file| Ok(file.write_all(b"done")?)).unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), b"done");
+    }
+
+    #[test]
+    fn export_jpeg_uppercase_extension() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("photo.JPG");
+
+        let msg = export_image(
+            test_image(),
+            &EditParams::default(),
+            &[],
+            Dither::Off,
+            &path,
+        );
+        assert!(msg.starts_with("Exported to"), "unexpected: {msg}");
+
+        let bytes = std::fs::read(&path).unwrap();
+        assert_eq!(&bytes[0..2], &[0xFF, 0xD8], "not a JPEG file");
+    }
+
+    #[test]
+    fn export_png_writes_valid_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("out.png");
+
+        let msg = export_image(test_image(), &test_params(), &[], Dither::Off, &path);
+
+        assert!(msg.starts_with("Exported to"), "unexpected: {msg}");
+        assert!(path.exists());
+
+        let img = image::open(&path).unwrap();
+        assert_eq!(img.width(), 4);
+        assert_eq!(img.height(), 2);
+
+        let bytes = std::fs::read(&path).unwrap();
+        assert_eq!(&bytes[0..4], &[0x89, 0x50, 0x4E, 0x47]);
+    }
+
+    #[test]
+    fn export_tiff_writes_valid_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("out.tiff");
+
+        let msg = export_image(test_image(), &test_params(), &[], Dither::Off, &path);
+
+        assert!(msg.starts_with("Exported to"), "unexpected: {msg}");
+        assert!(path.exists());
+
+        let img = image::open(&path).unwrap();
+        assert_eq!(img.width(), 4);
+        assert_eq!(img.height(), 2);
+    }
+
+    #[test]
+    fn export_applies_edits() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let path_default = dir.path().join("default.png");
+        export_image(
+            test_image(),
+            &EditParams::default(),
+            &[],
+            Dither::Off,
+            &path_default,
+        );
+
+        let bright_params = EditParams {
+            exposure: 2.0,
+            ..EditParams::default()
+        };
+        let path_bright = dir.path().join("bright.png");
+        export_image(test_image(), &bright_params, &[], Dither::Off, &path_bright);
+
+        let img_default = image::open(&path_default).unwrap().into_rgba8();
+        let img_bright = image::open(&path_bright).unwrap().into_rgba8();
+
+        let avg_default: u32 = img_default.pixels().map(|p| p.0[0] as u32).sum();
+        let avg_bright: u32 = img_bright.pixels().map(|p| p.0[0] as u32).sum();
+        assert!(
+            avg_bright > avg_default,
+            "exposure boost should brighten: default={avg_default} bright={avg_bright}"
+        );
+    }
+
+    #[test]
+    fn export_default_params_identity() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("identity.png");
+
+        let buf = ImageBuf::from_data(2, 2, vec![0.5; 2 * 2 * 3]).unwrap();
+        export_image(buf, &EditParams::default(), &[], Dither::Off, &path);
+
+        let img = image::open(&path).unwrap().into_rgba8();
+        let first = img.pixels().next().unwrap().0;
+        for px in img.pixels() {
+            assert_eq!(px.0, first, "all pixels should be identical");
+        }
+    }
+
+    #[test]
+    fn export_dithering_breaks_up_flat_levels() {
+        let dir = tempfile::tempdir().unwrap();
+        // Halfway between two 8-bit levels, which plain rounding flattens.
+        let level = crema_core::color::srgb_to_linear(120.5 / 255.0);
+        let buf = ImageBuf::from_data(16, 16, vec![level; 16 * 16 * 3]).unwrap();
+
+        let distinct = |dither| {
+            let path = dir.path().join(format!("{dither:?}.png"));
+            export_image(buf.clone(), &EditParams::default(), &[], dither, &path);
+            let img = image::open(&path).unwrap().into_rgb8();
+            let mut reds: Vec<u8> = img.pixels().map(|p| p.0[0]).collect();
+            reds.sort_unstable();
+            reds.dedup();
+            reds.len()
+        };
+        assert_eq!(distinct(Dither::Off), 1);
+        assert!(distinct(Dither::BlueNoise) > 1);
+        assert!(distinct(Dither::Triangular) > 1);
+    }
+
+    #[test]
+    fn export_to_nonexistent_dir_fails_gracefully() {
+        let path = Path::new("/nonexistent/dir/photo.jpg");
+        let msg = export_image(test_image(), &EditParams::default(), &[], Dither::Off, path);
+        assert!(msg.starts_with("Export failed:"), "unexpected: {msg}");
+    }
+
+    #[test]
+    fn export_message_contains_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("result.jpg");
+
+        let msg = export_image(
+            test_image(),
+            &EditParams::default(),
+            &[],
+            Dither::Off,
+            &path,
+        );
+        assert!(
+            msg.contains("result.jpg"),
+            "success message should contain filename: {msg}"
+        );
+    }
+}
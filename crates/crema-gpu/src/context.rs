@@ -5,6 +5,8 @@ use tracing::info;
 pub struct GpuContext {
     pub device: wgpu::Device,
     pub queue: wgpu::Queue,
+    /// The adapter the device was created on.
+    pub adapter: wgpu::AdapterInfo,
 }
 
 impl GpuContext {
@@ -23,9 +25,11 @@ impl GpuContext {
             .await
             .context("no suitable GPU adapter found")?;
 
+        let info = adapter.get_info();
         info!(
-            adapter = adapter.get_info().name,
-            backend = ?adapter.get_info().backend,
+            adapter = info.name,
+            backend = ?info.backend,
+            device_type = ?info.device_type,
             "selected GPU adapter"
         );
 
@@ -39,6 +43,17 @@ impl GpuContext {
             .await
             .context("failed to create GPU device")?;
 
-        Ok(Self { device, queue })
+        Ok(Self {
+            device,
+            queue,
+            adapter: info,
+        })
+    }
+
+    /// Whether the adapter is a software rasterizer such as llvmpipe, WARP
+    /// or SwiftShader, which wgpu picks when there's no usable GPU. These
+    /// run the shaders on the CPU, slower than the CPU pipeline itself.
+    pub fn is_software(&self) -> bool {
+        self.adapter.device_type == wgpu::DeviceType::Cpu
     }
 }
@@ -1,7 +1,7 @@
 use anyhow::Result;
 use tracing::debug;
 
-use crema_core::image_buf::EditParams;
+use crema_core::image_buf::{EditParams, ImageBuf};
 use crema_core::pipeline::modules::wb_matrix;
 
 use crate::context::GpuContext;
@@ -9,6 +9,9 @@ use crate::shader::ShaderManager;
 use crate::texture::GpuTexture;
 
 const WORKGROUP_SIZE: u32 = 16;
+/// Edge of the scratch image pushed through the pipeline by
+/// [`GpuPipeline::warm_up`].
+const WARMUP_EDGE: u32 = 32;
 
 pub struct GpuPipeline {
     shaders: ShaderManager,
@@ -80,6 +83,31 @@ impl GpuPipeline {
         Ok(current)
     }
 
+    /// Build every compute pipeline and push a small scratch image through
+    /// all of them, waiting for the result. Shader compilation and driver
+    /// setup then happen here instead of on the first real frame.
+    pub fn warm_up(&mut self, ctx: &GpuContext) -> Result<()> {
+        let pixels = (WARMUP_EDGE * WARMUP_EDGE * 3) as usize;
+        let scratch = ImageBuf::from_data(WARMUP_EDGE, WARMUP_EDGE, vec![0.5; pixels])?;
+        let input = GpuTexture::from_image_buf(&ctx.device, &ctx.queue, &scratch, "warmup");
+        // Every stage skips itself at its neutral value, so nudge each one.
+        let params = EditParams {
+            contrast: 10.0,
+            vibrance: 10.0,
+            saturation: 10.0,
+            split_shadow_sat: 10.0,
+            hsl_saturation: 10.0,
+            sharpen_amount: 10.0,
+            vignette_amount: -10.0,
+            crop_w: 0.5,
+            crop_h: 0.5,
+            ..EditParams::default()
+        };
+        let output = self.process(ctx, &input, &params)?;
+        output.download(&ctx.device, &ctx.queue)?;
+        Ok(())
+    }
+
     fn dispatch_simple(
         &mut self,
         ctx: &GpuContext,
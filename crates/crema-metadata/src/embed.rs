@@ -0,0 +1,303 @@
+//! Writing descriptive metadata into exported files, so delivered images
+//! carry their title, caption, keywords, copyright and rating. JPEGs get
+//! both XMP and the older IPTC-IIM block many newsroom and stock tools
+//! still read; PNGs get XMP.
+
+use std::path::Path;
+
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+
+const XMP_SIGNATURE: &[u8] = b"http://ns.adobe.com/xap/1.0/\0";
+const PHOTOSHOP_SIGNATURE: &[u8] = b"Photoshop 3.0\0";
+/// Keyword, then no compression, no language and no translated keyword.
+const PNG_XMP_KEYWORD: &[u8] = b"XML:com.adobe.xmp\0\0\0\0\0";
+/// Largest payload of a JPEG marker segment, after its length field.
+const MAX_SEGMENT: usize = 65533;
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct EmbeddedMetadata {
+    pub title: Option<String>,
+    pub caption: Option<String>,
+    pub keywords: Vec<String>,
+    pub copyright: Option<String>,
+    /// Stars, 0 to 5.
+    pub rating: Option<i32>,
+}
+
+impl EmbeddedMetadata {
+    pub fn is_empty(&self) -> bool {
+        self.title.is_none()
+            && self.caption.is_none()
+            && self.keywords.is_empty()
+            && self.copyright.is_none()
+            && self.rating.is_none()
+    }
+
+    /// An XMP packet holding every field that's set.
+    pub fn to_xmp(&self) -> String {
+        let mut props = String::new();
+        if let Some(rating) = self.rating {
+            props.push_str(&format!("   <xmp:Rating>{rating}</xmp:Rating>\n"));
+        }
+        let alt = |name: &str, value: &str| {
+            format!(
+                "   <dc:{name}><rdf:Alt><rdf:li xml:lang=\"x-default\">{}</rdf:li></rdf:Alt></dc:{name}>\n",
+                escape(value)
+            )
+        };
+        if let Some(title) = &self.title {
+            props.push_str(&alt("title", title));
+        }
+        if let Some(caption) = &self.caption {
+            props.push_str(&alt("description", caption));
+        }
+        if let Some(copyright) = &self.copyright {
+            props.push_str(&alt("rights", copyright));
+        }
+        if !self.keywords.is_empty() {
+            props.push_str("   <dc:subject><rdf:Bag>");
+            for keyword in &self.keywords {
+                props.push_str(&format!("<rdf:li>{}</rdf:li>", escape(keyword)));
+            }
+            props.push_str("</rdf:Bag></dc:subject>\n");
+        }
+        format!(
+            "<?xpacket begin=\"\u{feff}\" id=\"W5M0MpCehiHzreSzNTczkc9d\"?>\n\
+             <x:xmpmeta xmlns:x=\"adobe:ns:meta/\">\n \
+             <rdf:RDF xmlns:rdf=\"http://www.w3.org/1999/02/22-rdf-syntax-ns#\">\n  \
+             <rdf:Description rdf:about=\"\" \
+             xmlns:dc=\"http://purl.org/dc/elements/1.1/\" \
+             xmlns:xmp=\"http://ns.adobe.com/xap/1.0/\">\n\
+             {props}  </rdf:Description>\n \
+             </rdf:RDF>\n\
+             </x:xmpmeta>\n\
+             <?xpacket end=\"w\"?>"
+        )
+    }
+
+    /// IPTC-IIM records in UTF-8, cut to the lengths the standard allows.
+    fn to_iptc(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        // 1:90, coded character set: UTF-8.
+        iim_record(&mut out, 1, 90, b"\x1b%G");
+        // 2:00, record version 4.
+        iim_record(&mut out, 2, 0, &[0, 4]);
+        if let Some(title) = &self.title {
+            iim_record(&mut out, 2, 5, truncate(title, 64).as_bytes());
+        }
+        for keyword in &self.keywords {
+            iim_record(&mut out, 2, 25, truncate(keyword, 64).as_bytes());
+        }
+        if let Some(copyright) = &self.copyright {
+            iim_record(&mut out, 2, 116, truncate(copyright, 128).as_bytes());
+        }
+        if let Some(caption) = &self.caption {
+            iim_record(&mut out, 2, 120, truncate(caption, 2000).as_bytes());
+        }
+        out
+    }
+}
+
+/// Whether [`embed`] can write into a file at `path`.
+pub fn supports(path: &Path) -> bool {
+    matches!(extension(path).as_str(), "jpg" | "jpeg" | "png")
+}
+
+/// How many bytes [`embed`] adds to a file at `path`, so an export with a
+/// size limit can leave room for it.
+pub fn overhead(path: &Path, metadata: &EmbeddedMetadata) -> u64 {
+    if metadata.is_empty() {
+        return 0;
+    }
+    let xmp = metadata.to_xmp().len();
+    let size = match extension(path).as_str() {
+        "jpg" | "jpeg" => {
+            let iptc = image_resource(0x0404, &metadata.to_iptc()).len();
+            4 + XMP_SIGNATURE.len() + xmp + 4 + PHOTOSHOP_SIGNATURE.len() + iptc
+        }
+        // Length, type, keyword and flags, the packet, then the CRC.
+        "png" => 4 + 4 + PNG_XMP_KEYWORD.len() + xmp + 4,
+        _ => 0,
+    };
+    size as u64
+}
+
+/// Write `metadata` into the JPEG or PNG at `path`, in place.
+pub fn embed(path: &Path, metadata: &EmbeddedMetadata) -> Result<()> {
+    if metadata.is_empty() {
+        return Ok(());
+    }
+    let bytes = std::fs::read(path).with_context(|| format!("read {}", path.display()))?;
+    let out = match extension(path).as_str() {
+        "jpg" | "jpeg" => embed_jpeg(&bytes, metadata)?,
+        "png" => embed_png(&bytes, metadata)?,
+        _ => bail!("can't write metadata into {}", path.display()),
+    };
+    std::fs::write(path, out).with_context(|| format!("write {}", path.display()))
+}
+
+fn extension(path: &Path) -> String {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("")
+        .to_ascii_lowercase()
+}
+
+/// Insert APP1 (XMP) and APP13 (IPTC) segments after the JPEG's existing
+/// application segments, which JFIF and EXIF readers expect first.
+fn embed_jpeg(bytes: &[u8], metadata: &EmbeddedMetadata) -> Result<Vec<u8>> {
+    if !bytes.starts_with(&[0xff, 0xd8]) {
+        bail!("not a JPEG");
+    }
+    let mut at = 2;
+    while let [0xff, 0xe0..=0xef, hi, lo, ..] = bytes[at..] {
+        at += 2 + u16::from_be_bytes([hi, lo]) as usize;
+        if at > bytes.len() {
+            bail!("truncated JPEG");
+        }
+    }
+
+    let mut xmp = XMP_SIGNATURE.to_vec();
+    xmp.extend_from_slice(metadata.to_xmp().as_bytes());
+    let mut photoshop = PHOTOSHOP_SIGNATURE.to_vec();
+    photoshop.extend_from_slice(&image_resource(0x0404, &metadata.to_iptc()));
+
+    let mut out = Vec::with_capacity(bytes.len() + xmp.len() + photoshop.len() + 8);
+    out.extend_from_slice(&bytes[..at]);
+    jpeg_segment(&mut out, 0xe1, &xmp)?;
+    jpeg_segment(&mut out, 0xed, &photoshop)?;
+    out.extend_from_slice(&bytes[at..]);
+    Ok(out)
+}
+
+fn jpeg_segment(out: &mut Vec<u8>, marker: u8, payload: &[u8]) -> Result<()> {
+    if payload.len() > MAX_SEGMENT {
+        bail!("metadata is too long for a JPEG segment");
+    }
+    out.extend_from_slice(&[0xff, marker]);
+    out.extend_from_slice(&(payload.len() as u16 + 2).to_be_bytes());
+    out.extend_from_slice(payload);
+    Ok(())
+}
+
+/// A Photoshop image resource block with an empty name.
+fn image_resource(id: u16, data: &[u8]) -> Vec<u8> {
+    let mut out = b"8BIM".to_vec();
+    out.extend_from_slice(&id.to_be_bytes());
+    out.extend_from_slice(&[0, 0]);
+    out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    out.extend_from_slice(data);
+    if data.len() % 2 == 1 {
+        out.push(0);
+    }
+    out
+}
+
+fn iim_record(out: &mut Vec<u8>, record: u8, dataset: u8, data: &[u8]) {
+    out.extend_from_slice(&[0x1c, record, dataset]);
+    out.extend_from_slice(&(data.len() as u16).to_be_bytes());
+    out.extend_from_slice(data);
+}
+
+/// Insert an iTXt chunk holding the XMP packet right after IHDR.
+fn embed_png(bytes: &[u8], metadata: &EmbeddedMetadata) -> Result<Vec<u8>> {
+    const SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+    if !bytes.starts_with(SIGNATURE) || bytes.len() < 33 || &bytes[12..16] != b"IHDR" {
+        bail!("not a PNG");
+    }
+    // Signature, then IHDR: length, type, 13 bytes of data and CRC.
+    let at = SIGNATURE.len() + 4 + 4 + 13 + 4;
+
+    let mut chunk = b"iTXt".to_vec();
+    chunk.extend_from_slice(PNG_XMP_KEYWORD);
+    chunk.extend_from_slice(metadata.to_xmp().as_bytes());
+
+    let mut out = Vec::with_capacity(bytes.len() + chunk.len() + 8);
+    out.extend_from_slice(&bytes[..at]);
+    out.extend_from_slice(&(chunk.len() as u32 - 4).to_be_bytes());
+    out.extend_from_slice(&chunk);
+    out.extend_from_slice(&crc32fast::hash(&chunk).to_be_bytes());
+    out.extend_from_slice(&bytes[at..]);
+    Ok(out)
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// `value` cut to at most `max` bytes without splitting a character.
+fn truncate(value: &str, max: usize) -> &str {
+    let mut end = value.len().min(max);
+    while !value.is_char_boundary(end) {
+        end -= 1;
+    }
+    &value[..end]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metadata() -> EmbeddedMetadata {
+        EmbeddedMetadata {
+            title: Some("Harbor at dawn".into()),
+            caption: Some("Boats & fog <early>".into()),
+            keywords: vec!["harbor".into(), "fog".into()],
+            copyright: Some("© 2026 Studio".into()),
+            rating: Some(4),
+        }
+    }
+
+    fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+        haystack.windows(needle.len()).any(|w| w == needle)
+    }
+
+    #[test]
+    fn jpegs_get_xmp_and_iptc_and_still_decode() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("out.jpg");
+        image::RgbImage::from_pixel(16, 16, image::Rgb([200, 120, 40]))
+            .save(&path)
+            .unwrap();
+        let before = std::fs::metadata(&path).unwrap().len();
+        embed(&path, &metadata()).unwrap();
+
+        let bytes = std::fs::read(&path).unwrap();
+        assert_eq!(bytes.len() as u64, before + overhead(&path, &metadata()));
+        assert!(contains(&bytes, XMP_SIGNATURE));
+        assert!(contains(&bytes, b"<xmp:Rating>4</xmp:Rating>"));
+        assert!(contains(&bytes, b"Boats &amp; fog &lt;early&gt;"));
+        // IPTC 2:25 keyword record for "fog".
+        assert!(contains(&bytes, b"\x1c\x02\x19\x00\x03fog"));
+        assert_eq!(image::open(&path).unwrap().width(), 16);
+    }
+
+    #[test]
+    fn pngs_get_an_xmp_chunk() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("out.png");
+        image::RgbImage::from_pixel(8, 8, image::Rgb([10, 20, 30]))
+            .save(&path)
+            .unwrap();
+        let before = std::fs::metadata(&path).unwrap().len();
+        embed(&path, &metadata()).unwrap();
+
+        let bytes = std::fs::read(&path).unwrap();
+        assert_eq!(bytes.len() as u64, before + overhead(&path, &metadata()));
+        assert!(contains(&bytes, b"iTXtXML:com.adobe.xmp"));
+        assert!(contains(&bytes, "© 2026 Studio".as_bytes()));
+        assert_eq!(image::open(&path).unwrap().height(), 8);
+        assert!(!supports(Path::new("out.tif")));
+    }
+
+    #[test]
+    fn iptc_fields_are_cut_on_character_boundaries() {
+        assert_eq!(truncate("héllo", 2), "h");
+        assert_eq!(truncate("abc", 64), "abc");
+    }
+}
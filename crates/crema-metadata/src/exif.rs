@@ -19,6 +19,14 @@ pub struct ExifData {
     pub iso: Option<u32>,
     pub date_taken: Option<String>,
     pub orientation: Option<u32>,
+    pub serial_number: Option<String>,
+    /// Exposure time in seconds; `shutter_speed` is the display form.
+    pub exposure_secs: Option<f64>,
+    /// Ambient or sensor temperature in degrees Celsius, when recorded.
+    pub temperature: Option<f64>,
+    /// Exposure compensation in EV, which sets bracketed frames apart.
+    #[serde(default)]
+    pub exposure_bias: Option<f64>,
 }
 
 impl ExifData {
@@ -43,6 +51,10 @@ impl ExifData {
             iso: get_u32(&exif, Tag::PhotographicSensitivity),
             date_taken: get_string(&exif, Tag::DateTimeOriginal),
             orientation: get_u32(&exif, Tag::Orientation),
+            serial_number: get_string(&exif, Tag::BodySerialNumber),
+            exposure_secs: get_rational_f64(&exif, Tag::ExposureTime),
+            temperature: get_rational_f64(&exif, Tag::Temperature),
+            exposure_bias: get_rational_f64(&exif, Tag::ExposureBiasValue),
         })
     }
 
@@ -135,6 +147,13 @@ fn get_rational_f64(exif: &exif::Exif, tag: Tag) -> Option<f64> {
                     Some(r.num as f64 / r.denom as f64)
                 }
             }),
+            exif::Value::SRational(ref v) => v.first().and_then(|r| {
+                if r.denom == 0 {
+                    None
+                } else {
+                    Some(r.num as f64 / r.denom as f64)
+                }
+            }),
             _ => f.display_value().to_string().trim().parse().ok(),
         })
 }
@@ -360,6 +379,10 @@ mod tests {
             height: Some(5464),
             date_taken: Some("2024-03-15 10:30:00".into()),
             orientation: None,
+            serial_number: None,
+            exposure_secs: None,
+            temperature: None,
+            exposure_bias: None,
         };
         let lines = data.summary_lines();
         assert_eq!(lines.len(), 8);
@@ -400,6 +423,10 @@ mod tests {
             height: Some(4672),
             date_taken: Some("2025-01-20 14:00:00".into()),
             orientation: Some(1),
+            serial_number: None,
+            exposure_secs: None,
+            temperature: None,
+            exposure_bias: None,
         };
         let lines = data.summary_lines();
         let labels: Vec<&str> = lines.iter().map(|(k, _)| k.as_str()).collect();
@@ -719,6 +746,10 @@ mod tests {
             iso: Some(64),
             date_taken: Some("2025-06-01 08:15:30".into()),
             orientation: Some(1),
+            serial_number: None,
+            exposure_secs: None,
+            temperature: None,
+            exposure_bias: None,
         };
         let json = serde_json::to_string(&data).unwrap();
         let rt: ExifData = serde_json::from_str(&json).unwrap();
@@ -47,6 +47,53 @@ impl ThumbnailCache {
         fs::read(&path).ok()
     }
 
+    /// Read the most recently stored thumbnail whose key starts with
+    /// `prefix`, for when the exact key can't be worked out.
+    pub fn load_newest(&self, prefix: &str) -> Option<Vec<u8>> {
+        let bucket = self.thumbnail_path(prefix).parent()?.to_path_buf();
+        let newest = fs::read_dir(bucket)
+            .ok()?
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| {
+                entry
+                    .path()
+                    .file_stem()
+                    .and_then(|s| s.to_str())
+                    .is_some_and(|key| key.starts_with(prefix))
+            })
+            .max_by_key(|entry| entry.metadata().and_then(|m| m.modified()).ok())?;
+        fs::read(newest.path()).ok()
+    }
+
+    /// Delete every cached thumbnail whose key starts with `prefix`, other
+    /// than `keep`, for variants that have been superseded. Returns how
+    /// many were removed.
+    pub fn prune(&self, prefix: &str, keep: &str) -> Result<usize> {
+        let Some(bucket) = self.thumbnail_path(prefix).parent().map(Path::to_path_buf) else {
+            return Ok(0);
+        };
+        let entries = match fs::read_dir(&bucket) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+            Err(err) => {
+                return Err(err).with_context(|| format!("list cache: {}", bucket.display()));
+            }
+        };
+        let mut removed = 0;
+        for entry in entries {
+            let path = entry?.path();
+            let Some(key) = path.file_stem().and_then(|s| s.to_str()) else {
+                continue;
+            };
+            if key.starts_with(prefix) && key != keep {
+                fs::remove_file(&path)
+                    .with_context(|| format!("remove thumbnail: {}", path.display()))?;
+                removed += 1;
+            }
+        }
+        Ok(removed)
+    }
+
     pub fn cache_dir(&self) -> &Path {
         &self.cache_dir
     }
@@ -118,6 +165,46 @@ mod tests {
         let _ = fs::remove_dir_all(&dir);
     }
 
+    #[test]
+    fn prune_keeps_only_the_current_variant() {
+        let dir = env::temp_dir().join("crema_cache_test_prune");
+        let _ = fs::remove_dir_all(&dir);
+        let cache = ThumbnailCache::new(dir.clone()).unwrap();
+
+        for key in [
+            "ab12",
+            "ab12-edited-old",
+            "ab12-edited-new",
+            "ab12-frame2-edited-x",
+        ] {
+            cache.store(key, b"thumb").unwrap();
+        }
+        assert_eq!(cache.prune("ab12-edited-", "ab12-edited-new").unwrap(), 1);
+        assert!(cache.has_thumbnail("ab12"));
+        assert!(!cache.has_thumbnail("ab12-edited-old"));
+        assert!(cache.has_thumbnail("ab12-edited-new"));
+        assert!(cache.has_thumbnail("ab12-frame2-edited-x"));
+        assert_eq!(cache.prune("cd34-edited-", "").unwrap(), 0);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn load_newest_picks_the_latest_match() {
+        let dir = env::temp_dir().join("crema_cache_test_newest");
+        let _ = fs::remove_dir_all(&dir);
+        let cache = ThumbnailCache::new(dir.clone()).unwrap();
+
+        cache.store("ab12-v1", b"old").unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        cache.store("ab12-v2", b"new").unwrap();
+        cache.store("ab12-frame2-v3", b"other frame").unwrap();
+        assert_eq!(cache.load_newest("ab12-v").unwrap(), b"new");
+        assert!(cache.load_newest("cd34-v").is_none());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
     #[test]
     fn cache_dir_accessor() {
         let dir = env::temp_dir().join("crema_cache_test_accessor");
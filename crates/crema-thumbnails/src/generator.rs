@@ -6,7 +6,7 @@ use image::imageops::FilterType;
 use image::{DynamicImage, RgbaImage};
 use tracing::debug;
 
-use crema_core::image_buf::ImageBuf;
+use crema_core::image_buf::{EditParams, ImageBuf};
 
 const THUMBNAIL_LONGEST_EDGE: u32 = 512;
 
@@ -52,6 +52,23 @@ pub fn fast_thumbnail(path: &Path) -> Result<Vec<u8>> {
     thumbnail_for_file(path)
 }
 
+/// A thumbnail of one frame of an animated file.
+pub fn frame_thumbnail(path: &Path, frame: u32) -> Result<Vec<u8>> {
+    let buf = crema_core::raw::load_frame_scaled(path, frame, Some(THUMBNAIL_LONGEST_EDGE))?;
+    generate_thumbnail(&buf)
+}
+
+/// Render a thumbnail of `frame` with `params` applied, for photos edited
+/// from the library without opening them in develop. Stills have only
+/// frame 0.
+pub fn edited_thumbnail(path: &Path, frame: u32, params: &EditParams) -> Result<Vec<u8>> {
+    let buf = crema_core::raw::load_frame_scaled(path, frame, Some(THUMBNAIL_LONGEST_EDGE))?;
+    let processed = crema_core::pipeline::Pipeline::new()
+        .process_cpu(buf, params)
+        .context("apply edits to thumbnail")?;
+    generate_thumbnail(&processed)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -197,4 +214,29 @@ mod tests {
 
         assert!(gradient_jpeg.len() > solid_jpeg.len());
     }
+
+    #[test]
+    fn edited_thumbnail_applies_exposure() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("gray.png");
+        image::RgbImage::from_pixel(64, 64, image::Rgb([100, 100, 100]))
+            .save(&path)
+            .unwrap();
+
+        let center_luma = |jpeg: &[u8]| {
+            let img = image::load_from_memory(jpeg).unwrap().to_luma8();
+            img.get_pixel(img.width() / 2, img.height() / 2).0[0]
+        };
+        let plain = edited_thumbnail(&path, 0, &EditParams::default()).unwrap();
+        let brighter = edited_thumbnail(
+            &path,
+            0,
+            &EditParams {
+                exposure: 1.0,
+                ..EditParams::default()
+            },
+        )
+        .unwrap();
+        assert!(center_luma(&brighter) > center_luma(&plain) + 20);
+    }
 }
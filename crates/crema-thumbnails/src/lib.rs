@@ -1,2 +1,3 @@
 pub mod cache;
 pub mod generator;
+pub mod smart_preview;
@@ -0,0 +1,82 @@
+//! Smart previews: reduced, lossy proxies of a photo's decode, written at
+//! import so edits keep working when the original's drive is offline.
+//!
+//! Proxies are sRGB JPEGs at high quality. DNG and JPEG XL would keep more
+//! bit depth, but the `image` crate can write neither, and at this size the
+//! sRGB curve spends its 8 bits where edits need them.
+
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use image::RgbImage;
+use image::codecs::jpeg::JpegEncoder;
+
+use crema_core::image_buf::ImageBuf;
+
+/// Longest edge of a smart preview: enough for a full-screen develop view
+/// and web-sized exports.
+pub const SMART_PREVIEW_EDGE: u32 = 2560;
+const SMART_PREVIEW_QUALITY: u8 = 90;
+
+/// Downsample `buf` and write it to `path` as a smart preview, returning
+/// the stored size.
+pub fn write_smart_preview(buf: &ImageBuf, path: &Path) -> Result<(u32, u32)> {
+    let small = buf.downsample(SMART_PREVIEW_EDGE);
+    let rgb: Vec<u8> = small
+        .to_rgba_u8_srgb()
+        .chunks_exact(4)
+        .flat_map(|p| [p[0], p[1], p[2]])
+        .collect();
+    let img = RgbImage::from_raw(small.width, small.height, rgb)
+        .context("failed to create image from buffer")?;
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("create smart preview dir: {}", parent.display()))?;
+    }
+    let file = fs::File::create(path)
+        .with_context(|| format!("create smart preview: {}", path.display()))?;
+    let encoder =
+        JpegEncoder::new_with_quality(std::io::BufWriter::new(file), SMART_PREVIEW_QUALITY);
+    img.write_with_encoder(encoder)
+        .with_context(|| format!("encode smart preview: {}", path.display()))?;
+    Ok((small.width, small.height))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gradient(w: u32, h: u32) -> ImageBuf {
+        let mut buf = ImageBuf::new(w, h);
+        for (i, px) in buf.data.chunks_exact_mut(3).enumerate() {
+            let t = (i as u32 % w) as f32 / w as f32;
+            px.copy_from_slice(&[t, 0.5 * t, 0.18]);
+        }
+        buf
+    }
+
+    #[test]
+    fn shrinks_to_the_preview_edge() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("nested").join("proxy.jpg");
+        let size = write_smart_preview(&gradient(3000, 2000), &path).unwrap();
+        assert_eq!(size, (SMART_PREVIEW_EDGE, 1707));
+
+        let loaded = crema_core::raw::load_any(&path).unwrap();
+        assert_eq!((loaded.width, loaded.height), size);
+    }
+
+    #[test]
+    fn keeps_small_images_and_their_tones() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("proxy.jpg");
+        let buf = gradient(64, 32);
+        assert_eq!(write_smart_preview(&buf, &path).unwrap(), (64, 32));
+
+        let loaded = crema_core::raw::load_any(&path).unwrap();
+        let mean = |b: &ImageBuf| b.data.iter().sum::<f32>() / b.data.len() as f32;
+        assert!((mean(&loaded) - mean(&buf)).abs() < 0.01);
+    }
+}
@@ -0,0 +1,287 @@
+//! Short shareable animations of an edit: a crossfade from the original to
+//! the edited photo, or a sweep of one parameter from its default to the
+//! current value. GIFs are encoded in-process; MP4 goes through `ffmpeg`.
+
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use anyhow::{Context, Result, bail};
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::{Delay, Frame, RgbaImage};
+
+use crema_core::image_buf::{EditParams, ImageBuf};
+use crema_core::pipeline::Pipeline;
+use crema_core::{comparison, edit_diff};
+
+/// Longest edge of animation frames. Sharing sites downscale anything
+/// larger, and GIF size grows quickly with resolution.
+const MAX_EDGE: u32 = 720;
+/// Frames for one direction of the animation; it plays forward then back.
+const FRAMES: u32 = 20;
+const FRAME_DELAY_MS: u32 = 70;
+/// Frames held still at each end so the before and after can be read.
+const HOLD_FRAMES: u32 = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnimationKind {
+    /// Crossfade from the unedited photo to the current edit.
+    BeforeAfter,
+    /// Sweep one parameter, named by its `edit_diff` key, from its default
+    /// to the current value with everything else held at the current edit.
+    Sweep(&'static str),
+}
+
+impl AnimationKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            AnimationKind::BeforeAfter => "Before / After",
+            AnimationKind::Sweep(key) => edit_diff::find(key).map_or(key, |p| p.label),
+        }
+    }
+
+    pub fn file_stem(self) -> String {
+        match self {
+            AnimationKind::BeforeAfter => "before-after".into(),
+            AnimationKind::Sweep(key) => key.replace('_', "-"),
+        }
+    }
+}
+
+/// Parameters that make a visible sweep: those moved away from their
+/// default, excluding crop and straighten which change the frame size.
+pub fn sweepable_params(params: &EditParams) -> Vec<&'static edit_diff::EditParam> {
+    edit_diff::diff(&EditParams::default(), params)
+        .into_iter()
+        .map(|d| d.param)
+        .filter(|p| !p.key.starts_with("crop_") && p.key != "rotation")
+        .collect()
+}
+
+/// Render every frame of the animation, including the holds and the
+/// return trip.
+pub fn render_frames(
+    buf: &ImageBuf,
+    params: &EditParams,
+    kind: AnimationKind,
+) -> Result<Vec<RgbaImage>> {
+    let source = buf.downsample(MAX_EDGE);
+    let pipeline = Pipeline::new();
+    let render = |params: &EditParams| -> Result<RgbaImage> {
+        let out = pipeline.process_cpu(source.clone(), params)?;
+        RgbaImage::from_raw(out.width, out.height, out.to_rgba_u8_srgb())
+            .context("could not construct frame buffer")
+    };
+
+    let forward = match kind {
+        AnimationKind::BeforeAfter => {
+            let before = render(&comparison::before_params(params))?;
+            let after = render(params)?;
+            (0..FRAMES)
+                .map(|i| blend(&before, &after, progress(i)))
+                .collect()
+        }
+        AnimationKind::Sweep(key) => {
+            let Some(param) = edit_diff::find(key) else {
+                bail!("unknown parameter {key}");
+            };
+            let from = param.value(&EditParams::default());
+            let to = param.value(params);
+            let mut frame_params = params.clone();
+            (0..FRAMES)
+                .map(|i| {
+                    param.set(&mut frame_params, from + (to - from) * progress(i));
+                    render(&frame_params)
+                })
+                .collect::<Result<Vec<_>>>()?
+        }
+    };
+
+    Ok(ping_pong(forward))
+}
+
+/// Eased 0..=1 position of frame `i`.
+fn progress(i: u32) -> f32 {
+    let t = i as f32 / (FRAMES - 1) as f32;
+    t * t * (3.0 - 2.0 * t)
+}
+
+fn blend(a: &RgbaImage, b: &RgbaImage, t: f32) -> RgbaImage {
+    let mut out = a.clone();
+    for (o, &v) in out.iter_mut().zip(b.iter()) {
+        *o = (*o as f32 + (v as f32 - *o as f32) * t).round() as u8;
+    }
+    out
+}
+
+/// Hold the first and last frames, then play back to the start so the
+/// animation loops without a jump.
+fn ping_pong(forward: Vec<RgbaImage>) -> Vec<RgbaImage> {
+    let (Some(first), Some(last)) = (forward.first().cloned(), forward.last().cloned()) else {
+        return forward;
+    };
+    let mut frames = Vec::with_capacity(forward.len() * 2 + HOLD_FRAMES as usize * 2);
+    frames.extend(std::iter::repeat_n(first, HOLD_FRAMES as usize));
+    frames.extend(forward.iter().cloned());
+    frames.extend(std::iter::repeat_n(last, HOLD_FRAMES as usize));
+    frames.extend(forward.into_iter().rev().skip(1));
+    frames
+}
+
+/// Encode `frames` to `path`, choosing the format from the extension.
+pub fn encode(frames: Vec<RgbaImage>, path: &Path) -> Result<()> {
+    let ext = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("")
+        .to_ascii_lowercase();
+    match ext.as_str() {
+        "gif" => encode_gif(frames, path),
+        "mp4" => encode_mp4(&frames, path),
+        _ => bail!("unsupported animation format \".{ext}\""),
+    }
+}
+
+fn encode_gif(frames: Vec<RgbaImage>, path: &Path) -> Result<()> {
+    let file = std::fs::File::create(path)
+        .with_context(|| format!("failed to create {}", path.display()))?;
+    let mut encoder = GifEncoder::new_with_speed(std::io::BufWriter::new(file), 10);
+    encoder.set_repeat(Repeat::Infinite)?;
+    let delay = Delay::from_numer_denom_ms(FRAME_DELAY_MS, 1);
+    encoder
+        .encode_frames(
+            frames
+                .into_iter()
+                .map(|f| Frame::from_parts(f, 0, 0, delay)),
+        )
+        .context("failed to encode GIF")
+}
+
+/// Pipe raw RGBA frames to the system `ffmpeg`. H.264 needs even
+/// dimensions, so odd edges are trimmed by the scale filter.
+fn encode_mp4(frames: &[RgbaImage], path: &Path) -> Result<()> {
+    let Some(first) = frames.first() else {
+        bail!("no frames to encode");
+    };
+    let fps = 1000.0 / FRAME_DELAY_MS as f32;
+    let mut child = Command::new("ffmpeg")
+        .args([
+            "-y",
+            "-loglevel",
+            "error",
+            "-f",
+            "rawvideo",
+            "-pix_fmt",
+            "rgba",
+        ])
+        .args(["-s", &format!("{}x{}", first.width(), first.height())])
+        .args(["-r", &format!("{fps:.3}"), "-i", "-"])
+        .args(["-vf", "scale=trunc(iw/2)*2:trunc(ih/2)*2"])
+        .args([
+            "-c:v",
+            "libx264",
+            "-pix_fmt",
+            "yuv420p",
+            "-movflags",
+            "+faststart",
+        ])
+        .arg(path)
+        .stdin(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("MP4 export needs ffmpeg on the PATH")?;
+
+    {
+        let mut stdin = child.stdin.take().context("ffmpeg stdin unavailable")?;
+        for frame in frames {
+            stdin
+                .write_all(frame.as_raw())
+                .context("failed to send frame to ffmpeg")?;
+        }
+    }
+    let output = child.wait_with_output().context("ffmpeg did not finish")?;
+    if !output.status.success() {
+        bail!(
+            "ffmpeg failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gray(level: f32) -> ImageBuf {
+        ImageBuf::from_data(8, 6, vec![level; 8 * 6 * 3]).unwrap()
+    }
+
+    #[test]
+    fn sweepable_params_skip_geometry() {
+        let params = EditParams {
+            exposure: 1.0,
+            crop_w: 0.5,
+            rotation: 3.0,
+            ..EditParams::default()
+        };
+        let keys: Vec<_> = sweepable_params(&params).iter().map(|p| p.key).collect();
+        assert_eq!(keys, ["exposure"]);
+    }
+
+    #[test]
+    fn before_after_starts_unedited_and_ends_edited() {
+        let params = EditParams {
+            exposure: 1.5,
+            ..EditParams::default()
+        };
+        let frames = render_frames(&gray(0.18), &params, AnimationKind::BeforeAfter).unwrap();
+        assert_eq!(frames.len(), (FRAMES * 2 - 1 + HOLD_FRAMES * 2) as usize);
+
+        let first = frames[0].get_pixel(4, 3).0[0];
+        let peak = frames[(HOLD_FRAMES + FRAMES) as usize].get_pixel(4, 3).0[0];
+        assert!(peak > first + 30, "{first} -> {peak}");
+        assert_eq!(
+            frames.last().unwrap().get_pixel(4, 3),
+            frames[0].get_pixel(4, 3)
+        );
+    }
+
+    #[test]
+    fn sweep_is_monotonic() {
+        let params = EditParams {
+            exposure: 2.0,
+            ..EditParams::default()
+        };
+        let frames = render_frames(&gray(0.1), &params, AnimationKind::Sweep("exposure")).unwrap();
+        let forward = &frames[HOLD_FRAMES as usize..(HOLD_FRAMES + FRAMES) as usize];
+        let levels: Vec<u8> = forward.iter().map(|f| f.get_pixel(0, 0).0[0]).collect();
+        assert!(levels.windows(2).all(|w| w[0] <= w[1]), "{levels:?}");
+        assert!(levels[0] < *levels.last().unwrap());
+    }
+
+    #[test]
+    fn writes_a_gif() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("anim.gif");
+        let frames = render_frames(
+            &gray(0.3),
+            &EditParams {
+                contrast: 40.0,
+                ..EditParams::default()
+            },
+            AnimationKind::BeforeAfter,
+        )
+        .unwrap();
+        encode(frames, &path).unwrap();
+        let bytes = std::fs::read(&path).unwrap();
+        assert!(bytes.starts_with(b"GIF89a"));
+    }
+
+    #[test]
+    fn rejects_unknown_format() {
+        let dir = tempfile::tempdir().unwrap();
+        let err = encode(vec![RgbaImage::new(2, 2)], &dir.path().join("a.webm")).unwrap_err();
+        assert!(err.to_string().contains("unsupported"));
+    }
+}
@@ -1,18 +1,39 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 
 use iced::{Element, Task, Theme};
 use tracing::{error, info};
 
 use crema_catalog::db::Catalog;
-use crema_catalog::models::{Photo, PhotoId};
+use crema_catalog::models::{
+    Animation, DarkFrameSettings, DerivationKind, MasterDarkId, MetadataOverride, Photo, PhotoId,
+    PhotoLink, PhotoStack, QuarantineId, QuarantinedFile, SmartPreview, Snapshot, SnapshotId,
+    ThumbnailFidelity,
+};
+use crema_core::annotation::{Annotation, FrameMap};
+use crema_core::color_range::ColorRange;
+use crema_core::comparison::{self, ComparisonLayout};
+use crema_core::compositing::StackMethod;
+use crema_core::dither::Dither;
 use crema_core::image_buf::{EditParams, ImageBuf};
+use crema_core::pipeline::precision::PrecisionReport;
+use crema_core::preview;
+use crema_core::scan_border::ScanCrop;
+use crema_export::format::{
+    self as export_format, ExportColorSpace, ExportEncoding, ExportOutcome, FittedQuality,
+};
+use crema_export::render::export_full_res;
 use crema_gpu::context::GpuContext;
 use crema_gpu::pipeline::GpuPipeline;
+use crema_metadata::embed;
+use crema_metadata::exif::ExifData;
 use crema_thumbnails::cache::ThumbnailCache;
 
 type GpuHandle = Arc<std::sync::Mutex<(GpuContext, GpuPipeline)>>;
+/// Photos per month and day of one year.
+type DayCounts = Vec<(u8, u8, usize)>;
 
 #[derive(Clone)]
 pub(crate) struct GpuReady(GpuHandle);
@@ -22,10 +43,46 @@ impl std::fmt::Debug for GpuReady {
     }
 }
 
+use crate::animation::AnimationKind;
+use crate::calibration::{
+    Calibration, DetectedDefectMap, DetectedFlatField, DetectedMasterDark, build_master_dark,
+    detect_defect_map, detect_flat_field,
+};
+use crate::demo;
+use crate::export_check::{self, ExportWarning};
+use crate::export_crop::{self, ExportCrop};
+use crate::export_metadata::ExportMetadata;
+use crate::export_plugin;
+use crate::notifications::{self, Action, Category, Notification, NotificationCenter};
+use crate::preferences::Preferences;
+use crate::render_farm;
+use crate::smart_preview;
+use crate::theme::{AccentColor, ColorVision, ScopePalette};
 use crate::views;
-use crate::widgets::date_sidebar::{DateExpansionKey, DateFilter, RatingFilter, SortOrder};
+use crate::volumes::{self, Volume};
+use crate::widgets::auto_stack::{self, AutoStackReview, StackRule, StackThreshold};
+use crate::widgets::batch_metadata::{BatchMetadataForm, MetadataField};
+use crate::widgets::color_range::RangeBound;
+use crate::widgets::comparison_export::ComparisonExport;
+use crate::widgets::date_sidebar::{
+    self, DateExpansionKey, DateFilter, DateIndex, GearCounts, GearFilter, RatingFilter, SortOrder,
+};
+use crate::widgets::export_options::ExportOptions;
+use crate::widgets::export_progress::ExportProgress;
+use crate::widgets::export_warnings::ExportReview;
+use crate::widgets::gear_override::{self, GearField, GearOverrideForm};
+use crate::widgets::gpu_diagnostics::RendererStatus;
 use crate::widgets::histogram::HistogramData;
-use crate::widgets::zoomable_image::ZoomState;
+use crate::widgets::metadata_panel::ProvenanceLink;
+use crate::widgets::path_remap::PathRemapForm;
+use crate::widgets::preset_editor::PresetEditor;
+use crate::widgets::quick_develop::QuickAdjustment;
+use crate::widgets::render_consistency::RenderConsistency;
+use crate::widgets::thumbnail_grid::GridLayout;
+use crate::widgets::time_machine::TimeMachine;
+use crate::widgets::zoomable_image::{
+    AnnotationLayer, AnnotationTool, ZoomState, actual_size_zoom,
+};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Workspace {
@@ -44,6 +101,11 @@ pub enum PanelSection {
     Detail,
     Lens,
     Crop,
+    Snapshots,
+    Annotations,
+    Presets,
+    QuickDevelop,
+    ColorRange,
     Metadata,
 }
 
@@ -86,8 +148,58 @@ pub enum EditControl {
     Rotation,
 }
 
+/// Which side of the edits diff a value is taken from. The left side is
+/// always the photo's current edit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffSide {
+    Left,
+    Right,
+}
+
 const MAX_UNDO_HISTORY: usize = 100;
 
+/// Photos per streamed page while the catalog loads. Small enough that the
+/// first page arrives well within a frame budget on large libraries.
+const PHOTO_PAGE_SIZE: usize = 1000;
+
+/// Longest edge the precision audit runs at. Rounding error doesn't depend
+/// on resolution, and the f64 pass is slow enough to keep this modest.
+const PRECISION_AUDIT_EDGE: u32 = 1024;
+
+/// Longest edge photos are measured at when matching exposure; a median
+/// is stable long before full resolution.
+const EXPOSURE_MATCH_EDGE: u32 = 512;
+
+/// Longest edge both backends render at for the consistency check, large
+/// enough that sharpening and other detail work is exercised.
+const RENDER_CONSISTENCY_EDGE: u32 = 2048;
+
+/// Longest edge photos are rendered at to measure a color range across a
+/// selection; a share of the frame settles well below full resolution.
+const COLOR_RANGE_EDGE: u32 = 1024;
+
+/// One chunk of the photo list, streamed while the catalog loads. The last
+/// message for a load is an empty page with `done` set, carrying the error
+/// if the list couldn't be read in full.
+#[derive(Debug, Clone)]
+pub struct PhotoPage {
+    pub photos: Vec<Photo>,
+    pub total: usize,
+    pub done: bool,
+    pub error: Option<String>,
+}
+
+/// Progress of an in-flight photo list load.
+struct CatalogLoad {
+    loaded: usize,
+    total: usize,
+    /// Show photos as pages arrive. Only used when the grid starts empty;
+    /// refreshes collect into `buffer` and swap in the finished list so the
+    /// grid doesn't shrink and regrow.
+    progressive: bool,
+    buffer: Vec<Photo>,
+}
+
 fn sidecar_path(photo_path: &str) -> PathBuf {
     Path::new(photo_path).with_extension("crema.json")
 }
@@ -101,8 +213,12 @@ pub struct App {
     right_panel_open: bool,
     catalog: Option<Catalog>,
     catalog_path: Option<String>,
+    /// Booted with `--demo` on a synthetic in-memory catalog.
+    demo: bool,
     photos: Vec<Photo>,
     thumbnails: std::collections::HashMap<PhotoId, iced::widget::image::Handle>,
+    /// Width over height of each loaded thumbnail, for aspect-aware grids.
+    thumbnail_aspects: std::collections::HashMap<PhotoId, f32>,
 
     current_image: Option<Arc<ImageBuf>>,
     preview_image: Option<Arc<ImageBuf>>,
@@ -114,6 +230,20 @@ pub struct App {
     undo_stack: Vec<EditParams>,
     redo_stack: Vec<EditParams>,
     edit_clipboard: Option<EditParams>,
+    snapshots: Vec<Snapshot>,
+    /// The snapshot the current edit was taken as or applied from, which
+    /// names the version and tells whether it's been changed since.
+    applied_snapshot: Option<SnapshotId>,
+    edit_diff: Option<SnapshotId>,
+    /// Review marks on the photo open in develop.
+    annotations: Vec<Annotation>,
+    annotations_visible: bool,
+    annotation_tool: Option<AnnotationTool>,
+    /// A note placed on the preview whose text is still being typed.
+    note_draft: Option<([f32; 2], String)>,
+    provenance_sources: Vec<ProvenanceLink>,
+    provenance_derivatives: Vec<ProvenanceLink>,
+    render_consistency: Option<RenderConsistency>,
 
     zoom_state: ZoomState,
     preview_dimensions: (u32, u32),
@@ -125,10 +255,26 @@ pub struct App {
     status_message: String,
 
     processing_generation: u64,
+    /// Bumped for each sidebar recount so only the latest one lands.
+    sidebar_generation: u64,
     thumbnail_cache_dir: Option<PathBuf>,
+    /// Folders whose thumbnails don't follow the fidelity preference.
+    folder_thumbnail_fidelity: HashMap<String, ThumbnailFidelity>,
+    /// The export crop whose metadata template preferences show, or
+    /// `None` for the shared one.
+    metadata_template_crop: Option<ExportCrop>,
     is_importing: bool,
     is_exporting: bool,
+    export_progress: Option<ExportProgress>,
+    catalog_load: Option<CatalogLoad>,
+    photo_load_generation: u64,
     is_loading_photo: bool,
+    /// Edits are rendering against a RAW file's embedded JPEG while the
+    /// full decode runs; they are re-applied to the decode when it lands.
+    editing_embedded_preview: bool,
+    /// The loaded photo's original is offline, so its smart preview was
+    /// opened instead.
+    editing_smart_preview: bool,
     is_processing: bool,
 
     gpu: Option<GpuHandle>,
@@ -136,9 +282,78 @@ pub struct App {
 
     date_filter: DateFilter,
     rating_filter: RatingFilter,
+    gear_filter: GearFilter,
     sort_order: SortOrder,
     expanded_dates: HashSet<DateExpansionKey>,
+    date_index: DateIndex,
+    gear_counts: GearCounts,
     panel_sections: HashSet<PanelSection>,
+
+    preferences: Preferences,
+    theme: Theme,
+    preferences_open: bool,
+
+    batch_metadata: Option<BatchMetadataForm>,
+    gear_override: Option<GearOverrideForm>,
+    preset_editor: Option<PresetEditor>,
+    auto_stack: Option<AutoStackReview>,
+    path_remap: Option<PathRemapForm>,
+    animation_export_open: bool,
+    comparison_export: Option<ComparisonExport>,
+    /// The export dialog, before a destination is chosen.
+    export_options: Option<ExportOptions>,
+    /// A single-photo export waiting on its warnings.
+    export_review: Option<ExportReview>,
+
+    quarantine: Vec<QuarantinedFile>,
+    /// Dead pixel maps and flat fields, applied to every full decode.
+    calibration: Arc<Calibration>,
+    smart_previews: std::collections::HashMap<PhotoId, SmartPreview>,
+    /// Animated GIFs and PNGs, with the frame each is shown as.
+    animations: std::collections::HashMap<PhotoId, Animation>,
+    /// Stacks by cover photo.
+    stacks: std::collections::HashMap<PhotoId, PhotoStack>,
+    /// The cover of each stacked photo that isn't one, hidden from the
+    /// grid unless its stack is expanded.
+    stacked_under: std::collections::HashMap<PhotoId, PhotoId>,
+    expanded_stacks: HashSet<PhotoId>,
+    color_range: ColorRange,
+    color_range_mask: bool,
+    /// Share of the develop preview in `color_range`, and the preview with
+    /// everything outside it dimmed.
+    color_range_reading: Option<(f32, iced::widget::image::Handle)>,
+    color_range_generation: u64,
+    /// Coverage of each photo from the last "Measure Selection", by name.
+    color_range_set: Vec<(String, Result<f32, String>)>,
+    /// Photos in the running batch export rendered from smart previews.
+    batch_smart_previews: usize,
+    /// Where the running batch export writes, for revealing it after.
+    batch_export_folder: Option<PathBuf>,
+    /// The file the running single-photo export writes.
+    export_target: Option<PathBuf>,
+    notifications: NotificationCenter,
+    quarantine_open: bool,
+    renderer: RendererStatus,
+    /// Days the time machine picker offers, while it's open.
+    time_machine_dates: Option<Vec<String>>,
+    time_machine: Option<TimeMachine>,
+    gpu_notice_open: bool,
+    gpu_diagnostics_open: bool,
+    stack_open: bool,
+    link_sources_open: bool,
+    /// Photos whose thumbnail failed to decode this session, so the
+    /// thumbnail pass doesn't keep retrying them.
+    thumbnail_failures: HashSet<PhotoId>,
+    /// Removable drives photos are on, and the photos on those offline.
+    volumes: Vec<Volume>,
+    offline_photos: HashSet<PhotoId>,
+    /// Thumbnails that couldn't be built because their drive is offline.
+    held_thumbnails: HashSet<PhotoId>,
+    /// Batch export jobs waiting for their drive to be mounted.
+    held_exports: Vec<render_farm::ExportJob>,
+    /// Smart previews to build, with their originals, once those are
+    /// mounted again.
+    held_previews: Vec<(PhotoId, PathBuf)>,
 }
 
 #[derive(Debug, Clone)]
@@ -150,9 +365,45 @@ pub enum Message {
 
     Import,
     ImportsSelected(Vec<PathBuf>),
-    ImportComplete(usize, usize),
+    /// Photos imported and failed, and the imported photos whose smart
+    /// previews wait for their drive.
+    ImportComplete(usize, usize, Vec<(PhotoId, PathBuf)>),
+    SmartPreviewsHeld(Vec<(PhotoId, PathBuf)>),
 
     ThumbnailReady(PhotoId, Vec<u8>),
+    ThumbnailFailed(PhotoId, String),
+
+    CreateDefectMap,
+    DarkFrameSelected(PathBuf),
+    DefectMapDetected(Result<DetectedDefectMap, String>),
+    DeleteDefectMap(String),
+    CreateFlatField,
+    FlatFrameSelected(PathBuf),
+    FlatFieldDetected(Result<DetectedFlatField, String>),
+    DeleteFlatField(String, String),
+    CreateMasterDark,
+    DarkFramesSelected(Vec<PathBuf>),
+    MasterDarkBuilt(Result<Arc<DetectedMasterDark>, String>),
+    DeleteMasterDark(MasterDarkId),
+    SetDarkFrameSubtraction(bool),
+
+    OpenStack,
+    CloseStack,
+    StackPhotos(StackMethod),
+    StackPathSelected(StackMethod, PathBuf),
+    StackComplete(Result<(PathBuf, Vec<PhotoId>), String>),
+
+    SidebarCounted(u64, Result<DateCounts, String>),
+
+    OpenLinkSources,
+    CloseLinkSources,
+    LinkSources(DerivationKind),
+
+    OpenQuarantine,
+    CloseQuarantine,
+    RetryQuarantined(QuarantineId),
+    QuarantineRetried(QuarantineId, Result<bool, String>),
+    DismissQuarantined(QuarantineId),
 
     ExposureChanged(f32),
     ContrastChanged(f32),
@@ -189,6 +440,12 @@ pub enum Message {
     PasteEdits,
     ZoomAtPoint(f32, f32, f32, f32, f32),
     PanDelta(f32, f32),
+    /// A two-finger pinch: zoom factor, how far the fingers' midpoint
+    /// moved, where it is now and the viewport size.
+    PinchAt(f32, iced::Vector, iced::Point, iced::Size),
+    /// Double-click or double-tap at a point in a viewport: 100% from fit,
+    /// back to fit otherwise.
+    ToggleActualSize(f32, f32, f32, f32),
     ResetZoom,
     ToggleBeforeAfter,
     OriginalReady(iced::widget::image::Handle),
@@ -204,38 +461,202 @@ pub enum Message {
     ResetCrop,
 
     ImageLoaded(PhotoId, Arc<ImageBuf>, Arc<ImageBuf>, Vec<(String, String)>),
+    EmbeddedPreviewLoaded(PhotoId, Arc<ImageBuf>),
     ImageProcessed(u64, iced::widget::image::Handle, Box<HistogramData>),
     ImageLoadFailed(PhotoId),
 
-    GpuInitDone(Option<GpuReady>),
+    GpuInitDone(RendererStatus, Option<GpuReady>),
+    DismissGpuNotice,
+    OpenGpuDiagnostics,
+    CloseGpuDiagnostics,
 
     CatalogOpened(String),
-    PhotosListed(Vec<Photo>),
+    PhotoPageLoaded(u64, PhotoPage),
 
+    /// Open the export dialog for the open photo.
     Export,
-    ExportPathSelected(PathBuf),
-    ExportComplete(String),
+    CloseExportOptions,
+    /// Pick where the export in the dialog goes, then start it.
+    ChooseExportDestination,
+    OpenAnimationExport,
+    CloseAnimationExport,
+    ExportAnimation(AnimationKind),
+    AnimationPathSelected(AnimationKind, PathBuf),
+    OpenComparisonExport,
+    ComparisonLayoutChanged(ComparisonLayout),
+    ComparisonLabelsToggled(bool),
+    ExportComparison,
+    ComparisonPathSelected(PathBuf),
+    CloseComparisonExport,
+    /// Show the open animated photo as this frame.
+    SetKeyFrame(u32),
+    ColorRangeChanged(RangeBound, f32),
+    ColorRangeMaskToggled(bool),
+    ColorRangeMeasured(u64, f32, iced::widget::image::Handle),
+    MeasureColorRangeSelection,
+    ColorRangeSelectionMeasured(Vec<(String, Result<f32, String>)>),
+    ExportPathSelected(PathBuf, ExportOptions),
+    ToggleExportWarningSilenced(ExportWarning),
+    ExportAnyway,
+    ExportAs16BitTiff,
+    CancelExportReview,
+    ExportProgress(f32),
+    CancelExport,
+    ExportComplete(ExportOutcome),
+    ToggleNotificationDrawer,
+    DismissNotification(u64),
+    RunNotificationAction(Action),
+    ExpireToasts,
+    SetNotificationMuted(Category, bool),
+    ClearNotifications,
 
     SaveSidecar,
     LoadSidecar,
 
+    AuditPrecision,
+    PrecisionAudited(Result<PrecisionReport, String>),
+    VerifyRenderConsistency,
+    RenderConsistencyVerified(Result<RenderConsistency, String>),
+    CloseRenderConsistency,
+
+    /// Open the export dialog for the selected photos.
     BatchExport,
-    BatchExportFolderSelected(PathBuf),
-    BatchExportComplete(usize, usize, usize),
+    BatchExportFolderSelected(PathBuf, ExportOptions),
+    /// Exported, renamed, total, post-processor failures, and the JPEG
+    /// quality each file fit to a target size was written at, by file name.
+    BatchExportComplete(
+        usize,
+        usize,
+        usize,
+        Vec<String>,
+        Vec<(String, FittedQuality)>,
+    ),
+    BatchExportProgress(usize, usize),
+    RenderFarmFinished(Result<render_farm::FarmReport, String>),
 
     SetDateFilter(DateFilter),
     SetRatingFilter(RatingFilter),
+    SetGearFilter(GearFilter),
     SetSortOrder(SortOrder),
     ToggleDateExpansion(DateExpansionKey),
     TogglePanelSection(PanelSection),
 
     ModifiersChanged(iced::keyboard::Modifiers),
+    /// Poll removable drives for being mounted or unmounted.
+    CheckVolumes,
+
+    TakeSnapshot,
+    DeleteSnapshot(SnapshotId),
+    ApplySnapshot(SnapshotId),
+    CompareSnapshot(SnapshotId),
+    TakeDiffParam(&'static str, DiffSide),
+    CloseEditDiff,
+
+    SetAnnotationTool(Option<AnnotationTool>),
+    SetAnnotationsVisible(bool),
+    AddAnnotation(Annotation),
+    /// Start a note at this source-frame point.
+    PlaceNote([f32; 2]),
+    NoteDraftChanged(String),
+    ConfirmNote,
+    CancelNote,
+    RemoveLastAnnotation,
+    ClearAnnotations,
+    SetExportAnnotations(bool),
+
+    QuickDevelop(QuickAdjustment),
+    OpenTimeMachine,
+    CloseTimeMachinePicker,
+    /// Browse the library as it was at the end of this `YYYY-MM-DD`.
+    BrowseAsOf(String),
+    RestoreFromTimeMachine,
+    LeaveTimeMachine,
+    OpenPathRemap,
+    PathRemapFromChanged(String),
+    PathRemapToChanged(String),
+    ApplyPathRemap,
+    ClosePathRemap,
+    PathRemapVerified(usize, usize),
+    MatchExposure,
+    ExposureMeasured(PhotoId, Vec<(PhotoId, Option<f32>)>),
+    AutoCropScan,
+    ScanCropDetected(PhotoId, Option<ScanCrop>),
+    AutoCropScans,
+    ScansAnalyzed(Vec<(PhotoId, Option<ScanCrop>)>),
+
+    OpenBatchMetadata,
+    BatchMetadataChanged(MetadataField, String),
+    BatchMetadataShiftChanged(String),
+    ApplyBatchMetadata,
+    CloseBatchMetadata,
+    OpenGearOverride,
+    GearOverrideChanged(GearField, String),
+    SetGearOverrideShoot(bool),
+    ApplyGearOverride,
+    ClearGearOverride,
+    CloseGearOverride,
+    NewPreset,
+    EditPreset(usize),
+    PresetNameChanged(String),
+    TogglePresetParam(&'static str),
+    TogglePresetGroup(usize),
+    SavePreset,
+    ClosePresetEditor,
+    ApplyPreset(usize),
+    DeletePreset(usize),
+    OpenAutoStack,
+    AutoStackScanned(std::collections::HashMap<PhotoId, f64>),
+    SetAutoStackRule(StackRule, bool),
+    StepAutoStackThreshold(StackThreshold, i32),
+    ToggleProposedStack(usize),
+    CommitAutoStack,
+    CloseAutoStack,
+    ToggleStackExpanded(PhotoId),
+    Unstack(PhotoId),
+
+    TogglePreferences,
+    SetAccentColor(AccentColor),
+    SetColorVision(ColorVision),
+    SetHighContrast(bool),
+    SetReduceMotion(bool),
+    SetGridLayout(GridLayout),
+    ToggleExportCrop(ExportCrop),
+    SetExportWorkers(usize),
+    SetExportDither(Dither),
+    SetExportColorSpace(ExportColorSpace),
+    SetExportTiff16Bit(bool),
+    SetExportTargetSize(Option<u64>),
+    ResetExportWarnings,
+    SetExportMetadataEnabled(bool),
+    ShowMetadataTemplate(Option<ExportCrop>),
+    SetCropMetadataOverride(ExportCrop, bool),
+    SetMetadataTemplateField(MetadataField, String),
+    SetMetadataTemplateRating(bool),
+    SetSmartPreviews(bool),
+    SetGpuWarmup(bool),
+    AddExportPlugin,
+    ExportPluginSelected(PathBuf),
+    SetThumbnailFidelity(ThumbnailFidelity),
+    AddThumbnailFolder,
+    ThumbnailFolderSelected(PathBuf),
+    SetFolderThumbnailFidelity(String, Option<ThumbnailFidelity>),
+    SetExportPluginEnabled(usize, bool),
+    RemoveExportPlugin(usize),
 
     Noop,
 }
 
 impl App {
     pub fn new() -> (Self, Task<Message>) {
+        let demo = demo::requested(&std::env::args().collect::<Vec<_>>());
+        // The demo starts from defaults and never writes the user's file.
+        let preferences = if demo {
+            Preferences::default()
+        } else {
+            Preferences::load()
+        };
+        let mut notifications = NotificationCenter::default();
+        notifications.hold_toasts = preferences.reduce_motion;
         let app = Self {
             menu: None,
             workspace: Workspace::Library,
@@ -245,8 +666,10 @@ impl App {
             right_panel_open: true,
             catalog: None,
             catalog_path: None,
+            demo,
             photos: Vec::new(),
             thumbnails: std::collections::HashMap::new(),
+            thumbnail_aspects: std::collections::HashMap::new(),
             current_image: None,
             preview_image: None,
             processed_image: None,
@@ -256,6 +679,16 @@ impl App {
             undo_stack: Vec::new(),
             redo_stack: Vec::new(),
             edit_clipboard: None,
+            snapshots: Vec::new(),
+            applied_snapshot: None,
+            edit_diff: None,
+            annotations: Vec::new(),
+            annotations_visible: true,
+            annotation_tool: None,
+            note_draft: None,
+            provenance_sources: Vec::new(),
+            provenance_derivatives: Vec::new(),
+            render_consistency: None,
             zoom_state: ZoomState::default(),
             preview_dimensions: (0, 0),
             original_display: None,
@@ -264,42 +697,131 @@ impl App {
             crop_aspect: None,
             status_message: "Welcome to Crema. Import photos to get started.".into(),
             processing_generation: 0,
-            thumbnail_cache_dir: dirs::cache_dir().map(|d| d.join("crema").join("thumbnails")),
+            sidebar_generation: 0,
+            // Demo thumbnails stay in memory rather than filling the cache.
+            thumbnail_cache_dir: dirs::cache_dir()
+                .filter(|_| !demo)
+                .map(|d| d.join("crema").join("thumbnails")),
+            folder_thumbnail_fidelity: HashMap::new(),
+            metadata_template_crop: None,
             is_importing: false,
             is_exporting: false,
+            export_progress: None,
+            catalog_load: None,
+            photo_load_generation: 0,
             is_loading_photo: false,
+            editing_embedded_preview: false,
+            editing_smart_preview: false,
             is_processing: false,
             gpu: None,
             modifiers: iced::keyboard::Modifiers::default(),
 
             date_filter: DateFilter::All,
             rating_filter: RatingFilter::All,
+            gear_filter: GearFilter::All,
             sort_order: SortOrder::default(),
             expanded_dates: HashSet::new(),
+            date_index: DateIndex::default(),
+            gear_counts: GearCounts::default(),
             panel_sections: HashSet::from([
                 PanelSection::Histogram,
                 PanelSection::Light,
                 PanelSection::Color,
+                PanelSection::QuickDevelop,
             ]),
+
+            theme: crate::theme::app_theme(&preferences),
+            preferences,
+            preferences_open: false,
+
+            batch_metadata: None,
+            gear_override: None,
+            preset_editor: None,
+            auto_stack: None,
+            path_remap: None,
+            animation_export_open: false,
+            comparison_export: None,
+            export_options: None,
+            export_review: None,
+            quarantine: Vec::new(),
+            calibration: Arc::new(Calibration::default()),
+            smart_previews: std::collections::HashMap::new(),
+            animations: std::collections::HashMap::new(),
+            stacks: std::collections::HashMap::new(),
+            stacked_under: std::collections::HashMap::new(),
+            expanded_stacks: HashSet::new(),
+            color_range: ColorRange::default(),
+            color_range_mask: false,
+            color_range_reading: None,
+            color_range_generation: 0,
+            color_range_set: Vec::new(),
+            batch_smart_previews: 0,
+            batch_export_folder: None,
+            export_target: None,
+            notifications,
+            quarantine_open: false,
+            renderer: RendererStatus::Detecting,
+            time_machine_dates: None,
+            time_machine: None,
+            gpu_notice_open: false,
+            gpu_diagnostics_open: false,
+            stack_open: false,
+            link_sources_open: false,
+            thumbnail_failures: HashSet::new(),
+            volumes: Vec::new(),
+            offline_photos: HashSet::new(),
+            held_thumbnails: HashSet::new(),
+            held_exports: Vec::new(),
+            held_previews: Vec::new(),
         };
 
         let default_catalog = dirs_catalog_path();
-        let catalog_task = Task::perform(async move { default_catalog }, Message::CatalogOpened);
+        // Run migrations off the UI thread, once; later opens skip them.
+        let catalog_task = if demo {
+            Task::perform(
+                async {
+                    demo::open().unwrap_or_else(|err| {
+                        error!(%err, "failed to build demo catalog");
+                        String::new()
+                    })
+                },
+                Message::CatalogOpened,
+            )
+        } else {
+            Task::perform(
+                async move {
+                    if let Err(err) = Catalog::open(&default_catalog) {
+                        tracing::warn!(%err, "catalog migration failed");
+                    }
+                    default_catalog
+                },
+                Message::CatalogOpened,
+            )
+        };
 
         let gpu_task = Task::perform(
-            async {
+            async move {
                 match GpuContext::new().await {
+                    Ok(ctx) if ctx.is_software() => {
+                        tracing::warn!(
+                            adapter = ctx.adapter.name,
+                            "only a software GPU adapter found, using CPU pipeline"
+                        );
+                        (RendererStatus::from_adapter(&ctx.adapter), None)
+                    }
                     Ok(ctx) => {
+                        let status = RendererStatus::from_adapter(&ctx.adapter);
                         let pipeline = GpuPipeline::new(&ctx);
-                        Some(GpuReady(Arc::new(std::sync::Mutex::new((ctx, pipeline)))))
+                        let gpu = Arc::new(std::sync::Mutex::new((ctx, pipeline)));
+                        (status, Some(GpuReady(gpu)))
                     }
                     Err(e) => {
                         tracing::warn!("GPU init failed, using CPU pipeline: {e}");
-                        None
+                        (RendererStatus::Unavailable(format!("{e:#}")), None)
                     }
                 }
             },
-            Message::GpuInitDone,
+            |(status, ready)| Message::GpuInitDone(status, ready),
         );
 
         (app, Task::batch([catalog_task, gpu_task]))
@@ -319,14 +841,24 @@ impl App {
                         .to_string()
                 })
                 .unwrap_or_default();
-            format!("Crema - {name}")
+            // Edit state is only known for the photo loaded in Develop.
+            let name = if self.loaded_photo == Some(id) {
+                edit_aware_name(&name, self.current_version_name(), self.is_edit_modified())
+            } else {
+                name
+            };
+            format!("{} - {name}", self.app_name())
         } else {
-            format!("Crema - {} photos", self.photos.len())
+            format!("{} - {} photos", self.app_name(), self.photos.len())
         }
     }
 
+    fn app_name(&self) -> &'static str {
+        if self.demo { "Crema (Demo)" } else { "Crema" }
+    }
+
     pub fn theme(&self) -> Theme {
-        Theme::Dark
+        self.theme.clone()
     }
 
     pub fn update(&mut self, message: Message) -> Task<Message> {
@@ -336,22 +868,262 @@ impl App {
             crate::icon::set_dock_icon();
         }
 
+        if self.time_machine.is_some() && !allowed_in_time_machine(&message) {
+            self.status_message =
+                "The library is read-only while browsing a past date; return to today to make changes"
+                    .into();
+            return Task::none();
+        }
+
         match message {
-            Message::GpuInitDone(ready) => {
+            Message::GpuInitDone(status, ready) => {
+                let mut warm_up = Task::none();
                 if let Some(GpuReady(handle)) = ready {
                     info!("GPU pipeline ready");
+                    // Warm up only once the GPU is in use, so it never holds
+                    // back the switch from CPU previews.
+                    if self.preferences.gpu_warmup {
+                        let gpu = handle.clone();
+                        warm_up =
+                            Task::perform(async move { warm_up_gpu(&gpu) }, |_| Message::Noop);
+                    }
                     self.gpu = Some(handle);
                 }
+                self.gpu_notice_open =
+                    status.is_fallback() && !self.preferences.cpu_rendering_noticed;
+                self.renderer = status;
+                warm_up
+            }
+            Message::DismissGpuNotice => {
+                self.gpu_notice_open = false;
+                self.preferences.cpu_rendering_noticed = true;
+                self.preferences_changed()
+            }
+            Message::OpenGpuDiagnostics => {
+                self.gpu_diagnostics_open = true;
+                if self.gpu_notice_open {
+                    self.gpu_notice_open = false;
+                    self.preferences.cpu_rendering_noticed = true;
+                    return self.preferences_changed();
+                }
+                Task::none()
+            }
+            Message::CloseGpuDiagnostics => {
+                self.gpu_diagnostics_open = false;
                 Task::none()
             }
             Message::CatalogOpened(path) => self.handle_catalog_opened(path),
             Message::Import => self.handle_import(),
             Message::ImportsSelected(paths) => self.handle_imports_selected(paths),
-            Message::ImportComplete(imported, errors) => {
-                self.handle_import_complete(imported, errors)
+            Message::ImportComplete(imported, errors, held_previews) => {
+                self.handle_import_complete(imported, errors, held_previews)
+            }
+            Message::PhotoPageLoaded(generation, page) => {
+                self.handle_photo_page_loaded(generation, page)
             }
-            Message::PhotosListed(photos) => self.handle_photos_listed(photos),
             Message::ThumbnailReady(id, bytes) => self.handle_thumbnail_ready(id, bytes),
+            Message::ThumbnailFailed(id, reason) => self.handle_thumbnail_failed(id, reason),
+            Message::CreateDefectMap => Task::perform(
+                async {
+                    rfd::AsyncFileDialog::new()
+                        .set_title("Choose a dark frame (lens cap on, shortest shutter)")
+                        .pick_file()
+                        .await
+                        .map(|h| h.path().to_path_buf())
+                },
+                |result| match result {
+                    Some(path) => Message::DarkFrameSelected(path),
+                    None => Message::Noop,
+                },
+            ),
+            Message::DarkFrameSelected(path) => {
+                self.status_message = "Looking for stuck pixels...".into();
+                Task::perform(
+                    async move { detect_defect_map(&path).map_err(|e| format!("{e:#}")) },
+                    Message::DefectMapDetected,
+                )
+            }
+            Message::DefectMapDetected(result) => self.handle_defect_map_detected(result),
+            Message::DeleteDefectMap(serial) => {
+                if let Some(catalog) = &self.catalog
+                    && let Err(err) = catalog.delete_defect_map(&serial)
+                {
+                    error!(%err, "failed to delete defect map");
+                }
+                self.reload_calibration();
+                Task::none()
+            }
+            Message::CreateFlatField => Task::perform(
+                async {
+                    rfd::AsyncFileDialog::new()
+                        .set_title("Choose a flat frame (evenly lit surface, mid-gray exposure)")
+                        .pick_file()
+                        .await
+                        .map(|h| h.path().to_path_buf())
+                },
+                |result| match result {
+                    Some(path) => Message::FlatFrameSelected(path),
+                    None => Message::Noop,
+                },
+            ),
+            Message::FlatFrameSelected(path) => {
+                self.status_message = "Measuring flat field...".into();
+                Task::perform(
+                    async move { detect_flat_field(&path).map_err(|e| format!("{e:#}")) },
+                    Message::FlatFieldDetected,
+                )
+            }
+            Message::FlatFieldDetected(result) => self.handle_flat_field_detected(result),
+            Message::DeleteFlatField(camera_model, lens) => {
+                if let Some(catalog) = &self.catalog
+                    && let Err(err) = catalog.delete_flat_field(&camera_model, &lens)
+                {
+                    error!(%err, "failed to delete flat field");
+                }
+                self.reload_calibration();
+                Task::none()
+            }
+            Message::CreateMasterDark => Task::perform(
+                async {
+                    rfd::AsyncFileDialog::new()
+                        .set_title("Choose dark frames (lens capped, same ISO and shutter)")
+                        .pick_files()
+                        .await
+                        .map(|files| {
+                            files
+                                .into_iter()
+                                .map(|h| h.path().to_path_buf())
+                                .collect::<Vec<_>>()
+                        })
+                },
+                |result| match result {
+                    Some(paths) if !paths.is_empty() => Message::DarkFramesSelected(paths),
+                    _ => Message::Noop,
+                },
+            ),
+            Message::DarkFramesSelected(paths) => {
+                self.status_message = format!("Averaging {} dark frames...", paths.len());
+                Task::perform(
+                    async move {
+                        build_master_dark(&paths)
+                            .map(Arc::new)
+                            .map_err(|e| format!("{e:#}"))
+                    },
+                    Message::MasterDarkBuilt,
+                )
+            }
+            Message::MasterDarkBuilt(result) => self.handle_master_dark_built(result),
+            Message::DeleteMasterDark(id) => {
+                if let Some(catalog) = &self.catalog
+                    && let Err(err) = catalog.delete_master_dark(id)
+                {
+                    error!(%err, "failed to delete master dark");
+                }
+                self.reload_calibration();
+                Task::none()
+            }
+            Message::SetDarkFrameSubtraction(enabled) => {
+                self.preferences.dark_frame_subtraction = enabled;
+                self.reload_calibration();
+                self.preferences_changed()
+            }
+            Message::OpenStack => {
+                self.stack_open = self.selected_photos.len() >= 2;
+                Task::none()
+            }
+            Message::CloseStack => {
+                self.stack_open = false;
+                Task::none()
+            }
+            Message::StackPhotos(method) => self.handle_stack_photos(method),
+            Message::OpenLinkSources => {
+                self.link_sources_open = !self.link_source_ids().is_empty();
+                Task::none()
+            }
+            Message::CloseLinkSources => {
+                self.link_sources_open = false;
+                Task::none()
+            }
+            Message::LinkSources(kind) => {
+                self.handle_link_sources(kind);
+                Task::none()
+            }
+            Message::StackPathSelected(method, path) => {
+                self.handle_stack_path_selected(method, path)
+            }
+            Message::StackComplete(result) => self.handle_stack_complete(result),
+            Message::AuditPrecision => self.handle_audit_precision(),
+            Message::PrecisionAudited(result) => {
+                self.handle_precision_audited(result);
+                Task::none()
+            }
+            Message::VerifyRenderConsistency => self.handle_verify_render_consistency(),
+            Message::RenderConsistencyVerified(result) => {
+                match result {
+                    Ok(check) => {
+                        self.status_message = format!(
+                            "CPU vs GPU: max {:.2} levels, mean {:.3}",
+                            check.max_levels, check.mean_levels
+                        );
+                        self.render_consistency = Some(check);
+                    }
+                    Err(err) => {
+                        error!(%err, "render consistency check failed");
+                        self.status_message = format!("Render consistency check failed: {err}");
+                    }
+                }
+                Task::none()
+            }
+            Message::CloseRenderConsistency => {
+                self.render_consistency = None;
+                Task::none()
+            }
+            Message::OpenQuarantine => {
+                self.reload_quarantine();
+                self.quarantine_open = true;
+                Task::none()
+            }
+            Message::CloseQuarantine => {
+                self.quarantine_open = false;
+                Task::none()
+            }
+            Message::RetryQuarantined(id) => self.handle_retry_quarantined(id),
+            Message::QuarantineRetried(id, result) => self.handle_quarantine_retried(id, result),
+            Message::DismissQuarantined(id) => {
+                if let Some(catalog) = &self.catalog
+                    && let Err(err) = catalog.dismiss_quarantine(id)
+                {
+                    error!(%err, id, "failed to dismiss quarantined file");
+                }
+                self.reload_quarantine();
+                Task::none()
+            }
+            Message::OpenTimeMachine => {
+                self.save_current_edits();
+                self.time_machine_dates = Some(match &self.catalog {
+                    Some(catalog) => catalog.history_dates().unwrap_or_else(|err| {
+                        error!(%err, "failed to list history dates");
+                        Vec::new()
+                    }),
+                    None => Vec::new(),
+                });
+                Task::none()
+            }
+            Message::CloseTimeMachinePicker => {
+                self.time_machine_dates = None;
+                Task::none()
+            }
+            Message::BrowseAsOf(date) => self.handle_browse_as_of(date),
+            Message::RestoreFromTimeMachine => self.handle_restore_from_time_machine(),
+            Message::LeaveTimeMachine => {
+                self.time_machine = None;
+                self.status_message = "Back to today's library".into();
+                self.refresh_sidebar_counts()
+            }
+            Message::SidebarCounted(generation, counts) => {
+                self.handle_sidebar_counted(generation, counts);
+                Task::none()
+            }
             Message::SelectPhoto(id) => self.handle_select_photo(id),
             Message::OpenPhoto(id) => self.open_photo(id),
             Message::SetWorkspace(workspace) => self.handle_set_workspace(workspace),
@@ -362,22 +1134,183 @@ impl App {
             Message::ImageLoaded(id, buf, preview, exif) => {
                 self.handle_image_loaded(id, buf, preview, exif)
             }
+            Message::EmbeddedPreviewLoaded(id, preview) => {
+                self.handle_embedded_preview_loaded(id, preview)
+            }
             Message::ImageProcessed(generation, handle, hist) => {
                 self.handle_image_processed(generation, handle, hist)
             }
             Message::ImageLoadFailed(id) => self.handle_image_load_failed(id),
-            Message::Export => self.handle_export(),
+            Message::Export => {
+                if self.can_export() {
+                    self.export_options = Some(ExportOptions::default());
+                }
+                Task::none()
+            }
+            Message::CloseExportOptions => {
+                self.export_options = None;
+                Task::none()
+            }
+            Message::ChooseExportDestination => match self.export_options.take() {
+                Some(options) if options.batch => self.handle_batch_export(options),
+                Some(options) => self.handle_export(options),
+                None => Task::none(),
+            },
+            Message::OpenAnimationExport => {
+                self.animation_export_open = self.can_export();
+                Task::none()
+            }
+            Message::CloseAnimationExport => {
+                self.animation_export_open = false;
+                Task::none()
+            }
+            Message::ExportAnimation(kind) => self.handle_export_animation(kind),
+            Message::AnimationPathSelected(kind, path) => {
+                self.handle_animation_path_selected(kind, path)
+            }
+            Message::OpenComparisonExport => {
+                if self.can_export() {
+                    self.comparison_export = Some(ComparisonExport::default());
+                }
+                Task::none()
+            }
+            Message::ComparisonLayoutChanged(layout) => {
+                if let Some(options) = &mut self.comparison_export {
+                    options.layout = layout;
+                }
+                Task::none()
+            }
+            Message::ComparisonLabelsToggled(labels) => {
+                if let Some(options) = &mut self.comparison_export {
+                    options.labels = labels;
+                }
+                Task::none()
+            }
+            Message::ExportComparison => self.handle_export_comparison(),
+            Message::ComparisonPathSelected(path) => self.handle_comparison_path_selected(path),
+            Message::CloseComparisonExport => {
+                self.comparison_export = None;
+                Task::none()
+            }
+            Message::SetKeyFrame(frame) => self.handle_set_key_frame(frame),
+            Message::ColorRangeChanged(which, value) => {
+                which.apply(&mut self.color_range, value);
+                self.measure_color_range()
+            }
+            Message::ColorRangeMaskToggled(shown) => {
+                self.color_range_mask = shown;
+                Task::none()
+            }
+            Message::ColorRangeMeasured(generation, coverage, mask) => {
+                if generation == self.color_range_generation {
+                    self.color_range_reading = Some((coverage, mask));
+                }
+                Task::none()
+            }
+            Message::MeasureColorRangeSelection => self.handle_measure_color_range_selection(),
+            Message::ColorRangeSelectionMeasured(results) => {
+                self.status_message =
+                    format!("Measured the color range in {} photos", results.len());
+                self.color_range_set = results;
+                Task::none()
+            }
             Message::SaveSidecar => self.handle_save_sidecar(),
             Message::LoadSidecar => self.handle_load_sidecar(),
-            Message::ExportPathSelected(path) => self.handle_export_path_selected(path),
+            Message::ExportPathSelected(path, options) => {
+                self.handle_export_path_selected(path, options)
+            }
+            Message::ToggleExportWarningSilenced(warning) => {
+                if let Some(review) = &mut self.export_review {
+                    review.toggle_silenced(warning);
+                }
+                Task::none()
+            }
+            Message::ExportAnyway => {
+                let Some(review) = self.take_export_review() else {
+                    return Task::none();
+                };
+                let saved = self.preferences_changed();
+                let export = if review.options.batch {
+                    self.start_batch_export(review.path, review.options)
+                } else {
+                    self.start_full_res_export(review.path, review.options)
+                };
+                Task::batch([saved, export])
+            }
+            Message::ExportAs16BitTiff => {
+                let Some(review) = self.take_export_review() else {
+                    return Task::none();
+                };
+                let saved = self.preferences_changed();
+                let options = ExportOptions {
+                    tiff_16bit: true,
+                    ..review.options
+                };
+                // Checked again, in case another warning still applies.
+                let path = review.path.with_extension("tif");
+                Task::batch([saved, self.handle_export_path_selected(path, options)])
+            }
+            Message::CancelExportReview => {
+                self.export_review = None;
+                Task::none()
+            }
+            Message::ExportProgress(fraction) => {
+                if let Some(progress) = &mut self.export_progress {
+                    progress.fraction = fraction;
+                }
+                Task::none()
+            }
+            Message::CancelExport => {
+                if let Some(progress) = &self.export_progress {
+                    progress.cancel.store(true, Ordering::Relaxed);
+                }
+                Task::none()
+            }
             Message::ExportComplete(msg) => self.handle_export_complete(msg),
-            Message::BatchExport => self.handle_batch_export(),
-            Message::BatchExportFolderSelected(folder) => {
-                self.handle_batch_export_folder_selected(folder)
+            Message::ToggleNotificationDrawer => {
+                self.notifications.toggle_drawer();
+                Task::none()
+            }
+            Message::DismissNotification(id) => {
+                self.notifications.dismiss(id);
+                Task::none()
+            }
+            Message::RunNotificationAction(action) => self.handle_notification_action(action),
+            Message::ExpireToasts => {
+                self.notifications.expire(std::time::Instant::now());
+                Task::none()
+            }
+            Message::SetNotificationMuted(category, muted) => {
+                if muted {
+                    self.preferences.muted_notifications.insert(category);
+                } else {
+                    self.preferences.muted_notifications.remove(&category);
+                }
+                self.preferences_changed()
+            }
+            Message::ClearNotifications => {
+                self.notifications.clear();
+                Task::none()
+            }
+            Message::BatchExport => {
+                if !self.selected_photos.is_empty() {
+                    self.export_options = Some(ExportOptions {
+                        batch: true,
+                        ..ExportOptions::default()
+                    });
+                }
+                Task::none()
             }
-            Message::BatchExportComplete(success, skipped, total) => {
-                self.handle_batch_export_complete(success, skipped, total)
+            Message::BatchExportFolderSelected(folder, options) => {
+                self.handle_batch_export_folder_selected(folder, options)
+            }
+            Message::BatchExportProgress(done, total) => {
+                self.status_message = format!("Exporting {done}/{total}...");
+                Task::none()
             }
+            Message::RenderFarmFinished(result) => self.handle_render_farm_finished(result),
+            Message::BatchExportComplete(success, skipped, total, plugin_failures, fitted) => self
+                .handle_batch_export_complete(success, skipped, total, plugin_failures, 0, fitted),
             Message::ExposureChanged(v) => {
                 self.snapshot_for_undo();
                 self.edit_params.exposure = v;
@@ -550,6 +1483,23 @@ impl App {
                 self.zoom_state.pan.y += dy;
                 Task::none()
             }
+            Message::PinchAt(factor, shift, center, viewport) => {
+                if !self.zoom_state.is_fit() {
+                    self.zoom_state.pan += shift;
+                }
+                self.handle_zoom_at_point(
+                    factor,
+                    center.x,
+                    center.y,
+                    viewport.width,
+                    viewport.height,
+                );
+                Task::none()
+            }
+            Message::ToggleActualSize(cx, cy, vw, vh) => {
+                self.handle_toggle_actual_size(cx, cy, vw, vh);
+                Task::none()
+            }
             Message::ResetZoom => {
                 self.zoom_state = ZoomState::default();
                 Task::none()
@@ -620,12 +1570,19 @@ impl App {
                 self.rating_filter = filter;
                 Task::none()
             }
+            Message::SetGearFilter(filter) => {
+                self.gear_filter = filter;
+                Task::none()
+            }
             Message::SetSortOrder(order) => {
                 self.sort_order = order;
                 Task::none()
             }
             Message::ToggleDateExpansion(key) => {
                 if !self.expanded_dates.remove(&key) {
+                    if let DateExpansionKey::Year(year) = key {
+                        self.load_date_year(year);
+                    }
                     self.expanded_dates.insert(key);
                 }
                 Task::none()
@@ -634,530 +1591,3040 @@ impl App {
                 if !self.panel_sections.remove(&section) {
                     self.panel_sections.insert(section);
                 }
+                if section == PanelSection::ColorRange {
+                    return self.measure_color_range();
+                }
                 Task::none()
             }
             Message::ModifiersChanged(mods) => {
                 self.modifiers = mods;
                 Task::none()
             }
-            Message::Noop => Task::none(),
-        }
-    }
-
-    fn handle_catalog_opened(&mut self, path: String) -> Task<Message> {
-        match Catalog::open(&path) {
-            Ok(catalog) => {
-                info!(%path, "catalog opened");
-                self.catalog = Some(catalog);
-                self.catalog_path = Some(path);
-                self.refresh_photos()
+            Message::CheckVolumes => self.handle_check_volumes(),
+            Message::SmartPreviewsHeld(held) => {
+                self.held_previews.extend(held);
+                Task::none()
             }
-            Err(err) => {
-                error!(%err, "failed to open catalog");
-                self.status_message = format!("Error opening catalog: {err}");
+            Message::TakeSnapshot => {
+                self.handle_take_snapshot();
                 Task::none()
             }
-        }
-    }
-
-    fn handle_import(&self) -> Task<Message> {
-        Task::perform(
-            async {
-                let dialog = rfd::AsyncFileDialog::new()
-                    .set_title("Import photos")
-                    .add_filter(
-                        "Images",
-                        &[
-                            "cr2", "cr3", "crw", "nef", "nrw", "arw", "srf", "sr2", "raf", "rw2",
-                            "orf", "pef", "dng", "3fr", "ari", "bay", "cap", "dcr", "erf", "fff",
-                            "iiq", "k25", "kdc", "mef", "mos", "mrw", "raw", "rwl", "srw", "x3f",
-                            "jpg", "jpeg", "png", "tiff", "tif",
-                        ],
-                    );
-                let handles = dialog.pick_files().await.unwrap_or_default();
-                handles.iter().map(|h| h.path().to_path_buf()).collect()
-            },
-            Message::ImportsSelected,
-        )
-    }
-
-    fn handle_imports_selected(&mut self, paths: Vec<PathBuf>) -> Task<Message> {
-        if paths.is_empty() {
-            return Task::none();
-        }
-
-        self.is_importing = true;
-        self.status_message = format!("Importing {} file(s)...", paths.len());
-        let catalog_path = self.catalog_path.clone().unwrap_or_default();
-        Task::perform(
-            async move {
-                let catalog = Catalog::open(&catalog_path).ok();
-                if let Some(catalog) = catalog {
-                    match crema_catalog::import::import_paths(&catalog, &paths) {
-                        Ok(result) => (result.imported.len(), result.errors.len()),
-                        Err(_) => (0, 1),
-                    }
-                } else {
-                    (0, 1)
+            Message::DeleteSnapshot(id) => {
+                self.handle_delete_snapshot(id);
+                Task::none()
+            }
+            Message::ApplySnapshot(id) => self.handle_apply_snapshot(id),
+            Message::CompareSnapshot(id) => {
+                self.edit_diff = Some(id);
+                Task::none()
+            }
+            Message::TakeDiffParam(key, side) => self.handle_take_diff_param(key, side),
+            Message::SetAnnotationTool(tool) => {
+                self.annotation_tool = tool;
+                if tool.is_some() {
+                    self.annotations_visible = true;
                 }
-            },
-            |(imported, errors)| Message::ImportComplete(imported, errors),
-        )
-    }
-
-    fn handle_import_complete(&mut self, imported: usize, errors: usize) -> Task<Message> {
-        self.is_importing = false;
-        self.status_message = format!("Imported {imported} photos ({errors} errors)");
-        self.refresh_photos()
-    }
-
-    fn handle_photos_listed(&mut self, photos: Vec<Photo>) -> Task<Message> {
-        self.photos = photos;
-        self.status_message = format!("{} photos in catalog", self.photos.len());
-
-        if self
-            .selected_photo
-            .is_some_and(|id| !self.photos.iter().any(|photo| photo.id == id))
-        {
-            self.selected_photo = None;
-        }
-
-        if self
-            .loaded_photo
-            .is_some_and(|id| !self.photos.iter().any(|photo| photo.id == id))
-        {
-            self.loaded_photo = None;
-            self.current_image = None;
-            self.preview_image = None;
-            self.processed_image = None;
-            self.histogram = None;
-            self.current_exif.clear();
-        }
-
-        self.update_export_enabled();
+                Task::none()
+            }
+            Message::SetAnnotationsVisible(visible) => {
+                self.annotations_visible = visible;
+                if !visible {
+                    self.annotation_tool = None;
+                    self.note_draft = None;
+                }
+                Task::none()
+            }
+            Message::AddAnnotation(annotation) => {
+                self.annotations.push(annotation);
+                self.save_annotations();
+                Task::none()
+            }
+            Message::PlaceNote(at) => {
+                let text = self.note_draft.take().map(|(_, text)| text);
+                self.note_draft = Some((at, text.unwrap_or_default()));
+                Task::none()
+            }
+            Message::NoteDraftChanged(text) => {
+                if let Some((_, draft)) = &mut self.note_draft {
+                    *draft = text;
+                }
+                Task::none()
+            }
+            Message::ConfirmNote => {
+                if let Some((at, text)) = self.note_draft.take()
+                    && !text.trim().is_empty()
+                {
+                    self.annotations.push(Annotation::Note {
+                        at,
+                        text: text.trim().to_string(),
+                    });
+                    self.save_annotations();
+                }
+                Task::none()
+            }
+            Message::CancelNote => {
+                self.note_draft = None;
+                Task::none()
+            }
+            Message::RemoveLastAnnotation => {
+                if self.annotations.pop().is_some() {
+                    self.save_annotations();
+                }
+                Task::none()
+            }
+            Message::ClearAnnotations => {
+                self.annotations.clear();
+                self.note_draft = None;
+                self.save_annotations();
+                Task::none()
+            }
+            Message::SetExportAnnotations(enabled) => {
+                if let Some(options) = &mut self.export_options {
+                    options.annotations = enabled;
+                }
+                Task::none()
+            }
+            Message::CloseEditDiff => {
+                self.edit_diff = None;
+                Task::none()
+            }
+            Message::QuickDevelop(adjustment) => self.handle_quick_develop(adjustment),
+            Message::OpenPathRemap => {
+                self.path_remap = Some(PathRemapForm::default());
+                Task::none()
+            }
+            Message::PathRemapFromChanged(from) => {
+                if let Some(form) = &mut self.path_remap {
+                    form.from = from;
+                }
+                self.refresh_path_remap_preview();
+                Task::none()
+            }
+            Message::PathRemapToChanged(to) => {
+                if let Some(form) = &mut self.path_remap {
+                    form.to = to;
+                }
+                self.refresh_path_remap_preview();
+                Task::none()
+            }
+            Message::ApplyPathRemap => self.handle_apply_path_remap(),
+            Message::ClosePathRemap => {
+                self.path_remap = None;
+                Task::none()
+            }
+            Message::PathRemapVerified(missing, total) => {
+                let notification = if missing == 0 {
+                    Notification::new(
+                        Category::Jobs,
+                        format!("Remapped {total} photos; all were found at their new location."),
+                    )
+                } else {
+                    Notification::new(
+                        Category::Jobs,
+                        format!("Remapped {total} photos; {missing} are still missing."),
+                    )
+                    .failed(true)
+                };
+                self.notify(notification);
+                Task::none()
+            }
+            Message::MatchExposure => self.handle_match_exposure(),
+            Message::ExposureMeasured(reference, medians) => {
+                self.handle_exposure_measured(reference, medians)
+            }
+            Message::AutoCropScan => {
+                let (Some(id), Some(full_res)) = (self.loaded_photo, self.current_image.clone())
+                else {
+                    return Task::none();
+                };
+                Task::perform(
+                    async move { crema_core::scan_border::detect(&full_res) },
+                    move |crop| Message::ScanCropDetected(id, crop),
+                )
+            }
+            Message::ScanCropDetected(id, _) if self.loaded_photo != Some(id) => Task::none(),
+            Message::ScanCropDetected(_, Some(crop)) => {
+                self.snapshot_for_undo();
+                crop.apply(&mut self.edit_params);
+                self.status_message = describe_scan_crop(&crop);
+                if self.crop_mode {
+                    Task::none()
+                } else {
+                    self.reprocess_image()
+                }
+            }
+            Message::ScanCropDetected(_, None) => {
+                self.status_message = "No scanner border found around this photo.".into();
+                Task::none()
+            }
+            Message::AutoCropScans => self.handle_auto_crop_scans(),
+            Message::ScansAnalyzed(results) => self.handle_scans_analyzed(results),
+            Message::OpenBatchMetadata => self.handle_open_batch_metadata(),
+            Message::BatchMetadataChanged(field, value) => {
+                if let Some(form) = &mut self.batch_metadata {
+                    form.set(field, value);
+                }
+                Task::none()
+            }
+            Message::BatchMetadataShiftChanged(value) => {
+                if let Some(form) = &mut self.batch_metadata {
+                    form.date_shift = value;
+                }
+                Task::none()
+            }
+            Message::ApplyBatchMetadata => self.handle_apply_batch_metadata(),
+            Message::OpenGearOverride => self.handle_open_gear_override(),
+            Message::GearOverrideChanged(field, value) => {
+                if let Some(form) = &mut self.gear_override {
+                    form.set(field, value);
+                }
+                Task::none()
+            }
+            Message::SetGearOverrideShoot(whole_shoot) => {
+                if let Some(form) = &mut self.gear_override {
+                    form.whole_shoot = whole_shoot;
+                }
+                Task::none()
+            }
+            Message::ApplyGearOverride => self.handle_apply_gear_override(false),
+            Message::ClearGearOverride => self.handle_apply_gear_override(true),
+            Message::CloseGearOverride => {
+                self.gear_override = None;
+                Task::none()
+            }
+            Message::OpenAutoStack => self.handle_open_auto_stack(),
+            Message::AutoStackScanned(biases) => {
+                if let Some(review) = &mut self.auto_stack {
+                    review.set_biases(biases, &self.preferences.auto_stack);
+                }
+                Task::none()
+            }
+            Message::SetAutoStackRule(rule, on) => {
+                auto_stack::set_rule(&mut self.preferences.auto_stack, rule, on);
+                self.auto_stack_rules_changed()
+            }
+            Message::StepAutoStackThreshold(threshold, delta) => {
+                auto_stack::step_threshold(&mut self.preferences.auto_stack, threshold, delta);
+                self.auto_stack_rules_changed()
+            }
+            Message::ToggleProposedStack(index) => {
+                if let Some(review) = &mut self.auto_stack {
+                    review.toggle(index);
+                }
+                Task::none()
+            }
+            Message::CommitAutoStack => self.handle_commit_auto_stack(),
+            Message::CloseAutoStack => {
+                self.auto_stack = None;
+                Task::none()
+            }
+            Message::ToggleStackExpanded(cover) => {
+                if !self.expanded_stacks.remove(&cover) {
+                    self.expanded_stacks.insert(cover);
+                }
+                Task::none()
+            }
+            Message::Unstack(cover) => self.handle_unstack(cover),
+            Message::NewPreset => {
+                self.preset_editor = Some(PresetEditor::new(&self.edit_params));
+                Task::none()
+            }
+            Message::EditPreset(index) => {
+                if let Some(preset) = self.preferences.presets.get(index) {
+                    self.preset_editor = Some(PresetEditor::edit(index, preset));
+                }
+                Task::none()
+            }
+            Message::PresetNameChanged(name) => {
+                if let Some(editor) = &mut self.preset_editor {
+                    editor.name = name;
+                }
+                Task::none()
+            }
+            Message::TogglePresetParam(key) => {
+                if let Some(editor) = &mut self.preset_editor {
+                    editor.toggle(key);
+                }
+                Task::none()
+            }
+            Message::TogglePresetGroup(index) => {
+                if let (Some(editor), Some(group)) = (
+                    &mut self.preset_editor,
+                    crema_core::preset::GROUPS.get(index),
+                ) {
+                    editor.toggle_group(group);
+                }
+                Task::none()
+            }
+            Message::SavePreset => self.handle_save_preset(),
+            Message::ClosePresetEditor => {
+                self.preset_editor = None;
+                Task::none()
+            }
+            Message::ApplyPreset(index) => self.handle_apply_preset(index),
+            Message::DeletePreset(index) => {
+                if index < self.preferences.presets.len() {
+                    let preset = self.preferences.presets.remove(index);
+                    self.status_message = format!("Deleted preset {}.", preset.name);
+                    return self.preferences_changed();
+                }
+                Task::none()
+            }
+            Message::CloseBatchMetadata => {
+                self.batch_metadata = None;
+                Task::none()
+            }
+            Message::TogglePreferences => {
+                self.preferences_open = !self.preferences_open;
+                Task::none()
+            }
+            Message::SetAccentColor(accent) => {
+                self.preferences.accent = accent;
+                self.preferences_changed()
+            }
+            Message::SetColorVision(vision) => {
+                self.preferences.color_vision = vision;
+                self.preferences_changed()
+            }
+            Message::SetHighContrast(enabled) => {
+                self.preferences.high_contrast = enabled;
+                self.preferences_changed()
+            }
+            Message::SetReduceMotion(enabled) => {
+                self.preferences.reduce_motion = enabled;
+                self.notifications.hold_toasts = enabled;
+                self.preferences_changed()
+            }
+            Message::SetGridLayout(layout) => {
+                self.preferences.grid_layout = layout;
+                self.preferences_changed()
+            }
+            Message::ToggleExportCrop(crop) => {
+                let crops = &mut self.preferences.export_crops;
+                if let Some(i) = crops.iter().position(|&c| c == crop) {
+                    crops.remove(i);
+                } else {
+                    crops.push(crop);
+                }
+                self.preferences_changed()
+            }
+            Message::SetExportWorkers(workers) => {
+                self.preferences.export_workers = workers;
+                self.preferences_changed()
+            }
+            Message::SetExportDither(dither) => {
+                self.preferences.export_dither = dither;
+                self.preferences_changed()
+            }
+            Message::SetExportColorSpace(color_space) => {
+                self.preferences.export_color_space = color_space;
+                self.preferences_changed()
+            }
+            Message::SetExportTiff16Bit(enabled) => {
+                self.preferences.export_tiff_16bit = enabled;
+                self.preferences_changed()
+            }
+            Message::SetExportTargetSize(size) => {
+                if let Some(options) = &mut self.export_options {
+                    options.target_size = size;
+                }
+                Task::none()
+            }
+            Message::ResetExportWarnings => {
+                self.preferences.suppressed_export_warnings.clear();
+                self.preferences_changed()
+            }
+            Message::SetExportMetadataEnabled(enabled) => {
+                self.preferences.export_metadata.enabled = enabled;
+                self.preferences_changed()
+            }
+            Message::ShowMetadataTemplate(crop) => {
+                self.metadata_template_crop = crop;
+                Task::none()
+            }
+            Message::SetCropMetadataOverride(crop, own) => {
+                self.preferences
+                    .export_metadata
+                    .set_crop_override(crop, own);
+                self.preferences_changed()
+            }
+            Message::SetMetadataTemplateField(field, value) => {
+                let crop = self.metadata_template_crop;
+                match self.preferences.export_metadata.template_mut(crop) {
+                    Some(template) => {
+                        template.set_field(field, value);
+                        self.preferences_changed()
+                    }
+                    None => Task::none(),
+                }
+            }
+            Message::SetMetadataTemplateRating(rating) => {
+                let crop = self.metadata_template_crop;
+                match self.preferences.export_metadata.template_mut(crop) {
+                    Some(template) => {
+                        template.rating = rating;
+                        self.preferences_changed()
+                    }
+                    None => Task::none(),
+                }
+            }
+            Message::SetSmartPreviews(enabled) => {
+                self.preferences.smart_previews = enabled;
+                self.preferences_changed()
+            }
+            Message::SetGpuWarmup(enabled) => {
+                self.preferences.gpu_warmup = enabled;
+                let saved = self.preferences_changed();
+                // Turned on mid-session: warm up now rather than next launch.
+                match self.gpu.clone() {
+                    Some(gpu) if enabled => Task::batch([
+                        saved,
+                        Task::perform(async move { warm_up_gpu(&gpu) }, |_| Message::Noop),
+                    ]),
+                    _ => saved,
+                }
+            }
+            Message::SetThumbnailFidelity(fidelity) => {
+                let reload = self
+                    .change_thumbnail_fidelity(|app| app.preferences.thumbnail_fidelity = fidelity);
+                Task::batch([self.preferences_changed(), reload])
+            }
+            Message::AddThumbnailFolder => Task::perform(
+                async {
+                    rfd::AsyncFileDialog::new()
+                        .set_title("Choose a folder")
+                        .pick_folder()
+                        .await
+                        .map(|h| h.path().to_path_buf())
+                },
+                |result| match result {
+                    Some(folder) => Message::ThumbnailFolderSelected(folder),
+                    None => Message::Noop,
+                },
+            ),
+            Message::ThumbnailFolderSelected(folder) => {
+                // Matching the preference would change nothing, so a new
+                // folder starts on the other setting.
+                let fidelity = match self.preferences.thumbnail_fidelity {
+                    ThumbnailFidelity::Fast => ThumbnailFidelity::Accurate,
+                    ThumbnailFidelity::Accurate => ThumbnailFidelity::Fast,
+                };
+                self.set_folder_thumbnail_fidelity(
+                    folder.to_string_lossy().to_string(),
+                    Some(fidelity),
+                )
+            }
+            Message::SetFolderThumbnailFidelity(folder, fidelity) => {
+                self.set_folder_thumbnail_fidelity(folder, fidelity)
+            }
+            Message::AddExportPlugin => Task::perform(
+                async {
+                    rfd::AsyncFileDialog::new()
+                        .set_title("Choose an export post-processor")
+                        .pick_file()
+                        .await
+                        .map(|h| h.path().to_path_buf())
+                },
+                |result| match result {
+                    Some(path) => Message::ExportPluginSelected(path),
+                    None => Message::Noop,
+                },
+            ),
+            Message::ExportPluginSelected(path) => {
+                self.preferences
+                    .export_plugins
+                    .push(export_plugin::ExportPlugin::new(path));
+                self.preferences_changed()
+            }
+            Message::SetExportPluginEnabled(index, enabled) => {
+                if let Some(plugin) = self.preferences.export_plugins.get_mut(index) {
+                    plugin.enabled = enabled;
+                }
+                self.preferences_changed()
+            }
+            Message::RemoveExportPlugin(index) => {
+                if index < self.preferences.export_plugins.len() {
+                    self.preferences.export_plugins.remove(index);
+                }
+                self.preferences_changed()
+            }
+            Message::Noop => Task::none(),
+        }
+    }
+
+    fn handle_catalog_opened(&mut self, path: String) -> Task<Message> {
+        match Catalog::open_existing(&path) {
+            Ok(catalog) => {
+                info!(%path, "catalog opened");
+                self.catalog = Some(catalog);
+                self.catalog_path = Some(path);
+                self.reload_quarantine();
+                self.reload_calibration();
+                self.reload_folder_settings();
+                self.refresh_photos()
+            }
+            Err(err) => {
+                error!(%err, "failed to open catalog");
+                self.status_message = format!("Error opening catalog: {err}");
+                Task::none()
+            }
+        }
+    }
+
+    fn handle_import(&self) -> Task<Message> {
+        Task::perform(
+            async {
+                let dialog = rfd::AsyncFileDialog::new()
+                    .set_title("Import photos")
+                    .add_filter(
+                        "Images",
+                        &[
+                            "cr2", "cr3", "crw", "nef", "nrw", "arw", "srf", "sr2", "raf", "rw2",
+                            "orf", "pef", "dng", "3fr", "ari", "bay", "cap", "dcr", "erf", "fff",
+                            "iiq", "k25", "kdc", "mef", "mos", "mrw", "raw", "rwl", "srw", "x3f",
+                            "jpg", "jpeg", "png", "tiff", "tif", "gif",
+                        ],
+                    );
+                let handles = dialog.pick_files().await.unwrap_or_default();
+                handles.iter().map(|h| h.path().to_path_buf()).collect()
+            },
+            Message::ImportsSelected,
+        )
+    }
+
+    fn handle_imports_selected(&mut self, paths: Vec<PathBuf>) -> Task<Message> {
+        if paths.is_empty() {
+            return Task::none();
+        }
+
+        self.is_importing = true;
+        self.status_message = format!("Importing {} file(s)...", paths.len());
+        let catalog_path = self.catalog_path.clone().unwrap_or_default();
+        let build_previews = self.preferences.smart_previews;
+        let calibration = self.calibration.clone();
+        Task::perform(
+            async move {
+                let catalog = Catalog::open_existing(&catalog_path).ok();
+                if let Some(catalog) = catalog {
+                    match crema_catalog::import::import_paths(&catalog, &paths) {
+                        Ok(result) => {
+                            let held = if build_previews {
+                                let dir = smart_preview::previews_dir(&catalog_path);
+                                smart_preview::build_all(
+                                    &catalog,
+                                    &result.imported,
+                                    &calibration,
+                                    &dir,
+                                )
+                            } else {
+                                Vec::new()
+                            };
+                            (result.imported.len(), result.errors.len(), held)
+                        }
+                        Err(_) => (0, 1, Vec::new()),
+                    }
+                } else {
+                    (0, 1, Vec::new())
+                }
+            },
+            |(imported, errors, held)| Message::ImportComplete(imported, errors, held),
+        )
+    }
+
+    fn handle_import_complete(
+        &mut self,
+        imported: usize,
+        errors: usize,
+        held_previews: Vec<(PhotoId, PathBuf)>,
+    ) -> Task<Message> {
+        self.is_importing = false;
+        self.held_previews.extend(held_previews);
+        self.reload_quarantine();
+        let noun = if imported == 1 { "photo" } else { "photos" };
+        let notification = if errors > 0 {
+            Notification::new(
+                Category::Imports,
+                format!("Imported {imported} {noun}; {errors} failed"),
+            )
+            .failed(true)
+            .with_action(Action::ReviewQuarantine(errors))
+        } else {
+            Notification::new(Category::Imports, format!("Imported {imported} {noun}"))
+        };
+        self.notify(notification);
+        self.refresh_photos()
+    }
+
+    fn reload_quarantine(&mut self) {
+        self.quarantine = match &self.catalog {
+            Some(catalog) => catalog.list_quarantine().unwrap_or_else(|err| {
+                error!(%err, "failed to load quarantine");
+                Vec::new()
+            }),
+            None => Vec::new(),
+        };
+        if self.quarantine.is_empty() {
+            self.quarantine_open = false;
+        }
+    }
+
+    fn reload_folder_settings(&mut self) {
+        self.folder_thumbnail_fidelity = match &self.catalog {
+            Some(catalog) => catalog.folder_thumbnail_fidelity().unwrap_or_else(|err| {
+                error!(%err, "failed to load folder settings");
+                HashMap::new()
+            }),
+            None => HashMap::new(),
+        };
+    }
+
+    /// What `photo`'s thumbnail shows: its folder's override, or else the
+    /// preference.
+    fn thumbnail_fidelity(&self, photo: &Photo) -> ThumbnailFidelity {
+        folder_override(&self.folder_thumbnail_fidelity, &photo.file_path)
+            .unwrap_or(self.preferences.thumbnail_fidelity)
+    }
+
+    /// The edits `photo`'s thumbnail is rendered with, or `None` to show
+    /// the original. Unedited photos show the original either way.
+    fn thumbnail_edits(&self, photo: &Photo) -> Option<EditParams> {
+        if self.thumbnail_fidelity(photo) == ThumbnailFidelity::Fast {
+            return None;
+        }
+        self.catalog
+            .as_ref()?
+            .get_edits(photo.id)
+            .ok()
+            .flatten()
+            .map(|e| e.to_edit_params())
+            .filter(|params| *params != EditParams::default())
+    }
+
+    /// Apply `change` to the thumbnail settings, then load thumbnails
+    /// again for every photo whose fidelity it changed.
+    fn change_thumbnail_fidelity(&mut self, change: impl FnOnce(&mut Self)) -> Task<Message> {
+        let before: Vec<ThumbnailFidelity> = self
+            .photos
+            .iter()
+            .map(|p| self.thumbnail_fidelity(p))
+            .collect();
+        change(self);
+        let changed: Vec<PhotoId> = self
+            .photos
+            .iter()
+            .zip(before)
+            .filter(|(p, before)| self.thumbnail_fidelity(p) != *before)
+            .map(|(p, _)| p.id)
+            .collect();
+        for id in changed {
+            self.thumbnails.remove(&id);
+        }
         self.load_next_thumbnail_batch()
     }
 
-    fn handle_thumbnail_ready(&mut self, id: PhotoId, bytes: Vec<u8>) -> Task<Message> {
-        let handle = iced::widget::image::Handle::from_bytes(bytes);
-        self.thumbnails.insert(id, handle);
+    fn set_folder_thumbnail_fidelity(
+        &mut self,
+        folder: String,
+        fidelity: Option<ThumbnailFidelity>,
+    ) -> Task<Message> {
+        let Some(catalog) = &self.catalog else {
+            return Task::none();
+        };
+        if let Err(err) = catalog.set_folder_thumbnail_fidelity(&folder, fidelity) {
+            error!(%err, %folder, "failed to save folder thumbnail setting");
+            self.status_message = format!("Could not save the folder's thumbnail setting: {err}");
+            return Task::none();
+        }
+        self.change_thumbnail_fidelity(Self::reload_folder_settings)
+    }
+
+    /// Load `id`'s thumbnail again after its edits were saved, if it shows
+    /// them.
+    fn refresh_edited_thumbnail(&mut self, id: Option<PhotoId>) -> Task<Message> {
+        let Some(photo) = id.and_then(|id| self.photos.iter().find(|p| p.id == id)) else {
+            return Task::none();
+        };
+        if self.thumbnail_fidelity(photo) == ThumbnailFidelity::Fast {
+            return Task::none();
+        }
+        let id = photo.id;
+        self.thumbnails.remove(&id);
         self.load_next_thumbnail_batch()
     }
 
-    fn handle_select_photo(&mut self, id: PhotoId) -> Task<Message> {
-        if self.modifiers.command() {
-            return self.handle_toggle_photo_selection(id);
-        }
-        if self.modifiers.shift() {
-            return self.handle_select_photo_range(id);
+    fn reload_smart_previews(&mut self) {
+        let previews = match &self.catalog {
+            Some(catalog) => catalog.list_smart_previews().unwrap_or_else(|err| {
+                error!(%err, "failed to load smart previews");
+                Vec::new()
+            }),
+            None => Vec::new(),
+        };
+        self.smart_previews = previews.into_iter().map(|p| (p.photo_id, p)).collect();
+    }
+
+    fn reload_animations(&mut self) {
+        let animations = match &self.catalog {
+            Some(catalog) => catalog.list_animations().unwrap_or_else(|err| {
+                error!(%err, "failed to load animations");
+                Vec::new()
+            }),
+            None => Vec::new(),
+        };
+        self.animations = animations.into_iter().map(|a| (a.photo_id, a)).collect();
+    }
+
+    fn reload_stacks(&mut self) {
+        let stacks = match &self.catalog {
+            Some(catalog) => catalog.list_stacks().unwrap_or_else(|err| {
+                error!(%err, "failed to load stacks");
+                Vec::new()
+            }),
+            None => Vec::new(),
+        };
+        self.stacked_under = stacks
+            .iter()
+            .flat_map(|stack| stack.members[1..].iter().map(|&id| (id, stack.cover())))
+            .collect();
+        self.stacks = stacks.into_iter().map(|s| (s.cover(), s)).collect();
+        self.expanded_stacks
+            .retain(|cover| self.stacks.contains_key(cover));
+    }
+
+    /// The frame `id` is shown as: its chosen key frame if it's animated,
+    /// otherwise the first and only one.
+    fn key_frame(&self, id: PhotoId) -> u32 {
+        self.animations.get(&id).map_or(0, |a| a.key_frame)
+    }
+
+    fn handle_set_key_frame(&mut self, frame: u32) -> Task<Message> {
+        let (Some(id), Some(catalog)) = (self.loaded_photo, &self.catalog) else {
+            return Task::none();
+        };
+        if self.key_frame(id) == frame {
+            return Task::none();
+        }
+        if let Err(err) = catalog.set_key_frame(id, frame) {
+            error!(%err, "failed to set key frame");
+            self.status_message = format!("Could not change the key frame: {err}");
+            return Task::none();
+        }
+        if let Some(animation) = self.animations.get_mut(&id) {
+            animation.key_frame = frame;
+        }
+        // Decode the new frame and let the grid pick up its thumbnail.
+        self.save_current_edits();
+        self.loaded_photo = None;
+        self.thumbnails.remove(&id);
+        Task::batch([self.open_photo(id), self.load_next_thumbnail_batch()])
+    }
+
+    /// The smart preview to open in place of `photo`, if its original is
+    /// offline.
+    fn offline_smart_preview(&self, photo: &Photo) -> Option<&SmartPreview> {
+        if Path::new(&photo.file_path).exists() {
+            return None;
+        }
+        self.smart_previews
+            .get(&photo.id)
+            .filter(|p| Path::new(&p.path).exists())
+    }
+
+    fn reload_calibration(&mut self) {
+        let calibration = match &self.catalog {
+            Some(catalog) => Calibration::load(catalog, self.preferences.dark_frame_subtraction)
+                .unwrap_or_else(|err| {
+                    error!(%err, "failed to load calibration");
+                    Calibration::default()
+                }),
+            None => Calibration::default(),
+        };
+        self.calibration = Arc::new(calibration);
+    }
+
+    fn handle_master_dark_built(
+        &mut self,
+        result: Result<Arc<DetectedMasterDark>, String>,
+    ) -> Task<Message> {
+        let built = match result {
+            Ok(built) => built,
+            Err(err) => {
+                self.status_message = format!("Could not build a master dark: {err}");
+                return Task::none();
+            }
+        };
+        let Some(catalog) = &self.catalog else {
+            return Task::none();
+        };
+        match catalog.save_master_dark(&built.settings, built.frame_count, &built.dark) {
+            Ok(_) => {
+                self.status_message = format!(
+                    "Saved master dark from {} frames for {}.",
+                    built.frame_count,
+                    dark_settings_label(&built.settings)
+                );
+                self.reload_calibration();
+            }
+            Err(err) => {
+                error!(%err, "failed to save master dark");
+                self.status_message = format!("Failed to save master dark: {err}");
+            }
+        }
+        Task::none()
+    }
+
+    fn handle_defect_map_detected(
+        &mut self,
+        result: Result<DetectedDefectMap, String>,
+    ) -> Task<Message> {
+        let detected = match result {
+            Ok(detected) => detected,
+            Err(err) => {
+                self.status_message = format!("Could not build a dead pixel map: {err}");
+                return Task::none();
+            }
+        };
+        let Some(catalog) = &self.catalog else {
+            return Task::none();
+        };
+        let camera = detected.camera_model.as_deref().unwrap_or("camera");
+        match catalog.save_defect_map(
+            &detected.camera_serial,
+            detected.camera_model.as_deref(),
+            &detected.map,
+        ) {
+            Ok(()) => {
+                self.status_message = format!(
+                    "Mapped {} stuck pixels for {camera} #{}. Photos from this body are corrected when opened or exported.",
+                    detected.map.pixels.len(),
+                    detected.camera_serial
+                );
+                self.reload_calibration();
+            }
+            Err(err) => {
+                error!(%err, "failed to save defect map");
+                self.status_message = format!("Failed to save dead pixel map: {err}");
+            }
+        }
+        Task::none()
+    }
+
+    fn handle_flat_field_detected(
+        &mut self,
+        result: Result<DetectedFlatField, String>,
+    ) -> Task<Message> {
+        let detected = match result {
+            Ok(detected) => detected,
+            Err(err) => {
+                self.status_message = format!("Could not build a flat field: {err}");
+                return Task::none();
+            }
+        };
+        let Some(catalog) = &self.catalog else {
+            return Task::none();
+        };
+        match catalog.save_flat_field(&detected.camera_model, &detected.lens, &detected.field) {
+            Ok(()) => {
+                let lens = if detected.lens.is_empty() {
+                    "an unreported lens"
+                } else {
+                    &detected.lens
+                };
+                self.status_message = format!(
+                    "Saved flat field for {} with {lens}. Matching photos are corrected when opened or exported.",
+                    detected.camera_model
+                );
+                self.reload_calibration();
+            }
+            Err(err) => {
+                error!(%err, "failed to save flat field");
+                self.status_message = format!("Failed to save flat field: {err}");
+            }
+        }
+        Task::none()
+    }
+
+    /// A photo imported fine but its pixels can't be decoded: quarantine it
+    /// so it shows up for review rather than as a blank grid cell.
+    fn handle_thumbnail_failed(&mut self, id: PhotoId, reason: String) -> Task<Message> {
+        self.thumbnail_failures.insert(id);
+        // Nothing's wrong with a file whose drive is unplugged; build its
+        // thumbnail once the drive is back.
+        if self
+            .photos
+            .iter()
+            .find(|p| p.id == id)
+            .is_some_and(|p| volumes::is_offline(Path::new(&p.file_path)))
+        {
+            self.held_thumbnails.insert(id);
+            return self.load_next_thumbnail_batch();
+        }
+        // Quarantine is for files that are there but won't decode; a missing
+        // file is for the path remap to find, not a decoder problem.
+        if let (Some(catalog), Some(photo)) = (
+            &self.catalog,
+            self.photos
+                .iter()
+                .find(|p| p.id == id && Path::new(&p.file_path).is_file()),
+        ) {
+            match catalog.quarantine_file(&photo.file_path, &reason) {
+                Ok(()) => self.reload_quarantine(),
+                Err(err) => error!(%err, id, "failed to quarantine file"),
+            }
+        }
+        self.load_next_thumbnail_batch()
+    }
+
+    /// Decode the file again and, if it works now, import it when it isn't
+    /// in the catalog yet. Resolves to whether it was newly imported.
+    fn handle_retry_quarantined(&mut self, id: QuarantineId) -> Task<Message> {
+        let Some(file) = self.quarantine.iter().find(|f| f.id == id) else {
+            return Task::none();
+        };
+        let path = file.file_path.clone();
+        let in_catalog = self.photos.iter().any(|p| p.file_path == path);
+        let catalog_path = self.catalog_path.clone().unwrap_or_default();
+        self.status_message = format!("Retrying {}...", file_name(&path));
+        Task::perform(
+            async move {
+                // Decode for real; a cached thumbnail says nothing about
+                // whether the file reads now.
+                crema_thumbnails::generator::fast_thumbnail(Path::new(&path))
+                    .map_err(|e| format!("{e:#}"))?;
+                if in_catalog {
+                    return Ok(false);
+                }
+                let catalog =
+                    Catalog::open_existing(&catalog_path).map_err(|e| format!("{e:#}"))?;
+                crema_catalog::import::import_file(&catalog, Path::new(&path))
+                    .map(|id| id.is_some())
+                    .map_err(|e| format!("{e:#}"))
+            },
+            move |result| Message::QuarantineRetried(id, result),
+        )
+    }
+
+    fn handle_quarantine_retried(
+        &mut self,
+        id: QuarantineId,
+        result: Result<bool, String>,
+    ) -> Task<Message> {
+        let Some(file) = self.quarantine.iter().find(|f| f.id == id) else {
+            return Task::none();
+        };
+        let path = file.file_path.clone();
+        let name = file_name(&path);
+        let Some(catalog) = &self.catalog else {
+            return Task::none();
+        };
+        match result {
+            Ok(imported) => {
+                if let Err(err) = catalog.release_quarantine(&path) {
+                    error!(%err, "failed to release quarantined file");
+                }
+                if let Some(photo) = self.photos.iter().find(|p| p.file_path == path) {
+                    self.thumbnail_failures.remove(&photo.id);
+                }
+                self.reload_quarantine();
+                self.status_message = format!("{name} decoded successfully");
+                if imported {
+                    self.refresh_photos()
+                } else {
+                    self.load_next_thumbnail_batch()
+                }
+            }
+            Err(reason) => {
+                if !Path::new(&path).is_file() {
+                    self.status_message = format!("{name} is missing or its drive is offline");
+                    return Task::none();
+                }
+                if let Err(err) = catalog.quarantine_file(&path, &reason) {
+                    error!(%err, "failed to update quarantined file");
+                }
+                self.reload_quarantine();
+                self.status_message = format!("{name} still fails: {reason}");
+                Task::none()
+            }
+        }
+    }
+
+    fn handle_photos_listed(&mut self, photos: Vec<Photo>) -> Task<Message> {
+        self.photos = photos;
+        self.reload_smart_previews();
+        self.reload_animations();
+        self.reload_stacks();
+        let counts = self.refresh_sidebar_counts();
+        self.volumes = volumes::scan(&self.photos);
+        self.offline_photos = volumes::offline_photos(&self.photos, &self.volumes);
+        self.status_message = format!("{} photos in catalog", self.photos.len());
+
+        if self
+            .selected_photo
+            .is_some_and(|id| !self.photos.iter().any(|photo| photo.id == id))
+        {
+            self.selected_photo = None;
+        }
+
+        if self
+            .loaded_photo
+            .is_some_and(|id| !self.photos.iter().any(|photo| photo.id == id))
+        {
+            self.loaded_photo = None;
+            self.current_image = None;
+            self.preview_image = None;
+            self.processed_image = None;
+            self.color_range_reading = None;
+            self.histogram = None;
+            self.current_exif.clear();
+            self.snapshots.clear();
+            self.edit_diff = None;
+        }
+
+        self.update_export_enabled();
+        Task::batch([
+            counts,
+            self.load_next_thumbnail_batch(),
+            self.release_held_work(),
+        ])
+    }
+
+    fn handle_check_volumes(&mut self) -> Task<Message> {
+        let mut changed = false;
+        for volume in &mut self.volumes {
+            let online = volumes::is_mounted(&volume.root);
+            changed |= online != volume.online;
+            volume.online = online;
+        }
+        if changed {
+            self.offline_photos = volumes::offline_photos(&self.photos, &self.volumes);
+        }
+        self.release_held_work()
+    }
+
+    /// Start the thumbnails, exports and smart previews that were waiting
+    /// on drives that are mounted now.
+    fn release_held_work(&mut self) -> Task<Message> {
+        let mut tasks = Vec::new();
+        let back: Vec<PhotoId> = self
+            .held_thumbnails
+            .iter()
+            .filter(|id| !self.offline_photos.contains(id))
+            .copied()
+            .collect();
+        if !back.is_empty() {
+            for id in &back {
+                self.held_thumbnails.remove(id);
+                self.thumbnail_failures.remove(id);
+            }
+            tasks.push(self.load_next_thumbnail_batch());
+        }
+
+        // One export at a time; the rest wait for the next check.
+        if !self.is_exporting && !self.held_exports.is_empty() {
+            let (ready, held): (Vec<_>, Vec<_>) = std::mem::take(&mut self.held_exports)
+                .into_iter()
+                .partition(|job| !volumes::is_offline(Path::new(&job.source)));
+            self.held_exports = held;
+            if !ready.is_empty() {
+                info!(
+                    count = ready.len(),
+                    "drive reconnected, resuming held exports"
+                );
+                tasks.push(self.run_export_jobs(ready));
+            }
+        }
+
+        if !self.held_previews.is_empty() {
+            let (held, ready): (Vec<_>, Vec<_>) = std::mem::take(&mut self.held_previews)
+                .into_iter()
+                .partition(|(_, path)| volumes::is_offline(path));
+            self.held_previews = held;
+            if !ready.is_empty() {
+                let ids: Vec<PhotoId> = ready.into_iter().map(|(id, _)| id).collect();
+                let catalog_path = self.catalog_path.clone().unwrap_or_default();
+                let calibration = self.calibration.clone();
+                tasks.push(Task::perform(
+                    async move {
+                        let catalog = match Catalog::open_existing(&catalog_path) {
+                            Ok(catalog) => catalog,
+                            Err(err) => {
+                                error!(%err, "failed to open catalog for smart previews");
+                                return Vec::new();
+                            }
+                        };
+                        let dir = smart_preview::previews_dir(&catalog_path);
+                        smart_preview::build_all(&catalog, &ids, &calibration, &dir)
+                    },
+                    Message::SmartPreviewsHeld,
+                ));
+            }
+        }
+
+        self.count_waiting_exports();
+        Task::batch(tasks)
+    }
+
+    fn count_waiting_exports(&mut self) {
+        for volume in &mut self.volumes {
+            volume.waiting = self
+                .held_exports
+                .iter()
+                .filter(|job| Path::new(&job.source).starts_with(&volume.root))
+                .count();
+        }
+    }
+
+    /// Recount the sidebar's years and gear for the library on show. Years
+    /// already expanded are loaded again so they stay open. The catalog's
+    /// counts scan every photo, so they run off the UI thread; the
+    /// sidebar keeps its old counts until they arrive.
+    fn refresh_sidebar_counts(&mut self) -> Task<Message> {
+        self.gear_counts = GearCounts::from_photos(self.photos());
+        self.sidebar_generation += 1;
+        let expanded = self.expanded_years();
+        let catalog_path = match (&self.time_machine, &self.catalog_path) {
+            (None, Some(path)) if self.catalog.is_some() => path.clone(),
+            _ => {
+                self.date_index = DateIndex::from_photos(self.photos());
+                for year in expanded {
+                    self.load_date_year(year);
+                }
+                return Task::none();
+            }
+        };
+        let generation = self.sidebar_generation;
+        Task::perform(
+            async move { count_dates(&catalog_path, &expanded).map_err(|err| format!("{err:#}")) },
+            move |counts| Message::SidebarCounted(generation, counts),
+        )
+    }
+
+    fn handle_sidebar_counted(&mut self, generation: u64, counts: Result<DateCounts, String>) {
+        if generation != self.sidebar_generation {
+            return;
+        }
+        match counts {
+            Ok(DateCounts { years, days }) => {
+                self.date_index = DateIndex::new(self.photos().len(), years);
+                for (year, days) in days {
+                    self.date_index.set_days(year, days);
+                }
+            }
+            Err(err) => {
+                error!(%err, "failed to count photos by date");
+                self.date_index = DateIndex::from_photos(self.photos());
+                for year in self.expanded_years() {
+                    let days = date_sidebar::day_counts(self.photos(), year);
+                    self.date_index.set_days(year, days);
+                }
+            }
+        }
+    }
+
+    fn expanded_years(&self) -> Vec<u16> {
+        self.expanded_dates
+            .iter()
+            .filter_map(|key| match key {
+                DateExpansionKey::Year(year) => Some(*year),
+                DateExpansionKey::Month(..) => None,
+            })
+            .collect()
+    }
+
+    /// Fetch the months and days of `year` the first time it's expanded.
+    fn load_date_year(&mut self, year: u16) {
+        if !self.date_index.needs_days(year) {
+            return;
+        }
+        let days = match (&self.time_machine, &self.catalog) {
+            (None, Some(catalog)) => catalog.day_counts(year).unwrap_or_else(|err| {
+                error!(%err, year, "failed to count photos by day");
+                Vec::new()
+            }),
+            _ => date_sidebar::day_counts(self.photos(), year),
+        };
+        self.date_index.set_days(year, days);
+    }
+
+    fn handle_thumbnail_ready(&mut self, id: PhotoId, bytes: Vec<u8>) -> Task<Message> {
+        if let Some(aspect) = thumbnail_aspect(&bytes) {
+            self.thumbnail_aspects.insert(id, aspect);
+        }
+        let handle = iced::widget::image::Handle::from_bytes(bytes);
+        self.thumbnails.insert(id, handle);
+        self.load_next_thumbnail_batch()
+    }
+
+    fn handle_select_photo(&mut self, id: PhotoId) -> Task<Message> {
+        if self.modifiers.command() {
+            return self.handle_toggle_photo_selection(id);
+        }
+        if self.modifiers.shift() {
+            return self.handle_select_photo_range(id);
+        }
+
+        self.selected_photo = Some(id);
+        self.selected_photos.clear();
+        self.update_export_enabled();
+
+        if let Some(photo) = self.photos.iter().find(|photo| photo.id == id) {
+            let name = std::path::Path::new(&photo.file_path)
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string();
+            self.status_message = format!("Selected {name}. Open Develop to edit.");
+        }
+
+        Task::none()
+    }
+
+    fn open_photo(&mut self, id: PhotoId) -> Task<Message> {
+        if self.loaded_photo == Some(id) && self.preview_image.is_some() {
+            self.selected_photo = Some(id);
+            self.workspace = Workspace::Develop;
+            self.right_panel_open = true;
+            self.update_export_enabled();
+            return Task::none();
+        }
+
+        let closed = self.loaded_photo;
+        self.save_current_edits();
+        let refresh = self.refresh_edited_thumbnail(closed);
+        self.clear_undo_history();
+        self.zoom_state = ZoomState::default();
+        self.original_display = None;
+        self.showing_before = false;
+        self.crop_mode = false;
+
+        self.selected_photo = Some(id);
+        self.workspace = Workspace::Develop;
+        self.right_panel_open = true;
+        self.current_image = None;
+        self.preview_image = None;
+        self.processed_image = None;
+        self.color_range_reading = None;
+        self.histogram = None;
+        self.current_exif.clear();
+        self.loaded_photo = None;
+        self.is_loading_photo = true;
+        self.editing_embedded_preview = false;
+        self.editing_smart_preview = false;
+        self.is_processing = false;
+
+        if let Some(ref catalog) = self.catalog {
+            if let Ok(Some(edit)) = catalog.get_edits(id) {
+                self.edit_params = edit.to_edit_params();
+            } else {
+                self.edit_params = EditParams::default();
+            }
+        }
+        self.edit_diff = None;
+        self.reload_snapshots(id);
+        // Edits aren't stored with the snapshot they came from, so a photo
+        // opens as the version it still matches, if any.
+        self.applied_snapshot = self
+            .snapshots
+            .iter()
+            .find(|s| s.params == self.edit_params)
+            .map(|s| s.id);
+        self.reload_annotations(id);
+        self.reload_provenance(id);
+
+        self.update_export_enabled();
+
+        let photo = self.photos.iter().find(|p| p.id == id).cloned();
+        let Some(photo) = photo else {
+            self.is_loading_photo = false;
+            self.status_message = "Unable to locate that photo.".into();
+            return refresh;
+        };
+
+        let name = std::path::Path::new(&photo.file_path)
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+        self.status_message = format!("Loading {name}...");
+
+        let preview_path = photo.file_path.clone();
+        let embedded_task = Task::perform(
+            async move {
+                crema_core::raw::load_embedded_preview(
+                    Path::new(&preview_path),
+                    Some(preview::PREVIEW_EDGE),
+                )
+            },
+            move |preview| match preview {
+                Some(preview) => Message::EmbeddedPreviewLoaded(id, Arc::new(preview)),
+                None => Message::Noop,
+            },
+        );
+
+        let path = photo.file_path.clone();
+        let key_frame = self.key_frame(id);
+        let smart_preview = self.offline_smart_preview(&photo).map(|p| p.path.clone());
+        self.editing_smart_preview = smart_preview.is_some();
+        let calibration = self.calibration.clone();
+        let decode_task = Task::perform(
+            async move {
+                let t0 = std::time::Instant::now();
+                let p = std::path::Path::new(&path);
+                let exif = calibration.exif_for(p);
+                let buf = match &smart_preview {
+                    // Calibrated when it was built.
+                    Some(proxy) => crema_core::raw::load_any(Path::new(proxy)).ok()?,
+                    None => calibration.decode(p, key_frame, exif.as_ref()).ok()?,
+                };
+                let preview = preview::downsample(&buf);
+                let exif = exif.map(|e| e.summary_lines()).unwrap_or_default();
+                info!(
+                    elapsed_ms = t0.elapsed().as_millis(),
+                    w = buf.width,
+                    h = buf.height,
+                    "image loaded"
+                );
+                Some((id, Arc::new(buf), Arc::new(preview), exif))
+            },
+            move |result| match result {
+                Some((id, buf, preview, exif)) => Message::ImageLoaded(id, buf, preview, exif),
+                None => Message::ImageLoadFailed(id),
+            },
+        );
+        Task::batch([embedded_task, decode_task, refresh])
+    }
+
+    fn handle_set_workspace(&mut self, workspace: Workspace) -> Task<Message> {
+        let mut refresh = Task::none();
+        if workspace == Workspace::Library && self.workspace == Workspace::Develop {
+            self.save_current_edits();
+            refresh = self.refresh_edited_thumbnail(self.loaded_photo);
+        }
+
+        self.workspace = workspace;
+        self.update_export_enabled();
+
+        if workspace == Workspace::Develop {
+            self.right_panel_open = true;
+            if let Some(id) = self.selected_photo {
+                if self.loaded_photo != Some(id) || self.preview_image.is_none() {
+                    return self.open_photo(id);
+                }
+            } else {
+                self.status_message = "Select a photo in Library to open Develop.".into();
+            }
+        }
+
+        refresh
+    }
+
+    fn handle_image_loaded(
+        &mut self,
+        id: PhotoId,
+        buf: Arc<ImageBuf>,
+        preview: Arc<ImageBuf>,
+        exif: Vec<(String, String)>,
+    ) -> Task<Message> {
+        if self.selected_photo != Some(id) {
+            return Task::none();
+        }
+
+        self.loaded_photo = Some(id);
+        self.current_image = Some(buf);
+        self.preview_image = Some(preview.clone());
+        self.current_exif = exif;
+        self.is_loading_photo = false;
+        // Whatever was edited against the embedded preview carries over:
+        // the reprocess below renders the same params on the full decode.
+        self.editing_embedded_preview = false;
+        self.original_display = None;
+        self.showing_before = false;
+        self.status_message = format!("Rendering {}...", self.current_photo_label());
+        self.update_export_enabled();
+
+        let original_task = Task::perform(
+            async move {
+                let rgba = preview.to_rgba_u8_srgb();
+                iced::widget::image::Handle::from_rgba(preview.width, preview.height, rgba)
+            },
+            Message::OriginalReady,
+        );
+
+        Task::batch([self.reprocess_image(), original_task])
+    }
+
+    /// Start editing against the embedded preview, unless the full decode
+    /// already won the race.
+    fn handle_embedded_preview_loaded(
+        &mut self,
+        id: PhotoId,
+        preview: Arc<ImageBuf>,
+    ) -> Task<Message> {
+        if self.selected_photo != Some(id) || !self.is_loading_photo {
+            return Task::none();
+        }
+
+        self.loaded_photo = Some(id);
+        self.preview_image = Some(preview);
+        self.editing_embedded_preview = true;
+        self.status_message = format!(
+            "Editing the embedded preview of {} while the RAW decodes...",
+            self.current_photo_label()
+        );
+        self.reprocess_image()
+    }
+
+    fn handle_image_processed(
+        &mut self,
+        generation: u64,
+        handle: iced::widget::image::Handle,
+        hist: Box<HistogramData>,
+    ) -> Task<Message> {
+        if generation != self.processing_generation {
+            return Task::none();
+        }
+
+        self.processed_image = Some(handle);
+        self.histogram = Some(hist);
+        self.is_processing = false;
+        if let Some(ref preview) = self.preview_image {
+            self.preview_dimensions = (preview.width, preview.height);
+        }
+        if self.editing_smart_preview {
+            self.status_message = format!(
+                "Editing the smart preview of {}; the original is offline",
+                self.current_photo_label()
+            );
+        } else if !self.editing_embedded_preview {
+            self.status_message = format!("Ready to edit {}", self.current_photo_label());
+        }
+        self.save_current_edits();
+        self.measure_color_range()
+    }
+
+    /// Measure the color range on the develop preview while its section is
+    /// open. Readings are tagged so a slow one can't replace a newer one.
+    fn measure_color_range(&mut self) -> Task<Message> {
+        if !self.is_panel_open(PanelSection::ColorRange) {
+            return Task::none();
+        }
+        let Some(iced::widget::image::Handle::Rgba {
+            width,
+            height,
+            pixels,
+            ..
+        }) = self.processed_image.clone()
+        else {
+            return Task::none();
+        };
+        self.color_range_generation += 1;
+        let generation = self.color_range_generation;
+        let range = self.color_range;
+        Task::perform(
+            async move {
+                let coverage = range.coverage_rgba8(&pixels);
+                let mask = range.mask_rgba8(&pixels);
+                (
+                    coverage,
+                    iced::widget::image::Handle::from_rgba(width, height, mask),
+                )
+            },
+            move |(coverage, mask)| Message::ColorRangeMeasured(generation, coverage, mask),
+        )
+    }
+
+    /// Measure the color range across the selection, each photo rendered
+    /// with its own edits, for checking a color holds across a set.
+    fn handle_measure_color_range_selection(&mut self) -> Task<Message> {
+        self.save_current_edits();
+        let photos: Vec<(String, u32, EditParams)> = self
+            .batch_target_ids()
+            .into_iter()
+            .filter_map(|id| {
+                let photo = self.photos.iter().find(|p| p.id == id)?;
+                Some((
+                    photo.file_path.clone(),
+                    self.key_frame(id),
+                    self.saved_edits(id),
+                ))
+            })
+            .collect();
+        if photos.is_empty() {
+            return Task::none();
+        }
+        self.status_message = format!("Measuring the color range in {} photos...", photos.len());
+        let range = self.color_range;
+        Task::perform(
+            async move {
+                let mut results: Vec<_> = photos
+                    .into_iter()
+                    .map(|(path, frame, params)| {
+                        let p = Path::new(&path);
+                        let coverage =
+                            crema_core::raw::load_frame_scaled(p, frame, Some(COLOR_RANGE_EDGE))
+                                .and_then(|buf| {
+                                    crema_core::pipeline::Pipeline::new().process_cpu(buf, &params)
+                                })
+                                .map(|buf| range.coverage(&buf))
+                                .map_err(|err| {
+                                    error!(%err, path, "failed to measure color range");
+                                    format!("{err:#}")
+                                });
+                        (file_name(&path), coverage)
+                    })
+                    .collect();
+                results.sort_by(|a, b| a.0.cmp(&b.0));
+                results
+            },
+            Message::ColorRangeSelectionMeasured,
+        )
+    }
+
+    fn handle_image_load_failed(&mut self, id: PhotoId) -> Task<Message> {
+        if self.selected_photo == Some(id) {
+            self.is_loading_photo = false;
+            self.is_processing = false;
+            self.status_message = if self.editing_embedded_preview {
+                "Unable to decode the RAW; only its embedded preview is available.".into()
+            } else {
+                "Unable to load that photo.".into()
+            };
+            self.editing_embedded_preview = false;
+        }
+        Task::none()
+    }
+
+    fn handle_export(&self, options: ExportOptions) -> Task<Message> {
+        let default_name = self.default_export_filename();
+        Task::perform(
+            async move {
+                let dialog = rfd::AsyncFileDialog::new()
+                    .set_title("Export photo")
+                    .set_file_name(&default_name)
+                    .add_filter("JPEG", &["jpg", "jpeg"])
+                    .add_filter("PNG", &["png"])
+                    .add_filter("TIFF", &["tiff", "tif"]);
+                dialog.save_file().await.map(|h| h.path().to_path_buf())
+            },
+            move |result| match result {
+                Some(path) => Message::ExportPathSelected(path, options),
+                None => Message::Noop,
+            },
+        )
+    }
+
+    fn handle_stack_photos(&mut self, method: StackMethod) -> Task<Message> {
+        self.stack_open = false;
+        let stem = self
+            .photos
+            .iter()
+            .find(|p| self.selected_photos.contains(&p.id))
+            .and_then(|p| Path::new(&p.file_path).file_stem())
+            .map_or_else(|| "stack".into(), |s| s.to_string_lossy().to_string());
+        let default_name = format!("{stem}-stack.tif");
+        Task::perform(
+            async move {
+                let dialog = rfd::AsyncFileDialog::new()
+                    .set_title("Save stacked image")
+                    .set_file_name(&default_name)
+                    .add_filter("TIFF", &["tif", "tiff"]);
+                dialog.save_file().await.map(|h| h.path().to_path_buf())
+            },
+            move |result| match result {
+                Some(path) => Message::StackPathSelected(method, path),
+                None => Message::Noop,
+            },
+        )
+    }
+
+    fn handle_stack_path_selected(&mut self, method: StackMethod, path: PathBuf) -> Task<Message> {
+        let mut files: Vec<(String, PhotoId)> = self
+            .photos
+            .iter()
+            .filter(|p| self.selected_photos.contains(&p.id))
+            .map(|p| (p.file_path.clone(), p.id))
+            .collect();
+        files.sort();
+        if files.len() < 2 {
+            return Task::none();
+        }
+
+        self.is_exporting = true;
+        self.status_message = format!("Stacking {} frames...", files.len());
+        let calibration = self.calibration.clone();
+        Task::perform(
+            async move {
+                let frames = files.iter().map(|(file, _)| {
+                    let p = Path::new(file);
+                    calibration.decode(p, 0, calibration.exif_for(p).as_ref())
+                });
+                crema_core::compositing::stack(frames, method)
+                    .and_then(|buf| crema_core::compositing::save_tiff16(&buf, &path))
+                    .map_err(|e| format!("{e:#}"))?;
+                Ok((path, files.into_iter().map(|(_, id)| id).collect()))
+            },
+            Message::StackComplete,
+        )
+    }
+
+    fn handle_stack_complete(
+        &mut self,
+        result: Result<(PathBuf, Vec<PhotoId>), String>,
+    ) -> Task<Message> {
+        self.is_exporting = false;
+        let (path, sources) = match result {
+            Ok(done) => done,
+            Err(err) => {
+                error!(%err, "stacking failed");
+                self.notify(
+                    Notification::new(Category::Jobs, format!("Stacking failed: {err}"))
+                        .failed(true),
+                );
+                return Task::none();
+            }
+        };
+        let count = sources.len();
+        let name = file_name(&path.to_string_lossy());
+        let Some(catalog) = &self.catalog else {
+            return Task::none();
+        };
+        let imported = match crema_catalog::import::import_file(catalog, &path) {
+            Ok(Some(id)) => {
+                if let Err(err) = catalog.link_derived(id, &sources, DerivationKind::Stack) {
+                    error!(%err, "failed to record stack sources");
+                }
+                Ok(())
+            }
+            Ok(None) => Ok(()),
+            Err(err) => {
+                error!(%err, "failed to import stacked image");
+                Err(err)
+            }
+        };
+        let notification = match &imported {
+            Ok(()) => Notification::new(
+                Category::Jobs,
+                format!("Stacked {count} frames into {name}"),
+            ),
+            Err(err) => Notification::new(
+                Category::Jobs,
+                format!("Stacked {count} frames into {name}, but import failed: {err}"),
+            )
+            .failed(true),
+        };
+        self.notify(notification.with_action(Action::Reveal(path)));
+        if imported.is_err() {
+            return Task::none();
+        }
+        self.refresh_photos()
+    }
+
+    fn handle_audit_precision(&mut self) -> Task<Message> {
+        let Some(full_res) = self.current_image.clone() else {
+            return Task::none();
+        };
+        self.status_message = format!("Auditing precision of {}...", self.current_photo_label());
+        let params = self.edit_params.clone();
+        Task::perform(
+            async move {
+                let input = full_res.downsample(PRECISION_AUDIT_EDGE);
+                crema_core::pipeline::Pipeline::new()
+                    .audit_precision(&input, &params)
+                    .map_err(|e| format!("{e:#}"))
+            },
+            Message::PrecisionAudited,
+        )
+    }
+
+    fn handle_verify_render_consistency(&mut self) -> Task<Message> {
+        let Some(full_res) = self.current_image.clone() else {
+            return Task::none();
+        };
+        let Some(gpu) = self.gpu.clone() else {
+            self.status_message =
+                "No GPU is available, so there's nothing to compare against.".into();
+            return Task::none();
+        };
+        let photo = self.current_photo_label();
+        self.status_message = format!("Rendering {photo} on the CPU and GPU...");
+        let mut params = self.edit_params.clone();
+        let skipped_noise_reduction = !gpu_supports_preview_params(&params);
+        params.nr_luminance = 0.0;
+        params.nr_color = 0.0;
+        Task::perform(
+            async move {
+                let input = Arc::new(full_res.downsample(RENDER_CONSISTENCY_EDGE));
+                let gpu_render =
+                    process_gpu(&gpu, &input, &params).ok_or("the GPU render failed")?;
+                let cpu_render = crema_core::pipeline::Pipeline::new()
+                    .process_cpu(ImageBuf::clone(&input), &params)
+                    .map_err(|e| format!("{e:#}"))?;
+                let diff = crema_core::render_diff::compare(&cpu_render, &gpu_render)
+                    .map_err(|e| format!("{e:#}"))?;
+                let heatmap = iced::widget::image::Handle::from_rgba(
+                    diff.heatmap.width,
+                    diff.heatmap.height,
+                    diff.heatmap.to_rgba_u8_srgb(),
+                );
+                Ok(RenderConsistency {
+                    photo,
+                    max_levels: diff.max_levels,
+                    mean_levels: diff.mean_levels,
+                    worst_pixel: diff.worst_pixel,
+                    differing_fraction: diff.differing_fraction,
+                    heatmap,
+                    skipped_noise_reduction,
+                })
+            },
+            Message::RenderConsistencyVerified,
+        )
+    }
+
+    fn handle_precision_audited(&mut self, result: Result<PrecisionReport, String>) {
+        let report = match result {
+            Ok(report) => report,
+            Err(err) => {
+                error!(%err, "precision audit failed");
+                self.status_message = format!("Precision audit failed: {err}");
+                return;
+            }
+        };
+        for module in &report.modules {
+            info!(module = %module.name, max_levels = ?module.max_levels, "precision audit");
+        }
+        let (x, y) = report.worst_pixel;
+        let mut message = format!(
+            "f32 vs f64: max {:.4} levels at ({x}, {y}), mean {:.5}",
+            report.max_levels, report.mean_levels
+        );
+        if let Some(worst) = report.worst_module()
+            && let Some(levels) = worst.max_levels
+        {
+            message.push_str(&format!(", largest in {} ({levels:.4})", worst.name));
+        }
+        self.status_message = message;
+    }
+
+    fn handle_export_animation(&mut self, kind: AnimationKind) -> Task<Message> {
+        self.animation_export_open = false;
+        let stem = Path::new(&self.default_export_filename())
+            .file_stem()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+        let default_name = format!("{stem}-{}.gif", kind.file_stem());
+        Task::perform(
+            async move {
+                let dialog = rfd::AsyncFileDialog::new()
+                    .set_title("Export animation")
+                    .set_file_name(&default_name)
+                    .add_filter("GIF", &["gif"])
+                    .add_filter("MP4 (requires ffmpeg)", &["mp4"]);
+                dialog.save_file().await.map(|h| h.path().to_path_buf())
+            },
+            move |result| match result {
+                Some(path) => Message::AnimationPathSelected(kind, path),
+                None => Message::Noop,
+            },
+        )
+    }
+
+    fn handle_animation_path_selected(
+        &mut self,
+        kind: AnimationKind,
+        path: PathBuf,
+    ) -> Task<Message> {
+        let Some(full_res) = self.current_image.clone() else {
+            return Task::none();
+        };
+
+        self.is_exporting = true;
+        self.export_target = Some(path.clone());
+        self.status_message = format!("Rendering {} animation...", kind.label());
+        let params = self.edit_params.clone();
+        Task::perform(
+            async move {
+                let result = crate::animation::render_frames(&full_res, &params, kind)
+                    .and_then(|frames| crate::animation::encode(frames, &path));
+                match result {
+                    Ok(()) => {
+                        ExportOutcome::Exported(format!("Exported animation to {}", path.display()))
+                    }
+                    Err(err) => {
+                        error!(%err, "animation export failed");
+                        ExportOutcome::Failed(format!("Animation export failed: {err:#}"))
+                    }
+                }
+            },
+            Message::ExportComplete,
+        )
+    }
+
+    fn handle_export_comparison(&mut self) -> Task<Message> {
+        let stem = Path::new(&self.default_export_filename())
+            .file_stem()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+        let default_name = format!("{stem}-before-after.jpg");
+        Task::perform(
+            async move {
+                let dialog = rfd::AsyncFileDialog::new()
+                    .set_title("Export before / after")
+                    .set_file_name(&default_name)
+                    .add_filter("JPEG", &["jpg", "jpeg"]);
+                dialog.save_file().await.map(|h| h.path().to_path_buf())
+            },
+            |result| match result {
+                Some(path) => Message::ComparisonPathSelected(path),
+                None => Message::Noop,
+            },
+        )
+    }
+
+    fn handle_comparison_path_selected(&mut self, path: PathBuf) -> Task<Message> {
+        let Some(options) = self.comparison_export.take() else {
+            return Task::none();
+        };
+        let Some(full_res) = self.current_image.clone() else {
+            return Task::none();
+        };
+
+        self.is_exporting = true;
+        self.export_target = Some(path.clone());
+        self.status_message = format!(
+            "Rendering before / after of {}...",
+            self.current_photo_label()
+        );
+        let params = self.edit_params.clone();
+        let metadata = self.current_photo().and_then(|photo| {
+            self.preferences
+                .export_metadata
+                .render(ExportCrop::AsEdited, photo)
+        });
+        let encoding = self.export_encoding(&ExportOptions::default());
+        Task::perform(
+            async move {
+                let pipeline = crema_core::pipeline::Pipeline::new();
+                let render =
+                    |params: &EditParams| pipeline.process_cpu(ImageBuf::clone(&full_res), params);
+                let rendered = render(&comparison::before_params(&params))
+                    .and_then(|before| Ok((before, render(&params)?)));
+                let (before, after) = match rendered {
+                    Ok(pair) => pair,
+                    Err(err) => {
+                        error!(%err, "before / after export failed");
+                        return ExportOutcome::Failed(format!("Export failed: {err}"));
+                    }
+                };
+                let canvas = comparison::compose(&before, &after, options.layout, options.labels);
+                let outcome = export_format::write(canvas, &encoding, &path);
+                match metadata {
+                    Some(metadata) if outcome.is_exported() && embed::supports(&path) => {
+                        match embed::embed(&path, &metadata) {
+                            Ok(()) => outcome,
+                            Err(err) => ExportOutcome::Failed(format!(
+                                "Export failed: could not write metadata: {err:#}"
+                            )),
+                        }
+                    }
+                    _ => outcome,
+                }
+            },
+            Message::ExportComplete,
+        )
+    }
+
+    fn handle_save_sidecar(&mut self) -> Task<Message> {
+        let Some(photo) = self.current_photo().cloned() else {
+            return Task::none();
+        };
+        let sidecar = sidecar_path(&photo.file_path);
+        match serde_json::to_string_pretty(&self.edit_params) {
+            Ok(json) => match std::fs::write(&sidecar, json) {
+                Ok(()) => {
+                    let name = sidecar
+                        .file_name()
+                        .unwrap_or_default()
+                        .to_string_lossy()
+                        .to_string();
+                    self.status_message = format!("Saved sidecar to {name}");
+                }
+                Err(err) => {
+                    error!(%err, "failed to write sidecar");
+                    self.status_message = format!("Failed to save sidecar: {err}");
+                }
+            },
+            Err(err) => {
+                error!(%err, "failed to serialize edits");
+                self.status_message = format!("Failed to save sidecar: {err}");
+            }
+        }
+        Task::none()
+    }
+
+    fn handle_load_sidecar(&mut self) -> Task<Message> {
+        let Some(photo) = self.current_photo().cloned() else {
+            return Task::none();
+        };
+        let sidecar = sidecar_path(&photo.file_path);
+        let data = match std::fs::read_to_string(&sidecar) {
+            Ok(d) => d,
+            Err(err) => {
+                let name = sidecar
+                    .file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .to_string();
+                self.status_message = format!("No sidecar found ({name}): {err}");
+                return Task::none();
+            }
+        };
+        match serde_json::from_str::<EditParams>(&data) {
+            Ok(params) => {
+                self.snapshot_for_undo();
+                self.edit_params = params;
+                self.save_current_edits();
+                let name = sidecar
+                    .file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .to_string();
+                self.status_message = format!("Loaded sidecar from {name}");
+                self.reprocess_image()
+            }
+            Err(err) => {
+                error!(%err, "failed to parse sidecar");
+                self.status_message = format!("Failed to load sidecar: {err}");
+                Task::none()
+            }
+        }
+    }
+
+    /// The settings a single-photo export with `options` is written with.
+    fn export_encoding(&self, options: &ExportOptions) -> ExportEncoding {
+        ExportEncoding {
+            dither: self.preferences.export_dither,
+            color_space: self.preferences.export_color_space,
+            tiff_16bit: self.preferences.export_tiff_16bit || options.tiff_16bit,
+            target_size: options.target_size,
+        }
+    }
+
+    /// Check the export before starting it, holding it in a dialog if
+    /// anything is worth a warning.
+    fn handle_export_path_selected(
+        &mut self,
+        path: PathBuf,
+        options: ExportOptions,
+    ) -> Task<Message> {
+        if self.current_image.is_none() {
+            return Task::none();
+        }
+        let warnings = export_check::check(
+            &self.edit_params,
+            &self.export_encoding(&options),
+            &path,
+            &self.preferences.suppressed_export_warnings,
+        );
+        if warnings.is_empty() {
+            self.start_full_res_export(path, options)
+        } else {
+            self.export_review = Some(ExportReview::new(path, options, warnings));
+            Task::none()
+        }
+    }
+
+    /// Close the warnings dialog, keeping the warnings it silenced.
+    fn take_export_review(&mut self) -> Option<ExportReview> {
+        let review = self.export_review.take()?;
+        self.preferences
+            .suppressed_export_warnings
+            .extend(review.silenced.iter().copied());
+        Some(review)
+    }
+
+    fn start_full_res_export(&mut self, path: PathBuf, options: ExportOptions) -> Task<Message> {
+        let Some(ref full_res) = self.current_image else {
+            return Task::none();
+        };
+        if self.export_progress.is_some() {
+            self.status_message = "An export is already running".into();
+            return Task::none();
+        }
+
+        let cancel = Arc::new(AtomicBool::new(false));
+        self.is_exporting = true;
+        self.export_progress = Some(ExportProgress::new(
+            self.current_photo_label(),
+            Arc::clone(&cancel),
+        ));
+        self.status_message = format!("Exporting {}...", self.current_photo_label());
+        let job = FullResExport {
+            buf: Arc::clone(full_res),
+            params: self.edit_params.clone(),
+            crops: export_crop::selected(&self.preferences.export_crops),
+            encoding: self.export_encoding(&options),
+            path,
+            source: self
+                .current_photo()
+                .map(|p| p.file_path.clone())
+                .unwrap_or_default(),
+            plugins: self.preferences.export_plugins.clone(),
+            from_smart_preview: self.editing_smart_preview,
+            annotations: if options.annotations {
+                self.annotations.clone()
+            } else {
+                Vec::new()
+            },
+            photo: self.current_photo().cloned(),
+            metadata: self.preferences.export_metadata.clone(),
+        };
+        // Several crops are written next to the chosen path, not to it.
+        self.export_target = match job.crops.len() {
+            1 => Some(job.path.clone()),
+            _ => job.path.parent().map(Path::to_path_buf),
+        };
+        Task::run(stream_full_res_export(job, cancel), |event| match event {
+            ExportEvent::Progress(fraction) => Message::ExportProgress(fraction),
+            ExportEvent::Finished(outcome) => Message::ExportComplete(outcome),
+        })
+    }
+
+    fn handle_export_complete(&mut self, outcome: ExportOutcome) -> Task<Message> {
+        self.is_exporting = false;
+        self.export_progress = None;
+        let target = self.export_target.take();
+        let notification = match outcome {
+            ExportOutcome::Cancelled => {
+                self.status_message = outcome.message().into();
+                return Task::none();
+            }
+            ExportOutcome::Exported(_) | ExportOutcome::OverTarget(_) => {
+                // A file that missed its size limit is still there to show,
+                // but warns.
+                let over_target = matches!(outcome, ExportOutcome::OverTarget(_));
+                let notification =
+                    Notification::new(Category::Exports, outcome.message()).failed(over_target);
+                match target {
+                    Some(target) => notification.with_action(Action::Reveal(target)),
+                    None => notification,
+                }
+            }
+            ExportOutcome::Failed(msg) => Notification::new(Category::Exports, msg).failed(true),
+        };
+        self.notify(notification);
+        Task::none()
+    }
+
+    /// Post `notification` to the notification center. It replaces the
+    /// status line, which usually still says the work was under way.
+    fn notify(&mut self, notification: Notification) {
+        self.status_message.clear();
+        self.notifications
+            .post(notification, &self.preferences.muted_notifications);
+    }
+
+    fn handle_notification_action(&mut self, action: Action) -> Task<Message> {
+        match action {
+            Action::Reveal(path) => {
+                if let Err(err) = notifications::reveal(&path) {
+                    error!(%err, path = %path.display(), "failed to reveal file");
+                    self.status_message = format!("Could not open the file manager: {err}");
+                }
+            }
+            Action::ReviewQuarantine(_) => {
+                self.reload_quarantine();
+                self.quarantine_open = !self.quarantine.is_empty();
+            }
         }
+        Task::none()
+    }
 
+    fn handle_toggle_photo_selection(&mut self, id: PhotoId) -> Task<Message> {
+        if self.selected_photos.contains(&id) {
+            self.selected_photos.remove(&id);
+        } else {
+            self.selected_photos.insert(id);
+        }
         self.selected_photo = Some(id);
-        self.selected_photos.clear();
         self.update_export_enabled();
+        Task::none()
+    }
 
-        if let Some(photo) = self.photos.iter().find(|photo| photo.id == id) {
-            let name = std::path::Path::new(&photo.file_path)
-                .file_name()
-                .unwrap_or_default()
-                .to_string_lossy()
-                .to_string();
-            self.status_message = format!("Selected {name}. Open Develop to edit.");
+    fn handle_select_photo_range(&mut self, id: PhotoId) -> Task<Message> {
+        let filtered: Vec<PhotoId> = self.filtered_photos().iter().map(|p| p.id).collect();
+
+        let anchor = self.selected_photo.unwrap_or(id);
+        let anchor_idx = filtered.iter().position(|&pid| pid == anchor);
+        let target_idx = filtered.iter().position(|&pid| pid == id);
+
+        if let (Some(start), Some(end)) = (anchor_idx, target_idx) {
+            let lo = start.min(end);
+            let hi = start.max(end);
+            for &pid in &filtered[lo..=hi] {
+                self.selected_photos.insert(pid);
+            }
         }
 
+        self.selected_photo = Some(id);
+        self.update_export_enabled();
         Task::none()
     }
 
-    fn open_photo(&mut self, id: PhotoId) -> Task<Message> {
-        if self.loaded_photo == Some(id) && self.preview_image.is_some() {
-            self.selected_photo = Some(id);
-            self.workspace = Workspace::Develop;
-            self.right_panel_open = true;
-            self.update_export_enabled();
-            return Task::none();
+    fn reload_snapshots(&mut self, photo_id: PhotoId) {
+        self.snapshots = match &self.catalog {
+            Some(catalog) => catalog.list_snapshots(photo_id).unwrap_or_else(|err| {
+                error!(%err, photo_id, "failed to load snapshots");
+                Vec::new()
+            }),
+            None => Vec::new(),
+        };
+    }
+
+    fn reload_annotations(&mut self, photo_id: PhotoId) {
+        self.note_draft = None;
+        self.annotations = match &self.catalog {
+            Some(catalog) => catalog.annotations(photo_id).unwrap_or_else(|err| {
+                error!(%err, photo_id, "failed to load annotations");
+                Vec::new()
+            }),
+            None => Vec::new(),
+        };
+    }
+
+    fn save_annotations(&mut self) {
+        let (Some(id), Some(catalog)) = (self.loaded_photo, &self.catalog) else {
+            return;
+        };
+        if let Err(err) = catalog.save_annotations(id, &self.annotations) {
+            error!(%err, photo_id = id, "failed to save annotations");
+            self.status_message = format!("Failed to save annotations: {err}");
         }
+    }
 
-        self.save_current_edits();
-        self.clear_undo_history();
-        self.zoom_state = ZoomState::default();
-        self.original_display = None;
-        self.showing_before = false;
-        self.crop_mode = false;
+    fn reload_provenance(&mut self, photo_id: PhotoId) {
+        let Some(catalog) = &self.catalog else {
+            self.provenance_sources.clear();
+            self.provenance_derivatives.clear();
+            return;
+        };
+        // Linked photos can be outside the current filter, so they're
+        // named from the catalog rather than `self.photos`.
+        let named = |links: anyhow::Result<Vec<PhotoLink>>| -> Vec<ProvenanceLink> {
+            links
+                .unwrap_or_else(|err| {
+                    error!(%err, photo_id, "failed to load provenance");
+                    Vec::new()
+                })
+                .into_iter()
+                .filter_map(|link| {
+                    let photo = catalog.get_photo(link.photo_id).unwrap_or_else(|err| {
+                        error!(%err, photo_id = link.photo_id, "failed to load linked photo");
+                        None
+                    })?;
+                    Some(ProvenanceLink {
+                        photo_id: link.photo_id,
+                        name: file_name(&photo.file_path),
+                        kind: link.kind,
+                    })
+                })
+                .collect()
+        };
+        self.provenance_sources = named(catalog.sources_of(photo_id));
+        self.provenance_derivatives = named(catalog.derivatives_of(photo_id));
+    }
 
-        self.selected_photo = Some(id);
-        self.workspace = Workspace::Develop;
-        self.right_panel_open = true;
-        self.current_image = None;
-        self.preview_image = None;
-        self.processed_image = None;
-        self.histogram = None;
-        self.current_exif.clear();
-        self.loaded_photo = None;
-        self.is_loading_photo = true;
-        self.is_processing = false;
+    /// Record the selected photo as derived from the rest of the selection.
+    fn handle_link_sources(&mut self, kind: DerivationKind) {
+        self.link_sources_open = false;
+        let sources = self.link_source_ids();
+        let (Some(derived), Some(catalog)) = (self.selected_photo, &self.catalog) else {
+            return;
+        };
+        if sources.is_empty() {
+            return;
+        }
+        let name = self.current_photo_label();
+        match catalog.link_derived(derived, &sources, kind) {
+            Ok(()) => {
+                self.status_message = format!(
+                    "Linked {} source images to {name} ({}).",
+                    sources.len(),
+                    kind.label()
+                );
+            }
+            Err(err) => {
+                error!(%err, derived, "failed to link sources");
+                self.status_message = format!("Couldn't link sources: {err}");
+            }
+        }
+        if let Some(id) = self.loaded_photo {
+            self.reload_provenance(id);
+        }
+    }
 
-        if let Some(ref catalog) = self.catalog {
-            if let Ok(Some(edit)) = catalog.get_edits(id) {
-                self.edit_params = edit.to_edit_params();
-            } else {
-                self.edit_params = EditParams::default();
+    fn handle_take_snapshot(&mut self) {
+        let (Some(id), Some(catalog)) = (self.loaded_photo, &self.catalog) else {
+            return;
+        };
+        let name = format!("Snapshot {}", self.snapshots.len() + 1);
+        match catalog.save_snapshot(id, &name, &self.edit_params) {
+            Ok(snapshot_id) => {
+                self.status_message = format!("Saved {name}.");
+                self.applied_snapshot = Some(snapshot_id);
+                self.reload_snapshots(id);
+            }
+            Err(err) => {
+                error!(%err, "failed to save snapshot");
+                self.status_message = format!("Failed to save snapshot: {err}");
             }
         }
+    }
 
-        self.update_export_enabled();
+    fn handle_save_preset(&mut self) -> Task<Message> {
+        let Some(editor) = self.preset_editor.take() else {
+            return Task::none();
+        };
+        let preset = match editor.to_preset(&self.edit_params) {
+            Ok(preset) => preset,
+            Err(err) => {
+                self.status_message = err;
+                self.preset_editor = Some(editor);
+                return Task::none();
+            }
+        };
+        self.status_message = format!("Saved preset {}.", preset.name);
+        match editor
+            .editing
+            .and_then(|index| self.preferences.presets.get_mut(index))
+        {
+            Some(existing) => *existing = preset,
+            None => self.preferences.presets.push(preset),
+        }
+        self.preferences_changed()
+    }
 
-        let photo = self.photos.iter().find(|p| p.id == id).cloned();
-        let Some(photo) = photo else {
-            self.is_loading_photo = false;
-            self.status_message = "Unable to locate that photo.".into();
+    /// Lay a preset over the current edit. Parameters it doesn't include
+    /// keep their values, so presets stack.
+    fn handle_apply_preset(&mut self, index: usize) -> Task<Message> {
+        let Some(preset) = self.preferences.presets.get(index).cloned() else {
             return Task::none();
         };
+        if self.loaded_photo.is_none() {
+            return Task::none();
+        }
+        self.snapshot_for_undo();
+        preset.apply(&mut self.edit_params);
+        self.status_message = format!("Applied preset {}.", preset.name);
+        self.reprocess_image()
+    }
 
-        let name = std::path::Path::new(&photo.file_path)
-            .file_name()
-            .unwrap_or_default()
-            .to_string_lossy()
-            .to_string();
-        self.status_message = format!("Loading {name}...");
+    fn handle_delete_snapshot(&mut self, snapshot_id: SnapshotId) {
+        let (Some(id), Some(catalog)) = (self.loaded_photo, &self.catalog) else {
+            return;
+        };
+        if let Err(err) = catalog.delete_snapshot(snapshot_id) {
+            error!(%err, "failed to delete snapshot");
+            return;
+        }
+        if self.edit_diff == Some(snapshot_id) {
+            self.edit_diff = None;
+        }
+        if self.applied_snapshot == Some(snapshot_id) {
+            self.applied_snapshot = None;
+        }
+        self.reload_snapshots(id);
+    }
 
-        let path = photo.file_path.clone();
-        Task::perform(
-            async move {
-                let t0 = std::time::Instant::now();
-                let p = std::path::Path::new(&path);
-                let buf = crema_core::raw::load_any(p).ok()?;
-                let preview = buf.downsample(2048);
-                let exif = crema_metadata::exif::ExifData::from_file(p)
-                    .ok()
-                    .map(|e| e.summary_lines())
-                    .unwrap_or_default();
-                info!(
-                    elapsed_ms = t0.elapsed().as_millis(),
-                    w = buf.width,
-                    h = buf.height,
-                    "image loaded"
-                );
-                Some((id, Arc::new(buf), Arc::new(preview), exif))
-            },
-            move |result| match result {
-                Some((id, buf, preview, exif)) => Message::ImageLoaded(id, buf, preview, exif),
-                None => Message::ImageLoadFailed(id),
-            },
-        )
+    /// Replace the current edit with a snapshot's, as an undoable step.
+    fn handle_apply_snapshot(&mut self, snapshot_id: SnapshotId) -> Task<Message> {
+        let Some(snapshot) = self.snapshots.iter().find(|s| s.id == snapshot_id) else {
+            return Task::none();
+        };
+        let params = snapshot.params.clone();
+        self.snapshot_for_undo();
+        self.edit_params = params;
+        self.applied_snapshot = Some(snapshot_id);
+        self.reprocess_image()
     }
 
-    fn handle_set_workspace(&mut self, workspace: Workspace) -> Task<Message> {
-        if workspace == Workspace::Library && self.workspace == Workspace::Develop {
-            self.save_current_edits();
+    /// Resolve one parameter of the edits diff. Taking the left value writes
+    /// the current edit into the snapshot; taking the right value applies the
+    /// snapshot's value to the current edit, which can be undone as usual.
+    fn handle_take_diff_param(&mut self, key: &str, side: DiffSide) -> Task<Message> {
+        let Some(snapshot_id) = self.edit_diff else {
+            return Task::none();
+        };
+        let Some(index) = self.snapshots.iter().position(|s| s.id == snapshot_id) else {
+            return Task::none();
+        };
+
+        match side {
+            DiffSide::Left => {
+                let snapshot = &mut self.snapshots[index];
+                crema_core::edit_diff::take(&mut snapshot.params, &self.edit_params, key);
+                if let Some(catalog) = &self.catalog
+                    && let Err(err) = catalog.update_snapshot(snapshot.id, &snapshot.params)
+                {
+                    error!(%err, "failed to update snapshot");
+                }
+                Task::none()
+            }
+            DiffSide::Right => {
+                self.snapshot_for_undo();
+                let source = self.snapshots[index].params.clone();
+                crema_core::edit_diff::take(&mut self.edit_params, &source, key);
+                self.reprocess_image()
+            }
         }
+    }
 
-        self.workspace = workspace;
-        self.update_export_enabled();
+    /// Photos targeted by batch actions: the multi-selection if there is one,
+    /// otherwise the single selected photo.
+    fn batch_target_ids(&self) -> Vec<PhotoId> {
+        if self.selected_photos.is_empty() {
+            self.selected_photo.into_iter().collect()
+        } else {
+            self.selected_photos.iter().copied().collect()
+        }
+    }
 
-        if workspace == Workspace::Develop {
-            self.right_panel_open = true;
-            if let Some(id) = self.selected_photo {
-                if self.loaded_photo != Some(id) || self.preview_image.is_none() {
-                    return self.open_photo(id);
-                }
-            } else {
-                self.status_message = "Select a photo in Library to open Develop.".into();
+    fn handle_quick_develop(&mut self, adjustment: QuickAdjustment) -> Task<Message> {
+        let ids = self.batch_target_ids();
+        let Some(catalog) = &self.catalog else {
+            return Task::none();
+        };
+        if ids.is_empty() {
+            return Task::none();
+        }
+
+        // The loaded photo's in-memory edit may be ahead of the catalog.
+        self.save_current_edits();
+        let updated = match catalog.adjust_edits(&ids, |params| adjustment.apply(params)) {
+            Ok(updated) => updated,
+            Err(err) => {
+                error!(%err, "quick develop failed");
+                self.status_message = format!("Quick develop failed: {err}");
+                return Task::none();
             }
+        };
+        info!(count = updated.len(), ?adjustment, "applied quick develop");
+        self.status_message = format!(
+            "Applied {} to {} photos.",
+            adjustment.describe(),
+            updated.len()
+        );
+        self.show_adjusted_edits(updated)
+    }
+
+    fn refresh_path_remap_preview(&mut self) {
+        let (Some(form), Some(catalog)) = (&mut self.path_remap, &self.catalog) else {
+            return;
+        };
+        let preview = catalog
+            .plan_path_remap(form.from.trim(), form.to.trim())
+            .unwrap_or_else(|err| {
+                error!(%err, "failed to preview path remap");
+                Vec::new()
+            });
+        let existing: HashSet<&str> = self.photos.iter().map(|p| p.file_path.as_str()).collect();
+        form.set_preview(preview, &existing);
+    }
+
+    fn handle_apply_path_remap(&mut self) -> Task<Message> {
+        let (Some(form), Some(catalog)) = (&self.path_remap, &self.catalog) else {
+            return Task::none();
+        };
+        if !form.can_apply() {
+            return Task::none();
         }
+        self.save_current_edits();
+        let remaps = match catalog.remap_path_prefix(form.from.trim(), form.to.trim()) {
+            Ok(remaps) => remaps,
+            Err(err) => {
+                error!(%err, "path remap failed");
+                self.status_message = format!("Remap failed: {err:#}");
+                return Task::none();
+            }
+        };
+        self.path_remap = None;
+        self.reload_folder_settings();
+        self.status_message = format!("Remapped {} photos; checking files...", remaps.len());
+        Task::batch([
+            self.refresh_photos(),
+            Task::perform(
+                async move {
+                    // Thumbnails are keyed by content, so they follow the
+                    // files without being moved.
+                    let missing = remaps
+                        .iter()
+                        .filter(|remap| !Path::new(&remap.new_path).exists())
+                        .count();
+                    (missing, remaps.len())
+                },
+                |(missing, total)| Message::PathRemapVerified(missing, total),
+            ),
+        ])
+    }
 
-        Task::none()
+    /// Measure the selection so every photo can be brought to the
+    /// brightness of the selected one.
+    fn handle_match_exposure(&mut self) -> Task<Message> {
+        let Some(reference) = self.selected_photo else {
+            return Task::none();
+        };
+        let mut ids = self.batch_target_ids();
+        if !ids.contains(&reference) {
+            ids.push(reference);
+        }
+        let photos: Vec<(PhotoId, String)> = ids
+            .into_iter()
+            .filter_map(|id| {
+                let photo = self.photos.iter().find(|p| p.id == id)?;
+                Some((id, photo.file_path.clone()))
+            })
+            .collect();
+        // Photos filtered out of the grid drop out here too, so count after.
+        if photos.len() < 2 || !photos.iter().any(|(id, _)| *id == reference) {
+            self.status_message = "Select the photos to match along with the reference.".into();
+            return Task::none();
+        }
+        self.status_message = format!(
+            "Matching {} photos to {}...",
+            photos.len() - 1,
+            self.current_photo_label()
+        );
+        Task::perform(
+            async move {
+                photos
+                    .into_iter()
+                    .map(|(id, path)| {
+                        let median = match crema_core::raw::load_any(Path::new(&path)) {
+                            Ok(buf) => Some(crema_core::exposure_match::median_luminance(
+                                &buf.downsample(EXPOSURE_MATCH_EDGE),
+                            )),
+                            Err(err) => {
+                                error!(%err, id, "failed to load photo to match");
+                                None
+                            }
+                        };
+                        (id, median)
+                    })
+                    .collect()
+            },
+            move |medians| Message::ExposureMeasured(reference, medians),
+        )
     }
 
-    fn handle_image_loaded(
+    fn handle_exposure_measured(
         &mut self,
-        id: PhotoId,
-        buf: Arc<ImageBuf>,
-        preview: Arc<ImageBuf>,
-        exif: Vec<(String, String)>,
+        reference: PhotoId,
+        medians: Vec<(PhotoId, Option<f32>)>,
     ) -> Task<Message> {
-        if self.selected_photo != Some(id) {
+        let Some(catalog) = &self.catalog else {
             return Task::none();
-        }
-
-        self.loaded_photo = Some(id);
-        self.current_image = Some(buf);
-        self.preview_image = Some(preview.clone());
-        self.current_exif = exif;
-        self.is_loading_photo = false;
-        self.original_display = None;
-        self.showing_before = false;
-        self.status_message = format!("Rendering {}...", self.current_photo_label());
-        self.update_export_enabled();
+        };
+        let Some(reference_median) = medians
+            .iter()
+            .find(|(id, _)| *id == reference)
+            .and_then(|(_, m)| *m)
+        else {
+            self.status_message = "Couldn't read the reference photo.".into();
+            return Task::none();
+        };
+        self.save_current_edits();
+        let reference_exposure = catalog
+            .get_edits(reference)
+            .ok()
+            .flatten()
+            .map_or(0.0, |e| e.to_edit_params().exposure);
+
+        let total = medians.len() - 1;
+        let mut updated = Vec::new();
+        for (id, median) in medians {
+            if id == reference {
+                continue;
+            }
+            let Some(exposure) = median.and_then(|m| {
+                crema_core::exposure_match::matched_exposure(
+                    reference_median,
+                    reference_exposure,
+                    m,
+                )
+            }) else {
+                continue;
+            };
+            match catalog.adjust_edits(&[id], |params| params.exposure = exposure) {
+                Ok(mut edits) => updated.append(&mut edits),
+                Err(err) => error!(%err, id, "failed to save matched exposure"),
+            }
+        }
+        info!(matched = updated.len(), total, "matched exposure");
+        self.status_message = if updated.len() == total {
+            format!("Matched the exposure of {total} photos.")
+        } else {
+            format!(
+                "Matched the exposure of {} of {total} photos; the rest couldn't be measured.",
+                updated.len()
+            )
+        };
+        self.show_adjusted_edits(updated)
+    }
 
-        let original_task = Task::perform(
+    fn handle_auto_crop_scans(&mut self) -> Task<Message> {
+        let photos: Vec<(PhotoId, String)> = self
+            .batch_target_ids()
+            .into_iter()
+            .filter_map(|id| {
+                let photo = self.photos.iter().find(|p| p.id == id)?;
+                Some((id, photo.file_path.clone()))
+            })
+            .collect();
+        if photos.is_empty() {
+            return Task::none();
+        }
+        self.status_message = format!("Looking for scanner borders in {} photos...", photos.len());
+        Task::perform(
             async move {
-                let rgba = preview.to_rgba_u8_srgb();
-                iced::widget::image::Handle::from_rgba(preview.width, preview.height, rgba)
+                photos
+                    .into_iter()
+                    .map(|(id, path)| {
+                        let crop = match crema_core::raw::load_any(Path::new(&path)) {
+                            Ok(buf) => crema_core::scan_border::detect(&buf),
+                            Err(err) => {
+                                error!(%err, id, "failed to load scan");
+                                None
+                            }
+                        };
+                        (id, crop)
+                    })
+                    .collect()
             },
-            Message::OriginalReady,
-        );
-
-        Task::batch([self.reprocess_image(), original_task])
+            Message::ScansAnalyzed,
+        )
     }
 
-    fn handle_image_processed(
+    fn handle_scans_analyzed(
         &mut self,
-        generation: u64,
-        handle: iced::widget::image::Handle,
-        hist: Box<HistogramData>,
+        results: Vec<(PhotoId, Option<ScanCrop>)>,
     ) -> Task<Message> {
-        if generation != self.processing_generation {
+        let Some(catalog) = &self.catalog else {
             return Task::none();
+        };
+        self.save_current_edits();
+        let total = results.len();
+        let mut updated = Vec::new();
+        for (id, crop) in results {
+            let Some(crop) = crop else {
+                continue;
+            };
+            match catalog.adjust_edits(&[id], |params| crop.apply(params)) {
+                Ok(mut edits) => updated.append(&mut edits),
+                Err(err) => error!(%err, id, "failed to save scan crop"),
+            }
         }
+        info!(cropped = updated.len(), total, "auto-cropped scans");
+        self.status_message = if updated.len() == total {
+            format!("Cropped the scanner border from {total} photos.")
+        } else {
+            format!(
+                "Cropped the scanner border from {} of {total} photos; no border found in the rest.",
+                updated.len()
+            )
+        };
+        self.show_adjusted_edits(updated)
+    }
 
-        self.processed_image = Some(handle);
-        self.histogram = Some(hist);
-        self.is_processing = false;
-        if let Some(ref preview) = self.preview_image {
-            self.preview_dimensions = (preview.width, preview.height);
+    /// Pick up edits just written to the catalog: reload the open photo if
+    /// it's among them and refresh each thumbnail.
+    fn show_adjusted_edits(&mut self, updated: Vec<(PhotoId, EditParams)>) -> Task<Message> {
+        let mut tasks = Vec::with_capacity(updated.len() + 1);
+        for (id, params) in updated {
+            if self.loaded_photo == Some(id) {
+                self.snapshot_for_undo();
+                self.edit_params = params.clone();
+                tasks.push(self.reprocess_image());
+            }
+            let Some(photo) = self.photos.iter().find(|p| p.id == id) else {
+                continue;
+            };
+            let path = photo.file_path.clone();
+            let key_frame = self.key_frame(id);
+            tasks.push(Task::perform(
+                async move {
+                    crema_thumbnails::generator::edited_thumbnail(
+                        Path::new(&path),
+                        key_frame,
+                        &params,
+                    )
+                },
+                move |result| match result {
+                    Ok(bytes) => Message::ThumbnailReady(id, bytes),
+                    Err(err) => {
+                        error!(%err, id, "failed to refresh thumbnail");
+                        Message::Noop
+                    }
+                },
+            ));
         }
-        self.status_message = format!("Ready to edit {}", self.current_photo_label());
-        self.save_current_edits();
-        Task::none()
+        Task::batch(tasks)
     }
 
-    fn handle_image_load_failed(&mut self, id: PhotoId) -> Task<Message> {
-        if self.selected_photo == Some(id) {
-            self.is_loading_photo = false;
-            self.is_processing = false;
-            self.status_message = "Unable to load that photo.".into();
+    fn handle_open_batch_metadata(&mut self) -> Task<Message> {
+        let ids = self.batch_target_ids();
+        let photos: Vec<&Photo> = self.photos.iter().filter(|p| ids.contains(&p.id)).collect();
+        if !photos.is_empty() {
+            self.batch_metadata = Some(BatchMetadataForm::from_photos(&photos));
         }
         Task::none()
     }
 
-    fn handle_export(&self) -> Task<Message> {
-        let default_name = self.default_export_filename();
-        Task::perform(
-            async move {
-                let dialog = rfd::AsyncFileDialog::new()
-                    .set_title("Export photo")
-                    .set_file_name(&default_name)
-                    .add_filter("JPEG", &["jpg", "jpeg"])
-                    .add_filter("PNG", &["png"])
-                    .add_filter("TIFF", &["tiff", "tif"]);
-                dialog.save_file().await.map(|h| h.path().to_path_buf())
-            },
-            |result| match result {
-                Some(path) => Message::ExportPathSelected(path),
-                None => Message::Noop,
-            },
-        )
-    }
-
-    fn handle_save_sidecar(&mut self) -> Task<Message> {
-        let Some(photo) = self.current_photo().cloned() else {
+    fn handle_apply_batch_metadata(&mut self) -> Task<Message> {
+        let Some(form) = self.batch_metadata.take() else {
             return Task::none();
         };
-        let sidecar = sidecar_path(&photo.file_path);
-        match serde_json::to_string_pretty(&self.edit_params) {
-            Ok(json) => match std::fs::write(&sidecar, json) {
-                Ok(()) => {
-                    let name = sidecar
-                        .file_name()
-                        .unwrap_or_default()
-                        .to_string_lossy()
-                        .to_string();
-                    self.status_message = format!("Saved sidecar to {name}");
-                }
-                Err(err) => {
-                    error!(%err, "failed to write sidecar");
-                    self.status_message = format!("Failed to save sidecar: {err}");
-                }
-            },
+        let update = match form.to_update() {
+            Ok(update) => update,
             Err(err) => {
-                error!(%err, "failed to serialize edits");
-                self.status_message = format!("Failed to save sidecar: {err}");
+                self.status_message = err;
+                self.batch_metadata = Some(form);
+                return Task::none();
+            }
+        };
+        let Some(catalog) = &self.catalog else {
+            return Task::none();
+        };
+        match catalog.update_metadata(&form.ids, &update) {
+            Ok(count) => {
+                info!(count, "updated photo metadata");
+                self.status_message = format!("Updated metadata for {count} photos.");
+                self.refresh_photos()
+            }
+            Err(err) => {
+                error!(%err, "failed to update metadata");
+                self.status_message = format!("Failed to update metadata: {err}");
+                Task::none()
             }
         }
-        Task::none()
     }
 
-    fn handle_load_sidecar(&mut self) -> Task<Message> {
-        let Some(photo) = self.current_photo().cloned() else {
+    fn handle_open_gear_override(&mut self) -> Task<Message> {
+        let ids = self.batch_target_ids();
+        let Some(catalog) = &self.catalog else {
             return Task::none();
         };
-        let sidecar = sidecar_path(&photo.file_path);
-        let data = match std::fs::read_to_string(&sidecar) {
-            Ok(d) => d,
+        let Some(&first) = ids.first() else {
+            return Task::none();
+        };
+        // The library holds overridden values; the dialog shows the EXIF.
+        let original = match catalog.get_photo(first) {
+            Ok(Some(photo)) => photo,
+            Ok(None) => return Task::none(),
             Err(err) => {
-                let name = sidecar
-                    .file_name()
-                    .unwrap_or_default()
-                    .to_string_lossy()
-                    .to_string();
-                self.status_message = format!("No sidecar found ({name}): {err}");
+                error!(%err, "failed to load photo");
                 return Task::none();
             }
         };
-        match serde_json::from_str::<EditParams>(&data) {
-            Ok(params) => {
-                self.snapshot_for_undo();
-                self.edit_params = params;
-                self.save_current_edits();
-                let name = sidecar
-                    .file_name()
-                    .unwrap_or_default()
-                    .to_string_lossy()
-                    .to_string();
-                self.status_message = format!("Loaded sidecar from {name}");
-                self.reprocess_image()
-            }
-            Err(err) => {
-                error!(%err, "failed to parse sidecar");
-                self.status_message = format!("Failed to load sidecar: {err}");
-                Task::none()
+        let existing = catalog.metadata_override(first).unwrap_or_else(|err| {
+            error!(%err, "failed to load metadata override");
+            None
+        });
+        let mut shoot = self
+            .photos
+            .iter()
+            .find(|p| p.id == first)
+            .map(|anchor| gear_override::shoot_of(&self.photos, anchor))
+            .unwrap_or_default();
+        for &id in &ids {
+            if !shoot.contains(&id) {
+                shoot.push(id);
             }
         }
+        self.gear_override = Some(GearOverrideForm::new(ids, shoot, &original, existing));
+        Task::none()
     }
 
-    fn handle_export_path_selected(&mut self, path: PathBuf) -> Task<Message> {
-        let Some(ref full_res) = self.current_image else {
+    /// Write the dialog's override to its photos, or remove theirs when
+    /// `clear` is set. Corrections and the library pick it up on reload.
+    fn handle_apply_gear_override(&mut self, clear: bool) -> Task<Message> {
+        let Some(form) = self.gear_override.take() else {
+            return Task::none();
+        };
+        let fixup = if clear {
+            MetadataOverride::default()
+        } else {
+            match form.to_override() {
+                Ok(fixup) => fixup,
+                Err(err) => {
+                    self.status_message = err;
+                    self.gear_override = Some(form);
+                    return Task::none();
+                }
+            }
+        };
+        let Some(catalog) = &self.catalog else {
             return Task::none();
         };
+        let targets = form.targets();
+        if let Err(err) = catalog.set_metadata_override(targets, &fixup) {
+            error!(%err, "failed to save metadata override");
+            self.status_message = format!("Failed to save camera and lens: {err}");
+            return Task::none();
+        }
+        info!(count = targets.len(), clear, "updated metadata overrides");
+        let count = targets.len();
+        let plural = if count == 1 { "" } else { "s" };
+        self.status_message = if clear {
+            format!("Cleared camera and lens override on {count} photo{plural}.")
+        } else {
+            format!("Set camera and lens on {count} photo{plural}.")
+        };
+        self.reload_calibration();
+        self.refresh_photos()
+    }
 
-        self.is_exporting = true;
-        self.status_message = format!("Exporting {}...", self.current_photo_label());
-        let buf = ImageBuf::clone(full_res);
-        let params = self.edit_params.clone();
+    /// Propose stacks for every photo not already in one. Exposure biases
+    /// aren't in the catalog, so they're read from the files first.
+    fn handle_open_auto_stack(&mut self) -> Task<Message> {
+        let candidates: Vec<Photo> = self
+            .photos
+            .iter()
+            .filter(|p| !self.stacks.contains_key(&p.id) && !self.stacked_under.contains_key(&p.id))
+            .cloned()
+            .collect();
+        let review = AutoStackReview::new(candidates);
+        let paths = review.candidate_paths();
+        self.auto_stack = Some(review);
         Task::perform(
-            async move { export_image(buf, &params, &path) },
-            Message::ExportComplete,
+            async move {
+                paths
+                    .into_iter()
+                    .filter_map(|(id, path)| {
+                        let exif = ExifData::from_file(Path::new(&path)).ok()?;
+                        Some((id, exif.exposure_bias?))
+                    })
+                    .collect()
+            },
+            Message::AutoStackScanned,
         )
     }
 
-    fn handle_export_complete(&mut self, msg: String) -> Task<Message> {
-        self.is_exporting = false;
-        self.status_message = msg;
-        Task::none()
+    fn auto_stack_rules_changed(&mut self) -> Task<Message> {
+        if let Some(review) = &mut self.auto_stack {
+            review.update(&self.preferences.auto_stack);
+        }
+        self.preferences_changed()
     }
 
-    fn handle_toggle_photo_selection(&mut self, id: PhotoId) -> Task<Message> {
-        if self.selected_photos.contains(&id) {
-            self.selected_photos.remove(&id);
-        } else {
-            self.selected_photos.insert(id);
+    fn handle_commit_auto_stack(&mut self) -> Task<Message> {
+        let Some(review) = self.auto_stack.take() else {
+            return Task::none();
+        };
+        let Some(catalog) = &self.catalog else {
+            return Task::none();
+        };
+        let (mut stacks, mut photos) = (0, 0);
+        for stack in review.accepted() {
+            match catalog.create_stack(stack.kind, &stack.members) {
+                Ok(_) => {
+                    stacks += 1;
+                    photos += stack.members.len();
+                }
+                Err(err) => error!(%err, "failed to create stack"),
+            }
         }
-        self.selected_photo = Some(id);
-        self.update_export_enabled();
+        info!(stacks, photos, "auto-stacked library");
+        self.status_message = format!(
+            "Stacked {photos} photos into {stacks} stack{}.",
+            if stacks == 1 { "" } else { "s" }
+        );
+        self.reload_stacks();
         Task::none()
     }
 
-    fn handle_select_photo_range(&mut self, id: PhotoId) -> Task<Message> {
-        let filtered: Vec<PhotoId> = self.filtered_photos().iter().map(|p| p.id).collect();
-
-        let anchor = self.selected_photo.unwrap_or(id);
-        let anchor_idx = filtered.iter().position(|&pid| pid == anchor);
-        let target_idx = filtered.iter().position(|&pid| pid == id);
-
-        if let (Some(start), Some(end)) = (anchor_idx, target_idx) {
-            let lo = start.min(end);
-            let hi = start.max(end);
-            for &pid in &filtered[lo..=hi] {
-                self.selected_photos.insert(pid);
-            }
+    fn handle_unstack(&mut self, cover: PhotoId) -> Task<Message> {
+        let (Some(catalog), Some(stack)) = (&self.catalog, self.stacks.get(&cover)) else {
+            return Task::none();
+        };
+        if let Err(err) = catalog.unstack(stack.id) {
+            error!(%err, "failed to unstack");
+            self.status_message = format!("Failed to unstack: {err}");
+            return Task::none();
         }
-
-        self.selected_photo = Some(id);
-        self.update_export_enabled();
+        self.status_message = format!("Unstacked {} photos.", stack.members.len());
+        self.reload_stacks();
         Task::none()
     }
 
-    fn handle_batch_export(&self) -> Task<Message> {
+    fn handle_batch_export(&self, options: ExportOptions) -> Task<Message> {
         Task::perform(
             async {
                 let dialog = rfd::AsyncFileDialog::new().set_title("Choose export folder");
                 dialog.pick_folder().await.map(|h| h.path().to_path_buf())
             },
-            |result| match result {
-                Some(folder) => Message::BatchExportFolderSelected(folder),
+            move |result| match result {
+                Some(folder) => Message::BatchExportFolderSelected(folder, options),
                 None => Message::Noop,
             },
         )
     }
 
-    fn handle_batch_export_folder_selected(&mut self, folder: PathBuf) -> Task<Message> {
-        let ids: Vec<PhotoId> = self.selected_photos.iter().copied().collect();
-        if ids.is_empty() {
-            return Task::none();
+    /// Check a batch export before starting it, holding it in the warnings
+    /// dialog if any photo is worth one.
+    fn handle_batch_export_folder_selected(
+        &mut self,
+        folder: PathBuf,
+        options: ExportOptions,
+    ) -> Task<Message> {
+        let photo_data = self.batch_export_sources();
+        let warnings = export_check::check_batch(
+            photo_data.iter().map(|(_, params)| params),
+            &self.preferences.suppressed_export_warnings,
+        );
+        if warnings.is_empty() {
+            self.start_batch_export(folder, options)
+        } else {
+            self.export_review = Some(ExportReview::new(folder, options, warnings));
+            Task::none()
         }
+    }
+
+    /// The edit `id` has in the catalog, or had on the day browsed in the
+    /// time machine.
+    fn saved_edits(&self, id: PhotoId) -> EditParams {
+        if let Some(machine) = &self.time_machine {
+            return machine
+                .then
+                .get(&id)
+                .map(|state| state.params.clone())
+                .unwrap_or_default();
+        }
+        self.catalog
+            .as_ref()
+            .and_then(|cat| cat.get_edits(id).ok().flatten())
+            .map(|e| e.to_edit_params())
+            .unwrap_or_default()
+    }
 
-        let photo_data: Vec<(String, EditParams)> = ids
+    /// Source path and saved edit of every selected photo.
+    fn batch_export_sources(&self) -> Vec<(String, EditParams)> {
+        self.selected_photos
             .iter()
             .filter_map(|&id| {
                 let photo = self.photos.iter().find(|p| p.id == id)?;
-                let params = self
-                    .catalog
-                    .as_ref()
-                    .and_then(|cat| cat.get_edits(id).ok().flatten())
-                    .map(|e| e.to_edit_params())
-                    .unwrap_or_default();
-                Some((photo.file_path.clone(), params))
+                Some((photo.file_path.clone(), self.saved_edits(id)))
             })
-            .collect();
+            .collect()
+    }
+
+    fn start_batch_export(&mut self, folder: PathBuf, options: ExportOptions) -> Task<Message> {
+        let photo_data = self.batch_export_sources();
+        if photo_data.is_empty() {
+            return Task::none();
+        }
+
+        let crops = export_crop::selected(&self.preferences.export_crops);
+        let mut jobs = render_farm::plan(&photo_data, &crops, &folder);
+        self.batch_export_folder = Some(folder);
+        for job in &mut jobs {
+            let photo = self.photos.iter().find(|p| p.file_path == job.source);
+            job.smart_preview = photo
+                .and_then(|p| self.offline_smart_preview(p))
+                .map(|p| p.path.clone());
+            job.key_frame = photo.map_or(0, |p| self.key_frame(p.id));
+            job.target_size = options.target_size;
+            if let Some(photo) = photo {
+                for output in &mut job.outputs {
+                    output.metadata = self.preferences.export_metadata.render(output.crop, photo);
+                }
+            }
+            if options.annotations
+                && let (Some(photo), Some(catalog)) = (photo, &self.catalog)
+            {
+                job.annotations = catalog.annotations(photo.id).unwrap_or_else(|err| {
+                    error!(%err, photo_id = photo.id, "failed to load annotations");
+                    Vec::new()
+                });
+            }
+        }
+
+        // Sources on an unplugged drive, with no smart preview to stand in,
+        // wait for the drive rather than failing.
+        let (held, jobs): (Vec<_>, Vec<_>) = jobs.into_iter().partition(|job| {
+            job.smart_preview.is_none() && volumes::is_offline(Path::new(&job.source))
+        });
+        let held_count = held.len();
+        self.held_exports.extend(held);
+        let task = if jobs.is_empty() {
+            Task::none()
+        } else {
+            self.run_export_jobs(jobs)
+        };
+        if held_count > 0 {
+            let note = format!(
+                "{held_count} photo{} will export when {} drive is reconnected.",
+                if held_count == 1 { "" } else { "s" },
+                if held_count == 1 { "its" } else { "their" }
+            );
+            self.status_message = if self.is_exporting {
+                format!("{} {note}", self.status_message)
+            } else {
+                note
+            };
+            self.count_waiting_exports();
+        }
+        task
+    }
 
-        let total = photo_data.len();
+    /// Export planned jobs, across worker processes when there are enough
+    /// of them.
+    fn run_export_jobs(&mut self, jobs: Vec<render_farm::ExportJob>) -> Task<Message> {
+        self.batch_smart_previews = jobs.iter().filter(|j| j.smart_preview.is_some()).count();
+        let total: usize = jobs.iter().map(|j| j.outputs.len()).sum();
         self.is_exporting = true;
         self.status_message = format!("Exporting 0/{total}...");
 
+        let workers = self.preferences.export_workers;
+        if workers > 1 && jobs.len() > 1 {
+            let manifest = render_farm::Manifest {
+                catalog: self.catalog_path.clone(),
+                dark_frame_subtraction: self.preferences.dark_frame_subtraction,
+                dither: self.preferences.export_dither,
+                jobs,
+                plugins: self.preferences.export_plugins.clone(),
+            };
+            return Task::run(stream_render_farm(manifest, workers), |event| match event {
+                FarmEvent::Progress(done, total) => Message::BatchExportProgress(done, total),
+                FarmEvent::Finished(result) => Message::RenderFarmFinished(result),
+            });
+        }
+
+        let calibration = self.calibration.clone();
+        let encoding = ExportEncoding {
+            dither: self.preferences.export_dither,
+            ..ExportEncoding::default()
+        };
+        let plugins = self.preferences.export_plugins.clone();
         Task::perform(
             async move {
                 let mut success_count = 0usize;
                 let mut skipped_count = 0usize;
-                let mut used_paths = std::collections::HashSet::new();
-                for (file_path, params) in &photo_data {
-                    let path = std::path::Path::new(file_path);
-                    let stem = path
-                        .file_stem()
-                        .unwrap_or_default()
-                        .to_string_lossy()
-                        .to_string();
-                    let output_path = unique_export_path(&folder, &stem, "jpg", &mut used_paths);
-
-                    let buf = match crema_core::raw::load_any(path) {
-                        Ok(buf) => buf,
-                        Err(e) => {
-                            error!("Failed to load {}: {e}", file_path);
-                            continue;
-                        }
-                    };
-
-                    let result = export_image(buf, params, &output_path);
-                    if result.starts_with("Exported") {
-                        success_count += 1;
-                        if output_path
-                            .file_stem()
-                            .and_then(|name| name.to_str())
-                            .is_some_and(|name| name != stem)
-                        {
-                            skipped_count += 1;
+                let mut plugin_failures = Vec::new();
+                let mut fitted = Vec::new();
+                for job in &jobs {
+                    let results = render_farm::run_job(job, &calibration, encoding);
+                    for (output, result) in job.outputs.iter().zip(results) {
+                        match result {
+                            Ok(quality) => {
+                                success_count += 1;
+                                skipped_count += usize::from(output.renamed);
+                                plugin_failures
+                                    .extend(render_farm::post_process(job, output, &plugins));
+                                fitted.extend(
+                                    quality.map(|q| (render_farm::file_name(&output.path), q)),
+                                );
+                            }
+                            Err(err) => error!("{}: {err}", job.source),
                         }
-                    } else {
-                        error!("{result}");
                     }
                 }
-                (success_count, skipped_count, total)
+                (success_count, skipped_count, total, plugin_failures, fitted)
             },
-            |(success, skipped, total)| Message::BatchExportComplete(success, skipped, total),
+            |(success, skipped, total, plugin_failures, fitted)| {
+                Message::BatchExportComplete(success, skipped, total, plugin_failures, fitted)
+            },
+        )
+    }
+
+    fn handle_batch_export_complete(
+        &mut self,
+        success: usize,
+        skipped: usize,
+        total: usize,
+        plugin_failures: Vec<String>,
+        failed: usize,
+        fitted: Vec<(String, FittedQuality)>,
+    ) -> Task<Message> {
+        self.is_exporting = false;
+        let mut summary = if skipped > 0 {
+            format!("Exported {success}/{total} photos ({skipped} renamed to avoid conflicts).")
+        } else {
+            format!("Exported {success}/{total} photos.")
+        };
+        if self.batch_smart_previews > 0 {
+            summary.push_str(&format!(
+                " {} from smart previews; their originals are offline.",
+                self.batch_smart_previews
+            ));
+            self.batch_smart_previews = 0;
+        }
+        if !plugin_failures.is_empty() {
+            for failure in &plugin_failures {
+                error!("export post-processor: {failure}");
+            }
+            summary.push_str(&format!(
+                " {} post-processor run{} failed; see the log for details.",
+                plugin_failures.len(),
+                if plugin_failures.len() == 1 { "" } else { "s" }
+            ));
+        }
+        if failed > 0 {
+            summary.push_str(&format!(" {failed} failed; see the log for details."));
+        }
+        summary.push_str(&fitted_summary(&fitted));
+        let mut notification = Notification::new(Category::Exports, summary).failed(
+            success < total
+                || !plugin_failures.is_empty()
+                || fitted.iter().any(|(_, f)| f.over_target),
+        );
+        if success > 0
+            && let Some(folder) = self.batch_export_folder.clone()
+        {
+            notification = notification.with_action(Action::Reveal(folder));
+        }
+        self.notify(notification);
+        Task::none()
+    }
+
+    fn handle_render_farm_finished(
+        &mut self,
+        result: Result<render_farm::FarmReport, String>,
+    ) -> Task<Message> {
+        let report = match result {
+            Ok(report) => report,
+            Err(err) => {
+                self.is_exporting = false;
+                error!(%err, "multi-process export failed");
+                self.notify(
+                    Notification::new(Category::Exports, format!("Export failed: {err}"))
+                        .failed(true),
+                );
+                return Task::none();
+            }
+        };
+        for failure in &report.failures {
+            error!("{failure}");
+        }
+        self.handle_batch_export_complete(
+            report.exported,
+            report.renamed,
+            report.total,
+            report.plugin_failures,
+            report.failures.len(),
+            report.fitted,
         )
     }
 
-    fn handle_batch_export_complete(
-        &mut self,
-        success: usize,
-        skipped: usize,
-        total: usize,
-    ) -> Task<Message> {
-        self.is_exporting = false;
-        self.status_message = if skipped > 0 {
-            format!("Exported {success}/{total} photos ({skipped} renamed to avoid conflicts).")
-        } else {
-            format!("Exported {success}/{total} photos.")
-        };
+    fn preferences_changed(&mut self) -> Task<Message> {
+        self.theme = crate::theme::app_theme(&self.preferences);
+        if self.demo {
+            return Task::none();
+        }
+        if let Err(err) = self.preferences.save() {
+            error!(%err, "failed to save preferences");
+            self.status_message = format!("Failed to save preferences: {err}");
+        }
         Task::none()
     }
 
@@ -1173,8 +4640,43 @@ impl App {
     }
 
     pub fn subscription(&self) -> iced::Subscription<Message> {
+        let volumes = if self.volumes.is_empty() {
+            iced::Subscription::none()
+        } else {
+            iced::time::every(volumes::POLL_INTERVAL).map(|_| Message::CheckVolumes)
+        };
+        let toasts = if self.notifications.expiring() {
+            iced::time::every(notifications::EXPIRY_TICK).map(|_| Message::ExpireToasts)
+        } else {
+            iced::Subscription::none()
+        };
+        // Shortcuts like Backspace-to-delete must not fire behind a dialog,
+        // or while a note is being typed.
+        if self.batch_metadata.is_some()
+            || self.gear_override.is_some()
+            || self.preset_editor.is_some()
+            || self.auto_stack.is_some()
+            || self.edit_diff.is_some()
+            || self.animation_export_open
+            || self.comparison_export.is_some()
+            || self.export_options.is_some()
+            || self.export_review.is_some()
+            || self.quarantine_open
+            || self.stack_open
+            || self.link_sources_open
+            || self.render_consistency.is_some()
+            || self.path_remap.is_some()
+            || self.gpu_notice_open
+            || self.gpu_diagnostics_open
+            || self.time_machine_dates.is_some()
+            || self.note_draft.is_some()
+        {
+            return iced::Subscription::batch([crate::menu::subscription(), volumes, toasts]);
+        }
         iced::Subscription::batch([
             crate::menu::subscription(),
+            volumes,
+            toasts,
             iced::keyboard::listen().map(|event| match event {
                 iced::keyboard::Event::KeyPressed { key, modifiers, .. } => {
                     handle_key_press(key, modifiers).unwrap_or(Message::ModifiersChanged(modifiers))
@@ -1193,17 +4695,68 @@ impl App {
         views::unified::view(self)
     }
 
-    fn refresh_photos(&self) -> Task<Message> {
-        let catalog_path = self.catalog_path.clone().unwrap_or_default();
-        Task::perform(
-            async move {
-                let catalog = Catalog::open(&catalog_path).ok();
-                catalog
-                    .map(|c| c.list_photos().unwrap_or_default())
-                    .unwrap_or_default()
-            },
-            Message::PhotosListed,
-        )
+    fn refresh_photos(&mut self) -> Task<Message> {
+        let Some(catalog_path) = self.catalog_path.clone() else {
+            return Task::none();
+        };
+        self.photo_load_generation += 1;
+        let generation = self.photo_load_generation;
+        self.catalog_load = Some(CatalogLoad {
+            loaded: 0,
+            total: 0,
+            progressive: self.photos.is_empty(),
+            buffer: Vec::new(),
+        });
+        Task::run(stream_photo_pages(catalog_path), move |page| {
+            Message::PhotoPageLoaded(generation, page)
+        })
+    }
+
+    fn handle_photo_page_loaded(&mut self, generation: u64, page: PhotoPage) -> Task<Message> {
+        if generation != self.photo_load_generation {
+            return Task::none();
+        }
+        let Some(load) = &mut self.catalog_load else {
+            return Task::none();
+        };
+
+        load.loaded += page.photos.len();
+        load.total = page.total;
+        if page.done {
+            let load = self.catalog_load.take().expect("checked above");
+            if let Some(err) = page.error {
+                // Keep what's shown rather than swap in a partial list.
+                self.status_message = if load.progressive && !self.photos.is_empty() {
+                    format!(
+                        "Only {} of {} photos could be loaded: {err}",
+                        self.photos.len(),
+                        load.total
+                    )
+                } else {
+                    format!("Couldn't load the photo list: {err}")
+                };
+                return Task::none();
+            }
+            let photos = if load.progressive {
+                std::mem::take(&mut self.photos)
+            } else {
+                load.buffer
+            };
+            return self.handle_photos_listed(photos);
+        }
+
+        self.status_message = format!("{} of {} photos loaded", load.loaded, load.total);
+        if !load.progressive {
+            load.buffer.extend(page.photos);
+            return Task::none();
+        }
+        let first_page = self.photos.is_empty();
+        self.photos.extend(page.photos);
+        if first_page {
+            self.load_next_thumbnail_batch()
+        } else {
+            Task::none()
+        }
     }
 
     fn reprocess_image(&mut self) -> Task<Message> {
@@ -1229,27 +4782,11 @@ impl App {
 
         Task::perform(
             async move {
-                let gpu_result = gpu.and_then(|g| process_gpu(&g, &buf, &params));
-
-                let processed = gpu_result.or_else(|| {
-                    let pipeline = crema_core::pipeline::Pipeline::new();
-                    let owned = ImageBuf::clone(&buf);
-                    pipeline.process_cpu(owned, &params).ok()
-                });
-
-                let (w, h, rgba) = match processed {
-                    Some(img) => {
-                        let rgba = img.to_rgba_u8_srgb();
-                        (img.width, img.height, rgba)
-                    }
-                    None => {
-                        let rgba = buf.to_rgba_u8_srgb();
-                        (buf.width, buf.height, rgba)
-                    }
-                };
-                let histogram = crate::widgets::histogram::compute_histogram(&rgba);
-                let handle = iced::widget::image::Handle::from_rgba(w, h, rgba);
-                (generation, handle, histogram)
+                let developed = gpu.and_then(|g| process_gpu(&g, &buf, &params));
+                let shown = preview::display(&buf, &params, developed);
+                let handle =
+                    iced::widget::image::Handle::from_rgba(shown.width, shown.height, shown.rgba);
+                (generation, handle, shown.histogram)
             },
             |(generation, handle, histogram)| {
                 Message::ImageProcessed(generation, handle, Box::new(histogram))
@@ -1263,22 +4800,31 @@ impl App {
         let tasks: Vec<_> = self
             .photos
             .iter()
-            .filter(|p| !self.thumbnails.contains_key(&p.id))
+            .filter(|p| {
+                !self.thumbnails.contains_key(&p.id) && !self.thumbnail_failures.contains(&p.id)
+            })
             .take(THUMBNAIL_BATCH_SIZE)
             .map(|p| {
                 let id = p.id;
                 let path = p.file_path.clone();
+                let file_hash = p.file_hash.clone();
+                let key_frame = self.key_frame(id);
+                let edits = self.thumbnail_edits(p);
                 let cache_dir = cache_dir.clone();
                 Task::perform(
                     async move {
-                        match load_thumbnail_bytes(&path, cache_dir.as_deref()) {
-                            Ok(bytes) => Some((id, bytes)),
-                            Err(_) => None,
-                        }
+                        load_thumbnail_bytes(
+                            id,
+                            &path,
+                            &file_hash,
+                            key_frame,
+                            edits.as_ref(),
+                            cache_dir.as_deref(),
+                        )
                     },
-                    |result| match result {
-                        Some((id, bytes)) => Message::ThumbnailReady(id, bytes),
-                        None => Message::Noop,
+                    move |result| match result {
+                        Ok(bytes) => Message::ThumbnailReady(id, bytes),
+                        Err(err) => Message::ThumbnailFailed(id, format!("{err:#}")),
                     },
                 )
             })
@@ -1300,8 +4846,12 @@ impl App {
                 && self.current_image.is_some()
                 && self.loaded_photo == self.selected_photo;
             menu.export_item.set_enabled(enabled);
+            menu.export_animation_item.set_enabled(enabled);
+            menu.export_comparison_item.set_enabled(enabled);
             menu.save_sidecar_item.set_enabled(enabled);
             menu.load_sidecar_item.set_enabled(enabled);
+            menu.audit_precision_item.set_enabled(enabled);
+            menu.verify_render_item.set_enabled(enabled);
         }
     }
 
@@ -1472,6 +5022,36 @@ impl App {
         }
     }
 
+    fn handle_toggle_actual_size(&mut self, cx: f32, cy: f32, vw: f32, vh: f32) {
+        if !self.zoom_state.is_fit() {
+            self.zoom_state = ZoomState::default();
+            return;
+        }
+        let (pw, ph) = self.preview_dimensions;
+        if pw == 0 || ph == 0 {
+            return;
+        }
+        // The preview is a downscaled copy; 100% means one pixel of the original.
+        let scale = self.original_scale();
+        let image = iced::Size::new(pw as f32 * scale, ph as f32 * scale);
+        let target = actual_size_zoom(image, iced::Size::new(vw, vh));
+        self.handle_zoom_at_point(target / self.zoom_state.zoom, cx, cy, vw, vh);
+    }
+
+    /// How many original pixels each preview pixel stands for, from the
+    /// longer sides so rotation doesn't matter.
+    fn original_scale(&self) -> f32 {
+        let original = self
+            .current_photo()
+            .and_then(|photo| photo.width.zip(photo.height))
+            .map(|(w, h)| w.max(h));
+        let preview = self.preview_image.as_ref().map(|p| p.width.max(p.height));
+        match (original, preview) {
+            (Some(original), Some(preview)) if preview > 0 => original as f32 / preview as f32,
+            _ => 1.0,
+        }
+    }
+
     fn navigate_photo(&mut self, delta: i32) -> Task<Message> {
         let filtered = self.filtered_photos();
         if filtered.is_empty() {
@@ -1548,6 +5128,73 @@ impl App {
         self.edit_params.crop_h = new_h;
     }
 
+    fn handle_browse_as_of(&mut self, date: String) -> Task<Message> {
+        self.time_machine_dates = None;
+        let Some(catalog) = &self.catalog else {
+            return Task::none();
+        };
+        let states = catalog
+            .local_to_utc(&crate::widgets::time_machine::end_of_day(&date))
+            .and_then(|end| catalog.library_as_of(&end))
+            .and_then(|then| Ok((then, catalog.library_as_of("9999-12-31 23:59:59")?)));
+        match states {
+            Ok((then, now)) => {
+                let machine = TimeMachine::new(date, &self.photos, then, now);
+                self.status_message = format!(
+                    "Browsing {} photos as of {}",
+                    machine.photos.len(),
+                    machine.date
+                );
+                self.workspace = Workspace::Library;
+                self.time_machine = Some(machine);
+                return self.refresh_sidebar_counts();
+            }
+            Err(err) => {
+                error!(%err, "failed to reconstruct the library");
+                self.status_message = format!("Couldn't browse {date}: {err}");
+            }
+        }
+        Task::none()
+    }
+
+    /// Put the selected photos' ratings and edits back to how they were on
+    /// the browsed date. The restore is itself recorded, so it can be
+    /// undone the same way.
+    fn handle_restore_from_time_machine(&mut self) -> Task<Message> {
+        let ids = self.batch_target_ids();
+        let (Some(machine), Some(catalog)) = (&mut self.time_machine, &self.catalog) else {
+            return Task::none();
+        };
+        let mut restored = 0;
+        for &id in &ids {
+            let Some(then) = machine.then.get(&id) else {
+                continue;
+            };
+            if let Err(err) = catalog
+                .save_edits(id, &then.params)
+                .and_then(|()| catalog.set_rating(id, then.rating))
+            {
+                error!(%err, "failed to restore photo");
+                continue;
+            }
+            if let Some(photo) = self.photos.iter_mut().find(|p| p.id == id) {
+                photo.rating = then.rating;
+            }
+            machine.now.insert(id, then.clone());
+            restored += 1;
+        }
+        if self.loaded_photo.is_some_and(|id| ids.contains(&id)) {
+            // Reload the restored edits next time it opens.
+            self.loaded_photo = None;
+        }
+        self.status_message = format!(
+            "Restored {restored} photo{} to {}",
+            if restored == 1 { "" } else { "s" },
+            machine.date
+        );
+        Task::none()
+    }
+
     fn handle_set_rating(&mut self, rating: i32) -> Task<Message> {
         let Some(id) = self.selected_photo else {
             return Task::none();
@@ -1575,20 +5222,25 @@ impl App {
             error!(%err, "failed to delete photo");
             return Task::none();
         }
+        if let Some(preview) = self.smart_previews.remove(&id) {
+            smart_preview::remove(&preview);
+        }
         self.photos.retain(|p| p.id != id);
         self.thumbnails.remove(&id);
+        let counts = self.refresh_sidebar_counts();
         if self.loaded_photo == Some(id) {
             self.loaded_photo = None;
             self.current_image = None;
             self.preview_image = None;
             self.processed_image = None;
+            self.color_range_reading = None;
             self.histogram = None;
             self.current_exif.clear();
         }
         self.selected_photo = None;
         self.update_export_enabled();
         self.status_message = "Photo removed from catalog.".into();
-        Task::none()
+        counts
     }
 
     pub fn crop_mode(&self) -> bool {
@@ -1611,16 +5263,85 @@ impl App {
         self.showing_before
     }
 
+    pub fn annotations(&self) -> &[Annotation] {
+        &self.annotations
+    }
+
+    pub fn annotations_visible(&self) -> bool {
+        self.annotations_visible
+    }
+
+    pub fn annotation_tool(&self) -> Option<AnnotationTool> {
+        self.annotation_tool
+    }
+
+    pub fn note_draft(&self) -> Option<&str> {
+        self.note_draft.as_ref().map(|(_, text)| text.as_str())
+    }
+
+    /// The marks to draw over the preview. They're left off while cropping
+    /// and in the before view, which don't show the developed frame.
+    pub fn annotation_layer(&self) -> Option<AnnotationLayer> {
+        if !self.annotations_visible || self.crop_mode || self.showing_before {
+            return None;
+        }
+        let source = self.preview_image.as_ref()?;
+        Some(AnnotationLayer {
+            items: self.annotations.clone(),
+            map: FrameMap::new(&self.edit_params, source.width, source.height),
+            tool: self.annotation_tool,
+            pending_note: self.note_draft.as_ref().map(|(at, _)| *at),
+        })
+    }
+
     pub fn display_image(&self) -> Option<&iced::widget::image::Handle> {
         if self.showing_before {
             self.original_display.as_ref()
+        } else if let Some((_, mask)) = self
+            .color_range_reading
+            .as_ref()
+            .filter(|_| self.color_range_mask_shown())
+        {
+            Some(mask)
         } else {
             self.processed_image.as_ref()
         }
     }
 
+    pub fn color_range(&self) -> &ColorRange {
+        &self.color_range
+    }
+
+    /// Whether the develop view shows the color range mask instead of the
+    /// photo. Only while the Color Range section is open.
+    pub fn color_range_mask_shown(&self) -> bool {
+        self.color_range_mask && self.is_panel_open(PanelSection::ColorRange)
+    }
+
+    pub fn color_range_coverage(&self) -> Option<f32> {
+        self.color_range_reading
+            .as_ref()
+            .map(|(coverage, _)| *coverage)
+    }
+
+    pub fn color_range_set(&self) -> &[(String, Result<f32, String>)] {
+        &self.color_range_set
+    }
+
+    /// The library as shown: today's, or the past one being browsed.
     pub fn photos(&self) -> &[Photo] {
-        &self.photos
+        match &self.time_machine {
+            Some(machine) => &machine.photos,
+            None => &self.photos,
+        }
+    }
+
+    pub fn time_machine(&self) -> Option<&TimeMachine> {
+        self.time_machine.as_ref()
+    }
+
+    pub fn time_machine_dates(&self) -> Option<&[String]> {
+        self.time_machine_dates.as_deref()
     }
 
     pub fn current_photo(&self) -> Option<&Photo> {
@@ -1632,99 +5353,314 @@ impl App {
         &self.thumbnails
     }
 
-    pub fn edit_params(&self) -> &EditParams {
-        &self.edit_params
+    pub fn thumbnail_aspects(&self) -> &std::collections::HashMap<PhotoId, f32> {
+        &self.thumbnail_aspects
+    }
+
+    pub fn animations(&self) -> &std::collections::HashMap<PhotoId, Animation> {
+        &self.animations
+    }
+
+    /// The open photo's animation, if it has one.
+    pub fn loaded_animation(&self) -> Option<&Animation> {
+        self.loaded_photo.and_then(|id| self.animations.get(&id))
+    }
+
+    pub fn edit_params(&self) -> &EditParams {
+        &self.edit_params
+    }
+
+    pub fn preview_image(&self) -> Option<&Arc<ImageBuf>> {
+        self.preview_image.as_ref()
+    }
+
+    pub fn histogram(&self) -> Option<&HistogramData> {
+        self.histogram.as_deref()
+    }
+
+    pub fn current_exif(&self) -> &[(String, String)] {
+        &self.current_exif
+    }
+
+    /// Photos the current photo was made from.
+    pub fn provenance_sources(&self) -> &[ProvenanceLink] {
+        &self.provenance_sources
+    }
+
+    /// Photos made from the current photo.
+    pub fn provenance_derivatives(&self) -> &[ProvenanceLink] {
+        &self.provenance_derivatives
+    }
+
+    pub fn status_message(&self) -> &str {
+        &self.status_message
+    }
+
+    pub fn footer_status(&self) -> String {
+        let mut states = Vec::new();
+
+        if self.is_importing {
+            states.push("Importing");
+        }
+        if self.is_loading_photo {
+            states.push("Loading photo");
+        }
+        if self.is_processing {
+            states.push("Rendering preview");
+        }
+        if self.is_exporting {
+            states.push("Exporting");
+        }
+        if self.catalog_load.is_some() {
+            states.push("Loading catalog");
+        }
+
+        if states.is_empty() {
+            self.status_message.clone()
+        } else {
+            format!("{}  |  {}", states.join(" · "), self.status_message)
+        }
+    }
+
+    pub fn date_filter(&self) -> &DateFilter {
+        &self.date_filter
+    }
+
+    pub fn volumes(&self) -> &[Volume] {
+        &self.volumes
+    }
+
+    pub fn offline_photos(&self) -> &HashSet<PhotoId> {
+        &self.offline_photos
+    }
+
+    pub fn date_index(&self) -> &DateIndex {
+        &self.date_index
+    }
+
+    pub fn gear_counts(&self) -> &GearCounts {
+        &self.gear_counts
+    }
+
+    pub fn expanded_dates(&self) -> &HashSet<DateExpansionKey> {
+        &self.expanded_dates
+    }
+
+    pub fn filtered_photos(&self) -> Vec<&Photo> {
+        let mut photos: Vec<&Photo> = self
+            .photos()
+            .iter()
+            .filter(|photo| {
+                self.date_filter.matches(photo)
+                    && self.rating_filter.matches(photo)
+                    && self.gear_filter.matches(photo)
+                    && self
+                        .stacked_under
+                        .get(&photo.id)
+                        .is_none_or(|cover| self.expanded_stacks.contains(cover))
+            })
+            .collect();
+        self.sort_order.sort(&mut photos);
+        photos
+    }
+
+    pub fn rating_filter(&self) -> RatingFilter {
+        self.rating_filter
+    }
+
+    pub fn gear_filter(&self) -> &GearFilter {
+        &self.gear_filter
+    }
+
+    pub fn sort_order(&self) -> SortOrder {
+        self.sort_order
+    }
+
+    pub fn workspace(&self) -> Workspace {
+        self.workspace
+    }
+
+    pub fn selected_photo(&self) -> Option<PhotoId> {
+        self.selected_photo
+    }
+
+    pub fn selected_photos(&self) -> &HashSet<PhotoId> {
+        &self.selected_photos
+    }
+
+    pub fn right_panel_open(&self) -> bool {
+        self.right_panel_open
+    }
+
+    pub fn is_panel_open(&self, section: PanelSection) -> bool {
+        self.panel_sections.contains(&section)
+    }
+
+    /// `(loaded, total)` while the photo list is streaming in.
+    pub fn catalog_progress(&self) -> Option<(usize, usize)> {
+        self.catalog_load
+            .as_ref()
+            .map(|load| (load.loaded, load.total))
+    }
+
+    pub fn snapshots(&self) -> &[Snapshot] {
+        &self.snapshots
+    }
+
+    /// The snapshot the current edit matches exactly, naming which version
+    /// of the photo is open.
+    pub fn current_version_name(&self) -> Option<&str> {
+        self.loaded_photo?;
+        edit_version(&self.snapshots, self.applied_snapshot, &self.edit_params).0
+    }
+
+    /// The open photo's edit has changed since its snapshot was taken or
+    /// applied.
+    pub fn is_edit_modified(&self) -> bool {
+        self.loaded_photo.is_some()
+            && edit_version(&self.snapshots, self.applied_snapshot, &self.edit_params).1
+    }
+
+    pub fn applied_snapshot(&self) -> Option<SnapshotId> {
+        self.applied_snapshot
+    }
+
+    /// The snapshot being compared against the current edit, if any.
+    pub fn edit_diff(&self) -> Option<&Snapshot> {
+        let id = self.edit_diff?;
+        self.snapshots.iter().find(|s| s.id == id)
+    }
+
+    pub fn animation_export_open(&self) -> bool {
+        self.animation_export_open
+    }
+
+    pub fn comparison_export(&self) -> Option<&ComparisonExport> {
+        self.comparison_export.as_ref()
+    }
+
+    pub fn export_options(&self) -> Option<&ExportOptions> {
+        self.export_options.as_ref()
+    }
+
+    pub fn notifications(&self) -> &NotificationCenter {
+        &self.notifications
+    }
+
+    pub fn export_review(&self) -> Option<&ExportReview> {
+        self.export_review.as_ref()
+    }
+
+    pub fn stack_open(&self) -> bool {
+        self.stack_open
+    }
+
+    pub fn link_sources_open(&self) -> bool {
+        self.link_sources_open
+    }
+
+    /// The rest of the multi-selection, which Link Sources records the
+    /// selected photo as made from.
+    pub fn link_source_ids(&self) -> Vec<PhotoId> {
+        self.selected_photos
+            .iter()
+            .copied()
+            .filter(|&id| Some(id) != self.selected_photo)
+            .collect()
+    }
+
+    pub fn render_consistency(&self) -> Option<&RenderConsistency> {
+        self.render_consistency.as_ref()
+    }
+
+    pub fn export_progress(&self) -> Option<&ExportProgress> {
+        self.export_progress.as_ref()
+    }
+
+    pub fn calibration(&self) -> &Calibration {
+        &self.calibration
+    }
+
+    pub fn folder_thumbnail_fidelity(&self) -> &HashMap<String, ThumbnailFidelity> {
+        &self.folder_thumbnail_fidelity
     }
 
-    pub fn preview_image(&self) -> Option<&Arc<ImageBuf>> {
-        self.preview_image.as_ref()
+    pub fn quarantine(&self) -> &[QuarantinedFile] {
+        &self.quarantine
     }
 
-    pub fn histogram(&self) -> Option<&HistogramData> {
-        self.histogram.as_deref()
+    pub fn quarantine_open(&self) -> bool {
+        self.quarantine_open
     }
 
-    pub fn current_exif(&self) -> &[(String, String)] {
-        &self.current_exif
+    pub fn renderer(&self) -> &RendererStatus {
+        &self.renderer
     }
 
-    pub fn status_message(&self) -> &str {
-        &self.status_message
+    pub fn gpu_notice_open(&self) -> bool {
+        self.gpu_notice_open
     }
 
-    pub fn footer_status(&self) -> String {
-        let mut states = Vec::new();
+    pub fn gpu_diagnostics_open(&self) -> bool {
+        self.gpu_diagnostics_open
+    }
 
-        if self.is_importing {
-            states.push("Importing");
-        }
-        if self.is_loading_photo {
-            states.push("Loading photo");
-        }
-        if self.is_processing {
-            states.push("Rendering preview");
-        }
-        if self.is_exporting {
-            states.push("Exporting");
-        }
+    pub fn path_remap(&self) -> Option<&PathRemapForm> {
+        self.path_remap.as_ref()
+    }
 
-        if states.is_empty() {
-            self.status_message.clone()
-        } else {
-            format!("{}  |  {}", states.join(" · "), self.status_message)
-        }
+    pub fn batch_metadata(&self) -> Option<&BatchMetadataForm> {
+        self.batch_metadata.as_ref()
     }
 
-    pub fn date_filter(&self) -> &DateFilter {
-        &self.date_filter
+    pub fn gear_override(&self) -> Option<&GearOverrideForm> {
+        self.gear_override.as_ref()
     }
 
-    pub fn expanded_dates(&self) -> &HashSet<DateExpansionKey> {
-        &self.expanded_dates
+    pub fn auto_stack(&self) -> Option<&AutoStackReview> {
+        self.auto_stack.as_ref()
     }
 
-    pub fn filtered_photos(&self) -> Vec<&Photo> {
-        let mut photos: Vec<&Photo> = self
-            .photos
-            .iter()
-            .filter(|photo| self.date_filter.matches(photo) && self.rating_filter.matches(photo))
-            .collect();
-        self.sort_order.sort(&mut photos);
-        photos
+    pub fn stacks(&self) -> &std::collections::HashMap<PhotoId, PhotoStack> {
+        &self.stacks
     }
 
-    pub fn rating_filter(&self) -> RatingFilter {
-        self.rating_filter
+    pub fn expanded_stacks(&self) -> &HashSet<PhotoId> {
+        &self.expanded_stacks
     }
 
-    pub fn sort_order(&self) -> SortOrder {
-        self.sort_order
+    pub fn preset_editor(&self) -> Option<&PresetEditor> {
+        self.preset_editor.as_ref()
     }
 
-    pub fn workspace(&self) -> Workspace {
-        self.workspace
+    pub fn presets(&self) -> &[crema_core::preset::Preset] {
+        &self.preferences.presets
     }
 
-    pub fn selected_photo(&self) -> Option<PhotoId> {
-        self.selected_photo
+    pub fn preferences(&self) -> &Preferences {
+        &self.preferences
     }
 
-    pub fn selected_photos(&self) -> &HashSet<PhotoId> {
-        &self.selected_photos
+    pub fn metadata_template_crop(&self) -> Option<ExportCrop> {
+        self.metadata_template_crop
     }
 
-    pub fn right_panel_open(&self) -> bool {
-        self.right_panel_open
+    pub fn preferences_open(&self) -> bool {
+        self.preferences_open
     }
 
-    pub fn is_panel_open(&self, section: PanelSection) -> bool {
-        self.panel_sections.contains(&section)
+    pub fn scope_palette(&self) -> ScopePalette {
+        ScopePalette::for_vision(self.preferences.color_vision)
     }
 
     pub fn is_loading_photo(&self) -> bool {
         self.is_loading_photo
     }
 
+    pub fn editing_embedded_preview(&self) -> bool {
+        self.editing_embedded_preview
+    }
+
     pub fn is_processing(&self) -> bool {
         self.is_processing
     }
@@ -1838,6 +5774,7 @@ impl App {
                 .file_stem()
                 .and_then(|s| s.to_str())
         {
+            let stem = edit_aware_stem(stem, self.current_version_name(), self.is_edit_modified());
             return format!("{stem}.jpg");
         }
 
@@ -1881,6 +5818,138 @@ fn handle_key_press(
     }
 }
 
+/// Compile every GPU pipeline ahead of the first real frame, logging how
+/// long it took.
+fn warm_up_gpu(gpu: &GpuHandle) {
+    let started = std::time::Instant::now();
+    let Ok(mut lock) = gpu.lock() else {
+        return;
+    };
+    let (ctx, pipeline) = &mut *lock;
+    match pipeline.warm_up(ctx) {
+        Ok(()) => info!(elapsed = ?started.elapsed(), "GPU warmed up"),
+        Err(err) => error!(%err, "GPU warmup failed"),
+    }
+}
+
+/// Messages handled while browsing the library as of a past date: looking
+/// around, preferences, batch exports of the past edits, and the results
+/// of work already under way. Anything else could change the catalog or
+/// render today's edits, so it's turned away; new messages stay blocked
+/// until they're listed here.
+fn allowed_in_time_machine(message: &Message) -> bool {
+    matches!(
+        message,
+        Message::SelectPhoto(_)
+            | Message::SetWorkspace(Workspace::Library)
+            | Message::ToggleRightPanel
+            | Message::NextPhoto
+            | Message::PrevPhoto
+            | Message::SetDateFilter(_)
+            | Message::SetRatingFilter(_)
+            | Message::SetGearFilter(_)
+            | Message::SetSortOrder(_)
+            | Message::ToggleDateExpansion(_)
+            | Message::TogglePanelSection(_)
+            | Message::ToggleStackExpanded(_)
+            | Message::ModifiersChanged(_)
+            | Message::ZoomAtPoint(..)
+            | Message::PanDelta(..)
+            | Message::PinchAt(..)
+            | Message::ToggleActualSize(..)
+            | Message::ResetZoom
+            | Message::ToggleBeforeAfter
+            | Message::SetAnnotationsVisible(_)
+            | Message::OpenQuarantine
+            | Message::CloseQuarantine
+            | Message::CloseStack
+            | Message::CloseLinkSources
+            | Message::ClosePathRemap
+            | Message::CloseBatchMetadata
+            | Message::CloseGearOverride
+            | Message::ClosePresetEditor
+            | Message::CloseAutoStack
+            | Message::CloseAnimationExport
+            | Message::CloseComparisonExport
+            | Message::CloseEditDiff
+            | Message::CloseTimeMachinePicker
+            | Message::BrowseAsOf(_)
+            | Message::SidebarCounted(..)
+            | Message::RestoreFromTimeMachine
+            | Message::LeaveTimeMachine
+            | Message::ColorRangeChanged(..)
+            | Message::ColorRangeMaskToggled(_)
+            | Message::ColorRangeMeasured(..)
+            | Message::MeasureColorRangeSelection
+            | Message::ColorRangeSelectionMeasured(_)
+            | Message::BatchExport
+            | Message::CloseExportOptions
+            | Message::ChooseExportDestination
+            | Message::SetExportTargetSize(_)
+            | Message::SetExportAnnotations(_)
+            | Message::BatchExportFolderSelected(..)
+            | Message::ToggleExportWarningSilenced(_)
+            | Message::ExportAnyway
+            | Message::CancelExportReview
+            | Message::ExportProgress(_)
+            | Message::CancelExport
+            | Message::ExportComplete(_)
+            | Message::BatchExportProgress(..)
+            | Message::BatchExportComplete(..)
+            | Message::RenderFarmFinished(_)
+            | Message::ToggleNotificationDrawer
+            | Message::DismissNotification(_)
+            | Message::RunNotificationAction(_)
+            | Message::ExpireToasts
+            | Message::SetNotificationMuted(..)
+            | Message::ClearNotifications
+            | Message::AuditPrecision
+            | Message::PrecisionAudited(_)
+            | Message::VerifyRenderConsistency
+            | Message::RenderConsistencyVerified(_)
+            | Message::CloseRenderConsistency
+            | Message::GpuInitDone(..)
+            | Message::DismissGpuNotice
+            | Message::OpenGpuDiagnostics
+            | Message::CloseGpuDiagnostics
+            | Message::PhotoPageLoaded(..)
+            | Message::ThumbnailReady(..)
+            | Message::ThumbnailFailed(..)
+            | Message::ImageLoaded(..)
+            | Message::EmbeddedPreviewLoaded(..)
+            | Message::ImageProcessed(..)
+            | Message::ImageLoadFailed(_)
+            | Message::OriginalReady(_)
+            | Message::CheckVolumes
+            | Message::SmartPreviewsHeld(_)
+            | Message::TogglePreferences
+            | Message::SetAccentColor(_)
+            | Message::SetColorVision(_)
+            | Message::SetHighContrast(_)
+            | Message::SetReduceMotion(_)
+            | Message::SetGridLayout(_)
+            | Message::ToggleExportCrop(_)
+            | Message::SetExportWorkers(_)
+            | Message::SetExportDither(_)
+            | Message::SetExportColorSpace(_)
+            | Message::SetExportTiff16Bit(_)
+            | Message::ResetExportWarnings
+            | Message::SetExportMetadataEnabled(_)
+            | Message::ShowMetadataTemplate(_)
+            | Message::SetCropMetadataOverride(..)
+            | Message::SetMetadataTemplateField(..)
+            | Message::SetMetadataTemplateRating(_)
+            | Message::SetSmartPreviews(_)
+            | Message::SetGpuWarmup(_)
+            | Message::SetThumbnailFidelity(_)
+            | Message::AddExportPlugin
+            | Message::ExportPluginSelected(_)
+            | Message::SetExportPluginEnabled(..)
+            | Message::RemoveExportPlugin(_)
+            | Message::Noop
+    )
+}
+
 fn process_gpu(
     gpu: &Arc<std::sync::Mutex<(GpuContext, GpuPipeline)>>,
     buf: &Arc<ImageBuf>,
@@ -1904,6 +5973,93 @@ fn gpu_supports_preview_params(params: &EditParams) -> bool {
     params.nr_luminance == 0.0 && params.nr_color == 0.0
 }
 
+/// Read the photo list on a background thread, yielding it a page at a time.
+fn stream_photo_pages(path: String) -> impl iced::futures::Stream<Item = PhotoPage> {
+    let (tx, rx) = iced::futures::channel::mpsc::unbounded();
+    std::thread::spawn(move || {
+        let mut total = 0;
+        let result = Catalog::open_existing(&path).and_then(|catalog| {
+            total = catalog.photo_count()? as usize;
+            // The library shows and filters by overridden camera and lens.
+            let overrides = catalog.list_metadata_overrides()?;
+            catalog.list_photos_paged(PHOTO_PAGE_SIZE, |mut photos| {
+                for photo in &mut photos {
+                    if let Some(fixup) = overrides.get(&photo.id) {
+                        fixup.apply(photo);
+                    }
+                }
+                tx.unbounded_send(PhotoPage {
+                    photos,
+                    total,
+                    done: false,
+                    error: None,
+                })
+                .is_ok()
+            })
+        });
+        let error = result.err().map(|err| {
+            error!(%err, "failed to list photos");
+            format!("{err:#}")
+        });
+        tx.unbounded_send(PhotoPage {
+            photos: Vec::new(),
+            total,
+            done: true,
+            error,
+        })
+        .ok();
+    });
+    rx
+}
+
+enum FarmEvent {
+    Progress(usize, usize),
+    Finished(Result<render_farm::FarmReport, String>),
+}
+
+/// Run a batch export across worker processes on a background thread,
+/// streaming progress as workers report in.
+fn stream_render_farm(
+    manifest: render_farm::Manifest,
+    workers: usize,
+) -> impl iced::futures::Stream<Item = FarmEvent> {
+    let (tx, rx) = iced::futures::channel::mpsc::unbounded();
+    std::thread::spawn(move || {
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |d| d.as_nanos());
+        let manifest_path =
+            std::env::temp_dir().join(format!("crema-export-{}-{nanos}.json", std::process::id()));
+        let result = render_farm::write_manifest(&manifest, &manifest_path).and_then(|()| {
+            render_farm::run_farm(&manifest_path, &manifest, workers, |done, total| {
+                tx.unbounded_send(FarmEvent::Progress(done, total)).ok();
+            })
+        });
+        std::fs::remove_file(&manifest_path).ok();
+        tx.unbounded_send(FarmEvent::Finished(result.map_err(|e| format!("{e:#}"))))
+            .ok();
+    });
+    rx
+}
+
+/// The sidebar's date counts as the catalog has them: photos per year,
+/// and per day for each year already expanded.
+#[derive(Debug, Clone)]
+pub struct DateCounts {
+    years: Vec<(u16, usize)>,
+    days: Vec<(u16, DayCounts)>,
+}
+
+fn count_dates(catalog_path: &str, expanded: &[u16]) -> anyhow::Result<DateCounts> {
+    let catalog = Catalog::open_existing(catalog_path)?;
+    let years = catalog.year_counts()?;
+    let days = expanded
+        .iter()
+        .map(|&year| Ok((year, catalog.day_counts(year)?)))
+        .collect::<anyhow::Result<_>>()?;
+    Ok(DateCounts { years, days })
+}
+
 fn dirs_catalog_path() -> String {
     let data_dir = dirs::data_local_dir()
         .unwrap_or_else(|| PathBuf::from("."))
@@ -1912,18 +6068,7 @@ fn dirs_catalog_path() -> String {
     data_dir.join("catalog.db").to_string_lossy().to_string()
 }
 
-fn thumbnail_cache_key(path: &std::path::Path) -> String {
-    let mtime = std::fs::metadata(path).and_then(|m| m.modified()).ok();
-    let mut hasher = blake3::Hasher::new();
-    hasher.update(path.to_string_lossy().as_bytes());
-    if let Some(t) = mtime {
-        let dur = t.duration_since(std::time::UNIX_EPOCH).unwrap_or_default();
-        hasher.update(&dur.as_nanos().to_le_bytes());
-    }
-    hasher.finalize().to_hex().to_string()
-}
-
-fn unique_export_path(
+pub fn unique_export_path(
     folder: &std::path::Path,
     stem: &str,
     ext: &str,
@@ -1944,65 +6089,394 @@ fn unique_export_path(
     }
 }
 
-fn export_image(buf: ImageBuf, params: &EditParams, path: &std::path::Path) -> String {
-    let pipeline = crema_core::pipeline::Pipeline::new();
-    let processed = match pipeline.process_cpu(buf, params) {
-        Ok(p) => p,
-        Err(e) => return format!("Export failed: {e}"),
+/// "Z 7, ISO 800, 30s, 12°C": how a master dark is listed.
+pub fn dark_settings_label(settings: &DarkFrameSettings) -> String {
+    let secs = settings.exposure_secs;
+    let shutter = if secs >= 1.0 {
+        format!("{secs}s")
+    } else {
+        format!("1/{}s", (1.0 / secs).round())
     };
+    let mut label = format!("{}, ISO {}, {shutter}", settings.camera_model, settings.iso);
+    if let Some(t) = settings.temperature {
+        label.push_str(&format!(", {t:.0}°C"));
+    }
+    label
+}
 
-    let w = processed.width;
-    let h = processed.height;
-    let rgba = processed.to_rgba_u8_srgb();
+/// The name of the applied snapshot among `snapshots` and whether `params`
+/// differ from it. An edit that was never a snapshot has no version to
+/// have changed from.
+fn edit_version<'a>(
+    snapshots: &'a [Snapshot],
+    applied: Option<SnapshotId>,
+    params: &EditParams,
+) -> (Option<&'a str>, bool) {
+    match applied.and_then(|id| snapshots.iter().find(|s| s.id == id)) {
+        Some(snapshot) => (Some(snapshot.name.as_str()), snapshot.params != *params),
+        None => (None, false),
+    }
+}
 
-    let img = match image::RgbaImage::from_raw(w, h, rgba) {
-        Some(img) => image::DynamicImage::ImageRgba8(img),
-        None => return "Export failed: could not construct image buffer".into(),
-    };
+/// "IMG_1234.NEF (Black and White) *modified*": a photo's name with the
+/// version of its edit and whether it has changed since.
+fn edit_aware_name(name: &str, version: Option<&str>, modified: bool) -> String {
+    let mut label = name.to_string();
+    if let Some(version) = version {
+        label.push_str(&format!(" ({version})"));
+    }
+    if modified {
+        label.push_str(" *modified*");
+    }
+    label
+}
 
-    let ext = path
-        .extension()
-        .and_then(|e| e.to_str())
-        .unwrap_or("")
-        .to_ascii_lowercase();
+/// "IMG_1234-black-and-white": an export file stem naming the version of
+/// the edit, or marking an edit that isn't saved as one.
+fn edit_aware_stem(stem: &str, version: Option<&str>, modified: bool) -> String {
+    let mut name = stem.to_string();
+    if let Some(version) = version {
+        let slug: String = version
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .map(str::to_lowercase)
+            .collect::<Vec<_>>()
+            .join("-");
+        if !slug.is_empty() {
+            name.push('-');
+            name.push_str(&slug);
+        }
+    }
+    if modified {
+        name.push_str("-edited");
+    }
+    name
+}
 
-    let result = if ext == "jpg" || ext == "jpeg" {
-        let file = match std::fs::File::create(path) {
-            Ok(f) => f,
-            Err(e) => return format!("Export failed: {e}"),
-        };
-        let writer = std::io::BufWriter::new(file);
-        let encoder = image::codecs::jpeg::JpegEncoder::new_with_quality(writer, 92);
-        img.to_rgb8().write_with_encoder(encoder)
+/// `path` with `suffix` added to its file stem.
+fn suffixed_path(path: &Path, suffix: &str) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let mut name = format!("{stem}{suffix}");
+    if let Some(ext) = path.extension() {
+        name.push('.');
+        name.push_str(&ext.to_string_lossy());
+    }
+    path.with_file_name(name)
+}
+
+/// The batch summary's note on JPEGs fit to the target size: the quality
+/// range, then each file's quality, with the rest in the log.
+fn fitted_summary(fitted: &[(String, FittedQuality)]) -> String {
+    const LISTED: usize = 8;
+    let Some(low) = fitted.iter().map(|(_, f)| f.quality).min() else {
+        return String::new();
+    };
+    let high = fitted.iter().map(|(_, f)| f.quality).max().unwrap_or(low);
+    let mut summary = if low == high {
+        format!(" JPEG quality {low} to fit the target size")
     } else {
-        img.save(path)
+        format!(" JPEG quality {low}-{high} to fit the target size")
     };
+    let over = fitted.iter().filter(|(_, f)| f.over_target).count();
+    if over > 0 {
+        summary.push_str(&format!(" ({over} still over it)"));
+    }
+    let files: Vec<String> = fitted
+        .iter()
+        .take(LISTED)
+        .map(|(name, f)| format!("{name} {}", f.quality))
+        .collect();
+    summary.push_str(&format!(": {}", files.join(", ")));
+    if fitted.len() > LISTED {
+        for (name, f) in &fitted[LISTED..] {
+            info!(file = %name, quality = f.quality, "fit export to target size");
+        }
+        summary.push_str(&format!(" and {} more in the log", fitted.len() - LISTED));
+    }
+    summary.push('.');
+    summary
+}
+
+enum ExportEvent {
+    Progress(f32),
+    Finished(ExportOutcome),
+}
+
+/// A full resolution export of the photo open in develop, captured so it
+/// can run off the UI thread.
+struct FullResExport {
+    buf: Arc<ImageBuf>,
+    params: EditParams,
+    crops: Vec<ExportCrop>,
+    encoding: ExportEncoding,
+    path: PathBuf,
+    source: String,
+    plugins: Vec<export_plugin::ExportPlugin>,
+    from_smart_preview: bool,
+    /// Burned into every output; empty unless exporting proofs.
+    annotations: Vec<Annotation>,
+    /// The catalog entry the written metadata is filled from.
+    photo: Option<Photo>,
+    metadata: ExportMetadata,
+}
+
+impl FullResExport {
+    /// Export every selected crop, then run post-processors on what was
+    /// written.
+    fn run(self, cancel: &AtomicBool, progress: &(dyn Fn(f32) + Sync)) -> ExportOutcome {
+        let count = self.crops.len();
+        let mut plugin_failures = Vec::new();
+        let mut failures = Vec::new();
+        let mut over_target = 0;
+        let mut exported = String::new();
+        for (i, &crop) in self.crops.iter().enumerate() {
+            let output = if count == 1 {
+                self.path.clone()
+            } else {
+                suffixed_path(&self.path, &crop.file_suffix())
+            };
+            let params = crop.apply(&self.params, self.buf.width, self.buf.height);
+            let report = |fraction: f32| progress((i as f32 + fraction) / count as f32);
+            // Leave room in a size limit for the metadata written after.
+            let reserve = self
+                .photo
+                .as_ref()
+                .and_then(|photo| self.metadata.render(crop, photo))
+                .map_or(0, |metadata| embed::overhead(&output, &metadata));
+            let encoding = ExportEncoding {
+                target_size: self.encoding.target_size.map(|t| t.saturating_sub(reserve)),
+                ..self.encoding
+            };
+            let outcome = export_full_res(
+                ImageBuf::clone(&self.buf),
+                &params,
+                &self.annotations,
+                &encoding,
+                &output,
+                cancel,
+                &report,
+            );
+            let outcome = match (outcome, self.write_metadata(crop, &output)) {
+                (ExportOutcome::Exported(_) | ExportOutcome::OverTarget(_), Err(err)) => {
+                    ExportOutcome::Failed(format!(
+                        "Export failed: could not write metadata: {err:#}"
+                    ))
+                }
+                (outcome, _) => outcome,
+            };
+            if matches!(outcome, ExportOutcome::OverTarget(_)) {
+                over_target += 1;
+            }
+            match outcome {
+                ExportOutcome::Cancelled => return ExportOutcome::Cancelled,
+                ExportOutcome::Exported(msg) | ExportOutcome::OverTarget(msg) => {
+                    plugin_failures.extend(export_plugin::run_all(
+                        &self.plugins,
+                        &output,
+                        &self.source,
+                        &self.params,
+                    ));
+                    exported = msg;
+                }
+                ExportOutcome::Failed(msg) => failures.push(msg),
+            }
+        }
+
+        let mut outcome = match (count, failures.first()) {
+            (1, None) if over_target > 0 => ExportOutcome::OverTarget(exported),
+            (1, None) => ExportOutcome::Exported(exported),
+            (1, Some(failure)) => ExportOutcome::Failed(failure.clone()),
+            (_, None) if over_target > 0 => ExportOutcome::OverTarget(format!(
+                "Exported {count} crops next to {}; {over_target} still over the size limit",
+                self.path.display()
+            )),
+            (_, None) => ExportOutcome::Exported(format!(
+                "Exported {count} crops next to {}",
+                self.path.display()
+            )),
+            (_, Some(first)) => ExportOutcome::Failed(format!(
+                "{} of {count} crops failed: {first}",
+                failures.len()
+            )),
+        };
+        if let ExportOutcome::Exported(msg)
+        | ExportOutcome::OverTarget(msg)
+        | ExportOutcome::Failed(msg) = &mut outcome
+        {
+            if self.from_smart_preview && failures.is_empty() {
+                msg.push_str(" from the smart preview; reconnect the original for full resolution");
+            }
+            if let Some(first) = plugin_failures.first() {
+                msg.push_str(&format!(
+                    ". {} post-processor run{} failed: {first}",
+                    plugin_failures.len(),
+                    if plugin_failures.len() == 1 { "" } else { "s" }
+                ));
+            }
+        }
+        outcome
+    }
 
-    match result {
-        Ok(()) => format!("Exported to {}", path.display()),
-        Err(e) => format!("Export failed: {e}"),
+    /// Embed the metadata template for `crop` into `output`, when there is
+    /// one and the format can carry it.
+    fn write_metadata(&self, crop: ExportCrop, output: &Path) -> anyhow::Result<()> {
+        let metadata = self
+            .photo
+            .as_ref()
+            .and_then(|photo| self.metadata.render(crop, photo));
+        match metadata {
+            Some(metadata) if embed::supports(output) => embed::embed(output, &metadata),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Run a full resolution export on a background thread, streaming its
+/// progress.
+fn stream_full_res_export(
+    job: FullResExport,
+    cancel: Arc<AtomicBool>,
+) -> impl iced::futures::Stream<Item = ExportEvent> {
+    let (tx, rx) = iced::futures::channel::mpsc::unbounded();
+    std::thread::spawn(move || {
+        let outcome = job.run(&cancel, &|fraction| {
+            tx.unbounded_send(ExportEvent::Progress(fraction)).ok();
+        });
+        tx.unbounded_send(ExportEvent::Finished(outcome)).ok();
+    });
+    rx
+}
+
+/// Width over height of encoded thumbnail bytes, read from the header.
+fn thumbnail_aspect(bytes: &[u8]) -> Option<f32> {
+    let (w, h) = image::ImageReader::new(std::io::Cursor::new(bytes))
+        .with_guessed_format()
+        .ok()?
+        .into_dimensions()
+        .ok()?;
+    (w > 0 && h > 0).then(|| w as f32 / h as f32)
+}
+
+fn describe_scan_crop(crop: &ScanCrop) -> String {
+    let mut message = String::from("Cropped to the scanned print");
+    if crop.rotation != 0.0 {
+        message.push_str(&format!(", straightened {:+.1}°", crop.rotation));
+    }
+    if let Some((w, h)) = crop.aspect {
+        message.push_str(&format!(", {w}:{h}"));
+    }
+    message.push('.');
+    message
+}
+
+fn file_name(path: &str) -> String {
+    Path::new(path)
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string()
+}
+
+/// The fidelity set for the nearest folder above `path` that has one.
+fn folder_override(
+    overrides: &HashMap<String, ThumbnailFidelity>,
+    path: &str,
+) -> Option<ThumbnailFidelity> {
+    Path::new(path)
+        .ancestors()
+        .skip(1)
+        .find_map(|dir| overrides.get(dir.to_str()?).copied())
+}
+
+/// The cache key of photo `photo_id`'s thumbnail showing `key_frame`,
+/// rendered with `edits` if given, without the file's stamp. Keys start
+/// with the catalog hash rather than the path, so moving a file keeps its
+/// entries, and name the photo so duplicates of one file don't replace
+/// each other's. Each variant has its own entry, so switching fidelity or
+/// editing the photo never picks up a thumbnail made the other way.
+fn thumbnail_variant_key(
+    photo_id: PhotoId,
+    file_hash: &str,
+    key_frame: u32,
+    edits: Option<&EditParams>,
+) -> String {
+    let mut key = file_hash.to_string();
+    if key_frame != 0 {
+        key.push_str(&format!("-frame{key_frame}"));
+    }
+    key.push_str(&format!("-photo{photo_id}"));
+    if let Some(edits) = edits {
+        let json = serde_json::to_vec(edits).unwrap_or_default();
+        key.push_str(&format!("-edited-{}", &blake3::hash(&json).to_hex()[..16]));
     }
+    key
+}
+
+/// A stamp of `path`'s size and modification time, so a file changed in
+/// place gets a fresh thumbnail, or `None` if it can't be read, as when
+/// its drive is offline.
+fn file_stamp(path: &std::path::Path) -> Option<String> {
+    let metadata = std::fs::metadata(path).ok()?;
+    let mtime = metadata
+        .modified()
+        .ok()?
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default();
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(&metadata.len().to_le_bytes());
+    hasher.update(&mtime.as_nanos().to_le_bytes());
+    Some(hasher.finalize().to_hex()[..16].to_string())
 }
 
+/// The thumbnail of photo `photo_id` at `path` showing `key_frame`, with
+/// `edits` applied if given. Animated photos shown as a later frame are
+/// cached separately from the first, so changing the key frame back and
+/// forth doesn't regenerate either. While the file is offline the last
+/// thumbnail made of it is used.
 fn load_thumbnail_bytes(
+    photo_id: PhotoId,
     path: &str,
+    file_hash: &str,
+    key_frame: u32,
+    edits: Option<&EditParams>,
     cache_dir: Option<&std::path::Path>,
 ) -> anyhow::Result<Vec<u8>> {
     let p = std::path::Path::new(path);
+    let generate = || match (edits, key_frame) {
+        (Some(edits), frame) => crema_thumbnails::generator::edited_thumbnail(p, frame, edits),
+        (None, 0) => crema_thumbnails::generator::fast_thumbnail(p),
+        (None, frame) => crema_thumbnails::generator::frame_thumbnail(p, frame),
+    };
 
     if let Some(dir) = cache_dir
         && let Ok(cache) = ThumbnailCache::new(dir.to_path_buf())
     {
-        let key = thumbnail_cache_key(p);
+        let variant = thumbnail_variant_key(photo_id, file_hash, key_frame, edits);
+        let Some(stamp) = file_stamp(p) else {
+            return cache
+                .load_newest(&format!("{variant}-v"))
+                .map_or_else(generate, Ok);
+        };
+        let key = format!("{variant}-v{stamp}");
         if let Some(bytes) = cache.load(&key) {
             return Ok(bytes);
         }
-        let bytes = crema_thumbnails::generator::fast_thumbnail(p)?;
+        let bytes = generate()?;
         cache.store(&key, &bytes).ok();
+        // Thumbnails of earlier edits of this frame, or of the file before
+        // it last changed, won't be shown again.
+        let base = thumbnail_variant_key(photo_id, file_hash, key_frame, None);
+        let superseded = match edits {
+            Some(_) => format!("{base}-edited-"),
+            None => format!("{base}-v"),
+        };
+        if let Err(err) = cache.prune(&superseded, &key) {
+            error!(%err, "failed to prune superseded thumbnails");
+        }
         return Ok(bytes);
     }
 
-    crema_thumbnails::generator::fast_thumbnail(p)
+    generate()
 }
 
 #[cfg(test)]
@@ -2010,18 +6484,6 @@ mod tests {
     use super::*;
     use std::path::Path;
 
-    fn test_image() -> ImageBuf {
-        let mut data = vec![0.0f32; 4 * 2 * 3];
-        data[0] = 0.8;
-        data[1] = 0.1;
-        data[2] = 0.1;
-        let last = data.len() - 3;
-        data[last] = 0.1;
-        data[last + 1] = 0.1;
-        data[last + 2] = 0.8;
-        ImageBuf::from_data(4, 2, data).unwrap()
-    }
-
     fn test_params() -> EditParams {
         EditParams {
             exposure: 0.5,
@@ -2032,152 +6494,310 @@ mod tests {
     }
 
     #[test]
-    fn export_jpeg_writes_valid_file() {
-        let dir = tempfile::tempdir().unwrap();
-        let path = dir.path().join("out.jpg");
-
-        let msg = export_image(test_image(), &test_params(), &path);
-
-        assert!(msg.starts_with("Exported to"), "unexpected: {msg}");
-        assert!(path.exists());
-
-        let img = image::open(&path).unwrap();
-        assert_eq!(img.width(), 4);
-        assert_eq!(img.height(), 2);
+    fn fitted_summary_lists_each_files_quality() {
+        let fit = |quality, over_target| FittedQuality {
+            quality,
+            over_target,
+        };
+        assert_eq!(fitted_summary(&[]), "");
+        let summary = fitted_summary(&[
+            ("a.jpg".into(), fit(84, false)),
+            ("b.jpg".into(), fit(71, false)),
+            ("c.jpg".into(), fit(1, true)),
+        ]);
+        assert_eq!(
+            summary,
+            " JPEG quality 1-84 to fit the target size (1 still over it): a.jpg 84, b.jpg 71, c.jpg 1."
+        );
     }
 
     #[test]
-    fn export_jpeg_uppercase_extension() {
-        let dir = tempfile::tempdir().unwrap();
-        let path = dir.path().join("photo.JPG");
-
-        let msg = export_image(test_image(), &EditParams::default(), &path);
-        assert!(msg.starts_with("Exported to"), "unexpected: {msg}");
+    fn gpu_preview_falls_back_to_cpu_when_denoise_is_active() {
+        assert!(gpu_supports_preview_params(&EditParams::default()));
 
-        let bytes = std::fs::read(&path).unwrap();
-        assert_eq!(&bytes[0..2], &[0xFF, 0xD8], "not a JPEG file");
+        let params = EditParams {
+            nr_luminance: 25.0,
+            ..EditParams::default()
+        };
+        assert!(!gpu_supports_preview_params(&params));
     }
 
     #[test]
-    fn export_png_writes_valid_file() {
-        let dir = tempfile::tempdir().unwrap();
-        let path = dir.path().join("out.png");
-
-        let msg = export_image(test_image(), &test_params(), &path);
-
-        assert!(msg.starts_with("Exported to"), "unexpected: {msg}");
-        assert!(path.exists());
-
-        let img = image::open(&path).unwrap();
-        assert_eq!(img.width(), 4);
-        assert_eq!(img.height(), 2);
+    fn dark_settings_label_formats_shutter_and_temperature() {
+        let mut settings = DarkFrameSettings {
+            camera_model: "Z 7".into(),
+            iso: 800,
+            exposure_secs: 30.0,
+            temperature: Some(12.4),
+        };
+        assert_eq!(dark_settings_label(&settings), "Z 7, ISO 800, 30s, 12°C");
+        settings.exposure_secs = 0.004;
+        settings.temperature = None;
+        assert_eq!(dark_settings_label(&settings), "Z 7, ISO 800, 1/250s");
+    }
 
-        let bytes = std::fs::read(&path).unwrap();
-        assert_eq!(&bytes[0..4], &[0x89, 0x50, 0x4E, 0x47]);
+    #[test]
+    fn names_carry_the_edit_version_and_state() {
+        assert_eq!(edit_aware_name("IMG_1234.NEF", None, false), "IMG_1234.NEF");
+        assert_eq!(
+            edit_aware_name("IMG_1234.NEF", Some("BW copy"), true),
+            "IMG_1234.NEF (BW copy) *modified*"
+        );
+        assert_eq!(
+            edit_aware_stem("IMG_1234", Some("Black & White, v2"), false),
+            "IMG_1234-black-white-v2"
+        );
+        assert_eq!(
+            edit_aware_stem("IMG_1234", Some("!!"), true),
+            "IMG_1234-edited"
+        );
+        assert_eq!(edit_aware_stem("IMG_1234", None, false), "IMG_1234");
     }
 
     #[test]
-    fn export_tiff_writes_valid_file() {
-        let dir = tempfile::tempdir().unwrap();
-        let path = dir.path().join("out.tiff");
+    fn time_machine_turns_away_catalog_changes() {
+        for message in [
+            Message::DismissQuarantined(1),
+            Message::RetryQuarantined(1),
+            Message::SetFolderThumbnailFidelity("/photos".into(), None),
+            Message::Export,
+            Message::LoadSidecar,
+        ] {
+            assert!(!allowed_in_time_machine(&message), "{message:?}");
+        }
+        assert!(allowed_in_time_machine(&Message::BatchExport));
+        assert!(allowed_in_time_machine(&Message::SelectPhoto(1)));
+    }
 
-        let msg = export_image(test_image(), &test_params(), &path);
+    #[test]
+    fn versions_come_from_the_applied_snapshot() {
+        let snapshot = |id, name: &str, params: EditParams| Snapshot {
+            id,
+            photo_id: 1,
+            name: name.into(),
+            params,
+            created_at: String::new(),
+        };
+        let edited = test_params();
+        let snapshots = [
+            snapshot(1, "Color", EditParams::default()),
+            snapshot(2, "BW copy", edited.clone()),
+        ];
 
-        assert!(msg.starts_with("Exported to"), "unexpected: {msg}");
-        assert!(path.exists());
+        assert_eq!(edit_version(&snapshots, None, &edited), (None, false));
+        assert_eq!(
+            edit_version(&snapshots, Some(2), &edited),
+            (Some("BW copy"), false)
+        );
+        assert_eq!(
+            edit_version(&snapshots, Some(2), &EditParams::default()),
+            (Some("BW copy"), true)
+        );
+        assert_eq!(edit_version(&snapshots, Some(3), &edited), (None, false));
+    }
 
-        let img = image::open(&path).unwrap();
-        assert_eq!(img.width(), 4);
-        assert_eq!(img.height(), 2);
+    #[test]
+    fn suffixed_path_keeps_extension() {
+        assert_eq!(
+            suffixed_path(Path::new("/out/photo.jpg"), "-1x1"),
+            Path::new("/out/photo-1x1.jpg")
+        );
+        assert_eq!(
+            suffixed_path(Path::new("/out/photo"), "-4x5"),
+            Path::new("/out/photo-4x5")
+        );
     }
 
     #[test]
-    fn export_applies_edits() {
+    fn unique_export_path_avoids_existing_and_reserved_names() {
         let dir = tempfile::tempdir().unwrap();
+        let existing = dir.path().join("photo.jpg");
+        std::fs::write(&existing, b"already here").unwrap();
 
-        let path_default = dir.path().join("default.png");
-        export_image(test_image(), &EditParams::default(), &path_default);
-
-        let bright_params = EditParams {
-            exposure: 2.0,
-            ..EditParams::default()
-        };
-        let path_bright = dir.path().join("bright.png");
-        export_image(test_image(), &bright_params, &path_bright);
-
-        let img_default = image::open(&path_default).unwrap().into_rgba8();
-        let img_bright = image::open(&path_bright).unwrap().into_rgba8();
+        let mut used = std::collections::HashSet::new();
+        let first = unique_export_path(dir.path(), "photo", "jpg", &mut used);
+        let second = unique_export_path(dir.path(), "photo", "jpg", &mut used);
 
-        let avg_default: u32 = img_default.pixels().map(|p| p.0[0] as u32).sum();
-        let avg_bright: u32 = img_bright.pixels().map(|p| p.0[0] as u32).sum();
-        assert!(
-            avg_bright > avg_default,
-            "exposure boost should brighten: default={avg_default} bright={avg_bright}"
+        assert_eq!(
+            first.file_name().and_then(|n| n.to_str()),
+            Some("photo-1.jpg")
+        );
+        assert_eq!(
+            second.file_name().and_then(|n| n.to_str()),
+            Some("photo-2.jpg")
         );
     }
 
     #[test]
-    fn export_default_params_identity() {
+    fn photo_pages_stream_then_finish() {
+        use crema_catalog::db::InsertPhoto;
+        use iced::futures::StreamExt;
+
         let dir = tempfile::tempdir().unwrap();
-        let path = dir.path().join("identity.png");
+        let path = dir.path().join("catalog.db").to_string_lossy().to_string();
+        let catalog = Catalog::open(&path).unwrap();
+        for i in 0..3 {
+            catalog
+                .insert_photo(&InsertPhoto {
+                    file_path: format!("/photos/{i}.jpg"),
+                    file_hash: format!("hash{i}"),
+                    file_size: 1,
+                    width: None,
+                    height: None,
+                    camera_make: None,
+                    camera_model: None,
+                    lens: None,
+                    focal_length: None,
+                    aperture: None,
+                    shutter_speed: None,
+                    iso: None,
+                    date_taken: None,
+                    thumbnail_path: None,
+                })
+                .unwrap();
+        }
+        drop(catalog);
+
+        let pages: Vec<PhotoPage> =
+            iced::futures::executor::block_on(stream_photo_pages(path).collect());
+        let (last, rest) = pages.split_last().unwrap();
+        assert!(last.done && last.photos.is_empty());
+        assert!(last.error.is_none());
+        assert_eq!(last.total, 3);
+        assert!(rest.iter().all(|p| !p.done));
+        assert_eq!(rest.iter().map(|p| p.photos.len()).sum::<usize>(), 3);
+    }
 
-        let buf = ImageBuf::from_data(2, 2, vec![0.5; 2 * 2 * 3]).unwrap();
-        export_image(buf, &EditParams::default(), &path);
+    #[test]
+    fn photo_pages_report_a_failed_read() {
+        use iced::futures::StreamExt;
 
-        let img = image::open(&path).unwrap().into_rgba8();
-        let first = img.pixels().next().unwrap().0;
-        for px in img.pixels() {
-            assert_eq!(px.0, first, "all pixels should be identical");
-        }
+        let dir = tempfile::tempdir().unwrap();
+        // Opening without migrations leaves an empty file with no tables.
+        let path = dir.path().join("empty.db").to_string_lossy().to_string();
+        let pages: Vec<PhotoPage> =
+            iced::futures::executor::block_on(stream_photo_pages(path).collect());
+        assert_eq!(pages.len(), 1);
+        assert!(pages[0].done);
+        assert!(pages[0].error.is_some());
     }
 
     #[test]
-    fn export_to_nonexistent_dir_fails_gracefully() {
-        let path = Path::new("/nonexistent/dir/photo.jpg");
-        let msg = export_image(test_image(), &EditParams::default(), path);
-        assert!(msg.starts_with("Export failed:"), "unexpected: {msg}");
+    fn thumbnail_aspect_reads_encoded_dimensions() {
+        let mut bytes = Vec::new();
+        image::RgbImage::new(20, 30)
+            .write_to(
+                &mut std::io::Cursor::new(&mut bytes),
+                image::ImageFormat::Jpeg,
+            )
+            .unwrap();
+        let aspect = thumbnail_aspect(&bytes).unwrap();
+        assert!((aspect - 2.0 / 3.0).abs() < 1e-6);
+        assert!(thumbnail_aspect(b"not an image").is_none());
     }
 
     #[test]
-    fn export_message_contains_path() {
+    fn offline_thumbnails_are_found_from_the_catalog() {
         let dir = tempfile::tempdir().unwrap();
-        let path = dir.path().join("result.jpg");
-
-        let msg = export_image(test_image(), &EditParams::default(), &path);
-        assert!(
-            msg.contains("result.jpg"),
-            "success message should contain filename: {msg}"
-        );
+        let cache = dir.path().join("cache");
+        let original = dir.path().join("photo.png");
+        image::RgbImage::from_pixel(8, 6, image::Rgb([120, 80, 40]))
+            .save(&original)
+            .unwrap();
+        let path = original.to_string_lossy().to_string();
+        let cached = load_thumbnail_bytes(1, &path, "hash", 0, None, Some(&cache)).unwrap();
+
+        std::fs::remove_file(&original).unwrap();
+        let offline = load_thumbnail_bytes(1, &path, "hash", 0, None, Some(&cache)).unwrap();
+        assert_eq!(offline, cached);
+        let moved = dir
+            .path()
+            .join("moved/photo.png")
+            .to_string_lossy()
+            .to_string();
+        let moved = load_thumbnail_bytes(1, &moved, "hash", 0, None, Some(&cache)).unwrap();
+        assert_eq!(moved, cached);
     }
 
     #[test]
-    fn gpu_preview_falls_back_to_cpu_when_denoise_is_active() {
-        assert!(gpu_supports_preview_params(&EditParams::default()));
-
-        let params = EditParams {
-            nr_luminance: 25.0,
-            ..EditParams::default()
-        };
-        assert!(!gpu_supports_preview_params(&params));
+    fn files_changed_in_place_get_a_new_thumbnail() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = dir.path().join("cache");
+        let original = dir.path().join("photo.png");
+        image::RgbImage::from_pixel(8, 6, image::Rgb([120, 80, 40]))
+            .save(&original)
+            .unwrap();
+        let path = original.to_string_lossy().to_string();
+        let before = load_thumbnail_bytes(1, &path, "hash", 0, None, Some(&cache)).unwrap();
+
+        image::RgbImage::from_pixel(16, 6, image::Rgb([20, 200, 40]))
+            .save(&original)
+            .unwrap();
+        let after = load_thumbnail_bytes(1, &path, "hash", 0, None, Some(&cache)).unwrap();
+        assert!((thumbnail_aspect(&before).unwrap() - 8.0 / 6.0).abs() < 0.1);
+        assert!((thumbnail_aspect(&after).unwrap() - 16.0 / 6.0).abs() < 0.1);
     }
 
     #[test]
-    fn unique_export_path_avoids_existing_and_reserved_names() {
+    fn new_edits_replace_cached_edited_thumbnails() {
         let dir = tempfile::tempdir().unwrap();
-        let existing = dir.path().join("photo.jpg");
-        std::fs::write(&existing, b"already here").unwrap();
-
-        let mut used = std::collections::HashSet::new();
-        let first = unique_export_path(dir.path(), "photo", "jpg", &mut used);
-        let second = unique_export_path(dir.path(), "photo", "jpg", &mut used);
+        let cache_dir = dir.path().join("cache");
+        let original = dir.path().join("photo.png");
+        image::RgbImage::from_pixel(8, 6, image::Rgb([120, 80, 40]))
+            .save(&original)
+            .unwrap();
+        let path = original.to_string_lossy().to_string();
+        let first = test_params();
+        let second = EditParams {
+            exposure: first.exposure + 1.0,
+            ..first.clone()
+        };
+        for edits in [None, Some(&first), Some(&second)] {
+            load_thumbnail_bytes(1, &path, "ab12", 0, edits, Some(&cache_dir)).unwrap();
+        }
+        // A duplicate of the same file keeps its own edited thumbnail.
+        load_thumbnail_bytes(2, &path, "ab12", 0, Some(&first), Some(&cache_dir)).unwrap();
+
+        let cache = ThumbnailCache::new(cache_dir).unwrap();
+        let stamp = file_stamp(&original).unwrap();
+        let cached = |photo_id, edits| {
+            let variant = thumbnail_variant_key(photo_id, "ab12", 0, edits);
+            cache.has_thumbnail(&format!("{variant}-v{stamp}"))
+        };
+        assert!(cached(1, None));
+        assert!(!cached(1, Some(&first)));
+        assert!(cached(1, Some(&second)));
+        assert!(cached(2, Some(&first)));
+    }
 
-        assert_eq!(
-            first.file_name().and_then(|n| n.to_str()),
-            Some("photo-1.jpg")
+    #[test]
+    fn thumbnail_variants_have_their_own_keys() {
+        let hash = "0123abcd";
+        let edited = test_params();
+        let original = thumbnail_variant_key(1, hash, 0, None);
+        assert_eq!(original, "0123abcd-photo1");
+        assert_ne!(thumbnail_variant_key(1, hash, 0, Some(&edited)), original);
+        assert_ne!(
+            thumbnail_variant_key(1, hash, 0, Some(&edited)),
+            thumbnail_variant_key(1, hash, 0, Some(&EditParams::default()))
         );
+        assert_ne!(thumbnail_variant_key(1, hash, 2, Some(&edited)), original);
+        assert_ne!(thumbnail_variant_key(2, hash, 0, None), original);
+    }
+
+    #[test]
+    fn nearest_folder_override_wins() {
+        let overrides = HashMap::from([
+            ("/shoots".to_string(), ThumbnailFidelity::Accurate),
+            ("/shoots/raw".to_string(), ThumbnailFidelity::Fast),
+        ]);
+        let fidelity = |path| folder_override(&overrides, path);
+        assert_eq!(fidelity("/shoots/a.jpg"), Some(ThumbnailFidelity::Accurate));
+        assert_eq!(fidelity("/shoots/raw/b.nef"), Some(ThumbnailFidelity::Fast));
         assert_eq!(
-            second.file_name().and_then(|n| n.to_str()),
-            Some("photo-2.jpg")
+            fidelity("/shoots/client/day1/c.nef"),
+            Some(ThumbnailFidelity::Accurate)
         );
+        assert_eq!(fidelity("/shootsx/d.jpg"), None);
     }
 }
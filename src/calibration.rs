@@ -0,0 +1,408 @@
+//! Sensor and lens calibration stored in the catalog and applied to every
+//! full decode, before any edit: master darks matched by capture settings,
+//! dead pixel maps matched by camera serial and flat fields matched by
+//! camera model and lens, after any hand-entered camera or lens override.
+//! Darks and dead pixels are corrected on a RAW file's photosites, before
+//! demosaic, so only RAWs get them.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{Context, Result, bail};
+use tracing::{error, warn};
+
+use crema_catalog::db::Catalog;
+use crema_catalog::models::{
+    CameraDefectMap, DarkFrameSettings, LensFlatField, MasterDarkId, MasterDarkInfo,
+    MetadataOverride,
+};
+use crema_core::dark_frame::{DarkStack, MasterDark};
+use crema_core::defects::DefectMap;
+use crema_core::flat_field::FlatField;
+use crema_core::image_buf::ImageBuf;
+use crema_core::raw::SensorCorrections;
+use crema_metadata::exif::ExifData;
+
+/// Relative difference in exposure time still treated as the same shutter
+/// speed, to absorb rounding in how cameras record it.
+const EXPOSURE_TOLERANCE: f64 = 0.02;
+/// Thermal signal roughly doubles every 6 degrees; within this many the
+/// dark still matches.
+const TEMPERATURE_TOLERANCE: f64 = 3.0;
+
+#[derive(Debug, Clone, Default)]
+pub struct Calibration {
+    pub defect_maps: Vec<CameraDefectMap>,
+    pub flat_fields: Vec<LensFlatField>,
+    pub master_darks: Vec<MasterDarkInfo>,
+    /// Master dark pixels by id, loaded only with dark subtraction on.
+    /// Empty turns it off.
+    pub darks: HashMap<MasterDarkId, Arc<MasterDark>>,
+    /// Camera and lens overrides by file path, for photos whose EXIF is
+    /// missing or wrong.
+    pub overrides: HashMap<String, MetadataOverride>,
+}
+
+impl Calibration {
+    /// Read every correction from `catalog`, with the master darks' pixels
+    /// when `with_darks` turns dark subtraction on.
+    pub fn load(catalog: &Catalog, with_darks: bool) -> Result<Self> {
+        let master_darks = catalog.list_master_darks()?;
+        let mut darks = HashMap::new();
+        if with_darks {
+            for info in &master_darks {
+                match catalog.load_master_dark(info.id) {
+                    Ok(dark) => {
+                        darks.insert(info.id, Arc::new(dark));
+                    }
+                    Err(err) => error!(%err, id = info.id, "failed to load master dark"),
+                }
+            }
+        }
+        Ok(Self {
+            defect_maps: catalog.list_defect_maps()?,
+            flat_fields: catalog.list_flat_fields()?,
+            master_darks,
+            darks,
+            overrides: catalog.metadata_overrides_by_path()?,
+        })
+    }
+
+    /// EXIF for the file at `path` with its override laid over it, which
+    /// is what corrections should be matched against.
+    pub fn exif_for(&self, path: &Path) -> Option<ExifData> {
+        let exif = ExifData::from_file(path).ok();
+        let Some(fixup) = self.overrides.get(path.to_string_lossy().as_ref()) else {
+            return exif;
+        };
+        let mut exif = exif.unwrap_or_default();
+        fixup.apply_to_exif(&mut exif);
+        Some(exif)
+    }
+
+    /// Decode frame `frame` of `path` with every correction that matches
+    /// `exif`, the file's EXIF from [`Calibration::exif_for`]. The dark and
+    /// stuck photosites are corrected before demosaic, and so before the
+    /// flat field's gains can amplify them.
+    pub fn decode(&self, path: &Path, frame: u32, exif: Option<&ExifData>) -> Result<ImageBuf> {
+        let sensor = SensorCorrections {
+            dark: exif
+                .and_then(|exif| self.master_dark_for(exif))
+                .and_then(|info| self.darks.get(&info.id))
+                .map(|dark| dark.as_ref()),
+            defects: exif.and_then(|exif| self.defect_map_for(exif)),
+        };
+        let mut buf = crema_core::raw::load_frame_corrected(path, frame, &sensor)?;
+        self.apply(exif, &mut buf);
+        Ok(buf)
+    }
+
+    /// The corrections made to a decoded `buf`.
+    fn apply(&self, exif: Option<&ExifData>, buf: &mut ImageBuf) {
+        if let Some(field) = exif.and_then(|exif| self.flat_field_for(exif)) {
+            field.apply(buf);
+        }
+    }
+
+    /// The master dark shot at the same camera, ISO and shutter speed as
+    /// `exif`, preferring the closest temperature. A dark whose temperature
+    /// is known to be out of tolerance never matches. One that can't be
+    /// checked, with the temperature missing from it or the photo, is used
+    /// only when no dark is known to match.
+    pub fn master_dark_for(&self, exif: &ExifData) -> Option<&MasterDarkInfo> {
+        let model = exif.camera_model.as_deref()?;
+        let iso = exif.iso?;
+        let secs = exif.exposure_secs?;
+        let temperature_gap =
+            |info: &MasterDarkInfo| Some((info.settings.temperature? - exif.temperature?).abs());
+        let (verified, unverified): (Vec<_>, Vec<_>) = self
+            .master_darks
+            .iter()
+            .filter(|info| {
+                let s = &info.settings;
+                s.camera_model == model
+                    && s.iso == iso
+                    && (s.exposure_secs - secs).abs() <= s.exposure_secs * EXPOSURE_TOLERANCE
+                    && temperature_gap(info).is_none_or(|gap| gap <= TEMPERATURE_TOLERANCE)
+            })
+            .partition(|info| temperature_gap(info).is_some());
+        if let Some(best) = verified.into_iter().min_by(|a, b| {
+            temperature_gap(a)
+                .unwrap_or_default()
+                .total_cmp(&temperature_gap(b).unwrap_or_default())
+        }) {
+            return Some(best);
+        }
+        let fallback = unverified.into_iter().next()?;
+        warn!(
+            id = fallback.id,
+            "sensor temperature unknown; using a master dark that may not match"
+        );
+        Some(fallback)
+    }
+
+    fn defect_map_for(&self, exif: &ExifData) -> Option<&DefectMap> {
+        let serial = exif.serial_number.as_deref()?;
+        self.defect_maps
+            .iter()
+            .find(|m| m.camera_serial == serial)
+            .map(|m| &m.map)
+    }
+
+    fn flat_field_for(&self, exif: &ExifData) -> Option<&FlatField> {
+        let model = exif.camera_model.as_deref()?;
+        let lens = exif.lens.as_deref().unwrap_or("");
+        self.flat_fields
+            .iter()
+            .find(|f| f.camera_model == model && f.lens == lens)
+            .map(|f| &f.field)
+    }
+}
+
+/// A dead pixel map found in a dark frame, ready to store.
+#[derive(Debug, Clone)]
+pub struct DetectedDefectMap {
+    pub camera_serial: String,
+    pub camera_model: Option<String>,
+    pub map: DefectMap,
+}
+
+pub fn detect_defect_map(path: &Path) -> Result<DetectedDefectMap> {
+    let exif = ExifData::from_file(path)?;
+    let Some(camera_serial) = exif.serial_number else {
+        bail!("the file has no camera serial number in its EXIF");
+    };
+    let dark = crema_core::raw::load_sensor_data(path)?;
+    let map = DefectMap::detect(&dark)?;
+    Ok(DetectedDefectMap {
+        camera_serial,
+        camera_model: exif.camera_model,
+        map,
+    })
+}
+
+/// A flat field measured from a calibration frame, ready to store.
+#[derive(Debug, Clone)]
+pub struct DetectedFlatField {
+    pub camera_model: String,
+    pub lens: String,
+    pub field: FlatField,
+}
+
+pub fn detect_flat_field(path: &Path) -> Result<DetectedFlatField> {
+    let exif = ExifData::from_file(path)?;
+    let Some(camera_model) = exif.camera_model else {
+        bail!("the file has no camera model in its EXIF");
+    };
+    let flat = crema_core::raw::load_any(path)?;
+    let field = FlatField::from_frame(&flat)?;
+    Ok(DetectedFlatField {
+        camera_model,
+        lens: exif.lens.unwrap_or_default(),
+        field,
+    })
+}
+
+/// A master dark averaged from a series of dark frames, ready to store.
+#[derive(Debug, Clone)]
+pub struct DetectedMasterDark {
+    pub settings: DarkFrameSettings,
+    pub frame_count: u32,
+    pub dark: MasterDark,
+}
+
+/// Average `paths` into a master dark. Every frame must share the camera,
+/// ISO and shutter speed; the temperature is their mean.
+pub fn build_master_dark(paths: &[PathBuf]) -> Result<DetectedMasterDark> {
+    let mut settings: Option<DarkFrameSettings> = None;
+    let mut temperatures = Vec::new();
+    let mut stack = DarkStack::new();
+    for path in paths {
+        let name = path.display();
+        let exif = ExifData::from_file(path)?;
+        let frame = DarkFrameSettings {
+            camera_model: exif
+                .camera_model
+                .with_context(|| format!("{name} has no camera model"))?,
+            iso: exif.iso.with_context(|| format!("{name} has no ISO"))?,
+            exposure_secs: exif
+                .exposure_secs
+                .with_context(|| format!("{name} has no exposure time"))?,
+            temperature: None,
+        };
+        if let Some(first) = &settings
+            && (first.camera_model != frame.camera_model
+                || first.iso != frame.iso
+                || (first.exposure_secs - frame.exposure_secs).abs()
+                    > first.exposure_secs * EXPOSURE_TOLERANCE)
+        {
+            bail!("{name} was shot with different camera, ISO or shutter settings");
+        }
+        temperatures.extend(exif.temperature);
+        stack.add(&crema_core::raw::load_sensor_data(path)?)?;
+        settings.get_or_insert(frame);
+    }
+
+    let Some(mut settings) = settings else {
+        bail!("no dark frames selected");
+    };
+    if !temperatures.is_empty() {
+        settings.temperature = Some(temperatures.iter().sum::<f64>() / temperatures.len() as f64);
+    }
+    let frame_count = stack.count();
+    Ok(DetectedMasterDark {
+        settings,
+        frame_count,
+        dark: stack.finish()?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn exif(model: &str, lens: Option<&str>) -> ExifData {
+        ExifData {
+            camera_model: Some(model.into()),
+            lens: lens.map(Into::into),
+            ..ExifData::default()
+        }
+    }
+
+    fn halving_field() -> FlatField {
+        FlatField {
+            width: 1,
+            height: 1,
+            gains: vec![0.5; 3],
+        }
+    }
+
+    fn dark(id: i64, secs: f64, temperature: Option<f64>) -> MasterDarkInfo {
+        MasterDarkInfo {
+            id,
+            settings: DarkFrameSettings {
+                camera_model: "Z 7".into(),
+                iso: 800,
+                exposure_secs: secs,
+                temperature,
+            },
+            frame_count: 10,
+            width: 4,
+            height: 4,
+            created_at: String::new(),
+        }
+    }
+
+    fn light(secs: f64, temperature: Option<f64>) -> ExifData {
+        ExifData {
+            iso: Some(800),
+            exposure_secs: Some(secs),
+            temperature,
+            ..exif("Z 7", None)
+        }
+    }
+
+    #[test]
+    fn master_dark_matches_settings_and_nearest_temperature() {
+        let calibration = Calibration {
+            master_darks: vec![
+                dark(1, 30.0, Some(10.0)),
+                dark(2, 30.0, Some(14.0)),
+                dark(3, 120.0, None),
+            ],
+            ..Calibration::default()
+        };
+        let id = |exif: &ExifData| calibration.master_dark_for(exif).map(|d| d.id);
+
+        assert_eq!(id(&light(30.0, Some(13.0))), Some(2));
+        assert_eq!(id(&light(30.0, Some(9.0))), Some(1));
+        assert_eq!(id(&light(30.0, Some(25.0))), None);
+        assert_eq!(id(&light(120.0, Some(25.0))), Some(3));
+        assert_eq!(id(&light(60.0, None)), None);
+        // Without a temperature neither dark can be checked, so the first
+        // that matches the settings is the fallback.
+        assert_eq!(id(&light(30.0, None)), Some(1));
+        assert_eq!(
+            id(&ExifData {
+                iso: Some(1600),
+                ..light(30.0, None)
+            }),
+            None
+        );
+    }
+
+    #[test]
+    fn verified_temperatures_beat_unknown_ones() {
+        let calibration = Calibration {
+            master_darks: vec![dark(1, 30.0, None), dark(2, 30.0, Some(12.0))],
+            ..Calibration::default()
+        };
+        let id = |exif: &ExifData| calibration.master_dark_for(exif).map(|d| d.id);
+
+        assert_eq!(id(&light(30.0, Some(11.0))), Some(2));
+        assert_eq!(id(&light(30.0, Some(20.0))), Some(1));
+    }
+
+    #[test]
+    fn flat_field_matches_camera_and_lens() {
+        let calibration = Calibration {
+            flat_fields: vec![LensFlatField {
+                camera_model: "Z 7".into(),
+                lens: "50mm".into(),
+                field: halving_field(),
+                created_at: String::new(),
+            }],
+            ..Calibration::default()
+        };
+        let mut buf = ImageBuf::from_data(2, 2, vec![0.8; 12]).unwrap();
+
+        calibration.apply(Some(&exif("Z 7", Some("85mm"))), &mut buf);
+        assert_eq!(buf.data[0], 0.8);
+        calibration.apply(Some(&exif("Z 6", Some("50mm"))), &mut buf);
+        assert_eq!(buf.data[0], 0.8);
+        calibration.apply(Some(&exif("Z 7", Some("50mm"))), &mut buf);
+        assert_eq!(buf.data[0], 0.4);
+    }
+
+    #[test]
+    fn override_supplies_the_lens_exif_lacks() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("adapted.png");
+        image::RgbImage::new(1, 1).save(&path).unwrap();
+        let fixup = MetadataOverride {
+            camera_model: Some("Z 7".into()),
+            lens: Some("50mm".into()),
+            ..Default::default()
+        };
+        let calibration = Calibration {
+            flat_fields: vec![LensFlatField {
+                camera_model: "Z 7".into(),
+                lens: "50mm".into(),
+                field: halving_field(),
+                created_at: String::new(),
+            }],
+            overrides: HashMap::from([(path.to_string_lossy().to_string(), fixup)]),
+            ..Calibration::default()
+        };
+        let mut buf = ImageBuf::from_data(1, 1, vec![0.6; 3]).unwrap();
+        calibration.apply(calibration.exif_for(&path).as_ref(), &mut buf);
+        assert!((buf.data[0] - 0.3).abs() < 1e-6);
+    }
+
+    #[test]
+    fn missing_lens_matches_the_lensless_entry() {
+        let calibration = Calibration {
+            flat_fields: vec![LensFlatField {
+                camera_model: "Z 7".into(),
+                lens: String::new(),
+                field: halving_field(),
+                created_at: String::new(),
+            }],
+            ..Calibration::default()
+        };
+        let mut buf = ImageBuf::from_data(1, 1, vec![0.6; 3]).unwrap();
+        calibration.apply(Some(&exif("Z 7", None)), &mut buf);
+        assert!((buf.data[0] - 0.3).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,212 @@
+//! `--demo` fixture mode for UI work. The app boots on a throwaway
+//! in-memory catalog filled with synthetic photos, so views and widgets
+//! can be exercised without importing a personal library. Thumbnails are
+//! generated from the synthetic files and never touch the disk cache.
+
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+use anyhow::{Context, Result};
+use image::{Rgb, RgbImage};
+use tracing::error;
+
+use crema_catalog::db::{Catalog, InsertPhoto};
+use crema_core::image_buf::EditParams;
+
+const DEMO_FLAG: &str = "--demo";
+const PHOTO_COUNT: usize = 36;
+const LONG_EDGE: u32 = 1200;
+const SHORT_EDGE: u32 = 800;
+
+const CAMERAS: [(&str, &str, &str, f64, f64); 3] = [
+    ("FUJIFILM", "X-T5", "XF23mmF1.4 R LM WR", 23.0, 1.4),
+    ("SONY", "ILCE-7M4", "FE 24-70mm F2.8 GM II", 35.0, 2.8),
+    ("Canon", "EOS R6", "RF50mm F1.8 STM", 50.0, 1.8),
+];
+
+/// Ratings cycle through every star count and a reject.
+const RATINGS: [i32; 7] = [0, 3, 5, 1, 4, -1, 2];
+
+/// A shared-cache in-memory database dies with its last connection, and
+/// the app reopens its catalog by path all the time, so one connection
+/// is parked here for the life of the process.
+static KEEP_ALIVE: OnceLock<Mutex<Catalog>> = OnceLock::new();
+
+/// Where the synthetic photos were written, removed by [`clean_up`].
+static DIR: OnceLock<PathBuf> = OnceLock::new();
+
+pub fn requested(args: &[String]) -> bool {
+    args.iter().skip(1).any(|arg| arg == DEMO_FLAG)
+}
+
+/// Write the synthetic photos and build the demo catalog, returning the
+/// path the app should open it by.
+pub fn open() -> Result<String> {
+    let dir = std::env::temp_dir().join(format!("crema-demo-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).with_context(|| format!("failed to create {}", dir.display()))?;
+    // SQLite only shares an in-memory database between connections that
+    // open the same URI; naming it after the folder keeps smart previews
+    // and other catalog-relative files inside it.
+    let path = format!(
+        "file:{}?mode=memory&cache=shared",
+        dir.join("catalog.db").display()
+    );
+    DIR.get_or_init(|| dir.clone());
+    let catalog = Catalog::open(&path)?;
+    populate(&catalog, &dir)?;
+    KEEP_ALIVE.get_or_init(|| Mutex::new(catalog));
+    Ok(path)
+}
+
+/// Remove the synthetic photos and anything written beside them, once the
+/// app has closed.
+pub fn clean_up() {
+    let Some(dir) = DIR.get() else {
+        return;
+    };
+    if let Err(err) = std::fs::remove_dir_all(dir) {
+        error!(%err, dir = %dir.display(), "failed to remove demo files");
+    }
+}
+
+/// Add `PHOTO_COUNT` synthetic photos to the catalog, writing their files
+/// into `dir`. Returns the paths written.
+pub fn populate(catalog: &Catalog, dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut written = Vec::with_capacity(PHOTO_COUNT);
+    for index in 0..PHOTO_COUNT {
+        let path = dir.join(format!("DEMO_{:04}.jpg", index + 1));
+        let image = synthetic_photo(index);
+        image
+            .save(&path)
+            .with_context(|| format!("failed to write {}", path.display()))?;
+        let file_size = std::fs::metadata(&path)?.len() as i64;
+        let (make, model, lens, focal_length, aperture) = CAMERAS[index % CAMERAS.len()];
+        let photo = InsertPhoto {
+            file_path: path.to_string_lossy().to_string(),
+            file_hash: blake3::hash(image.as_raw()).to_hex().to_string(),
+            file_size,
+            width: Some(image.width()),
+            height: Some(image.height()),
+            camera_make: Some(make.into()),
+            camera_model: Some(model.into()),
+            lens: Some(lens.into()),
+            focal_length: Some(focal_length),
+            aperture: Some(aperture),
+            shutter_speed: Some(format!("1/{}", 60 << (index % 5))),
+            iso: Some(100 << (index % 6)),
+            date_taken: Some(date_taken(index)),
+            thumbnail_path: None,
+        };
+        let Some(id) = catalog.insert_photo(&photo)? else {
+            continue;
+        };
+        catalog.set_rating(id, RATINGS[index % RATINGS.len()])?;
+        if index % 3 == 0 {
+            let params = EditParams {
+                exposure: (index % 5) as f32 * 0.3 - 0.6,
+                contrast: 0.2,
+                ..EditParams::default()
+            };
+            catalog.save_edits(id, &params)?;
+        }
+        written.push(path);
+    }
+    Ok(written)
+}
+
+/// Spread shots over two years, a few per month, so the date sidebar and
+/// sorting have something to show.
+fn date_taken(index: usize) -> String {
+    let year = 2024 + index / 18;
+    let month = 1 + (index / 2) % 9 + (index % 2) * 3;
+    let day = 1 + (index * 7) % 28;
+    let hour = 7 + index % 12;
+    format!(
+        "{year}-{month:02}-{day:02} {hour:02}:{:02}:00",
+        (index * 13) % 60
+    )
+}
+
+/// A sky-over-ground landscape with a sun, its palette shifted per photo.
+/// Every fifth one is portrait so the grid gets mixed aspect ratios.
+fn synthetic_photo(index: usize) -> RgbImage {
+    let (width, height) = if index % 5 == 4 {
+        (SHORT_EDGE, LONG_EDGE)
+    } else {
+        (LONG_EDGE, SHORT_EDGE)
+    };
+    let hue = (index as f32 * 0.137).fract();
+    let sky = hue_to_rgb(hue);
+    let ground = hue_to_rgb((hue + 0.4).fract());
+    let horizon = height as f32 * (0.45 + 0.2 * ((index * 7) % 5) as f32 / 5.0);
+    let sun = (
+        width as f32 * (0.2 + 0.6 * ((index * 3) % 7) as f32 / 7.0),
+        horizon * 0.5,
+    );
+    let sun_radius = SHORT_EDGE as f32 * 0.08;
+
+    RgbImage::from_fn(width, height, |x, y| {
+        let (x, y) = (x as f32, y as f32);
+        let color = if y < horizon {
+            let t = y / horizon;
+            sky.map(|c| 0.25 + 0.75 * c * (0.4 + 0.6 * t))
+        } else {
+            let t = (y - horizon) / (height as f32 - horizon);
+            ground.map(|c| c * (0.6 - 0.45 * t))
+        };
+        let distance = ((x - sun.0).powi(2) + (y - sun.1).powi(2)).sqrt();
+        let glow = (1.0 - distance / sun_radius).clamp(0.0, 1.0);
+        Rgb(color.map(|c| ((c + glow).min(1.0) * 255.0) as u8))
+    })
+}
+
+fn hue_to_rgb(hue: f32) -> [f32; 3] {
+    let channel = |offset: f32| {
+        let h = (hue + offset).fract() * 6.0;
+        ((h - 3.0).abs() - 1.0).clamp(0.0, 1.0)
+    };
+    [channel(0.0), channel(2.0 / 3.0), channel(1.0 / 3.0)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn populate_fills_a_varied_catalog() {
+        let dir = tempfile::tempdir().unwrap();
+        let catalog = Catalog::open_in_memory().unwrap();
+        let written = populate(&catalog, dir.path()).unwrap();
+        assert_eq!(written.len(), PHOTO_COUNT);
+
+        let photos = catalog.list_photos().unwrap();
+        assert_eq!(photos.len(), PHOTO_COUNT);
+        assert!(photos.iter().any(|p| p.rating == 5));
+        assert!(photos.iter().any(|p| p.rating == -1));
+        assert!(photos.iter().any(|p| p.height > p.width));
+        let edited = photos
+            .iter()
+            .filter(|p| catalog.get_edits(p.id).unwrap().is_some())
+            .count();
+        assert_eq!(edited, PHOTO_COUNT / 3);
+
+        // The files decode like any imported photo.
+        let bytes = crema_thumbnails::generator::fast_thumbnail(&written[0]).unwrap();
+        assert!(!bytes.is_empty());
+    }
+
+    #[test]
+    fn demo_catalog_survives_reopening_by_path() {
+        let path = open().unwrap();
+        let reopened = Catalog::open(&path).unwrap();
+        assert_eq!(reopened.list_photos().unwrap().len(), PHOTO_COUNT);
+
+        let previews = crate::smart_preview::previews_dir(&path);
+        assert!(previews.starts_with(std::env::temp_dir()));
+
+        let dir = previews.parent().unwrap().to_path_buf();
+        assert!(dir.is_dir());
+        clean_up();
+        assert!(!dir.exists());
+    }
+}
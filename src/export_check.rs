@@ -0,0 +1,154 @@
+//! Checks run on an export's settings before it starts, so output likely to
+//! disappoint is caught while the settings can still change. Each warning
+//! can be turned off for good from the dialog that shows it.
+
+use std::collections::BTreeSet;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crema_core::image_buf::EditParams;
+use crema_export::format::ExportEncoding;
+
+/// Edits past any of these stretch the darkest few 8-bit levels far enough
+/// that smooth gradients can show steps.
+const BANDING_SHADOWS: f32 = 50.0;
+const BANDING_BLACKS: f32 = 40.0;
+const BANDING_EXPOSURE: f32 = 1.5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportWarning {
+    /// An 8-bit export of an edit with big shadow lifts.
+    Banding,
+    /// A wide-gamut export to a format that can't carry a profile.
+    UntaggedWideGamut,
+}
+
+impl ExportWarning {
+    pub fn title(self) -> &'static str {
+        match self {
+            Self::Banding => "Gradients may band",
+            Self::UntaggedWideGamut => "Colors may shift",
+        }
+    }
+
+    pub fn detail(self) -> &'static str {
+        match self {
+            Self::Banding => {
+                "This edit lifts the shadows a long way. At 8 bits per channel, smooth dark gradients can show visible steps. A 16-bit TIFF keeps every level."
+            }
+            Self::UntaggedWideGamut => {
+                "This file is in Display P3, but the format can't carry an ICC profile. Most apps will read it as sRGB and show dull, shifted colors. Export as JPEG, PNG or TIFF to embed the profile, or switch to sRGB."
+            }
+        }
+    }
+}
+
+/// Whether `params` stretch the tones enough to band at 8 bits.
+pub fn pushed_hard(params: &EditParams) -> bool {
+    params.shadows >= BANDING_SHADOWS
+        || params.blacks >= BANDING_BLACKS
+        || params.exposure >= BANDING_EXPOSURE
+}
+
+/// The warnings for exporting an edit made with `params` to `path`,
+/// without those in `suppressed`.
+pub fn check(
+    params: &EditParams,
+    encoding: &ExportEncoding,
+    path: &Path,
+    suppressed: &BTreeSet<ExportWarning>,
+) -> Vec<ExportWarning> {
+    let mut warnings = Vec::new();
+    if encoding.bit_depth(path) == 8 && pushed_hard(params) {
+        warnings.push(ExportWarning::Banding);
+    }
+    if !encoding.is_tagged(path) {
+        warnings.push(ExportWarning::UntaggedWideGamut);
+    }
+    warnings.retain(|w| !suppressed.contains(w));
+    warnings
+}
+
+/// The warnings for a batch export of edits made with `params`. Batches
+/// are written as 8-bit sRGB JPEGs, so only banding can apply.
+pub fn check_batch<'a>(
+    params: impl IntoIterator<Item = &'a EditParams>,
+    suppressed: &BTreeSet<ExportWarning>,
+) -> Vec<ExportWarning> {
+    let mut warnings = Vec::new();
+    if params.into_iter().any(pushed_hard) {
+        warnings.push(ExportWarning::Banding);
+    }
+    warnings.retain(|w| !suppressed.contains(w));
+    warnings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crema_export::format::ExportColorSpace;
+
+    #[test]
+    fn banding_warns_for_pushed_8_bit_exports() {
+        let lifted = EditParams {
+            shadows: 80.0,
+            ..EditParams::default()
+        };
+        let srgb = ExportEncoding::default();
+        let none = BTreeSet::new();
+        let jpeg = Path::new("out.jpg");
+        assert!(check(&EditParams::default(), &srgb, jpeg, &none).is_empty());
+        assert_eq!(check(&lifted, &srgb, jpeg, &none), [ExportWarning::Banding]);
+
+        let deep = ExportEncoding {
+            tiff_16bit: true,
+            ..srgb
+        };
+        assert!(check(&lifted, &deep, Path::new("out.tif"), &none).is_empty());
+        assert!(
+            check(
+                &lifted,
+                &srgb,
+                jpeg,
+                &BTreeSet::from([ExportWarning::Banding])
+            )
+            .is_empty()
+        );
+    }
+
+    #[test]
+    fn batches_warn_when_any_photo_is_pushed() {
+        let lifted = EditParams {
+            blacks: 60.0,
+            ..EditParams::default()
+        };
+        let plain = EditParams::default();
+        let none = BTreeSet::new();
+        assert!(check_batch([&plain, &plain], &none).is_empty());
+        assert_eq!(
+            check_batch([&plain, &lifted], &none),
+            [ExportWarning::Banding]
+        );
+        let silenced = BTreeSet::from([ExportWarning::Banding]);
+        assert!(check_batch([&lifted], &silenced).is_empty());
+    }
+
+    #[test]
+    fn untagged_wide_gamut_warns_for_formats_without_profiles() {
+        let p3 = ExportEncoding {
+            color_space: ExportColorSpace::DisplayP3,
+            tiff_16bit: true,
+            ..ExportEncoding::default()
+        };
+        let none = BTreeSet::new();
+        let params = EditParams::default();
+        assert!(check(&params, &p3, Path::new("out.png"), &none).is_empty());
+        assert!(check(&params, &p3, Path::new("out.tif"), &none).is_empty());
+        assert_eq!(
+            check(&params, &p3, Path::new("out.gif"), &none),
+            [ExportWarning::UntaggedWideGamut]
+        );
+    }
+}
@@ -0,0 +1,142 @@
+//! Export-only crops. Each preset trims the developed frame to a fixed
+//! aspect ratio at export time, so one edit can be delivered as several
+//! crops without touching the stored crop.
+
+use serde::{Deserialize, Serialize};
+
+use crema_core::image_buf::EditParams;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportCrop {
+    /// The develop crop, unchanged.
+    AsEdited,
+    /// The largest centered `w:h` rectangle inside the develop crop.
+    Aspect(u32, u32),
+}
+
+impl ExportCrop {
+    pub const ALL: [ExportCrop; 6] = [
+        ExportCrop::AsEdited,
+        ExportCrop::Aspect(1, 1),
+        ExportCrop::Aspect(4, 5),
+        ExportCrop::Aspect(3, 2),
+        ExportCrop::Aspect(2, 3),
+        ExportCrop::Aspect(16, 9),
+    ];
+
+    pub fn label(self) -> String {
+        match self {
+            ExportCrop::AsEdited => "As Edited".into(),
+            ExportCrop::Aspect(w, h) => format!("{w}:{h}"),
+        }
+    }
+
+    /// Appended to the file stem when several crops are exported at once.
+    pub fn file_suffix(self) -> String {
+        match self {
+            ExportCrop::AsEdited => String::new(),
+            ExportCrop::Aspect(w, h) => format!("-{w}x{h}"),
+        }
+    }
+
+    /// `params` with the crop narrowed to this preset's aspect ratio, for a
+    /// source image of `width` x `height` pixels.
+    pub fn apply(self, params: &EditParams, width: u32, height: u32) -> EditParams {
+        let mut out = params.clone();
+        let ExportCrop::Aspect(aw, ah) = self else {
+            return out;
+        };
+        if aw == 0 || ah == 0 || width == 0 || height == 0 {
+            return out;
+        }
+
+        let crop_w = params.crop_w * width as f32;
+        let crop_h = params.crop_h * height as f32;
+        let target = aw as f32 / ah as f32;
+        if crop_w / crop_h > target {
+            let new_w = crop_h * target;
+            out.crop_x += (crop_w - new_w) / 2.0 / width as f32;
+            out.crop_w = new_w / width as f32;
+        } else {
+            let new_h = crop_w / target;
+            out.crop_y += (crop_h - new_h) / 2.0 / height as f32;
+            out.crop_h = new_h / height as f32;
+        }
+        out
+    }
+}
+
+/// The crops to export, in preset order. Nothing selected means the
+/// develop crop alone.
+pub fn selected(crops: &[ExportCrop]) -> Vec<ExportCrop> {
+    let chosen: Vec<_> = ExportCrop::ALL
+        .into_iter()
+        .filter(|c| crops.contains(c))
+        .collect();
+    if chosen.is_empty() {
+        vec![ExportCrop::AsEdited]
+    } else {
+        chosen
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pixels(params: &EditParams, width: u32, height: u32) -> (f32, f32, f32, f32) {
+        (
+            params.crop_x * width as f32,
+            params.crop_y * height as f32,
+            params.crop_w * width as f32,
+            params.crop_h * height as f32,
+        )
+    }
+
+    #[test]
+    fn square_from_landscape_is_centered() {
+        let params = EditParams::default();
+        let out = ExportCrop::Aspect(1, 1).apply(&params, 600, 400);
+        let (x, y, w, h) = pixels(&out, 600, 400);
+        assert!((x - 100.0).abs() < 1e-3 && y.abs() < 1e-3);
+        assert!((w - 400.0).abs() < 1e-3 && (h - 400.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn stays_inside_the_develop_crop() {
+        let params = EditParams {
+            crop_x: 0.1,
+            crop_y: 0.2,
+            crop_w: 0.5,
+            crop_h: 0.5,
+            exposure: 1.0,
+            ..EditParams::default()
+        };
+        let out = ExportCrop::Aspect(4, 5).apply(&params, 1000, 1000);
+        let (x, y, w, h) = pixels(&out, 1000, 1000);
+        assert!((w / h - 0.8).abs() < 1e-4);
+        assert!((h - 500.0).abs() < 1e-3 && (x - 150.0).abs() < 1e-3);
+        assert!((y - 200.0).abs() < 1e-3);
+        assert_eq!(out.exposure, 1.0);
+        assert_eq!(params.crop_w, 0.5, "stored edit is untouched");
+    }
+
+    #[test]
+    fn as_edited_is_identity() {
+        let params = EditParams {
+            crop_w: 0.7,
+            ..EditParams::default()
+        };
+        assert_eq!(ExportCrop::AsEdited.apply(&params, 300, 200), params);
+    }
+
+    #[test]
+    fn selection_defaults_and_keeps_preset_order() {
+        assert_eq!(selected(&[]), [ExportCrop::AsEdited]);
+        assert_eq!(
+            selected(&[ExportCrop::Aspect(16, 9), ExportCrop::Aspect(1, 1)]),
+            [ExportCrop::Aspect(1, 1), ExportCrop::Aspect(16, 9)]
+        );
+    }
+}
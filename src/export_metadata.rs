@@ -0,0 +1,285 @@
+//! Templates for the metadata written into exports. Each field is text
+//! with `{token}`s filled from the photo's catalog entry, so a delivery can
+//! carry the photo's own caption, a fixed credit line, or both. The crops
+//! exported act as presets: each can have its own template, falling back
+//! to the one for every export.
+
+use serde::{Deserialize, Serialize};
+
+use crema_catalog::models::Photo;
+use crema_metadata::embed::EmbeddedMetadata;
+
+use crate::export_crop::ExportCrop;
+use crate::widgets::batch_metadata::MetadataField;
+
+/// Tokens a template can use.
+pub const TOKENS: [&str; 10] = [
+    "{title}",
+    "{caption}",
+    "{keywords}",
+    "{copyright}",
+    "{rating}",
+    "{filename}",
+    "{date}",
+    "{year}",
+    "{camera}",
+    "{lens}",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MetadataTemplate {
+    pub title: String,
+    pub caption: String,
+    /// Comma separated once filled in.
+    pub keywords: String,
+    pub copyright: String,
+    /// Write the star rating.
+    pub rating: bool,
+}
+
+impl Default for MetadataTemplate {
+    /// The catalog's own fields, unchanged.
+    fn default() -> Self {
+        Self {
+            title: "{title}".into(),
+            caption: "{caption}".into(),
+            keywords: "{keywords}".into(),
+            copyright: "{copyright}".into(),
+            rating: true,
+        }
+    }
+}
+
+impl MetadataTemplate {
+    pub fn field(&self, field: MetadataField) -> &str {
+        match field {
+            MetadataField::Title => &self.title,
+            MetadataField::Caption => &self.caption,
+            MetadataField::Copyright => &self.copyright,
+            MetadataField::Keywords => &self.keywords,
+        }
+    }
+
+    pub fn set_field(&mut self, field: MetadataField, value: String) {
+        match field {
+            MetadataField::Title => self.title = value,
+            MetadataField::Caption => self.caption = value,
+            MetadataField::Copyright => self.copyright = value,
+            MetadataField::Keywords => self.keywords = value,
+        }
+    }
+
+    /// The metadata for an export of `photo`. Fields that fill in empty
+    /// are left out of the file.
+    pub fn render(&self, photo: &Photo) -> EmbeddedMetadata {
+        let fill = |template: &str| Some(expand(template, photo)).filter(|s| !s.is_empty());
+        EmbeddedMetadata {
+            title: fill(&self.title),
+            caption: fill(&self.caption),
+            keywords: expand(&self.keywords, photo)
+                .split(',')
+                .map(str::trim)
+                .filter(|k| !k.is_empty())
+                .map(String::from)
+                .collect(),
+            copyright: fill(&self.copyright),
+            rating: (self.rating && photo.rating > 0).then_some(photo.rating),
+        }
+    }
+}
+
+/// Which template each export crop writes with.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExportMetadata {
+    /// Write metadata into exports at all.
+    pub enabled: bool,
+    /// Used for every crop without its own.
+    pub template: MetadataTemplate,
+    pub crop_templates: Vec<(ExportCrop, MetadataTemplate)>,
+}
+
+impl ExportMetadata {
+    /// The template `crop` exports with.
+    pub fn template_for(&self, crop: ExportCrop) -> &MetadataTemplate {
+        self.crop_templates
+            .iter()
+            .find(|(c, _)| *c == crop)
+            .map_or(&self.template, |(_, template)| template)
+    }
+
+    /// The template edited for `crop`, or the shared one for `None`.
+    pub fn template_mut(&mut self, crop: Option<ExportCrop>) -> Option<&mut MetadataTemplate> {
+        match crop {
+            None => Some(&mut self.template),
+            Some(crop) => self
+                .crop_templates
+                .iter_mut()
+                .find(|(c, _)| *c == crop)
+                .map(|(_, template)| template),
+        }
+    }
+
+    /// Give `crop` its own template, starting from the shared one, or drop
+    /// it to fall back to the shared one again.
+    pub fn set_crop_override(&mut self, crop: ExportCrop, own: bool) {
+        self.crop_templates.retain(|(c, _)| *c != crop);
+        if own {
+            self.crop_templates.push((crop, self.template.clone()));
+        }
+    }
+
+    pub fn has_override(&self, crop: ExportCrop) -> bool {
+        self.crop_templates.iter().any(|(c, _)| *c == crop)
+    }
+
+    /// What to embed in an export of `photo` cropped to `crop`, or `None`
+    /// when metadata is off.
+    pub fn render(&self, crop: ExportCrop, photo: &Photo) -> Option<EmbeddedMetadata> {
+        self.enabled
+            .then(|| self.template_for(crop).render(photo))
+            .filter(|metadata| !metadata.is_empty())
+    }
+}
+
+/// `template` with every token replaced. Unknown tokens are kept as typed,
+/// and values are never expanded again.
+fn expand(template: &str, photo: &Photo) -> String {
+    let mut out = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+        match TOKENS.iter().find(|token| rest.starts_with(**token)) {
+            Some(token) => {
+                out.push_str(&token_value(token, photo));
+                rest = &rest[token.len()..];
+            }
+            None => {
+                out.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out.trim().to_string()
+}
+
+fn token_value(token: &str, photo: &Photo) -> String {
+    let text = |value: &Option<String>| value.clone().unwrap_or_default();
+    let date = photo.date_taken.as_deref().unwrap_or("");
+    match token {
+        "{title}" => text(&photo.title),
+        "{caption}" => text(&photo.caption),
+        "{keywords}" => text(&photo.keywords),
+        "{copyright}" => text(&photo.copyright),
+        "{rating}" => photo.rating.to_string(),
+        "{filename}" => std::path::Path::new(&photo.file_path)
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default(),
+        "{date}" => capture_date(date).unwrap_or_default(),
+        "{year}" => capture_date(date)
+            .map(|date| date[..4].to_string())
+            .unwrap_or_default(),
+        "{camera}" => text(&photo.camera_model),
+        "{lens}" => text(&photo.lens),
+        _ => String::new(),
+    }
+}
+
+/// The `YYYY-MM-DD` day of a stored capture date, which is EXIF's
+/// `YYYY:MM:DD HH:MM:SS` unless it was edited in the catalog.
+fn capture_date(date: &str) -> Option<String> {
+    let day = date.get(..10)?;
+    let bytes = day.as_bytes();
+    let digits = [0..4, 5..7, 8..10]
+        .into_iter()
+        .all(|range| bytes[range].iter().all(u8::is_ascii_digit));
+    let separated = matches!((bytes[4], bytes[7]), (b':', b':') | (b'-', b'-'));
+    (digits && separated).then(|| day.replace(':', "-"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn photo() -> Photo {
+        Photo {
+            id: 1,
+            file_path: "/shoots/harbor/DSC_0042.NEF".into(),
+            camera_model: Some("Z 8".into()),
+            date_taken: Some("2026:05:02 06:12:00".into()),
+            rating: 4,
+            title: Some("Harbor at dawn".into()),
+            keywords: Some("harbor, fog".into()),
+            ..Photo::default()
+        }
+    }
+
+    #[test]
+    fn templates_mix_tokens_and_static_text() {
+        let template = MetadataTemplate {
+            caption: "{title}, {date} ({filename}) {unknown}".into(),
+            keywords: "{keywords}, client-acme".into(),
+            copyright: "© {year} Studio North".into(),
+            ..MetadataTemplate::default()
+        };
+        let metadata = template.render(&photo());
+        assert_eq!(metadata.title.as_deref(), Some("Harbor at dawn"));
+        assert_eq!(
+            metadata.caption.as_deref(),
+            Some("Harbor at dawn, 2026-05-02 (DSC_0042) {unknown}")
+        );
+        assert_eq!(metadata.keywords, ["harbor", "fog", "client-acme"]);
+        assert_eq!(metadata.copyright.as_deref(), Some("© 2026 Studio North"));
+        assert_eq!(metadata.rating, Some(4));
+    }
+
+    #[test]
+    fn dates_read_in_either_stored_form() {
+        assert_eq!(
+            capture_date("2026:05:02 06:12:00").as_deref(),
+            Some("2026-05-02")
+        );
+        assert_eq!(
+            capture_date("2026-05-02 06:12:00").as_deref(),
+            Some("2026-05-02")
+        );
+        assert_eq!(capture_date("2026-05"), None);
+        assert_eq!(capture_date("unknown date"), None);
+    }
+
+    #[test]
+    fn empty_fields_are_left_out() {
+        let metadata = MetadataTemplate::default().render(&Photo {
+            rating: 0,
+            ..photo()
+        });
+        assert!(metadata.caption.is_none());
+        assert!(metadata.copyright.is_none());
+        assert!(metadata.rating.is_none());
+    }
+
+    #[test]
+    fn crops_fall_back_to_the_shared_template() {
+        let mut metadata = ExportMetadata {
+            enabled: true,
+            ..ExportMetadata::default()
+        };
+        metadata.set_crop_override(ExportCrop::Aspect(1, 1), true);
+        metadata
+            .template_mut(Some(ExportCrop::Aspect(1, 1)))
+            .unwrap()
+            .title = "Square: {title}".into();
+
+        let square = metadata.render(ExportCrop::Aspect(1, 1), &photo()).unwrap();
+        assert_eq!(square.title.as_deref(), Some("Square: Harbor at dawn"));
+        let full = metadata.render(ExportCrop::AsEdited, &photo()).unwrap();
+        assert_eq!(full.title.as_deref(), Some("Harbor at dawn"));
+
+        metadata.enabled = false;
+        assert!(metadata.render(ExportCrop::AsEdited, &photo()).is_none());
+    }
+}
@@ -0,0 +1,459 @@
+//! Export post-processors: external programs run on every exported file,
+//! for frame borders, uploads, custom renaming and the like.
+//!
+//! The interface is versioned and meant to stay put. A post-processor is
+//! started with its configured arguments followed by the exported file's
+//! path, and reads one JSON [`Request`] from stdin. Exiting with status 0
+//! means success; anything else is a failure, reported with the last line
+//! the program wrote to stderr. A program that moves or renames the file
+//! prints `{"file": "<new path>"}` on stdout, and post-processors after it
+//! receive the new path.
+//!
+//! Each one runs in its own process under a time limit, so a crash or a
+//! hang costs that post-processor's work on one file and nothing else: the
+//! export itself has already succeeded. Dynamic libraries aren't loaded for
+//! the same reason; a library that crashes takes the app with it. A
+//! library can still be used through a small executable wrapper.
+
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crema_core::image_buf::EditParams;
+use crema_metadata::exif::ExifData;
+
+/// Version of the [`Request`] format, bumped only for breaking changes.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// How long a post-processor may run on one file before it is killed.
+const TIMEOUT: Duration = Duration::from_secs(120);
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// A post-processor registered in preferences.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExportPlugin {
+    pub name: String,
+    pub command: PathBuf,
+    /// Passed before the exported file's path.
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default = "enabled_by_default")]
+    pub enabled: bool,
+}
+
+fn enabled_by_default() -> bool {
+    true
+}
+
+impl ExportPlugin {
+    pub fn new(command: PathBuf) -> Self {
+        let name = command
+            .file_stem()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+        Self {
+            name,
+            command,
+            args: Vec::new(),
+            enabled: true,
+        }
+    }
+}
+
+/// What a post-processor receives on stdin.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Request {
+    pub version: u32,
+    /// The exported file.
+    pub file: PathBuf,
+    /// The photo it was rendered from.
+    pub source: String,
+    pub params: RequestParams,
+    pub exif: Option<RequestExif>,
+}
+
+impl Request {
+    pub fn new(file: &Path, source: &str, params: &EditParams) -> Self {
+        Self {
+            version: PROTOCOL_VERSION,
+            file: file.to_path_buf(),
+            source: source.to_string(),
+            params: params.into(),
+            exif: ExifData::from_file(Path::new(source))
+                .ok()
+                .map(RequestExif::from),
+        }
+    }
+}
+
+/// The develop settings the file was exported with, as sent in
+/// [`Request`]. Kept apart from [`EditParams`] so changing the editor
+/// doesn't change the protocol.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RequestParams {
+    pub exposure: f32,
+    pub wb_temp: f32,
+    pub wb_tint: f32,
+    pub contrast: f32,
+    pub highlights: f32,
+    pub shadows: f32,
+    pub blacks: f32,
+    pub vibrance: f32,
+    pub saturation: f32,
+    pub hsl_hue: f32,
+    pub hsl_saturation: f32,
+    pub hsl_lightness: f32,
+    pub split_shadow_hue: f32,
+    pub split_shadow_sat: f32,
+    pub split_highlight_hue: f32,
+    pub split_highlight_sat: f32,
+    pub split_balance: f32,
+    pub nr_luminance: f32,
+    pub nr_color: f32,
+    pub sharpen_amount: f32,
+    pub sharpen_radius: f32,
+    pub vignette_amount: f32,
+    pub distortion: f32,
+    pub rotation: f32,
+    pub crop_x: f32,
+    pub crop_y: f32,
+    pub crop_w: f32,
+    pub crop_h: f32,
+}
+
+impl From<&EditParams> for RequestParams {
+    fn from(params: &EditParams) -> Self {
+        Self {
+            exposure: params.exposure,
+            wb_temp: params.wb_temp,
+            wb_tint: params.wb_tint,
+            contrast: params.contrast,
+            highlights: params.highlights,
+            shadows: params.shadows,
+            blacks: params.blacks,
+            vibrance: params.vibrance,
+            saturation: params.saturation,
+            hsl_hue: params.hsl_hue,
+            hsl_saturation: params.hsl_saturation,
+            hsl_lightness: params.hsl_lightness,
+            split_shadow_hue: params.split_shadow_hue,
+            split_shadow_sat: params.split_shadow_sat,
+            split_highlight_hue: params.split_highlight_hue,
+            split_highlight_sat: params.split_highlight_sat,
+            split_balance: params.split_balance,
+            nr_luminance: params.nr_luminance,
+            nr_color: params.nr_color,
+            sharpen_amount: params.sharpen_amount,
+            sharpen_radius: params.sharpen_radius,
+            vignette_amount: params.vignette_amount,
+            distortion: params.distortion,
+            rotation: params.rotation,
+            crop_x: params.crop_x,
+            crop_y: params.crop_y,
+            crop_w: params.crop_w,
+            crop_h: params.crop_h,
+        }
+    }
+}
+
+/// The source photo's EXIF, as sent in [`Request`]. Kept apart from
+/// [`ExifData`] for the same reason as [`RequestParams`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RequestExif {
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub camera_make: Option<String>,
+    pub camera_model: Option<String>,
+    pub lens: Option<String>,
+    pub focal_length: Option<f64>,
+    pub aperture: Option<f64>,
+    pub shutter_speed: Option<String>,
+    pub iso: Option<u32>,
+    pub date_taken: Option<String>,
+    pub orientation: Option<u32>,
+    pub serial_number: Option<String>,
+    pub exposure_secs: Option<f64>,
+    pub temperature: Option<f64>,
+    pub exposure_bias: Option<f64>,
+}
+
+impl From<ExifData> for RequestExif {
+    fn from(exif: ExifData) -> Self {
+        Self {
+            width: exif.width,
+            height: exif.height,
+            camera_make: exif.camera_make,
+            camera_model: exif.camera_model,
+            lens: exif.lens,
+            focal_length: exif.focal_length,
+            aperture: exif.aperture,
+            shutter_speed: exif.shutter_speed,
+            iso: exif.iso,
+            date_taken: exif.date_taken,
+            orientation: exif.orientation,
+            serial_number: exif.serial_number,
+            exposure_secs: exif.exposure_secs,
+            temperature: exif.temperature,
+            exposure_bias: exif.exposure_bias,
+        }
+    }
+}
+
+/// What a post-processor may print on stdout.
+#[derive(Debug, Deserialize)]
+struct Response {
+    file: Option<PathBuf>,
+}
+
+/// Run `plugin` on the file in `request`, returning where the file is
+/// afterwards.
+pub fn run(plugin: &ExportPlugin, request: &Request) -> Result<PathBuf, String> {
+    let json = serde_json::to_vec(request).map_err(|err| err.to_string())?;
+    let mut child = Command::new(&plugin.command)
+        .args(&plugin.args)
+        .arg(&request.file)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|err| format!("could not start {}: {err}", plugin.command.display()))?;
+
+    // Pipes are drained on their own threads so a chatty or stubborn
+    // program can't block on a full buffer while we wait for it.
+    let mut stdin = child.stdin.take();
+    let writer = std::thread::spawn(move || {
+        if let Some(stdin) = stdin.as_mut() {
+            // A program that ignores stdin closes it early; that's fine.
+            stdin.write_all(&json).ok();
+        }
+    });
+    let stdout = drain(child.stdout.take());
+    let stderr = drain(child.stderr.take());
+
+    let started = Instant::now();
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break status,
+            Ok(None) if started.elapsed() >= TIMEOUT => {
+                child.kill().ok();
+                child.wait().ok();
+                return Err(format!("timed out after {}s", TIMEOUT.as_secs()));
+            }
+            Ok(None) => std::thread::sleep(POLL_INTERVAL),
+            Err(err) => return Err(format!("failed to wait: {err}")),
+        }
+    };
+    writer.join().ok();
+    let stdout = stdout.join().unwrap_or_default();
+    let stderr = stderr.join().unwrap_or_default();
+
+    if !status.success() {
+        let reason = last_line(&stderr).unwrap_or("no error message");
+        return Err(format!("exited with {status}: {reason}"));
+    }
+    let file = last_line(&stdout)
+        .and_then(|line| serde_json::from_str::<Response>(line).ok())
+        .and_then(|response| response.file)
+        .unwrap_or_else(|| request.file.clone());
+    Ok(file)
+}
+
+/// Run every enabled post-processor on `file` in order, returning
+/// "name: reason" for each that failed. A failure doesn't stop the ones
+/// after it.
+pub fn run_all(
+    plugins: &[ExportPlugin],
+    file: &Path,
+    source: &str,
+    params: &EditParams,
+) -> Vec<String> {
+    let mut enabled = plugins.iter().filter(|p| p.enabled).peekable();
+    if enabled.peek().is_none() {
+        return Vec::new();
+    }
+    let mut request = Request::new(file, source, params);
+    let mut failures = Vec::new();
+    for plugin in enabled {
+        match run(plugin, &request) {
+            Ok(file) => request.file = file,
+            Err(reason) => {
+                warn!(
+                    plugin = %plugin.name,
+                    file = %request.file.display(),
+                    %reason,
+                    "export post-processor failed"
+                );
+                failures.push(format!("{}: {reason}", plugin.name));
+            }
+        }
+    }
+    failures
+}
+
+fn drain(pipe: Option<impl Read + Send + 'static>) -> std::thread::JoinHandle<String> {
+    std::thread::spawn(move || {
+        let mut out = String::new();
+        if let Some(mut pipe) = pipe {
+            pipe.read_to_string(&mut out).ok();
+        }
+        out
+    })
+}
+
+fn last_line(output: &str) -> Option<&str> {
+    output.lines().rev().map(str::trim).find(|l| !l.is_empty())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    /// A post-processor written as a shell script.
+    fn script(dir: &Path, name: &str, body: &str) -> ExportPlugin {
+        use std::os::unix::fs::PermissionsExt;
+        let path = dir.join(name);
+        std::fs::write(&path, format!("#!/bin/sh\n{body}\n")).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        ExportPlugin::new(path)
+    }
+
+    fn exported(dir: &Path) -> PathBuf {
+        let file = dir.join("a.jpg");
+        std::fs::write(&file, b"jpeg").unwrap();
+        file
+    }
+
+    #[test]
+    fn passes_the_file_and_request_and_follows_renames() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = exported(dir.path());
+        let request_copy = dir.path().join("request.json");
+        let rename = script(
+            dir.path(),
+            "rename",
+            &format!(
+                "cat > {}\nmv \"$1\" \"$1.renamed\"\necho \"{{\\\"file\\\": \\\"$1.renamed\\\"}}\"",
+                request_copy.display()
+            ),
+        );
+        let touch = script(dir.path(), "touch", "touch \"$1.seen\"");
+
+        let failures = run_all(
+            &[rename, touch],
+            &file,
+            "/photos/a.NEF",
+            &EditParams::default(),
+        );
+        assert!(failures.is_empty(), "{failures:?}");
+        // The second post-processor was handed the renamed file.
+        assert!(dir.path().join("a.jpg.renamed.seen").exists());
+
+        let request: Request =
+            serde_json::from_str(&std::fs::read_to_string(request_copy).unwrap()).unwrap();
+        assert_eq!(request.version, PROTOCOL_VERSION);
+        assert_eq!(request.file, file);
+        assert_eq!(request.source, "/photos/a.NEF");
+    }
+
+    #[test]
+    fn failures_are_reported_and_do_not_stop_the_rest() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = exported(dir.path());
+        let failing = script(
+            dir.path(),
+            "upload",
+            "echo 'connection refused' >&2\nexit 3",
+        );
+        let missing = ExportPlugin::new(dir.path().join("not-there"));
+        let mut disabled = script(dir.path(), "disabled", "touch \"$1.disabled\"");
+        disabled.enabled = false;
+        let touch = script(dir.path(), "touch", "touch \"$1.seen\"");
+
+        let failures = run_all(
+            &[failing, missing, disabled, touch],
+            &file,
+            "/photos/a.NEF",
+            &EditParams::default(),
+        );
+        assert_eq!(failures.len(), 2, "{failures:?}");
+        assert!(failures[0].starts_with("upload: exited"));
+        assert!(failures[0].ends_with("connection refused"));
+        assert!(failures[1].starts_with("not-there: could not start"));
+        assert!(dir.path().join("a.jpg.seen").exists());
+        assert!(!dir.path().join("a.jpg.disabled").exists());
+    }
+
+    #[test]
+    fn request_format_is_unchanged() {
+        let request = Request {
+            version: PROTOCOL_VERSION,
+            file: PathBuf::from("/exports/a.jpg"),
+            source: "/photos/a.NEF".into(),
+            params: (&EditParams::default()).into(),
+            exif: Some(RequestExif::from(ExifData {
+                camera_make: Some("NIKON".into()),
+                iso: Some(400),
+                ..ExifData::default()
+            })),
+        };
+        // Protocol version 1. A change here needs a new PROTOCOL_VERSION.
+        let expected = serde_json::json!({
+            "version": 1,
+            "file": "/exports/a.jpg",
+            "source": "/photos/a.NEF",
+            "params": {
+                "exposure": 0.0,
+                "wb_temp": 5500.0,
+                "wb_tint": 0.0,
+                "contrast": 0.0,
+                "highlights": 0.0,
+                "shadows": 0.0,
+                "blacks": 0.0,
+                "vibrance": 0.0,
+                "saturation": 0.0,
+                "hsl_hue": 0.0,
+                "hsl_saturation": 0.0,
+                "hsl_lightness": 0.0,
+                "split_shadow_hue": 0.0,
+                "split_shadow_sat": 0.0,
+                "split_highlight_hue": 0.0,
+                "split_highlight_sat": 0.0,
+                "split_balance": 0.0,
+                "nr_luminance": 0.0,
+                "nr_color": 0.0,
+                "sharpen_amount": 0.0,
+                "sharpen_radius": 1.0,
+                "vignette_amount": 0.0,
+                "distortion": 0.0,
+                "rotation": 0.0,
+                "crop_x": 0.0,
+                "crop_y": 0.0,
+                "crop_w": 1.0,
+                "crop_h": 1.0
+            },
+            "exif": {
+                "width": null,
+                "height": null,
+                "camera_make": "NIKON",
+                "camera_model": null,
+                "lens": null,
+                "focal_length": null,
+                "aperture": null,
+                "shutter_speed": null,
+                "iso": 400,
+                "date_taken": null,
+                "orientation": null,
+                "serial_number": null,
+                "exposure_secs": null,
+                "temperature": null,
+                "exposure_bias": null
+            }
+        });
+        assert_eq!(serde_json::to_value(&request).unwrap(), expected);
+    }
+}
@@ -1,19 +1,39 @@
+mod animation;
 mod app;
+mod calibration;
+mod demo;
+mod export_check;
+mod export_crop;
+mod export_metadata;
+mod export_plugin;
 mod icon;
 mod menu;
+mod notifications;
+mod preferences;
+mod render_farm;
+mod smart_preview;
+mod theme;
 mod views;
+mod volumes;
 mod widgets;
 
 use tracing_subscriber::EnvFilter;
 
 fn main() -> iced::Result {
+    // Logs go to stderr: export workers report progress on stdout.
     tracing_subscriber::fmt()
         .with_env_filter(
             EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info")),
         )
+        .with_writer(std::io::stderr)
         .init();
 
-    iced::application(app::App::new, app::App::update, app::App::view)
+    let args: Vec<String> = std::env::args().collect();
+    if let Some(code) = render_farm::run_from_args(&args) {
+        std::process::exit(code);
+    }
+
+    let result = iced::application(app::App::new, app::App::update, app::App::view)
         .subscription(app::App::subscription)
         .title(app::App::title)
         .theme(app::App::theme)
@@ -23,5 +43,7 @@ fn main() -> iced::Result {
             ..Default::default()
         })
         .antialiasing(true)
-        .run()
+        .run();
+    demo::clean_up();
+    result
 }
@@ -9,11 +9,15 @@ use crate::app::Message;
 pub struct AppMenu {
     _menu: Menu,
     pub export_item: MenuItem,
+    pub export_animation_item: MenuItem,
+    pub export_comparison_item: MenuItem,
     pub save_sidecar_item: MenuItem,
     pub load_sidecar_item: MenuItem,
     pub undo_item: MenuItem,
     pub redo_item: MenuItem,
     pub paste_edits_item: MenuItem,
+    pub audit_precision_item: MenuItem,
+    pub verify_render_item: MenuItem,
 }
 
 pub fn build() -> AppMenu {
@@ -25,6 +29,13 @@ pub fn build() -> AppMenu {
         &[
             &PredefinedMenuItem::about(None, None),
             &PredefinedMenuItem::separator(),
+            &MenuItem::with_id(
+                "preferences",
+                "Settings...",
+                true,
+                Some(Accelerator::new(Some(Modifiers::META), Code::Comma)),
+            ),
+            &PredefinedMenuItem::separator(),
             &PredefinedMenuItem::services(None),
             &PredefinedMenuItem::separator(),
             &PredefinedMenuItem::hide(None),
@@ -43,6 +54,12 @@ pub fn build() -> AppMenu {
         Some(Accelerator::new(Some(Modifiers::META), Code::KeyE)),
     );
 
+    let export_animation_item =
+        MenuItem::with_id("export_animation", "Export Animation...", false, None);
+
+    let export_comparison_item =
+        MenuItem::with_id("export_comparison", "Export Before/After...", false, None);
+
     let save_sidecar_item = MenuItem::with_id(
         "save_sidecar",
         "Save Sidecar",
@@ -64,9 +81,19 @@ pub fn build() -> AppMenu {
                 Some(Accelerator::new(Some(Modifiers::META), Code::KeyI)),
             ),
             &export_item,
+            &export_animation_item,
+            &export_comparison_item,
             &PredefinedMenuItem::separator(),
             &save_sidecar_item,
             &load_sidecar_item,
+            &PredefinedMenuItem::separator(),
+            &MenuItem::with_id("dead_pixel_map", "Create Dead Pixel Map...", true, None),
+            &MenuItem::with_id("flat_field", "Create Flat Field...", true, None),
+            &MenuItem::with_id("master_dark", "Create Master Dark...", true, None),
+            &PredefinedMenuItem::separator(),
+            &MenuItem::with_id("remap_paths", "Remap Folder...", true, None),
+            &MenuItem::with_id("time_machine", "Browse Library As Of...", true, None),
+            &MenuItem::with_id("auto_stack", "Auto-Stack Library...", true, None),
         ],
     )
     .expect("failed to create File menu");
@@ -122,7 +149,29 @@ pub fn build() -> AppMenu {
     )
     .expect("failed to create Edit menu");
 
-    menu.append_items(&[&app_menu, &file_menu, &edit_menu])
+    let audit_precision_item =
+        MenuItem::with_id("audit_precision", "Audit Color Precision", false, None);
+
+    let verify_render_item = MenuItem::with_id(
+        "verify_render_consistency",
+        "Verify Render Consistency",
+        false,
+        None,
+    );
+
+    let debug_menu = Submenu::with_id_and_items(
+        "debug",
+        "Debug",
+        true,
+        &[
+            &audit_precision_item,
+            &verify_render_item,
+            &MenuItem::with_id("gpu_diagnostics", "GPU Diagnostics", true, None),
+        ],
+    )
+    .expect("failed to create Debug menu");
+
+    menu.append_items(&[&app_menu, &file_menu, &edit_menu, &debug_menu])
         .expect("failed to append menus");
 
     #[cfg(target_os = "macos")]
@@ -131,11 +180,15 @@ pub fn build() -> AppMenu {
     AppMenu {
         _menu: menu,
         export_item,
+        export_animation_item,
+        export_comparison_item,
         save_sidecar_item,
         load_sidecar_item,
         undo_item,
         redo_item,
         paste_edits_item,
+        audit_precision_item,
+        verify_render_item,
     }
 }
 
@@ -143,12 +196,24 @@ pub fn subscription() -> Subscription<Message> {
     iced::time::every(Duration::from_millis(50)).map(|_| match MenuEvent::receiver().try_recv() {
         Ok(event) if event.id == "import" => Message::Import,
         Ok(event) if event.id == "export" => Message::Export,
+        Ok(event) if event.id == "export_animation" => Message::OpenAnimationExport,
+        Ok(event) if event.id == "export_comparison" => Message::OpenComparisonExport,
         Ok(event) if event.id == "save_sidecar" => Message::SaveSidecar,
         Ok(event) if event.id == "load_sidecar" => Message::LoadSidecar,
+        Ok(event) if event.id == "dead_pixel_map" => Message::CreateDefectMap,
+        Ok(event) if event.id == "flat_field" => Message::CreateFlatField,
+        Ok(event) if event.id == "master_dark" => Message::CreateMasterDark,
+        Ok(event) if event.id == "remap_paths" => Message::OpenPathRemap,
+        Ok(event) if event.id == "time_machine" => Message::OpenTimeMachine,
+        Ok(event) if event.id == "auto_stack" => Message::OpenAutoStack,
         Ok(event) if event.id == "undo" => Message::Undo,
         Ok(event) if event.id == "redo" => Message::Redo,
         Ok(event) if event.id == "copy_edits" => Message::CopyEdits,
         Ok(event) if event.id == "paste_edits" => Message::PasteEdits,
+        Ok(event) if event.id == "audit_precision" => Message::AuditPrecision,
+        Ok(event) if event.id == "verify_render_consistency" => Message::VerifyRenderConsistency,
+        Ok(event) if event.id == "gpu_diagnostics" => Message::OpenGpuDiagnostics,
+        Ok(event) if event.id == "preferences" => Message::TogglePreferences,
         _ => Message::Noop,
     })
 }
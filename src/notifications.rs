@@ -0,0 +1,279 @@
+//! The notification center: events that deserve more than the status bar,
+//! such as finished exports, failed imports and background jobs. Each is
+//! shown briefly as a toast, with an action where one helps, and kept in
+//! the history drawer until cleared. Muting a category keeps its events in
+//! the history but skips the toast. With reduce motion on, toasts stay up
+//! until dismissed.
+
+use std::collections::{BTreeSet, VecDeque};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+/// How long a toast stays up unless dismissed.
+pub const TOAST_DURATION: Duration = Duration::from_secs(6);
+/// How often shown toasts are checked for expiry.
+pub const EXPIRY_TICK: Duration = Duration::from_secs(1);
+/// Toasts shown at once; older ones wait in the history.
+const MAX_TOASTS: usize = 3;
+const HISTORY_LIMIT: usize = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Category {
+    Exports,
+    Imports,
+    /// Stacking, path remaps and other work run off the UI thread.
+    Jobs,
+}
+
+impl Category {
+    pub const ALL: [Self; 3] = [Self::Exports, Self::Imports, Self::Jobs];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Exports => "Exports",
+            Self::Imports => "Imports",
+            Self::Jobs => "Background jobs",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Action {
+    /// Show a file or folder in the system file manager.
+    Reveal(PathBuf),
+    /// Open the quarantine, where this many files failed to import.
+    ReviewQuarantine(usize),
+}
+
+impl Action {
+    pub fn label(&self) -> String {
+        match self {
+            Self::Reveal(_) if cfg!(target_os = "macos") => "Reveal in Finder".into(),
+            Self::Reveal(_) if cfg!(windows) => "Show in Explorer".into(),
+            Self::Reveal(_) => "Show in Folder".into(),
+            Self::ReviewQuarantine(1) => "Review 1 error".into(),
+            Self::ReviewQuarantine(count) => format!("Review {count} errors"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Notification {
+    pub id: u64,
+    pub category: Category,
+    pub title: String,
+    pub failed: bool,
+    pub action: Option<Action>,
+    pub posted: Instant,
+    /// Still shown as a toast.
+    pub toast: bool,
+}
+
+impl Notification {
+    pub fn new(category: Category, title: impl Into<String>) -> Self {
+        Self {
+            id: 0,
+            category,
+            title: title.into(),
+            failed: false,
+            action: None,
+            posted: Instant::now(),
+            toast: true,
+        }
+    }
+
+    /// Mark as reporting a failure, which is shown in a warning color.
+    pub fn failed(mut self, failed: bool) -> Self {
+        self.failed = failed;
+        self
+    }
+
+    pub fn with_action(mut self, action: Action) -> Self {
+        self.action = Some(action);
+        self
+    }
+
+    /// How long ago it was posted, roughly, for the history drawer.
+    pub fn age(&self, now: Instant) -> String {
+        let secs = now.saturating_duration_since(self.posted).as_secs();
+        match secs {
+            0..60 => "Just now".into(),
+            60..3600 => format!("{} min ago", secs / 60),
+            _ => format!("{} h ago", secs / 3600),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct NotificationCenter {
+    next_id: u64,
+    /// Newest first.
+    history: VecDeque<Notification>,
+    unread: usize,
+    pub drawer_open: bool,
+    /// Keep toasts up until dismissed; set from `Preferences::reduce_motion`.
+    pub hold_toasts: bool,
+}
+
+impl NotificationCenter {
+    /// Add `notification` to the history, and show it as a toast unless
+    /// its category is in `muted` or the drawer already shows it.
+    pub fn post(&mut self, mut notification: Notification, muted: &BTreeSet<Category>) {
+        self.next_id += 1;
+        notification.id = self.next_id;
+        notification.toast = !muted.contains(&notification.category) && !self.drawer_open;
+        if !self.drawer_open {
+            self.unread += 1;
+        }
+        self.history.push_front(notification);
+        self.history.truncate(HISTORY_LIMIT);
+    }
+
+    /// Toasts to show, newest first.
+    pub fn toasts(&self) -> impl Iterator<Item = &Notification> {
+        self.history.iter().filter(|n| n.toast).take(MAX_TOASTS)
+    }
+
+    pub fn has_toasts(&self) -> bool {
+        self.history.iter().any(|n| n.toast)
+    }
+
+    pub fn dismiss(&mut self, id: u64) {
+        if let Some(notification) = self.history.iter_mut().find(|n| n.id == id) {
+            notification.toast = false;
+        }
+    }
+
+    /// Whether shown toasts are waiting to time out.
+    pub fn expiring(&self) -> bool {
+        !self.hold_toasts && self.has_toasts()
+    }
+
+    /// Take down toasts that have been up for [`TOAST_DURATION`], unless
+    /// they're held.
+    pub fn expire(&mut self, now: Instant) {
+        if self.hold_toasts {
+            return;
+        }
+        for notification in self.history.iter_mut().filter(|n| n.toast) {
+            if now.saturating_duration_since(notification.posted) >= TOAST_DURATION {
+                notification.toast = false;
+            }
+        }
+    }
+
+    pub fn history(&self) -> impl Iterator<Item = &Notification> {
+        self.history.iter()
+    }
+
+    pub fn unread(&self) -> usize {
+        self.unread
+    }
+
+    /// Open or close the drawer. Opening it marks everything read and
+    /// takes down the toasts it now lists.
+    pub fn toggle_drawer(&mut self) {
+        self.drawer_open = !self.drawer_open;
+        if self.drawer_open {
+            self.unread = 0;
+            for notification in &mut self.history {
+                notification.toast = false;
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.history.clear();
+        self.unread = 0;
+    }
+}
+
+/// Show `path` in the system file manager, selected where the platform
+/// allows it.
+pub fn reveal(path: &Path) -> std::io::Result<()> {
+    let mut command = if cfg!(target_os = "macos") {
+        let mut command = std::process::Command::new("open");
+        command.arg("-R").arg(path);
+        command
+    } else if cfg!(windows) {
+        let mut command = std::process::Command::new("explorer");
+        command.arg(format!("/select,{}", path.display()));
+        command
+    } else {
+        // xdg-open can't select a file, so open the folder it's in.
+        let folder = if path.is_dir() {
+            path
+        } else {
+            path.parent().unwrap_or(path)
+        };
+        let mut command = std::process::Command::new("xdg-open");
+        command.arg(folder);
+        command
+    };
+    let mut child = command.spawn()?;
+    std::thread::spawn(move || child.wait());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn muted_categories_skip_the_toast_but_keep_history() {
+        let mut center = NotificationCenter::default();
+        let muted = BTreeSet::from([Category::Jobs]);
+        center.post(Notification::new(Category::Exports, "Exported"), &muted);
+        center.post(Notification::new(Category::Jobs, "Stacked"), &muted);
+        assert_eq!(center.toasts().count(), 1);
+        assert_eq!(center.history().count(), 2);
+        assert_eq!(center.unread(), 2);
+
+        center.toggle_drawer();
+        assert!(!center.has_toasts());
+        assert_eq!(center.unread(), 0);
+    }
+
+    #[test]
+    fn toasts_expire_and_dismiss() {
+        let mut center = NotificationCenter::default();
+        let none = BTreeSet::new();
+        center.post(Notification::new(Category::Imports, "Imported"), &none);
+        center.post(Notification::new(Category::Exports, "Exported"), &none);
+        let newest = center.toasts().next().unwrap().id;
+        center.dismiss(newest);
+        assert_eq!(center.toasts().count(), 1);
+
+        center.expire(Instant::now() + TOAST_DURATION);
+        assert!(!center.has_toasts());
+        assert_eq!(center.history().count(), 2);
+    }
+
+    #[test]
+    fn held_toasts_stay_until_dismissed() {
+        let mut center = NotificationCenter {
+            hold_toasts: true,
+            ..NotificationCenter::default()
+        };
+        center.post(
+            Notification::new(Category::Exports, "Exported"),
+            &BTreeSet::new(),
+        );
+        assert!(!center.expiring());
+        center.expire(Instant::now() + TOAST_DURATION * 10);
+        assert_eq!(center.toasts().count(), 1);
+
+        let id = center.toasts().next().unwrap().id;
+        center.dismiss(id);
+        assert!(!center.has_toasts());
+    }
+
+    #[test]
+    fn action_labels_count_errors() {
+        assert_eq!(Action::ReviewQuarantine(3).label(), "Review 3 errors");
+        assert_eq!(Action::ReviewQuarantine(1).label(), "Review 1 error");
+    }
+}
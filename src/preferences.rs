@@ -0,0 +1,208 @@
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crema_catalog::auto_stack::AutoStackRules;
+use crema_catalog::models::ThumbnailFidelity;
+use crema_core::dither::Dither;
+use crema_core::preset::Preset;
+use crema_export::format::ExportColorSpace;
+
+use crate::export_check::ExportWarning;
+use crate::export_crop::ExportCrop;
+use crate::export_metadata::ExportMetadata;
+use crate::export_plugin::ExportPlugin;
+use crate::notifications::Category;
+use crate::theme::{AccentColor, ColorVision};
+use crate::widgets::thumbnail_grid::GridLayout;
+
+/// App-level settings that persist across launches. Missing fields fall back
+/// to their defaults so older files keep loading as settings are added.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Preferences {
+    pub accent: AccentColor,
+    pub color_vision: ColorVision,
+    /// Stronger borders and thicker selection outlines.
+    pub high_contrast: bool,
+    /// Leave toasts up until dismissed rather than timing them out. Zoom,
+    /// pan and photo changes are instant already; any transition added
+    /// later should be skipped when this is set.
+    pub reduce_motion: bool,
+    /// Notification categories that go to the history without a toast.
+    pub muted_notifications: BTreeSet<Category>,
+    pub grid_layout: GridLayout,
+    /// What library thumbnails show, unless a folder overrides it.
+    pub thumbnail_fidelity: ThumbnailFidelity,
+    /// Export-only crops written for every export; empty means the
+    /// develop crop alone.
+    pub export_crops: Vec<ExportCrop>,
+    /// Subtract a matching master dark from photos as they're decoded.
+    pub dark_frame_subtraction: bool,
+    /// Worker processes for batch export; one or fewer exports in-process.
+    pub export_workers: usize,
+    /// Noise added when exports are rounded to 8 bits, hiding banding in
+    /// smooth gradients such as skies.
+    pub export_dither: Dither,
+    /// Color space single-photo exports are written in. Batch exports stay
+    /// sRGB.
+    pub export_color_space: ExportColorSpace,
+    /// Write TIFF exports at 16 bits per channel.
+    pub export_tiff_16bit: bool,
+    /// Export warnings turned off with "Don't warn about this again".
+    pub suppressed_export_warnings: BTreeSet<ExportWarning>,
+    /// Build a smart preview of each photo as it's imported, so it can
+    /// still be edited with its drive disconnected.
+    pub smart_previews: bool,
+    /// Programs run on every exported file, in order.
+    pub export_plugins: Vec<ExportPlugin>,
+    /// Title, caption and other fields written into JPEG and PNG exports.
+    pub export_metadata: ExportMetadata,
+    /// Compile the GPU shaders and run a scratch frame through them at
+    /// launch, so the first edit in develop doesn't stutter.
+    pub gpu_warmup: bool,
+    /// The one-time notice that previews render on the CPU, because no
+    /// usable GPU was found, has been seen.
+    pub cpu_rendering_noticed: bool,
+    /// Develop presets, in the order they're listed.
+    pub presets: Vec<Preset>,
+    /// How "Auto-Stack Library" groups photos.
+    pub auto_stack: AutoStackRules,
+}
+
+impl Default for Preferences {
+    fn default() -> Self {
+        Self {
+            accent: AccentColor::default(),
+            color_vision: ColorVision::default(),
+            high_contrast: false,
+            reduce_motion: false,
+            muted_notifications: BTreeSet::new(),
+            grid_layout: GridLayout::default(),
+            thumbnail_fidelity: ThumbnailFidelity::default(),
+            export_crops: Vec::new(),
+            dark_frame_subtraction: false,
+            export_workers: 0,
+            export_dither: Dither::default(),
+            export_color_space: ExportColorSpace::default(),
+            export_tiff_16bit: false,
+            suppressed_export_warnings: BTreeSet::new(),
+            smart_previews: false,
+            export_plugins: Vec::new(),
+            export_metadata: ExportMetadata::default(),
+            gpu_warmup: true,
+            cpu_rendering_noticed: false,
+            presets: Vec::new(),
+            auto_stack: AutoStackRules::default(),
+        }
+    }
+}
+
+impl Preferences {
+    pub fn load() -> Self {
+        Self::load_from(&preferences_path())
+    }
+
+    pub fn load_from(path: &Path) -> Self {
+        let Ok(json) = std::fs::read_to_string(path) else {
+            return Self::default();
+        };
+        match serde_json::from_str(&json) {
+            Ok(prefs) => prefs,
+            Err(err) => {
+                warn!(%err, path = %path.display(), "ignoring malformed preferences");
+                Self::default()
+            }
+        }
+    }
+
+    pub fn save(&self) -> Result<()> {
+        self.save_to(&preferences_path())
+    }
+
+    pub fn save_to(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("failed to create {}", parent.display()))?;
+        }
+        let json = serde_json::to_string_pretty(self).context("failed to serialize preferences")?;
+        std::fs::write(path, json).with_context(|| format!("failed to write {}", path.display()))
+    }
+}
+
+fn preferences_path() -> PathBuf {
+    dirs::config_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("crema")
+        .join("preferences.json")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_file_uses_defaults() {
+        let dir = tempfile::tempdir().unwrap();
+        let prefs = Preferences::load_from(&dir.path().join("nope.json"));
+        assert_eq!(prefs, Preferences::default());
+    }
+
+    #[test]
+    fn gpu_warmup_is_on_unless_turned_off() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("preferences.json");
+        std::fs::write(&path, "{}").unwrap();
+        assert!(Preferences::load_from(&path).gpu_warmup);
+        std::fs::write(&path, r#"{"gpu_warmup": false}"#).unwrap();
+        assert!(!Preferences::load_from(&path).gpu_warmup);
+    }
+
+    #[test]
+    fn round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("nested").join("preferences.json");
+        let prefs = Preferences {
+            accent: AccentColor::Teal,
+            color_vision: ColorVision::Deuteranopia,
+            high_contrast: true,
+            reduce_motion: true,
+            grid_layout: GridLayout::Justified,
+            export_crops: vec![ExportCrop::AsEdited, ExportCrop::Aspect(1, 1)],
+            presets: vec![Preset::capture(
+                "Cool shadows",
+                &crema_core::image_buf::EditParams::default(),
+                ["split_shadow_hue", "split_shadow_sat"],
+            )],
+            auto_stack: AutoStackRules {
+                bursts: false,
+                burst_gap_secs: 3,
+                ..AutoStackRules::default()
+            },
+            ..Preferences::default()
+        };
+        prefs.save_to(&path).unwrap();
+        assert_eq!(Preferences::load_from(&path), prefs);
+    }
+
+    #[test]
+    fn partial_file_fills_defaults() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("preferences.json");
+        std::fs::write(&path, r#"{"color_vision":"protanopia"}"#).unwrap();
+        let prefs = Preferences::load_from(&path);
+        assert_eq!(prefs.color_vision, ColorVision::Protanopia);
+        assert_eq!(prefs.accent, AccentColor::Blue);
+    }
+
+    #[test]
+    fn malformed_file_uses_defaults() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("preferences.json");
+        std::fs::write(&path, "not json").unwrap();
+        assert_eq!(Preferences::load_from(&path), Preferences::default());
+    }
+}
@@ -0,0 +1,632 @@
+//! Multi-process batch export. The coordinator writes every planned
+//! export to a JSON manifest and starts worker processes (this binary with
+//! `--render-worker`), each rendering its own slice of the jobs and
+//! reporting one JSON line per finished file on stdout. Decoding and
+//! rendering then spread across every core while the UI process stays
+//! responsive, and a crashing worker only loses its own slice.
+
+use std::collections::HashSet;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdout, Command, Stdio};
+use std::sync::mpsc;
+use std::thread::JoinHandle;
+
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+use tracing::error;
+
+use crema_catalog::db::Catalog;
+use crema_core::annotation::Annotation;
+use crema_core::dither::Dither;
+use crema_core::image_buf::EditParams;
+use crema_export::format::{ExportEncoding, FittedQuality};
+use crema_export::render::render_export;
+use crema_metadata::embed::{self, EmbeddedMetadata};
+
+use crate::calibration::Calibration;
+use crate::export_crop::ExportCrop;
+use crate::export_plugin::{self, ExportPlugin};
+
+const WORKER_FLAG: &str = "--render-worker";
+const FARM_FLAG: &str = "--render-farm";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Manifest {
+    /// Catalog whose calibration (dead pixel maps, flat fields, darks)
+    /// workers apply, matching an in-process export. Workers open it
+    /// without migrating, so it must already be up to date.
+    pub catalog: Option<String>,
+    pub dark_frame_subtraction: bool,
+    #[serde(default)]
+    pub dither: Dither,
+    pub jobs: Vec<ExportJob>,
+    /// Post-processors run on every file written.
+    #[serde(default)]
+    pub plugins: Vec<ExportPlugin>,
+}
+
+impl Manifest {
+    pub fn encoding(&self) -> ExportEncoding {
+        ExportEncoding {
+            dither: self.dither,
+            ..ExportEncoding::default()
+        }
+    }
+}
+
+/// One source photo, decoded once and written to every output.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExportJob {
+    pub source: String,
+    pub params: EditParams,
+    pub outputs: Vec<JobOutput>,
+    /// Smart preview to render from because the source is offline.
+    #[serde(default)]
+    pub smart_preview: Option<String>,
+    /// Frame of an animated source to render.
+    #[serde(default)]
+    pub key_frame: u32,
+    /// Review annotations burned into every output, for proofs.
+    #[serde(default)]
+    pub annotations: Vec<Annotation>,
+    /// Largest size for each JPEG, in bytes, chosen for this export.
+    #[serde(default)]
+    pub target_size: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JobOutput {
+    pub path: PathBuf,
+    pub crop: ExportCrop,
+    /// The natural file name was taken, so a numbered one was used.
+    pub renamed: bool,
+    /// Written into the file once it's exported.
+    #[serde(default)]
+    pub metadata: Option<EmbeddedMetadata>,
+}
+
+/// A worker's report for one output, one JSON object per stdout line.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Progress {
+    job: usize,
+    output: usize,
+    error: Option<String>,
+    #[serde(default)]
+    plugin_failures: Vec<String>,
+    #[serde(default)]
+    fitted: Option<FittedQuality>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct FarmReport {
+    pub exported: usize,
+    pub renamed: usize,
+    pub total: usize,
+    /// "file: reason" for every output that wasn't written.
+    pub failures: Vec<String>,
+    /// "file: post-processor: reason" for every post-processor that failed
+    /// on a written output.
+    pub plugin_failures: Vec<String>,
+    /// File name and quality of every JPEG fit to the target size.
+    pub fitted: Vec<(String, FittedQuality)>,
+}
+
+/// Decide every output file up front, so workers never race for names.
+pub fn plan(
+    photos: &[(String, EditParams)],
+    crops: &[ExportCrop],
+    folder: &Path,
+) -> Vec<ExportJob> {
+    let mut used_paths = HashSet::new();
+    photos
+        .iter()
+        .map(|(source, params)| {
+            let stem = Path::new(source)
+                .file_stem()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string();
+            let outputs = crops
+                .iter()
+                .map(|&crop| {
+                    let stem = if crops.len() > 1 {
+                        format!("{stem}{}", crop.file_suffix())
+                    } else {
+                        stem.clone()
+                    };
+                    let path =
+                        crate::app::unique_export_path(folder, &stem, "jpg", &mut used_paths);
+                    let renamed = path.file_stem().and_then(|s| s.to_str()) != Some(stem.as_str());
+                    JobOutput {
+                        path,
+                        crop,
+                        renamed,
+                        metadata: None,
+                    }
+                })
+                .collect();
+            ExportJob {
+                source: source.clone(),
+                params: params.clone(),
+                outputs,
+                smart_preview: None,
+                key_frame: 0,
+                annotations: Vec::new(),
+                target_size: None,
+            }
+        })
+        .collect()
+}
+
+/// Render every output of `job`, returning one result per output: the
+/// quality chosen when it was fit to a target size.
+pub fn run_job(
+    job: &ExportJob,
+    calibration: &Calibration,
+    encoding: ExportEncoding,
+) -> Vec<Result<Option<FittedQuality>, String>> {
+    let (path, frame) = match &job.smart_preview {
+        Some(preview) => (Path::new(preview), 0),
+        None => (Path::new(&job.source), job.key_frame),
+    };
+    // Smart previews were calibrated when they were built.
+    let loaded = match &job.smart_preview {
+        Some(_) => crema_core::raw::load_frame_scaled(path, frame, None),
+        None => calibration.decode(path, frame, calibration.exif_for(path).as_ref()),
+    };
+    let buf = match loaded {
+        Ok(buf) => buf,
+        Err(err) => {
+            let reason = format!("failed to load: {err:#}");
+            return job.outputs.iter().map(|_| Err(reason.clone())).collect();
+        }
+    };
+
+    job.outputs
+        .iter()
+        .map(|output| {
+            let params = output.crop.apply(&job.params, buf.width, buf.height);
+            // Leave room in a size limit for the metadata written after.
+            let reserve = output
+                .metadata
+                .as_ref()
+                .map_or(0, |metadata| embed::overhead(&output.path, metadata));
+            let encoding = ExportEncoding {
+                target_size: job.target_size.map(|t| t.saturating_sub(reserve)),
+                ..encoding
+            };
+            let fitted = render_export(
+                buf.clone(),
+                &params,
+                &job.annotations,
+                &encoding,
+                &output.path,
+            )?;
+            if let Some(metadata) = &output.metadata {
+                embed::embed(&output.path, metadata)
+                    .map_err(|err| format!("failed to write metadata: {err:#}"))?;
+            }
+            Ok(fitted)
+        })
+        .collect()
+}
+
+/// Run `plugins` on `output` of `job` once it's written, returning
+/// "file: post-processor: reason" for each that failed.
+pub fn post_process(job: &ExportJob, output: &JobOutput, plugins: &[ExportPlugin]) -> Vec<String> {
+    export_plugin::run_all(plugins, &output.path, &job.source, &job.params)
+        .into_iter()
+        .map(|failure| format!("{}: {failure}", output.path.display()))
+        .collect()
+}
+
+fn load_calibration(manifest: &Manifest) -> Result<Calibration> {
+    let Some(path) = &manifest.catalog else {
+        return Ok(Calibration::default());
+    };
+    let catalog =
+        Catalog::open_existing(path).context("failed to open the catalog for calibration")?;
+    Calibration::load(&catalog, manifest.dark_frame_subtraction)
+}
+
+/// The name `path` is shown by in export summaries.
+pub fn file_name(path: &Path) -> String {
+    path.file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string()
+}
+
+pub fn write_manifest(manifest: &Manifest, path: &Path) -> Result<()> {
+    let json = serde_json::to_string(manifest).context("failed to serialize export manifest")?;
+    std::fs::write(path, json).with_context(|| format!("failed to write {}", path.display()))
+}
+
+pub fn read_manifest(path: &Path) -> Result<Manifest> {
+    let json = std::fs::read_to_string(path)
+        .with_context(|| format!("failed to read {}", path.display()))?;
+    serde_json::from_str(&json).with_context(|| format!("malformed manifest {}", path.display()))
+}
+
+/// Indices of the jobs worker `slice` of `workers` renders. Jobs are dealt
+/// round-robin so slow RAWs clustered in one folder spread across workers.
+fn slice_jobs(job_count: usize, slice: usize, workers: usize) -> impl Iterator<Item = usize> {
+    (0..job_count).filter(move |i| i % workers.max(1) == slice)
+}
+
+/// Body of a worker process.
+fn run_worker(manifest_path: &Path, slice: usize, workers: usize) -> Result<()> {
+    let manifest = read_manifest(manifest_path)?;
+    // Exporting uncalibrated would silently ship different pixels, so
+    // every job fails with the reason instead.
+    let calibration = load_calibration(&manifest).map_err(|err| {
+        error!(%err, "failed to load calibration for export");
+        format!("failed to load calibration: {err:#}")
+    });
+    let stdout = std::io::stdout();
+    for job_index in slice_jobs(manifest.jobs.len(), slice, workers) {
+        let job = &manifest.jobs[job_index];
+        let results = match &calibration {
+            Ok(calibration) => run_job(job, calibration, manifest.encoding()),
+            Err(reason) => vec![Err(reason.clone()); job.outputs.len()],
+        };
+        for (output, result) in results.into_iter().enumerate() {
+            let plugin_failures = if result.is_ok() {
+                post_process(job, &job.outputs[output], &manifest.plugins)
+            } else {
+                Vec::new()
+            };
+            let mut out = stdout.lock();
+            let (fitted, error) = match result {
+                Ok(fitted) => (fitted, None),
+                Err(err) => (None, Some(err)),
+            };
+            let line = serde_json::to_string(&Progress {
+                job: job_index,
+                output,
+                error,
+                plugin_failures,
+                fitted,
+            })?;
+            writeln!(out, "{line}")?;
+            out.flush()?;
+        }
+    }
+    Ok(())
+}
+
+/// Start worker `slice` of `workers` with its progress on a pipe.
+fn spawn_worker(
+    exe: &Path,
+    manifest_path: &Path,
+    slice: usize,
+    workers: usize,
+) -> Result<(Child, ChildStdout)> {
+    let mut child = Command::new(exe)
+        .arg(WORKER_FLAG)
+        .arg(manifest_path)
+        .arg(format!("{slice}/{workers}"))
+        .stdout(Stdio::piped())
+        .stdin(Stdio::null())
+        .spawn()
+        .context("failed to start export worker")?;
+    match child.stdout.take() {
+        Some(stdout) => Ok((child, stdout)),
+        None => {
+            stop_worker(&mut child);
+            bail!("worker stdout unavailable")
+        }
+    }
+}
+
+/// Kill a worker the farm is abandoning and reap it so it doesn't linger.
+fn stop_worker(child: &mut Child) {
+    if let Err(err) = child.kill() {
+        error!(%err, "failed to stop export worker");
+    }
+    child.wait().ok();
+}
+
+/// Run `manifest` across `workers` processes, calling `on_progress` with
+/// (finished, total) outputs as workers report in.
+pub fn run_farm(
+    manifest_path: &Path,
+    manifest: &Manifest,
+    workers: usize,
+    mut on_progress: impl FnMut(usize, usize),
+) -> Result<FarmReport> {
+    let exe = std::env::current_exe().context("could not locate the crema executable")?;
+    let workers = workers.clamp(1, manifest.jobs.len().max(1));
+    let total: usize = manifest.jobs.iter().map(|j| j.outputs.len()).sum();
+
+    let (tx, rx) = mpsc::channel::<Progress>();
+    let mut children: Vec<(usize, Child, JoinHandle<()>)> = Vec::with_capacity(workers);
+    for slice in 0..workers {
+        let (child, stdout) = match spawn_worker(&exe, manifest_path, slice, workers) {
+            Ok(spawned) => spawned,
+            Err(err) => {
+                for (_, mut child, reader) in children {
+                    stop_worker(&mut child);
+                    reader.join().ok();
+                }
+                return Err(err);
+            }
+        };
+        let tx = tx.clone();
+        let reader = std::thread::spawn(move || {
+            for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                match serde_json::from_str::<Progress>(&line) {
+                    Ok(progress) => {
+                        if tx.send(progress).is_err() {
+                            break;
+                        }
+                    }
+                    Err(err) => error!(%err, %line, "unreadable worker output"),
+                }
+            }
+        });
+        children.push((slice, child, reader));
+    }
+    drop(tx);
+
+    let mut report = FarmReport {
+        total,
+        ..FarmReport::default()
+    };
+    let mut reported = HashSet::new();
+    for progress in rx {
+        let Some(output) = manifest
+            .jobs
+            .get(progress.job)
+            .and_then(|job| job.outputs.get(progress.output))
+        else {
+            continue;
+        };
+        if !reported.insert((progress.job, progress.output)) {
+            continue;
+        }
+        report.plugin_failures.extend(progress.plugin_failures);
+        match progress.error {
+            None => {
+                report.exported += 1;
+                report.renamed += usize::from(output.renamed);
+                if let Some(fitted) = progress.fitted {
+                    report.fitted.push((file_name(&output.path), fitted));
+                }
+            }
+            Some(reason) => report
+                .failures
+                .push(format!("{}: {reason}", manifest.jobs[progress.job].source)),
+        }
+        on_progress(reported.len(), total);
+    }
+
+    for (slice, mut child, reader) in children {
+        reader.join().ok();
+        let status = child.wait().context("failed to wait for export worker")?;
+        if status.success() {
+            continue;
+        }
+        for job_index in slice_jobs(manifest.jobs.len(), slice, workers) {
+            for output in 0..manifest.jobs[job_index].outputs.len() {
+                if !reported.contains(&(job_index, output)) {
+                    report.failures.push(format!(
+                        "{}: export worker exited ({status}) before finishing",
+                        manifest.jobs[job_index].source
+                    ));
+                }
+            }
+        }
+    }
+    Ok(report)
+}
+
+/// Handle the render farm command lines, returning the process exit code
+/// when one was given so `main` skips starting the UI:
+///
+/// - `crema --render-farm <manifest> [workers]` renders a manifest from a
+///   script, one worker per core by default.
+/// - `crema --render-worker <manifest> <slice>/<workers>` is how the
+///   coordinator starts its workers.
+pub fn run_from_args(args: &[String]) -> Option<i32> {
+    let flag = args.get(1)?;
+    if flag != WORKER_FLAG && flag != FARM_FLAG {
+        return None;
+    }
+    let result = if flag == WORKER_FLAG {
+        parse_slice(args.get(3).map(String::as_str)).and_then(|(slice, workers)| {
+            let manifest = args.get(2).context("missing manifest path")?;
+            run_worker(Path::new(manifest), slice, workers)
+        })
+    } else {
+        run_cli(args)
+    };
+    match result {
+        Ok(()) => Some(0),
+        Err(err) => {
+            eprintln!("crema: {err:#}");
+            Some(1)
+        }
+    }
+}
+
+fn run_cli(args: &[String]) -> Result<()> {
+    let manifest_path = PathBuf::from(args.get(2).context("missing manifest path")?);
+    let workers = match args.get(3) {
+        Some(n) => n
+            .parse()
+            .with_context(|| format!("invalid worker count {n}"))?,
+        None => std::thread::available_parallelism().map_or(1, |n| n.get()),
+    };
+    let manifest = read_manifest(&manifest_path)?;
+    // A catalog from an older version is upgraded once here rather than by
+    // every worker at once.
+    if let Some(path) = &manifest.catalog {
+        Catalog::open(path).with_context(|| format!("failed to open the catalog {path}"))?;
+    }
+    let report = run_farm(&manifest_path, &manifest, workers, |done, total| {
+        eprint!("\rFinished {done}/{total}");
+    })?;
+    eprintln!();
+    for failure in &report.failures {
+        eprintln!("failed: {failure}");
+    }
+    if !report.failures.is_empty() {
+        bail!(
+            "{} of {} exports failed",
+            report.failures.len(),
+            report.total
+        );
+    }
+    Ok(())
+}
+
+fn parse_slice(arg: Option<&str>) -> Result<(usize, usize)> {
+    let arg = arg.context("missing worker slice")?;
+    let (slice, workers) = arg
+        .split_once('/')
+        .with_context(|| format!("invalid worker slice {arg}"))?;
+    let (slice, workers): (usize, usize) = (slice.parse()?, workers.parse()?);
+    if workers == 0 || slice >= workers {
+        bail!("invalid worker slice {arg}");
+    }
+    Ok((slice, workers))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plan_names_every_output_once() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a.jpg"), b"taken").unwrap();
+        let photos = vec![
+            ("/photos/a.NEF".to_string(), EditParams::default()),
+            ("/other/a.NEF".to_string(), EditParams::default()),
+        ];
+        let crops = [ExportCrop::AsEdited, ExportCrop::Aspect(1, 1)];
+        let jobs = plan(&photos, &crops, dir.path());
+
+        let names: Vec<_> = jobs
+            .iter()
+            .flat_map(|j| &j.outputs)
+            .map(|o| o.path.file_name().unwrap().to_string_lossy().to_string())
+            .collect();
+        assert_eq!(names, ["a-1.jpg", "a-1x1.jpg", "a-2.jpg", "a-1x1-1.jpg"]);
+        let renamed: Vec<_> = jobs
+            .iter()
+            .flat_map(|j| &j.outputs)
+            .map(|o| o.renamed)
+            .collect();
+        assert_eq!(renamed, [true, false, true, true]);
+    }
+
+    #[test]
+    fn slices_cover_every_job_once() {
+        let mut seen: Vec<usize> = (0..3).flat_map(|s| slice_jobs(10, s, 3)).collect();
+        seen.sort();
+        assert_eq!(seen, (0..10).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn parses_worker_slices() {
+        assert_eq!(parse_slice(Some("1/4")).unwrap(), (1, 4));
+        assert!(parse_slice(Some("4/4")).is_err());
+        assert!(parse_slice(Some("0/0")).is_err());
+        assert!(parse_slice(None).is_err());
+        assert!(run_from_args(&["crema".into()]).is_none());
+    }
+
+    #[test]
+    fn manifest_round_trips() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("manifest.json");
+        let mut manifest = Manifest {
+            catalog: Some("/tmp/catalog.db".into()),
+            dark_frame_subtraction: true,
+            dither: Dither::BlueNoise,
+            jobs: plan(
+                &[("/p/x.jpg".into(), EditParams::default())],
+                &[ExportCrop::AsEdited],
+                dir.path(),
+            ),
+            plugins: vec![ExportPlugin::new("/usr/local/bin/frame".into())],
+        };
+        manifest.jobs[0].target_size = Some(2_000_000);
+        write_manifest(&manifest, &path).unwrap();
+        let read = read_manifest(&path).unwrap();
+        assert_eq!(read.jobs, manifest.jobs);
+        assert_eq!(read.catalog, manifest.catalog);
+        assert_eq!(read.dither, Dither::BlueNoise);
+        assert_eq!(read.plugins, manifest.plugins);
+        assert_eq!(read.jobs[0].target_size, Some(2_000_000));
+    }
+
+    #[test]
+    fn unreadable_catalog_fails_calibration() {
+        let dir = tempfile::tempdir().unwrap();
+        let manifest = Manifest {
+            // A directory can't be opened as a catalog.
+            catalog: Some(dir.path().to_string_lossy().to_string()),
+            dark_frame_subtraction: false,
+            dither: Dither::default(),
+            jobs: Vec::new(),
+            plugins: Vec::new(),
+        };
+        assert!(load_calibration(&manifest).is_err());
+        let uncataloged = Manifest {
+            catalog: None,
+            ..manifest
+        };
+        assert!(load_calibration(&uncataloged).is_ok());
+    }
+
+    #[test]
+    fn run_job_reports_load_failures_per_output() {
+        let dir = tempfile::tempdir().unwrap();
+        let job = ExportJob {
+            source: dir.path().join("missing.jpg").to_string_lossy().to_string(),
+            params: EditParams::default(),
+            outputs: plan(
+                &[("x.jpg".into(), EditParams::default())],
+                &[ExportCrop::AsEdited, ExportCrop::Aspect(16, 9)],
+                dir.path(),
+            )
+            .remove(0)
+            .outputs,
+            smart_preview: None,
+            key_frame: 0,
+            annotations: Vec::new(),
+            target_size: None,
+        };
+        let results = run_job(&job, &Calibration::default(), ExportEncoding::default());
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().all(|r| r.is_err()));
+    }
+
+    #[test]
+    fn run_job_renders_offline_sources_from_their_smart_preview() {
+        let dir = tempfile::tempdir().unwrap();
+        let proxy = dir.path().join("proxy.jpg");
+        image::RgbImage::from_pixel(8, 6, image::Rgb([120, 80, 40]))
+            .save(&proxy)
+            .unwrap();
+        let mut job = plan(
+            &[(
+                dir.path().join("offline.cr3").to_string_lossy().to_string(),
+                EditParams::default(),
+            )],
+            &[ExportCrop::AsEdited],
+            dir.path(),
+        )
+        .remove(0);
+        job.smart_preview = Some(proxy.to_string_lossy().to_string());
+
+        let results = run_job(&job, &Calibration::default(), ExportEncoding::default());
+        assert_eq!(results, vec![Ok(None)]);
+        let exported = image::open(&job.outputs[0].path).unwrap();
+        assert_eq!((exported.width(), exported.height()), (8, 6));
+    }
+}
@@ -0,0 +1,177 @@
+//! Building smart previews for catalog photos. Proxies live in a folder
+//! beside the catalog, so they travel with it, named by photo id, so
+//! duplicates of one file each own theirs. The app deletes a proxy along
+//! with its photo; the catalog only records where it is.
+
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use tracing::error;
+
+use crema_catalog::db::Catalog;
+use crema_catalog::models::{PhotoId, SmartPreview};
+
+use crate::calibration::Calibration;
+use crate::volumes;
+
+pub fn previews_dir(catalog_path: &str) -> PathBuf {
+    // SQLite URIs (the `--demo` catalog) carry options after the file name.
+    let file = catalog_path
+        .strip_prefix("file:")
+        .map_or(catalog_path, |uri| uri.split('?').next().unwrap_or(uri));
+    Path::new(file).with_extension("previews")
+}
+
+/// Decode the photo's original, calibrate it and store a smart preview.
+/// Calibration happens here because a proxy is too small to match dark
+/// frames or defect maps later; proxies are never calibrated again.
+pub fn build(
+    catalog: &Catalog,
+    id: PhotoId,
+    calibration: &Calibration,
+    dir: &Path,
+) -> Result<SmartPreview> {
+    let photo = catalog.get_photo(id)?.context("photo not found")?;
+    let source = Path::new(&photo.file_path);
+    let buf = calibration.decode(source, 0, calibration.exif_for(source).as_ref())?;
+
+    let path = dir.join(format!("{id}.jpg"));
+    let (width, height) = crema_thumbnails::smart_preview::write_smart_preview(&buf, &path)?;
+    let preview = SmartPreview {
+        photo_id: id,
+        path: path.to_string_lossy().to_string(),
+        width,
+        height,
+    };
+    catalog.save_smart_preview(&preview)?;
+    Ok(preview)
+}
+
+/// Build previews for `ids`, leaving out photos whose original is on an
+/// unmounted drive. Returns those with their original's path, to be built
+/// once the drive is back.
+pub fn build_all(
+    catalog: &Catalog,
+    ids: &[PhotoId],
+    calibration: &Calibration,
+    dir: &Path,
+) -> Vec<(PhotoId, PathBuf)> {
+    let mut held = Vec::new();
+    for &id in ids {
+        if let Ok(Some(photo)) = catalog.get_photo(id)
+            && volumes::is_offline(Path::new(&photo.file_path))
+        {
+            held.push((id, PathBuf::from(photo.file_path)));
+            continue;
+        }
+        if let Err(err) = build(catalog, id, calibration, dir) {
+            error!(%err, id, "failed to build smart preview");
+        }
+    }
+    held
+}
+
+/// Delete the proxy of a photo removed from the catalog.
+pub fn remove(preview: &SmartPreview) {
+    if let Err(err) = std::fs::remove_file(&preview.path)
+        && err.kind() != ErrorKind::NotFound
+    {
+        error!(%err, path = %preview.path, "failed to remove smart preview");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn preview_stands_in_for_an_offline_original() {
+        let dir = tempfile::tempdir().unwrap();
+        let original = dir.path().join("sky.png");
+        image::RgbImage::from_pixel(40, 30, image::Rgb([90, 140, 220]))
+            .save(&original)
+            .unwrap();
+        let catalog_path = dir.path().join("catalog.db");
+        let catalog = Catalog::open(&catalog_path.to_string_lossy()).unwrap();
+        let id = crema_catalog::import::import_file(&catalog, &original)
+            .unwrap()
+            .unwrap();
+
+        let previews = previews_dir(&catalog_path.to_string_lossy());
+        assert_eq!(previews, dir.path().join("catalog.previews"));
+        let preview = build(&catalog, id, &Calibration::default(), &previews).unwrap();
+        assert_eq!((preview.width, preview.height), (40, 30));
+
+        std::fs::remove_file(&original).unwrap();
+        let proxy = crema_core::raw::load_any(Path::new(&preview.path)).unwrap();
+        assert_eq!(proxy.width, 40);
+    }
+
+    #[test]
+    fn duplicates_keep_their_own_previews() {
+        let dir = tempfile::tempdir().unwrap();
+        let catalog = Catalog::open_in_memory().unwrap();
+        let ids: Vec<_> = ["a.png", "copy of a.png"]
+            .into_iter()
+            .map(|name| {
+                let path = dir.path().join(name);
+                image::RgbImage::new(8, 8).save(&path).unwrap();
+                crema_catalog::import::import_file(&catalog, &path)
+                    .unwrap()
+                    .unwrap()
+            })
+            .collect();
+        let previews = dir.path().join("previews");
+        let [a, copy] = [ids[0], ids[1]]
+            .map(|id| build(&catalog, id, &Calibration::default(), &previews).unwrap());
+        assert_ne!(a.path, copy.path);
+
+        remove(&a);
+        assert!(!Path::new(&a.path).exists());
+        assert!(Path::new(&copy.path).exists());
+        // Already gone is fine.
+        remove(&a);
+    }
+
+    #[test]
+    fn previews_of_offline_originals_wait_for_their_drive() {
+        let dir = tempfile::tempdir().unwrap();
+        let catalog = Catalog::open_in_memory().unwrap();
+        let online = dir.path().join("a.png");
+        image::RgbImage::new(8, 8).save(&online).unwrap();
+        let online = crema_catalog::import::import_file(&catalog, &online)
+            .unwrap()
+            .unwrap();
+        let unplugged = "/Volumes/crema-unplugged-drive/b.png";
+        let offline = catalog
+            .insert_photo(&crema_catalog::db::InsertPhoto {
+                file_path: unplugged.into(),
+                file_hash: "b".into(),
+                file_size: 1,
+                width: None,
+                height: None,
+                camera_make: None,
+                camera_model: None,
+                lens: None,
+                focal_length: None,
+                aperture: None,
+                shutter_speed: None,
+                iso: None,
+                date_taken: None,
+                thumbnail_path: None,
+            })
+            .unwrap()
+            .unwrap();
+
+        let previews = dir.path().join("previews");
+        let held = build_all(
+            &catalog,
+            &[online, offline],
+            &Calibration::default(),
+            &previews,
+        );
+        assert_eq!(held, [(offline, PathBuf::from(unplugged))]);
+        assert!(catalog.smart_preview(online).unwrap().is_some());
+    }
+}
@@ -0,0 +1,291 @@
+use iced::theme::Palette;
+use iced::theme::palette::Extended;
+use iced::widget::text;
+use iced::{Color, Theme};
+use serde::{Deserialize, Serialize};
+
+use crate::preferences::Preferences;
+
+const MUTED: Color = Color::from_rgb(0.66, 0.66, 0.69);
+const BORDER: Color = Color::from_rgb(0.20, 0.20, 0.22);
+const HIGH_CONTRAST_BORDER: Color = Color::from_rgb(0.78, 0.78, 0.82);
+const THEME_NAME: &str = "Crema";
+/// Only [`app_theme`] builds a theme with this name, and only when
+/// `Preferences::high_contrast` is set.
+const HIGH_CONTRAST_THEME_NAME: &str = "Crema High Contrast";
+
+/// Accent used for selection, active filters and primary actions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AccentColor {
+    #[default]
+    Blue,
+    Orange,
+    Teal,
+    Purple,
+    Yellow,
+}
+
+impl AccentColor {
+    pub const ALL: [AccentColor; 5] = [
+        AccentColor::Blue,
+        AccentColor::Orange,
+        AccentColor::Teal,
+        AccentColor::Purple,
+        AccentColor::Yellow,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            AccentColor::Blue => "Blue",
+            AccentColor::Orange => "Orange",
+            AccentColor::Teal => "Teal",
+            AccentColor::Purple => "Purple",
+            AccentColor::Yellow => "Yellow",
+        }
+    }
+
+    /// Everything except Blue comes from the Okabe-Ito set, which stays
+    /// distinguishable under the common forms of color blindness.
+    pub fn color(self) -> Color {
+        match self {
+            AccentColor::Blue => Color::from_rgb(0.26, 0.52, 0.94),
+            AccentColor::Orange => Color::from_rgb8(0xE6, 0x9F, 0x00),
+            AccentColor::Teal => Color::from_rgb8(0x00, 0x9E, 0x73),
+            AccentColor::Purple => Color::from_rgb8(0xCC, 0x79, 0xA7),
+            AccentColor::Yellow => Color::from_rgb8(0xF0, 0xE4, 0x42),
+        }
+    }
+}
+
+/// Color vision mode used to pick scope and overlay colors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ColorVision {
+    #[default]
+    Standard,
+    Deuteranopia,
+    Protanopia,
+}
+
+impl ColorVision {
+    pub const ALL: [ColorVision; 3] = [
+        ColorVision::Standard,
+        ColorVision::Deuteranopia,
+        ColorVision::Protanopia,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ColorVision::Standard => "Standard",
+            ColorVision::Deuteranopia => "Deuteranopia",
+            ColorVision::Protanopia => "Protanopia",
+        }
+    }
+}
+
+/// Colors for histogram channels and clipping warnings.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScopePalette {
+    pub red: Color,
+    pub green: Color,
+    pub blue: Color,
+    pub shadow_clip: Color,
+    pub highlight_clip: Color,
+    /// Outline each channel with its own dash pattern so the plots can be
+    /// told apart without relying on hue alone.
+    pub patterned: bool,
+}
+
+impl ScopePalette {
+    pub fn for_vision(vision: ColorVision) -> Self {
+        match vision {
+            ColorVision::Standard => Self {
+                red: Color::from_rgb(1.0, 0.0, 0.0),
+                green: Color::from_rgb(0.0, 1.0, 0.0),
+                blue: Color::from_rgb(0.0, 0.4, 1.0),
+                shadow_clip: Color::from_rgb(0.2, 0.5, 1.0),
+                highlight_clip: Color::from_rgb(1.0, 0.25, 0.2),
+                patterned: false,
+            },
+            // Red and green collapse onto one yellow-brown axis, so the
+            // channels are separated by lightness and blue content instead.
+            ColorVision::Deuteranopia => Self {
+                red: Color::from_rgb8(0xD5, 0x5E, 0x00),
+                green: Color::from_rgb8(0xF0, 0xE4, 0x42),
+                blue: Color::from_rgb8(0x00, 0x72, 0xB2),
+                shadow_clip: Color::from_rgb8(0x56, 0xB4, 0xE9),
+                highlight_clip: Color::from_rgb8(0xE6, 0x9F, 0x00),
+                patterned: true,
+            },
+            // Protanopes see long wavelengths as darker, so red is lifted to
+            // orange to keep it from sinking into the background, and
+            // clipping takes the blue-heavy reddish purple instead.
+            ColorVision::Protanopia => Self {
+                red: Color::from_rgb8(0xE6, 0x9F, 0x00),
+                green: Color::from_rgb8(0xF0, 0xE4, 0x42),
+                blue: Color::from_rgb8(0x00, 0x72, 0xB2),
+                shadow_clip: Color::from_rgb8(0x56, 0xB4, 0xE9),
+                highlight_clip: Color::from_rgb8(0xCC, 0x79, 0xA7),
+                patterned: true,
+            },
+        }
+    }
+}
+
+/// Build the iced theme for the given preferences. Widgets read the accent
+/// back out through `theme.palette().primary`, and borders through
+/// [`border`].
+pub fn app_theme(prefs: &Preferences) -> Theme {
+    let high_contrast = prefs.high_contrast;
+    let palette = Palette {
+        primary: prefs.accent.color(),
+        text: if high_contrast {
+            Color::WHITE
+        } else {
+            Palette::DARK.text
+        },
+        ..Palette::DARK
+    };
+    let name = if high_contrast {
+        HIGH_CONTRAST_THEME_NAME
+    } else {
+        THEME_NAME
+    };
+
+    Theme::custom_with_fn(name, palette, move |palette| {
+        let mut extended = Extended::generate(palette);
+        if high_contrast {
+            // iced's own widgets (scrollbars, sliders) draw with these.
+            extended.background.strong.color = HIGH_CONTRAST_BORDER;
+            extended.background.stronger.color = Color::WHITE;
+        }
+        extended
+    })
+}
+
+/// Whether `theme` was built with `Preferences::high_contrast` on. Style
+/// functions only see the theme, so the preference travels in its name.
+pub fn is_high_contrast(theme: &Theme) -> bool {
+    matches!(theme, Theme::Custom(custom) if custom.to_string() == HIGH_CONTRAST_THEME_NAME)
+}
+
+pub fn border(theme: &Theme) -> Color {
+    if is_high_contrast(theme) {
+        HIGH_CONTRAST_BORDER
+    } else {
+        BORDER
+    }
+}
+
+/// Width of selection and hover outlines, thickened in high contrast so
+/// the focused item is obvious at a glance.
+pub fn ring_width(theme: &Theme, base: f32) -> f32 {
+    if is_high_contrast(theme) {
+        base + 1.0
+    } else {
+        base
+    }
+}
+
+pub fn accent(theme: &Theme) -> Color {
+    theme.palette().primary
+}
+
+/// Text color that stays readable on top of the accent.
+pub fn on_accent(theme: &Theme) -> Color {
+    theme.extended_palette().primary.base.text
+}
+
+/// Label style for toggle-like buttons: accent when active, muted otherwise.
+pub fn active_label(theme: &Theme, active: bool) -> text::Style {
+    text::Style {
+        color: Some(if active { accent(theme) } else { MUTED }),
+    }
+}
+
+/// Scale the accent's RGB channels, e.g. for hover and pressed states.
+pub fn shade(color: Color, factor: f32) -> Color {
+    Color {
+        r: (color.r * factor).min(1.0),
+        g: (color.g * factor).min(1.0),
+        b: (color.b * factor).min(1.0),
+        a: color.a,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn distance(a: Color, b: Color) -> f32 {
+        ((a.r - b.r).powi(2) + (a.g - b.g).powi(2) + (a.b - b.b).powi(2)).sqrt()
+    }
+
+    #[test]
+    fn default_accent_matches_original_blue() {
+        let theme = app_theme(&Preferences::default());
+        assert_eq!(accent(&theme), Color::from_rgb(0.26, 0.52, 0.94));
+    }
+
+    #[test]
+    fn theme_uses_selected_accent() {
+        let prefs = Preferences {
+            accent: AccentColor::Orange,
+            ..Preferences::default()
+        };
+        assert_eq!(accent(&app_theme(&prefs)), AccentColor::Orange.color());
+    }
+
+    #[test]
+    fn high_contrast_strengthens_borders() {
+        let normal = app_theme(&Preferences::default());
+        let high = app_theme(&Preferences {
+            high_contrast: true,
+            ..Preferences::default()
+        });
+        assert!(!is_high_contrast(&normal));
+        assert!(is_high_contrast(&high));
+        assert_eq!(border(&normal), BORDER);
+        assert_eq!(border(&high), HIGH_CONTRAST_BORDER);
+        assert_eq!(ring_width(&normal, 1.0), 1.0);
+        assert_eq!(ring_width(&high, 1.0), 2.0);
+    }
+
+    #[test]
+    fn color_blind_palettes_are_patterned() {
+        assert!(!ScopePalette::for_vision(ColorVision::Standard).patterned);
+        assert!(ScopePalette::for_vision(ColorVision::Deuteranopia).patterned);
+        assert!(ScopePalette::for_vision(ColorVision::Protanopia).patterned);
+    }
+
+    #[test]
+    fn channel_colors_are_distinct() {
+        for vision in ColorVision::ALL {
+            let p = ScopePalette::for_vision(vision);
+            assert!(distance(p.red, p.green) > 0.3, "{vision:?} red/green");
+            assert!(distance(p.green, p.blue) > 0.3, "{vision:?} green/blue");
+            assert!(distance(p.red, p.blue) > 0.3, "{vision:?} red/blue");
+        }
+    }
+
+    #[test]
+    fn every_scope_color_is_distinct() {
+        for vision in ColorVision::ALL {
+            let p = ScopePalette::for_vision(vision);
+            let colors = [p.red, p.green, p.blue, p.shadow_clip, p.highlight_clip];
+            for (i, a) in colors.iter().enumerate() {
+                for b in &colors[i + 1..] {
+                    assert!(distance(*a, *b) > 0.2, "{vision:?} {a:?}/{b:?}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn shade_clamps_to_one() {
+        let c = shade(Color::from_rgb(0.8, 0.5, 0.1), 1.5);
+        assert_eq!(c.r, 1.0);
+        assert!((c.g - 0.75).abs() < 1e-6);
+    }
+}
@@ -1,30 +1,42 @@
-use iced::widget::{Space, button, column, container, row, scrollable, text};
+use iced::widget::{
+    Space, button, center, column, container, opaque, progress_bar, row, scrollable, stack, text,
+    text_input, toggler,
+};
 use iced::{Alignment, Background, Border, Color, Element, Length, Shadow, Theme};
 
-use crema_catalog::models::Photo;
+use crema_catalog::models::{Animation, Photo, ThumbnailFidelity};
+use crema_core::dither::Dither;
+use crema_export::format::ExportColorSpace;
 
-use crate::app::{App, Message, PanelSection, Workspace};
+use crate::app::{App, Message, PanelSection, Workspace, dark_settings_label};
+use crate::export_crop::ExportCrop;
+use crate::export_metadata::{self, ExportMetadata};
+use crate::theme::{self, AccentColor, ColorVision};
 use crate::widgets;
+use crate::widgets::batch_metadata::MetadataField;
+use crate::widgets::thumbnail_grid::GridLayout;
 use crate::widgets::zoomable_image::CropOverlay;
 
 const APP_BG: Color = Color::from_rgb(0.08, 0.08, 0.09);
 const PANEL_BG: Color = Color::from_rgb(0.12, 0.12, 0.13);
 const PANEL_ALT_BG: Color = Color::from_rgb(0.10, 0.10, 0.11);
 const CANVAS_BG: Color = Color::from_rgb(0.06, 0.06, 0.07);
-const BORDER: Color = Color::from_rgb(0.20, 0.20, 0.22);
 const MUTED: Color = Color::from_rgb(0.66, 0.66, 0.69);
-const ACCENT: Color = Color::from_rgb(0.26, 0.52, 0.94);
 
 pub fn view(app: &App) -> Element<'_, Message> {
     let filtered = app.filtered_photos();
     let filtered_count = filtered.len();
 
-    let content = match app.workspace() {
-        Workspace::Library => library_body(app, filtered),
-        Workspace::Develop => develop_body(app),
+    let content = if app.preferences_open() {
+        preferences_body(app)
+    } else {
+        match app.workspace() {
+            Workspace::Library => library_body(app, filtered),
+            Workspace::Develop => develop_body(app),
+        }
     };
 
-    container(
+    let shell = container(
         column![toolbar(app, filtered_count), content, bottom_bar(app)]
             .width(Length::Fill)
             .height(Length::Fill)
@@ -32,8 +44,99 @@ pub fn view(app: &App) -> Element<'_, Message> {
     )
     .style(app_shell)
     .width(Length::Fill)
-    .height(Length::Fill)
-    .into()
+    .height(Length::Fill);
+
+    let dialog = if let Some(form) = app.batch_metadata() {
+        Some(widgets::batch_metadata::view(form))
+    } else if let Some(editor) = app.preset_editor() {
+        Some(widgets::preset_editor::view(editor, app.edit_params()))
+    } else if let Some(review) = app.auto_stack() {
+        Some(widgets::auto_stack::view(
+            review,
+            &app.preferences().auto_stack,
+        ))
+    } else if let Some(form) = app.gear_override() {
+        Some(widgets::gear_override::view(form))
+    } else if app.animation_export_open() {
+        Some(widgets::animation_export::view(app.edit_params()))
+    } else if let Some(options) = app.comparison_export() {
+        Some(widgets::comparison_export::view(options))
+    } else if let Some(options) = app.export_options() {
+        Some(widgets::export_options::view(options))
+    } else if let Some(review) = app.export_review() {
+        Some(widgets::export_warnings::view(review))
+    } else if app.stack_open() {
+        Some(widgets::stack::view(app.selected_photos().len()))
+    } else if app.link_sources_open() {
+        Some(widgets::link_sources::view(
+            app.current_photo_label(),
+            app.link_source_ids().len(),
+        ))
+    } else if app.quarantine_open() {
+        Some(widgets::quarantine::view(app.quarantine()))
+    } else if let Some(form) = app.path_remap() {
+        Some(widgets::path_remap::view(form))
+    } else if let Some(check) = app.render_consistency() {
+        Some(widgets::render_consistency::view(check))
+    } else if let Some(dates) = app.time_machine_dates() {
+        Some(widgets::time_machine::picker(dates))
+    } else if app.gpu_diagnostics_open() {
+        Some(widgets::gpu_diagnostics::view(app.renderer()))
+    } else if app.gpu_notice_open() {
+        Some(widgets::gpu_diagnostics::notice(app.renderer()))
+    } else {
+        app.edit_diff().map(|snapshot| {
+            widgets::edit_diff::view(
+                "Current",
+                &snapshot.name,
+                crema_core::edit_diff::diff(app.edit_params(), &snapshot.params),
+            )
+        })
+    };
+
+    let base: Element<'_, Message> = match dialog {
+        Some(dialog) => stack![
+            shell,
+            opaque(center(dialog).style(|_theme: &Theme| container::Style {
+                background: Some(Background::Color(Color::from_rgba(0.0, 0.0, 0.0, 0.55))),
+                ..Default::default()
+            })),
+        ]
+        .into(),
+        None => shell.into(),
+    };
+
+    let notifications = app.notifications();
+    let mut floating = column![].spacing(8).align_x(Alignment::End);
+    if notifications.has_toasts() {
+        floating = floating.push(widgets::notifications::toasts(notifications));
+    }
+    if let Some(progress) = app.export_progress() {
+        floating = floating.push(widgets::export_progress::view(progress));
+    }
+    let mut layers = stack![
+        base,
+        // Clear of the bottom bar, so its status stays readable.
+        container(floating)
+            .padding([56, 20])
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .align_right(Length::Fill)
+            .align_bottom(Length::Fill),
+    ];
+    if notifications.drawer_open {
+        layers = layers.push(
+            container(widgets::notifications::drawer(
+                notifications,
+                &app.preferences().muted_notifications,
+            ))
+            .padding([72, 14])
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .align_right(Length::Fill),
+        );
+    }
+    layers.into()
 }
 
 fn toolbar(app: &App, filtered_count: usize) -> Element<'_, Message> {
@@ -78,6 +181,29 @@ fn toolbar(app: &App, filtered_count: usize) -> Element<'_, Message> {
     .padding([8, 12])
     .style(secondary_action);
 
+    let unread = app.notifications().unread();
+    let notifications_btn = button(text(if unread > 0 {
+        format!("Notifications ({unread})")
+    } else {
+        "Notifications".to_string()
+    }))
+    .on_press(Message::ToggleNotificationDrawer)
+    .padding([8, 12])
+    .style(if app.notifications().drawer_open {
+        primary_action
+    } else {
+        secondary_action
+    });
+
+    let settings_btn = button("Settings")
+        .on_press(Message::TogglePreferences)
+        .padding([8, 12])
+        .style(if app.preferences_open() {
+            primary_action
+        } else {
+            secondary_action
+        });
+
     let photo_summary = if app.workspace() == Workspace::Develop {
         column![
             text(app.current_photo_label()).size(14),
@@ -109,6 +235,10 @@ fn toolbar(app: &App, filtered_count: usize) -> Element<'_, Message> {
             export_btn,
             Space::new().width(8),
             panel_btn,
+            Space::new().width(8),
+            notifications_btn,
+            Space::new().width(8),
+            settings_btn,
         ]
         .align_y(Alignment::Center),
     )
@@ -120,19 +250,49 @@ fn toolbar(app: &App, filtered_count: usize) -> Element<'_, Message> {
 fn library_body<'a>(app: &'a App, filtered: Vec<&'a Photo>) -> Element<'a, Message> {
     row![
         widgets::date_sidebar::view(
-            app.photos(),
+            app.date_index(),
+            app.volumes(),
+            app.gear_counts(),
             app.date_filter(),
             app.expanded_dates(),
             app.rating_filter(),
+            app.gear_filter(),
             app.sort_order()
         ),
         library_grid(app, filtered),
+        quick_develop_panel(app),
     ]
     .width(Length::Fill)
     .height(Length::Fill)
     .into()
 }
 
+fn quick_develop_panel(app: &App) -> Element<'_, Message> {
+    let body = match app.time_machine() {
+        Some(machine) => {
+            let targets = match app.selected_photos().len() {
+                0 => usize::from(app.has_selection()),
+                n => n,
+            };
+            widgets::time_machine::panel(machine, app.selected_photo(), targets)
+        }
+        None => section_card(
+            "Quick Develop",
+            app.is_panel_open(PanelSection::QuickDevelop),
+            Message::TogglePanelSection(PanelSection::QuickDevelop),
+            None,
+            widgets::quick_develop::view(app.has_selection()),
+        ),
+    };
+    let content = column![body].spacing(10).padding(12).width(240);
+
+    container(scrollable(content).height(Length::Fill))
+        .style(side_panel)
+        .width(240)
+        .height(Length::Fill)
+        .into()
+}
+
 fn library_grid<'a>(app: &'a App, filtered: Vec<&'a Photo>) -> Element<'a, Message> {
     let selection_label: Element<'a, Message> = if app.has_selection() {
         text(format!("Selected: {}", app.current_photo_label()))
@@ -152,17 +312,76 @@ fn library_grid<'a>(app: &'a App, filtered: Vec<&'a Photo>) -> Element<'a, Messa
         .style(primary_action)
         .into();
 
+    let metadata_button = button("Edit Metadata")
+        .on_press_maybe(app.has_selection().then_some(Message::OpenBatchMetadata))
+        .padding([8, 14])
+        .style(secondary_action);
+
+    let gear_button = button("Camera & Lens")
+        .on_press_maybe(app.has_selection().then_some(Message::OpenGearOverride))
+        .padding([8, 14])
+        .style(secondary_action);
+
+    let stack_button: Element<'a, Message> = if app.selected_photos().len() >= 2 {
+        button(text(format!("Stack {}", app.selected_photos().len())))
+            .on_press(Message::OpenStack)
+            .padding([8, 14])
+            .style(secondary_action)
+            .into()
+    } else {
+        Space::new().into()
+    };
+
+    let link_button: Element<'a, Message> = if app.link_source_ids().is_empty() {
+        Space::new().into()
+    } else {
+        button("Link Sources")
+            .on_press(Message::OpenLinkSources)
+            .padding([8, 14])
+            .style(secondary_action)
+            .into()
+    };
+
+    let quarantine_button: Element<'a, Message> = if app.quarantine().is_empty() {
+        Space::new().into()
+    } else {
+        button(text(format!("Quarantine ({})", app.quarantine().len())))
+            .on_press(Message::OpenQuarantine)
+            .padding([8, 14])
+            .style(secondary_action)
+            .into()
+    };
+
+    let mut title = column![
+        text("Library").size(20),
+        text(format!("{} visible photos", filtered.len()))
+            .size(12)
+            .color(MUTED),
+    ]
+    .spacing(2);
+    if let Some((loaded, total)) = app.catalog_progress() {
+        title = title.push(
+            progress_bar(0.0..=total.max(1) as f32, loaded as f32)
+                .length(160)
+                .girth(4),
+        );
+    }
+
     let heading = row![
-        column![
-            text("Library").size(20),
-            text(format!("{} visible photos", filtered.len()))
-                .size(12)
-                .color(MUTED),
-        ]
-        .spacing(2),
+        title,
         Space::new().width(Length::Fill),
         selection_label,
         Space::new().width(12),
+        quarantine_button,
+        Space::new().width(8),
+        stack_button,
+        Space::new().width(8),
+        link_button,
+        Space::new().width(8),
+        gear_button,
+        Space::new().width(8),
+        metadata_button,
+        Space::new().width(8),
         open_button,
     ]
     .align_y(Alignment::Center);
@@ -173,8 +392,14 @@ fn library_grid<'a>(app: &'a App, filtered: Vec<&'a Photo>) -> Element<'a, Messa
             scrollable(widgets::thumbnail_grid::view(
                 filtered,
                 app.thumbnails(),
+                app.thumbnail_aspects(),
+                app.animations(),
+                app.stacks(),
+                app.expanded_stacks(),
+                app.offline_photos(),
                 app.selected_photo(),
                 app.selected_photos(),
+                app.preferences().grid_layout,
             ))
             .height(Length::Fill)
             .width(Length::Fill),
@@ -188,6 +413,545 @@ fn library_grid<'a>(app: &'a App, filtered: Vec<&'a Photo>) -> Element<'a, Messa
     .into()
 }
 
+fn preferences_body(app: &App) -> Element<'_, Message> {
+    let prefs = app.preferences();
+
+    let mut accents = row![].spacing(6);
+    for accent in AccentColor::ALL {
+        let swatch = container(Space::new().width(12).height(12)).style(move |theme: &Theme| {
+            container::Style {
+                background: Some(Background::Color(accent.color())),
+                border: Border {
+                    color: theme::border(theme),
+                    width: 1.0,
+                    radius: 3.0.into(),
+                },
+                ..Default::default()
+            }
+        });
+        accents = accents.push(
+            button(
+                row![swatch, text(accent.label()).size(12)]
+                    .spacing(6)
+                    .align_y(Alignment::Center),
+            )
+            .on_press(Message::SetAccentColor(accent))
+            .padding([6, 10])
+            .style(if prefs.accent == accent {
+                primary_action
+            } else {
+                secondary_action
+            }),
+        );
+    }
+
+    let mut visions = row![].spacing(6);
+    for vision in ColorVision::ALL {
+        visions = visions.push(
+            button(text(vision.label()).size(12))
+                .on_press(Message::SetColorVision(vision))
+                .padding([6, 10])
+                .style(if prefs.color_vision == vision {
+                    primary_action
+                } else {
+                    secondary_action
+                }),
+        );
+    }
+
+    let appearance = column![
+        text("Appearance").size(16),
+        text("Accent color").size(13),
+        accents,
+        text("Color vision").size(13),
+        text("Changes histogram channel colors and clipping warnings.")
+            .size(11)
+            .color(MUTED),
+        visions,
+        widgets::histogram::view(app.histogram(), app.scope_palette()),
+    ]
+    .spacing(10)
+    .padding(14);
+
+    let mut layouts = row![].spacing(6);
+    for layout in GridLayout::ALL {
+        layouts = layouts.push(
+            button(text(layout.label()).size(12))
+                .on_press(Message::SetGridLayout(layout))
+                .padding([6, 10])
+                .style(if prefs.grid_layout == layout {
+                    primary_action
+                } else {
+                    secondary_action
+                }),
+        );
+    }
+
+    let fidelity_buttons =
+        |chosen: ThumbnailFidelity, on_press: &dyn Fn(ThumbnailFidelity) -> Message| {
+            let mut buttons = row![].spacing(6);
+            for fidelity in ThumbnailFidelity::ALL {
+                buttons = buttons.push(
+                    button(text(fidelity.label()).size(12))
+                        .on_press(on_press(fidelity))
+                        .padding([6, 10])
+                        .style(if chosen == fidelity {
+                            primary_action
+                        } else {
+                            secondary_action
+                        }),
+                );
+            }
+            buttons
+        };
+    let mut folders: Vec<_> = app.folder_thumbnail_fidelity().iter().collect();
+    folders.sort_by(|a, b| a.0.cmp(b.0));
+    let mut folder_rows = column![].spacing(6);
+    for (folder, &fidelity) in folders {
+        folder_rows = folder_rows.push(
+            row![
+                text(folder).size(12).width(Length::Fill),
+                fidelity_buttons(fidelity, &|fidelity| {
+                    Message::SetFolderThumbnailFidelity(folder.clone(), Some(fidelity))
+                }),
+                button(text("Remove").size(12))
+                    .on_press(Message::SetFolderThumbnailFidelity(folder.clone(), None))
+                    .padding([4, 10])
+                    .style(secondary_action),
+            ]
+            .spacing(10)
+            .align_y(Alignment::Center),
+        );
+    }
+
+    let library = column![
+        text("Library").size(16),
+        text("Grid layout").size(13),
+        text("Square crops thumbnails to fill uniform cells, Fit letterboxes them, and Justified keeps each aspect ratio in rows of equal height.")
+            .size(11)
+            .color(MUTED),
+        layouts,
+        text("Thumbnails").size(13),
+        text("Originals are quick to make. With edits shows each photo as developed, but takes longer and renders again after every edit. A folder can override this for the photos in and below it.")
+            .size(11)
+            .color(MUTED),
+        fidelity_buttons(prefs.thumbnail_fidelity, &Message::SetThumbnailFidelity),
+        folder_rows,
+        button(text("Add Folder Override...").size(12))
+            .on_press(Message::AddThumbnailFolder)
+            .padding([6, 10])
+            .style(secondary_action),
+        toggler(prefs.smart_previews)
+            .label("Build smart previews on import")
+            .text_size(13)
+            .on_toggle(Message::SetSmartPreviews),
+        text("Off by default. A 2560px proxy of each photo is kept in a folder next to the catalog, so photos still open, edit and export when the original's drive is disconnected. Only photos imported with this on get one.")
+            .size(11)
+            .color(MUTED),
+    ]
+    .spacing(10)
+    .padding(14);
+
+    let mut crops = row![].spacing(6);
+    for crop in ExportCrop::ALL {
+        let chosen = prefs.export_crops.contains(&crop);
+        crops = crops.push(
+            button(text(crop.label()).size(12))
+                .on_press(Message::ToggleExportCrop(crop))
+                .padding([6, 10])
+                .style(if chosen {
+                    primary_action
+                } else {
+                    secondary_action
+                }),
+        );
+    }
+
+    let mut workers = row![].spacing(6);
+    for count in [1, 2, 4, 8] {
+        let chosen = prefs.export_workers.max(1) == count;
+        workers = workers.push(
+            button(
+                text(if count == 1 {
+                    "In App".to_string()
+                } else {
+                    format!("{count} Processes")
+                })
+                .size(12),
+            )
+            .on_press(Message::SetExportWorkers(count))
+            .padding([6, 10])
+            .style(if chosen {
+                primary_action
+            } else {
+                secondary_action
+            }),
+        );
+    }
+
+    let mut dithers = row![].spacing(6);
+    for dither in Dither::ALL {
+        dithers = dithers.push(
+            button(text(dither.label()).size(12))
+                .on_press(Message::SetExportDither(dither))
+                .padding([6, 10])
+                .style(if prefs.export_dither == dither {
+                    primary_action
+                } else {
+                    secondary_action
+                }),
+        );
+    }
+
+    let mut color_spaces = row![].spacing(6);
+    for color_space in ExportColorSpace::ALL {
+        color_spaces = color_spaces.push(
+            button(text(color_space.label()).size(12))
+                .on_press(Message::SetExportColorSpace(color_space))
+                .padding([6, 10])
+                .style(if prefs.export_color_space == color_space {
+                    primary_action
+                } else {
+                    secondary_action
+                }),
+        );
+    }
+
+    let mut export = column![
+        text("Export").size(16),
+        text("Crops").size(13),
+        text("Each export writes one file per selected crop, trimmed from the center of the develop crop. The edit itself keeps its crop.")
+            .size(11)
+            .color(MUTED),
+        crops,
+        text("Batch export").size(13),
+        text("Large batches can render in separate worker processes, each taking a share of the photos, so every core is used and the app stays responsive.")
+            .size(11)
+            .color(MUTED),
+        workers,
+        text("Dithering").size(13),
+        text("Adds a trace of grain when rounding to 8 bits so skies and other smooth gradients don't show bands. Blue noise gives the finest grain. Previews are unaffected.")
+            .size(11)
+            .color(MUTED),
+        dithers,
+        text("Color space").size(13),
+        text("Display P3 keeps colors sRGB can't hold, for screens that show them. JPEG, PNG and TIFF exports carry a profile saying so. Batch exports are always sRGB.")
+            .size(11)
+            .color(MUTED),
+        color_spaces,
+        toggler(prefs.export_tiff_16bit)
+            .label("Write TIFFs at 16 bits per channel")
+            .text_size(13)
+            .on_toggle(Message::SetExportTiff16Bit),
+        metadata_template(&prefs.export_metadata, app.metadata_template_crop()),
+        text("Post-processors").size(13),
+        text("Programs run on every exported file, in order, for borders, uploads or renaming. Each is given the file's path and a JSON description of the photo; one that fails or hangs is stopped and reported without affecting the export.")
+            .size(11)
+            .color(MUTED),
+    ]
+    .spacing(10)
+    .padding(14);
+    for (index, plugin) in prefs.export_plugins.iter().enumerate() {
+        export = export.push(
+            row![
+                toggler(plugin.enabled)
+                    .label(&plugin.name)
+                    .text_size(13)
+                    .on_toggle(move |enabled| Message::SetExportPluginEnabled(index, enabled))
+                    .width(Length::Fill),
+                button(text("Remove").size(12))
+                    .on_press(Message::RemoveExportPlugin(index))
+                    .padding([4, 10])
+                    .style(secondary_action),
+            ]
+            .spacing(10)
+            .align_y(Alignment::Center),
+        );
+    }
+    export = export.push(
+        button(text("Add Post-Processor...").size(12))
+            .on_press(Message::AddExportPlugin)
+            .padding([6, 10])
+            .style(secondary_action),
+    );
+    if !prefs.suppressed_export_warnings.is_empty() {
+        export = export.push(
+            button(text("Show Export Warnings Again").size(12))
+                .on_press(Message::ResetExportWarnings)
+                .padding([6, 10])
+                .style(secondary_action),
+        );
+    }
+
+    let calibration = app.calibration();
+    let mut cameras = column![
+        text("Cameras").size(16),
+        text("Stuck pixels found in a dark frame are patched in every photo from the same camera body, matched by serial number.")
+            .size(11)
+            .color(MUTED),
+    ]
+    .spacing(10)
+    .padding(14);
+    for entry in &calibration.defect_maps {
+        cameras = cameras.push(
+            row![
+                text(format!(
+                    "{} #{}",
+                    entry.camera_model.as_deref().unwrap_or("Camera"),
+                    entry.camera_serial
+                ))
+                .size(13)
+                .width(Length::Fill),
+                text(format!("{} pixels", entry.map.pixels.len()))
+                    .size(12)
+                    .color(MUTED),
+                button(text("Remove").size(12))
+                    .on_press(Message::DeleteDefectMap(entry.camera_serial.clone()))
+                    .padding([4, 10])
+                    .style(secondary_action),
+            ]
+            .spacing(10)
+            .align_y(Alignment::Center),
+        );
+    }
+    cameras = cameras.push(
+        button(text("Create from Dark Frame...").size(12))
+            .on_press(Message::CreateDefectMap)
+            .padding([6, 10])
+            .style(secondary_action),
+    );
+    cameras = cameras.push(
+        text("A flat frame of an evenly lit surface removes vignetting and color cast from every photo taken with the same camera and lens.")
+            .size(11)
+            .color(MUTED),
+    );
+    for entry in &calibration.flat_fields {
+        let lens = if entry.lens.is_empty() {
+            "Unknown lens"
+        } else {
+            &entry.lens
+        };
+        cameras = cameras.push(
+            row![
+                text(format!("{} with {lens}", entry.camera_model))
+                    .size(13)
+                    .width(Length::Fill),
+                text(&entry.created_at).size(12).color(MUTED),
+                button(text("Remove").size(12))
+                    .on_press(Message::DeleteFlatField(
+                        entry.camera_model.clone(),
+                        entry.lens.clone()
+                    ))
+                    .padding([4, 10])
+                    .style(secondary_action),
+            ]
+            .spacing(10)
+            .align_y(Alignment::Center),
+        );
+    }
+    cameras = cameras.push(
+        button(text("Create from Flat Frame...").size(12))
+            .on_press(Message::CreateFlatField)
+            .padding([6, 10])
+            .style(secondary_action),
+    );
+    cameras = cameras.push(
+        toggler(prefs.dark_frame_subtraction)
+            .label("Subtract master darks")
+            .text_size(13)
+            .on_toggle(Message::SetDarkFrameSubtraction),
+    );
+    cameras = cameras.push(
+        text("Long exposures shot with the same camera, ISO and shutter speed, within 3°C, have the master dark's thermal noise and amp glow removed.")
+            .size(11)
+            .color(MUTED),
+    );
+    for info in &calibration.master_darks {
+        cameras = cameras.push(
+            row![
+                text(dark_settings_label(&info.settings))
+                    .size(13)
+                    .width(Length::Fill),
+                text(format!("{} frames", info.frame_count))
+                    .size(12)
+                    .color(MUTED),
+                button(text("Remove").size(12))
+                    .on_press(Message::DeleteMasterDark(info.id))
+                    .padding([4, 10])
+                    .style(secondary_action),
+            ]
+            .spacing(10)
+            .align_y(Alignment::Center),
+        );
+    }
+    cameras = cameras.push(
+        button(text("Create Master Dark...").size(12))
+            .on_press(Message::CreateMasterDark)
+            .padding([6, 10])
+            .style(secondary_action),
+    );
+
+    let performance = column![
+        text("Performance").size(16),
+        toggler(prefs.gpu_warmup)
+            .label("Warm up the GPU at launch")
+            .text_size(13)
+            .on_toggle(Message::SetGpuWarmup),
+        text("Compiles the develop shaders in the background at startup, so the first edit is as quick as the rest.")
+            .size(11)
+            .color(MUTED),
+    ]
+    .spacing(10)
+    .padding(14);
+
+    let accessibility = column![
+        text("Accessibility").size(16),
+        toggler(prefs.high_contrast)
+            .label("High contrast")
+            .text_size(13)
+            .on_toggle(Message::SetHighContrast),
+        text("Brighter borders and thicker outlines around selected and hovered items.")
+            .size(11)
+            .color(MUTED),
+        toggler(prefs.reduce_motion)
+            .label("Reduce motion")
+            .text_size(13)
+            .on_toggle(Message::SetReduceMotion),
+        text("Keeps notifications up until you dismiss them instead of hiding them after a few seconds.")
+            .size(11)
+            .color(MUTED),
+    ]
+    .spacing(10)
+    .padding(14);
+
+    let heading = row![
+        text("Settings").size(20),
+        Space::new().width(Length::Fill),
+        button("Done")
+            .on_press(Message::TogglePreferences)
+            .padding([8, 14])
+            .style(secondary_action),
+    ]
+    .align_y(Alignment::Center);
+
+    container(
+        column![
+            heading,
+            container(appearance).style(card_container).max_width(640),
+            container(library).style(card_container).max_width(640),
+            container(export).style(card_container).max_width(640),
+            container(cameras).style(card_container).max_width(640),
+            container(performance).style(card_container).max_width(640),
+            container(accessibility)
+                .style(card_container)
+                .max_width(640),
+        ]
+        .spacing(12)
+        .padding(14),
+    )
+    .style(canvas_panel)
+    .width(Length::Fill)
+    .height(Length::Fill)
+    .into()
+}
+
+/// The export metadata switch and the template editor, for the shared
+/// template or one crop's own.
+fn metadata_template(
+    metadata: &ExportMetadata,
+    showing: Option<ExportCrop>,
+) -> Element<'_, Message> {
+    let tokens = export_metadata::TOKENS.join(" ");
+    let mut section = column![
+        text("Metadata").size(13),
+        text("Title, caption, keywords, copyright and rating are written into JPEG and PNG exports as XMP, and into JPEGs as IPTC too. Fields mix fixed text with tokens filled from each photo; keywords are comma separated. Export crops stand in for presets: each crop can have its own template or use the one for all exports.")
+            .size(11)
+            .color(MUTED),
+        toggler(metadata.enabled)
+            .label("Write metadata into exports")
+            .text_size(13)
+            .on_toggle(Message::SetExportMetadataEnabled),
+    ]
+    .spacing(10);
+    if !metadata.enabled {
+        return section.into();
+    }
+
+    let mut targets = row![
+        button(text("All Exports").size(12))
+            .on_press(Message::ShowMetadataTemplate(None))
+            .padding([6, 10])
+            .style(if showing.is_none() {
+                primary_action
+            } else {
+                secondary_action
+            })
+    ]
+    .spacing(6);
+    for crop in ExportCrop::ALL {
+        let label = if metadata.has_override(crop) {
+            format!("{} \u{2022}", crop.label())
+        } else {
+            crop.label()
+        };
+        targets = targets.push(
+            button(text(label).size(12))
+                .on_press(Message::ShowMetadataTemplate(Some(crop)))
+                .padding([6, 10])
+                .style(if showing == Some(crop) {
+                    primary_action
+                } else {
+                    secondary_action
+                }),
+        );
+    }
+    section = section.push(targets);
+
+    if let Some(crop) = showing {
+        section = section.push(
+            toggler(metadata.has_override(crop))
+                .label(format!("Own template for {} exports", crop.label()))
+                .text_size(13)
+                .on_toggle(move |own| Message::SetCropMetadataOverride(crop, own)),
+        );
+        if !metadata.has_override(crop) {
+            return section
+                .push(
+                    text("Uses the template for all exports.")
+                        .size(11)
+                        .color(MUTED),
+                )
+                .into();
+        }
+    }
+
+    let template = match showing {
+        Some(crop) => metadata.template_for(crop),
+        None => &metadata.template,
+    };
+    for field in MetadataField::ALL {
+        section = section.push(
+            column![
+                text(field.label()).size(12),
+                text_input("", template.field(field))
+                    .on_input(move |value| Message::SetMetadataTemplateField(field, value))
+                    .size(13)
+                    .padding(6),
+            ]
+            .spacing(4),
+        );
+    }
+    section
+        .push(
+            toggler(template.rating)
+                .label("Write the star rating")
+                .text_size(13)
+                .on_toggle(Message::SetMetadataTemplateRating),
+        )
+        .push(text(format!("Tokens: {tokens}")).size(11).color(MUTED))
+        .into()
+}
+
 fn develop_body(app: &App) -> Element<'_, Message> {
     let mut center = row![photo_area(app)]
         .width(Length::Fill)
@@ -208,7 +972,12 @@ fn develop_body(app: &App) -> Element<'_, Message> {
 }
 
 fn photo_area(app: &App) -> Element<'_, Message> {
-    let status_message: Element<'_, Message> = if app.is_loading_photo() {
+    let status_message: Element<'_, Message> = if app.editing_embedded_preview() {
+        text("Editing embedded preview, decoding full RAW")
+            .size(11)
+            .color(MUTED)
+            .into()
+    } else if app.is_loading_photo() {
         text("Loading original + preview")
             .size(11)
             .color(MUTED)
@@ -224,7 +993,7 @@ fn photo_area(app: &App) -> Element<'_, Message> {
         text(zoom_label).size(11).color(MUTED).into()
     } else {
         row![
-            text(zoom_label).size(11).color(ACCENT),
+            text(zoom_label).size(11).style(text::primary),
             Space::new().width(6),
             button("Reset")
                 .on_press(Message::ResetZoom)
@@ -268,7 +1037,14 @@ fn photo_area(app: &App) -> Element<'_, Message> {
         None
     };
     let content: Element<'_, Message> = if let Some(handle) = app.display_image() {
-        widgets::zoomable_image::view(handle, pw, ph, app.zoom_state(), crop_overlay)
+        widgets::zoomable_image::view(
+            handle,
+            pw,
+            ph,
+            app.zoom_state(),
+            crop_overlay,
+            app.annotation_layer(),
+        )
     } else if app.is_loading_photo() {
         empty_viewport(
             "Loading photo",
@@ -319,21 +1095,35 @@ fn right_panel(app: &App) -> Element<'_, Message> {
             .padding([4, 10])
             .style(secondary_action),
         Space::new().width(6),
+        button("Animate")
+            .on_press_maybe(app.can_export().then_some(Message::OpenAnimationExport))
+            .padding([4, 10])
+            .style(secondary_action),
+        Space::new().width(6),
+        button("Compare")
+            .on_press_maybe(app.can_export().then_some(Message::OpenComparisonExport))
+            .padding([4, 10])
+            .style(secondary_action),
+        Space::new().width(6),
         button("Reset")
             .on_press(Message::ResetEdits)
             .padding([4, 10])
             .style(secondary_action),
     ]
     .align_y(Alignment::Center);
+    let mut header = column![tools_header].spacing(10);
+    if let Some(animation) = app.loaded_animation() {
+        header = header.push(key_frame_picker(animation));
+    }
 
     let content = column![
-        tools_header,
+        header,
         section_card(
             "Histogram",
             app.is_panel_open(PanelSection::Histogram),
             Message::TogglePanelSection(PanelSection::Histogram),
             None,
-            widgets::histogram::view(app.histogram()),
+            widgets::histogram::view(app.histogram(), app.scope_palette()),
         ),
         widgets::edit_panel::view(app),
         section_card(
@@ -341,7 +1131,11 @@ fn right_panel(app: &App) -> Element<'_, Message> {
             app.is_panel_open(PanelSection::Metadata),
             Message::TogglePanelSection(PanelSection::Metadata),
             None,
-            widgets::metadata_panel::view(app.current_exif()),
+            widgets::metadata_panel::view(
+                app.current_exif(),
+                app.provenance_sources(),
+                app.provenance_derivatives(),
+            ),
         ),
     ]
     .spacing(10)
@@ -355,6 +1149,35 @@ fn right_panel(app: &App) -> Element<'_, Message> {
         .into()
 }
 
+/// Steps through an animated photo's frames to choose the one it is
+/// shown, edited and exported as.
+fn key_frame_picker<'a>(animation: &Animation) -> Element<'a, Message> {
+    let frame = animation.key_frame;
+    let last = animation.frame_count.saturating_sub(1);
+    container(
+        row![
+            text("Key frame").size(13),
+            Space::new().width(Length::Fill),
+            button("<")
+                .on_press_maybe((frame > 0).then(|| Message::SetKeyFrame(frame - 1)))
+                .padding([2, 8])
+                .style(secondary_action),
+            text(format!("{} of {}", frame + 1, animation.frame_count))
+                .size(12)
+                .color(MUTED),
+            button(">")
+                .on_press_maybe((frame < last).then(|| Message::SetKeyFrame(frame + 1)))
+                .padding([2, 8])
+                .style(secondary_action),
+        ]
+        .spacing(8)
+        .align_y(Alignment::Center),
+    )
+    .padding(10)
+    .style(card_container)
+    .into()
+}
+
 pub fn section_card<'a>(
     title: &'a str,
     is_open: bool,
@@ -424,11 +1247,11 @@ fn app_shell(_theme: &Theme) -> container::Style {
     }
 }
 
-fn panel_container(_theme: &Theme) -> container::Style {
+fn panel_container(theme: &Theme) -> container::Style {
     container::Style {
         background: Some(Background::Color(PANEL_BG)),
         border: Border {
-            color: BORDER,
+            color: theme::border(theme),
             width: 0.0,
             radius: 0.0.into(),
         },
@@ -436,11 +1259,11 @@ fn panel_container(_theme: &Theme) -> container::Style {
     }
 }
 
-fn canvas_panel(_theme: &Theme) -> container::Style {
+fn canvas_panel(theme: &Theme) -> container::Style {
     container::Style {
         background: Some(Background::Color(CANVAS_BG)),
         border: Border {
-            color: BORDER,
+            color: theme::border(theme),
             width: 1.0,
             radius: 0.0.into(),
         },
@@ -448,11 +1271,11 @@ fn canvas_panel(_theme: &Theme) -> container::Style {
     }
 }
 
-fn side_panel(_theme: &Theme) -> container::Style {
+fn side_panel(theme: &Theme) -> container::Style {
     container::Style {
         background: Some(Background::Color(PANEL_ALT_BG)),
         border: Border {
-            color: BORDER,
+            color: theme::border(theme),
             width: 1.0,
             radius: 0.0.into(),
         },
@@ -460,11 +1283,11 @@ fn side_panel(_theme: &Theme) -> container::Style {
     }
 }
 
-fn card_container(_theme: &Theme) -> container::Style {
+fn card_container(theme: &Theme) -> container::Style {
     container::Style {
         background: Some(Background::Color(PANEL_BG)),
         border: Border {
-            color: BORDER,
+            color: theme::border(theme),
             width: 1.0,
             radius: 8.0.into(),
         },
@@ -472,11 +1295,11 @@ fn card_container(_theme: &Theme) -> container::Style {
     }
 }
 
-fn footer_container(_theme: &Theme) -> container::Style {
+fn footer_container(theme: &Theme) -> container::Style {
     container::Style {
         background: Some(Background::Color(PANEL_BG)),
         border: Border {
-            color: BORDER,
+            color: theme::border(theme),
             width: 0.0,
             radius: 0.0.into(),
         },
@@ -484,20 +1307,32 @@ fn footer_container(_theme: &Theme) -> container::Style {
     }
 }
 
-fn primary_action(_theme: &Theme, status: button::Status) -> button::Style {
-    let background = match status {
-        button::Status::Hovered => Color::from_rgb(0.30, 0.56, 0.98),
-        button::Status::Pressed => Color::from_rgb(0.21, 0.44, 0.82),
-        button::Status::Disabled => Color::from_rgb(0.18, 0.22, 0.28),
-        _ => ACCENT,
+fn primary_action(theme: &Theme, status: button::Status) -> button::Style {
+    let accent = theme::accent(theme);
+    let (background, text_color) = match status {
+        button::Status::Hovered => (theme::shade(accent, 1.08), theme::on_accent(theme)),
+        button::Status::Pressed => (theme::shade(accent, 0.85), theme::on_accent(theme)),
+        button::Status::Disabled => (Color::from_rgb(0.18, 0.22, 0.28), Color::WHITE),
+        _ => (accent, theme::on_accent(theme)),
     };
 
+    let hovered = matches!(status, button::Status::Hovered | button::Status::Pressed);
+    let high_contrast = theme::is_high_contrast(theme);
+
     button::Style {
         background: Some(Background::Color(background)),
-        text_color: Color::WHITE,
+        text_color,
         border: Border {
-            color: background,
-            width: 1.0,
+            color: if hovered && high_contrast {
+                Color::WHITE
+            } else {
+                background
+            },
+            width: if hovered {
+                theme::ring_width(theme, 1.0)
+            } else {
+                1.0
+            },
             radius: 6.0.into(),
         },
         shadow: Shadow::default(),
@@ -505,7 +1340,7 @@ fn primary_action(_theme: &Theme, status: button::Status) -> button::Style {
     }
 }
 
-fn secondary_action(_theme: &Theme, status: button::Status) -> button::Style {
+fn secondary_action(theme: &Theme, status: button::Status) -> button::Style {
     let (background, text_color) = match status {
         button::Status::Hovered => (Color::from_rgb(0.18, 0.18, 0.20), Color::WHITE),
         button::Status::Pressed => (Color::from_rgb(0.14, 0.14, 0.16), Color::WHITE),
@@ -513,12 +1348,23 @@ fn secondary_action(_theme: &Theme, status: button::Status) -> button::Style {
         _ => (Color::from_rgb(0.13, 0.13, 0.14), Color::WHITE),
     };
 
+    let hovered = matches!(status, button::Status::Hovered | button::Status::Pressed);
+    let high_contrast = theme::is_high_contrast(theme);
+
     button::Style {
         background: Some(Background::Color(background)),
         text_color,
         border: Border {
-            color: BORDER,
-            width: 1.0,
+            color: if hovered && high_contrast {
+                theme::accent(theme)
+            } else {
+                theme::border(theme)
+            },
+            width: if hovered {
+                theme::ring_width(theme, 1.0)
+            } else {
+                1.0
+            },
             radius: 6.0.into(),
         },
         shadow: Shadow::default(),
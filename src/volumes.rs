@@ -0,0 +1,144 @@
+//! Removable drives photos live on. Photos under a volume mount point are
+//! grouped by it, so a whole drive can be shown as online or offline and
+//! work that needs its originals can wait for it to come back.
+
+use std::collections::HashSet;
+use std::path::{Component, Path, PathBuf};
+use std::time::Duration;
+
+use crema_catalog::models::{Photo, PhotoId};
+
+/// How often mount points are checked while any photo is on one.
+pub const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Directories removable drives are mounted under, with how many path
+/// components below them name a drive: `/media/<user>/<drive>` on most
+/// Linux desktops, `/Volumes/<drive>` on macOS.
+const MOUNT_PARENTS: [(&str, usize); 4] = [
+    ("/Volumes", 1),
+    ("/media", 2),
+    ("/run/media", 2),
+    ("/mnt", 1),
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Volume {
+    pub root: PathBuf,
+    pub name: String,
+    pub online: bool,
+    pub photos: usize,
+    /// Exports held until the volume is mounted again.
+    pub waiting: usize,
+}
+
+/// The mount point of the removable drive `path` is on, or `None` for
+/// paths on the system drive.
+pub fn removable_root(path: &Path) -> Option<PathBuf> {
+    MOUNT_PARENTS.iter().find_map(|&(parent, depth)| {
+        let rest = path.strip_prefix(parent).ok()?;
+        let names: Vec<_> = rest
+            .components()
+            .take(depth)
+            .take_while(|c| matches!(c, Component::Normal(_)))
+            .collect();
+        // The file itself isn't part of the mount point.
+        (names.len() == depth && rest.components().count() > depth).then(|| {
+            names
+                .iter()
+                .fold(PathBuf::from(parent), |root, c| root.join(c))
+        })
+    })
+}
+
+/// Whether a drive is mounted at `root`. Mount points under `/mnt` stay
+/// behind as empty directories when their drive is gone, so an empty
+/// directory counts as unmounted.
+pub fn is_mounted(root: &Path) -> bool {
+    std::fs::read_dir(root).is_ok_and(|mut entries| entries.next().is_some())
+}
+
+/// Whether `path` is on a removable drive that isn't mounted.
+pub fn is_offline(path: &Path) -> bool {
+    removable_root(path).is_some_and(|root| !is_mounted(&root))
+}
+
+/// Every removable volume `photos` are on, by name, with its photo count.
+pub fn scan(photos: &[Photo]) -> Vec<Volume> {
+    let mut volumes: Vec<Volume> = Vec::new();
+    for photo in photos {
+        let Some(root) = removable_root(Path::new(&photo.file_path)) else {
+            continue;
+        };
+        match volumes.iter_mut().find(|v| v.root == root) {
+            Some(volume) => volume.photos += 1,
+            None => volumes.push(Volume {
+                name: root
+                    .file_name()
+                    .map(|name| name.to_string_lossy().to_string())
+                    .unwrap_or_default(),
+                online: is_mounted(&root),
+                root,
+                photos: 1,
+                waiting: 0,
+            }),
+        }
+    }
+    volumes.sort_by(|a, b| a.name.cmp(&b.name).then(a.root.cmp(&b.root)));
+    volumes
+}
+
+/// Photos on the volumes in `volumes` that are offline.
+pub fn offline_photos(photos: &[Photo], volumes: &[Volume]) -> HashSet<PhotoId> {
+    let offline: Vec<&Path> = volumes
+        .iter()
+        .filter(|v| !v.online)
+        .map(|v| v.root.as_path())
+        .collect();
+    if offline.is_empty() {
+        return HashSet::new();
+    }
+    photos
+        .iter()
+        .filter(|p| {
+            offline
+                .iter()
+                .any(|root| Path::new(&p.file_path).starts_with(root))
+        })
+        .map(|p| p.id)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roots_are_found_under_mount_parents() {
+        let root = |path: &str| removable_root(Path::new(path));
+        assert_eq!(
+            root("/Volumes/Shoot/2024/a.cr3"),
+            Some(PathBuf::from("/Volumes/Shoot"))
+        );
+        assert_eq!(
+            root("/media/ana/SD_CARD/DCIM/a.jpg"),
+            Some(PathBuf::from("/media/ana/SD_CARD"))
+        );
+        assert_eq!(
+            root("/run/media/ana/Archive/a.nef"),
+            Some(PathBuf::from("/run/media/ana/Archive"))
+        );
+        assert_eq!(root("/home/ana/Pictures/a.jpg"), None);
+        // A file directly in the mount parent isn't on a drive.
+        assert_eq!(root("/media/ana/a.jpg"), None);
+        assert_eq!(root("/Volumes/Shoot"), None);
+    }
+
+    #[test]
+    fn empty_mount_points_are_unmounted() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(!is_mounted(dir.path()));
+        assert!(!is_mounted(&dir.path().join("gone")));
+        std::fs::write(dir.path().join("a.jpg"), b"").unwrap();
+        assert!(is_mounted(dir.path()));
+    }
+}
@@ -0,0 +1,67 @@
+use iced::widget::{Space, button, column, container, row, text};
+use iced::{Background, Border, Color, Element, Length, Theme};
+
+use crema_core::image_buf::EditParams;
+
+use crate::animation::{self, AnimationKind};
+use crate::app::Message;
+use crate::theme;
+
+const DIALOG_BG: Color = Color::from_rgb(0.12, 0.12, 0.13);
+const MUTED: Color = Color::from_rgb(0.66, 0.66, 0.69);
+
+/// Dialog for choosing what the exported animation shows.
+pub fn view(params: &EditParams) -> Element<'_, Message> {
+    let choice = |kind: AnimationKind| {
+        button(text(kind.label()).size(12))
+            .on_press(Message::ExportAnimation(kind))
+            .padding([6, 12])
+            .width(Length::Fill)
+            .style(button::secondary)
+    };
+
+    let mut choices = column![choice(AnimationKind::BeforeAfter)].spacing(6);
+    let sweepable = animation::sweepable_params(params);
+    if sweepable.is_empty() {
+        choices = choices.push(
+            text("Adjust a slider to sweep it from its default.")
+                .size(11)
+                .color(MUTED),
+        );
+    } else {
+        choices = choices.push(text("Sweep one adjustment").size(12).color(MUTED));
+        for param in sweepable {
+            choices = choices.push(choice(AnimationKind::Sweep(param.key)));
+        }
+    }
+
+    container(
+        column![
+            text("Export Animation").size(18),
+            text("A short looping GIF or MP4 of this edit.")
+                .size(11)
+                .color(MUTED),
+            choices,
+            row![
+                Space::new().width(Length::Fill),
+                button("Cancel")
+                    .on_press(Message::CloseAnimationExport)
+                    .padding([6, 12])
+                    .style(button::secondary),
+            ],
+        ]
+        .spacing(12),
+    )
+    .padding(16)
+    .width(320)
+    .style(|theme: &Theme| container::Style {
+        background: Some(Background::Color(DIALOG_BG)),
+        border: Border {
+            color: theme::border(theme),
+            width: 1.0,
+            radius: 8.0.into(),
+        },
+        ..Default::default()
+    })
+    .into()
+}
@@ -0,0 +1,308 @@
+use std::collections::{BTreeSet, HashMap};
+
+use iced::widget::{Space, button, checkbox, column, container, row, scrollable, text, toggler};
+use iced::{Alignment, Background, Border, Color, Element, Length, Theme};
+
+use crema_catalog::auto_stack::{self, AutoStackRules, ProposedStack};
+use crema_catalog::models::{Photo, PhotoId};
+
+use crate::app::Message;
+use crate::theme;
+
+const DIALOG_BG: Color = Color::from_rgb(0.12, 0.12, 0.13);
+const MUTED: Color = Color::from_rgb(0.66, 0.66, 0.69);
+const MAX_GAP_SECS: u32 = 60;
+const MAX_FRAMES: usize = 9;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StackRule {
+    SameStem,
+    Brackets,
+    Bursts,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StackThreshold {
+    BracketGap,
+    BracketFrames,
+    BurstGap,
+    BurstFrames,
+}
+
+/// Turn a rule on or off.
+pub fn set_rule(rules: &mut AutoStackRules, rule: StackRule, on: bool) {
+    match rule {
+        StackRule::SameStem => rules.same_stem = on,
+        StackRule::Brackets => rules.brackets = on,
+        StackRule::Bursts => rules.bursts = on,
+    }
+}
+
+/// Move a threshold by `delta`, within what the dialog allows.
+pub fn step_threshold(rules: &mut AutoStackRules, threshold: StackThreshold, delta: i32) {
+    let gap = |secs: u32| secs.saturating_add_signed(delta).min(MAX_GAP_SECS);
+    let frames = |count: usize| {
+        count
+            .saturating_add_signed(delta as isize)
+            .clamp(2, MAX_FRAMES)
+    };
+    match threshold {
+        StackThreshold::BracketGap => rules.bracket_gap_secs = gap(rules.bracket_gap_secs),
+        StackThreshold::BracketFrames => {
+            rules.bracket_min_frames = frames(rules.bracket_min_frames)
+        }
+        StackThreshold::BurstGap => rules.burst_gap_secs = gap(rules.burst_gap_secs),
+        StackThreshold::BurstFrames => rules.burst_min_frames = frames(rules.burst_min_frames),
+    }
+}
+
+/// State for the auto-stack dialog: the unstacked photos, the stacks the
+/// rules propose for them, and which of those the user has unchecked.
+#[derive(Debug, Clone)]
+pub struct AutoStackReview {
+    candidates: Vec<Photo>,
+    /// Exposure bias by photo; `None` while the files are still being read.
+    biases: Option<HashMap<PhotoId, f64>>,
+    proposals: Vec<ProposedStack>,
+    skipped: BTreeSet<usize>,
+}
+
+impl AutoStackReview {
+    pub fn new(candidates: Vec<Photo>) -> Self {
+        Self {
+            candidates,
+            biases: None,
+            proposals: Vec::new(),
+            skipped: BTreeSet::new(),
+        }
+    }
+
+    /// The files to read exposure biases from.
+    pub fn candidate_paths(&self) -> Vec<(PhotoId, String)> {
+        self.candidates
+            .iter()
+            .map(|p| (p.id, p.file_path.clone()))
+            .collect()
+    }
+
+    pub fn is_scanning(&self) -> bool {
+        self.biases.is_none()
+    }
+
+    pub fn set_biases(&mut self, biases: HashMap<PhotoId, f64>, rules: &AutoStackRules) {
+        self.biases = Some(biases);
+        self.update(rules);
+    }
+
+    /// Propose again after the rules change. Unchecked stacks come back,
+    /// since the list they were unchecked from is gone.
+    pub fn update(&mut self, rules: &AutoStackRules) {
+        let Some(biases) = &self.biases else {
+            return;
+        };
+        self.proposals = auto_stack::propose(&self.candidates, biases, rules);
+        self.skipped.clear();
+    }
+
+    pub fn toggle(&mut self, index: usize) {
+        if !self.skipped.remove(&index) {
+            self.skipped.insert(index);
+        }
+    }
+
+    /// The proposals still checked.
+    pub fn accepted(&self) -> impl Iterator<Item = &ProposedStack> {
+        self.proposals
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| !self.skipped.contains(i))
+            .map(|(_, stack)| stack)
+    }
+
+    fn file_name(&self, id: PhotoId) -> String {
+        self.candidates
+            .iter()
+            .find(|p| p.id == id)
+            .and_then(|p| std::path::Path::new(&p.file_path).file_name())
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default()
+    }
+}
+
+fn stepper<'a>(label: String, threshold: StackThreshold, enabled: bool) -> Element<'a, Message> {
+    let step = |glyph: &'static str, delta: i32| {
+        button(text(glyph).size(12))
+            .on_press_maybe(enabled.then_some(Message::StepAutoStackThreshold(threshold, delta)))
+            .padding([2, 8])
+            .style(button::secondary)
+    };
+    row![
+        Space::new().width(20),
+        text(label).size(12).color(MUTED),
+        Space::new().width(Length::Fill),
+        step("\u{2212}", -1),
+        step("+", 1),
+    ]
+    .spacing(6)
+    .align_y(Alignment::Center)
+    .into()
+}
+
+fn rule_toggle<'a>(label: &'a str, on: bool, rule: StackRule) -> Element<'a, Message> {
+    toggler(on)
+        .label(label)
+        .text_size(13)
+        .on_toggle(move |on| Message::SetAutoStackRule(rule, on))
+        .into()
+}
+
+pub fn view<'a>(review: &'a AutoStackReview, rules: &AutoStackRules) -> Element<'a, Message> {
+    let plural =
+        |count: usize, unit: &str| format!("{count} {unit}{}", if count == 1 { "" } else { "s" });
+    let rules_column = column![
+        rule_toggle(
+            "Same file name (RAW + JPEG)",
+            rules.same_stem,
+            StackRule::SameStem
+        ),
+        rule_toggle("Exposure brackets", rules.brackets, StackRule::Brackets),
+        stepper(
+            format!(
+                "Frames at most {} apart",
+                plural(rules.bracket_gap_secs as usize, "second")
+            ),
+            StackThreshold::BracketGap,
+            rules.brackets,
+        ),
+        stepper(
+            format!(
+                "At least {} at different exposure bias",
+                plural(rules.bracket_min_frames, "frame")
+            ),
+            StackThreshold::BracketFrames,
+            rules.brackets,
+        ),
+        rule_toggle("Bursts", rules.bursts, StackRule::Bursts),
+        stepper(
+            format!(
+                "Frames at most {} apart",
+                plural(rules.burst_gap_secs as usize, "second")
+            ),
+            StackThreshold::BurstGap,
+            rules.bursts,
+        ),
+        stepper(
+            format!("At least {}", plural(rules.burst_min_frames, "frame")),
+            StackThreshold::BurstFrames,
+            rules.bursts,
+        ),
+    ]
+    .spacing(8);
+
+    let preview: Element<'a, Message> = if review.is_scanning() {
+        text(format!(
+            "Reading exposure data from {}...",
+            plural(review.candidates.len(), "photo")
+        ))
+        .size(12)
+        .color(MUTED)
+        .into()
+    } else if review.proposals.is_empty() {
+        text("No unstacked photos match these rules.")
+            .size(12)
+            .color(MUTED)
+            .into()
+    } else {
+        let mut list = column![].spacing(6);
+        for (index, stack) in review.proposals.iter().enumerate() {
+            let names: Vec<String> = stack
+                .members
+                .iter()
+                .map(|&id| review.file_name(id))
+                .collect();
+            list = list.push(
+                column![
+                    checkbox(!review.skipped.contains(&index))
+                        .label(format!(
+                            "{}: {}",
+                            stack.kind.label(),
+                            plural(stack.members.len(), "photo")
+                        ))
+                        .text_size(12)
+                        .on_toggle(move |_| Message::ToggleProposedStack(index)),
+                    row![
+                        Space::new().width(26),
+                        text(names.join(", ")).size(11).color(MUTED),
+                    ],
+                ]
+                .spacing(2),
+            );
+        }
+        scrollable(list).height(Length::Fixed(240.0)).into()
+    };
+
+    let accepted = review.accepted().count();
+    let buttons = row![
+        Space::new().width(Length::Fill),
+        button("Cancel")
+            .on_press(Message::CloseAutoStack)
+            .padding([6, 12])
+            .style(button::secondary),
+        button(text(format!("Create {}", plural(accepted, "Stack"))))
+            .on_press_maybe((accepted > 0).then_some(Message::CommitAutoStack))
+            .padding([6, 12])
+            .style(button::primary),
+    ]
+    .spacing(8)
+    .align_y(Alignment::Center);
+
+    container(
+        column![
+            text("Auto-Stack Library").size(18),
+            text(
+                "Photos already in a stack are left alone. The first file listed becomes the cover."
+            )
+            .size(11)
+            .color(MUTED),
+            rules_column,
+            text("Proposed stacks").size(13),
+            preview,
+            buttons,
+        ]
+        .spacing(12),
+    )
+    .padding(16)
+    .width(460)
+    .style(|theme: &Theme| container::Style {
+        background: Some(Background::Color(DIALOG_BG)),
+        border: Border {
+            color: theme::border(theme),
+            width: 1.0,
+            radius: 8.0.into(),
+        },
+        ..Default::default()
+    })
+    .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn thresholds_stay_in_range() {
+        let mut rules = AutoStackRules {
+            burst_gap_secs: 0,
+            bracket_min_frames: 2,
+            ..AutoStackRules::default()
+        };
+        step_threshold(&mut rules, StackThreshold::BurstGap, -1);
+        step_threshold(&mut rules, StackThreshold::BracketFrames, -1);
+        assert_eq!(rules.burst_gap_secs, 0);
+        assert_eq!(rules.bracket_min_frames, 2);
+        for _ in 0..20 {
+            step_threshold(&mut rules, StackThreshold::BurstFrames, 1);
+        }
+        assert_eq!(rules.burst_min_frames, MAX_FRAMES);
+    }
+}
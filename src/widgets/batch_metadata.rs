@@ -0,0 +1,305 @@
+use iced::widget::{Space, button, column, container, row, text, text_input};
+use iced::{Alignment, Background, Border, Color, Element, Length, Theme};
+
+use crema_catalog::db::MetadataUpdate;
+use crema_catalog::models::{Photo, PhotoId};
+
+use crate::app::Message;
+use crate::theme;
+
+const DIALOG_BG: Color = Color::from_rgb(0.12, 0.12, 0.13);
+const MUTED: Color = Color::from_rgb(0.66, 0.66, 0.69);
+const ERROR: Color = Color::from_rgb(0.87, 0.43, 0.38);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetadataField {
+    Title,
+    Caption,
+    Copyright,
+    Keywords,
+}
+
+impl MetadataField {
+    pub const ALL: [MetadataField; 4] = [
+        MetadataField::Title,
+        MetadataField::Caption,
+        MetadataField::Copyright,
+        MetadataField::Keywords,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            MetadataField::Title => "Title",
+            MetadataField::Caption => "Caption",
+            MetadataField::Copyright => "Copyright",
+            MetadataField::Keywords => "Keywords",
+        }
+    }
+
+    fn value(self, photo: &Photo) -> &str {
+        let value = match self {
+            MetadataField::Title => &photo.title,
+            MetadataField::Caption => &photo.caption,
+            MetadataField::Copyright => &photo.copyright,
+            MetadataField::Keywords => &photo.keywords,
+        };
+        value.as_deref().unwrap_or("")
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FieldState {
+    pub value: String,
+    /// The selected photos disagree on this field.
+    pub mixed: bool,
+    /// The user has typed into this field; only dirty fields are written.
+    pub dirty: bool,
+}
+
+/// State for the batch metadata dialog.
+#[derive(Debug, Clone)]
+pub struct BatchMetadataForm {
+    pub ids: Vec<PhotoId>,
+    fields: [FieldState; 4],
+    pub date_shift: String,
+}
+
+impl BatchMetadataForm {
+    pub fn from_photos(photos: &[&Photo]) -> Self {
+        let fields = MetadataField::ALL.map(|field| {
+            let first = photos.first().map(|p| field.value(p)).unwrap_or("");
+            let mixed = photos.iter().any(|p| field.value(p) != first);
+            FieldState {
+                value: if mixed {
+                    String::new()
+                } else {
+                    first.to_string()
+                },
+                mixed,
+                dirty: false,
+            }
+        });
+        Self {
+            ids: photos.iter().map(|p| p.id).collect(),
+            fields,
+            date_shift: String::new(),
+        }
+    }
+
+    pub fn field(&self, field: MetadataField) -> &FieldState {
+        &self.fields[field as usize]
+    }
+
+    pub fn set(&mut self, field: MetadataField, value: String) {
+        let state = &mut self.fields[field as usize];
+        state.value = value;
+        state.dirty = true;
+    }
+
+    /// Build the catalog update from the fields the user touched.
+    pub fn to_update(&self) -> Result<MetadataUpdate, String> {
+        let dirty = |field: MetadataField| {
+            let state = self.field(field);
+            state.dirty.then(|| state.value.clone())
+        };
+        let shift = parse_time_shift(&self.date_shift)
+            .ok_or_else(|| format!("Can't read time shift \"{}\"", self.date_shift.trim()))?;
+        Ok(MetadataUpdate {
+            title: dirty(MetadataField::Title),
+            caption: dirty(MetadataField::Caption),
+            copyright: dirty(MetadataField::Copyright),
+            keywords: dirty(MetadataField::Keywords),
+            date_shift_seconds: (shift != 0).then_some(shift),
+        })
+    }
+}
+
+/// Parse a capture-time shift like `+1`, `-0:30` or `+25:00` into seconds.
+/// An empty string means no shift.
+pub fn parse_time_shift(input: &str) -> Option<i64> {
+    let input = input.trim();
+    if input.is_empty() {
+        return Some(0);
+    }
+    let (sign, rest) = match input.as_bytes()[0] {
+        b'-' => (-1, &input[1..]),
+        b'+' => (1, &input[1..]),
+        _ => (1, input),
+    };
+    let (hours, minutes) = match rest.split_once(':') {
+        Some((h, m)) => (h.trim().parse::<i64>().ok()?, m.trim().parse::<i64>().ok()?),
+        None => (rest.trim().parse::<i64>().ok()?, 0),
+    };
+    if hours < 0 || !(0..60).contains(&minutes) {
+        return None;
+    }
+    Some(sign * (hours * 3600 + minutes * 60))
+}
+
+pub fn view(form: &BatchMetadataForm) -> Element<'_, Message> {
+    let mut fields = column![].spacing(10);
+    for field in MetadataField::ALL {
+        let state = form.field(field);
+        let mut label = row![text(field.label()).size(12)].spacing(6);
+        if state.mixed && !state.dirty {
+            label = label.push(text("Mixed").size(11).color(MUTED));
+        } else if state.dirty {
+            label = label.push(text("Changed").size(11).style(text::primary));
+        }
+        let placeholder = if state.mixed {
+            "Multiple values; type to replace all"
+        } else if field == MetadataField::Keywords {
+            "Comma separated"
+        } else {
+            ""
+        };
+        fields = fields.push(
+            column![
+                label,
+                text_input(placeholder, &state.value)
+                    .on_input(move |value| Message::BatchMetadataChanged(field, value))
+                    .size(13)
+                    .padding(6),
+            ]
+            .spacing(4),
+        );
+    }
+
+    let update = form.to_update();
+    let shift_hint: Element<'_, Message> = match &update {
+        Err(err) => text(err.clone()).size(11).color(ERROR).into(),
+        Ok(_) => text("Hours, or hours:minutes. Photos without a date are skipped.")
+            .size(11)
+            .color(MUTED)
+            .into(),
+    };
+    fields = fields.push(
+        column![
+            text("Shift capture time").size(12),
+            text_input("+1:30", &form.date_shift)
+                .on_input(Message::BatchMetadataShiftChanged)
+                .size(13)
+                .padding(6),
+            shift_hint,
+        ]
+        .spacing(4),
+    );
+
+    let can_apply = update.is_ok_and(|u| !u.is_empty());
+    let count = form.ids.len();
+    let buttons = row![
+        Space::new().width(Length::Fill),
+        button("Cancel")
+            .on_press(Message::CloseBatchMetadata)
+            .padding([6, 12])
+            .style(button::secondary),
+        button(text(format!(
+            "Apply to {count} Photo{}",
+            if count == 1 { "" } else { "s" }
+        )))
+        .on_press_maybe(can_apply.then_some(Message::ApplyBatchMetadata))
+        .padding([6, 12])
+        .style(button::primary),
+    ]
+    .spacing(8)
+    .align_y(Alignment::Center);
+
+    container(
+        column![
+            text("Edit Metadata").size(18),
+            text("Only fields you change are written.")
+                .size(11)
+                .color(MUTED),
+            fields,
+            buttons,
+        ]
+        .spacing(12),
+    )
+    .padding(16)
+    .width(420)
+    .style(|theme: &Theme| container::Style {
+        background: Some(Background::Color(DIALOG_BG)),
+        border: Border {
+            color: theme::border(theme),
+            width: 1.0,
+            radius: 8.0.into(),
+        },
+        ..Default::default()
+    })
+    .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn photo(id: PhotoId, title: Option<&str>, copyright: Option<&str>) -> Photo {
+        Photo {
+            id,
+            file_path: format!("/photos/{id}.jpg"),
+            title: title.map(String::from),
+            copyright: copyright.map(String::from),
+            ..Photo::default()
+        }
+    }
+
+    #[test]
+    fn detects_mixed_values() {
+        let a = photo(1, Some("Beach"), Some("(c) Me"));
+        let b = photo(2, Some("Forest"), Some("(c) Me"));
+        let form = BatchMetadataForm::from_photos(&[&a, &b]);
+
+        let title = form.field(MetadataField::Title);
+        assert!(title.mixed);
+        assert!(title.value.is_empty());
+
+        let copyright = form.field(MetadataField::Copyright);
+        assert!(!copyright.mixed);
+        assert_eq!(copyright.value, "(c) Me");
+
+        assert!(!form.field(MetadataField::Caption).mixed);
+        assert_eq!(form.ids, vec![1, 2]);
+    }
+
+    #[test]
+    fn untouched_form_is_empty_update() {
+        let a = photo(1, Some("Beach"), None);
+        let form = BatchMetadataForm::from_photos(&[&a]);
+        assert!(form.to_update().unwrap().is_empty());
+    }
+
+    #[test]
+    fn only_dirty_fields_are_written() {
+        let a = photo(1, Some("Beach"), None);
+        let b = photo(2, Some("Forest"), None);
+        let mut form = BatchMetadataForm::from_photos(&[&a, &b]);
+        form.set(MetadataField::Copyright, "(c) Me".into());
+
+        let update = form.to_update().unwrap();
+        assert_eq!(update.copyright.as_deref(), Some("(c) Me"));
+        assert!(update.title.is_none());
+        assert!(update.keywords.is_none());
+    }
+
+    #[test]
+    fn invalid_shift_is_an_error() {
+        let a = photo(1, None, None);
+        let mut form = BatchMetadataForm::from_photos(&[&a]);
+        form.date_shift = "soon".into();
+        assert!(form.to_update().is_err());
+        form.date_shift = "-2".into();
+        assert_eq!(form.to_update().unwrap().date_shift_seconds, Some(-7200));
+    }
+
+    #[test]
+    fn parse_shift_formats() {
+        assert_eq!(parse_time_shift(""), Some(0));
+        assert_eq!(parse_time_shift("1"), Some(3600));
+        assert_eq!(parse_time_shift("+1:30"), Some(5400));
+        assert_eq!(parse_time_shift("-0:45"), Some(-2700));
+        assert_eq!(parse_time_shift(" +25:00 "), Some(90_000));
+        assert_eq!(parse_time_shift("1:60"), None);
+        assert_eq!(parse_time_shift("--1"), None);
+        assert_eq!(parse_time_shift("abc"), None);
+    }
+}
@@ -0,0 +1,153 @@
+use iced::widget::{Space, button, column, row, slider, text, toggler};
+use iced::{Color, Element, Length};
+
+use crema_core::color_range::ColorRange;
+
+use crate::app::{App, Message};
+
+const MUTED: Color = Color::from_rgb(0.66, 0.66, 0.69);
+
+/// One adjustable bound of a [`ColorRange`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RangeBound {
+    Hue,
+    HueWidth,
+    SatMin,
+    SatMax,
+    LumMin,
+    LumMax,
+}
+
+impl RangeBound {
+    /// Set this bound, keeping each min no greater than its max.
+    pub fn apply(self, range: &mut ColorRange, value: f32) {
+        match self {
+            RangeBound::Hue => range.hue = value,
+            RangeBound::HueWidth => range.hue_width = value,
+            RangeBound::SatMin => {
+                range.sat_min = value;
+                range.sat_max = range.sat_max.max(value);
+            }
+            RangeBound::SatMax => {
+                range.sat_max = value;
+                range.sat_min = range.sat_min.min(value);
+            }
+            RangeBound::LumMin => {
+                range.lum_min = value;
+                range.lum_max = range.lum_max.max(value);
+            }
+            RangeBound::LumMax => {
+                range.lum_max = value;
+                range.lum_min = range.lum_min.min(value);
+            }
+        }
+    }
+}
+
+/// The Color Range analysis section: pick a hue, saturation and lightness
+/// range, see how much of the photo falls in it, and measure the same
+/// range across the selection.
+pub fn view(app: &App) -> Element<'_, Message> {
+    let range = app.color_range();
+    let coverage = match app.color_range_coverage() {
+        Some(share) => format!("{:.1}% of the frame", share * 100.0),
+        None => "Measuring...".into(),
+    };
+
+    let mut content = column![
+        row![
+            text(coverage).size(13),
+            Space::new().width(Length::Fill),
+            toggler(app.color_range_mask_shown())
+                .label("Mask")
+                .text_size(12)
+                .on_toggle(Message::ColorRangeMaskToggled),
+        ]
+        .align_y(iced::Alignment::Center),
+        bound(
+            "Hue",
+            format!("{:.0}\u{b0}", range.hue),
+            0.0..=360.0,
+            range.hue,
+            RangeBound::Hue,
+        ),
+        bound(
+            "Hue width",
+            format!("\u{b1}{:.0}\u{b0}", range.hue_width),
+            0.0..=180.0,
+            range.hue_width,
+            RangeBound::HueWidth,
+        ),
+        percent_bound("Saturation min", range.sat_min, RangeBound::SatMin),
+        percent_bound("Saturation max", range.sat_max, RangeBound::SatMax),
+        percent_bound("Lightness min", range.lum_min, RangeBound::LumMin),
+        percent_bound("Lightness max", range.lum_max, RangeBound::LumMax),
+        button("Measure Selection")
+            .on_press(Message::MeasureColorRangeSelection)
+            .padding([6, 14])
+            .style(button::secondary),
+    ]
+    .spacing(8);
+
+    for (name, result) in app.color_range_set() {
+        let reading = match result {
+            Ok(share) => text(format!("{:.1}%", share * 100.0)).size(12),
+            Err(_) => text("Failed").size(12).color(MUTED),
+        };
+        content = content.push(
+            row![
+                text(name).size(12).color(MUTED),
+                Space::new().width(Length::Fill),
+                reading,
+            ]
+            .align_y(iced::Alignment::Center),
+        );
+    }
+
+    content.into()
+}
+
+fn percent_bound<'a>(label: &'static str, value: f32, which: RangeBound) -> Element<'a, Message> {
+    bound(
+        label,
+        format!("{:.0}%", value * 100.0),
+        0.0..=1.0,
+        value,
+        which,
+    )
+}
+
+fn bound<'a>(
+    label: &'static str,
+    value_text: String,
+    range: std::ops::RangeInclusive<f32>,
+    value: f32,
+    which: RangeBound,
+) -> Element<'a, Message> {
+    let step = if *range.end() > 1.0 { 1.0 } else { 0.01 };
+    column![
+        row![
+            text(label).size(12).color(MUTED),
+            Space::new().width(Length::Fill),
+            text(value_text).size(12).color(MUTED),
+        ],
+        slider(range, value, move |v| Message::ColorRangeChanged(which, v)).step(step),
+    ]
+    .spacing(5)
+    .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bounds_keep_min_below_max() {
+        let mut range = ColorRange::default();
+        RangeBound::SatMin.apply(&mut range, 0.5);
+        RangeBound::SatMax.apply(&mut range, 0.3);
+        assert_eq!((range.sat_min, range.sat_max), (0.3, 0.3));
+        RangeBound::LumMax.apply(&mut range, 0.05);
+        assert_eq!((range.lum_min, range.lum_max), (0.05, 0.05));
+    }
+}
@@ -0,0 +1,83 @@
+use iced::widget::{Space, button, column, container, row, text, toggler};
+use iced::{Alignment, Background, Border, Color, Element, Length, Theme};
+
+use crema_core::comparison::ComparisonLayout;
+
+use crate::app::Message;
+use crate::theme;
+
+const DIALOG_BG: Color = Color::from_rgb(0.12, 0.12, 0.13);
+const MUTED: Color = Color::from_rgb(0.66, 0.66, 0.69);
+
+/// Options for a before/after comparison export.
+#[derive(Debug, Clone)]
+pub struct ComparisonExport {
+    pub layout: ComparisonLayout,
+    pub labels: bool,
+}
+
+impl Default for ComparisonExport {
+    fn default() -> Self {
+        Self {
+            layout: ComparisonLayout::SideBySide,
+            labels: true,
+        }
+    }
+}
+
+/// Dialog for exporting the unedited and edited photo as one JPEG.
+pub fn view(options: &ComparisonExport) -> Element<'_, Message> {
+    let mut layouts = row![].spacing(6);
+    for layout in ComparisonLayout::ALL {
+        layouts = layouts.push(
+            button(text(layout.label()).size(12))
+                .on_press(Message::ComparisonLayoutChanged(layout))
+                .padding([6, 12])
+                .style(if options.layout == layout {
+                    button::primary
+                } else {
+                    button::secondary
+                }),
+        );
+    }
+
+    container(
+        column![
+            text("Export Before / After").size(18),
+            text("One JPEG with the photo before and after this edit, framed the same way.")
+                .size(11)
+                .color(MUTED),
+            layouts,
+            toggler(options.labels)
+                .label("Label each half")
+                .text_size(13)
+                .on_toggle(Message::ComparisonLabelsToggled),
+            row![
+                Space::new().width(Length::Fill),
+                button("Cancel")
+                    .on_press(Message::CloseComparisonExport)
+                    .padding([6, 12])
+                    .style(button::secondary),
+                button("Export...")
+                    .on_press(Message::ExportComparison)
+                    .padding([6, 12])
+                    .style(button::primary),
+            ]
+            .spacing(8)
+            .align_y(Alignment::Center),
+        ]
+        .spacing(12),
+    )
+    .padding(16)
+    .width(360)
+    .style(|theme: &Theme| container::Style {
+        background: Some(Background::Color(DIALOG_BG)),
+        border: Border {
+            color: theme::border(theme),
+            width: 1.0,
+            radius: 8.0.into(),
+        },
+        ..Default::default()
+    })
+    .into()
+}
@@ -6,6 +6,8 @@ use iced::{Background, Border, Color, Element, Length, Padding, Theme};
 use crema_catalog::models::Photo;
 
 use crate::app::Message;
+use crate::theme;
+use crate::volumes::Volume;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum DateFilter {
@@ -23,6 +25,16 @@ pub enum RatingFilter {
     AtLeast(i32),
 }
 
+/// Facet narrowing the library to one camera body or lens. Photos are
+/// matched on their overridden values when they have an override.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum GearFilter {
+    #[default]
+    All,
+    Camera(String),
+    Lens(String),
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum SortOrder {
     #[default]
@@ -71,7 +83,7 @@ impl SortOrder {
                 photos.sort_by(|a, b| b.rating.cmp(&a.rating).then(b.id.cmp(&a.id)));
             }
             SortOrder::FileSize => {
-                photos.sort_by(|a, b| b.file_size.cmp(&a.file_size));
+                photos.sort_by_key(|p| std::cmp::Reverse(p.file_size));
             }
         }
     }
@@ -86,6 +98,16 @@ impl RatingFilter {
     }
 }
 
+impl GearFilter {
+    pub fn matches(&self, photo: &Photo) -> bool {
+        match self {
+            GearFilter::All => true,
+            GearFilter::Camera(model) => photo.camera_model.as_ref() == Some(model),
+            GearFilter::Lens(lens) => photo.lens.as_ref() == Some(lens),
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum DateExpansionKey {
     Year(u16),
@@ -127,84 +149,145 @@ pub fn parse_date(s: Option<&str>) -> Option<(u16, u8, u8)> {
     Some((year, month, day))
 }
 
-struct DateTree {
+/// Photo counts behind the date tree. Years are filled in up front from
+/// an aggregate query; a year's months and days only when it's first
+/// expanded, so drawing the tree costs the same however many photos the
+/// library holds.
+#[derive(Debug, Clone, Default)]
+pub struct DateIndex {
     total: usize,
     years: Vec<YearEntry>,
     unknown_count: usize,
 }
 
+#[derive(Debug, Clone)]
 struct YearEntry {
     year: u16,
     count: usize,
-    months: Vec<MonthEntry>,
+    /// `None` until the year is expanded.
+    months: Option<Vec<MonthEntry>>,
 }
 
+#[derive(Debug, Clone)]
 struct MonthEntry {
     month: u8,
     count: usize,
     days: Vec<DayEntry>,
 }
 
+#[derive(Debug, Clone)]
 struct DayEntry {
     day: u8,
     count: usize,
 }
 
-fn build_date_tree(photos: &[Photo]) -> DateTree {
-    let mut map: BTreeMap<u16, BTreeMap<u8, BTreeMap<u8, usize>>> = BTreeMap::new();
-    let mut unknown_count = 0;
-
-    for photo in photos {
-        match parse_date(photo.date_taken.as_deref()) {
-            Some((y, m, d)) => {
-                *map.entry(y)
-                    .or_default()
-                    .entry(m)
-                    .or_default()
-                    .entry(d)
-                    .or_default() += 1;
-            }
-            None => unknown_count += 1,
+impl DateIndex {
+    /// `years` holds the count per year, newest first; photos of `total`
+    /// not in any year have no usable date.
+    pub fn new(total: usize, years: Vec<(u16, usize)>) -> Self {
+        let dated: usize = years.iter().map(|&(_, count)| count).sum();
+        Self {
+            total,
+            unknown_count: total.saturating_sub(dated),
+            years: years
+                .into_iter()
+                .map(|(year, count)| YearEntry {
+                    year,
+                    count,
+                    months: None,
+                })
+                .collect(),
         }
     }
 
-    let mut years: Vec<YearEntry> = map
-        .into_iter()
-        .map(|(year, months_map)| {
-            let mut year_count = 0;
-            let months: Vec<MonthEntry> = months_map
-                .into_iter()
-                .map(|(month, days_map)| {
-                    let mut month_count = 0;
-                    let days: Vec<DayEntry> = days_map
-                        .into_iter()
-                        .map(|(day, count)| {
-                            month_count += count;
-                            DayEntry { day, count }
-                        })
-                        .collect();
-                    year_count += month_count;
-                    MonthEntry {
-                        month,
-                        count: month_count,
-                        days,
-                    }
-                })
-                .collect();
-            YearEntry {
-                year,
-                count: year_count,
-                months,
+    /// Year counts of photos already in memory, for libraries that aren't
+    /// read from the catalog, such as the time machine's.
+    pub fn from_photos(photos: &[Photo]) -> Self {
+        let mut years: BTreeMap<u16, usize> = BTreeMap::new();
+        for (year, _, _) in photos
+            .iter()
+            .filter_map(|p| parse_date(p.date_taken.as_deref()))
+        {
+            *years.entry(year).or_default() += 1;
+        }
+        Self::new(photos.len(), years.into_iter().rev().collect())
+    }
+
+    /// Whether `year` is in the tree but its months haven't been loaded.
+    pub fn needs_days(&self, year: u16) -> bool {
+        self.years
+            .iter()
+            .any(|entry| entry.year == year && entry.months.is_none())
+    }
+
+    /// Fill in `year` from `(month, day, count)` in calendar order.
+    pub fn set_days(&mut self, year: u16, days: Vec<(u8, u8, usize)>) {
+        let Some(entry) = self.years.iter_mut().find(|entry| entry.year == year) else {
+            return;
+        };
+        let mut months: Vec<MonthEntry> = Vec::new();
+        for (month, day, count) in days {
+            match months.last_mut() {
+                Some(last) if last.month == month => {
+                    last.count += count;
+                    last.days.push(DayEntry { day, count });
+                }
+                _ => months.push(MonthEntry {
+                    month,
+                    count,
+                    days: vec![DayEntry { day, count }],
+                }),
             }
-        })
-        .collect();
+        }
+        entry.months = Some(months);
+    }
+}
 
-    years.reverse();
+/// `(month, day, count)` for photos taken in `year`, in calendar order.
+pub fn day_counts(photos: &[Photo], year: u16) -> Vec<(u8, u8, usize)> {
+    let mut days: BTreeMap<(u8, u8), usize> = BTreeMap::new();
+    for (_, month, day) in photos
+        .iter()
+        .filter_map(|p| parse_date(p.date_taken.as_deref()))
+        .filter(|&(y, _, _)| y == year)
+    {
+        *days.entry((month, day)).or_default() += 1;
+    }
+    days.into_iter()
+        .map(|((month, day), count)| (month, day, count))
+        .collect()
+}
+
+/// Photos per camera body and lens, counted when the library is listed
+/// rather than on every frame.
+#[derive(Debug, Clone, Default)]
+pub struct GearCounts {
+    cameras: Vec<(String, usize)>,
+    lenses: Vec<(String, usize)>,
+}
 
-    DateTree {
-        total: photos.len(),
-        years,
-        unknown_count,
+impl GearCounts {
+    pub fn from_photos(photos: &[Photo]) -> Self {
+        let mut cameras: BTreeMap<&str, usize> = BTreeMap::new();
+        let mut lenses: BTreeMap<&str, usize> = BTreeMap::new();
+        for photo in photos {
+            if let Some(model) = &photo.camera_model {
+                *cameras.entry(model).or_default() += 1;
+            }
+            if let Some(lens) = &photo.lens {
+                *lenses.entry(lens).or_default() += 1;
+            }
+        }
+        let owned = |counts: BTreeMap<&str, usize>| {
+            counts
+                .into_iter()
+                .map(|(name, count)| (name.to_string(), count))
+                .collect()
+        };
+        Self {
+            cameras: owned(cameras),
+            lenses: owned(lenses),
+        }
     }
 }
 
@@ -220,18 +303,20 @@ fn month_name(m: u8) -> &'static str {
 
 const SIDEBAR_WIDTH: f32 = 220.0;
 const BG: Color = Color::from_rgb(0.10, 0.10, 0.11);
-const BORDER: Color = Color::from_rgb(0.20, 0.20, 0.22);
 const MUTED: Color = Color::from_rgb(0.66, 0.66, 0.69);
-const ACCENT: Color = Color::from_rgb(0.26, 0.52, 0.94);
+const ONLINE: Color = Color::from_rgb(0.42, 0.78, 0.50);
 
+#[allow(clippy::too_many_arguments)]
 pub fn view<'a>(
-    photos: &[Photo],
+    tree: &DateIndex,
+    volumes: &[Volume],
+    gear: &GearCounts,
     active_filter: &DateFilter,
     expanded: &HashSet<DateExpansionKey>,
     rating_filter: RatingFilter,
+    gear_filter: &GearFilter,
     sort_order: SortOrder,
 ) -> Element<'a, Message> {
-    let tree = build_date_tree(photos);
     let mut items: Vec<Element<'a, Message>> = vec![
         text("Browse By Date").size(13).color(MUTED).into(),
         text(format!("{} photos", tree.total))
@@ -267,7 +352,7 @@ pub fn view<'a>(
         );
 
         if is_expanded {
-            for month_entry in &year_entry.months {
+            for month_entry in year_entry.months.iter().flatten() {
                 let month_key = DateExpansionKey::Month(year_entry.year, month_entry.month);
                 let is_month_expanded = expanded.contains(&month_key);
                 let arrow = if is_month_expanded { "v" } else { ">" };
@@ -310,19 +395,32 @@ pub fn view<'a>(
         ));
     }
 
+    if !volumes.is_empty() {
+        items.push(Space::new().height(8).into());
+        items.push(text("Volumes").size(13).color(MUTED).into());
+        items.extend(volumes.iter().map(volume_row));
+    }
+
     items.push(Space::new().height(8).into());
     items.push(text("Filter By Rating").size(13).color(MUTED).into());
     items.push(rating_filter_row(rating_filter));
 
+    let gear = gear_filter_rows(gear, gear_filter);
+    if !gear.is_empty() {
+        items.push(Space::new().height(8).into());
+        items.push(text("Filter By Gear").size(13).color(MUTED).into());
+        items.extend(gear);
+    }
+
     items.push(Space::new().height(8).into());
     items.push(text("Sort By").size(13).color(MUTED).into());
     items.push(sort_order_row(sort_order));
 
     container(scrollable(column(items).spacing(4).padding(10)).height(Length::Fill))
-        .style(|_theme: &Theme| container::Style {
+        .style(|theme: &Theme| container::Style {
             background: Some(Background::Color(BG)),
             border: Border {
-                color: BORDER,
+                color: theme::border(theme),
                 width: 1.0,
                 radius: 0.0.into(),
             },
@@ -333,6 +431,29 @@ pub fn view<'a>(
         .into()
 }
 
+fn volume_row<'a>(volume: &Volume) -> Element<'a, Message> {
+    let (dot, status) = if volume.online {
+        (text("\u{25CF}").size(11).color(ONLINE), "Online")
+    } else {
+        (text("\u{25CB}").size(11).color(MUTED), "Offline")
+    };
+    let mut details = format!("{status} \u{00B7} {} photos", volume.photos);
+    if volume.waiting > 0 {
+        details.push_str(&format!(" \u{00B7} {} waiting to export", volume.waiting));
+    }
+    row![
+        dot,
+        column![
+            text(volume.name.clone()).size(12),
+            text(details).size(10).color(MUTED),
+        ]
+        .spacing(1),
+    ]
+    .spacing(6)
+    .padding(Padding::from([2, 8]))
+    .into()
+}
+
 fn rating_filter_row(active: RatingFilter) -> Element<'static, Message> {
     let options: &[(RatingFilter, &str)] = &[
         (RatingFilter::All, "All"),
@@ -346,23 +467,71 @@ fn rating_filter_row(active: RatingFilter) -> Element<'static, Message> {
     let mut items = column![].spacing(2);
     for &(filter, label) in options {
         let is_active = filter == active;
-        let label_color = if is_active { ACCENT } else { MUTED };
         items = items.push(
-            button(text(label).size(11).color(label_color))
-                .on_press(Message::SetRatingFilter(filter))
-                .padding(Padding::from([3, 6]))
-                .width(Length::Fill)
-                .style(if is_active {
-                    button::primary
-                } else {
-                    button::text
-                }),
+            button(
+                text(label)
+                    .size(11)
+                    .style(move |_theme: &Theme| text::Style {
+                        // Active rows sit on an accent background, so let
+                        // the button pick a readable text color.
+                        color: (!is_active).then_some(MUTED),
+                    }),
+            )
+            .on_press(Message::SetRatingFilter(filter))
+            .padding(Padding::from([3, 6]))
+            .width(Length::Fill)
+            .style(if is_active {
+                button::primary
+            } else {
+                button::text
+            }),
         );
     }
 
     items.into()
 }
 
+/// One row per camera body and lens in the library, with counts. Empty
+/// when no photo has either.
+fn gear_filter_rows<'a>(gear: &GearCounts, active: &GearFilter) -> Vec<Element<'a, Message>> {
+    if gear.cameras.is_empty() && gear.lenses.is_empty() {
+        return Vec::new();
+    }
+
+    let options =
+        std::iter::once((GearFilter::All, "All".to_string()))
+            .chain(gear.cameras.iter().map(|(model, count)| {
+                (
+                    GearFilter::Camera(model.clone()),
+                    format!("{model} ({count})"),
+                )
+            }))
+            .chain(gear.lenses.iter().map(|(lens, count)| {
+                (GearFilter::Lens(lens.clone()), format!("{lens} ({count})"))
+            }));
+    options
+        .map(|(filter, label)| {
+            let is_active = &filter == active;
+            button(
+                text(label)
+                    .size(11)
+                    .style(move |_theme: &Theme| text::Style {
+                        color: (!is_active).then_some(MUTED),
+                    }),
+            )
+            .on_press(Message::SetGearFilter(filter))
+            .padding(Padding::from([3, 6]))
+            .width(Length::Fill)
+            .style(if is_active {
+                button::primary
+            } else {
+                button::text
+            })
+            .into()
+        })
+        .collect()
+}
+
 fn sort_order_row(active: SortOrder) -> Element<'static, Message> {
     let options = [
         SortOrder::DateDesc,
@@ -375,16 +544,21 @@ fn sort_order_row(active: SortOrder) -> Element<'static, Message> {
     let mut items = row![].spacing(2);
     for order in options {
         let is_active = order == active;
-        let label_color = if is_active { ACCENT } else { MUTED };
         items = items.push(
-            button(text(order.label()).size(10).color(label_color))
-                .on_press(Message::SetSortOrder(order))
-                .padding(Padding::from([3, 4]))
-                .style(if is_active {
-                    button::primary
-                } else {
-                    button::text
-                }),
+            button(
+                text(order.label())
+                    .size(10)
+                    .style(move |_theme: &Theme| text::Style {
+                        color: (!is_active).then_some(MUTED),
+                    }),
+            )
+            .on_press(Message::SetSortOrder(order))
+            .padding(Padding::from([3, 4]))
+            .style(if is_active {
+                button::primary
+            } else {
+                button::text
+            }),
         );
     }
 
@@ -438,17 +612,9 @@ mod tests {
             file_size: 1000,
             width: Some(100),
             height: Some(100),
-            camera_make: None,
-            camera_model: None,
-            lens: None,
-            focal_length: None,
-            aperture: None,
-            shutter_speed: None,
-            iso: None,
             date_taken: date.map(String::from),
             imported_at: "2026-01-01".into(),
-            thumbnail_path: None,
-            rating: 0,
+            ..Photo::default()
         }
     }
 
@@ -482,7 +648,7 @@ mod tests {
 
     #[test]
     fn build_tree_grouping() {
-        let photos = vec![
+        let photos = [
             make_photo(1, Some("2026-02-05 10:00:00")),
             make_photo(2, Some("2026-02-05 11:00:00")),
             make_photo(3, Some("2026-02-03 09:00:00")),
@@ -491,7 +657,11 @@ mod tests {
             make_photo(6, None),
         ];
 
-        let tree = build_date_tree(&photos);
+        let mut tree = DateIndex::from_photos(&photos);
+        assert!(tree.needs_days(2026));
+        tree.set_days(2026, day_counts(&photos, 2026));
+        tree.set_days(2025, day_counts(&photos, 2025));
+        assert!(!tree.needs_days(2026));
         assert_eq!(tree.total, 6);
         assert_eq!(tree.unknown_count, 1);
         assert_eq!(tree.years.len(), 2);
@@ -503,7 +673,7 @@ mod tests {
         assert_eq!(tree.years[1].count, 1);
 
         // 2026 months
-        let months = &tree.years[0].months;
+        let months = tree.years[0].months.as_ref().unwrap();
         assert_eq!(months.len(), 2);
         assert_eq!(months[0].month, 1);
         assert_eq!(months[0].count, 1);
@@ -571,7 +741,7 @@ mod tests {
 
     #[test]
     fn sort_date_desc_is_default() {
-        let photos = vec![
+        let photos = [
             make_photo(1, Some("2025-01-01 10:00:00")),
             make_photo(2, Some("2026-06-15 12:00:00")),
             make_photo(3, Some("2026-01-01 08:00:00")),
@@ -585,7 +755,7 @@ mod tests {
 
     #[test]
     fn sort_date_asc() {
-        let photos = vec![
+        let photos = [
             make_photo(1, Some("2026-06-15 12:00:00")),
             make_photo(2, Some("2025-01-01 10:00:00")),
             make_photo(3, Some("2026-01-01 08:00:00")),
@@ -605,7 +775,7 @@ mod tests {
         p2.file_path = "/photos/apple.jpg".into();
         let mut p3 = make_photo(3, None);
         p3.file_path = "/photos/cherry.jpg".into();
-        let photos = vec![p1, p2, p3];
+        let photos = [p1, p2, p3];
         let mut refs: Vec<&Photo> = photos.iter().collect();
         SortOrder::Name.sort(&mut refs);
         assert_eq!(refs[0].id, 2); // apple
@@ -621,7 +791,7 @@ mod tests {
         p2.rating = 5;
         let mut p3 = make_photo(3, None);
         p3.rating = 1;
-        let photos = vec![p1, p2, p3];
+        let photos = [p1, p2, p3];
         let mut refs: Vec<&Photo> = photos.iter().collect();
         SortOrder::RatingDesc.sort(&mut refs);
         assert_eq!(refs[0].id, 2); // 5 stars
@@ -637,7 +807,7 @@ mod tests {
         p2.file_size = 2000;
         let mut p3 = make_photo(3, None);
         p3.file_size = 100;
-        let photos = vec![p1, p2, p3];
+        let photos = [p1, p2, p3];
         let mut refs: Vec<&Photo> = photos.iter().collect();
         SortOrder::FileSize.sort(&mut refs);
         assert_eq!(refs[0].id, 2); // 2000
@@ -647,7 +817,7 @@ mod tests {
 
     #[test]
     fn sort_date_asc_nulls_first() {
-        let photos = vec![
+        let photos = [
             make_photo(1, Some("2026-01-01 10:00:00")),
             make_photo(2, None),
             make_photo(3, Some("2025-06-01 12:00:00")),
@@ -0,0 +1,123 @@
+use iced::widget::{Space, button, column, container, row, scrollable, text};
+use iced::{Alignment, Background, Border, Color, Element, Length, Theme};
+
+use crema_core::edit_diff::{EditParam, ParamDiff};
+
+use crate::app::{DiffSide, Message};
+use crate::theme;
+
+const DIALOG_BG: Color = Color::from_rgb(0.12, 0.12, 0.13);
+const MUTED: Color = Color::from_rgb(0.66, 0.66, 0.69);
+
+/// Dialog listing every parameter that differs between two edit states,
+/// with a button on each side to keep that side's value.
+pub fn view<'a>(
+    left_label: &'a str,
+    right_label: &'a str,
+    diffs: Vec<ParamDiff>,
+) -> Element<'a, Message> {
+    let header = row![
+        text("Parameter").size(11).color(MUTED).width(Length::Fill),
+        text(left_label).size(11).color(MUTED).width(150),
+        text(right_label).size(11).color(MUTED).width(150),
+    ]
+    .spacing(8);
+
+    let body: Element<'a, Message> = if diffs.is_empty() {
+        text("These edits are identical.")
+            .size(12)
+            .color(MUTED)
+            .into()
+    } else {
+        let mut rows = column![].spacing(6);
+        for d in diffs {
+            rows = rows.push(
+                row![
+                    text(d.param.label).size(12).width(Length::Fill),
+                    side(d.param, d.left, DiffSide::Left),
+                    side(d.param, d.right, DiffSide::Right),
+                ]
+                .spacing(8)
+                .align_y(Alignment::Center),
+            );
+        }
+        scrollable(rows).height(Length::Shrink).into()
+    };
+
+    container(
+        column![
+            text("Compare Edits").size(18),
+            text("Take a value to copy it to the other side.")
+                .size(11)
+                .color(MUTED),
+            header,
+            body,
+            row![
+                Space::new().width(Length::Fill),
+                button("Done")
+                    .on_press(Message::CloseEditDiff)
+                    .padding([6, 12])
+                    .style(button::secondary),
+            ],
+        ]
+        .spacing(12),
+    )
+    .padding(16)
+    .width(560)
+    .max_height(520)
+    .style(|theme: &Theme| container::Style {
+        background: Some(Background::Color(DIALOG_BG)),
+        border: Border {
+            color: theme::border(theme),
+            width: 1.0,
+            radius: 8.0.into(),
+        },
+        ..Default::default()
+    })
+    .into()
+}
+
+fn side<'a>(param: &'static EditParam, value: f32, side: DiffSide) -> Element<'a, Message> {
+    row![
+        text(format_value(param, value))
+            .size(12)
+            .width(Length::Fill),
+        button(text("Take").size(11))
+            .on_press(Message::TakeDiffParam(param.key, side))
+            .padding([2, 8])
+            .style(button::secondary),
+    ]
+    .spacing(6)
+    .width(150)
+    .align_y(Alignment::Center)
+    .into()
+}
+
+/// Same formatting as the develop panel, with an extra decimal where the
+/// panel rounds away small differences.
+pub fn format_value(param: &EditParam, value: f32) -> String {
+    match param.key {
+        "exposure" => format!("{value:+.2} EV"),
+        "wb_temp" => format!("{value:.0} K"),
+        "wb_tint" | "split_balance" => format!("{value:+.0}"),
+        "hsl_hue" => format!("{value:+.0}°"),
+        "rotation" => format!("{value:+.1}°"),
+        "sharpen_radius" => format!("{value:.1}"),
+        key if key.starts_with("crop_") => format!("{:.1}%", value * 100.0),
+        _ => format!("{value:.0}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crema_core::edit_diff::find;
+
+    #[test]
+    fn formats_by_parameter_kind() {
+        assert_eq!(format_value(find("wb_temp").unwrap(), 5500.0), "5500 K");
+        assert_eq!(format_value(find("exposure").unwrap(), 0.5), "+0.50 EV");
+        assert_eq!(format_value(find("crop_w").unwrap(), 0.5), "50.0%");
+        assert_eq!(format_value(find("contrast").unwrap(), -12.0), "-12");
+    }
+}
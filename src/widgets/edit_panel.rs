@@ -1,12 +1,13 @@
-use iced::widget::{Space, button, column, row, slider, text};
+use iced::widget::{Space, button, column, row, slider, text, text_input, toggler};
 use iced::{Color, Element, Length};
 
 use crate::app::{App, EditControl, EditSection, Message, PanelSection, Workspace};
+use crate::theme;
 use crate::views::unified::section_card;
+use crate::widgets::zoomable_image::AnnotationTool;
 
 const MUTED: Color = Color::from_rgb(0.66, 0.66, 0.69);
 const ACTIVE: Color = Color::from_rgb(0.82, 0.86, 0.95);
-const ACCENT: Color = Color::from_rgb(0.26, 0.52, 0.94);
 
 pub fn view(app: &App) -> Element<'_, Message> {
     let mut sections = column![
@@ -75,6 +76,34 @@ pub fn view(app: &App) -> Element<'_, Message> {
             Some(Message::ResetCrop),
             crop_controls(app),
         ));
+        sections = sections.push(section_card(
+            "Color Range",
+            app.is_panel_open(PanelSection::ColorRange),
+            Message::TogglePanelSection(PanelSection::ColorRange),
+            None,
+            crate::widgets::color_range::view(app),
+        ));
+        sections = sections.push(section_card(
+            "Presets",
+            app.is_panel_open(PanelSection::Presets),
+            Message::TogglePanelSection(PanelSection::Presets),
+            None,
+            preset_controls(app),
+        ));
+        sections = sections.push(section_card(
+            "Annotations",
+            app.is_panel_open(PanelSection::Annotations),
+            Message::TogglePanelSection(PanelSection::Annotations),
+            None,
+            annotation_controls(app),
+        ));
+        sections = sections.push(section_card(
+            "Snapshots",
+            app.is_panel_open(PanelSection::Snapshots),
+            Message::TogglePanelSection(PanelSection::Snapshots),
+            None,
+            snapshot_controls(app),
+        ));
     }
 
     sections.into()
@@ -385,11 +414,12 @@ fn crop_controls(app: &App) -> Element<'_, Message> {
         .style(move |theme: &iced::Theme, status| {
             if crop_active {
                 use iced::{Background, Border, Shadow};
+                let accent = theme::accent(theme);
                 iced::widget::button::Style {
-                    background: Some(Background::Color(ACCENT)),
-                    text_color: Color::WHITE,
+                    background: Some(Background::Color(accent)),
+                    text_color: theme::on_accent(theme),
                     border: Border {
-                        color: ACCENT,
+                        color: accent,
                         width: 1.0,
                         radius: 6.0.into(),
                     },
@@ -412,19 +442,29 @@ fn crop_controls(app: &App) -> Element<'_, Message> {
     let mut aspect_row = row![].spacing(4);
     for &(label, ratio) in aspects {
         let is_active = current_aspect == ratio;
-        let label_color = if is_active { ACCENT } else { MUTED };
         aspect_row = aspect_row.push(
-            button(text(label).size(11).color(label_color))
-                .on_press(Message::SetCropAspect(ratio))
-                .padding([3, 6])
-                .style(button::text),
+            button(
+                text(label)
+                    .size(11)
+                    .style(move |t| theme::active_label(t, is_active)),
+            )
+            .on_press(Message::SetCropAspect(ratio))
+            .padding([3, 6])
+            .style(button::text),
         );
     }
 
     column![
-        row![toggle_btn, Space::new().width(Length::Fill),]
-            .align_y(iced::Alignment::Center)
-            .spacing(8),
+        row![
+            toggle_btn,
+            Space::new().width(Length::Fill),
+            button(text("Auto-Crop Scan").size(11))
+                .on_press(Message::AutoCropScan)
+                .padding([6, 10])
+                .style(button::secondary),
+        ]
+        .align_y(iced::Alignment::Center)
+        .spacing(8),
         aspect_row,
         control(
             "Straighten",
@@ -441,6 +481,184 @@ fn crop_controls(app: &App) -> Element<'_, Message> {
     .into()
 }
 
+fn snapshot_controls(app: &App) -> Element<'_, Message> {
+    let mut list = column![
+        button("Take Snapshot")
+            .on_press(Message::TakeSnapshot)
+            .padding([6, 14])
+            .style(button::secondary),
+    ]
+    .spacing(6);
+
+    if app.snapshots().is_empty() {
+        list = list.push(
+            text("Save the current edit to compare against or return to later.")
+                .size(11)
+                .color(MUTED),
+        );
+    }
+    for snapshot in app.snapshots() {
+        list = list.push(
+            row![
+                text(&snapshot.name).size(12),
+                Space::new().width(Length::Fill),
+                button(text("Apply").size(11))
+                    .on_press_maybe(
+                        (app.applied_snapshot() != Some(snapshot.id))
+                            .then_some(Message::ApplySnapshot(snapshot.id)),
+                    )
+                    .padding([2, 6])
+                    .style(button::text),
+                button(text("Compare").size(11))
+                    .on_press(Message::CompareSnapshot(snapshot.id))
+                    .padding([2, 6])
+                    .style(button::text),
+                button(text("Delete").size(11))
+                    .on_press(Message::DeleteSnapshot(snapshot.id))
+                    .padding([2, 6])
+                    .style(button::text),
+            ]
+            .align_y(iced::Alignment::Center),
+        );
+    }
+
+    list.into()
+}
+
+fn annotation_controls(app: &App) -> Element<'_, Message> {
+    let tools = [
+        ("Arrow", AnnotationTool::Arrow),
+        ("Box", AnnotationTool::Rect),
+        ("Note", AnnotationTool::Note),
+    ];
+    let mut tool_row = row![text("Draw").size(12).color(MUTED)]
+        .spacing(4)
+        .align_y(iced::Alignment::Center);
+    for (label, tool) in tools {
+        let is_active = app.annotation_tool() == Some(tool);
+        tool_row = tool_row.push(
+            button(
+                text(label)
+                    .size(11)
+                    .style(move |t| theme::active_label(t, is_active)),
+            )
+            .on_press(Message::SetAnnotationTool((!is_active).then_some(tool)))
+            .padding([3, 6])
+            .style(button::text),
+        );
+    }
+
+    let count = app.annotations().len();
+    let mut list = column![
+        toggler(app.annotations_visible())
+            .label("Show on preview")
+            .text_size(12)
+            .on_toggle(Message::SetAnnotationsVisible),
+        tool_row,
+    ]
+    .spacing(8);
+
+    if let Some(draft) = app.note_draft() {
+        list = list.push(
+            row![
+                text_input("Note text", draft)
+                    .on_input(Message::NoteDraftChanged)
+                    .on_submit(Message::ConfirmNote)
+                    .size(12),
+                button(text("Add").size(11))
+                    .on_press_maybe((!draft.trim().is_empty()).then_some(Message::ConfirmNote))
+                    .padding([4, 8])
+                    .style(button::primary),
+                button(text("Cancel").size(11))
+                    .on_press(Message::CancelNote)
+                    .padding([4, 8])
+                    .style(button::text),
+            ]
+            .spacing(4)
+            .align_y(iced::Alignment::Center),
+        );
+    } else if app.annotation_tool() == Some(AnnotationTool::Note) {
+        list = list.push(
+            text("Click the photo where the note should go.")
+                .size(11)
+                .color(MUTED),
+        );
+    }
+
+    list = list.push(
+        row![
+            text(match count {
+                0 => "No annotations".to_string(),
+                1 => "1 annotation".to_string(),
+                n => format!("{n} annotations"),
+            })
+            .size(12)
+            .color(MUTED),
+            Space::new().width(Length::Fill),
+            button(text("Remove Last").size(11))
+                .on_press_maybe((count > 0).then_some(Message::RemoveLastAnnotation))
+                .padding([2, 6])
+                .style(button::text),
+            button(text("Clear").size(11))
+                .on_press_maybe((count > 0).then_some(Message::ClearAnnotations))
+                .padding([2, 6])
+                .style(button::text),
+        ]
+        .align_y(iced::Alignment::Center),
+    );
+
+    list.into()
+}
+
+fn preset_controls(app: &App) -> Element<'_, Message> {
+    let mut list = column![
+        button("New Preset...")
+            .on_press(Message::NewPreset)
+            .padding([6, 14])
+            .style(button::secondary),
+    ]
+    .spacing(6);
+
+    if app.presets().is_empty() {
+        list = list.push(
+            text("Save some of the current settings to apply to other photos.")
+                .size(11)
+                .color(MUTED),
+        );
+    }
+    for (index, preset) in app.presets().iter().enumerate() {
+        let count = preset.values.len();
+        list = list.push(
+            row![
+                text(&preset.name).size(12),
+                Space::new().width(6),
+                text(format!(
+                    "{count} setting{}",
+                    if count == 1 { "" } else { "s" }
+                ))
+                .size(11)
+                .color(MUTED),
+                Space::new().width(Length::Fill),
+                button(text("Apply").size(11))
+                    .on_press(Message::ApplyPreset(index))
+                    .padding([2, 6])
+                    .style(button::text),
+                button(text("Edit").size(11))
+                    .on_press(Message::EditPreset(index))
+                    .padding([2, 6])
+                    .style(button::text),
+                button(text("Delete").size(11))
+                    .on_press(Message::DeletePreset(index))
+                    .padding([2, 6])
+                    .style(button::text),
+            ]
+            .align_y(iced::Alignment::Center),
+        );
+    }
+
+    list.into()
+}
+
 #[allow(clippy::too_many_arguments)]
 fn control<'a>(
     label: &'static str,
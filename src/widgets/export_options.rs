@@ -0,0 +1,101 @@
+use iced::widget::{Space, button, checkbox, column, container, row, text};
+use iced::{Alignment, Background, Border, Color, Element, Length, Theme};
+
+use crema_export::format as export_format;
+
+use crate::app::Message;
+use crate::theme;
+
+const DIALOG_BG: Color = Color::from_rgb(0.12, 0.12, 0.13);
+const MUTED: Color = Color::from_rgb(0.66, 0.66, 0.69);
+
+/// Choices that apply to one export only. They aren't saved, so each
+/// export starts from the defaults.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ExportOptions {
+    /// Export every selected photo to a folder rather than the open one
+    /// to a file.
+    pub batch: bool,
+    /// Largest JPEG, in bytes. Quality drops as far as it must to fit.
+    pub target_size: Option<u64>,
+    /// Burn review annotations in, for proofs sent back to a client or
+    /// retoucher.
+    pub annotations: bool,
+    /// Write a TIFF at 16 bits per channel even with the preference off,
+    /// as chosen from a banding warning.
+    pub tiff_16bit: bool,
+}
+
+/// Dialog shown before choosing where an export goes.
+pub fn view(options: &ExportOptions) -> Element<'_, Message> {
+    let mut target_sizes = row![
+        button(text("Any Size").size(12))
+            .on_press(Message::SetExportTargetSize(None))
+            .padding([6, 10])
+            .style(if options.target_size.is_none() {
+                button::primary
+            } else {
+                button::secondary
+            })
+    ]
+    .spacing(6);
+    for size in export_format::TARGET_SIZES {
+        target_sizes = target_sizes.push(
+            button(text(export_format::size_label(size)).size(12))
+                .on_press(Message::SetExportTargetSize(Some(size)))
+                .padding([6, 10])
+                .style(if options.target_size == Some(size) {
+                    button::primary
+                } else {
+                    button::secondary
+                }),
+        );
+    }
+
+    let (title, choose) = if options.batch {
+        ("Export Selected", "Choose Folder...")
+    } else {
+        ("Export", "Export...")
+    };
+
+    container(
+        column![
+            text(title).size(18),
+            text("JPEG file size").size(13),
+            text("For submissions with a size limit. Each JPEG is written at the highest quality that fits, found by re-encoding at a few qualities, so the export takes longer.")
+                .size(11)
+                .color(MUTED),
+            target_sizes,
+            checkbox(options.annotations)
+                .label("Include review annotations (proofs)")
+                .text_size(13)
+                .on_toggle(Message::SetExportAnnotations),
+            row![
+                Space::new().width(Length::Fill),
+                button("Cancel")
+                    .on_press(Message::CloseExportOptions)
+                    .padding([6, 12])
+                    .style(button::secondary),
+                button(choose)
+                    .on_press(Message::ChooseExportDestination)
+                    .padding([6, 12])
+                    .style(button::primary),
+            ]
+            .spacing(8)
+            .align_y(Alignment::Center),
+        ]
+        .spacing(12),
+    )
+    .padding(16)
+    .width(420)
+    .style(|theme: &Theme| container::Style {
+        background: Some(Background::Color(DIALOG_BG)),
+        border: Border {
+            color: theme::border(theme),
+            width: 1.0,
+            radius: 8.0.into(),
+        },
+        ..Default::default()
+    })
+    .into()
+}
@@ -0,0 +1,77 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use iced::widget::{Space, button, column, container, progress_bar, row, text};
+use iced::{Alignment, Background, Border, Color, Element, Length, Theme};
+
+use crate::app::Message;
+use crate::theme;
+
+const DIALOG_BG: Color = Color::from_rgb(0.12, 0.12, 0.13);
+const MUTED: Color = Color::from_rgb(0.66, 0.66, 0.69);
+
+/// A full resolution export of the open photo running in the background.
+#[derive(Debug, Clone)]
+pub struct ExportProgress {
+    pub photo: String,
+    /// How much of the export is done, 0..=1.
+    pub fraction: f32,
+    /// Set to stop the export; it checks between bands.
+    pub cancel: Arc<AtomicBool>,
+}
+
+impl ExportProgress {
+    pub fn new(photo: String, cancel: Arc<AtomicBool>) -> Self {
+        Self {
+            photo,
+            fraction: 0.0,
+            cancel,
+        }
+    }
+
+    pub fn cancelling(&self) -> bool {
+        self.cancel.load(Ordering::Relaxed)
+    }
+}
+
+/// Floating panel showing export progress with a Cancel button. It isn't
+/// modal: the rest of the app stays usable while the export runs.
+pub fn view(progress: &ExportProgress) -> Element<'_, Message> {
+    let status = if progress.cancelling() {
+        "Cancelling...".to_string()
+    } else {
+        format!("{:.0}%", progress.fraction * 100.0)
+    };
+    let mut cancel = button(text("Cancel").size(12))
+        .padding([4, 10])
+        .style(button::secondary);
+    if !progress.cancelling() {
+        cancel = cancel.on_press(Message::CancelExport);
+    }
+
+    container(
+        column![
+            text(format!("Exporting {}", progress.photo)).size(13),
+            progress_bar(0.0..=1.0, progress.fraction).girth(6),
+            row![
+                text(status).size(11).color(MUTED),
+                Space::new().width(Length::Fill),
+                cancel,
+            ]
+            .align_y(Alignment::Center),
+        ]
+        .spacing(8),
+    )
+    .padding(12)
+    .width(300)
+    .style(|theme: &Theme| container::Style {
+        background: Some(Background::Color(DIALOG_BG)),
+        border: Border {
+            color: theme::border(theme),
+            width: 1.0,
+            radius: 8.0.into(),
+        },
+        ..Default::default()
+    })
+    .into()
+}
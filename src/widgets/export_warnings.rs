@@ -0,0 +1,110 @@
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+
+use iced::widget::{Space, button, checkbox, column, container, row, text};
+use iced::{Alignment, Background, Border, Color, Element, Length, Theme};
+
+use crate::app::Message;
+use crate::export_check::ExportWarning;
+use crate::theme;
+use crate::widgets::export_options::ExportOptions;
+
+const DIALOG_BG: Color = Color::from_rgb(0.12, 0.12, 0.13);
+const MUTED: Color = Color::from_rgb(0.66, 0.66, 0.69);
+const WARNING: Color = Color::from_rgb(0.95, 0.74, 0.3);
+
+/// An export held back by warnings, waiting for the user to go ahead,
+/// change course or cancel.
+#[derive(Debug, Clone)]
+pub struct ExportReview {
+    pub path: PathBuf,
+    pub options: ExportOptions,
+    pub warnings: Vec<ExportWarning>,
+    /// Warnings checked "Don't warn again".
+    pub silenced: BTreeSet<ExportWarning>,
+}
+
+impl ExportReview {
+    pub fn new(path: PathBuf, options: ExportOptions, warnings: Vec<ExportWarning>) -> Self {
+        Self {
+            path,
+            options,
+            warnings,
+            silenced: BTreeSet::new(),
+        }
+    }
+
+    pub fn toggle_silenced(&mut self, warning: ExportWarning) {
+        if !self.silenced.remove(&warning) {
+            self.silenced.insert(warning);
+        }
+    }
+}
+
+pub fn view(review: &ExportReview) -> Element<'_, Message> {
+    let mut warnings = column![].spacing(14);
+    for &warning in &review.warnings {
+        warnings = warnings.push(
+            column![
+                text(warning.title()).size(14).color(WARNING),
+                text(warning.detail()).size(12).color(MUTED),
+                checkbox(review.silenced.contains(&warning))
+                    .label("Don't warn about this again")
+                    .text_size(12)
+                    .on_toggle(move |_| Message::ToggleExportWarningSilenced(warning)),
+            ]
+            .spacing(6),
+        );
+    }
+
+    let name = review
+        .path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let mut buttons = row![
+        Space::new().width(Length::Fill),
+        button("Cancel")
+            .on_press(Message::CancelExportReview)
+            .padding([6, 12])
+            .style(button::secondary),
+    ]
+    .spacing(8)
+    .align_y(Alignment::Center);
+    if review.warnings.contains(&ExportWarning::Banding) && !review.options.batch {
+        buttons = buttons.push(
+            button("Export 16-bit TIFF")
+                .on_press(Message::ExportAs16BitTiff)
+                .padding([6, 12])
+                .style(button::secondary),
+        );
+    }
+    buttons = buttons.push(
+        button("Export Anyway")
+            .on_press(Message::ExportAnyway)
+            .padding([6, 12])
+            .style(button::primary),
+    );
+
+    let mut content = column![text(format!("Export {name}?")).size(18)].spacing(16);
+    if review.options.batch {
+        content = content.push(
+            text("Some of the selected photos are affected. Batches are written as 8-bit JPEGs.")
+                .size(12)
+                .color(MUTED),
+        );
+    }
+    container(content.push(warnings).push(buttons))
+        .padding(16)
+        .width(440)
+        .style(|theme: &Theme| container::Style {
+            background: Some(Background::Color(DIALOG_BG)),
+            border: Border {
+                color: theme::border(theme),
+                width: 1.0,
+                radius: 8.0.into(),
+            },
+            ..Default::default()
+        })
+        .into()
+}
@@ -6,13 +6,12 @@ use iced::{Background, Border, Color, Element, Length, Shadow, Theme};
 use crema_catalog::models::{Photo, PhotoId};
 
 use crate::app::Message;
+use crate::theme;
 
 const THUMB_SIZE: f32 = 92.0;
 const STRIP_HEIGHT: f32 = 118.0;
 const BG: Color = Color::from_rgb(0.08, 0.08, 0.09);
 const CARD_BG: Color = Color::from_rgb(0.12, 0.12, 0.13);
-const BORDER: Color = Color::from_rgb(0.20, 0.20, 0.22);
-const ACCENT: Color = Color::from_rgb(0.26, 0.52, 0.94);
 const MUTED: Color = Color::from_rgb(0.66, 0.66, 0.69);
 
 pub fn view<'a>(
@@ -53,7 +52,9 @@ pub fn view<'a>(
             button(cell)
                 .on_press(Message::OpenPhoto(photo.id))
                 .padding(4)
-                .style(move |_theme: &Theme, status| filmstrip_button_style(status, is_selected))
+                .style(move |theme: &Theme, status| {
+                    filmstrip_button_style(theme, status, is_selected)
+                })
                 .into()
         })
         .collect();
@@ -66,10 +67,10 @@ pub fn view<'a>(
             .width(Length::Fill),
     )
     .height(STRIP_HEIGHT)
-    .style(|_theme: &Theme| container::Style {
+    .style(|theme: &Theme| container::Style {
         background: Some(Background::Color(BG)),
         border: Border {
-            color: BORDER,
+            color: theme::border(theme),
             width: 1.0,
             radius: 0.0.into(),
         },
@@ -79,7 +80,11 @@ pub fn view<'a>(
     .into()
 }
 
-fn filmstrip_button_style(status: button::Status, is_selected: bool) -> button::Style {
+fn filmstrip_button_style(
+    theme: &Theme,
+    status: button::Status,
+    is_selected: bool,
+) -> button::Style {
     let background = match status {
         button::Status::Hovered => Color::from_rgb(0.15, 0.15, 0.17),
         button::Status::Pressed => Color::from_rgb(0.11, 0.11, 0.12),
@@ -91,8 +96,16 @@ fn filmstrip_button_style(status: button::Status, is_selected: bool) -> button::
         background: Some(Background::Color(background)),
         text_color: Color::WHITE,
         border: Border {
-            color: if is_selected { ACCENT } else { BORDER },
-            width: 1.0,
+            color: if is_selected {
+                theme::accent(theme)
+            } else {
+                theme::border(theme)
+            },
+            width: if is_selected {
+                theme::ring_width(theme, 1.0)
+            } else {
+                1.0
+            },
             radius: 6.0.into(),
         },
         shadow: Shadow::default(),
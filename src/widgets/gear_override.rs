@@ -0,0 +1,297 @@
+use iced::widget::{Space, button, column, container, row, text, text_input, toggler};
+use iced::{Alignment, Background, Border, Color, Element, Length, Theme};
+
+use crema_catalog::models::{MetadataOverride, Photo, PhotoId};
+
+use crate::app::Message;
+use crate::theme;
+
+const DIALOG_BG: Color = Color::from_rgb(0.12, 0.12, 0.13);
+const MUTED: Color = Color::from_rgb(0.66, 0.66, 0.69);
+const ERROR: Color = Color::from_rgb(0.87, 0.43, 0.38);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GearField {
+    Make,
+    Model,
+    Lens,
+    FocalLength,
+}
+
+impl GearField {
+    const ALL: [GearField; 4] = [
+        GearField::Make,
+        GearField::Model,
+        GearField::Lens,
+        GearField::FocalLength,
+    ];
+
+    fn label(self) -> &'static str {
+        match self {
+            GearField::Make => "Camera make",
+            GearField::Model => "Camera model",
+            GearField::Lens => "Lens",
+            GearField::FocalLength => "Focal length (mm)",
+        }
+    }
+}
+
+/// State for the camera and lens override dialog.
+#[derive(Debug, Clone)]
+pub struct GearOverrideForm {
+    /// The photos the dialog was opened on.
+    pub ids: Vec<PhotoId>,
+    /// Every photo from the same shoot, `ids` included.
+    pub shoot: Vec<PhotoId>,
+    pub whole_shoot: bool,
+    values: [String; 4],
+    /// What the first photo's EXIF says, shown behind empty fields.
+    exif: [Option<String>; 4],
+    /// The first photo already has an override to clear.
+    pub has_override: bool,
+}
+
+impl GearOverrideForm {
+    /// `original` is the first photo as its EXIF recorded it, and
+    /// `existing` its current override, which prefills the fields.
+    pub fn new(
+        ids: Vec<PhotoId>,
+        shoot: Vec<PhotoId>,
+        original: &Photo,
+        existing: Option<MetadataOverride>,
+    ) -> Self {
+        let existing = existing.unwrap_or_default();
+        let has_override = !existing.is_empty();
+        Self {
+            ids,
+            shoot,
+            whole_shoot: false,
+            values: [
+                existing.camera_make.unwrap_or_default(),
+                existing.camera_model.unwrap_or_default(),
+                existing.lens.unwrap_or_default(),
+                existing
+                    .focal_length
+                    .map(format_focal_length)
+                    .unwrap_or_default(),
+            ],
+            exif: [
+                original.camera_make.clone(),
+                original.camera_model.clone(),
+                original.lens.clone(),
+                original.focal_length.map(format_focal_length),
+            ],
+            has_override,
+        }
+    }
+
+    pub fn value(&self, field: GearField) -> &str {
+        &self.values[field as usize]
+    }
+
+    pub fn set(&mut self, field: GearField, value: String) {
+        self.values[field as usize] = value;
+    }
+
+    /// The photos an apply or clear writes to.
+    pub fn targets(&self) -> &[PhotoId] {
+        if self.whole_shoot {
+            &self.shoot
+        } else {
+            &self.ids
+        }
+    }
+
+    /// Build the override from the fields; empty ones keep the EXIF value.
+    pub fn to_override(&self) -> Result<MetadataOverride, String> {
+        let value = |field: GearField| {
+            let value = self.value(field).trim();
+            (!value.is_empty()).then(|| value.to_string())
+        };
+        let focal_length = match value(GearField::FocalLength) {
+            None => None,
+            Some(input) => Some(
+                parse_focal_length(&input)
+                    .ok_or_else(|| format!("Can't read focal length \"{input}\""))?,
+            ),
+        };
+        Ok(MetadataOverride {
+            camera_make: value(GearField::Make),
+            camera_model: value(GearField::Model),
+            lens: value(GearField::Lens),
+            focal_length,
+        })
+    }
+}
+
+/// Photos shot on the same day with the same camera body as `anchor`,
+/// `anchor` included. Without a capture date it is a shoot of one.
+pub fn shoot_of(photos: &[Photo], anchor: &Photo) -> Vec<PhotoId> {
+    fn day(photo: &Photo) -> Option<&str> {
+        photo.date_taken.as_deref().and_then(|d| d.get(..10))
+    }
+    let Some(anchor_day) = day(anchor) else {
+        return vec![anchor.id];
+    };
+    photos
+        .iter()
+        .filter(|p| day(p) == Some(anchor_day) && p.camera_model == anchor.camera_model)
+        .map(|p| p.id)
+        .collect()
+}
+
+/// Parse `58`, `58mm` or `58.5 mm` into millimeters.
+fn parse_focal_length(input: &str) -> Option<f64> {
+    let number = input.trim().trim_end_matches("mm").trim();
+    number
+        .parse::<f64>()
+        .ok()
+        .filter(|mm| mm.is_finite() && *mm > 0.0)
+}
+
+fn format_focal_length(mm: f64) -> String {
+    if mm.fract() == 0.0 {
+        format!("{mm:.0}")
+    } else {
+        format!("{mm}")
+    }
+}
+
+pub fn view(form: &GearOverrideForm) -> Element<'_, Message> {
+    let mut fields = column![].spacing(10);
+    for field in GearField::ALL {
+        let placeholder = match &form.exif[field as usize] {
+            Some(value) => format!("EXIF: {value}"),
+            None => "Not in EXIF".to_string(),
+        };
+        fields = fields.push(
+            column![
+                text(field.label()).size(12),
+                text_input(&placeholder, form.value(field))
+                    .on_input(move |value| Message::GearOverrideChanged(field, value))
+                    .size(13)
+                    .padding(6),
+            ]
+            .spacing(4),
+        );
+    }
+
+    let fixup = form.to_override();
+    if let Err(err) = &fixup {
+        fields = fields.push(text(err.clone()).size(11).color(ERROR));
+    }
+
+    if form.shoot.len() > form.ids.len() {
+        fields = fields.push(
+            toggler(form.whole_shoot)
+                .label(format!(
+                    "Apply to all {} photos from this shoot",
+                    form.shoot.len()
+                ))
+                .text_size(13)
+                .on_toggle(Message::SetGearOverrideShoot),
+        );
+    }
+
+    let count = form.targets().len();
+    let plural = if count == 1 { "" } else { "s" };
+    let can_apply = fixup.is_ok_and(|f| !f.is_empty());
+    let clear: Element<'_, Message> = if form.has_override {
+        button("Clear Override")
+            .on_press(Message::ClearGearOverride)
+            .padding([6, 12])
+            .style(button::secondary)
+            .into()
+    } else {
+        Space::new().into()
+    };
+    let buttons = row![
+        clear,
+        Space::new().width(Length::Fill),
+        button("Cancel")
+            .on_press(Message::CloseGearOverride)
+            .padding([6, 12])
+            .style(button::secondary),
+        button(text(format!("Apply to {count} Photo{plural}")))
+            .on_press_maybe(can_apply.then_some(Message::ApplyGearOverride))
+            .padding([6, 12])
+            .style(button::primary),
+    ]
+    .spacing(8)
+    .align_y(Alignment::Center);
+
+    container(
+        column![
+            text("Camera & Lens").size(18),
+            text(
+                "Used for lens corrections and filters instead of EXIF. Empty fields keep the EXIF value."
+            )
+            .size(11)
+            .color(MUTED),
+            fields,
+            buttons,
+        ]
+        .spacing(12),
+    )
+    .padding(16)
+    .width(420)
+    .style(|theme: &Theme| container::Style {
+        background: Some(Background::Color(DIALOG_BG)),
+        border: Border {
+            color: theme::border(theme),
+            width: 1.0,
+            radius: 8.0.into(),
+        },
+        ..Default::default()
+    })
+    .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn photo(id: PhotoId, date: Option<&str>, model: Option<&str>) -> Photo {
+        Photo {
+            id,
+            file_path: format!("/photos/{id}.jpg"),
+            camera_model: model.map(String::from),
+            date_taken: date.map(String::from),
+            ..Photo::default()
+        }
+    }
+
+    #[test]
+    fn shoot_is_same_day_and_body() {
+        let photos = [
+            photo(1, Some("2026-05-02 09:00:00"), Some("Z 7")),
+            photo(2, Some("2026-05-02 17:30:00"), Some("Z 7")),
+            photo(3, Some("2026-05-02 12:00:00"), Some("X-T5")),
+            photo(4, Some("2026-05-03 09:00:00"), Some("Z 7")),
+            photo(5, None, Some("Z 7")),
+        ];
+        assert_eq!(shoot_of(&photos, &photos[0]), vec![1, 2]);
+        assert_eq!(shoot_of(&photos, &photos[4]), vec![5]);
+    }
+
+    #[test]
+    fn empty_fields_keep_exif_and_focal_length_parses() {
+        let original = photo(1, None, Some("Z 7"));
+        let mut form = GearOverrideForm::new(vec![1], vec![1, 2], &original, None);
+        assert!(form.to_override().unwrap().is_empty());
+
+        form.set(GearField::Lens, " Helios 44-2 ".into());
+        form.set(GearField::FocalLength, "58mm".into());
+        let fixup = form.to_override().unwrap();
+        assert_eq!(fixup.lens.as_deref(), Some("Helios 44-2"));
+        assert_eq!(fixup.focal_length, Some(58.0));
+        assert_eq!(fixup.camera_model, None);
+
+        form.set(GearField::FocalLength, "wide".into());
+        assert!(form.to_override().is_err());
+
+        assert_eq!(form.targets(), &[1]);
+        form.whole_shoot = true;
+        assert_eq!(form.targets(), &[1, 2]);
+    }
+}
@@ -0,0 +1,166 @@
+use iced::widget::{Space, button, column, container, row, text};
+use iced::{Alignment, Background, Border, Color, Element, Length, Theme};
+
+use crate::app::Message;
+use crate::theme;
+
+const DIALOG_BG: Color = Color::from_rgb(0.12, 0.12, 0.13);
+const MUTED: Color = Color::from_rgb(0.66, 0.66, 0.69);
+
+/// Which renderer develop previews use, and why.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RendererStatus {
+    /// GPU setup hasn't finished; previews render on the CPU meanwhile.
+    Detecting,
+    Gpu {
+        adapter: String,
+        backend: String,
+    },
+    /// The only adapter was a software rasterizer, which runs the shaders
+    /// on the CPU more slowly than the CPU pipeline, so it isn't used.
+    Software {
+        adapter: String,
+        backend: String,
+    },
+    /// No adapter or device could be created.
+    Unavailable(String),
+}
+
+impl RendererStatus {
+    pub fn from_adapter(adapter: &wgpu::AdapterInfo) -> Self {
+        let name = adapter.name.clone();
+        let backend = format!("{:?}", adapter.backend);
+        if adapter.device_type == wgpu::DeviceType::Cpu {
+            RendererStatus::Software {
+                adapter: name,
+                backend,
+            }
+        } else {
+            RendererStatus::Gpu {
+                adapter: name,
+                backend,
+            }
+        }
+    }
+
+    /// Whether previews fell back to the CPU for good.
+    pub fn is_fallback(&self) -> bool {
+        matches!(
+            self,
+            RendererStatus::Software { .. } | RendererStatus::Unavailable(_)
+        )
+    }
+
+    pub fn summary(&self) -> String {
+        match self {
+            RendererStatus::Detecting => "Detecting the GPU...".into(),
+            RendererStatus::Gpu { adapter, backend } => format!("GPU: {adapter} ({backend})"),
+            RendererStatus::Software { adapter, backend } => {
+                format!("CPU: only a software adapter was found, {adapter} ({backend})")
+            }
+            RendererStatus::Unavailable(reason) => format!("CPU: no GPU available, {reason}"),
+        }
+    }
+}
+
+/// One-time message shown when previews fall back to the CPU.
+pub fn notice(status: &RendererStatus) -> Element<'_, Message> {
+    let reason = match status {
+        RendererStatus::Software { .. } => {
+            "This computer has no usable graphics card, only a software renderer, which is common in virtual machines."
+        }
+        _ => {
+            "No graphics card could be set up, which is common in virtual machines and on old drivers."
+        }
+    };
+    dialog(
+        column![
+            text("Rendering on the CPU").size(18),
+            text(reason).size(12),
+            text("Every edit still works; previews just take a little longer. Diagnostics shows what was found.")
+                .size(11)
+                .color(MUTED),
+            row![
+                Space::new().width(Length::Fill),
+                button("Diagnostics")
+                    .on_press(Message::OpenGpuDiagnostics)
+                    .padding([6, 12])
+                    .style(button::secondary),
+                button("OK")
+                    .on_press(Message::DismissGpuNotice)
+                    .padding([6, 12])
+                    .style(button::primary),
+            ]
+            .spacing(8)
+            .align_y(Alignment::Center),
+        ]
+        .spacing(12),
+    )
+}
+
+/// Dialog describing the renderer in use, for bug reports.
+pub fn view(status: &RendererStatus) -> Element<'_, Message> {
+    dialog(
+        column![
+            text("GPU Diagnostics").size(18),
+            text(status.summary()).size(12),
+            text(format!(
+                "Crema {} on {} {}",
+                env!("CARGO_PKG_VERSION"),
+                std::env::consts::OS,
+                std::env::consts::ARCH
+            ))
+            .size(11)
+            .color(MUTED),
+            text("For a detailed log, launch from a terminal with RUST_LOG=wgpu=info and include its output with a bug report.")
+                .size(11)
+                .color(MUTED),
+            row![
+                Space::new().width(Length::Fill),
+                button("Done")
+                    .on_press(Message::CloseGpuDiagnostics)
+                    .padding([6, 12])
+                    .style(button::secondary),
+            ],
+        ]
+        .spacing(12),
+    )
+}
+
+fn dialog(body: iced::widget::Column<'_, Message>) -> Element<'_, Message> {
+    container(body)
+        .padding(16)
+        .width(420)
+        .style(|theme: &Theme| container::Style {
+            background: Some(Background::Color(DIALOG_BG)),
+            border: Border {
+                color: theme::border(theme),
+                width: 1.0,
+                radius: 8.0.into(),
+            },
+            ..Default::default()
+        })
+        .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_settled_cpu_rendering_is_a_fallback() {
+        assert!(!RendererStatus::Detecting.is_fallback());
+        let gpu = RendererStatus::Gpu {
+            adapter: "Radeon".into(),
+            backend: "Vulkan".into(),
+        };
+        assert!(!gpu.is_fallback());
+        let software = RendererStatus::Software {
+            adapter: "llvmpipe".into(),
+            backend: "Vulkan".into(),
+        };
+        assert!(software.is_fallback());
+        assert!(software.summary().contains("llvmpipe"));
+        assert!(RendererStatus::Unavailable("no adapter".into()).is_fallback());
+    }
+}
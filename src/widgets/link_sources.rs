@@ -0,0 +1,80 @@
+use iced::widget::{Space, button, column, container, row, text};
+use iced::{Background, Border, Color, Element, Length, Theme};
+
+use crema_catalog::models::DerivationKind;
+
+use crate::app::Message;
+use crate::theme;
+
+const DIALOG_BG: Color = Color::from_rgb(0.12, 0.12, 0.13);
+const MUTED: Color = Color::from_rgb(0.66, 0.66, 0.69);
+
+/// Derivations made outside crema, which only the user can record.
+/// Stacks are linked when crema makes them.
+const KINDS: [DerivationKind; 3] = [
+    DerivationKind::Hdr,
+    DerivationKind::Panorama,
+    DerivationKind::ExternalEdit,
+];
+
+/// Dialog for recording how the selected photo was made from the rest of
+/// the selection.
+pub fn view(derived: String, source_count: usize) -> Element<'static, Message> {
+    let mut choices = column![].spacing(6);
+    for kind in KINDS {
+        let hint = match kind {
+            DerivationKind::Hdr => "Merged from bracketed exposures",
+            DerivationKind::Panorama => "Stitched from overlapping frames",
+            DerivationKind::ExternalEdit => "Edited in another application",
+            DerivationKind::Stack => "Combined by crema",
+        };
+        choices = choices.push(
+            button(
+                column![
+                    text(kind.label()).size(12),
+                    text(hint).size(10).color(MUTED),
+                ]
+                .spacing(2),
+            )
+            .on_press(Message::LinkSources(kind))
+            .padding([6, 12])
+            .width(Length::Fill)
+            .style(button::secondary),
+        );
+    }
+
+    let sources = if source_count == 1 {
+        "the other selected photo".to_string()
+    } else {
+        format!("the other {source_count} selected photos")
+    };
+    container(
+        column![
+            text("Link Sources").size(18),
+            text(format!("Record {derived} as made from {sources}."))
+                .size(11)
+                .color(MUTED),
+            choices,
+            row![
+                Space::new().width(Length::Fill),
+                button("Cancel")
+                    .on_press(Message::CloseLinkSources)
+                    .padding([6, 12])
+                    .style(button::secondary),
+            ],
+        ]
+        .spacing(12),
+    )
+    .padding(16)
+    .width(340)
+    .style(|theme: &Theme| container::Style {
+        background: Some(Background::Color(DIALOG_BG)),
+        border: Border {
+            color: theme::border(theme),
+            width: 1.0,
+            radius: 8.0.into(),
+        },
+        ..Default::default()
+    })
+    .into()
+}
@@ -1,10 +1,24 @@
 use iced::Element;
-use iced::widget::{column, row, text};
+use iced::widget::{button, column, row, text};
+
+use crema_catalog::models::{DerivationKind, PhotoId};
 
 use crate::app::Message;
 
-pub fn view(exif_data: &[(String, String)]) -> Element<'_, Message> {
-    let mut items: Vec<Element<'_, Message>> = Vec::new();
+/// A photo linked to the current one by provenance.
+#[derive(Debug, Clone)]
+pub struct ProvenanceLink {
+    pub photo_id: PhotoId,
+    pub name: String,
+    pub kind: DerivationKind,
+}
+
+pub fn view<'a>(
+    exif_data: &'a [(String, String)],
+    sources: &'a [ProvenanceLink],
+    derivatives: &'a [ProvenanceLink],
+) -> Element<'a, Message> {
+    let mut items: Vec<Element<'a, Message>> = Vec::new();
 
     if exif_data.is_empty() {
         items.push(
@@ -25,5 +39,27 @@ pub fn view(exif_data: &[(String, String)]) -> Element<'_, Message> {
         }
     }
 
+    if let Some(kind) = sources.first().map(|l| l.kind) {
+        items.push(
+            text(format!("Source images ({})", kind.label()))
+                .size(12)
+                .into(),
+        );
+        items.extend(sources.iter().map(link_button));
+    }
+    if !derivatives.is_empty() {
+        items.push(text("Derived images").size(12).into());
+        items.extend(derivatives.iter().map(link_button));
+    }
+
     column(items).spacing(6).into()
 }
+
+/// Jumps to the linked photo.
+fn link_button(link: &ProvenanceLink) -> Element<'_, Message> {
+    button(text(&link.name).size(11))
+        .on_press(Message::OpenPhoto(link.photo_id))
+        .padding([2, 6])
+        .style(button::text)
+        .into()
+}
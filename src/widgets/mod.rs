@@ -1,7 +1,27 @@
+pub mod animation_export;
+pub mod auto_stack;
+pub mod batch_metadata;
+pub mod color_range;
+pub mod comparison_export;
 pub mod date_sidebar;
+pub mod edit_diff;
 pub mod edit_panel;
+pub mod export_options;
+pub mod export_progress;
+pub mod export_warnings;
 pub mod filmstrip;
+pub mod gear_override;
+pub mod gpu_diagnostics;
 pub mod histogram;
+pub mod link_sources;
 pub mod metadata_panel;
+pub mod notifications;
+pub mod path_remap;
+pub mod preset_editor;
+pub mod quarantine;
+pub mod quick_develop;
+pub mod render_consistency;
+pub mod stack;
 pub mod thumbnail_grid;
+pub mod time_machine;
 pub mod zoomable_image;
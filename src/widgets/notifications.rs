@@ -0,0 +1,140 @@
+use std::collections::BTreeSet;
+use std::time::Instant;
+
+use iced::widget::{Space, button, column, container, row, scrollable, text, toggler};
+use iced::{Alignment, Background, Border, Color, Element, Length, Theme};
+
+use crate::app::Message;
+use crate::notifications::{Category, Notification, NotificationCenter};
+use crate::theme;
+
+const DIALOG_BG: Color = Color::from_rgb(0.12, 0.12, 0.13);
+const MUTED: Color = Color::from_rgb(0.66, 0.66, 0.69);
+const FAILED: Color = Color::from_rgb(0.95, 0.45, 0.4);
+
+fn panel_style(theme: &Theme) -> container::Style {
+    container::Style {
+        background: Some(Background::Color(DIALOG_BG)),
+        border: Border {
+            color: theme::border(theme),
+            width: 1.0,
+            radius: 8.0.into(),
+        },
+        ..Default::default()
+    }
+}
+
+fn title(notification: &Notification) -> iced::widget::Text<'_> {
+    let title = text(&notification.title).size(13);
+    if notification.failed {
+        title.color(FAILED)
+    } else {
+        title
+    }
+}
+
+fn action_button(notification: &Notification) -> Option<Element<'_, Message>> {
+    let action = notification.action.as_ref()?;
+    Some(
+        button(text(action.label()).size(12))
+            .on_press(Message::RunNotificationAction(action.clone()))
+            .padding([4, 10])
+            .style(button::secondary)
+            .into(),
+    )
+}
+
+/// The toasts currently up, newest first, each with its action and a
+/// close button.
+pub fn toasts(center: &NotificationCenter) -> Element<'_, Message> {
+    let mut list = column![].spacing(8);
+    for notification in center.toasts() {
+        let body = column![title(notification)]
+            .push(action_button(notification))
+            .spacing(8)
+            .width(Length::Fill);
+        list = list.push(
+            container(
+                row![
+                    body,
+                    button(text("\u{00d7}").size(14))
+                        .on_press(Message::DismissNotification(notification.id))
+                        .padding([0, 6])
+                        .style(button::text),
+                ]
+                .spacing(8),
+            )
+            .padding(12)
+            .width(300)
+            .style(panel_style),
+        );
+    }
+    list.into()
+}
+
+/// The history drawer: every notification since launch or the last
+/// clear, and which categories show toasts.
+pub fn drawer<'a>(
+    center: &'a NotificationCenter,
+    muted: &BTreeSet<Category>,
+) -> Element<'a, Message> {
+    let empty = center.history().next().is_none();
+    let header = row![
+        text("Notifications").size(16),
+        Space::new().width(Length::Fill),
+        button(text("Clear").size(12))
+            .on_press_maybe((!empty).then_some(Message::ClearNotifications))
+            .padding([4, 10])
+            .style(button::secondary),
+        button(text("Close").size(12))
+            .on_press(Message::ToggleNotificationDrawer)
+            .padding([4, 10])
+            .style(button::secondary),
+    ]
+    .spacing(6)
+    .align_y(Alignment::Center);
+
+    let mut toggles = column![text("Show toasts for").size(12).color(MUTED)].spacing(6);
+    for category in Category::ALL {
+        toggles = toggles.push(
+            toggler(!muted.contains(&category))
+                .label(category.label())
+                .text_size(12)
+                .on_toggle(move |show| Message::SetNotificationMuted(category, !show)),
+        );
+    }
+
+    let now = Instant::now();
+    let mut list = column![].spacing(12);
+    for notification in center.history() {
+        list = list.push(
+            column![
+                title(notification),
+                text(format!(
+                    "{} \u{00b7} {}",
+                    notification.category.label(),
+                    notification.age(now)
+                ))
+                .size(11)
+                .color(MUTED),
+            ]
+            .push(action_button(notification))
+            .spacing(4),
+        );
+    }
+    let history: Element<'a, Message> = if empty {
+        text("Nothing yet. Finished exports, imports and background jobs show up here.")
+            .size(12)
+            .color(MUTED)
+            .into()
+    } else {
+        scrollable(list).height(Length::Fill).into()
+    };
+
+    container(column![header, toggles, history].spacing(16))
+        .padding(16)
+        .width(340)
+        .height(Length::Fill)
+        .style(panel_style)
+        .into()
+}
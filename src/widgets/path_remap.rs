@@ -0,0 +1,159 @@
+use std::collections::HashSet;
+use std::path::Path;
+
+use iced::widget::{Space, button, column, container, row, scrollable, text, text_input};
+use iced::{Alignment, Background, Border, Color, Element, Length, Theme};
+
+use crema_catalog::models::PathRemap;
+
+use crate::app::Message;
+use crate::theme;
+
+const DIALOG_BG: Color = Color::from_rgb(0.12, 0.12, 0.13);
+const MUTED: Color = Color::from_rgb(0.66, 0.66, 0.69);
+const ERROR: Color = Color::from_rgb(0.87, 0.43, 0.38);
+
+/// Rows listed in the preview; the count covers the rest.
+const PREVIEW_ROWS: usize = 50;
+
+/// State for the path remap dialog.
+#[derive(Debug, Clone, Default)]
+pub struct PathRemapForm {
+    pub from: String,
+    pub to: String,
+    /// Photos under `from`, with their new paths.
+    pub preview: Vec<PathRemap>,
+    /// Whether each listed row's new path exists, checked once per preview
+    /// rather than on every redraw.
+    found: Vec<bool>,
+    /// New paths that already belong to another photo in the catalog.
+    pub conflicts: usize,
+}
+
+impl PathRemapForm {
+    /// Show `preview`, given the paths already in the catalog.
+    pub fn set_preview(&mut self, preview: Vec<PathRemap>, existing: &HashSet<&str>) {
+        let moving: HashSet<&str> = preview.iter().map(|r| r.old_path.as_str()).collect();
+        self.conflicts = preview
+            .iter()
+            .filter(|r| {
+                existing.contains(r.new_path.as_str()) && !moving.contains(r.new_path.as_str())
+            })
+            .count();
+        self.found = preview
+            .iter()
+            .take(PREVIEW_ROWS)
+            .map(|r| Path::new(&r.new_path).exists())
+            .collect();
+        self.preview = preview;
+    }
+
+    pub fn can_apply(&self) -> bool {
+        !self.preview.is_empty() && !self.to.trim().is_empty() && self.conflicts == 0
+    }
+}
+
+/// Dialog for moving every photo under one folder to another, previewing
+/// the rows it touches before anything is written.
+pub fn view(form: &PathRemapForm) -> Element<'_, Message> {
+    let inputs = column![
+        text("Old location").size(12),
+        text_input("/Volumes/OldDrive/Photos", &form.from)
+            .on_input(Message::PathRemapFromChanged)
+            .size(13)
+            .padding(6),
+        text("New location").size(12),
+        text_input("/Volumes/NewDrive/Photos", &form.to)
+            .on_input(Message::PathRemapToChanged)
+            .size(13)
+            .padding(6),
+    ]
+    .spacing(4);
+
+    let count = form.preview.len();
+    let summary: Element<'_, Message> = if form.conflicts > 0 {
+        text(format!(
+            "{} of the new paths are already in the catalog.",
+            form.conflicts
+        ))
+        .size(11)
+        .color(ERROR)
+        .into()
+    } else if form.from.trim().is_empty() {
+        text("Enter the folder the photos used to be in.")
+            .size(11)
+            .color(MUTED)
+            .into()
+    } else {
+        text(format!(
+            "{count} photo{} under this folder.",
+            if count == 1 { "" } else { "s" }
+        ))
+        .size(11)
+        .color(MUTED)
+        .into()
+    };
+
+    let mut rows = column![].spacing(4);
+    for (remap, &found) in form.preview.iter().zip(&form.found) {
+        rows = rows.push(
+            row![
+                text(&remap.new_path).size(11).width(Length::Fill),
+                text(if found { "Found" } else { "Missing" })
+                    .size(11)
+                    .color(if found { MUTED } else { ERROR }),
+            ]
+            .spacing(8),
+        );
+    }
+    if count > PREVIEW_ROWS {
+        rows = rows.push(
+            text(format!("and {} more", count - PREVIEW_ROWS))
+                .size(11)
+                .color(MUTED),
+        );
+    }
+
+    let buttons = row![
+        Space::new().width(Length::Fill),
+        button("Cancel")
+            .on_press(Message::ClosePathRemap)
+            .padding([6, 12])
+            .style(button::secondary),
+        button(text(format!(
+            "Remap {count} Photo{}",
+            if count == 1 { "" } else { "s" }
+        )))
+        .on_press_maybe(form.can_apply().then_some(Message::ApplyPathRemap))
+        .padding([6, 12])
+        .style(button::primary),
+    ]
+    .spacing(8)
+    .align_y(Alignment::Center);
+
+    container(
+        column![
+            text("Remap Folder").size(18),
+            text("Point photos at their new location after moving them to another drive or folder. Edits, ratings and history are kept.")
+                .size(11)
+                .color(MUTED),
+            inputs,
+            summary,
+            scrollable(rows).height(Length::Fixed(200.0)),
+            buttons,
+        ]
+        .spacing(12),
+    )
+    .padding(16)
+    .width(520)
+    .style(|theme: &Theme| container::Style {
+        background: Some(Background::Color(DIALOG_BG)),
+        border: Border {
+            color: theme::border(theme),
+            width: 1.0,
+            radius: 8.0.into(),
+        },
+        ..Default::default()
+    })
+    .into()
+}
@@ -0,0 +1,241 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use iced::widget::{Space, button, checkbox, column, container, row, scrollable, text, text_input};
+use iced::{Alignment, Background, Border, Color, Element, Length, Theme};
+
+use crema_core::image_buf::EditParams;
+use crema_core::preset::{self, GROUPS, ParamGroup, Preset};
+
+use crate::app::Message;
+use crate::theme;
+use crate::widgets::edit_diff::format_value;
+
+const DIALOG_BG: Color = Color::from_rgb(0.12, 0.12, 0.13);
+const MUTED: Color = Color::from_rgb(0.66, 0.66, 0.69);
+
+/// State for the preset editor dialog.
+#[derive(Debug, Clone)]
+pub struct PresetEditor {
+    /// Index of the saved preset being edited; `None` for a new one.
+    pub editing: Option<usize>,
+    pub name: String,
+    included: BTreeSet<&'static str>,
+    /// The edited preset's values, kept for parameters that stay checked.
+    /// Newly checked ones take the current edit's value.
+    saved: BTreeMap<String, f32>,
+}
+
+impl PresetEditor {
+    /// A new preset from the current edit, starting with what it adjusts.
+    pub fn new(params: &EditParams) -> Self {
+        Self {
+            editing: None,
+            name: String::new(),
+            included: preset::adjusted_keys(params).into_iter().collect(),
+            saved: BTreeMap::new(),
+        }
+    }
+
+    pub fn edit(index: usize, preset: &Preset) -> Self {
+        let included = GROUPS
+            .iter()
+            .flat_map(|group| group.keys.iter().copied())
+            .filter(|key| preset.includes(key))
+            .collect();
+        Self {
+            editing: Some(index),
+            name: preset.name.clone(),
+            included,
+            saved: preset.values.clone(),
+        }
+    }
+
+    pub fn is_included(&self, key: &str) -> bool {
+        self.included.contains(key)
+    }
+
+    pub fn toggle(&mut self, key: &'static str) {
+        if !self.included.remove(key) {
+            self.included.insert(key);
+        }
+    }
+
+    /// Include every parameter in the group, or none if all already are.
+    pub fn toggle_group(&mut self, group: &ParamGroup) {
+        if self.group_included(group) {
+            for key in group.keys {
+                self.included.remove(key);
+            }
+        } else {
+            self.included.extend(group.keys.iter().copied());
+        }
+    }
+
+    fn group_included(&self, group: &ParamGroup) -> bool {
+        group.keys.iter().all(|key| self.included.contains(key))
+    }
+
+    /// The value a parameter will be saved with.
+    fn value(&self, key: &str, params: &EditParams) -> Option<f32> {
+        let param = crema_core::edit_diff::find(key)?;
+        Some(
+            self.saved
+                .get(key)
+                .copied()
+                .unwrap_or_else(|| param.value(params)),
+        )
+    }
+
+    pub fn to_preset(&self, params: &EditParams) -> Result<Preset, String> {
+        let name = self.name.trim();
+        if name.is_empty() {
+            return Err("Name the preset".into());
+        }
+        if self.included.is_empty() {
+            return Err("Check at least one setting".into());
+        }
+        let values = self
+            .included
+            .iter()
+            .filter_map(|&key| Some((key.to_string(), self.value(key, params)?)))
+            .collect();
+        Ok(Preset {
+            name: name.to_string(),
+            values,
+        })
+    }
+}
+
+pub fn view<'a>(editor: &'a PresetEditor, params: &EditParams) -> Element<'a, Message> {
+    let mut groups = column![].spacing(10);
+    for (index, group) in GROUPS.iter().enumerate() {
+        let mut entries = column![
+            checkbox(editor.group_included(group))
+                .label(group.label)
+                .text_size(13)
+                .on_toggle(move |_| Message::TogglePresetGroup(index)),
+        ]
+        .spacing(4);
+        for param in group.params() {
+            let value = editor
+                .value(param.key, params)
+                .map(|v| format_value(param, v))
+                .unwrap_or_default();
+            entries = entries.push(
+                row![
+                    Space::new().width(20),
+                    checkbox(editor.is_included(param.key))
+                        .label(param.label)
+                        .text_size(12)
+                        .on_toggle(move |_| Message::TogglePresetParam(param.key)),
+                    Space::new().width(Length::Fill),
+                    text(value).size(11).color(MUTED),
+                ]
+                .align_y(Alignment::Center),
+            );
+        }
+        groups = groups.push(entries);
+    }
+
+    let result = editor.to_preset(params);
+    let hint = match &result {
+        Ok(preset) => format!(
+            "Applies {} setting{} and leaves the rest alone.",
+            preset.values.len(),
+            if preset.values.len() == 1 { "" } else { "s" }
+        ),
+        Err(err) => err.clone(),
+    };
+    let save_label = if editor.editing.is_some() {
+        "Update Preset"
+    } else {
+        "Save Preset"
+    };
+    let buttons = row![
+        text(hint).size(11).color(MUTED),
+        Space::new().width(Length::Fill),
+        button("Cancel")
+            .on_press(Message::ClosePresetEditor)
+            .padding([6, 12])
+            .style(button::secondary),
+        button(save_label)
+            .on_press_maybe(result.is_ok().then_some(Message::SavePreset))
+            .padding([6, 12])
+            .style(button::primary),
+    ]
+    .spacing(8)
+    .align_y(Alignment::Center);
+
+    container(
+        column![
+            text(if editor.editing.is_some() {
+                "Edit Preset"
+            } else {
+                "New Preset"
+            })
+            .size(18),
+            text_input("Preset name", &editor.name)
+                .on_input(Message::PresetNameChanged)
+                .size(13)
+                .padding(6),
+            text("Only checked settings are stored. Applying presets one after another combines them.")
+                .size(11)
+                .color(MUTED),
+            scrollable(groups).height(Length::Fixed(360.0)),
+            buttons,
+        ]
+        .spacing(12),
+    )
+    .padding(16)
+    .width(460)
+    .style(|theme: &Theme| container::Style {
+        background: Some(Background::Color(DIALOG_BG)),
+        border: Border {
+            color: theme::border(theme),
+            width: 1.0,
+            radius: 8.0.into(),
+        },
+        ..Default::default()
+    })
+    .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn editing_keeps_saved_values_and_takes_new_ones_from_the_edit() {
+        let saved = Preset {
+            name: "Warm".into(),
+            values: BTreeMap::from([("wb_temp".into(), 6500.0)]),
+        };
+        let mut editor = PresetEditor::edit(0, &saved);
+        assert!(editor.is_included("wb_temp"));
+
+        let current = EditParams {
+            wb_temp: 4000.0,
+            vibrance: 15.0,
+            ..EditParams::default()
+        };
+        editor.toggle("vibrance");
+        let preset = editor.to_preset(&current).unwrap();
+        assert_eq!(preset.values["wb_temp"], 6500.0);
+        assert_eq!(preset.values["vibrance"], 15.0);
+        assert_eq!(preset.values.len(), 2);
+    }
+
+    #[test]
+    fn group_toggle_checks_all_then_none() {
+        let mut editor = PresetEditor::new(&EditParams::default());
+        editor.name = "Tone".into();
+        assert!(editor.to_preset(&EditParams::default()).is_err());
+
+        let light = &GROUPS[0];
+        editor.toggle("exposure");
+        editor.toggle_group(light);
+        assert!(light.keys.iter().all(|key| editor.is_included(key)));
+        editor.toggle_group(light);
+        assert!(!editor.is_included("exposure"));
+    }
+}
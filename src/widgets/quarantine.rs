@@ -0,0 +1,77 @@
+use std::path::Path;
+
+use iced::widget::{Space, button, column, container, row, scrollable, text};
+use iced::{Alignment, Background, Border, Color, Element, Length, Theme};
+
+use crema_catalog::models::QuarantinedFile;
+
+use crate::app::Message;
+use crate::theme;
+
+const DIALOG_BG: Color = Color::from_rgb(0.12, 0.12, 0.13);
+const MUTED: Color = Color::from_rgb(0.66, 0.66, 0.69);
+
+/// Dialog reviewing files that failed to import or decode, with a retry
+/// for after a codec or app update and a permanent dismiss.
+pub fn view(files: &[QuarantinedFile]) -> Element<'_, Message> {
+    let mut rows = column![].spacing(10);
+    for file in files {
+        let name = Path::new(&file.file_path)
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+        rows = rows.push(
+            row![
+                column![
+                    text(name).size(12),
+                    text(&file.file_path).size(10).color(MUTED),
+                    text(&file.reason).size(11).color(MUTED),
+                ]
+                .spacing(2)
+                .width(Length::Fill),
+                button(text("Retry").size(11))
+                    .on_press(Message::RetryQuarantined(file.id))
+                    .padding([2, 8])
+                    .style(button::secondary),
+                button(text("Dismiss").size(11))
+                    .on_press(Message::DismissQuarantined(file.id))
+                    .padding([2, 8])
+                    .style(button::secondary),
+            ]
+            .spacing(6)
+            .align_y(Alignment::Center),
+        );
+    }
+
+    container(
+        column![
+            text("Quarantine").size(18),
+            text("These files failed to import or decode. Retry after installing codecs or updating, or dismiss them for good.")
+                .size(11)
+                .color(MUTED),
+            scrollable(rows).height(Length::Shrink),
+            row![
+                Space::new().width(Length::Fill),
+                button("Done")
+                    .on_press(Message::CloseQuarantine)
+                    .padding([6, 12])
+                    .style(button::secondary),
+            ],
+        ]
+        .spacing(12),
+    )
+    .padding(16)
+    .width(560)
+    .max_height(520)
+    .style(|theme: &Theme| container::Style {
+        background: Some(Background::Color(DIALOG_BG)),
+        border: Border {
+            color: theme::border(theme),
+            width: 1.0,
+            radius: 8.0.into(),
+        },
+        ..Default::default()
+    })
+    .into()
+}
@@ -0,0 +1,183 @@
+use iced::widget::{button, column, row, text};
+use iced::{Color, Element, Length};
+
+use crema_core::image_buf::EditParams;
+
+use crate::app::Message;
+
+const MUTED: Color = Color::from_rgb(0.66, 0.66, 0.69);
+
+/// White balance presets, as absolute temperature and tint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WbPreset {
+    Daylight,
+    Cloudy,
+    Shade,
+    Tungsten,
+    Fluorescent,
+}
+
+impl WbPreset {
+    pub const ALL: [WbPreset; 5] = [
+        WbPreset::Daylight,
+        WbPreset::Cloudy,
+        WbPreset::Shade,
+        WbPreset::Tungsten,
+        WbPreset::Fluorescent,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            WbPreset::Daylight => "Daylight",
+            WbPreset::Cloudy => "Cloudy",
+            WbPreset::Shade => "Shade",
+            WbPreset::Tungsten => "Tungsten",
+            WbPreset::Fluorescent => "Fluorescent",
+        }
+    }
+
+    /// (temperature in K, tint)
+    pub fn temp_tint(self) -> (f32, f32) {
+        match self {
+            WbPreset::Daylight => (5500.0, 0.0),
+            WbPreset::Cloudy => (6500.0, 10.0),
+            WbPreset::Shade => (7500.0, 10.0),
+            WbPreset::Tungsten => (2850.0, 0.0),
+            WbPreset::Fluorescent => (3800.0, 20.0),
+        }
+    }
+}
+
+/// An adjustment applied on top of each photo's existing edit.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum QuickAdjustment {
+    Exposure(f32),
+    Contrast(f32),
+    WhiteBalance(WbPreset),
+}
+
+impl QuickAdjustment {
+    /// Exposure and contrast are relative and clamp to the develop sliders'
+    /// ranges; white balance presets replace the current values.
+    pub fn apply(self, params: &mut EditParams) {
+        match self {
+            QuickAdjustment::Exposure(delta) => {
+                params.exposure = (params.exposure + delta).clamp(-5.0, 5.0);
+            }
+            QuickAdjustment::Contrast(delta) => {
+                params.contrast = (params.contrast + delta).clamp(-100.0, 100.0);
+            }
+            QuickAdjustment::WhiteBalance(preset) => {
+                (params.wb_temp, params.wb_tint) = preset.temp_tint();
+            }
+        }
+    }
+
+    pub fn describe(self) -> String {
+        match self {
+            QuickAdjustment::Exposure(delta) => format!("{delta:+.2} EV"),
+            QuickAdjustment::Contrast(delta) => format!("{delta:+.0} contrast"),
+            QuickAdjustment::WhiteBalance(preset) => {
+                format!("{} white balance", preset.label())
+            }
+        }
+    }
+}
+
+pub fn view<'a>(enabled: bool) -> Element<'a, Message> {
+    let step = |label: &'a str, adjustment: QuickAdjustment| {
+        button(text(label).size(11))
+            .on_press_maybe(enabled.then_some(Message::QuickDevelop(adjustment)))
+            .padding([4, 0])
+            .width(Length::Fill)
+            .style(button::secondary)
+    };
+
+    let third = 1.0 / 3.0;
+    let exposure = row![
+        step("-1", QuickAdjustment::Exposure(-1.0)),
+        step("-⅓", QuickAdjustment::Exposure(-third)),
+        step("+⅓", QuickAdjustment::Exposure(third)),
+        step("+1", QuickAdjustment::Exposure(1.0)),
+    ]
+    .spacing(4);
+
+    let contrast = row![
+        step("-10", QuickAdjustment::Contrast(-10.0)),
+        step("+10", QuickAdjustment::Contrast(10.0)),
+    ]
+    .spacing(4);
+
+    let mut presets = column![].spacing(4);
+    for pair in WbPreset::ALL.chunks(2) {
+        let mut line = row![].spacing(4);
+        for &preset in pair {
+            line = line.push(step(preset.label(), QuickAdjustment::WhiteBalance(preset)));
+        }
+        presets = presets.push(line);
+    }
+
+    column![
+        text("Exposure").size(12).color(MUTED),
+        exposure,
+        text("Contrast").size(12).color(MUTED),
+        contrast,
+        text("White Balance").size(12).color(MUTED),
+        presets,
+        button(text("Match Exposure to Selected Photo").size(11))
+            .on_press_maybe(enabled.then_some(Message::MatchExposure))
+            .padding([4, 0])
+            .width(Length::Fill)
+            .style(button::secondary),
+        text("Scans").size(12).color(MUTED),
+        button(text("Auto-Crop Scanner Borders").size(11))
+            .on_press_maybe(enabled.then_some(Message::AutoCropScans))
+            .padding([4, 0])
+            .width(Length::Fill)
+            .style(button::secondary),
+    ]
+    .spacing(6)
+    .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exposure_is_relative_and_clamped() {
+        let mut params = EditParams {
+            exposure: 0.5,
+            ..EditParams::default()
+        };
+        QuickAdjustment::Exposure(1.0 / 3.0).apply(&mut params);
+        assert!((params.exposure - 0.8333).abs() < 1e-3);
+
+        params.exposure = 4.9;
+        QuickAdjustment::Exposure(1.0).apply(&mut params);
+        assert_eq!(params.exposure, 5.0);
+    }
+
+    #[test]
+    fn contrast_is_clamped() {
+        let mut params = EditParams {
+            contrast: -95.0,
+            ..EditParams::default()
+        };
+        QuickAdjustment::Contrast(-10.0).apply(&mut params);
+        assert_eq!(params.contrast, -100.0);
+    }
+
+    #[test]
+    fn white_balance_preset_is_absolute() {
+        let mut params = EditParams {
+            wb_temp: 4000.0,
+            wb_tint: -30.0,
+            exposure: 1.0,
+            ..EditParams::default()
+        };
+        QuickAdjustment::WhiteBalance(WbPreset::Shade).apply(&mut params);
+        assert_eq!((params.wb_temp, params.wb_tint), (7500.0, 10.0));
+        assert_eq!(params.exposure, 1.0);
+    }
+}
@@ -0,0 +1,93 @@
+use iced::widget::{Space, button, column, container, image, row, text};
+use iced::{Background, Border, Color, ContentFit, Element, Length, Theme};
+
+use crate::app::Message;
+use crate::theme;
+
+const DIALOG_BG: Color = Color::from_rgb(0.12, 0.12, 0.13);
+const MUTED: Color = Color::from_rgb(0.66, 0.66, 0.69);
+
+/// The current photo rendered by both backends and compared.
+#[derive(Debug, Clone)]
+pub struct RenderConsistency {
+    pub photo: String,
+    pub max_levels: f32,
+    pub mean_levels: f32,
+    pub worst_pixel: (u32, u32),
+    pub differing_fraction: f32,
+    pub heatmap: image::Handle,
+    /// Noise reduction was turned off for the comparison because it only
+    /// runs on the CPU.
+    pub skipped_noise_reduction: bool,
+}
+
+impl RenderConsistency {
+    /// Differences under half a level never change an 8-bit output.
+    pub fn consistent(&self) -> bool {
+        self.max_levels < 0.5
+    }
+}
+
+/// Dialog reporting how far the GPU render strays from the CPU render,
+/// with a heatmap of where.
+pub fn view(check: &RenderConsistency) -> Element<'_, Message> {
+    let verdict = if check.consistent() {
+        "The CPU and GPU renders match at 8 bits."
+    } else if check.max_levels < 2.0 {
+        "Small rounding differences, invisible in normal viewing."
+    } else {
+        "The renders differ visibly. Please include this report with a bug report."
+    };
+    let (x, y) = check.worst_pixel;
+    let mut body = column![
+        text(format!("Render Consistency: {}", check.photo)).size(18),
+        text(verdict).size(12),
+        text(format!(
+            "Max {:.2} levels at ({x}, {y}), mean {:.3}; {:.2}% of pixels round differently.",
+            check.max_levels,
+            check.mean_levels,
+            check.differing_fraction * 100.0
+        ))
+        .size(11)
+        .color(MUTED),
+        container(
+            image(check.heatmap.clone())
+                .content_fit(ContentFit::Contain)
+                .width(Length::Fill)
+                .height(Length::Fill),
+        )
+        .height(320),
+        text("Black where the renders agree, through blue and yellow to red at 4 levels or more.")
+            .size(11)
+            .color(MUTED),
+    ]
+    .spacing(12);
+    if check.skipped_noise_reduction {
+        body = body.push(
+            text("Noise reduction runs on the CPU only and was left out of the comparison.")
+                .size(11)
+                .color(MUTED),
+        );
+    }
+    body = body.push(row![
+        Space::new().width(Length::Fill),
+        button("Done")
+            .on_press(Message::CloseRenderConsistency)
+            .padding([6, 12])
+            .style(button::secondary),
+    ]);
+
+    container(body)
+        .padding(16)
+        .width(480)
+        .style(|theme: &Theme| container::Style {
+            background: Some(Background::Color(DIALOG_BG)),
+            border: Border {
+                color: theme::border(theme),
+                width: 1.0,
+                radius: 8.0.into(),
+            },
+            ..Default::default()
+        })
+        .into()
+}
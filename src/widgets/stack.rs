@@ -0,0 +1,65 @@
+use iced::widget::{Space, button, column, container, row, text};
+use iced::{Background, Border, Color, Element, Length, Theme};
+
+use crema_core::compositing::StackMethod;
+
+use crate::app::Message;
+use crate::theme;
+
+const DIALOG_BG: Color = Color::from_rgb(0.12, 0.12, 0.13);
+const MUTED: Color = Color::from_rgb(0.66, 0.66, 0.69);
+
+/// Dialog for choosing how the selected frames are combined.
+pub fn view(frame_count: usize) -> Element<'static, Message> {
+    let mut choices = column![].spacing(6);
+    for method in StackMethod::ALL {
+        let hint = match method {
+            StackMethod::SigmaClippedMean => "Removes satellite and plane trails",
+            StackMethod::Mean => "Smoothest result, keeps trails",
+            StackMethod::Median => "Robust with few frames",
+        };
+        choices = choices.push(
+            button(
+                column![
+                    text(method.label()).size(12),
+                    text(hint).size(10).color(MUTED),
+                ]
+                .spacing(2),
+            )
+            .on_press(Message::StackPhotos(method))
+            .padding([6, 12])
+            .width(Length::Fill)
+            .style(button::secondary),
+        );
+    }
+
+    container(
+        column![
+            text(format!("Stack {frame_count} Photos")).size(18),
+            text("Frames are aligned to the first and averaged into a new 16-bit TIFF added to the library.")
+                .size(11)
+                .color(MUTED),
+            choices,
+            row![
+                Space::new().width(Length::Fill),
+                button("Cancel")
+                    .on_press(Message::CloseStack)
+                    .padding([6, 12])
+                    .style(button::secondary),
+            ],
+        ]
+        .spacing(12),
+    )
+    .padding(16)
+    .width(340)
+    .style(|theme: &Theme| container::Style {
+        background: Some(Background::Color(DIALOG_BG)),
+        border: Border {
+            color: theme::border(theme),
+            width: 1.0,
+            radius: 8.0.into(),
+        },
+        ..Default::default()
+    })
+    .into()
+}
@@ -1,30 +1,71 @@
 use std::collections::{HashMap, HashSet};
 
 use iced::widget::{Space, button, column, container, image, responsive, row, text};
-use iced::{Background, Border, Color, Element, Length, Shadow, Theme};
+use iced::{Background, Border, Color, ContentFit, Element, Length, Shadow, Theme};
+use serde::{Deserialize, Serialize};
 
-use crema_catalog::models::{Photo, PhotoId};
+use crema_catalog::models::{Animation, Photo, PhotoId, PhotoStack};
 
 use crate::app::Message;
+use crate::theme;
 
 const TARGET_WIDTH: f32 = 210.0;
 const MIN_WIDTH: f32 = 170.0;
 const MAX_WIDTH: f32 = 240.0;
+const SPACING: f32 = 12.0;
+/// Horizontal space a card adds around its thumbnail.
+const CARD_PADDING: f32 = 10.0;
+/// Thumbnail height for square cells, as a fraction of the cell width.
+const CELL_RATIO: f32 = 0.72;
+/// Assumed width/height until a thumbnail or EXIF dimensions are known.
+const FALLBACK_ASPECT: f32 = 1.5;
 const CARD_BG: Color = Color::from_rgb(0.11, 0.11, 0.12);
 const CARD_HOVER: Color = Color::from_rgb(0.14, 0.14, 0.16);
 const CARD_SELECTED: Color = Color::from_rgb(0.16, 0.20, 0.28);
 const CARD_MULTI_SELECTED: Color = Color::from_rgb(0.13, 0.17, 0.24);
-const BORDER: Color = Color::from_rgb(0.20, 0.20, 0.22);
-const ACCENT: Color = Color::from_rgb(0.26, 0.52, 0.94);
-const MULTI_ACCENT: Color = Color::from_rgb(0.20, 0.42, 0.78);
 const MUTED: Color = Color::from_rgb(0.66, 0.66, 0.69);
 const REJECTED: Color = Color::from_rgb(0.87, 0.43, 0.38);
+/// Thumbnails of photos whose drive is unplugged are dimmed to this.
+const OFFLINE_OPACITY: f32 = 0.4;
 
+/// How thumbnails are arranged in the library grid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GridLayout {
+    /// Uniform cells, thumbnails cropped to fill them.
+    Square,
+    /// Uniform cells, thumbnails letterboxed to show the whole frame.
+    #[default]
+    Fit,
+    /// Rows of equal height where each thumbnail keeps its aspect ratio
+    /// and every full row spans the grid width.
+    Justified,
+}
+
+impl GridLayout {
+    pub const ALL: [GridLayout; 3] = [GridLayout::Square, GridLayout::Fit, GridLayout::Justified];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            GridLayout::Square => "Square",
+            GridLayout::Fit => "Fit",
+            GridLayout::Justified => "Justified",
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 pub fn view<'a>(
     photos: Vec<&'a Photo>,
     thumbnails: &'a HashMap<PhotoId, iced::widget::image::Handle>,
+    aspects: &'a HashMap<PhotoId, f32>,
+    animations: &'a HashMap<PhotoId, Animation>,
+    stacks: &'a HashMap<PhotoId, PhotoStack>,
+    expanded_stacks: &'a HashSet<PhotoId>,
+    offline: &'a HashSet<PhotoId>,
     selected: Option<PhotoId>,
     multi_selected: &'a HashSet<PhotoId>,
+    layout: GridLayout,
 ) -> Element<'a, Message> {
     if photos.is_empty() {
         return container(
@@ -43,6 +84,25 @@ pub fn view<'a>(
 
     responsive(move |size| {
         let available = (size.width - 24.0).max(MIN_WIDTH);
+        if layout == GridLayout::Justified {
+            return justified_view(
+                &photos,
+                thumbnails,
+                aspects,
+                animations,
+                stacks,
+                expanded_stacks,
+                offline,
+                selected,
+                multi_selected,
+                available,
+            );
+        }
+        let fit = if layout == GridLayout::Fit {
+            ContentFit::Contain
+        } else {
+            ContentFit::Cover
+        };
         let columns = (available / TARGET_WIDTH).floor().max(1.0) as usize;
         let cell_width = (available / columns as f32).clamp(MIN_WIDTH, MAX_WIDTH);
 
@@ -51,15 +111,26 @@ pub fn view<'a>(
 
         for photo in &photos {
             current_row.push(photo_cell(
-                photo,
-                thumbnails.get(&photo.id),
+                Cell {
+                    photo,
+                    thumbnail: thumbnails.get(&photo.id),
+                    animation: animations.get(&photo.id),
+                    stack: stack_badge(photo.id, stacks, expanded_stacks),
+                    offline: offline.contains(&photo.id),
+                },
                 selected,
                 multi_selected,
                 cell_width,
+                cell_width * CELL_RATIO,
+                fit,
             ));
 
             if current_row.len() == columns {
-                grid_rows.push(row(std::mem::take(&mut current_row)).spacing(12).into());
+                grid_rows.push(
+                    row(std::mem::take(&mut current_row))
+                        .spacing(SPACING)
+                        .into(),
+                );
             }
         }
 
@@ -67,21 +138,137 @@ pub fn view<'a>(
             while current_row.len() < columns {
                 current_row.push(Space::new().width(cell_width).into());
             }
-            grid_rows.push(row(std::mem::take(&mut current_row)).spacing(12).into());
+            grid_rows.push(
+                row(std::mem::take(&mut current_row))
+                    .spacing(SPACING)
+                    .into(),
+            );
         }
 
-        column(grid_rows).spacing(12).into()
+        column(grid_rows).spacing(SPACING).into()
     })
     .into()
 }
 
-fn photo_cell<'a>(
+#[allow(clippy::too_many_arguments)]
+fn justified_view<'a>(
+    photos: &[&'a Photo],
+    thumbnails: &'a HashMap<PhotoId, iced::widget::image::Handle>,
+    aspects: &HashMap<PhotoId, f32>,
+    animations: &'a HashMap<PhotoId, Animation>,
+    stacks: &HashMap<PhotoId, PhotoStack>,
+    expanded_stacks: &HashSet<PhotoId>,
+    offline: &HashSet<PhotoId>,
+    selected: Option<PhotoId>,
+    multi_selected: &HashSet<PhotoId>,
+    available: f32,
+) -> Element<'a, Message> {
+    let ratios: Vec<f32> = photos.iter().map(|p| aspect(p, aspects)).collect();
+    let target_height = TARGET_WIDTH * CELL_RATIO;
+    let rows = justify(&ratios, available, target_height);
+
+    let mut grid_rows: Vec<Element<'a, Message>> = Vec::with_capacity(rows.len());
+    let mut start = 0;
+    for (count, height) in rows {
+        let cells = (start..start + count).map(|i| {
+            let photo = photos[i];
+            photo_cell(
+                Cell {
+                    photo,
+                    thumbnail: thumbnails.get(&photo.id),
+                    animation: animations.get(&photo.id),
+                    stack: stack_badge(photo.id, stacks, expanded_stacks),
+                    offline: offline.contains(&photo.id),
+                },
+                selected,
+                multi_selected,
+                (height * ratios[i]).floor(),
+                height,
+                ContentFit::Cover,
+            )
+        });
+        grid_rows.push(row(cells).spacing(SPACING).into());
+        start += count;
+    }
+
+    column(grid_rows).spacing(SPACING).into()
+}
+
+/// Width over height of a photo's thumbnail, falling back to its EXIF
+/// dimensions and then to 3:2. Extreme panoramas are clamped so a single
+/// frame can't collapse a justified row.
+fn aspect(photo: &Photo, aspects: &HashMap<PhotoId, f32>) -> f32 {
+    let ratio = aspects.get(&photo.id).copied().or_else(|| {
+        let (w, h) = (photo.width?, photo.height?);
+        (w > 0 && h > 0).then(|| w as f32 / h as f32)
+    });
+    ratio.unwrap_or(FALLBACK_ASPECT).clamp(0.25, 4.0)
+}
+
+/// Split thumbnails with the given aspect ratios into rows, returning each
+/// row's length and thumbnail height. A row takes photos until it would be
+/// at least `available` wide at `target_height`, then its height is scaled
+/// so the row fills the width exactly. The last row keeps the target
+/// height rather than stretching a few thumbnails across the grid.
+fn justify(ratios: &[f32], available: f32, target_height: f32) -> Vec<(usize, f32)> {
+    let chrome = 2.0 * CARD_PADDING;
+    let mut rows = Vec::new();
+    let mut start = 0;
+    let mut ratio_sum = 0.0;
+    for (i, &ratio) in ratios.iter().enumerate() {
+        ratio_sum += ratio;
+        let count = i + 1 - start;
+        let fixed = count as f32 * chrome + (count - 1) as f32 * SPACING;
+        if ratio_sum * target_height + fixed >= available {
+            let height = ((available - fixed) / ratio_sum).max(1.0);
+            rows.push((count, height));
+            start = i + 1;
+            ratio_sum = 0.0;
+        }
+    }
+    if start < ratios.len() {
+        rows.push((ratios.len() - start, target_height));
+    }
+    rows
+}
+
+/// Size of the stack `id` is the cover of, and whether it's expanded.
+fn stack_badge(
+    id: PhotoId,
+    stacks: &HashMap<PhotoId, PhotoStack>,
+    expanded_stacks: &HashSet<PhotoId>,
+) -> Option<(usize, bool)> {
+    stacks
+        .get(&id)
+        .map(|stack| (stack.members.len(), expanded_stacks.contains(&id)))
+}
+
+/// One photo in the grid and what its cell shows about it.
+struct Cell<'a> {
     photo: &'a Photo,
     thumbnail: Option<&'a iced::widget::image::Handle>,
+    animation: Option<&'a Animation>,
+    /// Size of the stack the photo is the cover of, and whether it's
+    /// expanded.
+    stack: Option<(usize, bool)>,
+    offline: bool,
+}
+
+fn photo_cell<'a>(
+    cell: Cell<'a>,
     selected: Option<PhotoId>,
     multi_selected: &HashSet<PhotoId>,
     width: f32,
+    thumb_height: f32,
+    fit: ContentFit,
 ) -> Element<'a, Message> {
+    let Cell {
+        photo,
+        thumbnail,
+        animation,
+        stack,
+        offline,
+    } = cell;
     let is_primary = selected == Some(photo.id);
     let is_multi = multi_selected.contains(&photo.id);
     let is_selected = is_primary && !is_multi;
@@ -91,23 +278,28 @@ fn photo_cell<'a>(
         .to_string_lossy()
         .to_string();
 
-    let thumb_height = width * 0.72;
     let thumb_content: Element<'a, Message> = if let Some(handle) = thumbnail {
         image(handle.clone())
             .width(width)
             .height(thumb_height)
-            .content_fit(iced::ContentFit::Cover)
+            .content_fit(fit)
+            .opacity(if offline { OFFLINE_OPACITY } else { 1.0 })
             .into()
     } else {
-        container(text("Loading thumbnail").size(11).color(MUTED))
+        let placeholder = if offline {
+            "Drive offline"
+        } else {
+            "Loading thumbnail"
+        };
+        container(text(placeholder).size(11).color(MUTED))
             .width(width)
             .height(thumb_height)
             .center_x(width)
             .center_y(thumb_height)
-            .style(|_theme: &Theme| container::Style {
+            .style(|theme: &Theme| container::Style {
                 background: Some(Background::Color(Color::from_rgb(0.13, 0.13, 0.14))),
                 border: Border {
-                    color: BORDER,
+                    color: theme::border(theme),
                     width: 1.0,
                     radius: 6.0.into(),
                 },
@@ -130,9 +322,35 @@ fn photo_cell<'a>(
     let rejected_label = (photo.rating < 0).then_some("Rejected");
 
     let mut info_row = row![text(date_label).size(11).color(MUTED)].spacing(6);
+    if offline {
+        info_row = info_row.push(text("Offline").size(11).color(MUTED));
+    }
+    if let Some(animation) = animation {
+        info_row = info_row.push(
+            text(format!("\u{25B6} {} frames", animation.frame_count))
+                .size(11)
+                .style(text::primary),
+        );
+    }
+    if let Some((count, expanded)) = stack {
+        info_row = info_row.push(
+            button(text(format!("\u{29C9} {count}")).size(11))
+                .on_press(Message::ToggleStackExpanded(photo.id))
+                .padding([0, 4])
+                .style(button::text),
+        );
+        if expanded {
+            info_row = info_row.push(
+                button(text("Unstack").size(11))
+                    .on_press(Message::Unstack(photo.id))
+                    .padding([0, 4])
+                    .style(button::text),
+            );
+        }
+    }
     if !rating_label.is_empty() {
         info_row = info_row.push(Space::new().width(Length::Fill));
-        info_row = info_row.push(text(rating_label).size(11).color(ACCENT));
+        info_row = info_row.push(text(rating_label).size(11).style(text::primary));
     } else if let Some(label) = rejected_label {
         info_row = info_row.push(Space::new().width(Length::Fill));
         info_row = info_row.push(text(label).size(11).color(REJECTED));
@@ -145,7 +363,7 @@ fn photo_cell<'a>(
             .on_press(Message::SelectPhoto(photo.id))
             .padding(0)
             .width(width)
-            .style(move |_theme: &Theme, status| thumb_button_style(status, highlight)),
+            .style(move |theme: &Theme, status| thumb_button_style(theme, status, highlight)),
         text(filename).size(12),
         info_row,
     ]
@@ -155,7 +373,11 @@ fn photo_cell<'a>(
     if is_multi {
         card = card.push(
             row![
-                text("\u{2713} Selected").size(11).color(MULTI_ACCENT),
+                text("\u{2713} Selected")
+                    .size(11)
+                    .style(|theme: &Theme| text::Style {
+                        color: Some(multi_accent(theme)),
+                    }),
                 Space::new().width(Length::Fill),
             ]
             .align_y(iced::Alignment::Center),
@@ -163,12 +385,12 @@ fn photo_cell<'a>(
     } else if is_selected {
         card = card.push(
             row![
-                text("Selected").size(11).color(ACCENT),
+                text("Selected").size(11).style(text::primary),
                 Space::new().width(Length::Fill),
                 button("Develop")
                     .on_press(Message::OpenPhoto(photo.id))
                     .padding([3, 8])
-                    .style(move |_theme: &Theme, status| open_button_style(status)),
+                    .style(open_button_style),
             ]
             .align_y(iced::Alignment::Center),
         );
@@ -182,23 +404,23 @@ fn photo_cell<'a>(
         CARD_BG
     };
 
-    let border_color = if is_selected {
-        ACCENT
-    } else if is_multi {
-        MULTI_ACCENT
-    } else {
-        BORDER
-    };
-
-    let border_width = if is_multi { 2.0 } else { 1.0 };
-
     container(card)
         .padding(10)
-        .style(move |_theme: &Theme| container::Style {
+        .style(move |theme: &Theme| container::Style {
             background: Some(Background::Color(bg_color)),
             border: Border {
-                color: border_color,
-                width: border_width,
+                color: if is_selected {
+                    theme::accent(theme)
+                } else if is_multi {
+                    multi_accent(theme)
+                } else {
+                    theme::border(theme)
+                },
+                width: match (is_selected, is_multi) {
+                    (_, true) => theme::ring_width(theme, 2.0),
+                    (true, false) => theme::ring_width(theme, 1.0),
+                    _ => 1.0,
+                },
                 radius: 8.0.into(),
             },
             ..Default::default()
@@ -206,7 +428,11 @@ fn photo_cell<'a>(
         .into()
 }
 
-fn thumb_button_style(status: button::Status, selected: bool) -> button::Style {
+fn multi_accent(theme: &Theme) -> Color {
+    theme::shade(theme::accent(theme), 0.8)
+}
+
+fn thumb_button_style(theme: &Theme, status: button::Status, selected: bool) -> button::Style {
     let background = match status {
         button::Status::Hovered => CARD_HOVER,
         button::Status::Pressed => Color::from_rgb(0.12, 0.12, 0.14),
@@ -218,8 +444,16 @@ fn thumb_button_style(status: button::Status, selected: bool) -> button::Style {
         background: Some(Background::Color(background)),
         text_color: Color::WHITE,
         border: Border {
-            color: if selected { ACCENT } else { BORDER },
-            width: 1.0,
+            color: if selected {
+                theme::accent(theme)
+            } else {
+                theme::border(theme)
+            },
+            width: if selected {
+                theme::ring_width(theme, 1.0)
+            } else {
+                1.0
+            },
             radius: 6.0.into(),
         },
         shadow: Shadow::default(),
@@ -227,16 +461,17 @@ fn thumb_button_style(status: button::Status, selected: bool) -> button::Style {
     }
 }
 
-fn open_button_style(status: button::Status) -> button::Style {
+fn open_button_style(theme: &Theme, status: button::Status) -> button::Style {
+    let accent = theme::accent(theme);
     let background = match status {
-        button::Status::Hovered => Color::from_rgb(0.30, 0.56, 0.98),
-        button::Status::Pressed => Color::from_rgb(0.21, 0.44, 0.82),
-        _ => ACCENT,
+        button::Status::Hovered => theme::shade(accent, 1.08),
+        button::Status::Pressed => theme::shade(accent, 0.85),
+        _ => accent,
     };
 
     button::Style {
         background: Some(Background::Color(background)),
-        text_color: Color::WHITE,
+        text_color: theme::on_accent(theme),
         border: Border {
             color: background,
             width: 1.0,
@@ -246,3 +481,42 @@ fn open_button_style(status: button::Status) -> button::Style {
         snap: false,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row_width(ratios: &[f32], height: f32) -> f32 {
+        let n = ratios.len() as f32;
+        ratios.iter().sum::<f32>() * height + n * 2.0 * CARD_PADDING + (n - 1.0) * SPACING
+    }
+
+    #[test]
+    fn justified_rows_fill_the_width() {
+        let ratios = [1.5, 0.67, 1.5, 1.0, 0.67, 1.78, 1.5, 1.5, 0.67];
+        let rows = justify(&ratios, 900.0, 150.0);
+        assert_eq!(rows.iter().map(|r| r.0).sum::<usize>(), ratios.len());
+
+        let mut start = 0;
+        for &(count, height) in &rows[..rows.len() - 1] {
+            let width = row_width(&ratios[start..start + count], height);
+            assert!((width - 900.0).abs() < 0.01, "{width}");
+            assert!(height <= 150.0);
+            start += count;
+        }
+    }
+
+    #[test]
+    fn last_row_keeps_target_height() {
+        let rows = justify(&[1.5, 1.5, 1.5, 0.67], 700.0, 150.0);
+        assert_eq!(rows.last().unwrap().1, 150.0);
+        assert!(justify(&[], 700.0, 150.0).is_empty());
+    }
+
+    #[test]
+    fn single_wide_panorama_gets_its_own_row() {
+        let rows = justify(&[4.0, 1.0], 500.0, 150.0);
+        assert_eq!(rows[0].0, 1);
+        assert!((row_width(&[4.0], rows[0].1) - 500.0).abs() < 0.01);
+    }
+}
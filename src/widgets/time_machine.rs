@@ -0,0 +1,211 @@
+use std::collections::HashMap;
+
+use iced::widget::{Space, button, column, container, row, scrollable, text};
+use iced::{Alignment, Background, Border, Color, Element, Length, Theme};
+
+use crema_catalog::models::{PastState, Photo, PhotoId};
+
+use crate::app::Message;
+use crate::theme;
+use crate::widgets::edit_diff::format_value;
+
+const DIALOG_BG: Color = Color::from_rgb(0.12, 0.12, 0.13);
+const MUTED: Color = Color::from_rgb(0.66, 0.66, 0.69);
+
+/// The library as it stood at the end of a past day, browsed read-only.
+#[derive(Debug, Clone)]
+pub struct TimeMachine {
+    /// The day browsed, `YYYY-MM-DD`.
+    pub date: String,
+    /// Photos imported by then, carrying the ratings they had.
+    pub photos: Vec<Photo>,
+    pub then: HashMap<PhotoId, PastState>,
+    pub now: HashMap<PhotoId, PastState>,
+}
+
+impl TimeMachine {
+    /// Overlay `then` on the current library: photos imported later drop
+    /// out and the rest take their old ratings.
+    pub fn new(date: String, current: &[Photo], then: Vec<PastState>, now: Vec<PastState>) -> Self {
+        let then: HashMap<PhotoId, PastState> = then.into_iter().map(|s| (s.photo_id, s)).collect();
+        let photos = current
+            .iter()
+            .filter_map(|photo| {
+                let state = then.get(&photo.id)?;
+                Some(Photo {
+                    rating: state.rating,
+                    ..photo.clone()
+                })
+            })
+            .collect();
+        Self {
+            date,
+            photos,
+            then,
+            now: now.into_iter().map(|s| (s.photo_id, s)).collect(),
+        }
+    }
+}
+
+/// The last moment of the local day `date`.
+pub fn end_of_day(date: &str) -> String {
+    format!("{date} 23:59:59")
+}
+
+/// Dialog listing the days on which ratings or edits changed.
+pub fn picker(dates: &[String]) -> Element<'_, Message> {
+    let body: Element<'_, Message> = if dates.is_empty() {
+        text("No ratings or edits have changed yet.")
+            .size(12)
+            .color(MUTED)
+            .into()
+    } else {
+        let mut rows = column![].spacing(6);
+        for date in dates {
+            rows = rows.push(
+                row![
+                    text(date).size(13).width(Length::Fill),
+                    button(text("Browse").size(11))
+                        .on_press(Message::BrowseAsOf(date.clone()))
+                        .padding([2, 8])
+                        .style(button::secondary),
+                ]
+                .align_y(Alignment::Center),
+            );
+        }
+        scrollable(rows).height(Length::Shrink).into()
+    };
+
+    container(
+        column![
+            text("Browse Library As Of").size(18),
+            text("See ratings and edits as they were at the end of a day, to recover from a change you regret.")
+                .size(11)
+                .color(MUTED),
+            body,
+            row![
+                Space::new().width(Length::Fill),
+                button("Cancel")
+                    .on_press(Message::CloseTimeMachinePicker)
+                    .padding([6, 12])
+                    .style(button::secondary),
+            ],
+        ]
+        .spacing(12),
+    )
+    .padding(16)
+    .width(360)
+    .max_height(480)
+    .style(|theme: &Theme| container::Style {
+        background: Some(Background::Color(DIALOG_BG)),
+        border: Border {
+            color: theme::border(theme),
+            width: 1.0,
+            radius: 8.0.into(),
+        },
+        ..Default::default()
+    })
+    .into()
+}
+
+/// Side panel shown in place of Quick Develop while browsing the past:
+/// how the selected photo has changed since, and ways out.
+pub fn panel<'a>(
+    machine: &'a TimeMachine,
+    selected: Option<PhotoId>,
+    selection_count: usize,
+) -> Element<'a, Message> {
+    let mut content = column![
+        text(format!("As of {}", machine.date)).size(16),
+        text("Read-only. Ratings and edits are shown as they were at the end of that day.")
+            .size(11)
+            .color(MUTED),
+    ]
+    .spacing(10);
+
+    if let Some(id) = selected
+        && let (Some(then), Some(now)) = (machine.then.get(&id), machine.now.get(&id))
+    {
+        content = content.push(
+            text(format!(
+                "Rating: {} then, {} now",
+                rating_label(then.rating),
+                rating_label(now.rating)
+            ))
+            .size(12),
+        );
+        let diffs = crema_core::edit_diff::diff(&then.params, &now.params);
+        if diffs.is_empty() {
+            content = content.push(text("Edits unchanged since.").size(12).color(MUTED));
+        }
+        for d in diffs {
+            content = content.push(
+                column![
+                    text(d.param.label).size(12),
+                    text(format!(
+                        "{} then, {} now",
+                        format_value(d.param, d.left),
+                        format_value(d.param, d.right)
+                    ))
+                    .size(11)
+                    .color(MUTED),
+                ]
+                .spacing(2),
+            );
+        }
+    }
+
+    content = content.push(
+        button(text(match selection_count {
+            0 | 1 => "Restore Selected".to_string(),
+            n => format!("Restore {n} Selected"),
+        }))
+        .on_press_maybe((selection_count > 0).then_some(Message::RestoreFromTimeMachine))
+        .padding([6, 12])
+        .style(button::primary),
+    );
+    content = content.push(
+        button("Return to Today")
+            .on_press(Message::LeaveTimeMachine)
+            .padding([6, 12])
+            .style(button::secondary),
+    );
+    content.into()
+}
+
+fn rating_label(rating: i32) -> String {
+    match rating {
+        -1 => "rejected".into(),
+        0 => "unrated".into(),
+        n => "\u{2605}".repeat(n as usize),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn photo(id: PhotoId, rating: i32) -> Photo {
+        Photo {
+            id,
+            file_path: format!("/photos/{id}.jpg"),
+            imported_at: "2024-01-01".into(),
+            rating,
+            ..Photo::default()
+        }
+    }
+
+    #[test]
+    fn overlay_drops_later_imports_and_restores_ratings() {
+        let current = [photo(1, 1), photo(2, 1)];
+        let then = vec![PastState {
+            photo_id: 1,
+            rating: 4,
+            params: Default::default(),
+        }];
+        let machine = TimeMachine::new("2024-02-01".into(), &current, then, Vec::new());
+        assert_eq!(machine.photos.len(), 1);
+        assert_eq!((machine.photos[0].id, machine.photos[0].rating), (1, 4));
+        assert_eq!(end_of_day(&machine.date), "2024-02-01 23:59:59");
+    }
+}
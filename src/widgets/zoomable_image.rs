@@ -1,6 +1,11 @@
-use iced::mouse;
+use std::time::{Duration, Instant};
+
+use iced::advanced::mouse::{Click, click};
 use iced::widget::canvas::{self, Action, Event, Frame, Path, Stroke};
 use iced::{Color, Element, Length, Point, Rectangle, Renderer, Size, Theme, Vector};
+use iced::{keyboard, mouse, touch};
+
+use crema_core::annotation::{self, Annotation, FrameMap};
 
 use crate::app::Message;
 
@@ -8,6 +13,21 @@ const MIN_ZOOM: f32 = 1.0;
 const ZOOM_STEP: f32 = 1.15;
 const HANDLE_RADIUS: f32 = 6.0;
 const GRAB_RADIUS: f32 = 14.0;
+/// Drags shorter than this, in screen pixels, are clicks and draw nothing.
+const MIN_SKETCH: f32 = 6.0;
+const ARROW_HEAD: f32 = 14.0;
+const MARK_WIDTH: f32 = 2.5;
+const NOTE_RADIUS: f32 = 5.0;
+/// How far a swipe travels, in screen pixels, to move to the next photo.
+const SWIPE_DISTANCE: f32 = 120.0;
+/// A gap in trackpad scrolling this long ends one swipe, so momentum
+/// after a page turn doesn't turn the next.
+const SWIPE_PAUSE: Duration = Duration::from_millis(250);
+/// Touches that move less than this, in screen pixels, are taps.
+const TAP_SLOP: f32 = 10.0;
+/// Double-tapping an image that already shows at 100% or more when fit
+/// zooms by this much instead.
+const SMALL_IMAGE_ZOOM: f32 = 2.0;
 
 #[derive(Clone, Debug)]
 pub struct ZoomState {
@@ -47,6 +67,24 @@ pub struct CropOverlay {
     pub aspect: Option<f32>,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnnotationTool {
+    Arrow,
+    Rect,
+    Note,
+}
+
+/// Annotations drawn over the developed preview. `map` carries their
+/// source-frame points into the preview, which shows the developed frame.
+#[derive(Clone, Debug)]
+pub struct AnnotationLayer {
+    pub items: Vec<Annotation>,
+    pub map: FrameMap,
+    pub tool: Option<AnnotationTool>,
+    /// Where the note being typed will go.
+    pub pending_note: Option<[f32; 2]>,
+}
+
 #[derive(Clone, Copy, Debug)]
 enum CropHandle {
     TopLeft,
@@ -61,6 +99,7 @@ struct ZoomableImage {
     image_size: Size,
     zoom_state: ZoomState,
     crop: Option<CropOverlay>,
+    annotations: Option<AnnotationLayer>,
 }
 
 #[derive(Default)]
@@ -68,6 +107,85 @@ pub struct CanvasState {
     dragging: bool,
     last_cursor: Option<Point>,
     crop_handle: Option<CropHandle>,
+    /// Start and end of the arrow or box being drawn.
+    sketch: Option<(Point, Point)>,
+    modifiers: keyboard::Modifiers,
+    /// The last click or tap, to spot doubles.
+    click: Option<Click>,
+    swipe: SwipeTracker,
+    /// Fingers on the screen and where each is, relative to the canvas.
+    fingers: Vec<(touch::Finger, Point)>,
+    /// Where a lone finger went down, while it could still be a tap or a
+    /// swipe. A second finger makes it a pinch instead.
+    touch_start: Option<Point>,
+}
+
+/// Horizontal trackpad scrolling at fit zoom, which moves to the next or
+/// previous photo once it travels far enough.
+#[derive(Debug, Default)]
+struct SwipeTracker {
+    travel: f32,
+    /// This swipe has already moved to another photo.
+    spent: bool,
+    last: Option<Instant>,
+}
+
+impl SwipeTracker {
+    fn scroll(&mut self, dx: f32, now: Instant) -> Option<Message> {
+        if self
+            .last
+            .is_none_or(|last| now.saturating_duration_since(last) > SWIPE_PAUSE)
+        {
+            self.travel = 0.0;
+            self.spent = false;
+        }
+        self.last = Some(now);
+        if self.spent {
+            return None;
+        }
+        self.travel += dx;
+        let message = swipe_message(self.travel, 0.0)?;
+        self.spent = true;
+        Some(message)
+    }
+}
+
+/// The photo to move to for a swipe that traveled `(dx, dy)`: content
+/// dragged left brings in the next photo, as when turning a page.
+fn swipe_message(dx: f32, dy: f32) -> Option<Message> {
+    if dx.abs() < SWIPE_DISTANCE || dx.abs() < dy.abs() * 2.0 {
+        None
+    } else if dx < 0.0 {
+        Some(Message::NextPhoto)
+    } else {
+        Some(Message::PrevPhoto)
+    }
+}
+
+/// How two fingers moving from `from` to `to` zoom and pan: the zoom
+/// factor, how far their midpoint moved and where it is now.
+fn pinch(from: [Point; 2], to: [Point; 2]) -> (f32, Vector, Point) {
+    let midpoint = |[a, b]: [Point; 2]| Point::new((a.x + b.x) / 2.0, (a.y + b.y) / 2.0);
+    let spread = from[0].distance(from[1]);
+    let factor = if spread > 0.0 {
+        to[0].distance(to[1]) / spread
+    } else {
+        1.0
+    };
+    let center = midpoint(to);
+    (factor, center - midpoint(from), center)
+}
+
+/// The zoom at which one pixel of an image `image` pixels in size fills
+/// one screen pixel of a `viewport` it's fit to.
+pub fn actual_size_zoom(image: Size, viewport: Size) -> f32 {
+    let fit_scale = (viewport.width / image.width).min(viewport.height / image.height);
+    let zoom = 1.0 / fit_scale;
+    if zoom > MIN_ZOOM * 1.01 {
+        zoom
+    } else {
+        SMALL_IMAGE_ZOOM
+    }
 }
 
 impl ZoomableImage {
@@ -241,6 +359,136 @@ impl ZoomableImage {
         }
     }
 
+    /// Screen position of a source-frame point.
+    fn to_screen(dest: &Rectangle, map: &FrameMap, point: [f32; 2]) -> Point {
+        let [x, y] = map.to_output(point);
+        Point::new(dest.x + x * dest.width, dest.y + y * dest.height)
+    }
+
+    /// Source-frame point under a screen position.
+    fn to_source(dest: &Rectangle, map: &FrameMap, point: Point) -> [f32; 2] {
+        map.to_source([
+            (point.x - dest.x) / dest.width,
+            (point.y - dest.y) / dest.height,
+        ])
+    }
+
+    fn update_annotation(
+        &self,
+        state: &mut CanvasState,
+        event: &Event,
+        bounds: Rectangle,
+        cursor_pos: Point,
+    ) -> Option<Action<Message>> {
+        let layer = self.annotations.as_ref()?;
+        let tool = layer.tool?;
+        let dest = self.image_dest(bounds);
+
+        match event {
+            Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left)) => {
+                if !dest.contains(cursor_pos) {
+                    return None;
+                }
+                if tool == AnnotationTool::Note {
+                    let at = Self::to_source(&dest, &layer.map, cursor_pos);
+                    return Some(Action::publish(Message::PlaceNote(at)).and_capture());
+                }
+                state.sketch = Some((cursor_pos, cursor_pos));
+                Some(Action::request_redraw().and_capture())
+            }
+
+            Event::Mouse(mouse::Event::CursorMoved { .. }) => {
+                let (start, _) = state.sketch?;
+                state.sketch = Some((start, cursor_pos));
+                Some(Action::request_redraw().and_capture())
+            }
+
+            Event::Mouse(mouse::Event::ButtonReleased(mouse::Button::Left)) => {
+                let (start, end) = state.sketch.take()?;
+                if start.distance(end) < MIN_SKETCH {
+                    return Some(Action::request_redraw().and_capture());
+                }
+                let from = Self::to_source(&dest, &layer.map, start);
+                let to = Self::to_source(&dest, &layer.map, end);
+                let annotation = match tool {
+                    AnnotationTool::Arrow => Annotation::Arrow { from, to },
+                    AnnotationTool::Rect => Annotation::Rect { from, to },
+                    AnnotationTool::Note => return None,
+                };
+                Some(Action::publish(Message::AddAnnotation(annotation)).and_capture())
+            }
+
+            _ => None,
+        }
+    }
+
+    fn draw_annotations(&self, frame: &mut Frame, state: &CanvasState, dest: Rectangle) {
+        let Some(layer) = &self.annotations else {
+            return;
+        };
+        let [r, g, b] = annotation::COLOR;
+        let color = Color::from_linear_rgba(r, g, b, 1.0);
+        let stroke = Stroke::default().with_width(MARK_WIDTH).with_color(color);
+        let screen = |point: [f32; 2]| Self::to_screen(&dest, &layer.map, point);
+        let arrow = |frame: &mut Frame, from: Point, to: Point| {
+            for [a, b] in annotation::arrow_segments([from.x, from.y], [to.x, to.y], ARROW_HEAD) {
+                frame.stroke(
+                    &Path::line(Point::new(a[0], a[1]), Point::new(b[0], b[1])),
+                    stroke,
+                );
+            }
+        };
+        let note = |frame: &mut Frame, at: Point, text: &str| {
+            frame.fill(&Path::circle(at, NOTE_RADIUS), color);
+            frame.fill_text(canvas::Text {
+                content: text.to_string(),
+                position: Point::new(at.x + NOTE_RADIUS * 2.0, at.y - 8.0),
+                color,
+                size: 15.0.into(),
+                ..canvas::Text::default()
+            });
+        };
+
+        for item in &layer.items {
+            match item {
+                Annotation::Arrow { from, to } => arrow(frame, screen(*from), screen(*to)),
+                Annotation::Rect { from, to } => {
+                    let corners = [
+                        screen(*from),
+                        screen([to[0], from[1]]),
+                        screen(*to),
+                        screen([from[0], to[1]]),
+                    ];
+                    let outline = Path::new(|builder| {
+                        builder.move_to(corners[0]);
+                        for &corner in &corners[1..] {
+                            builder.line_to(corner);
+                        }
+                        builder.close();
+                    });
+                    frame.stroke(&outline, stroke);
+                }
+                Annotation::Note { at, text } => note(frame, screen(*at), text),
+            }
+        }
+        if let Some(at) = layer.pending_note {
+            note(frame, screen(at), "");
+        }
+        if let (Some((start, end)), Some(tool)) = (state.sketch, layer.tool) {
+            match tool {
+                AnnotationTool::Arrow => arrow(frame, start, end),
+                AnnotationTool::Rect => frame.stroke(
+                    &Path::rectangle(
+                        Point::new(start.x.min(end.x), start.y.min(end.y)),
+                        Size::new((end.x - start.x).abs(), (end.y - start.y).abs()),
+                    ),
+                    stroke,
+                ),
+                AnnotationTool::Note => {}
+            }
+        }
+    }
+
     fn draw_crop_overlay(&self, frame: &mut Frame, dest: Rectangle) {
         let Some(crop) = &self.crop else {
             return;
@@ -322,6 +570,128 @@ impl ZoomableImage {
             frame.fill_rectangle(pt, Size::new(hs * 2.0, hs * 2.0), handle_color);
         }
     }
+
+    /// Scrolling with a mouse wheel, or with Ctrl (Cmd on macOS) held,
+    /// zooms. A trackpad's two-finger scroll pans a zoomed-in photo, and at
+    /// fit zoom a sideways swipe moves between photos while an upward or
+    /// downward one still zooms.
+    ///
+    /// Trackpad pinches never get here or to [`Self::update_touch`]: winit
+    /// reports them as pinch gestures, which iced 0.14 drops, and macOS
+    /// doesn't turn them into Ctrl+wheel events the way browsers do. On a
+    /// trackpad, zoom with Cmd+scroll or a double click; pinch-to-zoom only
+    /// works on touch screens.
+    fn update_scroll(
+        &self,
+        state: &mut CanvasState,
+        delta: mouse::ScrollDelta,
+        bounds: Rectangle,
+        cursor_pos: Point,
+    ) -> Option<Action<Message>> {
+        let scroll_y = match delta {
+            mouse::ScrollDelta::Lines { y, .. } => y,
+            mouse::ScrollDelta::Pixels { x, y } if !state.modifiers.command() => {
+                if !self.zoom_state.is_fit() {
+                    return Some(Action::publish(Message::PanDelta(x, y)).and_capture());
+                }
+                if x.abs() > y.abs() {
+                    return match state.swipe.scroll(x, Instant::now()) {
+                        Some(message) => Some(Action::publish(message).and_capture()),
+                        None => Some(Action::capture()),
+                    };
+                }
+                y / 60.0
+            }
+            mouse::ScrollDelta::Pixels { y, .. } => y / 60.0,
+        };
+        if scroll_y == 0.0 {
+            return None;
+        }
+        let factor = if scroll_y > 0.0 {
+            ZOOM_STEP
+        } else {
+            1.0 / ZOOM_STEP
+        };
+        Some(
+            Action::publish(Message::ZoomAtPoint(
+                factor,
+                cursor_pos.x,
+                cursor_pos.y,
+                bounds.width,
+                bounds.height,
+            ))
+            .and_capture(),
+        )
+    }
+
+    /// Touch screens: one finger pans a zoomed-in photo or swipes between
+    /// photos at fit zoom, two pinch to zoom and pan together, and a
+    /// double tap toggles 100%.
+    fn update_touch(
+        &self,
+        state: &mut CanvasState,
+        event: touch::Event,
+        bounds: Rectangle,
+    ) -> Option<Action<Message>> {
+        let origin = Vector::new(bounds.x, bounds.y);
+        match event {
+            touch::Event::FingerPressed { id, position } => {
+                if !bounds.contains(position) || state.fingers.len() >= 2 {
+                    return None;
+                }
+                state.fingers.push((id, position - origin));
+                state.touch_start = (state.fingers.len() == 1).then_some(position - origin);
+                Some(Action::capture())
+            }
+            touch::Event::FingerMoved { id, position } => {
+                let position = position - origin;
+                let index = state.fingers.iter().position(|(finger, _)| *finger == id)?;
+                let last = state.fingers[index].1;
+                state.fingers[index].1 = position;
+                let message = match state.fingers[..] {
+                    [(_, a), (_, b)] => {
+                        let from = if index == 0 { [last, b] } else { [a, last] };
+                        let (factor, shift, center) = pinch(from, [a, b]);
+                        Message::PinchAt(factor, shift, center, bounds.size())
+                    }
+                    _ if !self.zoom_state.is_fit() => {
+                        Message::PanDelta(position.x - last.x, position.y - last.y)
+                    }
+                    _ => return Some(Action::capture()),
+                };
+                Some(Action::publish(message).and_capture())
+            }
+            touch::Event::FingerLifted { id, position } => {
+                let position = position - origin;
+                state.fingers.retain(|(finger, _)| *finger != id);
+                let start = state.touch_start.take()?;
+                let (dx, dy) = (position.x - start.x, position.y - start.y);
+                if start.distance(position) < TAP_SLOP {
+                    let tap = Click::new(position, mouse::Button::Left, state.click);
+                    state.click = Some(tap);
+                    if tap.kind() == click::Kind::Double {
+                        return Some(
+                            Action::publish(toggle_actual_size(position, bounds)).and_capture(),
+                        );
+                    }
+                } else if self.zoom_state.is_fit()
+                    && let Some(message) = swipe_message(dx, dy)
+                {
+                    return Some(Action::publish(message).and_capture());
+                }
+                Some(Action::capture())
+            }
+            touch::Event::FingerLost { id, .. } => {
+                state.fingers.retain(|(finger, _)| *finger != id);
+                state.touch_start = None;
+                None
+            }
+        }
+    }
+}
+
+fn toggle_actual_size(at: Point, bounds: Rectangle) -> Message {
+    Message::ToggleActualSize(at.x, at.y, bounds.width, bounds.height)
 }
 
 impl canvas::Program<Message> for ZoomableImage {
@@ -334,37 +704,28 @@ impl canvas::Program<Message> for ZoomableImage {
         bounds: Rectangle,
         cursor: mouse::Cursor,
     ) -> Option<Action<Message>> {
+        if let Event::Keyboard(keyboard::Event::ModifiersChanged(modifiers)) = event {
+            state.modifiers = *modifiers;
+            return None;
+        }
+
+        if let Event::Touch(touch) = event {
+            if self.crop.is_some() || self.annotations.as_ref().is_some_and(|l| l.tool.is_some()) {
+                return None;
+            }
+            return self.update_touch(state, *touch, bounds);
+        }
+
         let Some(cursor_pos) = cursor.position_in(bounds) else {
             state.dragging = false;
             state.last_cursor = None;
             state.crop_handle = None;
+            state.sketch = None;
             return None;
         };
 
-        // Scroll wheel always zooms
         if let Event::Mouse(mouse::Event::WheelScrolled { delta }) = event {
-            let scroll_y = match delta {
-                mouse::ScrollDelta::Lines { y, .. } => *y,
-                mouse::ScrollDelta::Pixels { y, .. } => *y / 60.0,
-            };
-            if scroll_y != 0.0 {
-                let factor = if scroll_y > 0.0 {
-                    ZOOM_STEP
-                } else {
-                    1.0 / ZOOM_STEP
-                };
-                return Some(
-                    Action::publish(Message::ZoomAtPoint(
-                        factor,
-                        cursor_pos.x,
-                        cursor_pos.y,
-                        bounds.width,
-                        bounds.height,
-                    ))
-                    .and_capture(),
-                );
-            }
-            return None;
+            return self.update_scroll(state, *delta, bounds, cursor_pos);
         }
 
         // If crop mode, handle crop interactions
@@ -372,9 +733,27 @@ impl canvas::Program<Message> for ZoomableImage {
             return self.update_crop(state, event, bounds, cursor_pos);
         }
 
+        // A drawing tool takes the mouse from panning
+        if self
+            .annotations
+            .as_ref()
+            .is_some_and(|layer| layer.tool.is_some())
+        {
+            return self.update_annotation(state, event, bounds, cursor_pos);
+        }
+
         // Otherwise, handle zoom/pan
         match event {
             Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left)) => {
+                let click = Click::new(cursor_pos, mouse::Button::Left, state.click);
+                state.click = Some(click);
+                if click.kind() == click::Kind::Double {
+                    state.dragging = false;
+                    state.last_cursor = None;
+                    return Some(
+                        Action::publish(toggle_actual_size(cursor_pos, bounds)).and_capture(),
+                    );
+                }
                 if self.zoom_state.zoom > MIN_ZOOM {
                     state.dragging = true;
                     state.last_cursor = Some(cursor_pos);
@@ -412,7 +791,7 @@ impl canvas::Program<Message> for ZoomableImage {
 
     fn draw(
         &self,
-        _state: &Self::State,
+        state: &Self::State,
         renderer: &Renderer,
         _theme: &Theme,
         bounds: Rectangle,
@@ -434,6 +813,7 @@ impl canvas::Program<Message> for ZoomableImage {
 
         frame.with_clip(clip, |frame| {
             frame.draw_image(dest, iced::advanced::image::Image::new(&self.handle));
+            self.draw_annotations(frame, state, dest);
             self.draw_crop_overlay(frame, dest);
         });
 
@@ -462,7 +842,13 @@ impl canvas::Program<Message> for ZoomableImage {
             };
         }
 
-        if state.dragging {
+        if self
+            .annotations
+            .as_ref()
+            .is_some_and(|layer| layer.tool.is_some())
+        {
+            mouse::Interaction::Crosshair
+        } else if state.dragging {
             mouse::Interaction::Grabbing
         } else if self.zoom_state.zoom > MIN_ZOOM {
             mouse::Interaction::Grab
@@ -478,14 +864,69 @@ pub fn view<'a>(
     image_height: u32,
     zoom_state: &ZoomState,
     crop: Option<CropOverlay>,
+    annotations: Option<AnnotationLayer>,
 ) -> Element<'a, Message> {
     iced::widget::canvas(ZoomableImage {
         handle: handle.clone(),
         image_size: Size::new(image_width as f32, image_height as f32),
         zoom_state: zoom_state.clone(),
         crop,
+        annotations,
     })
     .width(Length::Fill)
     .height(Length::Fill)
     .into()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trackpad_swipes_turn_one_page_until_scrolling_pauses() {
+        let mut swipe = SwipeTracker::default();
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+        assert!(swipe.scroll(-60.0, at(0)).is_none());
+        assert!(matches!(
+            swipe.scroll(-70.0, at(16)),
+            Some(Message::NextPhoto)
+        ));
+        // Momentum after the page turn does nothing.
+        assert!(swipe.scroll(-200.0, at(32)).is_none());
+        assert!(matches!(
+            swipe.scroll(150.0, at(32 + 300)),
+            Some(Message::PrevPhoto)
+        ));
+    }
+
+    #[test]
+    fn swipes_must_be_mostly_sideways() {
+        assert!(swipe_message(-150.0, 100.0).is_none());
+        assert!(swipe_message(-80.0, 0.0).is_none());
+        assert!(matches!(
+            swipe_message(-150.0, 20.0),
+            Some(Message::NextPhoto)
+        ));
+    }
+
+    #[test]
+    fn pinch_zooms_by_finger_spread_about_their_midpoint() {
+        let from = [Point::new(100.0, 100.0), Point::new(200.0, 100.0)];
+        let to = [Point::new(60.0, 110.0), Point::new(260.0, 110.0)];
+        let (factor, shift, center) = pinch(from, to);
+        assert!((factor - 2.0).abs() < 1e-6);
+        assert_eq!(shift, Vector::new(10.0, 10.0));
+        assert_eq!(center, Point::new(160.0, 110.0));
+    }
+
+    #[test]
+    fn actual_size_undoes_the_fit_scale() {
+        let viewport = Size::new(1000.0, 800.0);
+        assert!((actual_size_zoom(Size::new(6000.0, 4000.0), viewport) - 6.0).abs() < 1e-4);
+        assert_eq!(
+            actual_size_zoom(Size::new(500.0, 400.0), viewport),
+            SMALL_IMAGE_ZOOM
+        );
+    }
+}